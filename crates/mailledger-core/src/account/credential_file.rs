@@ -0,0 +1,545 @@
+//! Encrypted credential file for systems without a keyring.
+//!
+//! Headless machines often have no Secret Service to talk to, so
+//! [`EncryptedFileCredentialStore`] keeps the same credentials in a JSON file
+//! instead. Each value is sealed with AES-256-GCM under a key derived from a
+//! user passphrase with PBKDF2-HMAC-SHA256 and a random salt kept in the
+//! file. The salt and iteration count are authenticated along with every
+//! value, so editing them is detected.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::debug;
+
+use super::AccountId;
+use super::credentials::{
+    CredentialError, CredentialResult, CredentialStore, IMAP_CREDENTIAL, OAUTH_TOKEN_CREDENTIAL,
+    SMTP_CREDENTIAL, credential_key,
+};
+
+/// PBKDF2 iterations for new files (OWASP's 2023 figure for SHA-256).
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Iteration counts accepted from an existing file.
+///
+/// The count is read from the file itself, so it is bounded: fewer would
+/// weaken the key, and far more would hang [`EncryptedFileCredentialStore::open`].
+const ACCEPTED_ITERATIONS: RangeInclusive<u32> = 100_000..=10_000_000;
+
+/// Length of the PBKDF2 salt.
+const SALT_LEN: usize = 16;
+
+/// Length of an AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// Message sealed in the file to tell a wrong passphrase from a corrupt
+/// entry.
+const VERIFIER_MESSAGE: &[u8] = b"mailledger credentials";
+
+/// Prefix of the data authenticated with every sealed value.
+const FORMAT_LABEL: &[u8] = b"mailledger credentials v2";
+
+/// On-disk layout of the credential file.
+#[derive(Debug, Serialize, Deserialize)]
+struct CredentialFile {
+    /// Base64 PBKDF2 salt.
+    salt: String,
+    /// PBKDF2 iteration count.
+    iterations: u32,
+    /// [`VERIFIER_MESSAGE`], sealed.
+    verifier: SealedEntry,
+    /// Encrypted values by credential key.
+    entries: BTreeMap<String, SealedEntry>,
+}
+
+/// One encrypted value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedEntry {
+    /// Base64 AES-GCM nonce.
+    nonce: String,
+    /// Base64 ciphertext followed by the authentication tag.
+    ciphertext: String,
+}
+
+/// How the key for a file is derived.
+struct KdfPolicy {
+    /// Iterations for a new file.
+    new_file: u32,
+    /// Iterations accepted from an existing file.
+    accepted: RangeInclusive<u32>,
+}
+
+/// The cipher for one file, with the header it authenticates.
+struct Sealer {
+    cipher: Aes256Gcm,
+    /// Salt and iteration count, bound into every value.
+    header: Vec<u8>,
+}
+
+impl Sealer {
+    /// Derive the key from `passphrase` with PBKDF2-HMAC-SHA256.
+    fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+
+        let mut header = FORMAT_LABEL.to_vec();
+        header.extend_from_slice(&length_prefixed(salt));
+        header.extend_from_slice(&iterations.to_be_bytes());
+        Self {
+            cipher: Aes256Gcm::new(&key.into()),
+            header,
+        }
+    }
+
+    /// Data authenticated with the value stored under `key`, so a value
+    /// can't be moved to another entry or another file header.
+    fn associated_data(&self, key: &str) -> Vec<u8> {
+        let mut aad = self.header.clone();
+        aad.extend_from_slice(&length_prefixed(key.as_bytes()));
+        aad
+    }
+
+    fn seal(&self, key: &str, value: &[u8]) -> CredentialResult<SealedEntry> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let aad = self.associated_data(key);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: value,
+                    aad: &aad,
+                },
+            )
+            .map_err(|e| CredentialError::Corrupt(e.to_string()))?;
+        Ok(SealedEntry {
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt `entry`, or `None` if it fails authentication.
+    fn open(&self, key: &str, entry: &SealedEntry) -> CredentialResult<Option<Vec<u8>>> {
+        let nonce = decode(&entry.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(CredentialError::Corrupt(format!("Bad nonce for {key}")));
+        }
+        let ciphertext = decode(&entry.ciphertext)?;
+        let aad = self.associated_data(key);
+        Ok(self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .ok())
+    }
+}
+
+/// Credential store backed by a passphrase-encrypted file.
+///
+/// A fallback for when the system keyring is unavailable. Files are written
+/// with mode 0600 on Unix and replaced atomically on every change.
+pub struct EncryptedFileCredentialStore {
+    path: PathBuf,
+    sealer: Sealer,
+    file: Mutex<CredentialFile>,
+}
+
+impl std::fmt::Debug for EncryptedFileCredentialStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFileCredentialStore")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedFileCredentialStore {
+    /// Open the credential file at `path`, unlocking it with `passphrase`.
+    ///
+    /// A missing file is created on the first store.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CredentialError::WrongPassphrase`] if the file was
+    /// encrypted with a different passphrase, or an error if it can't be
+    /// read or parsed, or its key derivation settings were tampered with.
+    pub fn open(path: impl Into<PathBuf>, passphrase: &str) -> CredentialResult<Self> {
+        let policy = KdfPolicy {
+            new_file: PBKDF2_ITERATIONS,
+            accepted: ACCEPTED_ITERATIONS,
+        };
+        Self::open_with_policy(path.into(), passphrase, &policy)
+    }
+
+    fn open_with_policy(
+        path: PathBuf,
+        passphrase: &str,
+        policy: &KdfPolicy,
+    ) -> CredentialResult<Self> {
+        let (file, sealer) = match fs::read(&path) {
+            Ok(json) => {
+                let file: CredentialFile = serde_json::from_slice(&json)
+                    .map_err(|e| CredentialError::Corrupt(e.to_string()))?;
+                if !policy.accepted.contains(&file.iterations) {
+                    return Err(CredentialError::Corrupt(format!(
+                        "Unexpected PBKDF2 iteration count {}",
+                        file.iterations
+                    )));
+                }
+                let salt = decode(&file.salt)?;
+                if salt.len() != SALT_LEN {
+                    return Err(CredentialError::Corrupt("Bad salt length".to_string()));
+                }
+                let sealer = Sealer::derive(passphrase, &salt, file.iterations);
+                if sealer.open("", &file.verifier)?.as_deref() != Some(VERIFIER_MESSAGE) {
+                    return Err(CredentialError::WrongPassphrase);
+                }
+                (file, sealer)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let mut salt = [0u8; SALT_LEN];
+                rand::thread_rng().fill_bytes(&mut salt);
+                let sealer = Sealer::derive(passphrase, &salt, policy.new_file);
+                let file = CredentialFile {
+                    salt: STANDARD.encode(salt),
+                    iterations: policy.new_file,
+                    // Credential keys are never empty, so the verifier
+                    // can't stand in for an entry
+                    verifier: sealer.seal("", VERIFIER_MESSAGE)?,
+                    entries: BTreeMap::new(),
+                };
+                (file, sealer)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            sealer,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Get the path of the credential file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CredentialFile> {
+        self.file
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Encrypt and save a value.
+    fn put(&self, key: &str, value: &[u8]) -> CredentialResult<()> {
+        let entry = self.sealer.seal(key, value)?;
+
+        let mut file = self.lock();
+        file.entries.insert(key.to_string(), entry);
+        // Writing under the lock keeps concurrent stores from losing entries
+        let written = self.write(&file);
+        drop(file);
+        written
+    }
+
+    /// Load and decrypt a value.
+    fn get(&self, key: &str) -> CredentialResult<Option<Vec<u8>>> {
+        let Some(entry) = self.lock().entries.get(key).cloned() else {
+            return Ok(None);
+        };
+        self.sealer
+            .open(key, &entry)?
+            .map(Some)
+            .ok_or_else(|| CredentialError::Corrupt(format!("Bad tag for {key}")))
+    }
+
+    fn get_string(&self, key: &str) -> CredentialResult<Option<String>> {
+        self.get(key)?
+            .map(|bytes| {
+                String::from_utf8(bytes).map_err(|e| CredentialError::Corrupt(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Replace the file with `file`, through a temporary file so a crash
+    /// never leaves it half-written.
+    fn write(&self, file: &CredentialFile) -> CredentialResult<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let json =
+            serde_json::to_vec_pretty(file).map_err(|e| CredentialError::Corrupt(e.to_string()))?;
+
+        let temp = self.path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut out = options.open(&temp)?;
+        // The mode only applies to new files; tighten a leftover one too.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            out.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        out.write_all(&json)?;
+        out.sync_all()?;
+        fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl CredentialStore for EncryptedFileCredentialStore {
+    fn store_imap_password(&self, account_id: AccountId, password: &str) -> CredentialResult<()> {
+        self.put(
+            &credential_key(account_id, IMAP_CREDENTIAL),
+            password.as_bytes(),
+        )?;
+        debug!("Stored IMAP password for account {} in file", account_id.0);
+        Ok(())
+    }
+
+    fn store_smtp_password(&self, account_id: AccountId, password: &str) -> CredentialResult<()> {
+        self.put(
+            &credential_key(account_id, SMTP_CREDENTIAL),
+            password.as_bytes(),
+        )?;
+        debug!("Stored SMTP password for account {} in file", account_id.0);
+        Ok(())
+    }
+
+    fn get_imap_password(&self, account_id: AccountId) -> CredentialResult<Option<String>> {
+        self.get_string(&credential_key(account_id, IMAP_CREDENTIAL))
+    }
+
+    fn get_smtp_password(&self, account_id: AccountId) -> CredentialResult<Option<String>> {
+        self.get_string(&credential_key(account_id, SMTP_CREDENTIAL))
+    }
+
+    fn store_oauth_token(
+        &self,
+        account_id: AccountId,
+        token: &mailledger_oauth::Token,
+    ) -> CredentialResult<()> {
+        let json =
+            serde_json::to_vec(token).map_err(|e| CredentialError::Corrupt(e.to_string()))?;
+        self.put(&credential_key(account_id, OAUTH_TOKEN_CREDENTIAL), &json)?;
+        debug!("Stored OAuth2 token for account {} in file", account_id.0);
+        Ok(())
+    }
+
+    fn get_oauth_token(
+        &self,
+        account_id: AccountId,
+    ) -> CredentialResult<Option<mailledger_oauth::Token>> {
+        self.get(&credential_key(account_id, OAUTH_TOKEN_CREDENTIAL))?
+            .map(|json| {
+                serde_json::from_slice(&json).map_err(|e| CredentialError::Corrupt(e.to_string()))
+            })
+            .transpose()
+    }
+
+    fn delete_credentials(&self, account_id: AccountId) -> CredentialResult<()> {
+        let mut file = self.lock();
+        let before = file.entries.len();
+        for credential in [IMAP_CREDENTIAL, SMTP_CREDENTIAL, OAUTH_TOKEN_CREDENTIAL] {
+            file.entries.remove(&credential_key(account_id, credential));
+        }
+        if file.entries.len() == before {
+            return Ok(());
+        }
+        let written = self.write(&file);
+        drop(file);
+        debug!("Deleted credentials for account {} from file", account_id.0);
+        written
+    }
+}
+
+/// `bytes` preceded by its length, so adjacent fields can't be confused.
+fn length_prefixed(bytes: &[u8]) -> Vec<u8> {
+    let mut out = (bytes.len() as u64).to_be_bytes().to_vec();
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn decode(value: &str) -> CredentialResult<Vec<u8>> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| CredentialError::Corrupt(e.to_string()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Few iterations keep the tests fast.
+    const TEST_POLICY: KdfPolicy = KdfPolicy {
+        new_file: 10,
+        accepted: 10..=1000,
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "mailledger-credentials-{name}-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn open(path: &Path, passphrase: &str) -> CredentialResult<EncryptedFileCredentialStore> {
+        EncryptedFileCredentialStore::open_with_policy(path.to_path_buf(), passphrase, &TEST_POLICY)
+    }
+
+    #[test]
+    fn test_round_trip_across_reopen() {
+        let path = temp_path("round-trip");
+        let store = open(&path, "correct horse").unwrap();
+        let id = AccountId::new(1);
+        store.store_imap_password(id, "imap-secret").unwrap();
+        store.store_smtp_password(id, "smtp-secret").unwrap();
+        store
+            .store_oauth_token(id, &mailledger_oauth::Token::new("access", "Bearer"))
+            .unwrap();
+
+        // Nothing is readable without the passphrase
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("imap-secret"));
+        assert!(!contents.contains("access"));
+
+        let store = open(&path, "correct horse").unwrap();
+        assert_eq!(
+            store.get_imap_password(id).unwrap().as_deref(),
+            Some("imap-secret")
+        );
+        assert_eq!(
+            store.get_smtp_password(id).unwrap().as_deref(),
+            Some("smtp-secret")
+        );
+        assert_eq!(
+            store.get_oauth_token(id).unwrap().unwrap().access_token,
+            "access"
+        );
+        assert_eq!(store.get_imap_password(AccountId::new(2)).unwrap(), None);
+
+        store.delete_credentials(id).unwrap();
+        let store = open(&path, "correct horse").unwrap();
+        assert_eq!(store.get_imap_password(id).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_rejected() {
+        let path = temp_path("wrong-passphrase");
+        let store = open(&path, "right").unwrap();
+        store
+            .store_imap_password(AccountId::new(1), "secret")
+            .unwrap();
+
+        assert!(matches!(
+            open(&path, "wrong"),
+            Err(CredentialError::WrongPassphrase)
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let path = temp_path("tampered");
+        let store = open(&path, "passphrase").unwrap();
+        store.store_imap_password(AccountId::new(1), "one").unwrap();
+        store.store_imap_password(AccountId::new(2), "two").unwrap();
+
+        // Swapping two entries keeps each one intact but moves it
+        let mut file: CredentialFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        let one = credential_key(AccountId::new(1), IMAP_CREDENTIAL);
+        let two = credential_key(AccountId::new(2), IMAP_CREDENTIAL);
+        let first = file.entries[&one].clone();
+        let second = file.entries.insert(two, first).unwrap();
+        file.entries.insert(one, second);
+        fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+
+        let store = open(&path, "passphrase").unwrap();
+        assert!(matches!(
+            store.get_imap_password(AccountId::new(1)),
+            Err(CredentialError::Corrupt(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_key_derivation_settings_are_authenticated() {
+        let path = temp_path("kdf-settings");
+        let store = open(&path, "passphrase").unwrap();
+        store.store_imap_password(AccountId::new(1), "one").unwrap();
+
+        // Out of range counts are refused before any key is derived
+        for iterations in [1, u32::MAX] {
+            let mut file: CredentialFile =
+                serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+            file.iterations = iterations;
+            fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+            assert!(matches!(
+                open(&path, "passphrase"),
+                Err(CredentialError::Corrupt(_))
+            ));
+        }
+
+        // An in-range count or another salt changes the key, and the
+        // verifier no longer opens
+        let mut file: CredentialFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        file.iterations = TEST_POLICY.new_file + 1;
+        fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+        assert!(matches!(
+            open(&path, "passphrase"),
+            Err(CredentialError::WrongPassphrase)
+        ));
+
+        file.iterations = TEST_POLICY.new_file;
+        file.salt = STANDARD.encode([0u8; SALT_LEN]);
+        fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+        assert!(matches!(
+            open(&path, "passphrase"),
+            Err(CredentialError::WrongPassphrase)
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("mode");
+        let store = open(&path, "passphrase").unwrap();
+        store
+            .store_imap_password(AccountId::new(1), "secret")
+            .unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_file(&path).unwrap();
+    }
+}
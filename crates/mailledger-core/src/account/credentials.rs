@@ -6,28 +6,38 @@
 //! - macOS: Keychain
 //! - Windows: Credential Manager
 //!
-//! The [`CredentialStore`] trait abstracts credential storage, with two implementations:
+//! The [`CredentialStore`] trait abstracts credential storage, with three implementations:
 //! - [`KeyringCredentialStore`]: Uses the real system keyring (for production)
+//! - [`EncryptedFileCredentialStore`]: Passphrase-encrypted file, for systems
+//!   without a keyring
 //! - [`NoopCredentialStore`]: No-op implementation (for testing)
+//!
+//! [`default_credential_store`] picks between the first two.
 
+use std::path::Path;
 use std::sync::Arc;
 
 use keyring::Entry;
+use mailledger_oauth::TokenStore;
 use tracing::{debug, warn};
 
 use super::AccountId;
+use super::credential_file::EncryptedFileCredentialStore;
 
 /// Service name used for keyring entries.
 const SERVICE_NAME: &str = "mailledger";
 
+/// Environment variable holding the passphrase for the credential file.
+pub const PASSPHRASE_ENV: &str = "MAILLEDGER_CREDENTIAL_PASSPHRASE";
+
 /// Credential type identifier for IMAP passwords.
-const IMAP_CREDENTIAL: &str = "imap";
+pub(super) const IMAP_CREDENTIAL: &str = "imap";
 
 /// Credential type identifier for SMTP passwords.
-const SMTP_CREDENTIAL: &str = "smtp";
+pub(super) const SMTP_CREDENTIAL: &str = "smtp";
 
 /// Credential type identifier for `OAuth2` tokens.
-const OAUTH_TOKEN_CREDENTIAL: &str = "oauth_token";
+pub(super) const OAUTH_TOKEN_CREDENTIAL: &str = "oauth_token";
 
 /// Error type for credential operations.
 #[derive(Debug, thiserror::Error)]
@@ -39,6 +49,18 @@ pub enum CredentialError {
     /// Account ID is required for credential operations.
     #[error("Account ID is required for credential storage")]
     MissingAccountId,
+
+    /// Failed to read or write the credential file.
+    #[error("Credential file error: {0}")]
+    File(#[from] std::io::Error),
+
+    /// The credential file was encrypted with another passphrase.
+    #[error("Wrong passphrase for credential file")]
+    WrongPassphrase,
+
+    /// The credential file is damaged or was tampered with.
+    #[error("Credential file is corrupt: {0}")]
+    Corrupt(String),
 }
 
 /// Result type for credential operations.
@@ -87,6 +109,27 @@ pub trait CredentialStore: Send + Sync + std::fmt::Debug {
     /// Returns an error if the retrieval operation fails.
     fn get_smtp_password(&self, account_id: AccountId) -> CredentialResult<Option<String>>;
 
+    /// Store the `OAuth2` token for an account.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage operation fails.
+    fn store_oauth_token(
+        &self,
+        account_id: AccountId,
+        token: &mailledger_oauth::Token,
+    ) -> CredentialResult<()>;
+
+    /// Get the `OAuth2` token for an account.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the retrieval operation fails.
+    fn get_oauth_token(
+        &self,
+        account_id: AccountId,
+    ) -> CredentialResult<Option<mailledger_oauth::Token>>;
+
     /// Delete all credentials for an account.
     ///
     /// # Errors
@@ -114,6 +157,17 @@ impl KeyringCredentialStore {
     pub fn arc() -> Arc<dyn CredentialStore> {
         Arc::new(Self::new())
     }
+
+    /// Check whether the system keyring can be reached.
+    ///
+    /// Looks up an entry that never exists; a keyring that answers "no
+    /// entry" is working.
+    #[must_use]
+    pub fn is_available() -> bool {
+        let probe = Entry::new(SERVICE_NAME, &format!("{SERVICE_NAME}_probe"))
+            .and_then(|entry| entry.get_password());
+        matches!(probe, Ok(_) | Err(keyring::Error::NoEntry))
+    }
 }
 
 impl CredentialStore for KeyringCredentialStore {
@@ -133,6 +187,21 @@ impl CredentialStore for KeyringCredentialStore {
         get_smtp_password(account_id)
     }
 
+    fn store_oauth_token(
+        &self,
+        account_id: AccountId,
+        token: &mailledger_oauth::Token,
+    ) -> CredentialResult<()> {
+        store_oauth_token(account_id, token)
+    }
+
+    fn get_oauth_token(
+        &self,
+        account_id: AccountId,
+    ) -> CredentialResult<Option<mailledger_oauth::Token>> {
+        get_oauth_token(account_id)
+    }
+
     fn delete_credentials(&self, account_id: AccountId) -> CredentialResult<()> {
         delete_credentials(account_id)
     }
@@ -193,6 +262,29 @@ impl CredentialStore for NoopCredentialStore {
         Ok(None)
     }
 
+    fn store_oauth_token(
+        &self,
+        account_id: AccountId,
+        _token: &mailledger_oauth::Token,
+    ) -> CredentialResult<()> {
+        debug!(
+            "NoopCredentialStore: skipping OAuth2 token store for account {}",
+            account_id.0
+        );
+        Ok(())
+    }
+
+    fn get_oauth_token(
+        &self,
+        account_id: AccountId,
+    ) -> CredentialResult<Option<mailledger_oauth::Token>> {
+        debug!(
+            "NoopCredentialStore: returning None for OAuth2 token for account {}",
+            account_id.0
+        );
+        Ok(None)
+    }
+
     fn delete_credentials(&self, account_id: AccountId) -> CredentialResult<()> {
         debug!(
             "NoopCredentialStore: skipping credential deletion for account {}",
@@ -202,12 +294,89 @@ impl CredentialStore for NoopCredentialStore {
     }
 }
 
+/// Choose where account credentials are kept.
+///
+/// Uses the system keyring when it is available. Otherwise, if the
+/// [`PASSPHRASE_ENV`] environment variable is set, falls back to an
+/// [`EncryptedFileCredentialStore`] at `fallback_path` unlocked with it.
+/// Without either, the keyring is still used so its errors reach the user.
+///
+/// # Errors
+///
+/// Returns an error if the fallback file can't be opened with the
+/// passphrase.
+pub fn default_credential_store(
+    fallback_path: &Path,
+) -> CredentialResult<Arc<dyn CredentialStore>> {
+    if KeyringCredentialStore::is_available() {
+        return Ok(KeyringCredentialStore::arc());
+    }
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => {
+            warn!(
+                "System keyring unavailable, using encrypted credential file {}",
+                fallback_path.display()
+            );
+            Ok(Arc::new(EncryptedFileCredentialStore::open(
+                fallback_path,
+                &passphrase,
+            )?))
+        }
+        _ => {
+            warn!("System keyring unavailable and {PASSPHRASE_ENV} is not set");
+            Ok(KeyringCredentialStore::arc())
+        }
+    }
+}
+
+/// [`TokenStore`] over the `OAuth2` tokens kept in the system keyring by
+/// [`store_oauth_token`], keyed by account ID.
+///
+/// Lets [`OAuthClient::ensure_valid`](mailledger_oauth::OAuthClient::ensure_valid)
+/// refresh an account's token and save the new one where the account
+/// repository will find it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccountTokenStore;
+
+impl AccountTokenStore {
+    /// Parses the account key used with this store.
+    fn account_id(account: &str) -> mailledger_oauth::Result<AccountId> {
+        account.parse().map(AccountId::new).map_err(|_| {
+            mailledger_oauth::Error::InvalidConfig(format!("Not an account ID: {account}"))
+        })
+    }
+}
+
+impl TokenStore for AccountTokenStore {
+    fn save(&self, account: &str, token: &mailledger_oauth::Token) -> mailledger_oauth::Result<()> {
+        store_oauth_token(Self::account_id(account)?, token).map_err(into_oauth_error)
+    }
+
+    fn load(&self, account: &str) -> mailledger_oauth::Result<Option<mailledger_oauth::Token>> {
+        get_oauth_token(Self::account_id(account)?).map_err(into_oauth_error)
+    }
+
+    fn delete(&self, account: &str) -> mailledger_oauth::Result<()> {
+        delete_oauth_token(Self::account_id(account)?).map_err(into_oauth_error)
+    }
+}
+
+fn into_oauth_error(error: CredentialError) -> mailledger_oauth::Error {
+    match error {
+        CredentialError::Keyring(e) => mailledger_oauth::Error::Keyring(e),
+        CredentialError::File(e) => mailledger_oauth::Error::Io(e),
+        CredentialError::MissingAccountId
+        | CredentialError::WrongPassphrase
+        | CredentialError::Corrupt(_) => mailledger_oauth::Error::InvalidConfig(error.to_string()),
+    }
+}
+
 // ============================================================================
 // Standalone functions (kept for backward compatibility and internal use)
 // ============================================================================
 
 /// Generates the keyring entry key for a credential.
-fn credential_key(account_id: AccountId, credential_type: &str) -> String {
+pub(super) fn credential_key(account_id: AccountId, credential_type: &str) -> String {
     format!("{SERVICE_NAME}_{credential_type}_{}", account_id.0)
 }
 
@@ -488,4 +657,13 @@ mod tests {
         assert_eq!(get_imap_password(account_id).unwrap(), None);
         assert_eq!(get_smtp_password(account_id).unwrap(), None);
     }
+
+    #[test]
+    fn test_token_store_rejects_non_numeric_account() {
+        let result = AccountTokenStore.load("user@example.com");
+        assert!(matches!(
+            result,
+            Err(mailledger_oauth::Error::InvalidConfig(_))
+        ));
+    }
 }
@@ -0,0 +1,344 @@
+//! Mail server auto-discovery.
+//!
+//! Finds IMAP and SMTP settings for an email domain by trying, in order:
+//! 1. DNS SRV records (`_imaps._tcp`, `_submission._tcp`; RFC 6186, RFC 8314)
+//! 2. The domain's Mozilla autoconfig file
+//! 3. The Thunderbird ISP database (ISPDB)
+
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use hickory_resolver::TokioResolver;
+use tracing::debug;
+
+use super::Security;
+
+/// Thunderbird ISP database lookup URL; the domain is appended.
+const ISPDB_URL: &str = "https://autoconfig.thunderbird.net/v1.1/";
+
+/// Timeout for each autoconfig HTTP request.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Server settings found for a domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredConfig {
+    /// IMAP server host.
+    pub imap_host: String,
+    /// IMAP server port.
+    pub imap_port: u16,
+    /// IMAP connection security.
+    pub imap_security: Security,
+    /// SMTP submission server host.
+    pub smtp_host: String,
+    /// SMTP submission server port.
+    pub smtp_port: u16,
+    /// SMTP connection security.
+    pub smtp_security: Security,
+    /// Whether the provider advertises `OAuth2` authentication.
+    pub oauth: bool,
+}
+
+/// Error type for discovery operations.
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    /// Autoconfig request failed.
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// Autoconfig document is not well-formed XML.
+    #[error("Invalid autoconfig XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    /// Autoconfig document has no server of the given type.
+    #[error("Autoconfig has no {0} server")]
+    MissingServer(&'static str),
+
+    /// Autoconfig server entry is incomplete or unsupported.
+    #[error("Invalid autoconfig server: {0}")]
+    InvalidServer(String),
+}
+
+/// Discovers server settings for an email domain.
+///
+/// Returns `None` if no source knows the domain. Failures of individual
+/// sources are logged at debug level and the next source is tried.
+pub async fn discover(domain: &str) -> Option<DiscoveredConfig> {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    if domain.is_empty() {
+        return None;
+    }
+
+    if let Some(config) = discover_srv(&domain).await {
+        return Some(config);
+    }
+    discover_autoconfig(&domain).await
+}
+
+/// Looks up IMAP and submission SRV records, preferring implicit TLS.
+async fn discover_srv(domain: &str) -> Option<DiscoveredConfig> {
+    let resolver = match TokioResolver::builder_tokio() {
+        Ok(builder) => builder.build(),
+        Err(e) => {
+            debug!("DNS resolver unavailable: {e}");
+            return None;
+        }
+    };
+
+    let (imap_host, imap_port, imap_security) =
+        srv_service(&resolver, domain, "_imaps", "_imap").await?;
+    let (smtp_host, smtp_port, smtp_security) =
+        srv_service(&resolver, domain, "_submissions", "_submission").await?;
+
+    Some(DiscoveredConfig {
+        imap_host,
+        imap_port,
+        imap_security,
+        smtp_host,
+        smtp_port,
+        smtp_security,
+        oauth: false,
+    })
+}
+
+/// Resolves the TLS service, falling back to the STARTTLS one.
+async fn srv_service(
+    resolver: &TokioResolver,
+    domain: &str,
+    tls_service: &str,
+    starttls_service: &str,
+) -> Option<(String, u16, Security)> {
+    if let Some((host, port)) = srv_target(resolver, &format!("{tls_service}._tcp.{domain}.")).await
+    {
+        return Some((host, port, Security::Tls));
+    }
+    let (host, port) = srv_target(resolver, &format!("{starttls_service}._tcp.{domain}.")).await?;
+    Some((host, port, Security::StartTls))
+}
+
+/// Returns the preferred target of an SRV record set.
+async fn srv_target(resolver: &TokioResolver, name: &str) -> Option<(String, u16)> {
+    let lookup = match resolver.srv_lookup(name).await {
+        Ok(lookup) => lookup,
+        Err(e) => {
+            debug!("SRV lookup for {name} failed: {e}");
+            return None;
+        }
+    };
+    let best = lookup
+        .iter()
+        .min_by_key(|srv| (srv.priority(), Reverse(srv.weight())))?;
+    let target = best.target().to_ascii();
+    let target = target.trim_end_matches('.');
+    // A target of "." means the service is deliberately not offered.
+    if target.is_empty() {
+        return None;
+    }
+    Some((target.to_string(), best.port()))
+}
+
+/// Fetches the domain's autoconfig file, then the ISPDB entry.
+async fn discover_autoconfig(domain: &str) -> Option<DiscoveredConfig> {
+    let client = match reqwest::Client::builder().timeout(HTTP_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            debug!("HTTP client unavailable: {e}");
+            return None;
+        }
+    };
+
+    let urls = [
+        format!("https://autoconfig.{domain}/mail/config-v1.1.xml"),
+        format!("https://{domain}/.well-known/autoconfig/mail/config-v1.1.xml"),
+        format!("{ISPDB_URL}{domain}"),
+    ];
+    for url in urls {
+        match fetch_autoconfig(&client, &url, domain).await {
+            Ok(config) => return Some(config),
+            Err(e) => debug!("Autoconfig from {url} failed: {e}"),
+        }
+    }
+    None
+}
+
+async fn fetch_autoconfig(
+    client: &reqwest::Client,
+    url: &str,
+    domain: &str,
+) -> Result<DiscoveredConfig, DiscoveryError> {
+    let xml = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_autoconfig(&xml, domain)
+}
+
+/// Parses a Mozilla autoconfig (`config-v1.1.xml`) document.
+///
+/// Uses the first IMAP incoming server and the first SMTP outgoing server.
+/// `%EMAILDOMAIN%` in host names is replaced with `domain`.
+///
+/// # Errors
+///
+/// Returns an error if the XML is malformed or lacks a usable IMAP or SMTP
+/// server.
+pub fn parse_autoconfig(xml: &str, domain: &str) -> Result<DiscoveredConfig, DiscoveryError> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let server = |tag: &str, kind: &'static str| {
+        doc.descendants()
+            .find(|node| node.has_tag_name(tag) && node.attribute("type") == Some(kind))
+            .ok_or(DiscoveryError::MissingServer(kind))
+    };
+
+    let imap = ServerSettings::parse(server("incomingServer", "imap")?, domain)?;
+    let smtp = ServerSettings::parse(server("outgoingServer", "smtp")?, domain)?;
+
+    Ok(DiscoveredConfig {
+        imap_host: imap.host,
+        imap_port: imap.port,
+        imap_security: imap.security,
+        smtp_host: smtp.host,
+        smtp_port: smtp.port,
+        smtp_security: smtp.security,
+        oauth: imap.oauth || smtp.oauth,
+    })
+}
+
+/// One `incomingServer` or `outgoingServer` entry.
+struct ServerSettings {
+    host: String,
+    port: u16,
+    security: Security,
+    oauth: bool,
+}
+
+impl ServerSettings {
+    fn parse(node: roxmltree::Node<'_, '_>, domain: &str) -> Result<Self, DiscoveryError> {
+        let child = |name: &str| {
+            node.children()
+                .find(|child| child.has_tag_name(name))
+                .and_then(|child| child.text())
+                .map(str::trim)
+        };
+
+        let host = child("hostname")
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| DiscoveryError::InvalidServer("missing hostname".to_string()))?
+            .replace("%EMAILDOMAIN%", domain);
+        let port = child("port")
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(|| DiscoveryError::InvalidServer(format!("bad port for {host}")))?;
+        let security = match child("socketType").map(str::to_ascii_uppercase).as_deref() {
+            Some("SSL") => Security::Tls,
+            Some("STARTTLS") => Security::StartTls,
+            Some("PLAIN") => Security::None,
+            other => {
+                return Err(DiscoveryError::InvalidServer(format!(
+                    "unknown socket type {other:?} for {host}"
+                )));
+            }
+        };
+        let oauth = node
+            .children()
+            .filter(|child| child.has_tag_name("authentication"))
+            .any(|child| {
+                child
+                    .text()
+                    .is_some_and(|auth| auth.trim().eq_ignore_ascii_case("OAuth2"))
+            });
+
+        Ok(Self {
+            host,
+            port,
+            security,
+            oauth,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+
+    const AUTOCONFIG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<clientConfig version="1.1">
+  <emailProvider id="example.com">
+    <domain>example.com</domain>
+    <displayName>Example Mail</displayName>
+    <incomingServer type="pop3">
+      <hostname>pop.example.com</hostname>
+      <port>995</port>
+      <socketType>SSL</socketType>
+    </incomingServer>
+    <incomingServer type="imap">
+      <hostname>imap.%EMAILDOMAIN%</hostname>
+      <port>993</port>
+      <socketType>SSL</socketType>
+      <username>%EMAILADDRESS%</username>
+      <authentication>OAuth2</authentication>
+      <authentication>password-cleartext</authentication>
+    </incomingServer>
+    <outgoingServer type="smtp">
+      <hostname>smtp.example.com</hostname>
+      <port>587</port>
+      <socketType>STARTTLS</socketType>
+      <username>%EMAILADDRESS%</username>
+      <authentication>password-cleartext</authentication>
+    </outgoingServer>
+  </emailProvider>
+</clientConfig>"#;
+
+    #[test]
+    fn parses_autoconfig() {
+        let config = parse_autoconfig(AUTOCONFIG, "example.com").unwrap();
+        assert_eq!(
+            config,
+            DiscoveredConfig {
+                imap_host: "imap.example.com".to_string(),
+                imap_port: 993,
+                imap_security: Security::Tls,
+                smtp_host: "smtp.example.com".to_string(),
+                smtp_port: 587,
+                smtp_security: Security::StartTls,
+                oauth: true,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_autoconfig_without_smtp() {
+        let xml = AUTOCONFIG.replace("type=\"smtp\"", "type=\"exchange\"");
+        assert!(matches!(
+            parse_autoconfig(&xml, "example.com"),
+            Err(DiscoveryError::MissingServer("smtp"))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_socket_type() {
+        let xml = AUTOCONFIG.replace("STARTTLS", "magic");
+        assert!(matches!(
+            parse_autoconfig(&xml, "example.com"),
+            Err(DiscoveryError::InvalidServer(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        assert!(matches!(
+            parse_autoconfig("<clientConfig>", "example.com"),
+            Err(DiscoveryError::Xml(_))
+        ));
+    }
+}
@@ -2,14 +2,19 @@
 //!
 //! Provides account configuration, storage, and validation.
 
+mod credential_file;
 pub mod credentials;
+mod discovery;
 mod model;
 mod repository;
 mod validation;
 
+pub use credential_file::EncryptedFileCredentialStore;
 pub use credentials::{
-    CredentialError, CredentialResult, CredentialStore, KeyringCredentialStore, NoopCredentialStore,
+    AccountTokenStore, CredentialError, CredentialResult, CredentialStore, KeyringCredentialStore,
+    NoopCredentialStore, default_credential_store,
 };
-pub use model::{Account, AccountId, ImapConfig, Security, SmtpConfig};
+pub use discovery::{DiscoveredConfig, DiscoveryError, discover, parse_autoconfig};
+pub use model::{Account, AccountId, AuthMethod, ImapConfig, OAuthProvider, Security, SmtpConfig};
 pub use repository::AccountRepository;
 pub use validation::{ValidationError, ValidationResult, validate_account};
@@ -1,5 +1,6 @@
 //! Account model types.
 
+use mailledger_oauth::{Provider, Token};
 use serde::{Deserialize, Serialize};
 
 /// Unique identifier for an account.
@@ -44,6 +45,76 @@ impl Security {
     }
 }
 
+/// `OAuth2` provider an account signs in with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OAuthProvider {
+    /// Google (Gmail).
+    Google,
+    /// Microsoft (Outlook, Office 365).
+    Microsoft,
+    /// Yahoo.
+    Yahoo,
+}
+
+impl OAuthProvider {
+    /// Get display name for the provider.
+    #[must_use]
+    pub const fn display_name(&self) -> &'static str {
+        match self {
+            Self::Google => "Google",
+            Self::Microsoft => "Microsoft",
+            Self::Yahoo => "Yahoo",
+        }
+    }
+
+    /// Get the endpoints and scopes of the provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the built-in configuration is invalid.
+    pub fn provider(self) -> mailledger_oauth::Result<Provider> {
+        match self {
+            Self::Google => Provider::google(),
+            Self::Microsoft => Provider::microsoft(),
+            Self::Yahoo => Provider::yahoo(),
+        }
+    }
+}
+
+/// How an account authenticates to a server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AuthMethod {
+    /// Username and password, sent with LOGIN or AUTH PLAIN.
+    Password(String),
+    /// `OAuth2` bearer token, sent with XOAUTH2 or OAUTHBEARER.
+    OAuth {
+        /// Provider that issued the token.
+        provider: OAuthProvider,
+        /// Token from sign-in. Saved accounts refresh it from the keyring
+        /// before each connection, so this copy may be stale.
+        token: Token,
+        /// Client ID the token was issued to.
+        client_id: String,
+    },
+}
+
+impl AuthMethod {
+    /// Get the password, if this is password authentication.
+    #[must_use]
+    pub fn password(&self) -> Option<&str> {
+        match self {
+            Self::Password(password) => Some(password),
+            Self::OAuth { .. } => None,
+        }
+    }
+}
+
+impl Default for AuthMethod {
+    fn default() -> Self {
+        Self::Password(String::new())
+    }
+}
+
 /// IMAP server configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImapConfig {
@@ -55,8 +126,8 @@ pub struct ImapConfig {
     pub security: Security,
     /// Username for authentication.
     pub username: String,
-    /// Password for authentication.
-    pub password: String,
+    /// How to authenticate.
+    pub auth: AuthMethod,
 }
 
 impl ImapConfig {
@@ -81,8 +152,8 @@ pub struct SmtpConfig {
     pub security: Security,
     /// Username for authentication.
     pub username: String,
-    /// Password for authentication.
-    pub password: String,
+    /// How to authenticate.
+    pub auth: AuthMethod,
 }
 
 impl SmtpConfig {
@@ -1,13 +1,18 @@
 //! Account storage repository.
 
+use std::path::Path;
 use std::sync::Arc;
 
 use sqlx::Row;
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 use tracing::{debug, warn};
 
-use super::credentials::{CredentialStore, KeyringCredentialStore, NoopCredentialStore};
-use super::model::{Account, AccountId, ImapConfig, Security, SmtpConfig};
+use super::credentials::{CredentialStore, NoopCredentialStore, default_credential_store};
+use mailledger_oauth::Token;
+
+use super::model::{
+    Account, AccountId, AuthMethod, ImapConfig, OAuthProvider, Security, SmtpConfig,
+};
 use crate::Result;
 
 /// Repository for account storage and retrieval.
@@ -20,12 +25,28 @@ impl AccountRepository {
     /// Create a new repository with the given database path.
     ///
     /// Creates the database and tables if they don't exist.
-    /// Uses the real system keyring for credential storage.
+    /// Credentials go to the store chosen by [`default_credential_store`]:
+    /// the system keyring, or an encrypted `credentials.json` next to the
+    /// database when there is no keyring.
     ///
     /// # Errors
     ///
-    /// Returns an error if the database connection fails or schema creation fails.
+    /// Returns an error if the database connection fails, schema creation
+    /// fails or the credential file can't be unlocked.
     pub async fn new(database_path: &str) -> Result<Self> {
+        let fallback = Path::new(database_path).with_file_name("credentials.json");
+        Self::with_credential_store(database_path, default_credential_store(&fallback)?).await
+    }
+
+    /// Create a new repository that keeps credentials in `credential_store`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    pub async fn with_credential_store(
+        database_path: &str,
+        credential_store: Arc<dyn CredentialStore>,
+    ) -> Result<Self> {
         let url = format!("sqlite:{database_path}?mode=rwc");
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
@@ -34,7 +55,7 @@ impl AccountRepository {
 
         let repo = Self {
             pool,
-            credential_store: KeyringCredentialStore::arc(),
+            credential_store,
         };
         repo.initialize().await?;
         Ok(repo)
@@ -48,6 +69,12 @@ impl AccountRepository {
     ///
     /// Returns an error if the database connection fails or schema creation fails.
     pub async fn in_memory() -> Result<Self> {
+        Self::in_memory_with_credential_store(NoopCredentialStore::arc()).await
+    }
+
+    async fn in_memory_with_credential_store(
+        credential_store: Arc<dyn CredentialStore>,
+    ) -> Result<Self> {
         let pool = SqlitePoolOptions::new()
             .max_connections(1)
             .connect("sqlite::memory:")
@@ -55,7 +82,7 @@ impl AccountRepository {
 
         let repo = Self {
             pool,
-            credential_store: NoopCredentialStore::arc(),
+            credential_store,
         };
         repo.initialize().await?;
         Ok(repo)
@@ -88,6 +115,58 @@ impl AccountRepository {
         .execute(&self.pool)
         .await?;
 
+        // OAuth sign-ins; the token itself lives in the credential store
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS account_oauth (
+                account_id INTEGER NOT NULL,
+                protocol TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                client_id TEXT NOT NULL,
+                PRIMARY KEY(account_id, protocol)
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.migrate_plaintext_passwords().await
+    }
+
+    /// Move passwords that older versions kept in the database into the
+    /// credential store, leaving empty placeholders behind.
+    ///
+    /// A password the store rejects stays in the database and is tried
+    /// again next time.
+    async fn migrate_plaintext_passwords(&self) -> Result<()> {
+        let rows = sqlx::query(
+            r"
+            SELECT id, imap_password, smtp_password FROM accounts
+            WHERE imap_password != '' OR smtp_password != ''
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in &rows {
+            let id = AccountId::new(row.get("id"));
+            let stored = self
+                .credential_store
+                .store_imap_password(id, row.get("imap_password"))
+                .and_then(|()| {
+                    self.credential_store
+                        .store_smtp_password(id, row.get("smtp_password"))
+                });
+            if let Err(e) = stored {
+                warn!("Failed to migrate passwords of account {}: {e}", id.0);
+                continue;
+            }
+            sqlx::query("UPDATE accounts SET imap_password = '', smtp_password = '' WHERE id = ?")
+                .bind(id.0)
+                .execute(&self.pool)
+                .await?;
+            debug!("Moved passwords of account {} to credential store", id.0);
+        }
         Ok(())
     }
 
@@ -110,7 +189,10 @@ impl AccountRepository {
         .fetch_all(&self.pool)
         .await?;
 
-        let accounts = rows.iter().map(|row| self.row_to_account(row)).collect();
+        let mut accounts = Vec::with_capacity(rows.len());
+        for row in &rows {
+            accounts.push(self.load_oauth(self.row_to_account(row)).await?);
+        }
         Ok(accounts)
     }
 
@@ -134,7 +216,10 @@ impl AccountRepository {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.as_ref().map(|r| self.row_to_account(r)))
+        match row {
+            Some(row) => Ok(Some(self.load_oauth(self.row_to_account(&row)).await?)),
+            None => Ok(None),
+        }
     }
 
     /// Get the default account.
@@ -157,13 +242,16 @@ impl AccountRepository {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.as_ref().map(|r| self.row_to_account(r)))
+        match row {
+            Some(row) => Ok(Some(self.load_oauth(self.row_to_account(&row)).await?)),
+            None => Ok(None),
+        }
     }
 
     /// Save an account (insert or update).
     ///
-    /// Passwords are stored securely in the system keyring.
-    /// The database stores placeholder values for password fields.
+    /// Passwords and `OAuth2` tokens go to the credential store. The
+    /// database stores placeholder values for password fields.
     ///
     /// # Errors
     ///
@@ -204,11 +292,7 @@ impl AccountRepository {
             .execute(&self.pool)
             .await?;
 
-            // Store passwords in credential store
-            self.credential_store
-                .store_imap_password(id, &account.imap.password)?;
-            self.credential_store
-                .store_smtp_password(id, &account.smtp.password)?;
+            self.store_credentials(id, account)?;
             debug!("Stored credentials for account {}", id.0);
         } else {
             // Insert new
@@ -241,14 +325,14 @@ impl AccountRepository {
             let new_id = AccountId::new(result.last_insert_rowid());
             account.id = Some(new_id);
 
-            // Store passwords in credential store
-            self.credential_store
-                .store_imap_password(new_id, &account.imap.password)?;
-            self.credential_store
-                .store_smtp_password(new_id, &account.smtp.password)?;
+            self.store_credentials(new_id, account)?;
             debug!("Stored credentials for new account {}", new_id.0);
         }
 
+        if let Some(id) = account.id {
+            self.save_oauth(id, account).await?;
+        }
+
         // If this account is default, unset others
         if account.is_default
             && let Some(id) = account.id
@@ -264,7 +348,7 @@ impl AccountRepository {
 
     /// Delete an account.
     ///
-    /// Also removes credentials from the system keyring.
+    /// Also removes credentials from the credential store.
     ///
     /// # Errors
     ///
@@ -274,6 +358,10 @@ impl AccountRepository {
             .bind(id.0)
             .execute(&self.pool)
             .await?;
+        sqlx::query("DELETE FROM account_oauth WHERE account_id = ?")
+            .bind(id.0)
+            .execute(&self.pool)
+            .await?;
 
         // Delete credentials from credential store
         if let Err(e) = self.credential_store.delete_credentials(id) {
@@ -283,6 +371,104 @@ impl AccountRepository {
         Ok(())
     }
 
+    /// Store an account's passwords and `OAuth2` token in the credential store.
+    ///
+    /// Both protocols share one sign-in, so only one token is kept per
+    /// account, preferring the IMAP one.
+    fn store_credentials(&self, id: AccountId, account: &Account) -> Result<()> {
+        self.credential_store
+            .store_imap_password(id, account.imap.auth.password().unwrap_or_default())?;
+        self.credential_store
+            .store_smtp_password(id, account.smtp.auth.password().unwrap_or_default())?;
+        let token = [&account.imap.auth, &account.smtp.auth]
+            .into_iter()
+            .find_map(|auth| match auth {
+                AuthMethod::OAuth { token, .. } => Some(token),
+                AuthMethod::Password(_) => None,
+            });
+        if let Some(token) = token {
+            self.credential_store.store_oauth_token(id, token)?;
+        }
+        Ok(())
+    }
+
+    /// Record which protocols of an account sign in with `OAuth2`.
+    async fn save_oauth(&self, id: AccountId, account: &Account) -> Result<()> {
+        sqlx::query("DELETE FROM account_oauth WHERE account_id = ?")
+            .bind(id.0)
+            .execute(&self.pool)
+            .await?;
+
+        for (protocol, auth) in [("imap", &account.imap.auth), ("smtp", &account.smtp.auth)] {
+            if let AuthMethod::OAuth {
+                provider,
+                client_id,
+                ..
+            } = auth
+            {
+                sqlx::query(
+                    r"
+                    INSERT INTO account_oauth (account_id, protocol, provider, client_id)
+                    VALUES (?, ?, ?, ?)
+                    ",
+                )
+                .bind(id.0)
+                .bind(protocol)
+                .bind(provider_to_string(*provider))
+                .bind(client_id)
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the password authentication of protocols that sign in with
+    /// `OAuth2`.
+    ///
+    /// A token missing from the credential store is left empty; connecting
+    /// then fails until the user signs in again.
+    async fn load_oauth(&self, mut account: Account) -> Result<Account> {
+        let Some(id) = account.id else {
+            return Ok(account);
+        };
+        let rows = sqlx::query(
+            r"SELECT protocol, provider, client_id FROM account_oauth WHERE account_id = ?",
+        )
+        .bind(id.0)
+        .fetch_all(&self.pool)
+        .await?;
+        if rows.is_empty() {
+            return Ok(account);
+        }
+
+        let token = match self.credential_store.get_oauth_token(id) {
+            Ok(token) => token,
+            Err(e) => {
+                warn!("Failed to load OAuth2 token from credential store: {e}");
+                None
+            }
+        }
+        .unwrap_or_else(|| Token::new("", "Bearer"));
+
+        for row in &rows {
+            let Some(provider) = string_to_provider(row.get("provider")) else {
+                continue;
+            };
+            let auth = AuthMethod::OAuth {
+                provider,
+                token: token.clone(),
+                client_id: row.get("client_id"),
+            };
+            match row.get::<&str, _>("protocol") {
+                "imap" => account.imap.auth = auth,
+                "smtp" => account.smtp.auth = auth,
+                _ => {}
+            }
+        }
+        Ok(account)
+    }
+
     /// Load passwords from credential store with fallback to database.
     fn load_passwords_for_account(
         &self,
@@ -359,14 +545,14 @@ impl AccountRepository {
                 port: row.get::<i64, _>("imap_port") as u16,
                 security: string_to_security(row.get("imap_security")),
                 username: row.get("imap_username"),
-                password: imap_password,
+                auth: AuthMethod::Password(imap_password),
             },
             smtp: SmtpConfig {
                 host: row.get("smtp_host"),
                 port: row.get::<i64, _>("smtp_port") as u16,
                 security: string_to_security(row.get("smtp_security")),
                 username: row.get("smtp_username"),
-                password: smtp_password,
+                auth: AuthMethod::Password(smtp_password),
             },
             is_default: row.get::<i64, _>("is_default") != 0,
         }
@@ -389,6 +575,23 @@ fn string_to_security(s: &str) -> Security {
     }
 }
 
+const fn provider_to_string(provider: OAuthProvider) -> &'static str {
+    match provider {
+        OAuthProvider::Google => "google",
+        OAuthProvider::Microsoft => "microsoft",
+        OAuthProvider::Yahoo => "yahoo",
+    }
+}
+
+fn string_to_provider(s: &str) -> Option<OAuthProvider> {
+    match s {
+        "google" => Some(OAuthProvider::Google),
+        "microsoft" => Some(OAuthProvider::Microsoft),
+        "yahoo" => Some(OAuthProvider::Yahoo),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -400,15 +603,74 @@ fn string_to_security(s: &str) -> Security {
     clippy::similar_names
 )]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::super::credentials::CredentialResult;
     use super::*;
 
+    /// Keeps credentials in memory, standing in for the system keyring.
+    #[derive(Debug, Default)]
+    struct MemoryCredentialStore {
+        secrets: Mutex<HashMap<(i64, &'static str), String>>,
+    }
+
+    impl MemoryCredentialStore {
+        fn put(&self, id: AccountId, kind: &'static str, value: String) {
+            self.secrets.lock().unwrap().insert((id.0, kind), value);
+        }
+
+        fn get(&self, id: AccountId, kind: &'static str) -> Option<String> {
+            self.secrets.lock().unwrap().get(&(id.0, kind)).cloned()
+        }
+    }
+
+    impl CredentialStore for MemoryCredentialStore {
+        fn store_imap_password(&self, id: AccountId, password: &str) -> CredentialResult<()> {
+            self.put(id, "imap", password.to_string());
+            Ok(())
+        }
+
+        fn store_smtp_password(&self, id: AccountId, password: &str) -> CredentialResult<()> {
+            self.put(id, "smtp", password.to_string());
+            Ok(())
+        }
+
+        fn get_imap_password(&self, id: AccountId) -> CredentialResult<Option<String>> {
+            Ok(self.get(id, "imap"))
+        }
+
+        fn get_smtp_password(&self, id: AccountId) -> CredentialResult<Option<String>> {
+            Ok(self.get(id, "smtp"))
+        }
+
+        fn store_oauth_token(&self, id: AccountId, token: &Token) -> CredentialResult<()> {
+            self.put(id, "oauth", serde_json::to_string(token).unwrap());
+            Ok(())
+        }
+
+        fn get_oauth_token(&self, id: AccountId) -> CredentialResult<Option<Token>> {
+            Ok(self
+                .get(id, "oauth")
+                .map(|json| serde_json::from_str(&json).unwrap()))
+        }
+
+        fn delete_credentials(&self, id: AccountId) -> CredentialResult<()> {
+            self.secrets
+                .lock()
+                .unwrap()
+                .retain(|(key, _), _| *key != id.0);
+            Ok(())
+        }
+    }
+
     #[tokio::test]
     async fn test_create_and_retrieve_account() {
         let repo = AccountRepository::in_memory().await.unwrap();
 
         let mut account = Account::with_email("test@example.com");
-        account.imap.password = "secret".to_string();
-        account.smtp.password = "secret".to_string();
+        account.imap.auth = AuthMethod::Password("secret".to_string());
+        account.smtp.auth = AuthMethod::Password("secret".to_string());
 
         repo.save(&mut account).await.unwrap();
         assert!(account.id.is_some());
@@ -424,13 +686,13 @@ mod tests {
         let repo = AccountRepository::in_memory().await.unwrap();
 
         let mut account1 = Account::with_email("user1@example.com");
-        account1.imap.password = "secret".to_string();
-        account1.smtp.password = "secret".to_string();
+        account1.imap.auth = AuthMethod::Password("secret".to_string());
+        account1.smtp.auth = AuthMethod::Password("secret".to_string());
         repo.save(&mut account1).await.unwrap();
 
         let mut account2 = Account::with_email("user2@example.com");
-        account2.imap.password = "secret".to_string();
-        account2.smtp.password = "secret".to_string();
+        account2.imap.auth = AuthMethod::Password("secret".to_string());
+        account2.smtp.auth = AuthMethod::Password("secret".to_string());
         repo.save(&mut account2).await.unwrap();
 
         let accounts = repo.list().await.unwrap();
@@ -442,8 +704,8 @@ mod tests {
         let repo = AccountRepository::in_memory().await.unwrap();
 
         let mut account = Account::with_email("default@example.com");
-        account.imap.password = "secret".to_string();
-        account.smtp.password = "secret".to_string();
+        account.imap.auth = AuthMethod::Password("secret".to_string());
+        account.smtp.auth = AuthMethod::Password("secret".to_string());
         account.is_default = true;
         repo.save(&mut account).await.unwrap();
 
@@ -451,4 +713,108 @@ mod tests {
         assert!(default.is_some());
         assert_eq!(default.unwrap().email, "default@example.com");
     }
+
+    #[tokio::test]
+    async fn test_oauth_sign_in_round_trip() {
+        let repo = AccountRepository::in_memory().await.unwrap();
+
+        let mut account = Account::with_email("user@gmail.com");
+        let auth = AuthMethod::OAuth {
+            provider: OAuthProvider::Google,
+            token: Token::new("access", "Bearer").with_refresh_token("refresh"),
+            client_id: "client".to_string(),
+        };
+        account.imap.auth = auth.clone();
+        account.smtp.auth = auth;
+        repo.save(&mut account).await.unwrap();
+
+        let retrieved = repo.get(account.id.unwrap()).await.unwrap().unwrap();
+        for auth in [&retrieved.imap.auth, &retrieved.smtp.auth] {
+            let AuthMethod::OAuth {
+                provider,
+                client_id,
+                ..
+            } = auth
+            else {
+                panic!("Expected OAuth, got {auth:?}");
+            };
+            assert_eq!(*provider, OAuthProvider::Google);
+            assert_eq!(client_id, "client");
+        }
+
+        // Switching back to a password forgets the sign-in
+        let mut account = retrieved;
+        account.imap.auth = AuthMethod::Password("secret".to_string());
+        account.smtp.auth = AuthMethod::Password("secret".to_string());
+        repo.save(&mut account).await.unwrap();
+        let retrieved = repo.get(account.id.unwrap()).await.unwrap().unwrap();
+        assert!(retrieved.imap.auth.password().is_some());
+        assert!(retrieved.smtp.auth.password().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_passwords_live_in_credential_store() {
+        let store = Arc::new(MemoryCredentialStore::default());
+        let repo = AccountRepository::in_memory_with_credential_store(store.clone())
+            .await
+            .unwrap();
+
+        let mut account = Account::with_email("user@example.com");
+        account.imap.auth = AuthMethod::Password("imap-secret".to_string());
+        account.smtp.auth = AuthMethod::Password("smtp-secret".to_string());
+        repo.save(&mut account).await.unwrap();
+        let id = account.id.unwrap();
+
+        // Only placeholders reach the database
+        let row = sqlx::query("SELECT imap_password, smtp_password FROM accounts WHERE id = ?")
+            .bind(id.0)
+            .fetch_one(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>("imap_password"), "");
+        assert_eq!(row.get::<String, _>("smtp_password"), "");
+        assert_eq!(store.get(id, "imap").as_deref(), Some("imap-secret"));
+
+        let retrieved = repo.get(id).await.unwrap().unwrap();
+        assert_eq!(retrieved.imap.auth.password(), Some("imap-secret"));
+        assert_eq!(retrieved.smtp.auth.password(), Some("smtp-secret"));
+
+        repo.delete(id).await.unwrap();
+        assert_eq!(store.get(id, "imap"), None);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_passwords_are_migrated() {
+        let store = Arc::new(MemoryCredentialStore::default());
+        let repo = AccountRepository::in_memory_with_credential_store(store.clone())
+            .await
+            .unwrap();
+        sqlx::query(
+            r"
+            INSERT INTO accounts (
+                name, email,
+                imap_host, imap_port, imap_security, imap_username, imap_password,
+                smtp_host, smtp_port, smtp_security, smtp_username, smtp_password
+            ) VALUES ('Old', 'old@example.com', 'imap.example.com', 993, 'tls', 'old',
+                      'imap-plain', 'smtp.example.com', 465, 'tls', 'old', 'smtp-plain')
+            ",
+        )
+        .execute(&repo.pool)
+        .await
+        .unwrap();
+
+        repo.migrate_plaintext_passwords().await.unwrap();
+
+        let account = repo.list().await.unwrap().remove(0);
+        let id = account.id.unwrap();
+        assert_eq!(account.imap.auth.password(), Some("imap-plain"));
+        assert_eq!(account.smtp.auth.password(), Some("smtp-plain"));
+        assert_eq!(store.get(id, "smtp").as_deref(), Some("smtp-plain"));
+        let row = sqlx::query("SELECT imap_password FROM accounts WHERE id = ?")
+            .bind(id.0)
+            .fetch_one(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>("imap_password"), "");
+    }
 }
@@ -109,7 +109,7 @@ pub fn validate_account(account: &Account) -> ValidationResult {
     if account.imap.username.trim().is_empty() {
         errors.push(ValidationError::EmptyImapUsername);
     }
-    if account.imap.password.is_empty() {
+    if account.imap.auth.password().is_some_and(str::is_empty) {
         errors.push(ValidationError::EmptyImapPassword);
     }
 
@@ -123,7 +123,7 @@ pub fn validate_account(account: &Account) -> ValidationResult {
     if account.smtp.username.trim().is_empty() {
         errors.push(ValidationError::EmptySmtpUsername);
     }
-    if account.smtp.password.is_empty() {
+    if account.smtp.auth.password().is_some_and(str::is_empty) {
         errors.push(ValidationError::EmptySmtpPassword);
     }
 
@@ -178,6 +178,7 @@ fn is_valid_email(email: &str) -> bool {
 )]
 mod tests {
     use super::*;
+    use crate::account::AuthMethod;
 
     #[test]
     fn test_valid_email() {
@@ -209,9 +210,22 @@ mod tests {
     #[test]
     fn test_validate_complete_account() {
         let mut account = Account::with_email("test@gmail.com");
-        account.imap.password = "secret".to_string();
-        account.smtp.password = "secret".to_string();
+        account.imap.auth = AuthMethod::Password("secret".to_string());
+        account.smtp.auth = AuthMethod::Password("secret".to_string());
         let result = validate_account(&account);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_oauth_needs_no_password() {
+        let mut account = Account::with_email("test@gmail.com");
+        let auth = AuthMethod::OAuth {
+            provider: crate::account::OAuthProvider::Google,
+            token: mailledger_oauth::Token::new("access", "Bearer"),
+            client_id: "client".to_string(),
+        };
+        account.imap.auth = auth.clone();
+        account.smtp.auth = auth;
+        assert!(validate_account(&account).is_ok());
+    }
 }
@@ -6,5 +6,5 @@
 mod model;
 mod repository;
 
-pub use model::{CachedMessageContent, CachedMessageSummary};
-pub use repository::CacheRepository;
+pub use model::{CachedMessageContent, CachedMessageSummary, FolderSyncState};
+pub use repository::{CacheRepository, DEFAULT_ATTACHMENT_CACHE_BYTES};
@@ -3,6 +3,7 @@
 use chrono::{DateTime, Utc};
 
 use crate::AccountId;
+use crate::service::Attachment;
 
 /// A cached message summary for offline display.
 #[derive(Debug, Clone)]
@@ -33,6 +34,20 @@ pub struct CachedMessageSummary {
     pub cached_at: DateTime<Utc>,
 }
 
+/// Where the last sync of a folder left off.
+///
+/// The default state has a zero UIDVALIDITY, which never matches a server's,
+/// so syncing from it always starts over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FolderSyncState {
+    /// The folder's UIDVALIDITY when it was synced.
+    pub uid_validity: u32,
+    /// The UID the next new message will get at least.
+    pub uid_next: u32,
+    /// HIGHESTMODSEQ when it was synced, if the server has CONDSTORE.
+    pub highest_modseq: Option<u64>,
+}
+
 /// Cached message content for offline viewing.
 #[derive(Debug, Clone)]
 pub struct CachedMessageContent {
@@ -61,3 +76,27 @@ pub struct CachedMessageContent {
     /// When the content was cached.
     pub cached_at: DateTime<Utc>,
 }
+
+impl CachedMessageContent {
+    /// Serializes attachment metadata for [`attachments_json`](Self::attachments_json).
+    ///
+    /// Returns `None` when there are no attachments.
+    #[must_use]
+    pub fn encode_attachments(attachments: &[Attachment]) -> Option<String> {
+        if attachments.is_empty() {
+            return None;
+        }
+        serde_json::to_string(attachments).ok()
+    }
+
+    /// Returns the cached attachment metadata.
+    ///
+    /// Missing or unreadable JSON yields an empty list.
+    #[must_use]
+    pub fn attachments(&self) -> Vec<Attachment> {
+        self.attachments_json
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+}
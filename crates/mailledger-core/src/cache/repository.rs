@@ -4,12 +4,16 @@ use chrono::{DateTime, Utc};
 use sqlx::Row;
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 
-use super::model::{CachedMessageContent, CachedMessageSummary};
+use super::model::{CachedMessageContent, CachedMessageSummary, FolderSyncState};
 use crate::{AccountId, Result};
 
+/// Default limit on the total size of cached attachment bytes (256 MiB).
+pub const DEFAULT_ATTACHMENT_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
 /// Repository for message cache storage and retrieval.
 pub struct CacheRepository {
     pool: SqlitePool,
+    attachment_cap: u64,
 }
 
 impl CacheRepository {
@@ -27,7 +31,10 @@ impl CacheRepository {
             .connect(&url)
             .await?;
 
-        let repo = Self { pool };
+        let repo = Self {
+            pool,
+            attachment_cap: DEFAULT_ATTACHMENT_CACHE_BYTES,
+        };
         repo.initialize().await?;
         Ok(repo)
     }
@@ -44,11 +51,24 @@ impl CacheRepository {
             .connect("sqlite::memory:")
             .await?;
 
-        let repo = Self { pool };
+        let repo = Self {
+            pool,
+            attachment_cap: DEFAULT_ATTACHMENT_CACHE_BYTES,
+        };
         repo.initialize().await?;
         Ok(repo)
     }
 
+    /// Set the limit on the total size of cached attachment bytes.
+    ///
+    /// When the limit is exceeded, the least recently used attachments are
+    /// evicted first.
+    #[must_use]
+    pub const fn with_attachment_cap(mut self, bytes: u64) -> Self {
+        self.attachment_cap = bytes;
+        self
+    }
+
     /// Initialize database schema.
     async fn initialize(&self) -> Result<()> {
         // Message summaries table (for list view)
@@ -118,6 +138,120 @@ impl CacheRepository {
         .execute(&self.pool)
         .await?;
 
+        // Downloaded attachment bytes, evicted least recently used first
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS cached_attachments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                folder_path TEXT NOT NULL,
+                uid INTEGER NOT NULL,
+                part_number TEXT NOT NULL,
+                data BLOB NOT NULL,
+                size INTEGER NOT NULL,
+                last_used INTEGER NOT NULL,
+                UNIQUE(account_id, folder_path, uid, part_number)
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Where each folder's last sync left off
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS folder_sync_state (
+                account_id INTEGER NOT NULL,
+                folder_path TEXT NOT NULL,
+                uid_validity INTEGER NOT NULL,
+                uid_next INTEGER NOT NULL,
+                highest_modseq INTEGER,
+                PRIMARY KEY(account_id, folder_path)
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.initialize_fts().await
+    }
+
+    /// Create the full-text index and fill it from existing cache rows.
+    async fn initialize_fts(&self) -> Result<()> {
+        // Full-text index over subject, sender and body for offline search
+        sqlx::query(
+            r"
+            CREATE VIRTUAL TABLE IF NOT EXISTS cached_message_fts USING fts5(
+                subject,
+                sender,
+                body,
+                account_id UNINDEXED,
+                folder_path UNINDEXED,
+                uid UNINDEXED
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Index messages cached before the full-text table existed
+        sqlx::query(
+            r"
+            INSERT INTO cached_message_fts
+                (subject, sender, body, account_id, folder_path, uid)
+            SELECT s.subject, s.from_name || ' ' || s.from_email,
+                   COALESCE(c.body_text, s.snippet), s.account_id, s.folder_path, s.uid
+            FROM cached_message_summaries s
+            LEFT JOIN cached_message_content c
+                ON c.account_id = s.account_id
+                AND c.folder_path = s.folder_path
+                AND c.uid = s.uid
+            WHERE NOT EXISTS (SELECT 1 FROM cached_message_fts)
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Rebuild the full-text index entry for one message.
+    ///
+    /// Indexes the cached body text when present, falling back to the
+    /// snippet. Messages without a cached summary are not indexed.
+    async fn reindex(&self, account_id: AccountId, folder_path: &str, uid: u32) -> Result<()> {
+        sqlx::query(
+            r"
+            DELETE FROM cached_message_fts
+            WHERE account_id = ? AND folder_path = ? AND uid = ?
+            ",
+        )
+        .bind(account_id.0)
+        .bind(folder_path)
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO cached_message_fts
+                (subject, sender, body, account_id, folder_path, uid)
+            SELECT s.subject, s.from_name || ' ' || s.from_email,
+                   COALESCE(c.body_text, s.snippet), s.account_id, s.folder_path, s.uid
+            FROM cached_message_summaries s
+            LEFT JOIN cached_message_content c
+                ON c.account_id = s.account_id
+                AND c.folder_path = s.folder_path
+                AND c.uid = s.uid
+            WHERE s.account_id = ? AND s.folder_path = ? AND s.uid = ?
+            ",
+        )
+        .bind(account_id.0)
+        .bind(folder_path)
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 
@@ -160,7 +294,8 @@ impl CacheRepository {
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        self.reindex(summary.account_id, &summary.folder_path, summary.uid)
+            .await
     }
 
     /// Cache multiple message summaries in a batch.
@@ -199,32 +334,49 @@ impl CacheRepository {
         .fetch_all(&self.pool)
         .await?;
 
-        let summaries = rows
-            .iter()
-            .filter_map(|row| {
-                let cached_at_str: String = row.get("cached_at");
-                let cached_at = DateTime::parse_from_rfc3339(&cached_at_str)
-                    .ok()?
-                    .with_timezone(&Utc);
-
-                Some(CachedMessageSummary {
-                    account_id: AccountId(row.get::<i64, _>("account_id")),
-                    folder_path: row.get("folder_path"),
-                    uid: row.get::<u32, _>("uid"),
-                    from_name: row.get("from_name"),
-                    from_email: row.get("from_email"),
-                    subject: row.get("subject"),
-                    snippet: row.get("snippet"),
-                    date: row.get("date"),
-                    is_read: row.get::<bool, _>("is_read"),
-                    is_flagged: row.get::<bool, _>("is_flagged"),
-                    has_attachments: row.get::<bool, _>("has_attachments"),
-                    cached_at,
-                })
-            })
-            .collect();
+        Ok(rows.iter().filter_map(summary_from_row).collect())
+    }
+
+    /// Search cached messages of an account with the full-text index.
+    ///
+    /// Matches subject, sender and body text across all folders, best match
+    /// first. Words are matched as whole tokens; `"quoted text"` matches a
+    /// phrase and a trailing `*` matches a prefix, as in `invo*`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn search_fts(
+        &self,
+        account_id: AccountId,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<CachedMessageSummary>> {
+        let Some(expression) = fts_expression(query) else {
+            return Ok(Vec::new());
+        };
 
-        Ok(summaries)
+        let rows = sqlx::query(
+            r"
+            SELECT s.account_id, s.folder_path, s.uid, s.from_name, s.from_email, s.subject,
+                   s.snippet, s.date, s.is_read, s.is_flagged, s.has_attachments, s.cached_at
+            FROM cached_message_fts f
+            JOIN cached_message_summaries s
+                ON s.account_id = f.account_id
+                AND s.folder_path = f.folder_path
+                AND s.uid = f.uid
+            WHERE cached_message_fts MATCH ? AND f.account_id = ?
+            ORDER BY f.rank
+            LIMIT ?
+            ",
+        )
+        .bind(expression)
+        .bind(account_id.0)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().filter_map(summary_from_row).collect())
     }
 
     /// Cache message content.
@@ -266,7 +418,8 @@ impl CacheRepository {
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        self.reindex(content.account_id, &content.folder_path, content.uid)
+            .await
     }
 
     /// Get cached content for a message.
@@ -319,6 +472,99 @@ impl CacheRepository {
         Ok(content)
     }
 
+    /// Cache the decoded bytes of a downloaded attachment.
+    ///
+    /// Evicts the least recently used attachments if the total exceeds the
+    /// cap set with [`with_attachment_cap`](Self::with_attachment_cap).
+    /// Attachments larger than the cap are not cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn cache_attachment(
+        &self,
+        account_id: AccountId,
+        folder_path: &str,
+        uid: u32,
+        part_number: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        let size = data.len() as u64;
+        if size > self.attachment_cap {
+            return Ok(());
+        }
+        let size = i64::try_from(size).unwrap_or(i64::MAX);
+
+        sqlx::query(
+            r"
+            INSERT INTO cached_attachments
+                (account_id, folder_path, uid, part_number, data, size, last_used)
+            VALUES (?, ?, ?, ?, ?, ?,
+                    (SELECT COALESCE(MAX(last_used), 0) + 1 FROM cached_attachments))
+            ON CONFLICT(account_id, folder_path, uid, part_number) DO UPDATE SET
+                data = excluded.data,
+                size = excluded.size,
+                last_used = excluded.last_used
+            ",
+        )
+        .bind(account_id.0)
+        .bind(folder_path)
+        .bind(uid)
+        .bind(part_number)
+        .bind(data)
+        .bind(size)
+        .execute(&self.pool)
+        .await?;
+
+        // Keep the most recently used attachments that fit under the cap
+        sqlx::query(
+            r"
+            DELETE FROM cached_attachments WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, SUM(size) OVER (ORDER BY last_used DESC) AS running
+                    FROM cached_attachments
+                )
+                WHERE running > ?
+            )
+            ",
+        )
+        .bind(i64::try_from(self.attachment_cap).unwrap_or(i64::MAX))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the cached bytes of an attachment, marking it as recently used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn get_attachment(
+        &self,
+        account_id: AccountId,
+        folder_path: &str,
+        uid: u32,
+        part_number: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let row = sqlx::query(
+            r"
+            UPDATE cached_attachments
+            SET last_used = (SELECT COALESCE(MAX(last_used), 0) + 1 FROM cached_attachments)
+            WHERE account_id = ? AND folder_path = ? AND uid = ? AND part_number = ?
+            RETURNING data
+            ",
+        )
+        .bind(account_id.0)
+        .bind(folder_path)
+        .bind(uid)
+        .bind(part_number)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| row.get("data")))
+    }
+
     /// Clear cache for a specific folder.
     ///
     /// # Errors
@@ -339,6 +585,88 @@ impl CacheRepository {
             .execute(&self.pool)
             .await?;
 
+        sqlx::query(r"DELETE FROM cached_message_fts WHERE account_id = ? AND folder_path = ?")
+            .bind(account_id.0)
+            .bind(folder_path)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(r"DELETE FROM cached_attachments WHERE account_id = ? AND folder_path = ?")
+            .bind(account_id.0)
+            .bind(folder_path)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(r"DELETE FROM folder_sync_state WHERE account_id = ? AND folder_path = ?")
+            .bind(account_id.0)
+            .bind(folder_path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the saved sync state of a folder.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn get_sync_state(
+        &self,
+        account_id: AccountId,
+        folder_path: &str,
+    ) -> Result<Option<FolderSyncState>> {
+        let row = sqlx::query(
+            r"
+            SELECT uid_validity, uid_next, highest_modseq
+            FROM folder_sync_state
+            WHERE account_id = ? AND folder_path = ?
+            ",
+        )
+        .bind(account_id.0)
+        .bind(folder_path)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| FolderSyncState {
+            uid_validity: row.get::<u32, _>("uid_validity"),
+            uid_next: row.get::<u32, _>("uid_next"),
+            highest_modseq: row
+                .get::<Option<i64>, _>("highest_modseq")
+                .and_then(|modseq| u64::try_from(modseq).ok()),
+        }))
+    }
+
+    /// Save the sync state of a folder, replacing any earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn save_sync_state(
+        &self,
+        account_id: AccountId,
+        folder_path: &str,
+        state: &FolderSyncState,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            INSERT OR REPLACE INTO folder_sync_state
+                (account_id, folder_path, uid_validity, uid_next, highest_modseq)
+            VALUES (?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(account_id.0)
+        .bind(folder_path)
+        .bind(state.uid_validity)
+        .bind(state.uid_next)
+        .bind(
+            state
+                .highest_modseq
+                .and_then(|modseq| i64::try_from(modseq).ok()),
+        )
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 
@@ -358,6 +686,21 @@ impl CacheRepository {
             .execute(&self.pool)
             .await?;
 
+        sqlx::query(r"DELETE FROM cached_message_fts WHERE account_id = ?")
+            .bind(account_id.0)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(r"DELETE FROM cached_attachments WHERE account_id = ?")
+            .bind(account_id.0)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(r"DELETE FROM folder_sync_state WHERE account_id = ?")
+            .bind(account_id.0)
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
 
@@ -419,6 +762,72 @@ impl CacheRepository {
     }
 }
 
+/// Builds a cached summary from a `cached_message_summaries` row.
+fn summary_from_row(row: &sqlx::sqlite::SqliteRow) -> Option<CachedMessageSummary> {
+    let cached_at_str: String = row.get("cached_at");
+    let cached_at = DateTime::parse_from_rfc3339(&cached_at_str)
+        .ok()?
+        .with_timezone(&Utc);
+
+    Some(CachedMessageSummary {
+        account_id: AccountId(row.get::<i64, _>("account_id")),
+        folder_path: row.get("folder_path"),
+        uid: row.get::<u32, _>("uid"),
+        from_name: row.get("from_name"),
+        from_email: row.get("from_email"),
+        subject: row.get("subject"),
+        snippet: row.get("snippet"),
+        date: row.get("date"),
+        is_read: row.get::<bool, _>("is_read"),
+        is_flagged: row.get::<bool, _>("is_flagged"),
+        has_attachments: row.get::<bool, _>("has_attachments"),
+        cached_at,
+    })
+}
+
+/// Turns a user query into an FTS5 expression.
+///
+/// Every word and `"quoted phrase"` becomes a quoted FTS5 string, so
+/// operators and punctuation in the input can't break the query. A
+/// trailing `*` on a word is kept as a prefix match. Returns `None` if the
+/// query has no terms.
+fn fts_expression(query: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut rest = query.trim();
+
+    while !rest.is_empty() {
+        let (term, prefix, remainder) = next_fts_term(rest);
+        let term = term.trim();
+        if !term.is_empty() {
+            let quoted = format!("\"{}\"", term.replace('"', "\"\""));
+            terms.push(if prefix { quoted + "*" } else { quoted });
+        }
+        rest = remainder.trim_start();
+    }
+
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Splits the first word or quoted phrase off a query.
+///
+/// Returns the term, whether it ended in `*`, and the remaining input.
+fn next_fts_term(query: &str) -> (&str, bool, &str) {
+    query.strip_prefix('"').map_or_else(
+        || {
+            let end = query
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(query.len());
+            let (word, remainder) = query.split_at(end);
+            word.strip_suffix('*')
+                .map_or((word, false, remainder), |w| (w, true, remainder))
+        },
+        |quoted| {
+            let (phrase, remainder) = quoted.split_once('"').unwrap_or((quoted, ""));
+            (phrase, false, remainder)
+        },
+    )
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -463,7 +872,7 @@ mod tests {
             uid: 123,
             from: "John Doe <john@example.com>".to_string(),
             to: "me@example.com".to_string(),
-            cc: "".to_string(),
+            cc: String::new(),
             subject: "Test Subject".to_string(),
             date: "Fri, 24 Jan 2026 10:00:00 +0000".to_string(),
             body_text: Some("Hello, this is the message body.".to_string()),
@@ -540,4 +949,264 @@ mod tests {
         repo.clear_folder(AccountId(1), "INBOX").await.unwrap();
         assert!(!repo.has_cached_folder(AccountId(1), "INBOX").await.unwrap());
     }
+
+    fn summary(uid: u32, subject: &str, from_name: &str, snippet: &str) -> CachedMessageSummary {
+        CachedMessageSummary {
+            account_id: AccountId(1),
+            folder_path: "INBOX".to_string(),
+            uid,
+            from_name: from_name.to_string(),
+            from_email: format!("{}@example.com", from_name.to_lowercase()),
+            subject: subject.to_string(),
+            snippet: snippet.to_string(),
+            date: "Jan 24".to_string(),
+            is_read: false,
+            is_flagged: false,
+            has_attachments: false,
+            cached_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_fts() {
+        let repo = CacheRepository::in_memory().await.unwrap();
+        repo.cache_summaries(&[
+            summary(1, "Quarterly invoice", "Alice", "Please find attached"),
+            summary(2, "Lunch plans", "Bob", "Are you free on Friday?"),
+            summary(3, "Invoice reminder", "Carol", "The invoice is overdue"),
+        ])
+        .await
+        .unwrap();
+
+        let uids = |results: Vec<CachedMessageSummary>| {
+            let mut uids: Vec<u32> = results.iter().map(|s| s.uid).collect();
+            uids.sort_unstable();
+            uids
+        };
+
+        let results = repo.search_fts(AccountId(1), "invoice", 10).await.unwrap();
+        assert_eq!(uids(results), vec![1, 3]);
+
+        // Prefix and sender matching
+        let results = repo.search_fts(AccountId(1), "lun*", 10).await.unwrap();
+        assert_eq!(uids(results), vec![2]);
+        let results = repo.search_fts(AccountId(1), "carol", 10).await.unwrap();
+        assert_eq!(uids(results), vec![3]);
+
+        // Phrase queries match words in order only
+        let results = repo
+            .search_fts(AccountId(1), "\"invoice is overdue\"", 10)
+            .await
+            .unwrap();
+        assert_eq!(uids(results), vec![3]);
+        let results = repo
+            .search_fts(AccountId(1), "\"overdue invoice\"", 10)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        // Other accounts and empty queries match nothing
+        let results = repo.search_fts(AccountId(2), "invoice", 10).await.unwrap();
+        assert!(results.is_empty());
+        let results = repo.search_fts(AccountId(1), "  ", 10).await.unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_fts_tracks_cached_content() {
+        let repo = CacheRepository::in_memory().await.unwrap();
+        repo.cache_summary(&summary(7, "Hello", "Dave", "short preview"))
+            .await
+            .unwrap();
+        assert!(
+            repo.search_fts(AccountId(1), "kangaroo", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let content = CachedMessageContent {
+            account_id: AccountId(1),
+            folder_path: "INBOX".to_string(),
+            uid: 7,
+            from: "Dave <dave@example.com>".to_string(),
+            to: "me@example.com".to_string(),
+            cc: String::new(),
+            subject: "Hello".to_string(),
+            date: "Jan 24".to_string(),
+            body_text: Some("The full body mentions a kangaroo.".to_string()),
+            body_html: None,
+            attachments_json: None,
+            cached_at: Utc::now(),
+        };
+        repo.cache_content(&content).await.unwrap();
+
+        let results = repo.search_fts(AccountId(1), "kangaroo", 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].uid, 7);
+
+        // Re-caching the summary keeps a single index entry
+        repo.cache_summary(&summary(7, "Hello", "Dave", "short preview"))
+            .await
+            .unwrap();
+        let results = repo.search_fts(AccountId(1), "kangaroo", 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+
+        repo.clear_folder(AccountId(1), "INBOX").await.unwrap();
+        assert!(
+            repo.search_fts(AccountId(1), "kangaroo", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_fts_expression() {
+        assert_eq!(fts_expression("invoice"), Some("\"invoice\"".to_string()));
+        assert_eq!(
+            fts_expression("inv* \"due soon\" OR"),
+            Some("\"inv\"* \"due soon\" \"OR\"".to_string())
+        );
+        assert_eq!(fts_expression("\"\""), None);
+    }
+
+    #[tokio::test]
+    async fn test_attachments_json_round_trip() {
+        use crate::service::Attachment;
+
+        let repo = CacheRepository::in_memory().await.unwrap();
+        let attachments = vec![Attachment {
+            filename: "report.pdf".to_string(),
+            mime_type: "application/pdf".to_string(),
+            size: 2048,
+            part_number: "2".to_string(),
+            encoding: "base64".to_string(),
+        }];
+
+        let content = CachedMessageContent {
+            account_id: AccountId(1),
+            folder_path: "INBOX".to_string(),
+            uid: 5,
+            from: String::new(),
+            to: String::new(),
+            cc: String::new(),
+            subject: "Report".to_string(),
+            date: String::new(),
+            body_text: None,
+            body_html: None,
+            attachments_json: CachedMessageContent::encode_attachments(&attachments),
+            cached_at: Utc::now(),
+        };
+        repo.cache_content(&content).await.unwrap();
+
+        let retrieved = repo
+            .get_content(AccountId(1), "INBOX", 5)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(retrieved.attachments(), attachments);
+        assert_eq!(CachedMessageContent::encode_attachments(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_attachment_bytes_lru_eviction() {
+        let repo = CacheRepository::in_memory()
+            .await
+            .unwrap()
+            .with_attachment_cap(10);
+        let id = AccountId(1);
+
+        repo.cache_attachment(id, "INBOX", 1, "2", b"aaaa")
+            .await
+            .unwrap();
+        repo.cache_attachment(id, "INBOX", 2, "2", b"bbbb")
+            .await
+            .unwrap();
+        // Touch the first so the second becomes least recently used
+        assert_eq!(
+            repo.get_attachment(id, "INBOX", 1, "2").await.unwrap(),
+            Some(b"aaaa".to_vec())
+        );
+        repo.cache_attachment(id, "INBOX", 3, "2", b"cccc")
+            .await
+            .unwrap();
+
+        assert!(
+            repo.get_attachment(id, "INBOX", 1, "2")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            repo.get_attachment(id, "INBOX", 2, "2")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            repo.get_attachment(id, "INBOX", 3, "2")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Larger than the cap: never stored
+        repo.cache_attachment(id, "INBOX", 4, "1", &[0; 11])
+            .await
+            .unwrap();
+        assert!(
+            repo.get_attachment(id, "INBOX", 4, "1")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        repo.clear_account(id).await.unwrap();
+        assert!(
+            repo.get_attachment(id, "INBOX", 1, "2")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_state_round_trip() {
+        let repo = CacheRepository::in_memory().await.unwrap();
+        assert!(
+            repo.get_sync_state(AccountId(1), "INBOX")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let state = FolderSyncState {
+            uid_validity: 7,
+            uid_next: 120,
+            highest_modseq: Some(90_000),
+        };
+        repo.save_sync_state(AccountId(1), "INBOX", &state)
+            .await
+            .unwrap();
+        let newer = FolderSyncState {
+            uid_next: 125,
+            ..state
+        };
+        repo.save_sync_state(AccountId(1), "INBOX", &newer)
+            .await
+            .unwrap();
+        assert_eq!(
+            repo.get_sync_state(AccountId(1), "INBOX").await.unwrap(),
+            Some(newer)
+        );
+
+        repo.clear_folder(AccountId(1), "INBOX").await.unwrap();
+        assert!(
+            repo.get_sync_state(AccountId(1), "INBOX")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,14 @@
+//! Rule-based message filters.
+//!
+//! A [`Filter`] pairs match conditions (sender, recipient, subject, header,
+//! attachments) with actions (move, flag, mark read, delete, categorize).
+//! Filters are stored per account in [`FilterRepository`] and run against
+//! freshly loaded messages with [`FilterRepository::apply_filters`], which
+//! performs the moves, deletions and flag changes on the server and
+//! remembers the categories it assigns.
+
+mod model;
+mod repository;
+
+pub use model::{Action, Condition, Filter, FilterReport};
+pub use repository::FilterRepository;
@@ -0,0 +1,268 @@
+//! Filter data models.
+
+use mailledger_imap::types::Uid;
+use mailledger_mime::Headers;
+use serde::{Deserialize, Serialize};
+
+use crate::AccountId;
+use crate::service::MessageSummary;
+use crate::triage::InboxCategory;
+
+/// A test a message must pass for a filter to apply.
+///
+/// Text comparisons are case-insensitive substring matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Condition {
+    /// Sender name or address contains the text.
+    FromContains(String),
+    /// Sender address is at the domain or one of its subdomains.
+    FromDomain(String),
+    /// Recipient contains the text.
+    ToContains(String),
+    /// Subject contains the text.
+    SubjectContains(String),
+    /// A header field contains the text.
+    Header {
+        /// Header field name, e.g. `List-Id`.
+        name: String,
+        /// Text the value must contain.
+        contains: String,
+    },
+    /// The message does (or does not) have attachments.
+    HasAttachment(bool),
+}
+
+impl Condition {
+    /// Returns the header field this condition needs fetched, if any.
+    #[must_use]
+    pub fn header_name(&self) -> Option<&str> {
+        match self {
+            Self::Header { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Tests a message. `headers` holds the fields named by
+    /// [`header_name`](Self::header_name); header conditions fail without it.
+    #[must_use]
+    pub fn matches(&self, message: &MessageSummary, headers: Option<&Headers>) -> bool {
+        match self {
+            Self::FromContains(text) => {
+                contains(&message.from, text) || contains(&message.from_email, text)
+            }
+            Self::FromDomain(domain) => {
+                let domain = domain.trim_start_matches('@').to_lowercase();
+                message
+                    .from_email
+                    .rsplit_once('@')
+                    .is_some_and(|(_, host)| {
+                        let host = host.to_lowercase();
+                        host == domain || host.ends_with(&format!(".{domain}"))
+                    })
+            }
+            Self::ToContains(text) => contains(&message.to, text),
+            Self::SubjectContains(text) => contains(&message.subject, text),
+            Self::Header {
+                name,
+                contains: text,
+            } => headers.is_some_and(|headers| {
+                headers
+                    .get_all(name)
+                    .into_iter()
+                    .any(|value| contains(value, text))
+            }),
+            Self::HasAttachment(expected) => message.has_attachment == *expected,
+        }
+    }
+}
+
+/// Case-insensitive substring test.
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// What to do with a matching message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Action {
+    /// Move to the folder with this path.
+    Move(String),
+    /// Add the `\Flagged` flag.
+    Flag,
+    /// Add the `\Seen` flag.
+    MarkRead,
+    /// Add the `\Deleted` flag and expunge the message.
+    Delete,
+    /// Route to a triage category. Stored locally rather than on the server.
+    ApplyCategory(InboxCategory),
+}
+
+/// A rule: when every condition matches, run every action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    /// Database ID, `None` until saved.
+    pub id: Option<i64>,
+    /// Account the filter belongs to.
+    pub account_id: AccountId,
+    /// User-visible name.
+    pub name: String,
+    /// Disabled filters are kept but not run.
+    pub enabled: bool,
+    /// Conditions, all of which must match.
+    pub conditions: Vec<Condition>,
+    /// Actions, run in order.
+    pub actions: Vec<Action>,
+}
+
+impl Filter {
+    /// Creates an enabled, unsaved filter.
+    #[must_use]
+    pub fn new(
+        account_id: AccountId,
+        name: impl Into<String>,
+        conditions: Vec<Condition>,
+        actions: Vec<Action>,
+    ) -> Self {
+        Self {
+            id: None,
+            account_id,
+            name: name.into(),
+            enabled: true,
+            conditions,
+            actions,
+        }
+    }
+
+    /// Returns true if the filter is enabled and every condition matches.
+    ///
+    /// A filter without conditions never matches, so an empty rule cannot
+    /// act on the whole mailbox.
+    #[must_use]
+    pub fn matches(&self, message: &MessageSummary, headers: Option<&Headers>) -> bool {
+        self.enabled
+            && !self.conditions.is_empty()
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(message, headers))
+    }
+}
+
+/// What a filter run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterReport {
+    /// Messages moved, with their destination folder.
+    pub moved: Vec<(Uid, String)>,
+    /// Messages flagged.
+    pub flagged: Vec<Uid>,
+    /// Messages marked read.
+    pub marked_read: Vec<Uid>,
+    /// Messages deleted.
+    pub deleted: Vec<Uid>,
+    /// Messages routed to a triage category.
+    pub categorized: Vec<(Uid, InboxCategory)>,
+}
+
+impl FilterReport {
+    /// Returns true if the message was moved out of the folder or deleted.
+    #[must_use]
+    pub fn removed(&self, uid: Uid) -> bool {
+        self.deleted.contains(&uid) || self.moved.iter().any(|(moved, _)| *moved == uid)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn message() -> MessageSummary {
+        MessageSummary {
+            uid: Uid::new(1).unwrap(),
+            subject: "Your Invoice #42".to_string(),
+            from: "Billing Team".to_string(),
+            from_email: "billing@mail.Example.com".to_string(),
+            to: "me@example.org".to_string(),
+            date: String::new(),
+            is_read: false,
+            is_flagged: false,
+            has_attachment: true,
+            snippet: String::new(),
+            message_id: None,
+            in_reply_to: None,
+            thread_id: None,
+            references: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_text_conditions_are_case_insensitive() {
+        let message = message();
+        assert!(Condition::SubjectContains("invoice".into()).matches(&message, None));
+        assert!(Condition::FromContains("billing team".into()).matches(&message, None));
+        assert!(Condition::FromContains("BILLING@".into()).matches(&message, None));
+        assert!(Condition::ToContains("example.org".into()).matches(&message, None));
+        assert!(!Condition::SubjectContains("receipt".into()).matches(&message, None));
+    }
+
+    #[test]
+    fn test_from_domain_matches_subdomains_only() {
+        let message = message();
+        assert!(Condition::FromDomain("example.com".into()).matches(&message, None));
+        assert!(Condition::FromDomain("@mail.example.com".into()).matches(&message, None));
+        assert!(!Condition::FromDomain("ample.com".into()).matches(&message, None));
+        assert!(!Condition::FromDomain("other.com".into()).matches(&message, None));
+    }
+
+    #[test]
+    fn test_header_condition_needs_headers() {
+        let message = message();
+        let condition = Condition::Header {
+            name: "List-Id".into(),
+            contains: "announce".into(),
+        };
+        let headers = Headers::parse("List-Id: <Announce.lists.example.com>\r\n\r\n").unwrap();
+
+        assert_eq!(condition.header_name(), Some("List-Id"));
+        assert!(condition.matches(&message, Some(&headers)));
+        assert!(!condition.matches(&message, None));
+        assert!(!condition.matches(&message, Some(&Headers::new())));
+    }
+
+    #[test]
+    fn test_filter_requires_every_condition() {
+        let message = message();
+        let mut filter = Filter::new(
+            AccountId(1),
+            "Invoices",
+            vec![
+                Condition::SubjectContains("invoice".into()),
+                Condition::FromDomain("example.com".into()),
+                Condition::HasAttachment(true),
+            ],
+            vec![Action::Move("Receipts".into())],
+        );
+        assert!(filter.matches(&message, None));
+
+        filter.conditions.push(Condition::HasAttachment(false));
+        assert!(!filter.matches(&message, None));
+    }
+
+    #[test]
+    fn test_empty_or_disabled_filter_never_matches() {
+        let message = message();
+        let empty = Filter::new(AccountId(1), "Everything", Vec::new(), vec![Action::Delete]);
+        assert!(!empty.matches(&message, None));
+
+        let mut disabled = Filter::new(
+            AccountId(1),
+            "Off",
+            vec![Condition::HasAttachment(true)],
+            vec![Action::Flag],
+        );
+        disabled.enabled = false;
+        assert!(!disabled.matches(&message, None));
+    }
+}
@@ -0,0 +1,672 @@
+//! Filter storage and execution.
+
+use std::collections::HashMap;
+
+use mailledger_imap::command::{FetchAttribute, FetchItems, StoreAction};
+use mailledger_imap::connection::{Client, Selected};
+use mailledger_imap::parser::FetchItem;
+use mailledger_imap::types::{Flag, Uid};
+use mailledger_mime::Headers;
+use sqlx::Row;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions, SqliteRow};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::model::{Action, Filter, FilterReport};
+use crate::service::mail::uid_set_from;
+use crate::service::{MailServiceError, MessageSummary, move_messages};
+use crate::triage::InboxCategory;
+use crate::{AccountId, Result};
+
+/// Repository for message filters.
+pub struct FilterRepository {
+    pool: SqlitePool,
+}
+
+impl FilterRepository {
+    /// Create a new repository with the given database path.
+    ///
+    /// Creates the database and tables if they don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    pub async fn new(database_path: &str) -> Result<Self> {
+        let url = format!("sqlite:{database_path}?mode=rwc");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect(&url)
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Create an in-memory repository for testing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    #[allow(dead_code)]
+    pub async fn in_memory() -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Initialize database schema.
+    async fn initialize(&self) -> Result<()> {
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS filters (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                conditions TEXT NOT NULL,
+                actions TEXT NOT NULL
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r"
+            CREATE INDEX IF NOT EXISTS idx_filters_account ON filters(account_id)
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS filter_categories (
+                account_id INTEGER NOT NULL,
+                message_id TEXT NOT NULL,
+                category TEXT NOT NULL,
+                PRIMARY KEY (account_id, message_id)
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Save a filter, inserting it if it has no ID. Returns the filter ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn save(&self, filter: &Filter) -> Result<i64> {
+        let conditions = serde_json::to_string(&filter.conditions)?;
+        let actions = serde_json::to_string(&filter.actions)?;
+
+        if let Some(id) = filter.id {
+            sqlx::query(
+                r"
+                UPDATE filters
+                SET name = ?, enabled = ?, conditions = ?, actions = ?
+                WHERE id = ?
+                ",
+            )
+            .bind(&filter.name)
+            .bind(filter.enabled)
+            .bind(&conditions)
+            .bind(&actions)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+            return Ok(id);
+        }
+
+        let result = sqlx::query(
+            r"
+            INSERT INTO filters (account_id, name, enabled, conditions, actions)
+            VALUES (?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(filter.account_id.0)
+        .bind(&filter.name)
+        .bind(filter.enabled)
+        .bind(&conditions)
+        .bind(&actions)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Get every filter for an account, in the order they were created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn list_for_account(&self, account_id: AccountId) -> Result<Vec<Filter>> {
+        let rows = sqlx::query("SELECT * FROM filters WHERE account_id = ? ORDER BY id ASC")
+            .bind(account_id.0)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(filter_from_row).collect()
+    }
+
+    /// Delete a filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn delete(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM filters WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the categories filters have assigned, keyed by Message-ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn categories(
+        &self,
+        account_id: AccountId,
+    ) -> Result<HashMap<String, InboxCategory>> {
+        let rows =
+            sqlx::query("SELECT message_id, category FROM filter_categories WHERE account_id = ?")
+                .bind(account_id.0)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("message_id"),
+                    InboxCategory::parse(row.get("category")),
+                )
+            })
+            .collect())
+    }
+
+    /// Run the account's enabled filters over `messages` in the selected
+    /// folder, performing their actions on the server.
+    ///
+    /// Header fields used by header conditions are fetched for every message
+    /// in one `UID FETCH`. Each message collects the actions of every filter
+    /// it matches, except that only the first move or delete applies. Flag
+    /// changes are stored first, with one `UID STORE` per flag, then deleted
+    /// messages are expunged and the rest moved, one move per destination.
+    ///
+    /// Categories are stored by Message-ID for [`categories`](Self::categories),
+    /// so messages without one are only categorized in the returned report.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the filters cannot be loaded or saved, or an IMAP
+    /// command fails.
+    pub async fn apply_filters<S>(
+        &self,
+        account_id: AccountId,
+        messages: &[MessageSummary],
+        client: &mut Client<S, Selected>,
+    ) -> std::result::Result<FilterReport, MailServiceError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let filters = self
+            .list_for_account(account_id)
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+        let report = execute(&filters, messages, client).await?;
+        self.save_categories(account_id, messages, &report.categorized)
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+        Ok(report)
+    }
+
+    /// Records the categories filters assigned to `messages`.
+    async fn save_categories(
+        &self,
+        account_id: AccountId,
+        messages: &[MessageSummary],
+        categorized: &[(Uid, InboxCategory)],
+    ) -> Result<()> {
+        for (uid, category) in categorized {
+            let Some(message_id) = messages
+                .iter()
+                .find(|message| message.uid == *uid)
+                .and_then(|message| message.message_id.as_deref())
+            else {
+                continue;
+            };
+
+            sqlx::query(
+                r"
+                INSERT INTO filter_categories (account_id, message_id, category)
+                VALUES (?, ?, ?)
+                ON CONFLICT(account_id, message_id) DO UPDATE SET
+                    category = excluded.category
+                ",
+            )
+            .bind(account_id.0)
+            .bind(message_id)
+            .bind(category.as_str())
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs `filters` over `messages`; see [`FilterRepository::apply_filters`].
+async fn execute<S>(
+    filters: &[Filter],
+    messages: &[MessageSummary],
+    client: &mut Client<S, Selected>,
+) -> std::result::Result<FilterReport, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header_names: Vec<&str> = filters
+        .iter()
+        .filter(|filter| filter.enabled)
+        .flat_map(|filter| &filter.conditions)
+        .filter_map(|condition| condition.header_name())
+        .collect();
+    header_names.sort_unstable();
+    header_names.dedup();
+
+    let headers = if header_names.is_empty() {
+        HashMap::new()
+    } else {
+        fetch_header_fields(client, messages, &header_names).await?
+    };
+
+    let mut report = FilterReport::default();
+    for message in messages {
+        let headers = headers.get(&message.uid);
+        let mut settled = false;
+        let actions = filters
+            .iter()
+            .filter(|filter| filter.matches(message, headers))
+            .flat_map(|filter| &filter.actions);
+        for action in actions {
+            let uid = message.uid;
+            match action {
+                Action::Move(folder) if !settled => {
+                    report.moved.push((uid, folder.clone()));
+                    settled = true;
+                }
+                Action::Delete if !settled => {
+                    report.deleted.push(uid);
+                    settled = true;
+                }
+                Action::Move(_) | Action::Delete => {}
+                Action::Flag => push_unique(&mut report.flagged, uid),
+                Action::MarkRead => push_unique(&mut report.marked_read, uid),
+                Action::ApplyCategory(category) => {
+                    if !report.categorized.iter().any(|(done, _)| *done == uid) {
+                        report.categorized.push((uid, *category));
+                    }
+                }
+            }
+        }
+    }
+
+    add_flag(client, &report.flagged, Flag::Flagged).await?;
+    add_flag(client, &report.marked_read, Flag::Seen).await?;
+    add_flag(client, &report.deleted, Flag::Deleted).await?;
+    if let Some(set) = uid_set_from(&report.deleted) {
+        // UID EXPUNGE leaves messages other clients marked \Deleted alone
+        if client.supports_uidplus() {
+            client.uid_expunge(&set).await
+        } else {
+            client.expunge().await
+        }
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    }
+
+    let mut moves: Vec<(&str, Vec<Uid>)> = Vec::new();
+    for (uid, folder) in &report.moved {
+        match moves.iter_mut().find(|(dest, _)| dest == folder) {
+            Some((_, uids)) => uids.push(*uid),
+            None => moves.push((folder, vec![*uid])),
+        }
+    }
+    for (folder, uids) in moves {
+        if let Some(set) = uid_set_from(&uids) {
+            move_messages(client, &set, folder, false).await?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Fetches the named header fields of every message in one `UID FETCH`.
+///
+/// Uses `BODY.PEEK`, so `\Seen` is left untouched.
+async fn fetch_header_fields<S>(
+    client: &mut Client<S, Selected>,
+    messages: &[MessageSummary],
+    names: &[&str],
+) -> std::result::Result<HashMap<Uid, Headers>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let uids: Vec<Uid> = messages.iter().map(|message| message.uid).collect();
+    let Some(set) = uid_set_from(&uids) else {
+        return Ok(HashMap::new());
+    };
+    let items = FetchItems::Items(vec![
+        FetchAttribute::Uid,
+        FetchAttribute::BodyHeaderFields(names.iter().map(ToString::to_string).collect()),
+    ]);
+    let responses = client
+        .uid_fetch(&set, items)
+        .await
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+
+    Ok(responses
+        .into_iter()
+        .filter_map(|(_, items)| {
+            let mut uid = None;
+            let mut raw = None;
+            for item in items {
+                match item {
+                    FetchItem::Uid(u) => uid = Some(u),
+                    FetchItem::Body {
+                        section: Some(section),
+                        data,
+                        ..
+                    } if section.to_ascii_uppercase().starts_with("HEADER.FIELDS") => {
+                        raw = Some(data.unwrap_or_default());
+                    }
+                    _ => {}
+                }
+            }
+            let headers = Headers::parse(&String::from_utf8_lossy(&raw?)).ok()?;
+            Some((uid?, headers))
+        })
+        .collect())
+}
+
+fn push_unique(uids: &mut Vec<Uid>, uid: Uid) {
+    if !uids.contains(&uid) {
+        uids.push(uid);
+    }
+}
+
+/// Adds `flag` to every message in `uids` with one `UID STORE`.
+async fn add_flag<S>(
+    client: &mut Client<S, Selected>,
+    uids: &[Uid],
+    flag: Flag,
+) -> std::result::Result<(), MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Some(set) = uid_set_from(uids) {
+        client
+            .uid_store(&set, StoreAction::AddFlags(vec![flag]))
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Builds a filter from a row.
+fn filter_from_row(row: &SqliteRow) -> Result<Filter> {
+    Ok(Filter {
+        id: Some(row.get("id")),
+        account_id: AccountId(row.get::<i64, _>("account_id")),
+        name: row.get("name"),
+        enabled: row.get("enabled"),
+        conditions: serde_json::from_str(row.get("conditions"))?,
+        actions: serde_json::from_str(row.get("actions"))?,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::*;
+    use crate::filters::Condition;
+    use crate::triage::InboxCategory;
+
+    fn summary(uid: u32, from_email: &str, subject: &str) -> MessageSummary {
+        MessageSummary {
+            uid: Uid::new(uid).unwrap(),
+            subject: subject.to_string(),
+            from: from_email.to_string(),
+            from_email: from_email.to_string(),
+            to: String::new(),
+            date: String::new(),
+            is_read: false,
+            is_flagged: false,
+            has_attachment: false,
+            snippet: String::new(),
+            message_id: None,
+            in_reply_to: None,
+            thread_id: None,
+            references: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Accepts every command with OK and returns the commands it received.
+    ///
+    /// `UID FETCH` is answered with a `List-Id` header for UID 3 and no
+    /// fields for UID 4.
+    async fn serve(stream: DuplexStream) -> Vec<String> {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+        write
+            .write_all(b"* OK [CAPABILITY IMAP4rev1 MOVE UIDPLUS] ready\r\n")
+            .await
+            .unwrap();
+        let mut commands = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let (tag, command) = line.split_once(' ').unwrap();
+            let untagged = if command.starts_with("SELECT") {
+                "* 5 EXISTS\r\n".to_string()
+            } else if command.starts_with("UID FETCH") {
+                let header = "List-Id: <news.example.com>\r\n\r\n";
+                format!(
+                    "* 1 FETCH (UID 3 BODY[HEADER.FIELDS (LIST-ID)] {{{}}}\r\n{header})\r\n\
+                     * 2 FETCH (UID 4 BODY[HEADER.FIELDS (LIST-ID)] {{2}}\r\n\r\n)\r\n",
+                    header.len()
+                )
+            } else {
+                String::new()
+            };
+            let reply = format!("{untagged}{tag} OK done\r\n");
+            write.write_all(reply.as_bytes()).await.unwrap();
+            commands.push(command.to_string());
+        }
+        commands
+    }
+
+    #[tokio::test]
+    async fn test_save_and_list_round_trips_rules() {
+        let repo = FilterRepository::in_memory().await.unwrap();
+        let mut filter = Filter::new(
+            AccountId(1),
+            "Newsletters",
+            vec![
+                Condition::Header {
+                    name: "List-Id".into(),
+                    contains: "news".into(),
+                },
+                Condition::HasAttachment(false),
+            ],
+            vec![
+                Action::ApplyCategory(InboxCategory::Feed),
+                Action::Move("Newsletters".into()),
+            ],
+        );
+        filter.id = Some(repo.save(&filter).await.unwrap());
+        repo.save(&Filter::new(AccountId(2), "Other", Vec::new(), Vec::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.list_for_account(AccountId(1)).await.unwrap(),
+            [filter.clone()]
+        );
+
+        filter.enabled = false;
+        repo.save(&filter).await.unwrap();
+        assert!(!repo.list_for_account(AccountId(1)).await.unwrap()[0].enabled);
+
+        repo.delete(filter.id.unwrap()).await.unwrap();
+        assert!(
+            repo.list_for_account(AccountId(1))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    /// Saves `filters` and runs them over `messages` against [`serve`].
+    async fn apply(
+        repo: &FilterRepository,
+        filters: &[Filter],
+        messages: &[MessageSummary],
+    ) -> (FilterReport, Vec<String>) {
+        for filter in filters {
+            repo.save(filter).await.unwrap();
+        }
+
+        let (local, remote) = tokio::io::duplex(4096);
+        let server = tokio::spawn(serve(remote));
+        let client = Client::from_stream(local).await.unwrap();
+        let client = client.login("user", "pass").await.unwrap();
+        let (mut client, _) = client.select("INBOX").await.unwrap();
+
+        let report = repo
+            .apply_filters(AccountId(1), messages, &mut client)
+            .await
+            .unwrap();
+        drop(client);
+
+        // Skip LOGIN and SELECT
+        let commands = server.await.unwrap().into_iter().skip(2).collect();
+        (report, commands)
+    }
+
+    #[tokio::test]
+    async fn test_move_action_issues_uid_move() {
+        let filters = [
+            Filter::new(
+                AccountId(1),
+                "Receipts",
+                vec![Condition::SubjectContains("receipt".into())],
+                vec![Action::MarkRead, Action::Move("Receipts".into())],
+            ),
+            Filter::new(
+                AccountId(1),
+                "Boss",
+                vec![Condition::FromDomain("work.example.com".into())],
+                vec![Action::Flag, Action::Move("Work".into())],
+            ),
+        ];
+        let messages = [
+            summary(3, "shop@store.example.com", "Your receipt"),
+            summary(4, "boss@work.example.com", "Status?"),
+            summary(5, "shop@store.example.com", "Another Receipt"),
+            summary(6, "friend@example.com", "Lunch"),
+            summary(7, "boss@work.example.com", "Expense receipt"),
+        ];
+
+        let repo = FilterRepository::in_memory().await.unwrap();
+        let (report, commands) = apply(&repo, &filters, &messages).await;
+
+        let uid = |n| Uid::new(n).unwrap();
+        assert_eq!(
+            report.moved,
+            [
+                (uid(3), "Receipts".to_string()),
+                (uid(4), "Work".to_string()),
+                (uid(5), "Receipts".to_string()),
+                (uid(7), "Receipts".to_string()),
+            ]
+        );
+        assert_eq!(report.flagged, [uid(4), uid(7)]);
+        assert_eq!(report.marked_read, [uid(3), uid(5), uid(7)]);
+        assert!(report.removed(uid(4)));
+        assert!(!report.removed(uid(6)));
+
+        assert_eq!(
+            commands,
+            [
+                "UID STORE 4,7 +FLAGS (\\Flagged)",
+                "UID STORE 3,5,7 +FLAGS (\\Seen)",
+                "UID MOVE 3,5,7 Receipts",
+                "UID MOVE 4 Work",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_headers_are_fetched_once_and_deletes_expunged() {
+        let filters = [
+            Filter::new(
+                AccountId(1),
+                "Lists",
+                vec![Condition::Header {
+                    name: "List-Id".into(),
+                    contains: "news".into(),
+                }],
+                vec![Action::ApplyCategory(InboxCategory::Feed)],
+            ),
+            Filter::new(
+                AccountId(1),
+                "Spam",
+                vec![Condition::SubjectContains("winner".into())],
+                vec![Action::Delete],
+            ),
+        ];
+        let mut newsletter = summary(3, "news@example.com", "This week");
+        newsletter.message_id = Some("<issue-12@example.com>".into());
+        let messages = [
+            newsletter,
+            summary(4, "prize@example.net", "You are a winner"),
+        ];
+
+        let repo = FilterRepository::in_memory().await.unwrap();
+        let (report, commands) = apply(&repo, &filters, &messages).await;
+
+        let uid = |n| Uid::new(n).unwrap();
+        assert_eq!(report.categorized, [(uid(3), InboxCategory::Feed)]);
+        assert_eq!(report.deleted, [uid(4)]);
+
+        // One header fetch covers both messages
+        assert_eq!(commands.len(), 3);
+        assert!(commands[0].starts_with("UID FETCH 3:4 "));
+        assert!(commands[0].contains("HEADER.FIELDS"));
+        assert_eq!(
+            commands[1..],
+            ["UID STORE 4 +FLAGS (\\Deleted)", "UID EXPUNGE 4"]
+        );
+
+        let categories = repo.categories(AccountId(1)).await.unwrap();
+        assert_eq!(
+            categories.get("<issue-12@example.com>"),
+            Some(&InboxCategory::Feed)
+        );
+        assert_eq!(categories.len(), 1);
+    }
+}
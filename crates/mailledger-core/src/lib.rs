@@ -12,6 +12,8 @@
 //! - **Contact Management** - Address book and autocomplete
 //! - **Snooze/Reminders** - Snooze messages to reappear later
 //! - **Offline Cache** - Message caching for offline viewing
+//! - **Outbox** - Queued sending with retry while offline
+//! - **Filters** - Rules that move, flag, or categorize matching messages
 
 #![warn(missing_docs)]
 #![warn(clippy::all)]
@@ -22,23 +24,47 @@ pub mod account;
 pub mod cache;
 pub mod contacts;
 mod error;
+pub mod filters;
+pub mod outbox;
+pub mod receipts;
+pub mod remote_content;
 pub mod service;
 pub mod snooze;
+pub mod threading;
 pub mod triage;
 
 pub use account::credentials;
-pub use account::{Account, AccountId, AccountRepository, ImapConfig, Security, SmtpConfig};
 pub use account::{
-    CredentialError, CredentialResult, ValidationError, ValidationResult, validate_account,
+    Account, AccountId, AccountRepository, AuthMethod, ImapConfig, OAuthProvider, Security,
+    SmtpConfig,
 };
-pub use cache::{CacheRepository, CachedMessageContent, CachedMessageSummary};
+pub use account::{
+    CredentialError, CredentialResult, DiscoveredConfig, DiscoveryError,
+    EncryptedFileCredentialStore, ValidationError, ValidationResult, discover, validate_account,
+};
+pub use cache::{CacheRepository, CachedMessageContent, CachedMessageSummary, FolderSyncState};
 pub use contacts::{Contact, ContactRepository};
 pub use error::{Error, Result};
+pub use filters::{Action, Condition, Filter, FilterReport, FilterRepository};
+pub use outbox::{MAX_SEND_ATTEMPTS, OutboxEntry, OutboxRepository, OutboxStatus};
+pub use receipts::ReceiptRepository;
+pub use remote_content::{RemotePolicy, TrustedSenderRepository};
 pub use service::{
-    Attachment, AuthClient, Folder, FolderType, IdleEvent, MailServiceError, MessageContent,
-    MessageSummary, OutgoingMessage, SearchCriteria, SelectedClient, SmtpError, archive_message,
-    connect_and_login, download_attachment, fetch_message_content, fetch_messages, idle_monitor,
-    list_folders, mark_read, mark_unread, search_messages, select_folder, send_email, toggle_flag,
+    Attachment, AuthClient, ConnectionPool, ConnectionProbe, Connector, DeviceSignIn, ExpungeGuard,
+    ExpungePreview, ExpungeScope, ExpungeToken, FlushSummary, Folder, FolderChanges, FolderCounts,
+    FolderType, IdleEvent, ImapConnector, MailServiceError, MessageContent, MessageSummary,
+    OAuthMechanism, OutgoingMessage, PreferredBody, Retriable, RetryPolicy, SearchCriteria,
+    SelectedClient, SmtpError, SmtpSession, SyncResult, UnsubscribeInfo, archive_many,
+    archive_message, connect_and_login, current_uid, delete_many, delete_permanently,
+    download_attachment, fetch_batched, fetch_body_part, fetch_changes, fetch_flag_updates,
+    fetch_message_content, fetch_messages, fetch_messages_by_uid, fetch_messages_stream,
+    fetch_new_since, fetch_raw_message, finish_device_sign_in, flush_outbox, folder_counts,
+    fresh_token, idle_monitor, idle_subscription, imap_security, list_folders, mark_read,
+    mark_read_many, mark_unread, move_messages, probe_connection, read_receipt_matches_return_path,
+    resolve_uid_by_message_id, save_draft, search_messages, search_messages_matching,
+    search_offline, select_folder, select_folder_read_only, send_batch, send_email,
+    send_read_receipt, start_device_sign_in, sync_folder, toggle_flag, unsubscribe, with_backoff,
 };
 pub use snooze::{SnoozeDuration, SnoozeRepository, SnoozedMessage};
-pub use triage::{InboxCategory, ScreenedSender, SenderDecision, TriageRepository};
+pub use threading::{ThreadNode, build_threads};
+pub use triage::{InboxCategory, ScreenedSender, SenderDecision, TriageRepository, TriagedView};
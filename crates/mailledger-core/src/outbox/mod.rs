@@ -0,0 +1,10 @@
+//! Outbox for messages that could not be sent right away.
+//!
+//! Messages composed while offline, or whose send failed transiently, are
+//! persisted here and delivered later by [`flush_outbox`](crate::flush_outbox).
+
+mod model;
+mod repository;
+
+pub use model::{MAX_SEND_ATTEMPTS, OutboxEntry, OutboxStatus};
+pub use repository::OutboxRepository;
@@ -0,0 +1,78 @@
+//! Outbox data models.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::AccountId;
+use crate::service::OutgoingMessage;
+
+/// Number of delivery attempts before a message is marked failed.
+pub const MAX_SEND_ATTEMPTS: u32 = 5;
+
+/// Delivery state of an outbox entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxStatus {
+    /// Waiting for its next delivery attempt.
+    Queued,
+    /// A delivery attempt is in progress.
+    Sending,
+    /// Delivered to the SMTP server.
+    Sent,
+    /// Given up on; needs user attention.
+    Failed,
+}
+
+impl OutboxStatus {
+    /// Returns the database representation.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Sending => "sending",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Parses the database representation.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queued" => Some(Self::Queued),
+            "sending" => Some(Self::Sending),
+            "sent" => Some(Self::Sent),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A message waiting in the outbox.
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /// Database ID.
+    pub id: i64,
+    /// Account the message is sent from.
+    pub account_id: AccountId,
+    /// The message itself.
+    pub message: OutgoingMessage,
+    /// Delivery state.
+    pub status: OutboxStatus,
+    /// Delivery attempts made so far.
+    pub attempts: u32,
+    /// Error from the most recent failed attempt.
+    pub last_error: Option<String>,
+    /// Earliest time of the next attempt.
+    pub next_attempt_at: DateTime<Utc>,
+    /// When the message was queued.
+    pub queued_at: DateTime<Utc>,
+}
+
+impl OutboxEntry {
+    /// Delay before retrying after `attempts` failed attempts.
+    ///
+    /// Doubles from one minute, capped at one hour.
+    #[must_use]
+    pub fn retry_delay(attempts: u32) -> Duration {
+        Duration::minutes(1_i64 << attempts.saturating_sub(1).min(6)).min(Duration::hours(1))
+    }
+}
@@ -0,0 +1,384 @@
+//! Outbox storage repository.
+
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions, SqliteRow};
+
+use super::model::{OutboxEntry, OutboxStatus};
+use crate::service::OutgoingMessage;
+use crate::{AccountId, Result};
+
+/// Minutes an entry may stay [`OutboxStatus::Sending`] before it is taken
+/// to be lost, e.g. to a crash mid-send, and becomes due again.
+const SENDING_TIMEOUT_MINUTES: i64 = 15;
+
+/// Repository for messages waiting to be sent.
+pub struct OutboxRepository {
+    pool: SqlitePool,
+}
+
+impl OutboxRepository {
+    /// Create a new repository with the given database path.
+    ///
+    /// Creates the database and tables if they don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    pub async fn new(database_path: &str) -> Result<Self> {
+        let url = format!("sqlite:{database_path}?mode=rwc");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect(&url)
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Create an in-memory repository for testing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    #[allow(dead_code)]
+    pub async fn in_memory() -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Initialize database schema.
+    async fn initialize(&self) -> Result<()> {
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS outbox (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                from_address TEXT NOT NULL,
+                to_addresses TEXT NOT NULL DEFAULT '',
+                cc_addresses TEXT NOT NULL DEFAULT '',
+                bcc_addresses TEXT NOT NULL DEFAULT '',
+                subject TEXT NOT NULL DEFAULT '',
+                body TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                next_attempt_at TEXT NOT NULL,
+                queued_at TEXT NOT NULL
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r"
+            CREATE INDEX IF NOT EXISTS idx_outbox_due ON outbox(account_id, status, next_attempt_at)
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Queue a message for sending. Returns the entry ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn enqueue(&self, account_id: AccountId, message: &OutgoingMessage) -> Result<i64> {
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r"
+            INSERT INTO outbox
+                (account_id, from_address, to_addresses, cc_addresses, bcc_addresses,
+                 subject, body, status, next_attempt_at, queued_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(account_id.0)
+        .bind(&message.from)
+        .bind(message.to.join("\n"))
+        .bind(message.cc.join("\n"))
+        .bind(message.bcc.join("\n"))
+        .bind(&message.subject)
+        .bind(&message.body)
+        .bind(OutboxStatus::Queued.as_str())
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Get queued messages whose next attempt is due, oldest first.
+    ///
+    /// Entries left sending for longer than the send timeout are included,
+    /// so a message is not stuck if the app quit while sending it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn due(&self, account_id: AccountId) -> Result<Vec<OutboxEntry>> {
+        let rows = sqlx::query(
+            r"
+            SELECT * FROM outbox
+            WHERE account_id = ? AND status IN (?, ?) AND next_attempt_at <= ?
+            ORDER BY queued_at ASC, id ASC
+            ",
+        )
+        .bind(account_id.0)
+        .bind(OutboxStatus::Queued.as_str())
+        .bind(OutboxStatus::Sending.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().filter_map(entry_from_row).collect())
+    }
+
+    /// Get every outbox entry for an account, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn list_for_account(&self, account_id: AccountId) -> Result<Vec<OutboxEntry>> {
+        let rows = sqlx::query(
+            r"
+            SELECT * FROM outbox
+            WHERE account_id = ?
+            ORDER BY queued_at ASC, id ASC
+            ",
+        )
+        .bind(account_id.0)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().filter_map(entry_from_row).collect())
+    }
+
+    /// Mark an entry as being sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn mark_sending(&self, id: i64) -> Result<()> {
+        let timeout = Utc::now() + chrono::Duration::minutes(SENDING_TIMEOUT_MINUTES);
+        sqlx::query("UPDATE outbox SET status = ?, next_attempt_at = ? WHERE id = ?")
+            .bind(OutboxStatus::Sending.as_str())
+            .bind(timeout.to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark an entry as delivered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn mark_sent(&self, id: i64) -> Result<()> {
+        self.set_status(id, OutboxStatus::Sent).await
+    }
+
+    /// Record a failed attempt and queue the entry again at `next_attempt_at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn schedule_retry(
+        &self,
+        id: i64,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r"
+            UPDATE outbox
+            SET status = ?, attempts = attempts + 1, last_error = ?, next_attempt_at = ?
+            WHERE id = ?
+            ",
+        )
+        .bind(OutboxStatus::Queued.as_str())
+        .bind(error)
+        .bind(next_attempt_at.to_rfc3339())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt and stop retrying the entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn mark_failed(&self, id: i64, error: &str) -> Result<()> {
+        sqlx::query(
+            r"
+            UPDATE outbox
+            SET status = ?, attempts = attempts + 1, last_error = ?
+            WHERE id = ?
+            ",
+        )
+        .bind(OutboxStatus::Failed.as_str())
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove an entry, e.g. after the user discards a failed message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn remove(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM outbox WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_status(&self, id: i64, status: OutboxStatus) -> Result<()> {
+        sqlx::query("UPDATE outbox SET status = ? WHERE id = ?")
+            .bind(status.as_str())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Splits a newline-joined address column.
+fn addresses(joined: &str) -> Vec<String> {
+    joined
+        .split('\n')
+        .filter(|addr| !addr.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Builds an entry from a row, skipping rows with unparsable fields.
+fn entry_from_row(row: &SqliteRow) -> Option<OutboxEntry> {
+    let parse_time = |column: &str| {
+        DateTime::parse_from_rfc3339(&row.get::<String, _>(column))
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    };
+
+    let mut message = OutgoingMessage::new(
+        row.get::<String, _>("from_address"),
+        row.get::<String, _>("subject"),
+        row.get::<String, _>("body"),
+    );
+    message.to = addresses(row.get("to_addresses"));
+    message.cc = addresses(row.get("cc_addresses"));
+    message.bcc = addresses(row.get("bcc_addresses"));
+
+    Some(OutboxEntry {
+        id: row.get("id"),
+        account_id: AccountId(row.get::<i64, _>("account_id")),
+        message,
+        status: OutboxStatus::parse(row.get("status"))?,
+        attempts: row.get::<u32, _>("attempts"),
+        last_error: row.get("last_error"),
+        next_attempt_at: parse_time("next_attempt_at")?,
+        queued_at: parse_time("queued_at")?,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn message() -> OutgoingMessage {
+        OutgoingMessage::new("me@example.com", "Hello", "body")
+            .to("a@example.com")
+            .to("b@example.com")
+            .bcc("c@example.com")
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_and_list_due() {
+        let repo = OutboxRepository::in_memory().await.unwrap();
+
+        let id = repo.enqueue(AccountId(1), &message()).await.unwrap();
+        repo.enqueue(AccountId(2), &message()).await.unwrap();
+
+        let due = repo.due(AccountId(1)).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, id);
+        assert_eq!(due[0].status, OutboxStatus::Queued);
+        assert_eq!(due[0].attempts, 0);
+        assert_eq!(due[0].message.to, ["a@example.com", "b@example.com"]);
+        assert!(due[0].message.cc.is_empty());
+        assert_eq!(due[0].message.bcc, ["c@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_waits_for_backoff() {
+        let repo = OutboxRepository::in_memory().await.unwrap();
+        let id = repo.enqueue(AccountId(1), &message()).await.unwrap();
+
+        repo.mark_sending(id).await.unwrap();
+        assert!(repo.due(AccountId(1)).await.unwrap().is_empty());
+
+        repo.schedule_retry(id, "timeout", Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+        assert!(repo.due(AccountId(1)).await.unwrap().is_empty());
+
+        let entries = repo.list_for_account(AccountId(1)).await.unwrap();
+        assert_eq!(entries[0].status, OutboxStatus::Queued);
+        assert_eq!(entries[0].attempts, 1);
+        assert_eq!(entries[0].last_error.as_deref(), Some("timeout"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_sending_entry_is_due_again() {
+        let repo = OutboxRepository::in_memory().await.unwrap();
+        let id = repo.enqueue(AccountId(1), &message()).await.unwrap();
+
+        repo.mark_sending(id).await.unwrap();
+        assert!(repo.due(AccountId(1)).await.unwrap().is_empty());
+
+        // As if the app quit mid-send long ago
+        let expired = Utc::now() - Duration::minutes(1);
+        sqlx::query("UPDATE outbox SET next_attempt_at = ? WHERE id = ?")
+            .bind(expired.to_rfc3339())
+            .bind(id)
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+        let due = repo.due(AccountId(1)).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].status, OutboxStatus::Sending);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_an_hour() {
+        assert_eq!(OutboxEntry::retry_delay(1), Duration::minutes(1));
+        assert_eq!(OutboxEntry::retry_delay(3), Duration::minutes(4));
+        assert_eq!(OutboxEntry::retry_delay(20), Duration::hours(1));
+    }
+}
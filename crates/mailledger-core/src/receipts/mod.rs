@@ -0,0 +1,8 @@
+//! Read receipts received for sent messages.
+//!
+//! Incoming disposition notifications (RFC 8098) are recorded against the
+//! Message-ID they report on, so sent mail can show whether it was read.
+
+mod repository;
+
+pub use repository::ReceiptRepository;
@@ -0,0 +1,215 @@
+//! Read receipt storage repository.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use mailledger_mime::{MdnDisposition, MdnReport};
+use sqlx::Row;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+use crate::{AccountId, Result};
+
+/// Repository for received read receipts.
+pub struct ReceiptRepository {
+    pool: SqlitePool,
+}
+
+impl ReceiptRepository {
+    /// Create a new repository with the given database path.
+    ///
+    /// Creates the database and tables if they don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    pub async fn new(database_path: &str) -> Result<Self> {
+        let url = format!("sqlite:{database_path}?mode=rwc");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect(&url)
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Create an in-memory repository for testing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    #[allow(dead_code)]
+    pub async fn in_memory() -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Initialize database schema.
+    async fn initialize(&self) -> Result<()> {
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS read_receipts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                account_id INTEGER NOT NULL,
+                message_id TEXT NOT NULL,
+                recipient TEXT NOT NULL DEFAULT '',
+                disposition TEXT NOT NULL,
+                received_at TEXT NOT NULL,
+                UNIQUE(account_id, message_id, recipient)
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a received receipt.
+    ///
+    /// Returns `false` without storing anything if the receipt doesn't name
+    /// the message it is about. A later receipt from the same recipient
+    /// replaces the earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn record(&self, account_id: AccountId, report: &MdnReport) -> Result<bool> {
+        let Some(message_id) = &report.original_message_id else {
+            return Ok(false);
+        };
+
+        sqlx::query(
+            r"
+            INSERT INTO read_receipts (account_id, message_id, recipient, disposition, received_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(account_id, message_id, recipient) DO UPDATE SET
+                disposition = excluded.disposition,
+                received_at = excluded.received_at
+            ",
+        )
+        .bind(account_id.0)
+        .bind(message_id)
+        .bind(report.final_recipient.as_deref().unwrap_or_default())
+        .bind(report.disposition.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Get the reported disposition of each sent message, keyed by
+    /// Message-ID.
+    ///
+    /// When several recipients answered, `displayed` wins over the other
+    /// dispositions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn dispositions(
+        &self,
+        account_id: AccountId,
+    ) -> Result<HashMap<String, MdnDisposition>> {
+        let rows = sqlx::query(
+            r"
+            SELECT message_id, disposition
+            FROM read_receipts
+            WHERE account_id = ?
+            ORDER BY received_at ASC
+            ",
+        )
+        .bind(account_id.0)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut dispositions = HashMap::new();
+        for row in &rows {
+            let Some(disposition) = MdnDisposition::parse(row.get("disposition")) else {
+                continue;
+            };
+            dispositions
+                .entry(row.get("message_id"))
+                .and_modify(|existing| {
+                    if *existing != MdnDisposition::Displayed {
+                        *existing = disposition;
+                    }
+                })
+                .or_insert(disposition);
+        }
+
+        Ok(dispositions)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use mailledger_mime::MdnMode;
+
+    use super::*;
+
+    fn report(message_id: Option<&str>, recipient: &str, disposition: MdnDisposition) -> MdnReport {
+        MdnReport {
+            original_message_id: message_id.map(ToString::to_string),
+            final_recipient: Some(recipient.to_string()),
+            disposition,
+            mode: MdnMode::Manual,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_lookup() {
+        let repo = ReceiptRepository::in_memory().await.unwrap();
+
+        let read = report(
+            Some("<a@example.com>"),
+            "bob@example.com",
+            MdnDisposition::Displayed,
+        );
+        assert!(repo.record(AccountId(1), &read).await.unwrap());
+        let anonymous = report(None, "bob@example.com", MdnDisposition::Displayed);
+        assert!(!repo.record(AccountId(1), &anonymous).await.unwrap());
+
+        let dispositions = repo.dispositions(AccountId(1)).await.unwrap();
+        assert_eq!(dispositions.len(), 1);
+        assert_eq!(
+            dispositions.get("<a@example.com>"),
+            Some(&MdnDisposition::Displayed)
+        );
+        assert!(repo.dispositions(AccountId(2)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_displayed_wins_across_recipients() {
+        let repo = ReceiptRepository::in_memory().await.unwrap();
+        let id = Some("<b@example.com>");
+
+        repo.record(
+            AccountId(1),
+            &report(id, "bob@example.com", MdnDisposition::Displayed),
+        )
+        .await
+        .unwrap();
+        repo.record(
+            AccountId(1),
+            &report(id, "carol@example.com", MdnDisposition::Deleted),
+        )
+        .await
+        .unwrap();
+
+        let dispositions = repo.dispositions(AccountId(1)).await.unwrap();
+        assert_eq!(
+            dispositions.get("<b@example.com>"),
+            Some(&MdnDisposition::Displayed)
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! Remote content in HTML messages.
+//!
+//! Images hosted outside a message let the sender see when and where it
+//! was opened. [`RemotePolicy`] decides whether they load automatically,
+//! and senders the user trusts are stored in a [`TrustedSenderRepository`].
+
+mod model;
+mod repository;
+
+pub use model::{RemotePolicy, sender_address};
+pub use repository::TrustedSenderRepository;
@@ -0,0 +1,125 @@
+//! Remote content policy.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+/// When images and other remote content in a message load without asking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemotePolicy {
+    /// Never load remote content automatically.
+    Never,
+    /// Always load remote content.
+    Always,
+    /// Load remote content only in messages from trusted senders.
+    #[default]
+    TrustedSenders,
+}
+
+impl RemotePolicy {
+    /// Whether remote content in a message from `sender` may load
+    /// automatically.
+    ///
+    /// `sender` is either a bare address or `Name <address>`, and
+    /// `trusted_senders` holds lowercase addresses.
+    #[must_use]
+    pub fn allows(self, sender: &str, trusted_senders: &HashSet<String>) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Always => true,
+            Self::TrustedSenders => {
+                sender_address(sender).is_some_and(|address| trusted_senders.contains(&address))
+            }
+        }
+    }
+
+    /// Label shown in settings.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Never => "Never",
+            Self::Always => "Always",
+            Self::TrustedSenders => "Trusted senders",
+        }
+    }
+}
+
+/// Returns the lowercase address in `sender`, which is either a bare
+/// address or `Name <address>`.
+///
+/// Returns `None` if there is no address.
+#[must_use]
+pub fn sender_address(sender: &str) -> Option<String> {
+    let address = match (sender.rfind('<'), sender.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &sender[start + 1..end],
+        _ => sender,
+    }
+    .trim();
+
+    if address.contains('@') {
+        Some(address.to_lowercase())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn trusted(addresses: &[&str]) -> HashSet<String> {
+        addresses.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_sender_address() {
+        assert_eq!(
+            sender_address("Alice <Alice@Example.com>").as_deref(),
+            Some("alice@example.com")
+        );
+        assert_eq!(
+            sender_address(" bob@example.com ").as_deref(),
+            Some("bob@example.com")
+        );
+        assert_eq!(sender_address("Unknown"), None);
+        assert_eq!(sender_address(""), None);
+    }
+
+    #[test]
+    fn test_never_blocks_trusted_senders() {
+        let trusted = trusted(&["alice@example.com"]);
+        assert!(!RemotePolicy::Never.allows("alice@example.com", &trusted));
+    }
+
+    #[test]
+    fn test_always_allows_unknown_senders() {
+        assert!(RemotePolicy::Always.allows("stranger@example.com", &HashSet::new()));
+        assert!(RemotePolicy::Always.allows("", &HashSet::new()));
+    }
+
+    #[test]
+    fn test_trusted_senders_policy() {
+        let trusted = trusted(&["alice@example.com"]);
+        let policy = RemotePolicy::TrustedSenders;
+
+        assert!(policy.allows("Alice <ALICE@example.com>", &trusted));
+        assert!(policy.allows("alice@example.com", &trusted));
+        assert!(!policy.allows("Mallory <mallory@example.com>", &trusted));
+        assert!(!policy.allows("Unknown", &trusted));
+    }
+
+    #[test]
+    fn test_policy_serde() {
+        assert_eq!(
+            serde_json::to_string(&RemotePolicy::TrustedSenders).unwrap(),
+            "\"trusted_senders\""
+        );
+        assert_eq!(
+            serde_json::from_str::<RemotePolicy>("\"never\"").unwrap(),
+            RemotePolicy::Never
+        );
+        assert_eq!(RemotePolicy::default(), RemotePolicy::TrustedSenders);
+    }
+}
@@ -0,0 +1,155 @@
+//! Trusted sender storage repository.
+
+use std::collections::HashSet;
+
+use chrono::Utc;
+use sqlx::Row;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+use super::model::sender_address;
+use crate::Result;
+
+/// Repository for senders whose remote content loads automatically.
+pub struct TrustedSenderRepository {
+    pool: SqlitePool,
+}
+
+impl TrustedSenderRepository {
+    /// Create a new repository with the given database path.
+    ///
+    /// Creates the database and tables if they don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    pub async fn new(database_path: &str) -> Result<Self> {
+        let url = format!("sqlite:{database_path}?mode=rwc");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect(&url)
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Create an in-memory repository for testing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database connection fails or schema creation fails.
+    #[allow(dead_code)]
+    pub async fn in_memory() -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+
+        let repo = Self { pool };
+        repo.initialize().await?;
+        Ok(repo)
+    }
+
+    /// Initialize database schema.
+    async fn initialize(&self) -> Result<()> {
+        sqlx::query(
+            r"
+            CREATE TABLE IF NOT EXISTS trusted_senders (
+                address TEXT PRIMARY KEY,
+                trusted_at TEXT NOT NULL
+            )
+            ",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Trust a sender, given as a bare address or `Name <address>`.
+    ///
+    /// Returns the stored lowercase address, or `None` if `sender` has no
+    /// address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn trust(&self, sender: &str) -> Result<Option<String>> {
+        let Some(address) = sender_address(sender) else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r"
+            INSERT INTO trusted_senders (address, trusted_at)
+            VALUES (?, ?)
+            ON CONFLICT(address) DO NOTHING
+            ",
+        )
+        .bind(&address)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(Some(address))
+    }
+
+    /// Stop trusting a sender.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn untrust(&self, sender: &str) -> Result<()> {
+        let Some(address) = sender_address(sender) else {
+            return Ok(());
+        };
+
+        sqlx::query("DELETE FROM trusted_senders WHERE address = ?")
+            .bind(address)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the addresses of all trusted senders.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn list(&self) -> Result<HashSet<String>> {
+        let rows = sqlx::query("SELECT address FROM trusted_senders")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get("address")).collect())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::remote_content::RemotePolicy;
+
+    #[tokio::test]
+    async fn test_trust_and_untrust() {
+        let repo = TrustedSenderRepository::in_memory().await.unwrap();
+
+        assert_eq!(
+            repo.trust("Alice <Alice@Example.com>").await.unwrap(),
+            Some("alice@example.com".to_string())
+        );
+        // Trusting twice keeps a single entry
+        repo.trust("alice@example.com").await.unwrap();
+        assert_eq!(repo.trust("Unknown").await.unwrap(), None);
+
+        let trusted = repo.list().await.unwrap();
+        assert_eq!(trusted.len(), 1);
+        assert!(RemotePolicy::TrustedSenders.allows("alice@example.com", &trusted));
+
+        repo.untrust("ALICE@example.com").await.unwrap();
+        assert!(repo.list().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Server-side drafts.
+//!
+//! Drafts are stored in the account's Drafts folder with `APPEND` and the
+//! `\Draft` flag, so they survive closing the composer and show up in other
+//! clients.
+
+use mailledger_imap::command::StoreAction;
+use mailledger_imap::connection::{Authenticated, Client};
+use mailledger_imap::types::{Flag, Uid, UidSet};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::account::Account;
+
+use super::mail::{FolderType, MailServiceError, connect_and_login, list_folders};
+use super::smtp::OutgoingMessage;
+
+/// Save `message` to the account's Drafts folder and return its new UID.
+///
+/// Pass the UID returned by the previous save of the same draft as
+/// `replaces`; that copy is removed once the new one is stored.
+///
+/// With `create_if_missing`, an account without a Drafts folder gets one
+/// named `Drafts`, created when the server answers the APPEND with
+/// `[TRYCREATE]`.
+///
+/// # Errors
+///
+/// Returns an error if the account has no Drafts folder and
+/// `create_if_missing` is off, or any IMAP command fails.
+pub async fn save_draft(
+    account: &Account,
+    message: &OutgoingMessage,
+    replaces: Option<Uid>,
+    create_if_missing: bool,
+) -> Result<Uid, MailServiceError> {
+    let mut client = connect_and_login(account).await?;
+    let drafts = list_folders(&mut client)
+        .await?
+        .into_iter()
+        .find(|folder| folder.folder_type == FolderType::Drafts)
+        .map(|folder| folder.path)
+        .or_else(|| create_if_missing.then(|| "Drafts".to_string()))
+        .ok_or_else(|| MailServiceError::Operation("No Drafts folder found".to_string()))?;
+    store_draft(client, &drafts, message, replaces, create_if_missing).await
+}
+
+/// Append `message` to `folder` and expunge the copy it replaces.
+///
+/// Without UIDPLUS the new UID is found by searching for the draft's
+/// Message-ID.
+async fn store_draft<S>(
+    mut client: Client<S, Authenticated>,
+    folder: &str,
+    message: &OutgoingMessage,
+    replaces: Option<Uid>,
+    create_if_missing: bool,
+) -> Result<Uid, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let draft = message.to_draft();
+    let raw = draft.to_string();
+    let flags = Some(vec![Flag::Draft, Flag::Seen]);
+    let appended = if create_if_missing {
+        client.append_creating(folder, flags, raw.as_bytes()).await
+    } else {
+        client.append(folder, flags, raw.as_bytes()).await
+    }
+    .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    let appended_uid = appended.and_then(|append_uid| match append_uid.uids {
+        UidSet::Single(uid) => Some(uid),
+        _ => None,
+    });
+    if let (Some(uid), None) = (appended_uid, replaces) {
+        let _ = client.logout().await;
+        return Ok(uid);
+    }
+
+    let (mut client, _status) = client
+        .select(folder)
+        .await
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+
+    let uid = if let Some(uid) = appended_uid {
+        uid
+    } else {
+        let message_id = draft.message_id().unwrap_or_default();
+        client
+            .uid_search(&format!("HEADER Message-ID \"{message_id}\""))
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?
+            .into_iter()
+            .max()
+            .ok_or_else(|| {
+                MailServiceError::Operation("Saved draft not found in Drafts".to_string())
+            })?
+    };
+
+    if let Some(old) = replaces.filter(|old| *old != uid) {
+        let old = UidSet::single(old);
+        client
+            .uid_store(&old, StoreAction::AddFlags(vec![Flag::Deleted]))
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+        // UID EXPUNGE leaves messages other clients marked \Deleted alone
+        if client.supports_uidplus() {
+            client.uid_expunge(&old).await
+        } else {
+            client.expunge().await
+        }
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    }
+
+    let _ = client.logout().await;
+    Ok(uid)
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::*;
+
+    /// Serves one session and returns the commands and appended messages it
+    /// received.
+    async fn serve(stream: DuplexStream, capabilities: &str) -> (Vec<String>, Vec<String>) {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read);
+        let greeting = format!("* OK [CAPABILITY IMAP4rev1 {capabilities}] ready\r\n");
+        write.write_all(greeting.as_bytes()).await.unwrap();
+        let (mut commands, mut appended) = (Vec::new(), Vec::new());
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            let (tag, command) = line.trim_end().split_once(' ').unwrap();
+            let tag = tag.to_string();
+            let reply = if command.starts_with("APPEND") {
+                let size = command.rsplit_once('{').unwrap().1.trim_end_matches('}');
+                write.write_all(b"+ go ahead\r\n").await.unwrap();
+                let mut literal = vec![0; size.parse::<usize>().unwrap() + 2];
+                reader.read_exact(&mut literal).await.unwrap();
+                appended.push(String::from_utf8(literal).unwrap());
+                if capabilities.contains("UIDPLUS") {
+                    format!("{tag} OK [APPENDUID 9 12] done\r\n")
+                } else {
+                    format!("{tag} OK done\r\n")
+                }
+            } else if command.starts_with("SELECT") {
+                format!("* 2 EXISTS\r\n{tag} OK [READ-WRITE] done\r\n")
+            } else if command.starts_with("UID SEARCH") {
+                format!("* SEARCH 12\r\n{tag} OK done\r\n")
+            } else {
+                format!("{tag} OK done\r\n")
+            };
+            commands.push(command.to_string());
+            write.write_all(reply.as_bytes()).await.unwrap();
+            line.clear();
+        }
+        (commands, appended)
+    }
+
+    async fn save_with(
+        capabilities: &'static str,
+        replaces: Option<u32>,
+    ) -> (Uid, Vec<String>, Vec<String>) {
+        let (local, remote) = tokio::io::duplex(8192);
+        let server = tokio::spawn(serve(remote, capabilities));
+        let client = Client::from_stream(local).await.unwrap();
+        let client = client.login("user", "pass").await.unwrap();
+
+        let message = OutgoingMessage::new("me@example.com", "Plans", "line one\nline two")
+            .to("you@example.com")
+            .bcc("boss@example.com");
+        let uid = store_draft(
+            client,
+            "Drafts",
+            &message,
+            replaces.and_then(Uid::new),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let (commands, appended) = server.await.unwrap();
+        (uid, commands.into_iter().skip(1).collect(), appended)
+    }
+
+    #[tokio::test]
+    async fn appends_draft_and_expunges_replaced_copy() {
+        let (uid, commands, appended) = save_with("UIDPLUS", Some(11)).await;
+
+        assert_eq!(uid, Uid::new(12).unwrap());
+        assert!(commands[0].starts_with("APPEND Drafts (\\Draft \\Seen) {"));
+        assert_eq!(
+            commands[1..],
+            [
+                "SELECT Drafts",
+                "UID STORE 11 +FLAGS (\\Deleted)",
+                "UID EXPUNGE 11",
+                "LOGOUT",
+            ]
+        );
+
+        let draft = mailledger_mime::Message::parse(&appended[0]).unwrap();
+        assert_eq!(draft.headers.get("Bcc"), Some("boss@example.com"));
+        assert_eq!(draft.subject().as_deref(), Some("Plans"));
+        assert!(draft.message_id().is_some());
+        assert!(appended[0].ends_with("\r\n\r\nline one\r\nline two\r\n"));
+    }
+
+    #[tokio::test]
+    async fn finds_draft_by_message_id_without_uidplus() {
+        let (uid, commands, appended) = save_with("LITERAL+", None).await;
+
+        assert_eq!(uid, Uid::new(12).unwrap());
+        let draft = mailledger_mime::Message::parse(&appended[0]).unwrap();
+        let message_id = draft.message_id().unwrap();
+        assert_eq!(
+            commands[1..],
+            [
+                "SELECT Drafts".to_string(),
+                format!("UID SEARCH HEADER Message-ID \"{message_id}\""),
+                "LOGOUT".to_string(),
+            ]
+        );
+    }
+}
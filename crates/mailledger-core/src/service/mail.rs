@@ -3,12 +3,32 @@
 //! Provides high-level email operations like fetching folders,
 //! messages, and managing mail state.
 
-use mailledger_imap::command::{FetchAttribute, FetchItems, StoreAction};
-use mailledger_imap::connection::{Client, ImapStream, connect_tls};
-use mailledger_imap::parser::{Address, FetchItem};
-use mailledger_imap::types::{Flag, Flags, MailboxStatus, Uid, UidSet};
-
-use crate::account::Account;
+use std::collections::HashMap;
+
+use futures_util::{Stream, StreamExt, future};
+use mailledger_imap::Error as ImapError;
+use mailledger_imap::command::{
+    FetchAttribute, FetchItems, SearchCriteria as ImapSearchCriteria, StatusAttribute, StoreAction,
+};
+use mailledger_imap::connection::{
+    Authenticated, Client, Config, ImapStream, NotAuthenticated, Selected, connect,
+};
+use mailledger_imap::parser::{Address, BodyStructure, FetchItem};
+use mailledger_imap::types::{
+    CopyUid, Flag, Flags, Mailbox, MailboxAttribute, MailboxStatus, SeqNum, Uid, UidSet,
+};
+use mailledger_mime::encoding::decode_rfc2047;
+use mailledger_mime::{MdnReport, parse_mdn};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::account::{Account, AccountId, AuthMethod};
+use crate::cache::{CacheRepository, CachedMessageSummary};
+use crate::threading::parse_message_ids;
+
+use super::oauth::{OAuthMechanism, fresh_token};
+use super::retry::{RetryPolicy, SessionRetry, with_backoff};
+use super::unsubscribe::{UNSUBSCRIBE_HEADERS, UnsubscribeInfo};
 
 /// Errors that can occur during mail operations.
 #[derive(Debug, thiserror::Error)]
@@ -25,9 +45,30 @@ pub enum MailServiceError {
     #[error("Operation failed: {0}")]
     Operation(String),
 
-    /// Security mode not supported.
-    #[error("Security mode not supported: only SSL/TLS is currently supported")]
-    UnsupportedSecurity,
+    /// A destructive operation was confirmed without a matching preparation.
+    #[error("Confirmation rejected: {0}")]
+    ConfirmationRejected(String),
+
+    /// The server or connection failed temporarily; retrying may succeed.
+    #[error("Temporary failure: {0}")]
+    Transient(String),
+}
+
+impl MailServiceError {
+    /// Returns true if retrying the operation may succeed.
+    #[must_use]
+    pub const fn is_retriable(&self) -> bool {
+        matches!(self, Self::Transient(_))
+    }
+
+    /// Converts an IMAP error, keeping transient failures distinguishable.
+    pub(crate) fn from_imap(error: &ImapError, wrap: fn(String) -> Self) -> Self {
+        if error.is_retriable() {
+            Self::Transient(error.to_string())
+        } else {
+            wrap(error.to_string())
+        }
+    }
 }
 
 /// A folder in the mailbox.
@@ -49,6 +90,17 @@ pub struct Folder {
     pub total_count: Option<u32>,
 }
 
+/// Message counts of a folder, as reported by STATUS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FolderCounts {
+    /// Total number of messages.
+    pub total: u32,
+    /// Number of messages without the `\Seen` flag.
+    pub unread: u32,
+    /// Number of recent messages.
+    pub recent: u32,
+}
+
 /// Type of folder.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FolderType {
@@ -89,6 +141,21 @@ impl FolderType {
             Self::Regular
         }
     }
+
+    /// Maps a SPECIAL-USE attribute (RFC 6154) to a folder type.
+    ///
+    /// Returns `None` for roles without a matching type, such as `\All`.
+    #[must_use]
+    pub const fn from_special_use(attribute: &MailboxAttribute) -> Option<Self> {
+        match attribute {
+            MailboxAttribute::Sent => Some(Self::Sent),
+            MailboxAttribute::Drafts => Some(Self::Drafts),
+            MailboxAttribute::Trash => Some(Self::Trash),
+            MailboxAttribute::Junk => Some(Self::Spam),
+            MailboxAttribute::Archive => Some(Self::Archive),
+            _ => None,
+        }
+    }
 }
 
 /// Summary of an email message.
@@ -98,8 +165,10 @@ pub struct MessageSummary {
     pub uid: Uid,
     /// Message subject.
     pub subject: String,
-    /// Sender address.
+    /// Sender display name, or address if there is no name.
     pub from: String,
+    /// Sender email address, without the display name.
+    pub from_email: String,
     /// Recipient address.
     pub to: String,
     /// Date as string.
@@ -118,6 +187,10 @@ pub struct MessageSummary {
     pub in_reply_to: Option<String>,
     /// Thread ID (computed from Message-ID/In-Reply-To chain).
     pub thread_id: Option<String>,
+    /// Message IDs from the References header, oldest first.
+    pub references: Vec<String>,
+    /// Gmail labels, e.g. `\Important` or `Receipts`; empty on other servers.
+    pub labels: Vec<String>,
 }
 
 /// Full content of an email message.
@@ -141,10 +214,21 @@ pub struct MessageContent {
     pub body_html: Option<String>,
     /// List of attachments.
     pub attachments: Vec<Attachment>,
+    /// How to unsubscribe, for mailing-list messages.
+    pub unsubscribe: Option<UnsubscribeInfo>,
+    /// Message-ID header.
+    pub message_id: Option<String>,
+    /// Address the sender asked to get a read receipt at
+    /// (`Disposition-Notification-To`).
+    pub read_receipt_to: Option<String>,
+    /// The notification fields, if this message is itself a read receipt.
+    pub receipt: Option<MdnReport>,
+    /// The message's header block, as the server sent it.
+    pub raw_headers: Vec<u8>,
 }
 
 /// An email attachment.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Attachment {
     /// Filename.
     pub filename: String,
@@ -159,58 +243,97 @@ pub struct Attachment {
 }
 
 /// Type alias for authenticated IMAP client with TLS stream.
-pub type AuthClient = Client<ImapStream, mailledger_imap::connection::Authenticated>;
+pub type AuthClient = Client<ImapStream, Authenticated>;
 
 /// Type alias for selected IMAP client with TLS stream.
-pub type SelectedClient = Client<ImapStream, mailledger_imap::connection::Selected>;
+pub type SelectedClient = Client<ImapStream, Selected>;
+
+/// Maps the account security mode to the IMAP connection mode.
+#[must_use]
+pub const fn imap_security(security: crate::Security) -> mailledger_imap::Security {
+    match security {
+        crate::Security::Tls => mailledger_imap::Security::Implicit,
+        crate::Security::StartTls => mailledger_imap::Security::StartTls,
+        crate::Security::None => mailledger_imap::Security::None,
+    }
+}
 
 /// Connect to an IMAP server and authenticate.
 ///
+/// Transient failures, such as a reset connection or a server shutting
+/// down, are retried with backoff.
+///
 /// # Errors
 ///
 /// Returns an error if connection or authentication fails.
 pub async fn connect_and_login(account: &Account) -> Result<AuthClient, MailServiceError> {
-    // Check security mode
-    if account.imap.security != crate::Security::Tls {
-        return Err(MailServiceError::UnsupportedSecurity);
-    }
+    with_backoff(|| connect_and_login_once(account), &RetryPolicy::default()).await
+}
 
-    // Connect with TLS
-    let stream = connect_tls(&account.imap.host, account.imap.port)
+async fn connect_and_login_once(account: &Account) -> Result<AuthClient, MailServiceError> {
+    // Connect, upgrading with STARTTLS if configured, and read greeting
+    let config = Config::builder(&account.imap.host)
+        .port(account.imap.port)
+        .security(imap_security(account.imap.security))
+        .build();
+    let client = connect(&config)
         .await
-        .map_err(|e| MailServiceError::Connection(e.to_string()))?;
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Connection))?;
 
-    // Create client and read greeting
-    let client: Client<ImapStream, mailledger_imap::connection::NotAuthenticated> =
-        Client::from_stream(stream)
-            .await
-            .map_err(|e| MailServiceError::Connection(e.to_string()))?;
+    authenticate(client, account).await
+}
 
-    // Authenticate - try LOGIN first, fallback to AUTHENTICATE PLAIN if needed
-    let auth_client = if !client.login_disabled() {
-        // Try LOGIN command first (more compatible with some servers)
-        match client
-            .login(&account.imap.username, &account.imap.password)
-            .await
-        {
-            Ok(authenticated) => authenticated,
-            Err(e) => {
-                return Err(MailServiceError::Authentication(e.to_string()));
-            }
-        }
-    } else if client.supports_auth_plain() {
-        // Fallback to AUTHENTICATE PLAIN if LOGIN is disabled
-        client
-            .authenticate_plain(&account.imap.username, &account.imap.password)
-            .await
-            .map_err(|e| MailServiceError::Authentication(e.to_string()))?
-    } else {
-        return Err(MailServiceError::Authentication(
-            "No supported authentication method available".to_string(),
-        ));
+/// Sign in with the account's IMAP authentication method.
+///
+/// Passwords are sent with LOGIN, or AUTHENTICATE PLAIN if LOGIN is
+/// disabled. `OAuth2` accounts get a fresh access token and send it with
+/// OAUTHBEARER or XOAUTH2.
+pub(crate) async fn authenticate<S>(
+    client: Client<S, NotAuthenticated>,
+    account: &Account,
+) -> Result<Client<S, Authenticated>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // The server may have authenticated the connection already (PREAUTH)
+    let client = match client.preauthenticated() {
+        Ok(client) => return Ok(client),
+        Err(client) => client,
     };
 
-    Ok(auth_client)
+    let username = &account.imap.username;
+    let result = match &account.imap.auth {
+        // SCRAM keeps the password off the wire and authenticates the server
+        AuthMethod::Password(password) if client.supports_scram() => {
+            client.authenticate_scram(username, password).await
+        }
+        AuthMethod::Password(password) if !client.login_disabled() => {
+            client.login(username, password).await
+        }
+        AuthMethod::Password(password) if client.supports_auth_plain() => {
+            client.authenticate_plain(username, password).await
+        }
+        AuthMethod::Password(_) => {
+            return Err(MailServiceError::Authentication(
+                "No supported authentication method available".to_string(),
+            ));
+        }
+        AuthMethod::OAuth {
+            provider,
+            token,
+            client_id,
+        } => {
+            let token = fresh_token(account.id, *provider, token, client_id).await?;
+            let mechanism = OAuthMechanism::choose(|m| client.supports_auth(m));
+            client
+                .authenticate(
+                    mechanism.as_str(),
+                    Some(&mechanism.initial_response(username, &token)),
+                )
+                .await
+        }
+    };
+    result.map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Authentication))
 }
 
 /// List all folders from an authenticated client.
@@ -238,12 +361,16 @@ pub async fn list_folders(client: &mut AuthClient) -> Result<Vec<Folder>, MailSe
                 selectable: !mb
                     .attributes
                     .iter()
-                    .any(|a| matches!(a, mailledger_imap::types::MailboxAttribute::NoSelect)),
+                    .any(|a| matches!(a, MailboxAttribute::NoSelect)),
                 has_children: mb
                     .attributes
                     .iter()
-                    .any(|a| matches!(a, mailledger_imap::types::MailboxAttribute::HasChildren)),
-                folder_type: FolderType::from_name(mailbox_name),
+                    .any(|a| matches!(a, MailboxAttribute::HasChildren)),
+                // Prefer the server-declared role; names are localized
+                folder_type: mb
+                    .special_use()
+                    .and_then(FolderType::from_special_use)
+                    .unwrap_or_else(|| FolderType::from_name(mailbox_name)),
                 unread_count: None,
                 total_count: None,
             }
@@ -253,6 +380,49 @@ pub async fn list_folders(client: &mut AuthClient) -> Result<Vec<Folder>, MailSe
     Ok(folders)
 }
 
+/// Get message counts for each selectable folder over one connection.
+///
+/// Pipelines one STATUS per folder and returns the counts keyed by folder
+/// path. Folders the server refuses STATUS for are left out.
+///
+/// # Errors
+///
+/// Returns an error if the connection fails.
+pub async fn folder_counts(
+    client: &mut AuthClient,
+    folders: &[Folder],
+) -> Result<HashMap<String, FolderCounts>, MailServiceError> {
+    const ITEMS: [StatusAttribute; 3] = [
+        StatusAttribute::Messages,
+        StatusAttribute::Unseen,
+        StatusAttribute::Recent,
+    ];
+
+    let mailboxes: Vec<Mailbox> = folders
+        .iter()
+        .filter(|f| f.selectable)
+        .map(|f| Mailbox::new(&f.path))
+        .collect();
+    let statuses = client
+        .status_many(&mailboxes, &ITEMS)
+        .await
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+
+    Ok(statuses
+        .into_iter()
+        .map(|(mailbox, status)| {
+            (
+                mailbox.0,
+                FolderCounts {
+                    total: status.exists,
+                    unread: status.unseen_count.unwrap_or(0),
+                    recent: status.recent,
+                },
+            )
+        })
+        .collect())
+}
+
 /// Select a folder and return a selected client.
 ///
 /// # Errors
@@ -268,6 +438,24 @@ pub async fn select_folder(
         .map_err(|e| MailServiceError::Operation(e.to_string()))
 }
 
+/// Open a folder read-only with EXAMINE and return a selected client.
+///
+/// Browsing this way doesn't clear `\Recent`, and the returned client
+/// refuses STORE, MOVE and EXPUNGE before they reach the server.
+///
+/// # Errors
+///
+/// Returns an error if the operation fails.
+pub async fn select_folder_read_only(
+    client: AuthClient,
+    folder_path: &str,
+) -> Result<(SelectedClient, MailboxStatus), MailServiceError> {
+    client
+        .examine(folder_path)
+        .await
+        .map_err(|e| MailServiceError::Operation(e.to_string()))
+}
+
 /// Fetch message summaries from the selected folder.
 ///
 /// # Errors
@@ -277,8 +465,53 @@ pub async fn fetch_messages(
     client: &mut SelectedClient,
     uid_set: &UidSet,
 ) -> Result<Vec<MessageSummary>, MailServiceError> {
-    // Fetch envelope, flags, and UID
-    let fetch_items = FetchItems::Items(vec![
+    let fetch_items = summary_fetch_items(client);
+
+    let mut retry = SessionRetry::new(RetryPolicy::default());
+    let responses = loop {
+        match client.uid_fetch(uid_set, fetch_items.clone()).await {
+            Err(e) if retry.wait(&e).await => {}
+            result => break result,
+        }
+    }
+    .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+
+    Ok(responses
+        .into_iter()
+        .filter_map(|(_seq_num, items)| summary_from_items(items))
+        .collect())
+}
+
+/// Fetch message summaries, yielding each one as the server sends it.
+///
+/// Lets the message list fill in while a large batch is still arriving.
+/// Unlike [`fetch_messages`], failures aren't retried. Drain the stream
+/// before using `client` again.
+pub fn fetch_messages_stream<'a>(
+    client: &'a mut SelectedClient,
+    uid_set: &UidSet,
+) -> impl Stream<Item = Result<MessageSummary, MailServiceError>> + 'a {
+    let fetch_items = summary_fetch_items(client);
+    client
+        .uid_fetch_stream(uid_set, fetch_items)
+        .filter_map(|result| {
+            future::ready(match result {
+                Ok((_seq_num, items)) => summary_from_items(items).map(Ok),
+                Err(e) => Some(Err(MailServiceError::from_imap(
+                    &e,
+                    MailServiceError::Operation,
+                ))),
+            })
+        })
+}
+
+/// Items fetched for a [`MessageSummary`]: envelope, flags, UID, a body
+/// snippet, References, and Gmail labels and thread IDs where supported.
+fn summary_fetch_items<S>(client: &Client<S, Selected>) -> FetchItems
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut attributes = vec![
         FetchAttribute::Uid,
         FetchAttribute::Flags,
         FetchAttribute::Envelope,
@@ -287,69 +520,92 @@ pub async fn fetch_messages(
             peek: true,
             partial: Some((0, 200)),
         },
-    ]);
-
-    let responses = client
-        .uid_fetch(uid_set, fetch_items)
-        .await
-        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
-
-    let mut messages = Vec::new();
-    for (_seq_num, items) in responses {
-        let mut uid = None;
-        let mut envelope = None;
-        let mut flags = Flags::default();
-        let mut body_text: Option<Vec<u8>> = None;
+        FetchAttribute::BodyHeaderFields(vec!["References".to_string()]),
+    ];
+    if client.supports_gmail_extensions() {
+        attributes.extend([FetchAttribute::GmailLabels, FetchAttribute::GmailThreadId]);
+    }
+    FetchItems::Items(attributes)
+}
 
-        // Extract items from the response
-        for item in items {
-            match item {
-                FetchItem::Uid(u) => uid = Some(u),
-                FetchItem::Envelope(e) => envelope = Some(e),
-                FetchItem::Flags(f) => flags = f,
-                FetchItem::Body { data, .. } => body_text = data,
-                _ => {}
+/// Builds a summary from the items of one FETCH response; `None` without a UID.
+fn summary_from_items(items: Vec<FetchItem>) -> Option<MessageSummary> {
+    let mut uid = None;
+    let mut envelope = None;
+    let mut flags = Flags::default();
+    let mut body_text: Option<Vec<u8>> = None;
+    let mut references = Vec::new();
+    let mut labels = Vec::new();
+    let mut gmail_thread_id = None;
+
+    // Extract items from the response
+    for item in items {
+        match item {
+            FetchItem::Uid(u) => uid = Some(u),
+            FetchItem::Envelope(e) => envelope = Some(e),
+            FetchItem::Flags(f) => flags = f,
+            FetchItem::GmailLabels(l) => labels = l,
+            FetchItem::GmailThreadId(id) => gmail_thread_id = Some(id),
+            FetchItem::Body {
+                section: Some(section),
+                data,
+                ..
+            } if section.to_ascii_uppercase().starts_with("HEADER.FIELDS") => {
+                references = data
+                    .map(|d| parse_message_ids(&String::from_utf8_lossy(&d)))
+                    .unwrap_or_default();
             }
-        }
-
-        if let Some(uid) = uid {
-            let envelope = envelope.as_deref();
-
-            // Extract threading headers
-            let message_id = envelope.and_then(|e| e.message_id.clone());
-            let in_reply_to = envelope.and_then(|e| e.in_reply_to.clone());
-
-            // Compute thread_id: use the root message ID from In-Reply-To chain
-            // For simplicity, we use In-Reply-To if present, otherwise Message-ID
-            let thread_id = in_reply_to.clone().or_else(|| message_id.clone());
-
-            messages.push(MessageSummary {
-                uid,
-                subject: envelope.and_then(|e| e.subject.clone()).unwrap_or_default(),
-                from: envelope
-                    .and_then(|e| e.from.first())
-                    .map(format_address)
-                    .unwrap_or_default(),
-                to: envelope
-                    .and_then(|e| e.to.first())
-                    .map(format_address)
-                    .unwrap_or_default(),
-                date: envelope.and_then(|e| e.date.clone()).unwrap_or_default(),
-                is_read: flags.contains(&Flag::Seen),
-                is_flagged: flags.contains(&Flag::Flagged),
-                has_attachment: false, // Would need BODYSTRUCTURE to detect
-                snippet: body_text
-                    .as_ref()
-                    .map(|b| truncate_text(&extract_text_snippet(b), 100))
-                    .unwrap_or_default(),
-                message_id,
-                in_reply_to,
-                thread_id,
-            });
+            FetchItem::Body { data, .. } => body_text = data,
+            _ => {}
         }
     }
 
-    Ok(messages)
+    let uid = uid?;
+    let envelope = envelope.as_deref();
+
+    // Extract threading headers
+    let message_id = envelope.and_then(|e| e.message_id.clone());
+    let in_reply_to = envelope.and_then(|e| e.in_reply_to.clone());
+
+    // Compute thread_id: Gmail's own thread ID when available, else
+    // In-Reply-To if present, otherwise Message-ID
+    let thread_id = gmail_thread_id
+        .map(|id| id.to_string())
+        .or_else(|| in_reply_to.clone())
+        .or_else(|| message_id.clone());
+
+    Some(MessageSummary {
+        uid,
+        subject: envelope
+            .and_then(|e| e.subject.as_deref())
+            .map(decode_rfc2047)
+            .unwrap_or_default(),
+        from: envelope
+            .and_then(|e| e.from.first())
+            .map(format_address)
+            .unwrap_or_default(),
+        from_email: envelope
+            .and_then(|e| e.from.first())
+            .map(address_email)
+            .unwrap_or_default(),
+        to: envelope
+            .and_then(|e| e.to.first())
+            .map(format_address)
+            .unwrap_or_default(),
+        date: envelope.and_then(|e| e.date.clone()).unwrap_or_default(),
+        is_read: flags.contains(&Flag::Seen),
+        is_flagged: flags.contains(&Flag::Flagged),
+        has_attachment: false, // Would need BODYSTRUCTURE to detect
+        snippet: body_text
+            .as_ref()
+            .map(|b| truncate_text(&extract_text_snippet(b), 100))
+            .unwrap_or_default(),
+        message_id,
+        in_reply_to,
+        thread_id,
+        references,
+        labels,
+    })
 }
 
 /// Search criteria for IMAP SEARCH command.
@@ -374,6 +630,39 @@ pub struct SearchCriteria {
 }
 
 impl SearchCriteria {
+    /// Builds the IMAP search criteria.
+    ///
+    /// Dates that aren't in `DD-MMM-YYYY` format are ignored.
+    #[must_use]
+    pub fn to_search_criteria(&self) -> ImapSearchCriteria {
+        let mut builder = ImapSearchCriteria::builder();
+        if let Some(text) = &self.text {
+            builder = builder.text(text);
+        }
+        if let Some(from) = &self.from {
+            builder = builder.from(from);
+        }
+        if let Some(to) = &self.to {
+            builder = builder.to(to);
+        }
+        if let Some(subject) = &self.subject {
+            builder = builder.subject(subject);
+        }
+        if self.unread {
+            builder = builder.unseen();
+        }
+        if self.flagged {
+            builder = builder.flagged();
+        }
+        if let Some(since) = self.since.as_deref().and_then(parse_search_date) {
+            builder = builder.since(since);
+        }
+        if let Some(before) = self.before.as_deref().and_then(parse_search_date) {
+            builder = builder.before(before);
+        }
+        builder.build()
+    }
+
     /// Build IMAP SEARCH criteria string.
     #[must_use]
     pub fn to_imap_criteria(&self) -> String {
@@ -413,6 +702,15 @@ impl SearchCriteria {
     }
 }
 
+/// Parses a `DD-MMM-YYYY` search date.
+fn parse_search_date(date: &str) -> Option<chrono::NaiveDate> {
+    let parsed = chrono::NaiveDate::parse_from_str(date.trim(), "%d-%b-%Y").ok();
+    if parsed.is_none() {
+        tracing::warn!("Ignoring invalid search date: {date}");
+    }
+    parsed
+}
+
 /// Escape special characters in IMAP search strings.
 ///
 /// For safety, only allows alphanumeric characters, spaces, and common email characters.
@@ -434,17 +732,57 @@ pub async fn search_messages(
     client: &mut SelectedClient,
     criteria: &SearchCriteria,
 ) -> Result<Vec<Uid>, MailServiceError> {
-    let criteria_str = criteria.to_imap_criteria();
-    tracing::debug!("IMAP SEARCH criteria: {}", criteria_str);
+    search_messages_matching(client, &criteria.to_search_criteria()).await
+}
 
-    let uids = client
-        .uid_search(&criteria_str)
-        .await
-        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+/// Search for messages matching IMAP criteria, e.g. from
+/// [`ImapSearchCriteria::builder`].
+///
+/// Non-ASCII search text is sent as UTF-8.
+///
+/// # Errors
+///
+/// Returns an error if the IMAP search fails.
+pub async fn search_messages_matching(
+    client: &mut SelectedClient,
+    criteria: &ImapSearchCriteria,
+) -> Result<Vec<Uid>, MailServiceError> {
+    tracing::debug!("IMAP SEARCH criteria: {:?}", criteria);
+
+    let charset = (!criteria.is_ascii()).then_some("UTF-8");
+    let mut retry = SessionRetry::new(RetryPolicy::default());
+    let uids = loop {
+        match client.uid_search_criteria(criteria, charset).await {
+            Err(e) if retry.wait(&e).await => {}
+            result => break result,
+        }
+    }
+    .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
 
     Ok(uids)
 }
 
+/// Search the offline cache when the server is unreachable.
+///
+/// Matches subject, sender and cached body text of the account's messages
+/// using the cache's full-text index; see [`CacheRepository::search_fts`]
+/// for the query syntax.
+///
+/// # Errors
+///
+/// Returns an error if the cache query fails.
+pub async fn search_offline(
+    cache: &CacheRepository,
+    account_id: AccountId,
+    query: &str,
+    limit: u32,
+) -> Result<Vec<CachedMessageSummary>, MailServiceError> {
+    cache
+        .search_fts(account_id, query, limit)
+        .await
+        .map_err(|e| MailServiceError::Operation(e.to_string()))
+}
+
 /// Mark a message as read.
 ///
 /// # Errors
@@ -510,9 +848,8 @@ async fn remove_flag(
 
 /// Move a message to the Archive folder.
 ///
-/// Uses the MOVE command (RFC 6851) to move the message to the specified
-/// archive folder path. If the server doesn't support MOVE, falls back to
-/// COPY + STORE \Deleted + EXPUNGE.
+/// See [`move_messages`] for how servers without MOVE are handled and what
+/// `create_if_missing` does.
 ///
 /// # Errors
 ///
@@ -521,38 +858,393 @@ pub async fn archive_message(
     client: &mut SelectedClient,
     uid: Uid,
     archive_folder: &str,
+    create_if_missing: bool,
 ) -> Result<(), MailServiceError> {
-    let uid_set = UidSet::single(uid);
+    archive_many(
+        client,
+        &UidSet::single(uid),
+        archive_folder,
+        create_if_missing,
+    )
+    .await
+}
 
-    // Try MOVE first (preferred, atomic operation)
-    if client.uid_move(&uid_set, archive_folder).await.is_ok() {
-        return Ok(());
+/// Permanently delete a message: flag it `\Deleted` and expunge.
+///
+/// Uses `UID EXPUNGE` when the server has UIDPLUS, so only this message is
+/// removed; otherwise a plain EXPUNGE also removes any other message marked
+/// `\Deleted`. Returns the sequence numbers the server reported expunged,
+/// see [`Client::expunge`] for how to apply them.
+///
+/// # Errors
+///
+/// Returns an error if the operation fails.
+pub async fn delete_permanently(
+    client: &mut SelectedClient,
+    uid: Uid,
+) -> Result<Vec<SeqNum>, MailServiceError> {
+    let uids = UidSet::single(uid);
+    client
+        .uid_store(&uids, StoreAction::AddFlags(vec![Flag::Deleted]))
+        .await
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    if client.supports_uidplus() {
+        client.uid_expunge(&uids).await
+    } else {
+        client.expunge().await
     }
+    .map_err(|e| MailServiceError::Operation(e.to_string()))
+}
 
-    // Fallback: COPY then STORE \Deleted then EXPUNGE
+/// Mark several messages as read in one `UID STORE`.
+///
+/// # Errors
+///
+/// Returns an error if the operation fails.
+pub async fn mark_read_many(
+    client: &mut SelectedClient,
+    uids: &UidSet,
+) -> Result<(), MailServiceError> {
     client
-        .uid_copy(&uid_set, archive_folder)
+        .uid_store(uids, StoreAction::AddFlags(vec![Flag::Seen]))
         .await
         .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    Ok(())
+}
+
+/// Move several messages to the Archive folder in one round trip.
+///
+/// See [`move_messages`] for how servers without MOVE are handled and what
+/// `create_if_missing` does.
+///
+/// # Errors
+///
+/// Returns an error if the operation fails.
+pub async fn archive_many(
+    client: &mut SelectedClient,
+    uids: &UidSet,
+    archive_folder: &str,
+    create_if_missing: bool,
+) -> Result<(), MailServiceError> {
+    move_messages(client, uids, archive_folder, create_if_missing).await?;
+    Ok(())
+}
+
+/// Move several messages to the Trash folder in one round trip.
+///
+/// Nothing is expunged from Trash itself; permanent deletion goes through
+/// [`ExpungeGuard`].
+///
+/// # Errors
+///
+/// Returns an error if the operation fails.
+pub async fn delete_many(
+    client: &mut SelectedClient,
+    uids: &UidSet,
+    trash_folder: &str,
+) -> Result<(), MailServiceError> {
+    move_messages(client, uids, trash_folder, false).await?;
+    Ok(())
+}
+
+/// Move messages to `destination` in one round trip where possible.
+///
+/// Uses `UID MOVE` (RFC 6851) when the server advertises MOVE, otherwise
+/// `UID COPY` + `UID STORE +FLAGS \Deleted` + `UID EXPUNGE`, or a plain
+/// EXPUNGE without UIDPLUS. Returns where the messages landed if the server
+/// reports `COPYUID`.
+///
+/// With `create_if_missing`, a destination the server reports missing with
+/// `[TRYCREATE]` is created and subscribed to, and the move is retried once.
+///
+/// # Errors
+///
+/// Returns an error if any command fails.
+pub async fn move_messages<S>(
+    client: &mut Client<S, Selected>,
+    uids: &UidSet,
+    destination: &str,
+    create_if_missing: bool,
+) -> Result<Option<CopyUid>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let moves = client.supports_move();
+    let copied = match transfer(client, uids, destination, moves).await {
+        Err(e) if create_if_missing && e.is_try_create() => {
+            create_folder(client, destination).await?;
+            transfer(client, uids, destination, moves).await
+        }
+        result => result,
+    }
+    .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    if moves {
+        return Ok(copied);
+    }
+
     client
-        .uid_store(&uid_set, StoreAction::AddFlags(vec![Flag::Deleted]))
+        .uid_store(uids, StoreAction::AddFlags(vec![Flag::Deleted]))
         .await
         .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    // UID EXPUNGE leaves messages other clients marked \Deleted alone
+    if client.supports_uidplus() {
+        client.uid_expunge(uids).await
+    } else {
+        client.expunge().await
+    }
+    .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    Ok(copied)
+}
+
+/// Send `UID MOVE` if `moves` is set, or else `UID COPY`.
+async fn transfer<S>(
+    client: &mut Client<S, Selected>,
+    uids: &UidSet,
+    destination: &str,
+    moves: bool,
+) -> Result<Option<CopyUid>, ImapError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if moves {
+        client.uid_move(uids, destination).await
+    } else {
+        client.uid_copy(uids, destination).await
+    }
+}
+
+/// Create a folder a command failed to reach with `[TRYCREATE]`.
+///
+/// The folder is also subscribed to, since some clients only show
+/// subscribed folders; that part is best effort.
+async fn create_folder<S>(
+    client: &mut Client<S, Selected>,
+    folder: &str,
+) -> Result<(), MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     client
-        .expunge()
+        .create(folder)
         .await
-        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+    if let Err(e) = client.subscribe(folder).await {
+        tracing::debug!("failed to subscribe to {folder}: {e}");
+    }
     Ok(())
 }
 
+/// What a permanent deletion should remove.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpungeScope {
+    /// Only messages already flagged `\Deleted`.
+    Deleted,
+    /// Every message in the folder (e.g. emptying Trash).
+    EmptyFolder,
+}
+
+/// Opaque token tying a confirmation to a specific [`ExpungeGuard::prepare_expunge`] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExpungeToken(u64);
+
+/// Result of preparing a permanent deletion, shown to the user before committing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpungePreview {
+    /// Token to pass to [`ExpungeGuard::confirm_expunge`].
+    pub token: ExpungeToken,
+    /// Folder the deletion applies to.
+    pub folder: String,
+    /// Number of messages that will be permanently removed.
+    pub count: usize,
+    /// What is being removed.
+    pub scope: ExpungeScope,
+}
+
+/// A prepared deletion awaiting confirmation.
+#[derive(Debug, Clone)]
+struct PendingExpunge {
+    token: ExpungeToken,
+    folder: String,
+    scope: ExpungeScope,
+    uids: Vec<Uid>,
+}
+
+/// Two-step guard for irreversible deletions (EXPUNGE, empty-trash).
+///
+/// Nothing is removed until [`prepare_expunge`](Self::prepare_expunge) has
+/// reported the affected count and the caller hands back the same token to
+/// [`confirm_expunge`](Self::confirm_expunge). Only the most recent
+/// preparation is valid; preparing again invalidates earlier tokens.
+#[derive(Debug, Default)]
+pub struct ExpungeGuard {
+    pending: Option<PendingExpunge>,
+    next_token: u64,
+}
+
+impl ExpungeGuard {
+    /// Creates a guard with no pending deletion.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if a deletion is awaiting confirmation.
+    #[must_use]
+    pub const fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Discards any pending deletion.
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Counts the messages a permanent deletion would remove and issues a token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the IMAP search fails.
+    pub async fn prepare_expunge(
+        &mut self,
+        client: &mut SelectedClient,
+        scope: ExpungeScope,
+    ) -> Result<ExpungePreview, MailServiceError> {
+        let criteria = match scope {
+            ExpungeScope::Deleted => "DELETED",
+            ExpungeScope::EmptyFolder => "ALL",
+        };
+        let uids = client
+            .uid_search(criteria)
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+
+        Ok(self.register(client.mailbox().to_string(), scope, uids))
+    }
+
+    /// Permanently removes the messages counted by a matching preparation.
+    ///
+    /// Returns the number of messages expunged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MailServiceError::ConfirmationRejected`] if the token does not
+    /// match the pending preparation, the client has a different folder
+    /// selected, or the set of `\Deleted` messages changed since preparing.
+    /// Returns an error if any IMAP command fails.
+    pub async fn confirm_expunge(
+        &mut self,
+        client: &mut SelectedClient,
+        token: ExpungeToken,
+    ) -> Result<usize, MailServiceError> {
+        let pending = self.take(token, client.mailbox())?;
+
+        match pending.scope {
+            ExpungeScope::Deleted => {
+                let mut current = client
+                    .uid_search("DELETED")
+                    .await
+                    .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+                current.sort_unstable();
+                if current != pending.uids {
+                    return Err(MailServiceError::ConfirmationRejected(
+                        "deleted messages changed since confirmation was requested".to_string(),
+                    ));
+                }
+            }
+            ExpungeScope::EmptyFolder => {
+                if let Some(uid_set) = uid_set_from(&pending.uids) {
+                    client
+                        .uid_store(&uid_set, StoreAction::AddFlags(vec![Flag::Deleted]))
+                        .await
+                        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+                }
+            }
+        }
+
+        if pending.uids.is_empty() {
+            return Ok(0);
+        }
+
+        // Restrict the expunge to the confirmed messages when possible
+        let expunged = match uid_set_from(&pending.uids) {
+            Some(uid_set) if client.supports_uidplus() => client.uid_expunge(&uid_set).await,
+            _ => client.expunge().await,
+        }
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+        Ok(expunged.len())
+    }
+
+    /// Records a new pending deletion, replacing any previous one.
+    fn register(
+        &mut self,
+        folder: String,
+        scope: ExpungeScope,
+        mut uids: Vec<Uid>,
+    ) -> ExpungePreview {
+        uids.sort_unstable();
+        self.next_token = self.next_token.wrapping_add(1);
+        let token = ExpungeToken(self.next_token);
+        let preview = ExpungePreview {
+            token,
+            folder: folder.clone(),
+            count: uids.len(),
+            scope,
+        };
+        self.pending = Some(PendingExpunge {
+            token,
+            folder,
+            scope,
+            uids,
+        });
+        preview
+    }
+
+    /// Consumes the pending deletion if `token` and `folder` match it.
+    fn take(
+        &mut self,
+        token: ExpungeToken,
+        folder: &str,
+    ) -> Result<PendingExpunge, MailServiceError> {
+        match self.pending.take() {
+            Some(pending) if pending.token == token && pending.folder == folder => Ok(pending),
+            Some(pending) if pending.token == token => {
+                Err(MailServiceError::ConfirmationRejected(format!(
+                    "token was issued for folder {}, but {folder} is selected",
+                    pending.folder
+                )))
+            }
+            Some(pending) => {
+                // A stale token must not cancel the deletion the user is looking at.
+                self.pending = Some(pending);
+                Err(MailServiceError::ConfirmationRejected(
+                    "token does not match the pending deletion".to_string(),
+                ))
+            }
+            None => Err(MailServiceError::ConfirmationRejected(
+                "no deletion is pending confirmation".to_string(),
+            )),
+        }
+    }
+}
+
+/// Build a UID set from a list of UIDs, or `None` if the list is empty.
+pub(crate) fn uid_set_from(uids: &[Uid]) -> Option<UidSet> {
+    UidSet::from_uids(uids)
+}
+
 /// Format an address for display.
 fn format_address(addr: &Address) -> String {
     if let Some(ref name) = addr.name
         && !name.is_empty()
     {
-        return name.clone();
+        return decode_rfc2047(name);
     }
 
+    address_email(addr)
+}
+
+/// Extract the bare `mailbox@host` of an address.
+fn address_email(addr: &Address) -> String {
     match (&addr.mailbox, &addr.host) {
         (Some(m), Some(h)) => format!("{m}@{h}"),
         (Some(m), None) => m.clone(),
@@ -655,159 +1347,336 @@ pub enum IdleEvent {
     Disconnected(String),
 }
 
+/// Which body parts [`fetch_message_content`] downloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PreferredBody {
+    /// Only the HTML part, or the plain text part if there is none.
+    Html,
+    /// Only the plain text part, or the HTML part if there is none.
+    PlainText,
+    /// Both parts.
+    #[default]
+    Both,
+}
+
 /// Fetch full content for a single message.
 ///
+/// The message's BODYSTRUCTURE is fetched with its header, and only the
+/// text parts `preferred` asks for are downloaded afterwards. Attachments
+/// are listed from the structure; their contents are left for
+/// [`download_attachment`].
+///
 /// # Errors
 ///
 /// Returns an error if the fetch operation fails.
-pub async fn fetch_message_content(
-    client: &mut SelectedClient,
+pub async fn fetch_message_content<S>(
+    client: &mut Client<S, Selected>,
     uid: Uid,
-) -> Result<Option<MessageContent>, MailServiceError> {
-    use mailledger_imap::parser::BodyStructure;
-
+    preferred: PreferredBody,
+) -> Result<Option<MessageContent>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let uid_set = UidSet::single(uid);
 
-    // Fetch full body, envelope, and body structure for attachments
-    let fetch_items = FetchItems::Items(vec![
-        FetchAttribute::Uid,
-        FetchAttribute::Flags,
-        FetchAttribute::Envelope,
-        FetchAttribute::BodyStructure,
-        FetchAttribute::Body {
-            section: None, // Full message
-            peek: true,
-            partial: None,
-        },
-    ]);
+    let fetch_items = content_fetch_items();
 
-    let responses = client
-        .uid_fetch(&uid_set, fetch_items)
-        .await
-        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    let mut retry = SessionRetry::new(RetryPolicy::default());
+    let responses = loop {
+        match client.uid_fetch(&uid_set, fetch_items.clone()).await {
+            Err(e) if retry.wait(&e).await => {}
+            result => break result,
+        }
+    }
+    .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
 
     for (_seq_num, items) in responses {
         let mut msg_uid = None;
         let mut envelope = None;
-        let mut body_data: Option<Vec<u8>> = None;
+        let mut header: Option<Vec<u8>> = None;
         let mut body_structure: Option<BodyStructure> = None;
+        let mut unsubscribe = None;
 
         for item in items {
             match item {
                 FetchItem::Uid(u) => msg_uid = Some(u),
                 FetchItem::Envelope(e) => envelope = Some(e),
-                FetchItem::Body { data, .. } => {
-                    body_data = data;
+                FetchItem::Body {
+                    section: Some(section),
+                    data,
+                    ..
+                } if section.to_ascii_uppercase().starts_with("HEADER.FIELDS") => {
+                    unsubscribe = data.and_then(|raw| UnsubscribeInfo::from_header_block(&raw));
                 }
+                FetchItem::Body {
+                    section: Some(section),
+                    data,
+                    ..
+                } if section.eq_ignore_ascii_case("HEADER") => header = data,
                 FetchItem::BodyStructure(bs) => body_structure = Some(bs),
                 _ => {}
             }
         }
 
-        if let Some(uid) = msg_uid {
-            let envelope = envelope.as_deref();
-
-            // Parse the body to extract text/html parts
-            let (body_text, body_html) =
-                body_data.map_or((None, None), |raw_body| parse_message_body(&raw_body));
-
-            // Extract attachments from body structure
-            let attachments = body_structure
-                .map(|bs| extract_attachments_from_structure(&bs, ""))
-                .unwrap_or_default();
-
-            return Ok(Some(MessageContent {
-                uid,
-                subject: envelope.and_then(|e| e.subject.clone()).unwrap_or_default(),
-                from: envelope
-                    .and_then(|e| e.from.first())
-                    .map(format_address)
-                    .unwrap_or_default(),
-                to: envelope
-                    .map(|e| e.to.iter().map(format_address).collect())
-                    .unwrap_or_default(),
-                cc: envelope
-                    .map(|e| e.cc.iter().map(format_address).collect())
-                    .unwrap_or_default(),
-                date: envelope.and_then(|e| e.date.clone()).unwrap_or_default(),
-                body_text,
-                body_html,
-                attachments,
-            }));
-        }
+        let Some(uid) = msg_uid else {
+            continue;
+        };
+        let envelope = envelope.as_deref();
+        let header = header.unwrap_or_default();
+        let body = fetch_bodies(client, uid, &header, body_structure.as_ref(), preferred).await?;
+
+        let attachments = body_structure
+            .as_ref()
+            .map(attachments_from_structure)
+            .unwrap_or_default();
+
+        return Ok(Some(MessageContent {
+            uid,
+            subject: envelope
+                .and_then(|e| e.subject.as_deref())
+                .map(decode_rfc2047)
+                .unwrap_or_default(),
+            from: envelope
+                .and_then(|e| e.from.first())
+                .map(format_address)
+                .unwrap_or_default(),
+            to: envelope
+                .map(|e| format_addresses(&e.to))
+                .unwrap_or_default(),
+            cc: envelope
+                .map(|e| format_addresses(&e.cc))
+                .unwrap_or_default(),
+            date: envelope.and_then(|e| e.date.clone()).unwrap_or_default(),
+            body_text: body.text,
+            body_html: body.html,
+            attachments,
+            unsubscribe,
+            message_id: envelope.and_then(|e| e.message_id.clone()),
+            read_receipt_to: body.read_receipt_to,
+            receipt: body.receipt,
+            raw_headers: header,
+        }));
     }
 
     Ok(None)
 }
 
-/// Extract attachments from a BODYSTRUCTURE response.
-///
-/// Recursively traverses the body structure to find attachment parts.
-/// Attachments are identified by:
-/// - Content-Disposition: attachment
-/// - Non-inline parts with a filename
-/// - Non-text/non-html parts in multipart messages
-fn extract_attachments_from_structure(
-    structure: &mailledger_imap::parser::BodyStructure,
-    part_prefix: &str,
-) -> Vec<Attachment> {
-    use mailledger_imap::parser::BodyStructure;
-
-    let mut attachments = Vec::new();
-
-    match structure {
-        BodyStructure::Basic {
-            media_type,
-            media_subtype,
-            params,
-            size,
-            encoding,
-            ..
-        } => {
-            // Check if this part has a filename (likely an attachment)
-            let filename = params
+/// Fetch one body part of a message, such as `1.2`, still in its
+/// Content-Transfer-Encoding.
+///
+/// Returns `None` if the server has no such part.
+///
+/// # Errors
+///
+/// Returns an error if the fetch operation fails.
+pub async fn fetch_body_part<S>(
+    client: &mut Client<S, Selected>,
+    uid: Uid,
+    part_number: &str,
+) -> Result<Option<Vec<u8>>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fetch_body_section(client, uid, part_number, None).await
+}
+
+/// Items fetched first for [`fetch_message_content`]: the header,
+/// envelope, body structure to pick parts from, and the list headers for
+/// unsubscribe.
+fn content_fetch_items() -> FetchItems {
+    FetchItems::Items(vec![
+        FetchAttribute::Uid,
+        FetchAttribute::Flags,
+        FetchAttribute::Envelope,
+        FetchAttribute::BodyStructure,
+        FetchAttribute::Body {
+            section: Some("HEADER".to_string()),
+            peek: true,
+            partial: None,
+        },
+        FetchAttribute::BodyHeaderFields(
+            UNSUBSCRIBE_HEADERS
                 .iter()
-                .find(|(k, _)| k.eq_ignore_ascii_case("name"))
-                .map(|(_, v)| v.clone());
+                .map(ToString::to_string)
+                .collect(),
+        ),
+    ])
+}
 
-            if let Some(filename) = filename {
-                let part_number = if part_prefix.is_empty() {
-                    "1".to_string()
-                } else {
-                    part_prefix.to_string()
-                };
+/// Decoded bodies and receipt fields of a message.
+struct MessageBodies {
+    text: Option<String>,
+    html: Option<String>,
+    read_receipt_to: Option<String>,
+    receipt: Option<MdnReport>,
+}
 
-                attachments.push(Attachment {
-                    filename,
-                    mime_type: format!("{media_type}/{media_subtype}"),
-                    size: u64::from(*size),
-                    part_number,
-                    encoding: encoding.clone(),
-                });
-            }
-        }
-        BodyStructure::Text { .. } | BodyStructure::Message { .. } => {
-            // Text parts are content; nested messages are skipped for simplicity
-        }
-        BodyStructure::Multipart { bodies, .. } => {
-            // Recursively process each part
-            for (i, body) in bodies.iter().enumerate() {
-                let part_num = if part_prefix.is_empty() {
-                    format!("{}", i + 1)
-                } else {
-                    format!("{}.{}", part_prefix, i + 1)
-                };
-                attachments.extend(extract_attachments_from_structure(body, &part_num));
-            }
+/// Downloads and decodes the bodies of message `uid`.
+///
+/// With a body structure only the parts picked by [`body_parts`] are
+/// fetched. Without one, or for a read receipt whose report part is needed
+/// too, the whole text is fetched and parsed with `header`.
+async fn fetch_bodies<S>(
+    client: &mut Client<S, Selected>,
+    uid: Uid,
+    header: &[u8],
+    structure: Option<&BodyStructure>,
+    preferred: PreferredBody,
+) -> Result<MessageBodies, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let parts = structure
+        .filter(|s| s.content_type() != "multipart/report")
+        .map(|s| body_parts(s, preferred))
+        .unwrap_or_default();
+
+    if parts.is_empty() {
+        let mut raw = header.to_vec();
+        raw.extend_from_slice(
+            &fetch_body_part(client, uid, "TEXT")
+                .await?
+                .unwrap_or_default(),
+        );
+        let (text, html) = parse_message_body(&raw);
+        let (read_receipt_to, receipt) = receipt_fields(&raw);
+        return Ok(MessageBodies {
+            text,
+            html,
+            read_receipt_to,
+            receipt,
+        });
+    }
+
+    let mut bodies = MessageBodies {
+        text: None,
+        html: None,
+        read_receipt_to: receipt_fields(header).0,
+        receipt: None,
+    };
+    for (part_number, part) in parts {
+        let Some(data) = fetch_body_part(client, uid, &part_number).await? else {
+            continue;
+        };
+        let decoded = decode_body_part(&data, part);
+        if part.content_type() == "text/html" {
+            bodies.html = Some(decoded);
+        } else {
+            bodies.text = Some(decoded);
         }
     }
+    Ok(bodies)
+}
 
-    attachments
+/// Picks the part numbers of the bodies to download for `preferred`,
+/// skipping text parts that are attachments.
+fn body_parts(
+    structure: &BodyStructure,
+    preferred: PreferredBody,
+) -> Vec<(String, &BodyStructure)> {
+    let attachments: Vec<String> = structure
+        .attachments()
+        .into_iter()
+        .map(|a| a.part_number)
+        .collect();
+    let leaves: Vec<_> = structure
+        .leaves()
+        .into_iter()
+        .filter(|(number, _)| !attachments.contains(number))
+        .collect();
+    let find = |content_type: &str| {
+        leaves
+            .iter()
+            .find(|(_, part)| part.content_type() == content_type)
+            .cloned()
+    };
+    let (plain, html) = (find("text/plain"), find("text/html"));
+
+    match preferred {
+        PreferredBody::Html => html.or(plain).into_iter().collect(),
+        PreferredBody::PlainText => plain.or(html).into_iter().collect(),
+        PreferredBody::Both => plain.into_iter().chain(html).collect(),
+    }
+}
+
+/// Decodes a fetched text part using the transfer encoding and charset
+/// from its body structure.
+fn decode_body_part(data: &[u8], part: &BodyStructure) -> String {
+    let BodyStructure::Text {
+        params, encoding, ..
+    } = part
+    else {
+        return String::from_utf8_lossy(data).into_owned();
+    };
+
+    let text = String::from_utf8_lossy(data);
+    let bytes = match encoding.to_ascii_lowercase().as_str() {
+        "base64" => {
+            let cleaned: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+            mailledger_mime::encoding::decode_base64(&cleaned).unwrap_or_else(|_| data.to_vec())
+        }
+        "quoted-printable" => mailledger_mime::encoding::decode_quoted_printable(&text)
+            .unwrap_or_else(|_| data.to_vec()),
+        _ => data.to_vec(),
+    };
+
+    params
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("charset"))
+        .map_or_else(
+            || mailledger_mime::encoding::decode_undeclared_charset(&bytes),
+            |(_, charset)| mailledger_mime::encoding::decode_charset(&bytes, charset),
+        )
+}
+
+/// Formats an envelope address list, skipping group end markers.
+fn format_addresses(addresses: &[Address]) -> Vec<String> {
+    addresses
+        .iter()
+        .filter(|a| !a.is_group_end())
+        .map(format_address)
+        .collect()
+}
+
+/// Reads a raw message's read-receipt request and, if it is a receipt
+/// itself, its notification fields.
+fn receipt_fields(raw: &[u8]) -> (Option<String>, Option<MdnReport>) {
+    mailledger_mime::Message::parse(raw).map_or((None, None), |message| {
+        (
+            message
+                .disposition_notification_to()
+                .map(ToString::to_string),
+            parse_mdn(&message),
+        )
+    })
+}
+
+/// Lists the attachments in a BODYSTRUCTURE.
+///
+/// Parts marked `inline` by their Content-Disposition, such as images the
+/// HTML body references by `cid:`, are content rather than attachments
+/// and are left out.
+fn attachments_from_structure(structure: &BodyStructure) -> Vec<Attachment> {
+    structure
+        .attachments()
+        .into_iter()
+        .map(|info| Attachment {
+            filename: info
+                .filename
+                .unwrap_or_else(|| format!("attachment-{}", info.part_number)),
+            mime_type: info.content_type,
+            size: u64::from(info.size),
+            part_number: info.part_number,
+            encoding: info.encoding,
+        })
+        .collect()
 }
 
 /// Download an attachment from a message.
 ///
-/// Returns the decoded attachment data.
+/// Returns the decoded attachment data. Uses BINARY (RFC 3516) when the
+/// server supports it so the server does the decoding; otherwise fetches
+/// `BODY[part]` and decodes it locally.
 ///
 /// # Errors
 ///
@@ -818,54 +1687,135 @@ pub async fn download_attachment(
     part_number: &str,
     encoding: &str,
 ) -> Result<Vec<u8>, MailServiceError> {
-    let uid_set = UidSet::single(uid);
+    if client.supports_binary()
+        && let Some(section) = parse_part_number(part_number)
+    {
+        return client
+            .fetch_binary(uid, &section)
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()));
+    }
+
+    download_attachment_body(client, uid, part_number, encoding).await
+}
+
+/// Fetch the complete RFC 5322 source of a message, e.g. to save it as an
+/// `.eml` file.
+///
+/// Uses `BODY.PEEK[]` so the message is not marked as read.
+///
+/// # Errors
+///
+/// Returns an error if the fetch fails or the message does not exist.
+pub async fn fetch_raw_message(
+    client: &mut SelectedClient,
+    uid: Uid,
+) -> Result<Vec<u8>, MailServiceError> {
+    fetch_body_section(client, uid, "", None)
+        .await?
+        .ok_or_else(|| MailServiceError::Operation("Message not found".to_string()))
+}
 
-    // Fetch the specific body part
+/// Parses a dotted part number such as `2.1` into its components.
+fn parse_part_number(part_number: &str) -> Option<Vec<u32>> {
+    part_number.split('.').map(|p| p.parse().ok()).collect()
+}
+
+/// Bytes of encoded body fetched per request when decoding a Base64 part.
+const ATTACHMENT_CHUNK: u32 = 1024 * 1024;
+
+/// Fetches `BODY[part]` and decodes its content transfer encoding locally.
+async fn download_attachment_body(
+    client: &mut SelectedClient,
+    uid: Uid,
+    part_number: &str,
+    encoding: &str,
+) -> Result<Vec<u8>, MailServiceError> {
+    if encoding.eq_ignore_ascii_case("base64") {
+        return download_base64_body(client, uid, part_number, ATTACHMENT_CHUNK).await;
+    }
+
+    let data = fetch_body_section(client, uid, part_number, None)
+        .await?
+        .ok_or_else(|| MailServiceError::Operation("Attachment not found".to_string()))?;
+    match encoding.to_lowercase().as_str() {
+        "quoted-printable" => {
+            let text = String::from_utf8_lossy(&data);
+            mailledger_mime::encoding::decode_quoted_printable(&text)
+                .map_err(|e| MailServiceError::Operation(e.to_string()))
+        }
+        _ => Ok(data), // 7bit, 8bit, binary - return as-is
+    }
+}
+
+/// Fetches a Base64 part `chunk_size` bytes at a time with partial
+/// `BODY[part]<offset.size>` fetches, decoding each chunk as it arrives so
+/// the encoded body is never held in full.
+async fn download_base64_body<S>(
+    client: &mut Client<S, Selected>,
+    uid: Uid,
+    part_number: &str,
+    chunk_size: u32,
+) -> Result<Vec<u8>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut base64 = mailledger_mime::encoding::Base64Decoder::new();
+    let mut decoded = Vec::new();
+    let mut offset = 0;
+    loop {
+        let chunk =
+            fetch_body_section(client, uid, part_number, Some((offset, chunk_size))).await?;
+        let Some(chunk) = chunk else {
+            if offset == 0 {
+                return Err(MailServiceError::Operation(
+                    "Attachment not found".to_string(),
+                ));
+            }
+            break;
+        };
+        base64
+            .decode(&chunk, &mut decoded)
+            .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+        if chunk.len() < chunk_size as usize {
+            break;
+        }
+        offset += chunk_size;
+    }
+    base64
+        .finish(&mut decoded)
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+    Ok(decoded)
+}
+
+/// Fetches `BODY.PEEK[part]`, or the `partial` byte range of it.
+async fn fetch_body_section<S>(
+    client: &mut Client<S, Selected>,
+    uid: Uid,
+    part_number: &str,
+    partial: Option<(u32, u32)>,
+) -> Result<Option<Vec<u8>>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let fetch_items = FetchItems::Items(vec![FetchAttribute::Body {
         section: Some(part_number.to_string()),
         peek: true,
-        partial: None,
+        partial,
     }]);
 
     let responses = client
-        .uid_fetch(&uid_set, fetch_items)
+        .uid_fetch(&UidSet::single(uid), fetch_items)
         .await
         .map_err(|e| MailServiceError::Operation(e.to_string()))?;
 
-    for (_seq_num, items) in responses {
-        for item in items {
-            if let FetchItem::Body {
-                data: Some(data), ..
-            } = item
-            {
-                // Decode based on encoding
-                let decoded = match encoding.to_lowercase().as_str() {
-                    "base64" => {
-                        let cleaned: String = data
-                            .iter()
-                            .map(|&b| b as char)
-                            .filter(|c| !c.is_whitespace())
-                            .collect();
-                        mailledger_mime::encoding::decode_base64(&cleaned)
-                            .map_err(|e| MailServiceError::Operation(e.to_string()))?
-                    }
-                    "quoted-printable" => {
-                        let text = String::from_utf8_lossy(&data);
-                        mailledger_mime::encoding::decode_quoted_printable(&text)
-                            .map_err(|e| MailServiceError::Operation(e.to_string()))?
-                            .into_bytes()
-                    }
-                    _ => data, // 7bit, 8bit, binary - return as-is
-                };
-
-                return Ok(decoded);
-            }
-        }
-    }
-
-    Err(MailServiceError::Operation(
-        "Attachment not found".to_string(),
-    ))
+    Ok(responses
+        .into_iter()
+        .flat_map(|(_seq_num, items)| items)
+        .find_map(|item| match item {
+            FetchItem::Body { data, .. } => data,
+            _ => None,
+        }))
 }
 
 /// Parse raw message body to extract text and HTML parts.
@@ -1041,63 +1991,315 @@ fn decode_part(body: &str, headers: &str) -> String {
                 .and_then(|bytes| String::from_utf8(bytes).map_err(Into::into))
                 .unwrap_or_else(|_| body.to_string())
         }
-        "quoted-printable" => mailledger_mime::encoding::decode_quoted_printable(body)
-            .unwrap_or_else(|_| body.to_string()),
-        _ => body.to_string(),
+        "quoted-printable" => mailledger_mime::encoding::decode_quoted_printable(body)
+            .and_then(|bytes| String::from_utf8(bytes).map_err(Into::into))
+            .unwrap_or_else(|_| body.to_string()),
+        _ => body.to_string(),
+    }
+}
+
+/// Start IDLE monitoring on a folder.
+///
+/// This function connects to the IMAP server, selects the specified folder,
+/// enters IDLE mode, waits for an event, and returns the event.
+///
+/// The caller should restart IDLE monitoring after handling the event.
+///
+/// # Errors
+///
+/// Returns an error if connection or IDLE fails.
+pub async fn idle_monitor(
+    account: &Account,
+    folder_path: &str,
+    timeout_secs: u64,
+) -> Result<IdleEvent, MailServiceError> {
+    use std::time::Duration;
+
+    // Connect and authenticate
+    let auth_client = connect_and_login(account).await?;
+
+    // Select the folder
+    let (mut selected_client, _status) = select_folder(auth_client, folder_path).await?;
+
+    // Enter IDLE mode
+    let mut idle_handle = selected_client
+        .idle()
+        .await
+        .map_err(|e| MailServiceError::Operation(format!("IDLE failed: {e}")))?;
+
+    // Wait for an event
+    let timeout_duration = Duration::from_secs(timeout_secs);
+    let event = idle_handle
+        .wait(timeout_duration)
+        .await
+        .map_err(|e| MailServiceError::Operation(format!("IDLE wait failed: {e}")))?;
+
+    // Exit IDLE mode
+    idle_handle
+        .done()
+        .await
+        .map_err(|e| MailServiceError::Operation(format!("IDLE done failed: {e}")))?;
+
+    Ok(event.into())
+}
+
+impl From<mailledger_imap::IdleEvent> for IdleEvent {
+    fn from(event: mailledger_imap::IdleEvent) -> Self {
+        use mailledger_imap::IdleEvent as ImapIdleEvent;
+
+        match event {
+            ImapIdleEvent::Exists(count) => Self::NewMail(count),
+            ImapIdleEvent::Expunge(_) => Self::Expunge,
+            ImapIdleEvent::Fetch { .. } => Self::FlagsChanged,
+            ImapIdleEvent::Recent(_) => Self::NewMail(0),
+            ImapIdleEvent::Timeout => Self::Timeout,
+        }
+    }
+}
+
+/// First wait before reconnecting a dropped IDLE session.
+const IDLE_RECONNECT_MIN: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Longest wait between IDLE reconnect attempts.
+const IDLE_RECONNECT_MAX: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Watch a folder over a persistent IDLE session.
+///
+/// Keeps one connection in IDLE for as long as the stream is polled,
+/// re-issuing IDLE as RFC 2177 requires. When the connection drops, yields
+/// [`IdleEvent::Disconnected`] and reconnects with exponential backoff.
+/// Dropping the stream closes the session.
+pub fn idle_subscription(
+    account: Account,
+    folder_path: String,
+) -> impl Stream<Item = IdleEvent> + Send + 'static {
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+    let driver = async move {
+        let mut delay = IDLE_RECONNECT_MIN;
+        loop {
+            let reason = match idle_session(&account, &folder_path, &tx, &mut delay).await {
+                Ok(()) if tx.is_closed() => return,
+                Ok(()) => "server ended IDLE".to_string(),
+                Err(e) => e.to_string(),
+            };
+            tracing::debug!("IDLE on {folder_path} stopped: {reason}");
+            if tx.send(IdleEvent::Disconnected(reason)).await.is_err() {
+                return;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(IDLE_RECONNECT_MAX);
+        }
+    };
+
+    let events = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (event, rx))
+    });
+    // The driver never yields; it only feeds the channel while polled.
+    let driver = futures_util::stream::once(driver).filter_map(|()| future::ready(None));
+    futures_util::stream::select(events, driver)
+}
+
+/// Runs one IDLE session, forwarding events until it ends.
+///
+/// Resets `delay` once IDLE is running. Returns `Ok` if the server ends
+/// IDLE or the receiver goes away.
+async fn idle_session(
+    account: &Account,
+    folder_path: &str,
+    tx: &tokio::sync::mpsc::Sender<IdleEvent>,
+    delay: &mut std::time::Duration,
+) -> Result<(), MailServiceError> {
+    let auth_client = connect_and_login(account).await?;
+    let (mut selected_client, _status) = select_folder(auth_client, folder_path).await?;
+    let mut idle_handle = selected_client
+        .idle()
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+    *delay = IDLE_RECONNECT_MIN;
+
+    let mut events = std::pin::pin!(idle_handle.events());
+    while let Some(event) = events.next().await {
+        let event =
+            event.map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+        if tx.send(event.into()).await.is_err() {
+            return Ok(());
+        }
     }
+    Ok(())
 }
 
-/// Start IDLE monitoring on a folder.
-///
-/// This function connects to the IMAP server, selects the specified folder,
-/// enters IDLE mode, waits for an event, and returns the event.
+/// Messages that changed in part of a folder since a known mod-sequence.
+#[derive(Debug, Clone, Default)]
+pub struct FolderChanges {
+    /// New messages and messages whose flags changed.
+    pub changed: Vec<MessageSummary>,
+    /// UIDs still in the checked range; any others were expunged.
+    pub present: Vec<Uid>,
+}
+
+/// Fetch only what changed in `uid_set` since `since_modseq` (RFC 7162).
 ///
-/// The caller should restart IDLE monitoring after handling the event.
+/// Uses `UID FETCH ... (CHANGEDSINCE n)` for new and re-flagged messages
+/// and `UID SEARCH` to find which UIDs survived. The server must support
+/// CONDSTORE; `since_modseq` comes from the `highest_mod_seq` of an
+/// earlier SELECT.
 ///
 /// # Errors
 ///
-/// Returns an error if connection or IDLE fails.
-pub async fn idle_monitor(
-    account: &Account,
-    folder_path: &str,
-    timeout_secs: u64,
-) -> Result<IdleEvent, MailServiceError> {
-    use mailledger_imap::IdleEvent as ImapIdleEvent;
-    use std::time::Duration;
-
-    // Connect and authenticate
-    let auth_client = connect_and_login(account).await?;
+/// Returns an error if either command fails.
+pub async fn fetch_changes<S>(
+    client: &mut Client<S, Selected>,
+    uid_set: &UidSet,
+    since_modseq: u64,
+) -> Result<FolderChanges, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let fetch_items = summary_fetch_items(client);
+    let changed = client
+        .fetch_changed_since(&uid_set.as_sequence_set(), fetch_items, since_modseq)
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?
+        .into_iter()
+        .filter_map(|(_seq_num, items)| summary_from_items(items))
+        .collect();
+    let present = client
+        .uid_search(&format!("UID {uid_set}"))
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
 
-    // Select the folder
-    let (mut selected_client, _status) = select_folder(auth_client, folder_path).await?;
+    Ok(FolderChanges { changed, present })
+}
 
-    // Enter IDLE mode
-    let mut idle_handle = selected_client
-        .idle()
+/// Fetch summaries of messages that arrived after `last_uid`.
+///
+/// Works without CONDSTORE. A server answers `n:*` with its newest message
+/// even when `n` is past the end, so only UIDs above `last_uid` are kept.
+///
+/// # Errors
+///
+/// Returns an error if the fetch fails.
+pub async fn fetch_new_since<S>(
+    client: &mut Client<S, Selected>,
+    last_uid: Uid,
+) -> Result<Vec<MessageSummary>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let Some(next) = last_uid.get().checked_add(1).and_then(Uid::new) else {
+        return Ok(Vec::new());
+    };
+    let fetch_items = summary_fetch_items(client);
+    Ok(client
+        .uid_fetch(&UidSet::RangeFrom(next), fetch_items)
         .await
-        .map_err(|e| MailServiceError::Operation(format!("IDLE failed: {e}")))?;
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?
+        .into_iter()
+        .filter_map(|(_seq_num, items)| summary_from_items(items))
+        .filter(|summary| summary.uid > last_uid)
+        .collect())
+}
 
-    // Wait for an event
-    let timeout_duration = Duration::from_secs(timeout_secs);
-    let event = idle_handle
-        .wait(timeout_duration)
+/// Fetch the current flags of already-loaded messages.
+///
+/// Lets a flag change seen during IDLE be patched into the list without
+/// refetching envelopes. Messages that no longer exist are left out.
+///
+/// # Errors
+///
+/// Returns an error if the fetch fails.
+pub async fn fetch_flag_updates<S>(
+    client: &mut Client<S, Selected>,
+    uids: &UidSet,
+) -> Result<Vec<(Uid, Flags)>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let fetch_items = FetchItems::Items(vec![FetchAttribute::Uid, FetchAttribute::Flags]);
+    Ok(client
+        .uid_fetch(uids, fetch_items)
         .await
-        .map_err(|e| MailServiceError::Operation(format!("IDLE wait failed: {e}")))?;
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?
+        .into_iter()
+        .filter_map(|(_seq_num, items)| {
+            let mut uid = None;
+            let mut flags = None;
+            for item in items {
+                match item {
+                    FetchItem::Uid(u) => uid = Some(u),
+                    FetchItem::Flags(f) => flags = Some(f),
+                    _ => {}
+                }
+            }
+            Some((uid?, flags?))
+        })
+        .collect())
+}
 
-    // Exit IDLE mode
-    idle_handle
-        .done()
-        .await
-        .map_err(|e| MailServiceError::Operation(format!("IDLE done failed: {e}")))?;
+/// Fetch `items` for `uids`, at most `batch_size` UIDs per command.
+///
+/// The UIDs are sorted and each batch is sent as a compact set of ranges,
+/// one FETCH after another, with the responses concatenated in order. This
+/// keeps a large fetch under the command length servers accept.
+///
+/// # Errors
+///
+/// Returns an error if any of the fetches fails.
+pub async fn fetch_batched<S>(
+    client: &mut Client<S, Selected>,
+    uids: &[Uid],
+    items: FetchItems,
+    batch_size: usize,
+) -> Result<Vec<(SeqNum, Vec<FetchItem>)>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut responses = Vec::new();
+    for batch in uid_batches(uids, batch_size) {
+        responses.extend(
+            client
+                .uid_fetch(&batch, items.clone())
+                .await
+                .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?,
+        );
+    }
+    Ok(responses)
+}
 
-    // Convert to our event type
-    Ok(match event {
-        ImapIdleEvent::Exists(count) => IdleEvent::NewMail(count),
-        ImapIdleEvent::Expunge(_) => IdleEvent::Expunge,
-        ImapIdleEvent::Fetch { .. } => IdleEvent::FlagsChanged,
-        ImapIdleEvent::Recent(_) => IdleEvent::NewMail(0),
-        ImapIdleEvent::Timeout => IdleEvent::Timeout,
-    })
+/// Fetch summaries of the given messages, batched by the server's
+/// [`max_fetch_uids`](mailledger_imap::ServerQuirks::max_fetch_uids).
+///
+/// # Errors
+///
+/// Returns an error if any of the fetches fails.
+pub async fn fetch_messages_by_uid<S>(
+    client: &mut Client<S, Selected>,
+    uids: &[Uid],
+) -> Result<Vec<MessageSummary>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let fetch_items = summary_fetch_items(client);
+    let batch_size = client.quirks().max_fetch_uids;
+    Ok(fetch_batched(client, uids, fetch_items, batch_size)
+        .await?
+        .into_iter()
+        .filter_map(|(_seq_num, items)| summary_from_items(items))
+        .collect())
+}
+
+/// Splits `uids` into sets of at most `batch_size` UIDs, each written as
+/// ranges of consecutive UIDs. A `batch_size` of zero is treated as one.
+fn uid_batches(uids: &[Uid], batch_size: usize) -> Vec<UidSet> {
+    let mut sorted = uids.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    sorted
+        .chunks(batch_size.max(1))
+        .filter_map(UidSet::from_uids)
+        .collect()
 }
 
 #[cfg(test)]
@@ -1113,6 +2315,33 @@ pub async fn idle_monitor(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_retrying_operations_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let _ = |account: &Account, client: &mut SelectedClient, uids: &UidSet| {
+            assert_send(&connect_and_login(account));
+            assert_send(&fetch_messages(client, uids));
+            assert_send(&fetch_messages_stream(client, uids));
+        };
+    }
+
+    #[test]
+    fn test_transient_imap_errors_are_retriable() {
+        let bye = ImapError::Bye("shutting down".to_string());
+        let err = MailServiceError::from_imap(&bye, MailServiceError::Operation);
+        assert!(matches!(err, MailServiceError::Transient(_)));
+        assert!(err.is_retriable());
+
+        let no = ImapError::from_tagged(
+            mailledger_imap::types::Status::No,
+            None,
+            "no such message".to_string(),
+        );
+        let err = MailServiceError::from_imap(&no, MailServiceError::Operation);
+        assert!(matches!(err, MailServiceError::Operation(_)));
+        assert!(!err.is_retriable());
+    }
+
     // ===== FolderType::from_name tests =====
 
     mod folder_type {
@@ -1165,6 +2394,23 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_from_special_use() {
+            assert_eq!(
+                FolderType::from_special_use(&MailboxAttribute::Sent),
+                Some(FolderType::Sent)
+            );
+            assert_eq!(
+                FolderType::from_special_use(&MailboxAttribute::Junk),
+                Some(FolderType::Spam)
+            );
+            assert_eq!(
+                FolderType::from_special_use(&MailboxAttribute::Archive),
+                Some(FolderType::Archive)
+            );
+            assert_eq!(FolderType::from_special_use(&MailboxAttribute::All), None);
+        }
+
         #[test]
         fn test_regular() {
             assert_eq!(FolderType::from_name("Work"), FolderType::Regular);
@@ -1173,6 +2419,138 @@ mod tests {
         }
     }
 
+    // ===== move_messages tests =====
+
+    mod move_messages_tests {
+        use mailledger_imap::types::UidValidity;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+        use super::*;
+
+        /// Serves one session, answering moves and copies with COPYUID, and
+        /// returns the commands it received.
+        async fn serve(stream: DuplexStream, capabilities: &str) -> Vec<String> {
+            let (read, mut write) = tokio::io::split(stream);
+            let mut lines = BufReader::new(read).lines();
+            let greeting = format!("* OK [CAPABILITY IMAP4rev1 {capabilities}] ready\r\n");
+            write.write_all(greeting.as_bytes()).await.unwrap();
+            let mut commands = Vec::new();
+            let mut created = false;
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let reply = if command == "CREATE Receipts" {
+                    created = true;
+                    format!("{tag} OK done\r\n")
+                } else if command.ends_with(" Receipts") && !created {
+                    format!("{tag} NO [TRYCREATE] Mailbox doesn't exist: Receipts\r\n")
+                } else if command.starts_with("UID MOVE") {
+                    format!(
+                        "* OK [COPYUID 9 1:2 10:11] moved\r\n* 1 EXPUNGE\r\n* 1 EXPUNGE\r\n{tag} OK done\r\n"
+                    )
+                } else if command.starts_with("UID COPY") {
+                    format!("{tag} OK [COPYUID 9 1:2 10:11] done\r\n")
+                } else if command.starts_with("SELECT") {
+                    format!("* 2 EXISTS\r\n{tag} OK [READ-WRITE] done\r\n")
+                } else {
+                    format!("{tag} OK done\r\n")
+                };
+                write.write_all(reply.as_bytes()).await.unwrap();
+                commands.push(command.to_string());
+            }
+            commands
+        }
+
+        async fn move_to(
+            capabilities: &'static str,
+            destination: &str,
+            create_if_missing: bool,
+        ) -> (Result<Option<CopyUid>, MailServiceError>, Vec<String>) {
+            let (local, remote) = tokio::io::duplex(4096);
+            let server = tokio::spawn(serve(remote, capabilities));
+            let client = Client::from_stream(local).await.unwrap();
+            let client = client.login("user", "pass").await.unwrap();
+            let (mut client, _) = client.select("INBOX").await.unwrap();
+
+            let uids = UidSet::range(Uid::new(1).unwrap(), Uid::new(2).unwrap());
+            let result = move_messages(&mut client, &uids, destination, create_if_missing).await;
+            drop(client);
+
+            let commands = server.await.unwrap();
+            (result, commands.into_iter().skip(2).collect())
+        }
+
+        async fn move_with(capabilities: &'static str) -> (Option<CopyUid>, Vec<String>) {
+            let (result, commands) = move_to(capabilities, "Archive", false).await;
+            (result.unwrap(), commands)
+        }
+
+        fn expected_copy_uid() -> CopyUid {
+            CopyUid {
+                validity: UidValidity::new(9).unwrap(),
+                source: UidSet::range(Uid::new(1).unwrap(), Uid::new(2).unwrap()),
+                dest: UidSet::range(Uid::new(10).unwrap(), Uid::new(11).unwrap()),
+            }
+        }
+
+        #[tokio::test]
+        async fn uses_move_when_supported() {
+            let (copied, commands) = move_with("MOVE UIDPLUS").await;
+            assert_eq!(copied, Some(expected_copy_uid()));
+            assert_eq!(commands, vec!["UID MOVE 1:2 Archive"]);
+        }
+
+        #[tokio::test]
+        async fn falls_back_to_copy_and_uid_expunge() {
+            let (copied, commands) = move_with("UIDPLUS").await;
+            assert_eq!(copied, Some(expected_copy_uid()));
+            assert_eq!(
+                commands,
+                vec![
+                    "UID COPY 1:2 Archive",
+                    "UID STORE 1:2 +FLAGS (\\Deleted)",
+                    "UID EXPUNGE 1:2",
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn falls_back_to_expunge_without_uidplus() {
+            let (_, commands) = move_with("LITERAL+").await;
+            assert_eq!(
+                commands,
+                vec![
+                    "UID COPY 1:2 Archive",
+                    "UID STORE 1:2 +FLAGS (\\Deleted)",
+                    "EXPUNGE",
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn creates_missing_destination_and_retries_copy() {
+            let (result, commands) = move_to("UIDPLUS", "Receipts", true).await;
+            assert_eq!(result.unwrap(), Some(expected_copy_uid()));
+            assert_eq!(
+                commands,
+                vec![
+                    "UID COPY 1:2 Receipts",
+                    "CREATE Receipts",
+                    "SUBSCRIBE Receipts",
+                    "UID COPY 1:2 Receipts",
+                    "UID STORE 1:2 +FLAGS (\\Deleted)",
+                    "UID EXPUNGE 1:2",
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn missing_destination_fails_unless_asked_to_create() {
+            let (result, commands) = move_to("MOVE", "Receipts", false).await;
+            assert!(matches!(result, Err(MailServiceError::Operation(_))));
+            assert_eq!(commands, vec!["UID MOVE 1:2 Receipts"]);
+        }
+    }
+
     // ===== format_address tests =====
 
     mod format_address_tests {
@@ -1194,6 +2572,12 @@ mod tests {
             assert_eq!(format_address(&addr), "John Doe");
         }
 
+        #[test]
+        fn test_encoded_name_is_decoded() {
+            let addr = make_address(Some("=?UTF-8?Q?Andr=C3=A9?="), Some("a"), Some("b.com"));
+            assert_eq!(format_address(&addr), "André");
+        }
+
         #[test]
         fn test_without_name_full_email() {
             let addr = make_address(None, Some("jane"), Some("example.org"));
@@ -1257,6 +2641,21 @@ mod tests {
 
     // ===== split_headers_body tests =====
 
+    mod parse_part_number_tests {
+        use super::*;
+
+        #[test]
+        fn test_nested_part() {
+            assert_eq!(parse_part_number("2.1"), Some(vec![2, 1]));
+        }
+
+        #[test]
+        fn test_invalid_part() {
+            assert_eq!(parse_part_number("2.TEXT"), None);
+            assert_eq!(parse_part_number(""), None);
+        }
+    }
+
     mod split_headers_body_tests {
         use super::*;
 
@@ -1645,6 +3044,106 @@ mod tests {
         }
     }
 
+    mod expunge_guard {
+        use super::*;
+
+        fn uids(values: &[u32]) -> Vec<Uid> {
+            values.iter().map(|&v| Uid::new(v).unwrap()).collect()
+        }
+
+        #[test]
+        fn test_preview_reports_count() {
+            let mut guard = ExpungeGuard::new();
+            let preview = guard.register(
+                "Trash".to_string(),
+                ExpungeScope::EmptyFolder,
+                uids(&[3, 1, 2]),
+            );
+            assert_eq!(preview.count, 3);
+            assert_eq!(preview.folder, "Trash");
+            assert_eq!(preview.scope, ExpungeScope::EmptyFolder);
+            assert!(guard.has_pending());
+        }
+
+        #[test]
+        fn test_confirm_with_matching_token() {
+            let mut guard = ExpungeGuard::new();
+            let preview = guard.register("INBOX".to_string(), ExpungeScope::Deleted, uids(&[7, 5]));
+            let pending = guard.take(preview.token, "INBOX").unwrap();
+            assert_eq!(pending.uids, uids(&[5, 7]));
+            assert!(!guard.has_pending());
+        }
+
+        #[test]
+        fn test_confirm_without_prepare_rejected() {
+            let mut guard = ExpungeGuard::new();
+            let result = guard.take(ExpungeToken(1), "INBOX");
+            assert!(matches!(
+                result,
+                Err(MailServiceError::ConfirmationRejected(_))
+            ));
+        }
+
+        #[test]
+        fn test_stale_token_rejected_and_keeps_pending() {
+            let mut guard = ExpungeGuard::new();
+            let first = guard.register("INBOX".to_string(), ExpungeScope::Deleted, uids(&[1]));
+            let second = guard.register("INBOX".to_string(), ExpungeScope::Deleted, uids(&[1, 2]));
+            assert_ne!(first.token, second.token);
+
+            let result = guard.take(first.token, "INBOX");
+            assert!(matches!(
+                result,
+                Err(MailServiceError::ConfirmationRejected(_))
+            ));
+            assert!(guard.has_pending());
+            assert_eq!(guard.take(second.token, "INBOX").unwrap().uids.len(), 2);
+        }
+
+        #[test]
+        fn test_token_reused_after_confirm_rejected() {
+            let mut guard = ExpungeGuard::new();
+            let preview = guard.register("INBOX".to_string(), ExpungeScope::Deleted, uids(&[1]));
+            guard.take(preview.token, "INBOX").unwrap();
+            assert!(guard.take(preview.token, "INBOX").is_err());
+        }
+
+        #[test]
+        fn test_wrong_folder_rejected() {
+            let mut guard = ExpungeGuard::new();
+            let preview =
+                guard.register("Trash".to_string(), ExpungeScope::EmptyFolder, uids(&[1]));
+            let result = guard.take(preview.token, "INBOX");
+            assert!(matches!(
+                result,
+                Err(MailServiceError::ConfirmationRejected(_))
+            ));
+            assert!(!guard.has_pending());
+        }
+
+        #[test]
+        fn test_cancel_clears_pending() {
+            let mut guard = ExpungeGuard::new();
+            let preview = guard.register("INBOX".to_string(), ExpungeScope::Deleted, uids(&[1]));
+            guard.cancel();
+            assert!(guard.take(preview.token, "INBOX").is_err());
+        }
+
+        #[test]
+        fn test_uid_set_from() {
+            assert_eq!(uid_set_from(&[]), None);
+            assert_eq!(
+                uid_set_from(&uids(&[4])),
+                Some(UidSet::single(Uid::new(4).unwrap()))
+            );
+            assert_eq!(
+                uid_set_from(&uids(&[2, 1])),
+                Some(UidSet::range(Uid::new(1).unwrap(), Uid::new(2).unwrap()))
+            );
+            assert!(matches!(uid_set_from(&uids(&[1, 3])), Some(UidSet::Set(v)) if v.len() == 2));
+        }
+    }
+
     mod search {
         use super::*;
 
@@ -1679,5 +3178,519 @@ mod tests {
             let escaped = escape_search_string(email);
             assert_eq!(escaped, email);
         }
+
+        #[test]
+        fn test_to_search_criteria() {
+            let criteria = SearchCriteria {
+                from: Some("alice@example.com".to_string()),
+                unread: true,
+                since: Some("01-Feb-2024".to_string()),
+                before: Some("not a date".to_string()),
+                ..SearchCriteria::default()
+            };
+            assert_eq!(
+                criteria.to_search_criteria(),
+                ImapSearchCriteria::And(vec![
+                    ImapSearchCriteria::From("alice@example.com".to_string()),
+                    ImapSearchCriteria::Unseen,
+                    ImapSearchCriteria::Since("01-Feb-2024".to_string()),
+                ])
+            );
+            assert_eq!(
+                SearchCriteria::default().to_search_criteria(),
+                ImapSearchCriteria::All
+            );
+        }
+    }
+
+    mod content_tests {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+        use super::*;
+
+        const MIXED: &str = "((\"TEXT\" \"PLAIN\" (\"CHARSET\" \"us-ascii\") NIL NIL \"7BIT\" 15 1 NIL NIL NIL NIL)\
+             (\"APPLICATION\" \"PDF\" (\"NAME\" \"report.pdf\") NIL NIL \"BASE64\" 8 NIL \
+             (\"ATTACHMENT\" (\"FILENAME\" \"report.pdf\")) NIL NIL)\
+             (\"IMAGE\" \"PNG\" (\"NAME\" \"logo.png\") \"<logo@example.com>\" NIL \"BASE64\" 4216 NIL \
+             (\"INLINE\" (\"FILENAME\" \"logo.png\")) NIL NIL)\
+             (\"TEXT\" \"CSV\" (\"NAME\" \"totals.csv\") NIL NIL \"7BIT\" 30 2 NIL NIL NIL NIL) \
+             \"MIXED\" (\"BOUNDARY\" \"b1\") NIL NIL NIL)";
+
+        const ALTERNATIVE: &str = "(((\"TEXT\" \"PLAIN\" (\"CHARSET\" \"utf-8\") NIL NIL \"QUOTED-PRINTABLE\" 20 1 NIL NIL NIL NIL)\
+             (\"TEXT\" \"HTML\" (\"CHARSET\" \"iso-8859-1\") NIL NIL \"BASE64\" 20 1 NIL NIL NIL NIL) \
+             \"ALTERNATIVE\" (\"BOUNDARY\" \"alt\") NIL NIL NIL)\
+             (\"APPLICATION\" \"PDF\" (\"NAME\" \"report.pdf\") NIL NIL \"BASE64\" 8 NIL \
+             (\"ATTACHMENT\" (\"FILENAME\" \"report.pdf\")) NIL NIL) \
+             \"MIXED\" (\"BOUNDARY\" \"b1\") NIL NIL NIL)";
+
+        /// Answers fetches for UID 7: its header and `structure`, then each
+        /// requested part from `parts`. Returns the commands it received.
+        async fn serve(
+            stream: DuplexStream,
+            structure: &'static str,
+            parts: &'static [(&'static str, &'static str)],
+        ) -> Vec<String> {
+            const HEADER: &str = "From: alice@example.com\r\nSubject: Report\r\n\r\n";
+            let (read, mut write) = tokio::io::split(stream);
+            let mut lines = BufReader::new(read).lines();
+            write
+                .write_all(b"* OK [CAPABILITY IMAP4rev1] ready\r\n")
+                .await
+                .unwrap();
+            let mut commands = Vec::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let mut reply = Vec::new();
+                if command.contains("BODYSTRUCTURE") {
+                    reply.extend_from_slice(
+                        format!(
+                            "* 1 FETCH (UID 7 ENVELOPE (NIL \"Report\" NIL NIL NIL NIL NIL NIL NIL NIL) \
+                             BODYSTRUCTURE {structure} BODY[HEADER] {{{}}}\r\n{HEADER})\r\n",
+                            HEADER.len()
+                        )
+                        .as_bytes(),
+                    );
+                } else if let Some((_, rest)) = command.split_once("BODY.PEEK[") {
+                    let (section, _) = rest.split_once(']').unwrap();
+                    if let Some((_, data)) = parts.iter().find(|(number, _)| *number == section) {
+                        reply.extend_from_slice(
+                            format!(
+                                "* 1 FETCH (UID 7 BODY[{section}] {{{}}}\r\n{data})\r\n",
+                                data.len()
+                            )
+                            .as_bytes(),
+                        );
+                    }
+                } else if command.starts_with("SELECT") {
+                    reply.extend_from_slice(b"* 1 EXISTS\r\n");
+                }
+                reply.extend_from_slice(format!("{tag} OK done\r\n").as_bytes());
+                write.write_all(&reply).await.unwrap();
+                commands.push(command.to_string());
+            }
+            commands
+        }
+
+        async fn fetch(
+            structure: &'static str,
+            parts: &'static [(&'static str, &'static str)],
+            preferred: PreferredBody,
+        ) -> (MessageContent, Vec<String>) {
+            let (local, remote) = tokio::io::duplex(8192);
+            let server = tokio::spawn(serve(remote, structure, parts));
+            let client = Client::from_stream(local).await.unwrap();
+            let client = client.login("user", "pass").await.unwrap();
+            let (mut client, _) = client.select("INBOX").await.unwrap();
+
+            let content = fetch_message_content(&mut client, Uid::new(7).unwrap(), preferred)
+                .await
+                .unwrap()
+                .unwrap();
+            drop(client);
+            (content, server.await.unwrap())
+        }
+
+        #[tokio::test]
+        async fn lists_attachments_from_body_structure() {
+            let (content, commands) =
+                fetch(MIXED, &[("1", "See attached.\r\n")], PreferredBody::Both).await;
+
+            assert_eq!(content.subject, "Report");
+            assert_eq!(content.body_text.as_deref(), Some("See attached.\r\n"));
+            // The inline logo is part of the content, not an attachment
+            assert_eq!(
+                content.attachments,
+                vec![
+                    Attachment {
+                        filename: "report.pdf".to_string(),
+                        mime_type: "application/pdf".to_string(),
+                        size: 8,
+                        part_number: "2".to_string(),
+                        encoding: "base64".to_string(),
+                    },
+                    Attachment {
+                        filename: "totals.csv".to_string(),
+                        mime_type: "text/csv".to_string(),
+                        size: 30,
+                        part_number: "4".to_string(),
+                        encoding: "7bit".to_string(),
+                    },
+                ]
+            );
+
+            // Only the text part is downloaded; attachments are left for
+            // download_attachment
+            let fetches: Vec<_> = commands
+                .iter()
+                .filter(|c| c.contains("BODY.PEEK["))
+                .collect();
+            assert_eq!(fetches.len(), 2);
+            assert!(fetches[0].contains("BODYSTRUCTURE"));
+            assert!(fetches[1].contains("BODY.PEEK[1]"));
+        }
+
+        #[tokio::test]
+        async fn fetches_only_the_preferred_alternative() {
+            let (content, commands) = fetch(
+                ALTERNATIVE,
+                &[
+                    ("1.1", "Caf=C3=A9 menu\r\n"),
+                    // "<p>Caf\xe9</p>" in ISO-8859-1
+                    ("1.2", "PHA+Q2Fm6TwvcD4=\r\n"),
+                ],
+                PreferredBody::Html,
+            )
+            .await;
+
+            assert_eq!(content.body_html.as_deref(), Some("<p>Café</p>"));
+            assert_eq!(content.body_text, None);
+            assert!(commands.iter().any(|c| c.contains("BODY.PEEK[1.2]")));
+            assert!(!commands.iter().any(|c| c.contains("BODY.PEEK[1.1]")));
+        }
+
+        #[tokio::test]
+        async fn falls_back_to_the_other_body() {
+            let (content, _) = fetch(
+                "(\"TEXT\" \"HTML\" (\"CHARSET\" \"utf-8\") NIL NIL \"7BIT\" 12 1 NIL NIL NIL NIL)",
+                &[("1", "<p>Hi</p>")],
+                PreferredBody::PlainText,
+            )
+            .await;
+
+            assert_eq!(content.body_html.as_deref(), Some("<p>Hi</p>"));
+            assert_eq!(content.body_text, None);
+        }
+
+        #[tokio::test]
+        async fn both_fetches_text_and_html() {
+            let (content, _) = fetch(
+                ALTERNATIVE,
+                &[
+                    ("1.1", "Caf=C3=A9 menu\r\n"),
+                    ("1.2", "PHA+Q2Fm6TwvcD4=\r\n"),
+                ],
+                PreferredBody::Both,
+            )
+            .await;
+
+            assert_eq!(content.body_text.as_deref(), Some("Café menu\r\n"));
+            assert_eq!(content.body_html.as_deref(), Some("<p>Café</p>"));
+        }
+    }
+
+    mod download_tests {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+        use super::*;
+
+        /// Serves `body` as part 2 of UID 5, answering partial fetches with
+        /// the requested byte range, and returns the commands it received.
+        async fn serve(stream: DuplexStream, body: &'static [u8]) -> Vec<String> {
+            let (read, mut write) = tokio::io::split(stream);
+            let mut lines = BufReader::new(read).lines();
+            write
+                .write_all(b"* OK [CAPABILITY IMAP4rev1 LITERAL+] ready\r\n")
+                .await
+                .unwrap();
+            let mut commands = Vec::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let mut reply = Vec::new();
+                if let Some((_, range)) = command.split_once("BODY.PEEK[2]<") {
+                    let (range, _) = range.split_once('>').unwrap();
+                    let (offset, size) = range.split_once('.').unwrap();
+                    let offset = offset.parse::<usize>().unwrap().min(body.len());
+                    let end = (offset + size.parse::<usize>().unwrap()).min(body.len());
+                    let data = &body[offset..end];
+                    reply.extend_from_slice(
+                        format!("* 1 FETCH (UID 5 BODY[2]<{offset}> {{{}}}\r\n", data.len())
+                            .as_bytes(),
+                    );
+                    reply.extend_from_slice(data);
+                    reply.extend_from_slice(b")\r\n");
+                } else if command.starts_with("SELECT") {
+                    reply.extend_from_slice(b"* 1 EXISTS\r\n");
+                }
+                reply.extend_from_slice(format!("{tag} OK done\r\n").as_bytes());
+                write.write_all(&reply).await.unwrap();
+                commands.push(command.to_string());
+            }
+            commands
+        }
+
+        #[tokio::test]
+        async fn decodes_base64_across_partial_fetches() {
+            // "Hello, World!" wrapped so chunk boundaries split quanta and CRLFs
+            const BODY: &[u8] = b"SGVsbG8s\r\nIFdvcmxk\r\nIQ==\r\n";
+            let (local, remote) = tokio::io::duplex(4096);
+            let server = tokio::spawn(serve(remote, BODY));
+            let client = Client::from_stream(local).await.unwrap();
+            let client = client.login("user", "pass").await.unwrap();
+            let (mut client, _) = client.select("INBOX").await.unwrap();
+
+            let data = download_base64_body(&mut client, Uid::new(5).unwrap(), "2", 7)
+                .await
+                .unwrap();
+            drop(client);
+
+            assert_eq!(data, b"Hello, World!");
+            let fetches = server
+                .await
+                .unwrap()
+                .into_iter()
+                .skip(2)
+                .collect::<Vec<_>>();
+            assert_eq!(fetches.len(), BODY.len().div_ceil(7));
+            assert!(fetches[1].contains("BODY.PEEK[2]<7.7>"));
+        }
+    }
+
+    mod auth_tests {
+        use mailledger_oauth::Token;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+        use super::*;
+        use crate::account::OAuthProvider;
+
+        /// Greets with `capabilities` and accepts any command, returning the
+        /// first one.
+        async fn serve(stream: DuplexStream, capabilities: &'static str) -> String {
+            let (read, mut write) = tokio::io::split(stream);
+            let mut lines = BufReader::new(read).lines();
+            write
+                .write_all(format!("* OK [CAPABILITY {capabilities}] ready\r\n").as_bytes())
+                .await
+                .unwrap();
+            let line = lines.next_line().await.unwrap().unwrap();
+            let (tag, command) = line.split_once(' ').unwrap();
+            write
+                .write_all(format!("{tag} OK signed in\r\n").as_bytes())
+                .await
+                .unwrap();
+            command.to_string()
+        }
+
+        async fn sign_in(capabilities: &'static str, account: &Account) -> String {
+            let (local, remote) = tokio::io::duplex(4096);
+            let server = tokio::spawn(serve(remote, capabilities));
+            let client = Client::from_stream(local).await.unwrap();
+            authenticate(client, account).await.unwrap();
+            server.await.unwrap()
+        }
+
+        fn oauth_account() -> Account {
+            let mut account = Account::with_email("user@gmail.com");
+            account.imap.auth = AuthMethod::OAuth {
+                provider: OAuthProvider::Google,
+                token: Token::new("ya29.token", "Bearer"),
+                client_id: "client".to_string(),
+            };
+            account
+        }
+
+        #[tokio::test]
+        async fn oauth_sends_xoauth2_token() {
+            let command = sign_in("IMAP4rev1 SASL-IR AUTH=XOAUTH2", &oauth_account()).await;
+            assert_eq!(
+                command,
+                format!(
+                    "AUTHENTICATE XOAUTH2 {}",
+                    mailledger_oauth::sasl::xoauth2_response("user@gmail.com", "ya29.token")
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn oauth_prefers_oauthbearer() {
+            let command = sign_in(
+                "IMAP4rev1 SASL-IR AUTH=XOAUTH2 AUTH=OAUTHBEARER",
+                &oauth_account(),
+            )
+            .await;
+            assert_eq!(
+                command,
+                format!(
+                    "AUTHENTICATE OAUTHBEARER {}",
+                    mailledger_oauth::sasl::oauthbearer_response("user@gmail.com", "ya29.token")
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn password_uses_login() {
+            let mut account = Account::with_email("user@example.com");
+            account.imap.auth = AuthMethod::Password("secret".to_string());
+            let command = sign_in("IMAP4rev1 AUTH=PLAIN", &account).await;
+            assert_eq!(command, "LOGIN user@example.com secret");
+        }
+    }
+
+    mod changes_tests {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+        use super::*;
+
+        /// Reports UID 12 as new or re-flagged, UIDs 10 and 12 as present,
+        /// and answers a bare flags fetch with both messages' flags.
+        async fn serve(stream: DuplexStream) -> Vec<String> {
+            let (read, mut write) = tokio::io::split(stream);
+            let mut lines = BufReader::new(read).lines();
+            write
+                .write_all(b"* OK [CAPABILITY IMAP4rev1 CONDSTORE] ready\r\n")
+                .await
+                .unwrap();
+            let mut commands = Vec::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let reply = if command.ends_with("(UID FLAGS)") {
+                    format!(
+                        "* 1 FETCH (UID 10 FLAGS (\\Flagged))\r\n\
+                         * 2 FETCH (UID 12 FLAGS ())\r\n{tag} OK done\r\n"
+                    )
+                } else if command.starts_with("UID FETCH") {
+                    format!(
+                        "* 2 FETCH (UID 12 FLAGS (\\Seen) MODSEQ (90) ENVELOPE \
+                         (NIL \"Hi\" NIL NIL NIL NIL NIL NIL NIL NIL))\r\n{tag} OK done\r\n"
+                    )
+                } else if command.starts_with("UID SEARCH") {
+                    format!("* SEARCH 10 12\r\n{tag} OK done\r\n")
+                } else if command.starts_with("SELECT") {
+                    format!("* 2 EXISTS\r\n* OK [HIGHESTMODSEQ 90] ok\r\n{tag} OK done\r\n")
+                } else {
+                    format!("{tag} OK done\r\n")
+                };
+                write.write_all(reply.as_bytes()).await.unwrap();
+                commands.push(command.to_string());
+            }
+            commands
+        }
+
+        #[tokio::test]
+        async fn fetches_only_changes_since_modseq() {
+            let (local, remote) = tokio::io::duplex(4096);
+            let server = tokio::spawn(serve(remote));
+            let client = Client::from_stream(local).await.unwrap();
+            let client = client.login("user", "pass").await.unwrap();
+            let (mut client, status) = client.select("INBOX").await.unwrap();
+            assert_eq!(status.highest_mod_seq, Some(90));
+
+            let uids = UidSet::RangeFrom(Uid::new(10).unwrap());
+            let changes = fetch_changes(&mut client, &uids, 75).await.unwrap();
+            drop(client);
+
+            assert_eq!(changes.changed.len(), 1);
+            assert_eq!(changes.changed[0].uid, Uid::new(12).unwrap());
+            assert!(changes.changed[0].is_read);
+            assert_eq!(
+                changes.present,
+                [Uid::new(10).unwrap(), Uid::new(12).unwrap()]
+            );
+
+            let commands = server.await.unwrap();
+            assert!(commands[2].starts_with("UID FETCH 10:* "));
+            assert!(commands[2].ends_with("(CHANGEDSINCE 75)"));
+            assert_eq!(commands[3], "UID SEARCH UID 10:*");
+        }
+
+        async fn selected() -> (
+            Client<DuplexStream, Selected>,
+            tokio::task::JoinHandle<Vec<String>>,
+        ) {
+            let (local, remote) = tokio::io::duplex(4096);
+            let server = tokio::spawn(serve(remote));
+            let client = Client::from_stream(local).await.unwrap();
+            let client = client.login("user", "pass").await.unwrap();
+            let (client, _) = client.select("INBOX").await.unwrap();
+            (client, server)
+        }
+
+        #[tokio::test]
+        async fn new_mail_fetches_only_the_new_message() {
+            let (mut client, server) = selected().await;
+
+            let new = fetch_new_since(&mut client, Uid::new(11).unwrap())
+                .await
+                .unwrap();
+            // The server answers 13:* with its newest message, UID 12
+            let none = fetch_new_since(&mut client, Uid::new(12).unwrap())
+                .await
+                .unwrap();
+            drop(client);
+
+            assert_eq!(new.len(), 1);
+            assert_eq!(new[0].uid, Uid::new(12).unwrap());
+            assert_eq!(new[0].subject, "Hi");
+            assert!(none.is_empty());
+
+            let commands = server.await.unwrap();
+            assert!(commands[2].starts_with("UID FETCH 12:* "));
+            assert!(commands[3].starts_with("UID FETCH 13:* "));
+        }
+
+        #[tokio::test]
+        async fn flag_change_fetches_flags_only() {
+            let (mut client, server) = selected().await;
+
+            let uids = UidSet::parse("10,12").unwrap();
+            let updates = fetch_flag_updates(&mut client, &uids).await.unwrap();
+            drop(client);
+
+            assert_eq!(updates.len(), 2);
+            assert_eq!(updates[0].0, Uid::new(10).unwrap());
+            assert!(updates[0].1.contains(&Flag::Flagged));
+            assert_eq!(updates[1].0, Uid::new(12).unwrap());
+            assert!(updates[1].1.is_empty());
+
+            let commands = server.await.unwrap();
+            assert_eq!(commands[2], "UID FETCH 10,12 (UID FLAGS)");
+        }
+
+        #[test]
+        fn uid_batches_split_at_the_boundary() {
+            let uids: Vec<Uid> = [7, 1, 2, 3, 4, 5, 9, 3]
+                .into_iter()
+                .filter_map(Uid::new)
+                .collect();
+
+            let batches: Vec<String> = uid_batches(&uids, 3)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            assert_eq!(batches, ["1:3", "4:5,7", "9"]);
+
+            // Exactly one batch's worth stays a single command
+            let batches = uid_batches(&uids[1..4], 3);
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches[0].to_string(), "1:3");
+
+            assert!(uid_batches(&[], 3).is_empty());
+            assert_eq!(uid_batches(&uids[..2], 0).len(), 2);
+        }
+
+        #[tokio::test]
+        async fn batched_fetch_sends_one_command_per_batch() {
+            let (mut client, server) = selected().await;
+
+            let uids: Vec<Uid> = [10, 11, 12].into_iter().filter_map(Uid::new).collect();
+            let items = FetchItems::Items(vec![FetchAttribute::Uid, FetchAttribute::Flags]);
+            let responses = fetch_batched(&mut client, &uids, items, 2).await.unwrap();
+            drop(client);
+
+            // Each batch's responses are kept, in order
+            assert_eq!(responses.len(), 4);
+            let commands = server.await.unwrap();
+            assert_eq!(
+                commands[2..],
+                ["UID FETCH 10:11 (UID FLAGS)", "UID FETCH 12 (UID FLAGS)"]
+            );
+        }
+
+        #[test]
+        fn idle_subscription_is_send() {
+            fn assert_send<T: Send>(_: &T) {}
+            let _ = |account: Account| {
+                assert_send(&idle_subscription(account, "INBOX".to_string()));
+            };
+        }
     }
 }
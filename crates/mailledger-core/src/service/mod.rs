@@ -3,13 +3,37 @@
 //! This module provides the service layer that bridges the GUI
 //! with the underlying IMAP and SMTP libraries.
 
+pub mod drafts;
 pub mod mail;
+pub mod oauth;
+pub mod pool;
+pub mod probe;
+pub mod retry;
 pub mod smtp;
+pub mod sync;
+pub mod unsubscribe;
 
+pub use drafts::save_draft;
 pub use mail::{
-    Attachment, AuthClient, Folder, FolderType, IdleEvent, MailServiceError, MessageContent,
-    MessageSummary, SearchCriteria, SelectedClient, archive_message, connect_and_login,
-    download_attachment, fetch_message_content, fetch_messages, idle_monitor, list_folders,
-    mark_read, mark_unread, search_messages, select_folder, toggle_flag,
+    Attachment, AuthClient, ExpungeGuard, ExpungePreview, ExpungeScope, ExpungeToken, Folder,
+    FolderChanges, FolderCounts, FolderType, IdleEvent, MailServiceError, MessageContent,
+    MessageSummary, PreferredBody, SearchCriteria, SelectedClient, archive_many, archive_message,
+    connect_and_login, delete_many, delete_permanently, download_attachment, fetch_batched,
+    fetch_body_part, fetch_changes, fetch_flag_updates, fetch_message_content, fetch_messages,
+    fetch_messages_by_uid, fetch_messages_stream, fetch_new_since, fetch_raw_message,
+    folder_counts, idle_monitor, idle_subscription, imap_security, list_folders, mark_read,
+    mark_read_many, mark_unread, move_messages, search_messages, search_messages_matching,
+    search_offline, select_folder, select_folder_read_only, toggle_flag,
 };
-pub use smtp::{OutgoingMessage, SmtpError, send_email};
+pub use oauth::{
+    DeviceSignIn, OAuthMechanism, finish_device_sign_in, fresh_token, start_device_sign_in,
+};
+pub use pool::{ConnectionPool, Connector, ImapConnector};
+pub use probe::{ConnectionProbe, probe_connection};
+pub use retry::{Retriable, RetryPolicy, with_backoff};
+pub use smtp::{
+    FlushSummary, OutgoingMessage, SmtpError, SmtpSession, flush_outbox,
+    read_receipt_matches_return_path, send_batch, send_email, send_read_receipt,
+};
+pub use sync::{SyncResult, current_uid, resolve_uid_by_message_id, sync_folder};
+pub use unsubscribe::{UnsubscribeInfo, unsubscribe};
@@ -0,0 +1,223 @@
+//! `OAuth2` sign-in for IMAP and SMTP.
+//!
+//! Accounts that use [`AuthMethod::OAuth`] get a fresh access token before
+//! each connection and send it with OAUTHBEARER (RFC 7628) when the server
+//! offers it, falling back to the XOAUTH2 mechanism Google and Microsoft
+//! introduced first.
+
+use std::time::Duration;
+
+use mailledger_oauth::sasl::{oauthbearer_response, xoauth2_response};
+use mailledger_oauth::{DeviceAuthorization, DeviceFlow, Error as OAuthError, OAuthClient, Token};
+
+use crate::account::{AccountId, AccountTokenStore, AuthMethod, OAuthProvider};
+
+use super::mail::MailServiceError;
+
+/// SASL mechanism used to send an `OAuth2` token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OAuthMechanism {
+    /// RFC 7628 OAUTHBEARER.
+    OAuthBearer,
+    /// Google/Microsoft XOAUTH2.
+    XOAuth2,
+}
+
+impl OAuthMechanism {
+    /// Choose the mechanism, preferring OAUTHBEARER if `supports` says the
+    /// server offers it.
+    #[must_use]
+    pub fn choose(supports: impl Fn(&str) -> bool) -> Self {
+        if supports("OAUTHBEARER") {
+            Self::OAuthBearer
+        } else {
+            Self::XOAuth2
+        }
+    }
+
+    /// Get the mechanism name.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::OAuthBearer => "OAUTHBEARER",
+            Self::XOAuth2 => "XOAUTH2",
+        }
+    }
+
+    /// Build the base64 initial response carrying `token` for `user`.
+    #[must_use]
+    pub fn initial_response(self, user: &str, token: &Token) -> String {
+        match self {
+            Self::OAuthBearer => oauthbearer_response(user, &token.access_token),
+            Self::XOAuth2 => xoauth2_response(user, &token.access_token),
+        }
+    }
+}
+
+/// Get an access token that is valid now.
+///
+/// A saved account's token is loaded from the keyring and, if it has
+/// expired, refreshed and saved back. An unsaved account, such as one being
+/// checked during setup, uses the token it carries.
+///
+/// # Errors
+///
+/// Returns an error if no token is stored or the refresh fails.
+pub async fn fresh_token(
+    account_id: Option<AccountId>,
+    provider: OAuthProvider,
+    token: &Token,
+    client_id: &str,
+) -> Result<Token, MailServiceError> {
+    let client = OAuthClient::new(client_id, provider.provider().map_err(|e| auth_error(&e))?);
+    let result = match account_id {
+        Some(id) => {
+            client
+                .ensure_valid(&AccountTokenStore, &id.to_string())
+                .await
+        }
+        None if token.is_expired() => client.refresh_token(token).await,
+        None => Ok(token.clone()),
+    };
+    result.map_err(|e| auth_error(&e))
+}
+
+/// A device-flow sign-in waiting for the user (RFC 8628).
+#[derive(Debug, Clone)]
+pub struct DeviceSignIn {
+    /// Provider being signed in to.
+    pub provider: OAuthProvider,
+    /// Client ID the token is requested for.
+    pub client_id: String,
+    /// Code and page to show the user.
+    pub authorization: DeviceAuthorization,
+}
+
+/// Start signing in with the device flow.
+///
+/// Show the user `authorization.user_code` and
+/// `authorization.verification_uri`, then call [`finish_device_sign_in`].
+///
+/// # Errors
+///
+/// Returns an error if the provider has no device flow or rejects the
+/// request.
+pub async fn start_device_sign_in(
+    provider: OAuthProvider,
+    client_id: &str,
+) -> Result<DeviceSignIn, MailServiceError> {
+    let client = OAuthClient::new(client_id, provider.provider().map_err(|e| auth_error(&e))?);
+    let authorization = DeviceFlow::new(client)
+        .request_device_authorization(None)
+        .await
+        .map_err(|e| auth_error(&e))?;
+
+    Ok(DeviceSignIn {
+        provider,
+        client_id: client_id.to_string(),
+        authorization,
+    })
+}
+
+/// Wait for the user to approve a device-flow sign-in.
+///
+/// Polls at the interval the provider asked for until it issues a token or
+/// the code expires.
+///
+/// # Errors
+///
+/// Returns an error if the user declines, the code expires or polling
+/// fails.
+pub async fn finish_device_sign_in(sign_in: &DeviceSignIn) -> Result<AuthMethod, MailServiceError> {
+    let client = OAuthClient::new(
+        sign_in.client_id.as_str(),
+        sign_in.provider.provider().map_err(|e| auth_error(&e))?,
+    );
+    let flow = DeviceFlow::new(client);
+    let authorization = &sign_in.authorization;
+    let mut interval = Duration::from_secs(u64::from(authorization.interval.max(1)));
+    let mut waited = Duration::ZERO;
+
+    let token = loop {
+        if waited >= Duration::from_secs(u64::from(authorization.expires_in)) {
+            return Err(auth_error(&OAuthError::Timeout(
+                authorization.expires_in.into(),
+            )));
+        }
+        waited += interval;
+        match flow
+            .poll_for_token(&authorization.device_code, interval)
+            .await
+        {
+            Ok(token) => break token,
+            Err(OAuthError::OAuth { ref error, .. }) if error == "authorization_pending" => {}
+            Err(OAuthError::OAuth { ref error, .. }) if error == "slow_down" => {
+                // RFC 8628 section 3.5
+                interval += Duration::from_secs(5);
+            }
+            Err(e) => return Err(auth_error(&e)),
+        }
+    };
+
+    Ok(AuthMethod::OAuth {
+        provider: sign_in.provider,
+        token,
+        client_id: sign_in.client_id.clone(),
+    })
+}
+
+fn auth_error(error: &OAuthError) -> MailServiceError {
+    MailServiceError::Authentication(error.to_string())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn token() -> Token {
+        Token::new("ya29.token", "Bearer")
+    }
+
+    #[test]
+    fn prefers_oauthbearer_when_offered() {
+        assert_eq!(
+            OAuthMechanism::choose(|m| m == "OAUTHBEARER" || m == "XOAUTH2"),
+            OAuthMechanism::OAuthBearer
+        );
+        assert_eq!(
+            OAuthMechanism::choose(|m| m == "XOAUTH2"),
+            OAuthMechanism::XOAuth2
+        );
+        // Servers that advertise nothing still get the common mechanism
+        assert_eq!(OAuthMechanism::choose(|_| false), OAuthMechanism::XOAuth2);
+    }
+
+    #[test]
+    fn xoauth2_response_carries_the_token() {
+        let response = OAuthMechanism::XOAuth2.initial_response("user@gmail.com", &token());
+        assert_eq!(
+            response,
+            // user=user@gmail.com^Aauth=Bearer ya29.token^A^A
+            "dXNlcj11c2VyQGdtYWlsLmNvbQFhdXRoPUJlYXJlciB5YTI5LnRva2VuAQE="
+        );
+    }
+
+    #[test]
+    fn oauthbearer_response_carries_the_token() {
+        let response = OAuthMechanism::OAuthBearer.initial_response("user@gmail.com", &token());
+        assert_eq!(
+            response,
+            // n,a=user@gmail.com,^Aauth=Bearer ya29.token^A^A
+            "bixhPXVzZXJAZ21haWwuY29tLAFhdXRoPUJlYXJlciB5YTI5LnRva2VuAQE="
+        );
+    }
+
+    #[tokio::test]
+    async fn unsaved_account_uses_its_unexpired_token() {
+        let token = fresh_token(None, OAuthProvider::Google, &token(), "client")
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "ya29.token");
+    }
+}
@@ -0,0 +1,392 @@
+//! Reusable IMAP sessions.
+//!
+//! Opening a TLS connection and logging in for every UI action is slow and
+//! trips rate limits on providers like Gmail. [`ConnectionPool`] keeps one
+//! idle session per account, checks it with NOOP before handing it out, and
+//! reconnects transparently when the server has dropped it.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, PoisonError};
+
+use mailledger_imap::Error as ImapError;
+use mailledger_imap::connection::{Authenticated, Client, ImapStream, Selected};
+use mailledger_imap::types::MailboxStatus;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::mail::{MailServiceError, connect_and_login};
+use crate::account::{Account, AccountId};
+
+/// Opens new authenticated sessions for a [`ConnectionPool`].
+pub trait Connector: Send + Sync {
+    /// Stream type of the sessions this connector opens.
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send;
+
+    /// Connects to the account's IMAP server and logs in.
+    fn connect(
+        &self,
+        account: &Account,
+    ) -> impl Future<Output = Result<Client<Self::Stream, Authenticated>, MailServiceError>> + Send;
+}
+
+/// Connector that dials the account's server with [`connect_and_login`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImapConnector;
+
+impl Connector for ImapConnector {
+    type Stream = ImapStream;
+
+    async fn connect(
+        &self,
+        account: &Account,
+    ) -> Result<Client<ImapStream, Authenticated>, MailServiceError> {
+        connect_and_login(account).await
+    }
+}
+
+/// An idle session waiting in the pool.
+enum Idle<S> {
+    Authenticated(Client<S, Authenticated>),
+    Selected(Client<S, Selected>),
+}
+
+/// Keeps one authenticated IMAP session per account alive between operations.
+///
+/// Take a session with [`authenticated`](Self::authenticated) or
+/// [`selected`](Self::selected), run the operation, and hand it back with
+/// [`release`](Self::release) or [`release_authenticated`](Self::release_authenticated).
+/// Sessions that failed mid-operation should simply be dropped; the next
+/// checkout reconnects. Accounts without an ID are never pooled.
+pub struct ConnectionPool<C: Connector = ImapConnector> {
+    connector: C,
+    idle: Mutex<HashMap<AccountId, Idle<C::Stream>>>,
+}
+
+impl ConnectionPool {
+    /// Creates a pool that connects with [`connect_and_login`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_connector(ImapConnector)
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Connector> ConnectionPool<C> {
+    /// Creates a pool that opens sessions with `connector`.
+    #[must_use]
+    pub fn with_connector(connector: C) -> Self {
+        Self {
+            connector,
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns an authenticated session for the account.
+    ///
+    /// Reuses the pooled session if it is authenticated and answers NOOP.
+    /// A pooled session with a mailbox selected is replaced, since leaving
+    /// the mailbox with CLOSE would expunge it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a new connection or login fails.
+    pub async fn authenticated(
+        &self,
+        account: &Account,
+    ) -> Result<Client<C::Stream, Authenticated>, MailServiceError> {
+        if let Some(Idle::Authenticated(mut client)) = self.take(account)
+            && client.noop().await.is_ok()
+        {
+            return Ok(client);
+        }
+        self.connector.connect(account).await
+    }
+
+    /// Returns a session with `mailbox` selected.
+    ///
+    /// Reuses the pooled session if it answers NOOP, re-selecting the
+    /// mailbox to refresh its status. If the server drops the reused
+    /// session during SELECT, logs in again once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if connecting, logging in or selecting fails.
+    pub async fn selected(
+        &self,
+        account: &Account,
+        mailbox: &str,
+    ) -> Result<(Client<C::Stream, Selected>, MailboxStatus), MailServiceError> {
+        self.open(account, mailbox, false).await
+    }
+
+    /// Returns a session with `mailbox` opened read-only with EXAMINE.
+    ///
+    /// Like [`Self::selected`], but browsing this way leaves `\Recent`
+    /// alone, and flag changes and expunges fail before reaching the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if connecting, logging in or examining fails.
+    pub async fn examined(
+        &self,
+        account: &Account,
+        mailbox: &str,
+    ) -> Result<(Client<C::Stream, Selected>, MailboxStatus), MailServiceError> {
+        self.open(account, mailbox, true).await
+    }
+
+    async fn open(
+        &self,
+        account: &Account,
+        mailbox: &str,
+        read_only: bool,
+    ) -> Result<(Client<C::Stream, Selected>, MailboxStatus), MailServiceError> {
+        let reused = match self.take(account) {
+            Some(Idle::Authenticated(mut client)) => match client.noop().await {
+                Ok(()) if read_only => Some(client.examine(mailbox).await),
+                Ok(()) => Some(client.select(mailbox).await),
+                Err(_) => None,
+            },
+            Some(Idle::Selected(mut client)) => match client.noop().await {
+                Ok(()) if read_only => Some(client.examine(mailbox).await),
+                Ok(()) => Some(client.select(mailbox).await),
+                Err(_) => None,
+            },
+            None => None,
+        };
+
+        match reused {
+            Some(Ok(selected)) => return Ok(selected),
+            Some(Err(e)) if !is_disconnect(&e) => {
+                return Err(MailServiceError::Operation(e.to_string()));
+            }
+            Some(Err(e)) => tracing::debug!("Pooled IMAP session dropped, reconnecting: {e}"),
+            None => {}
+        }
+
+        let client = self.connector.connect(account).await?;
+        if read_only {
+            client.examine(mailbox).await
+        } else {
+            client.select(mailbox).await
+        }
+        .map_err(|e| MailServiceError::Operation(e.to_string()))
+    }
+
+    /// Returns a session with a mailbox selected to the pool.
+    pub fn release(&self, account: &Account, client: Client<C::Stream, Selected>) {
+        self.put(account, Idle::Selected(client));
+    }
+
+    /// Returns an authenticated session to the pool.
+    pub fn release_authenticated(
+        &self,
+        account: &Account,
+        client: Client<C::Stream, Authenticated>,
+    ) {
+        self.put(account, Idle::Authenticated(client));
+    }
+
+    /// Drops the pooled session for the account, if any.
+    pub fn evict(&self, account: &Account) {
+        self.take(account);
+    }
+
+    fn take(&self, account: &Account) -> Option<Idle<C::Stream>> {
+        let id = account.id?;
+        self.idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id)
+    }
+
+    fn put(&self, account: &Account, idle: Idle<C::Stream>) {
+        if let Some(id) = account.id {
+            self.idle
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(id, idle);
+        }
+    }
+}
+
+/// Returns true if the error means the connection itself is gone.
+const fn is_disconnect(error: &ImapError) -> bool {
+    matches!(
+        error,
+        ImapError::Bye(_) | ImapError::Io(_) | ImapError::ConnectionLost(_)
+    )
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use std::time::Duration;
+
+    use mailledger_imap::SearchCriteria;
+    use mailledger_imap::command::FetchItems;
+    use mailledger_imap::types::{Uid, UidSet};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::*;
+    use crate::service::retry::{RetryPolicy, with_backoff};
+
+    /// Connector backed by an in-memory server that counts connections.
+    #[derive(Default)]
+    struct MockConnector {
+        connects: Arc<AtomicUsize>,
+        /// Whether the first server hangs up on its first UID command.
+        hang_up_first: bool,
+    }
+
+    impl Connector for MockConnector {
+        type Stream = DuplexStream;
+
+        async fn connect(
+            &self,
+            _account: &Account,
+        ) -> Result<Client<DuplexStream, Authenticated>, MailServiceError> {
+            let first = self.connects.fetch_add(1, Ordering::SeqCst) == 0;
+            let (local, remote) = tokio::io::duplex(4096);
+            tokio::spawn(serve(remote, self.hang_up_first && first));
+            let client = Client::from_stream(local)
+                .await
+                .map_err(|e| MailServiceError::Connection(e.to_string()))?;
+            client
+                .login("user", "pass")
+                .await
+                .map_err(|e| MailServiceError::Authentication(e.to_string()))
+        }
+    }
+
+    /// Answers every command with a tagged OK, plus a status for SELECT.
+    ///
+    /// With `hang_up` the connection is closed instead of answering the
+    /// first UID command.
+    async fn serve(stream: DuplexStream, hang_up: bool) {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+        write
+            .write_all(b"* OK [CAPABILITY IMAP4rev1] ready\r\n")
+            .await
+            .unwrap();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let mut words = line.split(' ');
+            let tag = words.next().unwrap_or_default();
+            match words.next() {
+                Some("SELECT") => write.write_all(b"* 0 EXISTS\r\n").await.unwrap(),
+                Some("UID") if hang_up => return,
+                _ => {}
+            }
+            write
+                .write_all(format!("{tag} OK done\r\n").as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn account() -> Account {
+        Account {
+            id: Some(AccountId(1)),
+            ..Account::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn sequential_fetches_reuse_connection() {
+        let connector = MockConnector::default();
+        let connects = Arc::clone(&connector.connects);
+        let pool = ConnectionPool::with_connector(connector);
+        let account = account();
+        let uids = UidSet::single(Uid::new(1).unwrap());
+
+        for _ in 0..2 {
+            let (mut client, _) = pool.selected(&account, "INBOX").await.unwrap();
+            client.uid_fetch(&uids, FetchItems::Fast).await.unwrap();
+            pool.release(&account, client);
+        }
+
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dropped_session_reconnects() {
+        let connector = MockConnector::default();
+        let connects = Arc::clone(&connector.connects);
+        let pool = ConnectionPool::with_connector(connector);
+        let account = account();
+
+        let (client, _) = pool.selected(&account, "INBOX").await.unwrap();
+        drop(client);
+        let (client, _) = pool.selected(&account, "INBOX").await.unwrap();
+        pool.release(&account, client);
+
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_lost_session_reconnects() {
+        let connector = MockConnector {
+            hang_up_first: true,
+            ..MockConnector::default()
+        };
+        let connects = Arc::clone(&connector.connects);
+        let pool = ConnectionPool::with_connector(connector);
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let (pool, account) = (&pool, &account());
+
+        // The failed session is dropped rather than released, so the retry
+        // reconnects and selects the folder again
+        let uids = with_backoff(
+            || async move {
+                let (mut client, _) = pool.selected(account, "INBOX").await?;
+                let uids = client
+                    .uid_search_criteria(&SearchCriteria::All, None)
+                    .await
+                    .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+                pool.release(account, client);
+                Ok::<_, MailServiceError>(uids)
+            },
+            &policy,
+        )
+        .await
+        .unwrap();
+
+        assert!(uids.is_empty());
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn accounts_without_id_are_not_pooled() {
+        let connector = MockConnector::default();
+        let connects = Arc::clone(&connector.connects);
+        let pool = ConnectionPool::with_connector(connector);
+        let account = Account::default();
+
+        for _ in 0..2 {
+            let client = pool.authenticated(&account).await.unwrap();
+            pool.release_authenticated(&account, client);
+        }
+
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,221 @@
+//! Probing what an IMAP server supports before an account is saved.
+//!
+//! [`probe_connection`] goes through the same steps as a real sign-in,
+//! including STARTTLS, and reports the server's capabilities and
+//! special-use folders so the setup screen can show them.
+
+use mailledger_imap::connection::{Client, Config, NotAuthenticated, connect};
+use mailledger_imap::types::{Capability, MailboxAttribute};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::account::Account;
+
+use super::mail::{MailServiceError, authenticate, imap_security};
+
+/// What an IMAP server supports, as seen while signing in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionProbe {
+    /// Capabilities advertised after signing in.
+    pub capabilities: Vec<Capability>,
+    /// Whether the server pushes new mail with IDLE.
+    pub supports_idle: bool,
+    /// Whether messages can be moved without copying and expunging.
+    pub supports_move: bool,
+    /// Whether the server offered an `OAuth2` mechanism before signing in.
+    pub supports_oauth: bool,
+    /// Folders the server marked with a special use, by path.
+    pub special_use_folders: Vec<(String, MailboxAttribute)>,
+}
+
+/// Connect and sign in to the account's IMAP server, and report what it
+/// supports.
+///
+/// Nothing is changed on the server; the connection is closed afterwards.
+///
+/// # Errors
+///
+/// Returns an error if connecting, upgrading with STARTTLS, signing in or
+/// listing folders fails.
+pub async fn probe_connection(account: &Account) -> Result<ConnectionProbe, MailServiceError> {
+    let config = Config::builder(&account.imap.host)
+        .port(account.imap.port)
+        .security(imap_security(account.imap.security))
+        .build();
+    let client = connect(&config)
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Connection))?;
+
+    probe_client(client, account).await
+}
+
+/// Sign in on an open connection and report what the server supports.
+async fn probe_client<S>(
+    mut client: Client<S, NotAuthenticated>,
+    account: &Account,
+) -> Result<ConnectionProbe, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // Mechanisms are only advertised before signing in, and not every
+    // greeting lists them
+    if client.capabilities().is_empty() {
+        client
+            .capability()
+            .await
+            .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Connection))?;
+    }
+    let supports_oauth = client.supports_auth("XOAUTH2") || client.supports_auth("OAUTHBEARER");
+
+    let mut client = authenticate(client, account).await?;
+    // Servers often advertise more once signed in
+    client
+        .capability()
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+
+    let special_use_folders = client
+        .list("", "*")
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?
+        .into_iter()
+        .filter_map(|entry| {
+            let attribute = entry.special_use()?.clone();
+            Some((entry.mailbox.as_str().to_string(), attribute))
+        })
+        .collect();
+
+    let probe = ConnectionProbe {
+        capabilities: client.capabilities().to_vec(),
+        supports_idle: client.supports_idle(),
+        supports_move: client.supports_move(),
+        supports_oauth,
+        special_use_folders,
+    };
+    let _ = client.logout().await;
+    Ok(probe)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::*;
+    use crate::account::AuthMethod;
+
+    /// Replays a recorded session: the greeting, then one reply per
+    /// command with `TAG` replaced by the command's tag.
+    async fn replay(
+        stream: DuplexStream,
+        greeting: &'static str,
+        replies: &'static [&'static str],
+    ) -> Vec<String> {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(greeting.as_bytes()).await.unwrap();
+        let mut commands = Vec::new();
+        for reply in replies {
+            let Ok(Some(line)) = lines.next_line().await else {
+                break;
+            };
+            let (tag, command) = line.split_once(' ').unwrap();
+            write
+                .write_all(reply.replace("TAG", tag).as_bytes())
+                .await
+                .unwrap();
+            commands.push(command.to_string());
+        }
+        commands
+    }
+
+    async fn probe(
+        greeting: &'static str,
+        replies: &'static [&'static str],
+    ) -> (ConnectionProbe, Vec<String>) {
+        let (local, remote) = tokio::io::duplex(8192);
+        let server = tokio::spawn(replay(remote, greeting, replies));
+        let client = Client::from_stream(local).await.unwrap();
+        let mut account = Account::with_email("user@example.com");
+        account.imap.username = "user@example.com".to_string();
+        account.imap.auth = AuthMethod::Password("secret".to_string());
+        let probe = probe_client(client, &account).await.unwrap();
+        (probe, server.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_probe_dovecot() {
+        let (probe, commands) = probe(
+            "* OK [CAPABILITY IMAP4rev1 SASL-IR LITERAL+ ID ENABLE IDLE AUTH=PLAIN] Dovecot ready.\r\n",
+            &[
+                "TAG OK [CAPABILITY IMAP4rev1 SASL-IR LITERAL+ ID ENABLE IDLE SORT \
+                 SPECIAL-USE MOVE CONDSTORE] Logged in\r\n",
+                "* CAPABILITY IMAP4rev1 SASL-IR LITERAL+ ID ENABLE IDLE SORT SPECIAL-USE \
+                 MOVE CONDSTORE\r\nTAG OK Capability completed.\r\n",
+                "* LIST (\\HasNoChildren) \"/\" INBOX\r\n\
+                 * LIST (\\HasNoChildren \\Drafts) \"/\" Drafts\r\n\
+                 * LIST (\\HasNoChildren \\Sent) \"/\" \"Sent Messages\"\r\n\
+                 * LIST (\\HasNoChildren \\Trash) \"/\" Trash\r\n\
+                 * LIST (\\HasNoChildren) \"/\" Receipts\r\n\
+                 TAG OK List completed.\r\n",
+                "* BYE Logging out\r\nTAG OK Logout completed.\r\n",
+            ],
+        )
+        .await;
+
+        assert!(probe.supports_idle);
+        assert!(probe.supports_move);
+        assert!(!probe.supports_oauth);
+        assert!(probe.capabilities.contains(&Capability::CondStore));
+        assert_eq!(
+            probe.special_use_folders,
+            vec![
+                ("Drafts".to_string(), MailboxAttribute::Drafts),
+                ("Sent Messages".to_string(), MailboxAttribute::Sent),
+                ("Trash".to_string(), MailboxAttribute::Trash),
+            ]
+        );
+        assert_eq!(
+            commands,
+            [
+                "LOGIN user@example.com secret",
+                "CAPABILITY",
+                "LIST \"\" \"*\"",
+                "LOGOUT"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_gmail_offers_oauth() {
+        let (probe, _) = probe(
+            "* OK Gimap ready for requests\r\n",
+            &[
+                "* CAPABILITY IMAP4rev1 UNSELECT IDLE NAMESPACE QUOTA ID XLIST CHILDREN \
+                 X-GM-EXT-1 XYZZY SASL-IR AUTH=XOAUTH2 AUTH=PLAIN AUTH=OAUTHBEARER\r\n\
+                 TAG OK Thats all she wrote!\r\n",
+                "TAG OK user@example.com authenticated (Success)\r\n",
+                "* CAPABILITY IMAP4rev1 UNSELECT IDLE NAMESPACE QUOTA ID XLIST CHILDREN \
+                 X-GM-EXT-1 UIDPLUS COMPRESS=DEFLATE ENABLE MOVE CONDSTORE ESEARCH UTF8=ACCEPT \
+                 LIST-EXTENDED LIST-STATUS LITERAL- SPECIAL-USE APPENDLIMIT=35651584\r\n\
+                 TAG OK Success\r\n",
+                "* LIST (\\HasNoChildren) \"/\" \"INBOX\"\r\n\
+                 * LIST (\\All \\HasNoChildren) \"/\" \"[Gmail]/All Mail\"\r\n\
+                 * LIST (\\HasNoChildren \\Junk) \"/\" \"[Gmail]/Spam\"\r\n\
+                 TAG OK Success\r\n",
+                "* BYE LOGOUT Requested\r\nTAG OK 73 good day (Success)\r\n",
+            ],
+        )
+        .await;
+
+        assert!(probe.supports_oauth);
+        assert!(probe.supports_move);
+        assert!(probe.capabilities.contains(&Capability::SpecialUse));
+        assert_eq!(
+            probe.special_use_folders,
+            vec![
+                ("[Gmail]/All Mail".to_string(), MailboxAttribute::All),
+                ("[Gmail]/Spam".to_string(), MailboxAttribute::Junk),
+            ]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Retrying transient failures with exponential backoff.
+//!
+//! [`with_backoff`] reruns an operation while it fails with an error that
+//! [`Retriable::is_retriable`] classifies as transient, sleeping a jittered,
+//! exponentially growing delay between attempts. Commands on an open
+//! session are paced with `SessionRetry` instead, since most transient
+//! failures leave the session unusable.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use mailledger_imap::Error as ImapError;
+use rand::Rng;
+
+use super::mail::MailServiceError;
+
+/// Errors that can tell whether retrying may succeed.
+pub trait Retriable {
+    /// Returns true if the failed operation may succeed when retried.
+    fn is_retriable(&self) -> bool;
+}
+
+impl Retriable for ImapError {
+    fn is_retriable(&self) -> bool {
+        Self::is_retriable(self)
+    }
+}
+
+impl Retriable for MailServiceError {
+    fn is_retriable(&self) -> bool {
+        Self::is_retriable(self)
+    }
+}
+
+/// How often and how patiently to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    pub initial_delay: Duration,
+    /// Upper bound for a single delay.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the total number of attempts.
+    #[must_use]
+    pub const fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    #[must_use]
+    pub const fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    /// Sets the upper bound for a single delay.
+    #[must_use]
+    pub const fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Returns the delay before retry number `retry` (starting at 0).
+    ///
+    /// The exponential delay is capped at `max_delay`, then a random
+    /// amount of up to half of it is taken off so that clients failing
+    /// together don't retry in lockstep.
+    #[must_use]
+    pub fn delay(&self, retry: u32) -> Duration {
+        let base = self
+            .initial_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = base / 2;
+        let jitter_nanos = u64::try_from(half.as_nanos()).unwrap_or(u64::MAX);
+        let jitter = if jitter_nanos == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos(rand::thread_rng().gen_range(0..jitter_nanos))
+        };
+        base.saturating_sub(jitter)
+    }
+}
+
+/// Runs `op`, retrying transient failures according to `policy`.
+///
+/// Errors that aren't [retriable](Retriable::is_retriable), and the error of
+/// the last allowed attempt, are returned unchanged.
+///
+/// # Errors
+///
+/// Returns the error of the final attempt.
+pub async fn with_backoff<T, E, F, Fut>(mut op: F, policy: &RetryPolicy) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Retriable + Display,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if e.is_retriable() && attempt < policy.max_attempts => {
+                let delay = policy.delay(attempt - 1);
+                tracing::warn!("Attempt {attempt} failed, retrying in {delay:?}: {e}");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Paces retries of a command on an open session.
+///
+/// Only `NO [UNAVAILABLE]` is retried, the one transient failure the
+/// session survives. After `BYE` or a dropped connection, retrying on the
+/// same session can't succeed, so those errors are left for the caller,
+/// which retries with a fresh session, e.g. by wrapping a
+/// [`ConnectionPool::selected`](super::ConnectionPool::selected) checkout
+/// in [`with_backoff`].
+///
+/// ```ignore
+/// let mut retry = SessionRetry::new(RetryPolicy::default());
+/// let uids = loop {
+///     match client.uid_search_criteria(criteria, None).await {
+///         Err(e) if retry.wait(&e).await => {}
+///         result => break result,
+///     }
+/// };
+/// ```
+pub(crate) struct SessionRetry {
+    policy: RetryPolicy,
+    attempt: u32,
+}
+
+impl SessionRetry {
+    pub(crate) const fn new(policy: RetryPolicy) -> Self {
+        Self { policy, attempt: 1 }
+    }
+
+    /// Returns true, after waiting out the backoff delay, if the command
+    /// that failed with `error` should be sent again.
+    pub(crate) async fn wait(&mut self, error: &ImapError) -> bool {
+        if !is_unavailable(error) || self.attempt >= self.policy.max_attempts {
+            return false;
+        }
+        let delay = self.policy.delay(self.attempt - 1);
+        tracing::warn!("Server unavailable, retrying in {delay:?}: {error}");
+        tokio::time::sleep(delay).await;
+        self.attempt += 1;
+        true
+    }
+}
+
+/// Returns true if the server answered `NO [UNAVAILABLE]`.
+fn is_unavailable(error: &ImapError) -> bool {
+    match error {
+        ImapError::Unavailable(_) => true,
+        ImapError::Command { source, .. } => is_unavailable(source),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use mailledger_imap::types::{ResponseCode, Status};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_initial_delay(Duration::from_millis(1))
+            .with_max_delay(Duration::from_millis(2))
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let calls = AtomicU32::new(0);
+        let result = with_backoff(
+            || async {
+                match calls.fetch_add(1, Ordering::SeqCst) + 1 {
+                    1 => Err(ImapError::Bye("restarting".to_string())),
+                    2 => Err(ImapError::Unavailable("try later".to_string())),
+                    _ => Ok("done"),
+                }
+            },
+            &fast_policy(),
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = with_backoff(
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(ImapError::Io(std::io::ErrorKind::ConnectionReset.into()))
+            },
+            &fast_policy(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ImapError::Io(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_permanent_errors() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = with_backoff(
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(ImapError::from_tagged(
+                    Status::No,
+                    None,
+                    "no such mailbox".to_string(),
+                ))
+            },
+            &fast_policy(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(ImapError::Tagged {
+                status: Status::No,
+                ..
+            })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_retries_only_unavailable() {
+        let mut retry = SessionRetry::new(fast_policy());
+        let unavailable = || {
+            ImapError::from_tagged(
+                Status::No,
+                Some(ResponseCode::Unavailable),
+                "try later".to_string(),
+            )
+        };
+        assert!(retry.wait(&unavailable()).await);
+        assert!(retry.wait(&unavailable().with_command("UID FETCH")).await);
+        // The third attempt was the last
+        assert!(!retry.wait(&unavailable()).await);
+
+        // A session that said BYE is gone; only a new one can help
+        let mut retry = SessionRetry::new(fast_policy());
+        assert!(!retry.wait(&ImapError::Bye("restarting".to_string())).await);
+        let reset = ImapError::Io(std::io::ErrorKind::ConnectionReset.into());
+        assert!(!retry.wait(&reset).await);
+    }
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = RetryPolicy::default()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(300));
+        for _ in 0..20 {
+            let first = policy.delay(0);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.delay(2);
+            assert!(third >= Duration::from_millis(150) && third <= Duration::from_millis(300));
+        }
+        assert!(policy.delay(u32::MAX) <= Duration::from_millis(300));
+    }
+}
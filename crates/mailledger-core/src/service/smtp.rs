@@ -2,8 +2,17 @@
 //!
 //! Provides high-level email sending operations using the SMTP library.
 
+use mailledger_smtp::connection::{Authenticated, Connected, SmtpConnection, connect, connect_tls};
+use mailledger_smtp::{Address, AuthMechanism, Client};
+
+use mailledger_mime::{MdnDisposition, MdnMode, generate_mdn};
+
 use crate::Security;
-use crate::account::Account;
+use crate::account::{Account, AuthMethod};
+use crate::outbox::{MAX_SEND_ATTEMPTS, OutboxEntry, OutboxRepository};
+
+use super::mail::MessageContent;
+use super::oauth::{OAuthMechanism, fresh_token};
 
 /// Errors that can occur during SMTP operations.
 #[derive(Debug, thiserror::Error)]
@@ -27,6 +36,71 @@ pub enum SmtpError {
     /// Security mode not supported.
     #[error("Security mode not supported: {0}")]
     UnsupportedSecurity(String),
+
+    /// The recipient mailbox does not exist (X.1.1).
+    #[error("Mailbox does not exist: {0}")]
+    MailboxNotFound(String),
+
+    /// The recipient mailbox is full (X.2.2).
+    #[error("Mailbox full: {0}")]
+    MailboxFull(String),
+
+    /// The message exceeds a size limit (X.2.3, X.3.4).
+    #[error("Message too large: {0}")]
+    MessageTooLarge(String),
+
+    /// The server refused the message on policy or security grounds (X.7.Z).
+    #[error("Rejected by policy: {0}")]
+    PolicyRejected(String),
+
+    /// Any other temporary failure (4xx); the send may succeed later.
+    #[error("Temporary failure: {0}")]
+    TemporaryFailure(String),
+}
+
+impl SmtpError {
+    /// Returns true if retrying the send later may succeed.
+    #[must_use]
+    pub const fn is_transient(&self) -> bool {
+        matches!(self, Self::MailboxFull(_) | Self::TemporaryFailure(_))
+    }
+
+    /// Returns true if resending the same message can never succeed.
+    #[must_use]
+    pub const fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidAddress(_)
+                | Self::UnsupportedSecurity(_)
+                | Self::MailboxNotFound(_)
+                | Self::MessageTooLarge(_)
+                | Self::PolicyRejected(_)
+        )
+    }
+}
+
+impl From<mailledger_smtp::Error> for SmtpError {
+    /// Maps a failed send to a semantic variant using the reply's enhanced
+    /// status code (RFC 3463) when the server sent one.
+    fn from(error: mailledger_smtp::Error) -> Self {
+        let mailledger_smtp::Error::SmtpError {
+            code,
+            message,
+            enhanced_code,
+        } = error
+        else {
+            return Self::Send(error.to_string());
+        };
+        match enhanced_code {
+            Some((_, 1, 1)) => Self::MailboxNotFound(message),
+            Some((_, 2, 2)) => Self::MailboxFull(message),
+            Some((_, 2, 3) | (_, 3, 4)) => Self::MessageTooLarge(message),
+            Some((_, 7, _)) => Self::PolicyRejected(message),
+            Some((4, _, _)) => Self::TemporaryFailure(message),
+            None if (400..500).contains(&code) => Self::TemporaryFailure(message),
+            _ => Self::Send(format!("{code} {message}")),
+        }
+    }
 }
 
 /// An email message to send.
@@ -116,6 +190,46 @@ impl OutgoingMessage {
         message
     }
 
+    /// Builds the message as a MIME entity for saving as a draft.
+    ///
+    /// Unlike the sent form, Bcc is kept so the draft can be resumed, and a
+    /// Message-ID is assigned so the saved copy can be found again.
+    #[must_use]
+    pub fn to_draft(&self) -> mailledger_mime::Message {
+        use mailledger_mime::Headers;
+
+        let now = chrono::Utc::now();
+        let domain = self
+            .from
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain.trim_end_matches('>'));
+
+        let mut headers = Headers::new();
+        headers.set("From", &self.from);
+        for (name, recipients) in [("To", &self.to), ("Cc", &self.cc), ("Bcc", &self.bcc)] {
+            if !recipients.is_empty() {
+                headers.set(name, recipients.join(", "));
+            }
+        }
+        let subject = Headers::encode_value(&self.subject).unwrap_or_else(|_| self.subject.clone());
+        headers.set("Subject", subject);
+        headers.set("Date", now.to_rfc2822());
+        headers.set(
+            "Message-ID",
+            format!(
+                "<draft.{}.{}@{domain}>",
+                now.timestamp_nanos_opt().unwrap_or_default(),
+                std::process::id()
+            ),
+        );
+        headers.set("MIME-Version", "1.0");
+        headers.set("Content-Type", "text/plain; charset=utf-8");
+        headers.set("Content-Transfer-Encoding", "8bit");
+
+        let body = self.body.replace("\r\n", "\n").replace('\n', "\r\n");
+        mailledger_mime::Message::single_part(headers, body.into_bytes())
+    }
+
     /// Returns all recipients (to, cc, bcc).
     fn all_recipients(&self) -> Vec<&str> {
         let mut recipients: Vec<&str> = Vec::new();
@@ -138,19 +252,325 @@ impl OutgoingMessage {
 ///
 /// Returns an error if connection, authentication, or sending fails.
 pub async fn send_email(account: &Account, message: OutgoingMessage) -> Result<(), SmtpError> {
-    use mailledger_smtp::connection::{connect, connect_tls};
-    use mailledger_smtp::{Address, Client};
+    let mut session = SmtpSession::connect(account).await?;
+    session.send(&message).await?;
+    session.close().await
+}
+
+/// Send a read receipt (RFC 8098) for a message that asked for one.
+///
+/// `mode` records whether the user chose to send it or it went out by
+/// policy. The receipt always reports the message as displayed, and is
+/// sent with the null reverse-path so it can't trigger a notification in
+/// turn.
+///
+/// # Errors
+///
+/// Returns an error if the message did not ask for a receipt, or if
+/// connection, authentication, or sending fails.
+pub async fn send_read_receipt(
+    account: &Account,
+    content: &MessageContent,
+    mode: MdnMode,
+) -> Result<(), SmtpError> {
+    let (Some(notify_to), Some(receipt)) = (
+        content.read_receipt_to.as_deref(),
+        read_receipt(content, mode, &account.email),
+    ) else {
+        return Err(SmtpError::InvalidAddress(
+            "The message did not ask for a read receipt".into(),
+        ));
+    };
+
+    let mut session = SmtpSession::connect(account).await?;
+    session
+        .send_raw("", &[envelope_address(notify_to)], &receipt.to_eml_bytes())
+        .await?;
+    session.close().await
+}
+
+/// Returns true if the read receipt `content` asks for may be sent without
+/// asking the user.
+///
+/// RFC 8098 section 2.1 wants the user asked when the receipt would go
+/// somewhere other than the message's `Return-Path`, since anyone can ask
+/// for receipts to be sent to a third party.
+#[must_use]
+pub fn read_receipt_matches_return_path(content: &MessageContent) -> bool {
+    let Some(notify_to) = content.read_receipt_to.as_deref() else {
+        return false;
+    };
+    original_headers(content)
+        .and_then(|original| {
+            original.headers.get("return-path").map(|path| {
+                envelope_address(path).eq_ignore_ascii_case(envelope_address(notify_to))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Builds the receipt for `content` from `recipient`, if one was requested.
+fn read_receipt(
+    content: &MessageContent,
+    mode: MdnMode,
+    recipient: &str,
+) -> Option<mailledger_mime::Message> {
+    generate_mdn(
+        &original_headers(content)?,
+        MdnDisposition::Displayed,
+        mode,
+        recipient,
+    )
+}
+
+/// Parses the header block of the message `content` was loaded from.
+fn original_headers(content: &MessageContent) -> Option<mailledger_mime::Message> {
+    mailledger_mime::Message::parse(&content.raw_headers).ok()
+}
+
+/// Strips a display name, leaving the address an SMTP envelope needs.
+fn envelope_address(mailbox: &str) -> &str {
+    mailbox
+        .rsplit_once('<')
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .map_or(mailbox, |(address, _)| address)
+        .trim()
+}
+
+/// Sends several messages over one SMTP connection.
+///
+/// Returns one result per message, in order, so callers can report which
+/// messages went out.
+///
+/// # Errors
+///
+/// Returns an error if the initial connection or authentication fails.
+pub async fn send_batch(
+    account: &Account,
+    messages: Vec<OutgoingMessage>,
+) -> Result<Vec<Result<(), SmtpError>>, SmtpError> {
+    let mut session = SmtpSession::connect(account).await?;
+    let mut results = Vec::with_capacity(messages.len());
+    for message in &messages {
+        results.push(session.send(message).await);
+    }
+    if let Err(e) = session.close().await {
+        tracing::debug!("SMTP QUIT failed after batch: {e}");
+    }
+    Ok(results)
+}
+
+/// Outcome of [`flush_outbox`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushSummary {
+    /// Messages delivered.
+    pub sent: usize,
+    /// Messages that failed and will be retried later.
+    pub retrying: usize,
+    /// Messages that failed for good.
+    pub failed: usize,
+}
+
+/// Sends every outbox message of `account` whose next attempt is due.
+///
+/// Failed messages are retried with exponential backoff until they have
+/// been tried [`MAX_SEND_ATTEMPTS`] times, or marked failed straight away if
+/// the server rejected them permanently.
+///
+/// # Errors
+///
+/// Returns an error if the outbox database cannot be read or updated.
+pub async fn flush_outbox(
+    account: &Account,
+    outbox: &OutboxRepository,
+) -> crate::Result<FlushSummary> {
+    let mut summary = FlushSummary::default();
+    let Some(account_id) = account.id else {
+        return Ok(summary);
+    };
+    let due = outbox.due(account_id).await?;
+    if due.is_empty() {
+        return Ok(summary);
+    }
+
+    let mut session = match SmtpSession::connect(account).await {
+        Ok(session) => session,
+        Err(e) => {
+            for entry in &due {
+                record_failure(outbox, entry, &e, &mut summary).await?;
+            }
+            return Ok(summary);
+        }
+    };
+    for entry in &due {
+        outbox.mark_sending(entry.id).await?;
+        match session.send(&entry.message).await {
+            Ok(()) => {
+                outbox.mark_sent(entry.id).await?;
+                summary.sent += 1;
+            }
+            Err(e) => record_failure(outbox, entry, &e, &mut summary).await?,
+        }
+    }
+    if let Err(e) = session.close().await {
+        tracing::debug!("SMTP QUIT failed after flushing outbox: {e}");
+    }
+    Ok(summary)
+}
+
+/// Schedules a retry for a failed outbox entry, or gives up on it.
+async fn record_failure(
+    outbox: &OutboxRepository,
+    entry: &OutboxEntry,
+    error: &SmtpError,
+    summary: &mut FlushSummary,
+) -> crate::Result<()> {
+    let attempts = entry.attempts + 1;
+    if error.is_permanent() || attempts >= MAX_SEND_ATTEMPTS {
+        outbox.mark_failed(entry.id, &error.to_string()).await?;
+        summary.failed += 1;
+    } else {
+        let next_attempt_at = chrono::Utc::now() + OutboxEntry::retry_delay(attempts);
+        outbox
+            .schedule_retry(entry.id, &error.to_string(), next_attempt_at)
+            .await?;
+        summary.retrying += 1;
+    }
+    Ok(())
+}
 
-    // Validate recipients
-    if message.to.is_empty() {
-        return Err(SmtpError::InvalidAddress("No recipients specified".into()));
+/// Client ready to start a mail transaction.
+enum ReadyClient {
+    /// Freshly logged in.
+    Authenticated(Client<Authenticated>),
+    /// Returned from a completed transaction.
+    Connected(Client<Connected>),
+}
+
+/// An SMTP connection kept open across several messages.
+///
+/// The connection is reset with RSET before each message after the first.
+/// If the server has closed it (for example with `421`), the session logs
+/// in again. A failed message drops the connection; the next one reconnects.
+pub struct SmtpSession {
+    account: Account,
+    client: Option<ReadyClient>,
+}
+
+impl SmtpSession {
+    /// Connects and authenticates with the account's SMTP server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if connection or authentication fails.
+    pub async fn connect(account: &Account) -> Result<Self, SmtpError> {
+        let client = open_client(account).await?;
+        Ok(Self {
+            account: account.clone(),
+            client: Some(ReadyClient::Authenticated(client)),
+        })
+    }
+
+    /// Sends one message over the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an address is invalid, the server rejects the
+    /// message, or reconnecting fails.
+    pub async fn send(&mut self, message: &OutgoingMessage) -> Result<(), SmtpError> {
+        if message.to.is_empty() {
+            return Err(SmtpError::InvalidAddress("No recipients specified".into()));
+        }
+        let data = message.to_rfc5322();
+        self.send_raw(&message.from, &message.all_recipients(), data.as_bytes())
+            .await
     }
 
-    let all_recipients = message.all_recipients();
-    if all_recipients.is_empty() {
-        return Err(SmtpError::InvalidAddress("No recipients specified".into()));
+    /// Sends an already serialized RFC 5322 message to the envelope
+    /// recipients, which may differ from its headers.
+    ///
+    /// An empty `from` sends the message with the null reverse-path `<>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an address is invalid, the server rejects the
+    /// message, or reconnecting fails.
+    pub async fn send_raw(
+        &mut self,
+        from: &str,
+        recipients: &[&str],
+        data: &[u8],
+    ) -> Result<(), SmtpError> {
+        if recipients.is_empty() {
+            return Err(SmtpError::InvalidAddress("No recipients specified".into()));
+        }
+        let from = if from.is_empty() {
+            Address::null()
+        } else {
+            Address::new(from).map_err(|e| SmtpError::InvalidAddress(e.to_string()))?
+        };
+        let recipients = recipients
+            .iter()
+            .map(|addr| Address::new(*addr).map_err(|e| SmtpError::InvalidAddress(e.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (client, reused) = self.ready_client().await?;
+        match transmit(client, &from, &recipients, data).await {
+            Ok(client) => {
+                self.client = Some(ReadyClient::Connected(client));
+                Ok(())
+            }
+            Err(e) if reused && is_disconnect(&e) => {
+                tracing::debug!("SMTP connection dropped, reconnecting: {e}");
+                let client = ReadyClient::Authenticated(open_client(&self.account).await?);
+                let client = transmit(client, &from, &recipients, data)
+                    .await
+                    .map_err(SmtpError::from)?;
+                self.client = Some(ReadyClient::Connected(client));
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
     }
 
+    /// Sends QUIT and closes the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the QUIT command fails.
+    pub async fn close(mut self) -> Result<(), SmtpError> {
+        let result = match self.client.take() {
+            Some(ReadyClient::Authenticated(client)) => client.quit().await,
+            Some(ReadyClient::Connected(client)) => client.quit().await,
+            None => return Ok(()),
+        };
+        result.map_err(|e| SmtpError::Send(e.to_string()))
+    }
+
+    /// Takes the client for the next message, resetting a reused connection
+    /// and reconnecting if that fails. Also reports whether it was reused.
+    async fn ready_client(&mut self) -> Result<(ReadyClient, bool), SmtpError> {
+        match self.client.take() {
+            Some(ReadyClient::Connected(client)) => match client.reset().await {
+                Ok(client) => Ok((ReadyClient::Connected(client), true)),
+                Err(e) => {
+                    tracing::debug!("SMTP RSET failed, reconnecting: {e}");
+                    let client = open_client(&self.account).await?;
+                    Ok((ReadyClient::Authenticated(client), false))
+                }
+            },
+            Some(client @ ReadyClient::Authenticated(_)) => Ok((client, false)),
+            None => {
+                let client = open_client(&self.account).await?;
+                Ok((ReadyClient::Authenticated(client), false))
+            }
+        }
+    }
+}
+
+/// Connects to the account's SMTP server, upgrades to TLS if configured and
+/// logs in.
+async fn open_client(account: &Account) -> Result<Client<Authenticated>, SmtpError> {
     // Connect based on security mode
     let stream = match account.smtp.security {
         Security::Tls => connect_tls(&account.smtp.host, account.smtp.port)
@@ -183,56 +603,383 @@ pub async fn send_email(account: &Account, message: OutgoingMessage) -> Result<(
     };
 
     // Authenticate
-    let client = client
-        .auth_plain(&account.smtp.username, &account.smtp.password)
-        .await
-        .map_err(|e| SmtpError::Authentication(e.to_string()))?;
+    let username = &account.smtp.username;
+    let result = match &account.smtp.auth {
+        AuthMethod::Password(password) => {
+            let offered = client.server_info().auth_mechanisms();
+            match password_mechanism(&offered) {
+                AuthMechanism::Login => client.auth_login(username, password).await,
+                AuthMechanism::CramMd5 => client.auth_cram_md5(username, password).await,
+                _ => client.auth_plain(username, password).await,
+            }
+        }
+        AuthMethod::OAuth {
+            provider,
+            token,
+            client_id,
+        } => {
+            let token = fresh_token(account.id, *provider, token, client_id)
+                .await
+                .map_err(|e| SmtpError::Authentication(e.to_string()))?;
+            let offered = client.server_info().auth_mechanisms();
+            let mechanism = OAuthMechanism::choose(|m| {
+                AuthMechanism::parse(m).is_some_and(|m| offered.contains(&m))
+            });
+            let smtp_mechanism = match mechanism {
+                OAuthMechanism::OAuthBearer => AuthMechanism::OAuthBearer,
+                OAuthMechanism::XOAuth2 => AuthMechanism::XOAuth2,
+            };
+            client
+                .auth(smtp_mechanism, mechanism.initial_response(username, &token))
+                .await
+        }
+    };
+    result.map_err(|e| SmtpError::Authentication(e.to_string()))
+}
 
-    // Start mail transaction
-    let from_addr =
-        Address::new(&message.from).map_err(|e| SmtpError::InvalidAddress(e.to_string()))?;
+/// Picks the mechanism for password sign-in from those the server offers.
+///
+/// PLAIN is preferred, and also used when the server lists nothing. LOGIN
+/// sends the same secrets in two steps, so it comes next; CRAM-MD5 is the
+/// last resort since many servers can't verify it for hashed passwords.
+fn password_mechanism(offered: &[AuthMechanism]) -> AuthMechanism {
+    [
+        AuthMechanism::Plain,
+        AuthMechanism::Login,
+        AuthMechanism::CramMd5,
+    ]
+    .into_iter()
+    .find(|mechanism| offered.contains(mechanism))
+    .unwrap_or(AuthMechanism::Plain)
+}
 
-    let client = client
-        .mail_from(from_addr)
-        .await
-        .map_err(|e| SmtpError::Send(e.to_string()))?;
+/// Runs one mail transaction, returning the client ready for the next one.
+async fn transmit(
+    client: ReadyClient,
+    from: &Address,
+    recipients: &[Address],
+    data: &[u8],
+) -> mailledger_smtp::Result<Client<Connected>> {
+    let mut client = match client {
+        ReadyClient::Authenticated(client) => client.mail_from(from.clone()).await?,
+        ReadyClient::Connected(client) => client.mail_from(from.clone()).await?,
+    }
+    .rcpt_to(recipients[0].clone())
+    .await?;
 
-    // Add first recipient
-    let first_recipient =
-        Address::new(all_recipients[0]).map_err(|e| SmtpError::InvalidAddress(e.to_string()))?;
+    for recipient in &recipients[1..] {
+        client = client.rcpt_to(recipient.clone()).await?;
+    }
 
-    let mut client = client
-        .rcpt_to(first_recipient)
-        .await
-        .map_err(|e| SmtpError::Send(e.to_string()))?;
-
-    // Add remaining recipients
-    for recipient in all_recipients.iter().skip(1) {
-        let addr =
-            Address::new(*recipient).map_err(|e| SmtpError::InvalidAddress(e.to_string()))?;
-        client = client
-            .rcpt_to(addr)
+    client.data().await?.send_message(data).await
+}
+
+/// Returns true if the error means the server closed the connection.
+const fn is_disconnect(error: &mailledger_smtp::Error) -> bool {
+    matches!(
+        error,
+        mailledger_smtp::Error::Io(_) | mailledger_smtp::Error::SmtpError { code: 421, .. }
+    )
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use super::*;
+    use crate::outbox::OutboxStatus;
+
+    /// Starts a server that accepts every message and returns the commands
+    /// received on each connection. With `drop_on_rset`, the first
+    /// connection answers RSET with `421` and closes.
+    async fn serve(connections: usize, drop_on_rset: bool) -> (Account, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let mut transcripts = Vec::new();
+            for n in 0..connections {
+                let (socket, _) = listener.accept().await.unwrap();
+                let mut socket = BufReader::new(socket);
+                let mut commands = String::new();
+                let mut in_data = false;
+                socket.write_all(b"220 test ESMTP\r\n").await.unwrap();
+                loop {
+                    let mut line = String::new();
+                    if socket.read_line(&mut line).await.unwrap() == 0 {
+                        break;
+                    }
+                    if in_data {
+                        if line == ".\r\n" {
+                            in_data = false;
+                            socket.write_all(b"250 queued\r\n").await.unwrap();
+                        }
+                        continue;
+                    }
+                    let verb = line.split([' ', '\r']).next().unwrap().to_string();
+                    commands.push_str(&verb);
+                    commands.push(' ');
+                    let reply: &[u8] = match verb.as_str() {
+                        "AUTH" => b"235 ok\r\n",
+                        "DATA" => {
+                            in_data = true;
+                            b"354 go ahead\r\n"
+                        }
+                        "RSET" if drop_on_rset && n == 0 => {
+                            socket.write_all(b"421 closing\r\n").await.unwrap();
+                            break;
+                        }
+                        "QUIT" => b"221 bye\r\n",
+                        _ => b"250 ok\r\n",
+                    };
+                    socket.write_all(reply).await.unwrap();
+                }
+                transcripts.push(commands.trim_end().to_string());
+            }
+            transcripts
+        });
+        let mut account = Account::default();
+        account.smtp.host = "127.0.0.1".to_string();
+        account.smtp.port = port;
+        account.smtp.security = Security::None;
+        (account, handle)
+    }
+
+    #[test]
+    fn read_receipt_answers_the_requested_address() {
+        let content = MessageContent {
+            uid: mailledger_imap::types::Uid::new(7).unwrap(),
+            subject: "Contract".to_string(),
+            from: "Alice <alice@example.com>".to_string(),
+            to: vec!["me@example.com".to_string()],
+            cc: Vec::new(),
+            date: String::new(),
+            body_text: None,
+            body_html: None,
+            attachments: Vec::new(),
+            unsubscribe: None,
+            message_id: Some("<c1@example.com>".to_string()),
+            read_receipt_to: Some("Alice <receipts@example.com>".to_string()),
+            receipt: None,
+            raw_headers: b"Return-Path: <alice@example.com>\r\n\
+                From: Alice <alice@example.com>\r\n\
+                Subject: Contract\r\n\
+                Message-ID: <c1@example.com>\r\n\
+                Original-Recipient: rfc822;me@example.com\r\n\
+                Disposition-Notification-To: Alice <receipts@example.com>\r\n\r\n"
+                .to_vec(),
+        };
+
+        let receipt = read_receipt(&content, MdnMode::Automatic, "me@example.com").unwrap();
+        assert_eq!(
+            receipt.headers.get("to"),
+            Some("Alice <receipts@example.com>")
+        );
+        let report = mailledger_mime::parse_mdn(&receipt).unwrap();
+        assert_eq!(
+            report.original_message_id.as_deref(),
+            Some("<c1@example.com>")
+        );
+        assert_eq!(report.mode, MdnMode::Automatic);
+        let fields = String::from_utf8_lossy(&receipt.parts[1].body).into_owned();
+        assert!(fields.contains("Original-Recipient: rfc822;me@example.com\r\n"));
+        let returned = receipt.parts[2].body.as_slice();
+        assert!(returned.starts_with(b"Return-Path: <alice@example.com>\r\n"));
+        assert_eq!(
+            envelope_address(content.read_receipt_to.as_deref().unwrap()),
+            "receipts@example.com"
+        );
+
+        // Receipts to a third party need the user's consent
+        assert!(!read_receipt_matches_return_path(&content));
+        let own_address = MessageContent {
+            read_receipt_to: Some("Alice <ALICE@example.com>".to_string()),
+            ..content.clone()
+        };
+        assert!(read_receipt_matches_return_path(&own_address));
+
+        let unrequested = MessageContent {
+            read_receipt_to: None,
+            raw_headers: b"From: Alice <alice@example.com>\r\n\r\n".to_vec(),
+            ..content
+        };
+        assert!(read_receipt(&unrequested, MdnMode::Manual, "me@example.com").is_none());
+    }
+
+    fn message(subject: &str) -> OutgoingMessage {
+        OutgoingMessage::new("a@example.com", subject, "body").to("b@example.com")
+    }
+
+    #[test]
+    fn password_mechanism_follows_server_offer() {
+        use AuthMechanism::{CramMd5, Login, Plain, XOAuth2};
+
+        assert_eq!(password_mechanism(&[]), Plain);
+        assert_eq!(password_mechanism(&[CramMd5, Login, Plain]), Plain);
+        assert_eq!(password_mechanism(&[XOAuth2, CramMd5, Login]), Login);
+        assert_eq!(password_mechanism(&[CramMd5]), CramMd5);
+    }
+
+    #[tokio::test]
+    async fn send_batch_reuses_one_connection() {
+        let (account, server) = serve(1, false).await;
+
+        let results = send_batch(&account, vec![message("one"), message("two")])
             .await
-            .map_err(|e| SmtpError::Send(e.to_string()))?;
+            .unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            server.await.unwrap(),
+            ["EHLO AUTH MAIL RCPT DATA RSET MAIL RCPT DATA QUIT"]
+        );
     }
 
-    // Send message data
-    let client = client
-        .data()
-        .await
-        .map_err(|e| SmtpError::Send(e.to_string()))?;
+    #[tokio::test]
+    async fn session_reconnects_after_421() {
+        let (account, server) = serve(2, true).await;
 
-    let rfc5322_message = message.to_rfc5322();
-    let client = client
-        .send_message(rfc5322_message.as_bytes())
-        .await
-        .map_err(|e| SmtpError::Send(e.to_string()))?;
+        let results = send_batch(&account, vec![message("one"), message("two")])
+            .await
+            .unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            server.await.unwrap(),
+            [
+                "EHLO AUTH MAIL RCPT DATA RSET",
+                "EHLO AUTH MAIL RCPT DATA QUIT"
+            ]
+        );
+    }
 
-    // Quit
-    client
-        .quit()
-        .await
-        .map_err(|e| SmtpError::Send(e.to_string()))?;
+    fn rejected(code: u16, enhanced_code: Option<(u8, u8, u16)>) -> SmtpError {
+        mailledger_smtp::Error::SmtpError {
+            code,
+            message: "nope".to_string(),
+            enhanced_code,
+        }
+        .into()
+    }
 
-    Ok(())
+    #[test]
+    fn enhanced_codes_map_to_semantic_errors() {
+        assert!(matches!(
+            rejected(550, Some((5, 1, 1))),
+            SmtpError::MailboxNotFound(_)
+        ));
+        assert!(matches!(
+            rejected(452, Some((4, 2, 2))),
+            SmtpError::MailboxFull(_)
+        ));
+        assert!(matches!(
+            rejected(552, Some((5, 3, 4))),
+            SmtpError::MessageTooLarge(_)
+        ));
+        assert!(matches!(
+            rejected(550, Some((5, 7, 1))),
+            SmtpError::PolicyRejected(_)
+        ));
+        assert!(matches!(rejected(554, None), SmtpError::Send(_)));
+    }
+
+    #[test]
+    fn transient_failures_are_retryable() {
+        assert!(rejected(452, Some((4, 2, 2))).is_transient());
+        assert!(rejected(451, Some((4, 3, 0))).is_transient());
+        assert!(rejected(421, None).is_transient());
+        assert!(!rejected(550, Some((5, 1, 1))).is_transient());
+    }
+
+    #[tokio::test]
+    async fn send_rejects_message_without_recipients() {
+        let (account, _server) = serve(1, false).await;
+        let mut session = SmtpSession::connect(&account).await.unwrap();
+
+        let result = session
+            .send(&OutgoingMessage::new("a@example.com", "s", "b"))
+            .await;
+        assert!(matches!(result, Err(SmtpError::InvalidAddress(_))));
+    }
+
+    async fn queued(account: &Account, messages: &[OutgoingMessage]) -> OutboxRepository {
+        let outbox = OutboxRepository::in_memory().await.unwrap();
+        for message in messages {
+            outbox.enqueue(account.id.unwrap(), message).await.unwrap();
+        }
+        outbox
+    }
+
+    #[tokio::test]
+    async fn flush_outbox_sends_queued_messages() {
+        let (mut account, server) = serve(1, false).await;
+        account.id = Some(crate::AccountId(1));
+        let outbox = queued(&account, &[message("one"), message("two")]).await;
+
+        let summary = flush_outbox(&account, &outbox).await.unwrap();
+
+        assert_eq!(summary.sent, 2);
+        assert_eq!(
+            server.await.unwrap(),
+            ["EHLO AUTH MAIL RCPT DATA RSET MAIL RCPT DATA QUIT"]
+        );
+        let entries = outbox.list_for_account(crate::AccountId(1)).await.unwrap();
+        assert!(entries.iter().all(|e| e.status == OutboxStatus::Sent));
+        assert!(outbox.due(crate::AccountId(1)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn flush_outbox_gives_up_on_permanent_failure() {
+        let (mut account, _server) = serve(1, false).await;
+        account.id = Some(crate::AccountId(1));
+        let bad = OutgoingMessage::new("a@example.com", "bad", "body").to("nobody");
+        let outbox = queued(&account, &[bad, message("good")]).await;
+
+        let summary = flush_outbox(&account, &outbox).await.unwrap();
+
+        assert_eq!(
+            summary,
+            FlushSummary {
+                sent: 1,
+                retrying: 0,
+                failed: 1
+            }
+        );
+        let entries = outbox.list_for_account(crate::AccountId(1)).await.unwrap();
+        assert_eq!(entries[0].status, OutboxStatus::Failed);
+        assert_eq!(entries[0].attempts, 1);
+        assert!(entries[0].last_error.is_some());
+        assert_eq!(entries[1].status, OutboxStatus::Sent);
+    }
+
+    #[tokio::test]
+    async fn flush_outbox_retries_when_server_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut account = Account {
+            id: Some(crate::AccountId(1)),
+            ..Account::default()
+        };
+        account.smtp.host = "127.0.0.1".to_string();
+        account.smtp.port = listener.local_addr().unwrap().port();
+        account.smtp.security = Security::None;
+        drop(listener);
+        let outbox = queued(&account, &[message("one")]).await;
+
+        let summary = flush_outbox(&account, &outbox).await.unwrap();
+
+        assert_eq!(summary.retrying, 1);
+        let entries = outbox.list_for_account(crate::AccountId(1)).await.unwrap();
+        assert_eq!(entries[0].status, OutboxStatus::Queued);
+        assert!(entries[0].next_attempt_at > chrono::Utc::now());
+    }
 }
@@ -0,0 +1,406 @@
+//! UIDVALIDITY-aware delta sync of a selected folder.
+//!
+//! A folder's UIDs only stay meaningful while its UIDVALIDITY is unchanged
+//! (RFC 9051 section 2.3.1.1). [`sync_folder`] compares the server's value
+//! with the saved [`FolderSyncState`], and either starts over or asks only
+//! for what changed: new UIDs above `uid_next`, flag changes via CONDSTORE
+//! and expunged UIDs via QRESYNC.
+//!
+//! Cached UIDs are stale once UIDVALIDITY changes; [`current_uid`] finds
+//! such a message again by its `Message-ID` before it is moved or copied.
+
+use std::num::NonZeroU32;
+
+use mailledger_imap::command::{FetchAttribute, FetchItems, SearchCriteria};
+use mailledger_imap::connection::{Client, Selected};
+use mailledger_imap::parser::FetchItem;
+use mailledger_imap::types::{Capability, Uid, UidSet, UidValidity};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::cache::FolderSyncState;
+
+use super::mail::MailServiceError;
+
+/// What changed in a folder since its saved sync state.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncResult {
+    /// UIDVALIDITY changed (or nothing was saved yet), so every cached
+    /// message of the folder must be discarded and `added` holds all UIDs.
+    pub reset: bool,
+    /// UIDs of new messages, ascending.
+    pub added: Vec<Uid>,
+    /// UIDs of known messages whose flags changed, or `None` if the server
+    /// can't tell without CONDSTORE and the caller must refetch flags.
+    pub updated: Option<Vec<Uid>>,
+    /// UIDs of known messages that were expunged, or `None` if the server
+    /// can't report them without QRESYNC and the caller must compare the
+    /// cache against a `UID SEARCH`.
+    pub removed: Option<Vec<Uid>>,
+    /// State to save for the next sync.
+    pub state: FolderSyncState,
+}
+
+/// Work out what changed in the selected folder since `state`.
+///
+/// Uses the status of the SELECT that opened the folder, so the client
+/// should have been selected just before. Only UIDs are fetched; the
+/// caller fetches summaries for `added` and flags for `updated`.
+///
+/// # Errors
+///
+/// Returns an error if the server reported no UIDVALIDITY or a command
+/// fails.
+pub async fn sync_folder<S>(
+    client: &mut Client<S, Selected>,
+    state: &FolderSyncState,
+) -> Result<SyncResult, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let status = client.cached_status().clone();
+    let uid_validity = status
+        .uid_validity
+        .ok_or_else(|| MailServiceError::Operation("Server reported no UIDVALIDITY".to_string()))?
+        .get();
+    let reset = uid_validity != state.uid_validity;
+
+    let mut added = if reset {
+        client.uid_search("ALL").await
+    } else {
+        client
+            .uid_search(&format!("UID {}:*", state.uid_next.max(1)))
+            .await
+    }
+    .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+    if !reset {
+        // `n:*` always matches the newest message, even below `n`
+        added.retain(|uid| uid.get() >= state.uid_next);
+    }
+    added.sort_unstable();
+    added.dedup();
+
+    let mut highest_modseq = status.highest_mod_seq;
+    let (updated, removed) = if reset {
+        (Some(Vec::new()), Some(Vec::new()))
+    } else {
+        let known = state
+            .uid_next
+            .checked_sub(1)
+            .and_then(Uid::new)
+            .map(|last| UidSet::Range(Uid(NonZeroU32::MIN), last));
+        match (known, state.highest_modseq) {
+            (None, _) => (Some(Vec::new()), Some(Vec::new())),
+            (Some(known), Some(modseq)) if client.has_capability(&Capability::CondStore) => {
+                let (updated, removed, seen) = changes_since(client, &known, modseq).await?;
+                highest_modseq = highest_modseq.max(seen);
+                (Some(updated), removed)
+            }
+            (Some(_), _) => (None, None),
+        }
+    };
+
+    let uid_next = status
+        .uid_next
+        .map(Uid::get)
+        .into_iter()
+        .chain(added.last().map(|uid| uid.get().saturating_add(1)))
+        .chain((!reset).then_some(state.uid_next))
+        .max()
+        .unwrap_or(1);
+
+    Ok(SyncResult {
+        reset,
+        added,
+        updated,
+        removed,
+        state: FolderSyncState {
+            uid_validity,
+            uid_next,
+            highest_modseq,
+        },
+    })
+}
+
+/// Get the UID a cached message has in the selected folder now.
+///
+/// While the folder's UIDVALIDITY still matches `state`, `cached` is
+/// returned as is. Otherwise the message is looked up by `message_id`, so
+/// that a MOVE or COPY never hits whichever message took over the old UID.
+/// Returns `None` if the message can't be found that way.
+///
+/// # Errors
+///
+/// Returns an error if the search fails.
+pub async fn current_uid<S>(
+    client: &mut Client<S, Selected>,
+    state: &FolderSyncState,
+    cached: Uid,
+    message_id: Option<&str>,
+) -> Result<Option<Uid>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let uid_validity = client.cached_status().uid_validity.map(UidValidity::get);
+    if uid_validity == Some(state.uid_validity) {
+        return Ok(Some(cached));
+    }
+    match message_id {
+        Some(message_id) => resolve_uid_by_message_id(client, message_id).await,
+        None => Ok(None),
+    }
+}
+
+/// Find the UID of the message in the selected folder whose `Message-ID`
+/// header is `message_id`.
+///
+/// Returns `None` if no message matches, or if several do and it isn't
+/// clear which one is meant.
+///
+/// # Errors
+///
+/// Returns an error if the search fails.
+pub async fn resolve_uid_by_message_id<S>(
+    client: &mut Client<S, Selected>,
+    message_id: &str,
+) -> Result<Option<Uid>, MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let message_id = message_id.trim();
+    if message_id.is_empty() {
+        return Ok(None);
+    }
+    let criteria = SearchCriteria::Header("Message-ID".to_string(), message_id.to_string());
+    let mut uids = client
+        .uid_search_criteria(&criteria, None)
+        .await
+        .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+    uids.sort_unstable();
+    uids.dedup();
+    Ok(match uids.as_slice() {
+        [uid] => Some(*uid),
+        _ => None,
+    })
+}
+
+/// Fetch the UIDs in `known` whose flags changed since `modseq`, and the
+/// expunged ones if QRESYNC is enabled.
+///
+/// Also returns the highest mod-sequence seen in the answer.
+async fn changes_since<S>(
+    client: &mut Client<S, Selected>,
+    known: &UidSet,
+    modseq: u64,
+) -> Result<(Vec<Uid>, Option<Vec<Uid>>, Option<u64>), MailServiceError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let items = FetchItems::Items(vec![FetchAttribute::Uid]);
+    let (fetched, removed) = if client.is_enabled(&Capability::QResync) {
+        client
+            .fetch_vanished_since(known, items, modseq)
+            .await
+            .map(|(fetched, vanished)| (fetched, Some(vanished)))
+    } else {
+        client
+            .fetch_changed_since(&known.as_sequence_set(), items, modseq)
+            .await
+            .map(|fetched| (fetched, None))
+    }
+    .map_err(|e| MailServiceError::from_imap(&e, MailServiceError::Operation))?;
+
+    let mut updated = Vec::new();
+    let mut highest_modseq = None;
+    for item in fetched.into_iter().flat_map(|(_seq_num, items)| items) {
+        match item {
+            FetchItem::Uid(uid) => updated.push(uid),
+            FetchItem::ModSeq(seen) => highest_modseq = highest_modseq.max(Some(seen)),
+            _ => {}
+        }
+    }
+    updated.sort_unstable();
+    updated.dedup();
+    Ok((updated, removed, highest_modseq))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+    use super::*;
+
+    /// A folder with UIDVALIDITY 9, UIDs 3, 5 and 8, and UID 5 re-flagged
+    /// at mod-sequence 120.
+    async fn serve(stream: DuplexStream) -> Vec<String> {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+        write
+            .write_all(b"* OK [CAPABILITY IMAP4rev1 CONDSTORE] ready\r\n")
+            .await
+            .unwrap();
+        let mut commands = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let (tag, command) = line.split_once(' ').unwrap();
+            let reply = if command.starts_with("SELECT") {
+                format!(
+                    "* 3 EXISTS\r\n* OK [UIDVALIDITY 9] ok\r\n* OK [UIDNEXT 9] ok\r\n\
+                     * OK [HIGHESTMODSEQ 120] ok\r\n{tag} OK done\r\n"
+                )
+            } else if command == "UID SEARCH ALL" {
+                format!("* SEARCH 3 5 8\r\n{tag} OK done\r\n")
+            } else if command.starts_with("UID SEARCH UID") {
+                format!("* SEARCH 8\r\n{tag} OK done\r\n")
+            } else if command.starts_with("UID SEARCH HEADER Message-ID") {
+                // The message cached as UID 3 before UIDVALIDITY changed
+                format!("* SEARCH 12\r\n{tag} OK done\r\n")
+            } else if command.starts_with("UID FETCH") {
+                format!("* 2 FETCH (UID 5 MODSEQ (120))\r\n{tag} OK done\r\n")
+            } else {
+                format!("{tag} OK done\r\n")
+            };
+            write.write_all(reply.as_bytes()).await.unwrap();
+            commands.push(command.to_string());
+        }
+        commands
+    }
+
+    async fn selected() -> (
+        Client<DuplexStream, Selected>,
+        tokio::task::JoinHandle<Vec<String>>,
+    ) {
+        let (local, remote) = tokio::io::duplex(4096);
+        let server = tokio::spawn(serve(remote));
+        let client = Client::from_stream(local).await.unwrap();
+        let client = client.login("user", "pass").await.unwrap();
+        let (client, _) = client.select("INBOX").await.unwrap();
+        (client, server)
+    }
+
+    async fn sync(state: &FolderSyncState) -> (SyncResult, Vec<String>) {
+        let (mut client, server) = selected().await;
+        let result = sync_folder(&mut client, state).await.unwrap();
+        drop(client);
+        (result, server.await.unwrap())
+    }
+
+    fn uids(values: &[u32]) -> Vec<Uid> {
+        values.iter().map(|&n| Uid::new(n).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn validity_change_resets_and_lists_everything() {
+        let stale = FolderSyncState {
+            uid_validity: 4,
+            uid_next: 40,
+            highest_modseq: Some(500),
+        };
+        let (result, commands) = sync(&stale).await;
+
+        assert!(result.reset);
+        assert_eq!(result.added, uids(&[3, 5, 8]));
+        assert_eq!(result.updated, Some(Vec::new()));
+        assert_eq!(result.removed, Some(Vec::new()));
+        assert_eq!(
+            result.state,
+            FolderSyncState {
+                uid_validity: 9,
+                uid_next: 9,
+                highest_modseq: Some(120),
+            }
+        );
+        // The stale UIDs and mod-sequence are never sent
+        assert_eq!(commands[2..], ["UID SEARCH ALL"]);
+    }
+
+    #[tokio::test]
+    async fn first_sync_is_a_reset() {
+        let (result, _) = sync(&FolderSyncState::default()).await;
+        assert!(result.reset);
+        assert_eq!(result.added, uids(&[3, 5, 8]));
+    }
+
+    #[tokio::test]
+    async fn same_validity_fetches_only_the_delta() {
+        let saved = FolderSyncState {
+            uid_validity: 9,
+            uid_next: 6,
+            highest_modseq: Some(100),
+        };
+        let (result, commands) = sync(&saved).await;
+
+        assert!(!result.reset);
+        assert_eq!(result.added, uids(&[8]));
+        assert_eq!(result.updated, Some(uids(&[5])));
+        // QRESYNC isn't enabled, so expunges can't be reported
+        assert_eq!(result.removed, None);
+        assert_eq!(result.state.uid_next, 9);
+        assert_eq!(result.state.highest_modseq, Some(120));
+
+        assert_eq!(commands[2], "UID SEARCH UID 6:*");
+        assert_eq!(commands[3], "UID FETCH 1:5 UID (CHANGEDSINCE 100)");
+    }
+
+    #[tokio::test]
+    async fn without_saved_modseq_updates_are_unknown() {
+        let saved = FolderSyncState {
+            uid_validity: 9,
+            uid_next: 9,
+            highest_modseq: None,
+        };
+        let (result, commands) = sync(&saved).await;
+
+        // UID 8 is what the server matched for 9:*, but it isn't new
+        assert!(result.added.is_empty());
+        assert_eq!(result.updated, None);
+        assert_eq!(result.removed, None);
+        assert_eq!(commands[2..], ["UID SEARCH UID 9:*"]);
+    }
+
+    #[tokio::test]
+    async fn stale_uid_is_resolved_by_message_id() {
+        let stale = FolderSyncState {
+            uid_validity: 4,
+            uid_next: 40,
+            highest_modseq: None,
+        };
+        let (mut client, server) = selected().await;
+        let uid = current_uid(
+            &mut client,
+            &stale,
+            Uid::new(3).unwrap(),
+            Some("<abc@example.com>"),
+        )
+        .await
+        .unwrap();
+        drop(client);
+
+        assert_eq!(uid, Uid::new(12));
+        assert_eq!(
+            server.await.unwrap()[2..],
+            ["UID SEARCH HEADER Message-ID \"<abc@example.com>\""]
+        );
+    }
+
+    #[tokio::test]
+    async fn cached_uid_is_kept_while_validity_matches() {
+        let saved = FolderSyncState {
+            uid_validity: 9,
+            uid_next: 9,
+            highest_modseq: None,
+        };
+        let (mut client, server) = selected().await;
+        let uid = current_uid(
+            &mut client,
+            &saved,
+            Uid::new(3).unwrap(),
+            Some("<abc@example.com>"),
+        )
+        .await
+        .unwrap();
+        drop(client);
+
+        assert_eq!(uid, Uid::new(3));
+        assert!(server.await.unwrap()[2..].is_empty());
+    }
+}
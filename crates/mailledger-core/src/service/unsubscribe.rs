@@ -0,0 +1,319 @@
+//! Mailing-list unsubscribe (RFC 2369, RFC 8058).
+//!
+//! Newsletters advertise how to leave the list in `List-Unsubscribe`, and
+//! RFC 8058 senders add `List-Unsubscribe-Post` so a single HTTPS POST is
+//! enough, without opening a web page.
+
+use std::time::Duration;
+
+use mailledger_mime::Headers;
+use reqwest::Url;
+
+use crate::account::Account;
+
+use super::mail::MailServiceError;
+use super::smtp::{OutgoingMessage, send_email};
+
+/// Header fields fetched to build [`UnsubscribeInfo`].
+pub const UNSUBSCRIBE_HEADERS: [&str; 2] = ["LIST-UNSUBSCRIBE", "LIST-UNSUBSCRIBE-POST"];
+
+/// Form body of an RFC 8058 one-click POST.
+const ONE_CLICK_BODY: &str = "List-Unsubscribe=One-Click";
+
+/// Timeout for the one-click POST.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How to unsubscribe from the list a message was sent to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsubscribeInfo {
+    /// `mailto:` URI, including any `subject`/`body` query.
+    pub mailto: Option<String>,
+    /// HTTP(S) URL.
+    pub http: Option<Url>,
+    /// The sender supports RFC 8058 one-click POST to `http`.
+    pub one_click: bool,
+}
+
+impl UnsubscribeInfo {
+    /// Parses `List-Unsubscribe` and the optional `List-Unsubscribe-Post`.
+    ///
+    /// The first `mailto:` and first HTTP(S) URI are kept. One-click is only
+    /// set for HTTPS URLs, as RFC 8058 requires. Returns `None` if no usable
+    /// URI is present.
+    #[must_use]
+    pub fn parse(list_unsubscribe: &str, list_unsubscribe_post: Option<&str>) -> Option<Self> {
+        let mut mailto = None;
+        let mut http = None;
+        for uri in bracketed_uris(list_unsubscribe) {
+            if uri
+                .get(..7)
+                .is_some_and(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+            {
+                mailto.get_or_insert(uri);
+            } else if let Ok(url) = Url::parse(&uri)
+                && matches!(url.scheme(), "http" | "https")
+            {
+                http.get_or_insert(url);
+            }
+        }
+
+        if mailto.is_none() && http.is_none() {
+            return None;
+        }
+
+        let one_click = list_unsubscribe_post
+            .is_some_and(|post| post.trim().eq_ignore_ascii_case(ONE_CLICK_BODY))
+            && http.as_ref().is_some_and(|url| url.scheme() == "https");
+
+        Some(Self {
+            mailto,
+            http,
+            one_click,
+        })
+    }
+
+    /// Builds the info from a message's headers, if it has `List-Unsubscribe`.
+    #[must_use]
+    pub fn from_headers(headers: &Headers) -> Option<Self> {
+        Self::parse(
+            headers.get("list-unsubscribe")?,
+            headers.get("list-unsubscribe-post"),
+        )
+    }
+
+    /// Builds the info from a raw header block, such as the result of
+    /// fetching [`UNSUBSCRIBE_HEADERS`].
+    #[must_use]
+    pub fn from_header_block(raw: &[u8]) -> Option<Self> {
+        let headers = Headers::parse(&String::from_utf8_lossy(raw)).ok()?;
+        Self::from_headers(&headers)
+    }
+}
+
+/// Yields the contents of each `<...>` in a comma-separated URI list.
+///
+/// Whitespace inside the brackets is dropped, since folding may split long
+/// URIs (RFC 2369 section 2).
+fn bracketed_uris(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split(',').filter_map(|entry| {
+        let entry = entry.trim();
+        let inner = entry.strip_prefix('<')?.strip_suffix('>')?;
+        let uri: String = inner.chars().filter(|c| !c.is_whitespace()).collect();
+        (!uri.is_empty()).then_some(uri)
+    })
+}
+
+/// Unsubscribe from a mailing list.
+///
+/// Prefers the RFC 8058 one-click POST; otherwise sends the `mailto:`
+/// request from the account's address over SMTP. A plain HTTP link without
+/// one-click support needs a browser, so it is reported as an error for the
+/// caller to open instead.
+///
+/// # Errors
+///
+/// Returns an error if the POST or the email fails, or if neither method is
+/// available.
+pub async fn unsubscribe(
+    account: &Account,
+    info: &UnsubscribeInfo,
+) -> Result<(), MailServiceError> {
+    if info.one_click
+        && let Some(url) = &info.http
+    {
+        return post_one_click(url).await;
+    }
+
+    if let Some(mailto) = &info.mailto {
+        let message = mailto_message(&account.email, mailto)?;
+        return send_email(account, message)
+            .await
+            .map_err(|e| MailServiceError::Operation(e.to_string()));
+    }
+
+    Err(MailServiceError::Operation(
+        "This list can only be unsubscribed from in a browser".to_string(),
+    ))
+}
+
+/// Sends the one-click POST.
+async fn post_one_click(url: &Url) -> Result<(), MailServiceError> {
+    let client = reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+
+    client
+        .post(url.clone())
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(ONE_CLICK_BODY)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| MailServiceError::Operation(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Builds the email requested by a `mailto:` URI.
+fn mailto_message(from: &str, mailto: &str) -> Result<OutgoingMessage, MailServiceError> {
+    let url = Url::parse(mailto)
+        .map_err(|e| MailServiceError::Operation(format!("Invalid mailto URI: {e}")))?;
+    let to = url.path();
+    if to.is_empty() {
+        return Err(MailServiceError::Operation(
+            "mailto URI has no address".to_string(),
+        ));
+    }
+
+    let mut subject = "unsubscribe".to_string();
+    let mut body = String::new();
+    for (name, value) in url.query_pairs() {
+        if name.eq_ignore_ascii_case("subject") {
+            subject = value.into_owned();
+        } else if name.eq_ignore_ascii_case("body") {
+            body = value.into_owned();
+        }
+    }
+
+    Ok(OutgoingMessage::new(from, subject, body).to(to))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn parses_one_click_https() {
+        let info = UnsubscribeInfo::parse(
+            "<mailto:leave@lists.example.com?subject=unsubscribe>, \
+             <https://example.com/unsub?id=42>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+        assert_eq!(
+            info.mailto.as_deref(),
+            Some("mailto:leave@lists.example.com?subject=unsubscribe")
+        );
+        assert_eq!(
+            info.http.unwrap().as_str(),
+            "https://example.com/unsub?id=42"
+        );
+        assert!(info.one_click);
+    }
+
+    #[test]
+    fn mailto_only_is_not_one_click() {
+        let info = UnsubscribeInfo::parse(
+            "<mailto:leave@example.com>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+        assert_eq!(info.mailto.as_deref(), Some("mailto:leave@example.com"));
+        assert!(info.http.is_none());
+        assert!(!info.one_click);
+    }
+
+    #[test]
+    fn plain_http_or_missing_post_is_not_one_click() {
+        let http = UnsubscribeInfo::parse(
+            "<http://example.com/unsub>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+        assert!(!http.one_click);
+
+        let no_post = UnsubscribeInfo::parse("<https://example.com/unsub>", None).unwrap();
+        assert!(!no_post.one_click);
+    }
+
+    #[test]
+    fn keeps_first_of_each_kind_and_skips_junk() {
+        let info = UnsubscribeInfo::parse(
+            "unsubscribe here, <ftp://example.com/x>, <https://a.example.com/1>, \
+             <https://b.example.com/2>, <MAILTO:first@example.com>, <mailto:second@example.com>",
+            None,
+        )
+        .unwrap();
+        assert_eq!(info.http.unwrap().host_str(), Some("a.example.com"));
+        assert_eq!(info.mailto.as_deref(), Some("MAILTO:first@example.com"));
+    }
+
+    #[test]
+    fn parses_folded_header() {
+        let info = UnsubscribeInfo::from_header_block(
+            b"List-Unsubscribe: <https://example.com/unsubscribe/\r\n \
+             abcdef>,\r\n <mailto:leave@example.com>\r\n\
+             List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            info.http.unwrap().as_str(),
+            "https://example.com/unsubscribe/abcdef"
+        );
+        assert!(info.one_click);
+    }
+
+    #[test]
+    fn no_usable_uri_is_none() {
+        assert!(UnsubscribeInfo::parse("", None).is_none());
+        assert!(UnsubscribeInfo::parse("<javascript:alert(1)>, junk", None).is_none());
+        assert!(UnsubscribeInfo::from_headers(&Headers::new()).is_none());
+    }
+
+    #[test]
+    fn mailto_message_uses_query_subject_and_body() {
+        let message = mailto_message(
+            "me@example.com",
+            "mailto:leave@example.com?subject=Remove%20me&body=please",
+        )
+        .unwrap();
+        assert_eq!(message.to, ["leave@example.com"]);
+        assert_eq!(message.subject, "Remove me");
+        assert_eq!(message.body, "please");
+
+        let default = mailto_message("me@example.com", "mailto:leave@example.com").unwrap();
+        assert_eq!(default.subject, "unsubscribe");
+    }
+
+    #[tokio::test]
+    async fn one_click_posts_form_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "http://{}/unsub?id=7",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(ONE_CLICK_BODY.as_bytes()) {
+                let n = stream.read(&mut buf).await.unwrap();
+                assert!(n > 0, "connection closed before the body arrived");
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        post_one_click(&url).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /unsub?id=7 HTTP/1.1\r\n"));
+        assert!(
+            request
+                .to_lowercase()
+                .contains("content-type: application/x-www-form-urlencoded")
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Conversation threading.
+//!
+//! Implements Jamie Zawinski's threading algorithm
+//! (<https://www.jwz.org/doc/threading.html>): messages are linked through
+//! their `References` and `In-Reply-To` headers, missing ancestors become
+//! placeholder nodes, and threads whose roots were lost are merged by
+//! subject.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mailledger_core::threading::build_threads;
+//!
+//! for thread in build_threads(&messages) {
+//!     println!("{} messages", thread.message_count());
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::service::MessageSummary;
+
+/// A node in a conversation tree.
+#[derive(Debug, Clone)]
+pub struct ThreadNode {
+    /// The message at this position, or `None` for a placeholder standing
+    /// in for a referenced message that isn't available.
+    pub message: Option<MessageSummary>,
+    /// Replies to this message, in the order they were seen.
+    pub children: Vec<Self>,
+}
+
+impl ThreadNode {
+    /// Returns true if this node stands in for a missing message.
+    #[must_use]
+    pub const fn is_placeholder(&self) -> bool {
+        self.message.is_none()
+    }
+
+    /// Returns the number of messages in this subtree, excluding placeholders.
+    #[must_use]
+    pub fn message_count(&self) -> usize {
+        usize::from(self.message.is_some())
+            + self.children.iter().map(Self::message_count).sum::<usize>()
+    }
+}
+
+/// Groups messages into conversation trees.
+///
+/// Returns one root per thread, in the order the threads were first seen.
+/// Reference loops are broken, and placeholders are kept only where they
+/// join several replies to a missing root.
+#[must_use]
+pub fn build_threads(messages: &[MessageSummary]) -> Vec<ThreadNode> {
+    let mut arena = Arena::default();
+    for (idx, message) in messages.iter().enumerate() {
+        arena.add(idx, message);
+    }
+
+    let roots = (0..arena.containers.len())
+        .filter(|&c| arena.containers[c].parent.is_none())
+        .collect();
+    let roots = arena.prune(roots, true);
+    let roots = arena.group_by_subject(roots, messages);
+
+    roots
+        .into_iter()
+        .map(|root| arena.node(root, messages))
+        .collect()
+}
+
+/// A slot in the thread tree, possibly without a message.
+#[derive(Debug, Default)]
+struct Container {
+    message: Option<usize>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// Containers indexed by position, plus a Message-ID lookup.
+#[derive(Debug, Default)]
+struct Arena {
+    containers: Vec<Container>,
+    by_id: HashMap<String, usize>,
+}
+
+impl Arena {
+    fn new_container(&mut self) -> usize {
+        self.containers.push(Container::default());
+        self.containers.len() - 1
+    }
+
+    fn container_for(&mut self, id: &str) -> usize {
+        if let Some(&c) = self.by_id.get(id) {
+            return c;
+        }
+        let c = self.new_container();
+        self.by_id.insert(id.to_string(), c);
+        c
+    }
+
+    /// Returns true if `ancestor` is `node` or one of its parents.
+    fn is_ancestor(&self, ancestor: usize, mut node: usize) -> bool {
+        for _ in 0..=self.containers.len() {
+            if node == ancestor {
+                return true;
+            }
+            match self.containers[node].parent {
+                Some(parent) => node = parent,
+                None => return false,
+            }
+        }
+        // Only reachable if the parent links already form a cycle
+        true
+    }
+
+    fn unlink(&mut self, child: usize) {
+        if let Some(parent) = self.containers[child].parent.take() {
+            self.containers[parent].children.retain(|&c| c != child);
+        }
+    }
+
+    fn link(&mut self, parent: usize, child: usize) {
+        self.unlink(child);
+        self.containers[child].parent = Some(parent);
+        self.containers[parent].children.push(child);
+    }
+
+    /// Files one message, creating containers for everything it references.
+    fn add(&mut self, idx: usize, message: &MessageSummary) {
+        let this = match message.message_id.as_deref().map(str::trim) {
+            Some(id) if !id.is_empty() => {
+                let c = self.container_for(id);
+                // A duplicate Message-ID gets a container of its own
+                if self.containers[c].message.is_some() {
+                    self.new_container()
+                } else {
+                    c
+                }
+            }
+            _ => self.new_container(),
+        };
+        self.containers[this].message = Some(idx);
+
+        let mut references: Vec<&str> = message.references.iter().map(|r| r.trim()).collect();
+        if let Some(reply_to) = message.in_reply_to.as_deref().map(str::trim)
+            && !reply_to.is_empty()
+            && references.last() != Some(&reply_to)
+        {
+            references.push(reply_to);
+        }
+
+        // Chain the references in order, keeping links made by earlier messages
+        let mut prev = None;
+        for reference in references {
+            let c = self.container_for(reference);
+            if let Some(p) = prev
+                && self.containers[c].parent.is_none()
+                && !self.is_ancestor(c, p)
+            {
+                self.link(p, c);
+            }
+            prev = Some(c);
+        }
+
+        // The message's own headers are authoritative for its parent
+        match prev {
+            Some(parent) if !self.is_ancestor(this, parent) => self.link(parent, this),
+            Some(_) => {}
+            None => self.unlink(this),
+        }
+    }
+
+    /// Drops empty containers, promoting their children.
+    ///
+    /// At the root level an empty container is kept if it holds several
+    /// children, since it is the only thing tying those replies together.
+    fn prune(&mut self, nodes: Vec<usize>, is_root: bool) -> Vec<usize> {
+        let mut kept = Vec::new();
+        for c in nodes {
+            let children = std::mem::take(&mut self.containers[c].children);
+            let children = self.prune(children, false);
+
+            if self.containers[c].message.is_none() && (!is_root || children.len() <= 1) {
+                kept.extend(children);
+                continue;
+            }
+
+            for &child in &children {
+                self.containers[child].parent = Some(c);
+            }
+            self.containers[c].children = children;
+            kept.push(c);
+        }
+        if is_root {
+            for &c in &kept {
+                self.containers[c].parent = None;
+            }
+        }
+        kept
+    }
+
+    /// Merges root threads that share a normalized subject.
+    fn group_by_subject(&mut self, roots: Vec<usize>, messages: &[MessageSummary]) -> Vec<usize> {
+        let mut grouped = Vec::new();
+        let mut by_subject: HashMap<String, usize> = HashMap::new();
+
+        for root in roots {
+            let subject = self.subject(root, messages).map(|s| normalize_subject(s).0);
+            match subject {
+                Some(subject) if !subject.is_empty() => {
+                    if let Some(&pos) = by_subject.get(&subject) {
+                        grouped[pos] = self.merge(grouped[pos], root, messages);
+                    } else {
+                        by_subject.insert(subject, grouped.len());
+                        grouped.push(root);
+                    }
+                }
+                _ => grouped.push(root),
+            }
+        }
+        grouped
+    }
+
+    /// Returns the subject of a container, looking at its first child for placeholders.
+    fn subject<'a>(&self, c: usize, messages: &'a [MessageSummary]) -> Option<&'a str> {
+        let container = &self.containers[c];
+        container
+            .message
+            .or_else(|| {
+                container
+                    .children
+                    .first()
+                    .and_then(|&child| self.containers[child].message)
+            })
+            .map(|idx| messages[idx].subject.as_str())
+    }
+
+    /// Joins two roots with the same subject, returning the new root.
+    fn merge(&mut self, existing: usize, new: usize, messages: &[MessageSummary]) -> usize {
+        let existing_msg = self.containers[existing].message;
+        let new_msg = self.containers[new].message;
+
+        match (existing_msg, new_msg) {
+            (None, None) => {
+                for child in std::mem::take(&mut self.containers[new].children) {
+                    self.containers[child].parent = None;
+                    self.link(existing, child);
+                }
+                existing
+            }
+            (None, Some(_)) => {
+                self.link(existing, new);
+                existing
+            }
+            (Some(_), None) => {
+                self.link(new, existing);
+                new
+            }
+            (Some(a), Some(b)) => {
+                let existing_is_reply = normalize_subject(&messages[a].subject).1;
+                let new_is_reply = normalize_subject(&messages[b].subject).1;
+                if new_is_reply && !existing_is_reply {
+                    self.link(existing, new);
+                    existing
+                } else if existing_is_reply && !new_is_reply {
+                    self.link(new, existing);
+                    new
+                } else {
+                    let parent = self.new_container();
+                    self.link(parent, existing);
+                    self.link(parent, new);
+                    parent
+                }
+            }
+        }
+    }
+
+    fn node(&self, c: usize, messages: &[MessageSummary]) -> ThreadNode {
+        let container = &self.containers[c];
+        ThreadNode {
+            message: container.message.map(|idx| messages[idx].clone()),
+            children: container
+                .children
+                .iter()
+                .map(|&child| self.node(child, messages))
+                .collect(),
+        }
+    }
+}
+
+/// Strips reply and forward prefixes, returning the lowercased base subject
+/// and whether any prefix was removed.
+fn normalize_subject(subject: &str) -> (String, bool) {
+    const PREFIXES: [&str; 3] = ["re:", "fwd:", "fw:"];
+
+    let mut rest = subject.trim();
+    let mut stripped = false;
+    while let Some(prefix) = PREFIXES.iter().find(|p| {
+        rest.get(..p.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(p))
+    }) {
+        rest = rest[prefix.len()..].trim_start();
+        stripped = true;
+    }
+    (rest.to_lowercase(), stripped)
+}
+
+/// Extracts the `<...>` message IDs from a `References` or `In-Reply-To` value.
+#[must_use]
+pub fn parse_message_ids(value: &str) -> Vec<String> {
+    value
+        .split('<')
+        .skip(1)
+        .filter_map(|part| part.split_once('>'))
+        .map(|(id, _)| format!("<{}>", id.trim()))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use mailledger_imap::types::Uid;
+
+    use super::*;
+
+    fn message(uid: u32, subject: &str, id: &str, references: &[&str]) -> MessageSummary {
+        MessageSummary {
+            uid: Uid::new(uid).unwrap(),
+            subject: subject.to_string(),
+            from: String::new(),
+            from_email: String::new(),
+            to: String::new(),
+            date: String::new(),
+            is_read: false,
+            is_flagged: false,
+            has_attachment: false,
+            snippet: String::new(),
+            message_id: Some(id.to_string()),
+            in_reply_to: references.last().map(ToString::to_string),
+            thread_id: None,
+            references: references.iter().map(ToString::to_string).collect(),
+            labels: Vec::new(),
+        }
+    }
+
+    fn uid(node: &ThreadNode) -> Option<u32> {
+        node.message.as_ref().map(|m| m.uid.get())
+    }
+
+    #[test]
+    fn links_replies_through_references() {
+        let messages = [
+            message(1, "Plan", "<a@x>", &[]),
+            message(2, "Re: Plan", "<b@x>", &["<a@x>"]),
+            message(3, "Re: Plan", "<c@x>", &["<a@x>", "<b@x>"]),
+        ];
+        let threads = build_threads(&messages);
+
+        assert_eq!(threads.len(), 1);
+        assert_eq!(uid(&threads[0]), Some(1));
+        assert_eq!(uid(&threads[0].children[0]), Some(2));
+        assert_eq!(uid(&threads[0].children[0].children[0]), Some(3));
+        assert_eq!(threads[0].message_count(), 3);
+    }
+
+    #[test]
+    fn broken_reference_chain_skips_missing_message() {
+        // <b@x> was never fetched; C still hangs off A
+        let messages = [
+            message(1, "Plan", "<a@x>", &[]),
+            message(3, "Re: Plan", "<c@x>", &["<a@x>", "<b@x>"]),
+        ];
+        let threads = build_threads(&messages);
+
+        assert_eq!(threads.len(), 1);
+        assert_eq!(uid(&threads[0]), Some(1));
+        assert_eq!(threads[0].children.len(), 1);
+        assert_eq!(uid(&threads[0].children[0]), Some(3));
+    }
+
+    #[test]
+    fn ghost_parent_joins_sibling_replies() {
+        // Both reply to <root@x>, which isn't in the mailbox
+        let messages = [
+            message(2, "Re: Launch", "<b@x>", &["<root@x>"]),
+            message(3, "Re: Launch", "<c@x>", &["<root@x>"]),
+        ];
+        let threads = build_threads(&messages);
+
+        assert_eq!(threads.len(), 1);
+        assert!(threads[0].is_placeholder());
+        let children: Vec<_> = threads[0].children.iter().map(uid).collect();
+        assert_eq!(children, vec![Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn reference_loop_is_broken() {
+        let messages = [
+            message(1, "Loop", "<a@x>", &["<b@x>"]),
+            message(2, "Loop", "<b@x>", &["<a@x>"]),
+        ];
+        let threads = build_threads(&messages);
+
+        let total: usize = threads.iter().map(ThreadNode::message_count).sum();
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn subject_fallback_merges_orphaned_reply() {
+        let mut reply = message(2, "RE: Invoice", "<b@x>", &[]);
+        reply.in_reply_to = None;
+        let messages = [message(1, "Invoice", "<a@x>", &[]), reply];
+        let threads = build_threads(&messages);
+
+        assert_eq!(threads.len(), 1);
+        assert_eq!(uid(&threads[0]), Some(1));
+        assert_eq!(uid(&threads[0].children[0]), Some(2));
+    }
+
+    #[test]
+    fn unrelated_messages_stay_separate() {
+        let messages = [
+            message(1, "Lunch", "<a@x>", &[]),
+            message(2, "Standup", "<b@x>", &[]),
+        ];
+        assert_eq!(build_threads(&messages).len(), 2);
+    }
+
+    #[test]
+    fn parses_message_ids() {
+        assert_eq!(
+            parse_message_ids("<a@x>\r\n <b@x> junk <c@x>"),
+            vec!["<a@x>", "<b@x>", "<c@x>"]
+        );
+        assert!(parse_message_ids("none").is_empty());
+    }
+}
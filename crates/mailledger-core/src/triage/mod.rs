@@ -37,5 +37,5 @@
 mod model;
 mod repository;
 
-pub use model::{InboxCategory, ScreenedSender, SenderDecision};
+pub use model::{InboxCategory, ScreenedSender, SenderDecision, TriagedView};
 pub use repository::TriageRepository;
@@ -1,6 +1,9 @@
 //! Triage system data models.
 
+use serde::{Deserialize, Serialize};
+
 use crate::AccountId;
+use crate::service::MessageSummary;
 
 /// The user's decision about a sender.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -42,7 +45,8 @@ impl SenderDecision {
 /// - **Imbox**: Important emails from real people you care about
 /// - **Feed**: Newsletters and subscriptions you read when you have time
 /// - **Paper Trail**: Receipts, confirmations, and transactional emails
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InboxCategory {
     /// Important emails that deserve immediate attention.
     /// These appear front and center in your inbox.
@@ -181,6 +185,21 @@ impl ScreenedSender {
     }
 }
 
+/// A message list split by the sender decisions of its senders.
+#[derive(Debug, Clone, Default)]
+pub struct TriagedView {
+    /// Messages from senders approved into the Imbox.
+    pub imbox: Vec<MessageSummary>,
+    /// Messages from senders approved into The Feed.
+    pub feed: Vec<MessageSummary>,
+    /// Messages from senders approved into the Paper Trail.
+    pub paper_trail: Vec<MessageSummary>,
+    /// Messages from senders that have not been screened yet.
+    pub screener: Vec<MessageSummary>,
+    /// Messages from blocked senders, hidden from every other list.
+    pub blocked: Vec<MessageSummary>,
+}
+
 impl std::str::FromStr for SenderDecision {
     type Err = std::convert::Infallible;
 
@@ -1,11 +1,14 @@
 //! Triage repository for persistent storage of sender decisions.
 
+use std::collections::HashMap;
+
 use sqlx::Row;
 use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 
-use super::model::{InboxCategory, ScreenedSender, SenderDecision};
+use super::model::{InboxCategory, ScreenedSender, SenderDecision, TriagedView};
 use crate::Result;
 use crate::account::AccountId;
+use crate::service::MessageSummary;
 
 /// Repository for triage data (screened senders and categories).
 pub struct TriageRepository {
@@ -455,6 +458,68 @@ impl TriageRepository {
             }
         }))
     }
+
+    /// Route loaded messages by the decisions made about their senders.
+    ///
+    /// Messages from blocked senders are removed from `messages`, so callers
+    /// that keep showing the list no longer see them. Every message is also
+    /// copied into one bucket of the returned view: approved senders go to
+    /// their category, and senders that are unknown or still pending go to
+    /// the Screener.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database query fails.
+    pub async fn apply_triage(
+        &self,
+        account_id: AccountId,
+        messages: &mut Vec<MessageSummary>,
+    ) -> Result<TriagedView> {
+        let rows = sqlx::query(
+            r"
+            SELECT email, decision, category
+            FROM screened_senders
+            WHERE account_id = ?
+            ",
+        )
+        .bind(account_id.0)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let decisions: HashMap<String, (SenderDecision, InboxCategory)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("email"),
+                    (
+                        SenderDecision::parse(row.get("decision")),
+                        InboxCategory::parse(row.get("category")),
+                    ),
+                )
+            })
+            .collect();
+
+        let mut view = TriagedView::default();
+        messages.retain(|message| {
+            let decision = decisions.get(&message.from_email.to_lowercase());
+            let bucket = match decision {
+                Some((SenderDecision::Blocked, _)) => {
+                    view.blocked.push(message.clone());
+                    return false;
+                }
+                Some((SenderDecision::Approved, InboxCategory::Imbox)) => &mut view.imbox,
+                Some((SenderDecision::Approved, InboxCategory::Feed)) => &mut view.feed,
+                Some((SenderDecision::Approved, InboxCategory::PaperTrail)) => {
+                    &mut view.paper_trail
+                }
+                Some((SenderDecision::Pending, _)) | None => &mut view.screener,
+            };
+            bucket.push(message.clone());
+            true
+        });
+
+        Ok(view)
+    }
 }
 
 /// Statistics about triage state.
@@ -643,6 +708,104 @@ mod tests {
         );
     }
 
+    fn summary(uid: u32, from_email: &str) -> MessageSummary {
+        MessageSummary {
+            uid: mailledger_imap::types::Uid::new(uid).unwrap(),
+            subject: format!("Message {uid}"),
+            from: from_email.to_string(),
+            from_email: from_email.to_string(),
+            to: String::new(),
+            date: String::new(),
+            is_read: false,
+            is_flagged: false,
+            has_attachment: false,
+            snippet: String::new(),
+            message_id: None,
+            in_reply_to: None,
+            thread_id: None,
+            references: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    fn uids(messages: &[MessageSummary]) -> Vec<u32> {
+        messages.iter().map(|m| m.uid.get()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_apply_triage_buckets_mixed_senders() {
+        let repo = TriageRepository::in_memory().await.unwrap();
+        let account_id = AccountId::new(1);
+
+        for email in [
+            "friend@example.com",
+            "news@example.com",
+            "shop@example.com",
+            "spam@example.com",
+            "pending@example.com",
+        ] {
+            repo.record_sender(account_id, email, None).await.unwrap();
+        }
+        repo.approve_sender(account_id, "friend@example.com", InboxCategory::Imbox)
+            .await
+            .unwrap();
+        repo.approve_sender(account_id, "news@example.com", InboxCategory::Feed)
+            .await
+            .unwrap();
+        repo.approve_sender(account_id, "shop@example.com", InboxCategory::PaperTrail)
+            .await
+            .unwrap();
+        repo.block_sender(account_id, "spam@example.com")
+            .await
+            .unwrap();
+        // Decisions are per account
+        repo.record_sender(AccountId::new(2), "stranger@example.com", None)
+            .await
+            .unwrap();
+        repo.approve_sender(
+            AccountId::new(2),
+            "stranger@example.com",
+            InboxCategory::Imbox,
+        )
+        .await
+        .unwrap();
+
+        let mut messages = vec![
+            summary(1, "Friend@Example.com"),
+            summary(2, "news@example.com"),
+            summary(3, "spam@example.com"),
+            summary(4, "shop@example.com"),
+            summary(5, "pending@example.com"),
+            summary(6, "stranger@example.com"),
+            summary(7, "spam@example.com"),
+            summary(8, "friend@example.com"),
+        ];
+
+        let view = repo.apply_triage(account_id, &mut messages).await.unwrap();
+
+        assert_eq!(uids(&view.imbox), [1, 8]);
+        assert_eq!(uids(&view.feed), [2]);
+        assert_eq!(uids(&view.paper_trail), [4]);
+        assert_eq!(uids(&view.screener), [5, 6]);
+        assert_eq!(uids(&view.blocked), [3, 7]);
+        assert_eq!(uids(&messages), [1, 2, 4, 5, 6, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_triage_without_decisions_screens_everything() {
+        let repo = TriageRepository::in_memory().await.unwrap();
+        let mut messages = vec![summary(1, "a@example.com"), summary(2, "b@example.com")];
+
+        let view = repo
+            .apply_triage(AccountId::new(1), &mut messages)
+            .await
+            .unwrap();
+
+        assert_eq!(uids(&view.screener), [1, 2]);
+        assert!(view.imbox.is_empty() && view.blocked.is_empty());
+        assert_eq!(messages.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_stats() {
         let repo = TriageRepository::in_memory().await.unwrap();
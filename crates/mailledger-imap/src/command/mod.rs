@@ -2,17 +2,28 @@
 //!
 //! This module provides types and serialization for IMAP commands.
 
+mod search;
 mod serialize;
 mod tag_generator;
 mod types;
 
+use chrono::{DateTime, FixedOffset};
+
 use crate::types::{Flag, Mailbox, SequenceSet};
 
-pub use tag_generator::TagGenerator;
-pub use types::{FetchAttribute, FetchItems, SearchCriteria, StatusAttribute, StoreAction};
+pub use search::SearchCriteriaBuilder;
+pub use tag_generator::{TagGenerator, is_valid_tag};
+pub use types::{
+    AppendItem, FetchAttribute, FetchItems, ListReturnOpt, ListSelectOpt, SearchCriteria,
+    SearchReturnOption, SortKey, StatusAttribute, StoreAction, ThreadAlgorithm,
+};
 
+pub(crate) use serialize::{
+    split_at_literals, write_append_args, write_fetch_items, write_mailbox,
+};
 use serialize::{
-    write_astring, write_fetch_items, write_mailbox, write_search_criteria, write_store_action,
+    write_append_prefix, write_astring, write_list_extended, write_mailbox_pattern,
+    write_metadata_value, write_quoted, write_search_criteria, write_sort_keys, write_store_action,
 };
 
 /// IMAP command.
@@ -51,6 +62,8 @@ pub enum Command {
         /// None = ID NIL (no identification).
         parameters: Option<Vec<(String, String)>>,
     },
+    /// COMPRESS DEFLATE command (RFC 4978).
+    Compress,
     /// ENABLE command.
     Enable {
         /// Capabilities to enable.
@@ -102,8 +115,43 @@ pub enum Command {
         /// Mailbox pattern.
         pattern: String,
     },
+    /// LIST command with selection and return options (LIST-EXTENDED, RFC 5258).
+    ListExtended {
+        /// Selection options.
+        selection: Vec<ListSelectOpt>,
+        /// Reference name.
+        reference: String,
+        /// Mailbox patterns.
+        patterns: Vec<String>,
+        /// Return options.
+        return_opts: Vec<ListReturnOpt>,
+    },
     /// NAMESPACE command.
     Namespace,
+    /// GETQUOTAROOT command (RFC 9208).
+    GetQuotaRoot {
+        /// Mailbox whose quota roots to list.
+        mailbox: Mailbox,
+    },
+    /// GETQUOTA command (RFC 9208).
+    GetQuota {
+        /// Quota root name.
+        root: String,
+    },
+    /// GETMETADATA command (RFC 5464).
+    GetMetadata {
+        /// Mailbox to read annotations from, or empty for the server.
+        mailbox: Mailbox,
+        /// Entry names such as `/private/comment`.
+        entries: Vec<String>,
+    },
+    /// SETMETADATA command (RFC 5464).
+    SetMetadata {
+        /// Mailbox to annotate, or empty for the server.
+        mailbox: Mailbox,
+        /// Entry names and values; `None` removes the entry.
+        entries: Vec<(String, Option<String>)>,
+    },
     /// STATUS command.
     Status {
         /// Mailbox name.
@@ -117,9 +165,18 @@ pub enum Command {
         mailbox: Mailbox,
         /// Flags to set.
         flags: Option<Vec<Flag>>,
+        /// Internal date to set; the server uses the current time if `None`.
+        date: Option<DateTime<FixedOffset>>,
         /// Message data.
         message: Vec<u8>,
     },
+    /// APPEND of several messages in one command (MULTIAPPEND, RFC 3502).
+    MultiAppend {
+        /// Target mailbox.
+        mailbox: Mailbox,
+        /// Messages to upload, in order.
+        messages: Vec<AppendItem>,
+    },
 
     // Selected State Commands
     /// CLOSE command.
@@ -135,10 +192,36 @@ pub enum Command {
     },
     /// SEARCH command.
     Search {
+        /// Charset of the string arguments; `None` omits CHARSET (US-ASCII).
+        charset: Option<String>,
         /// Search criteria.
         criteria: SearchCriteria,
         /// Use UIDs.
         uid: bool,
+        /// ESEARCH result options (RFC 4731); empty for a plain SEARCH.
+        return_opts: Vec<SearchReturnOption>,
+    },
+    /// SORT command (RFC 5256).
+    Sort {
+        /// Sort keys, most significant first.
+        keys: Vec<SortKey>,
+        /// Charset for string comparisons in the criteria.
+        charset: String,
+        /// Search criteria selecting the messages to sort.
+        criteria: SearchCriteria,
+        /// Use UIDs.
+        uid: bool,
+    },
+    /// THREAD command (RFC 5256).
+    Thread {
+        /// Threading algorithm.
+        algorithm: ThreadAlgorithm,
+        /// Charset for string comparisons in the criteria.
+        charset: String,
+        /// Search criteria selecting the messages to thread.
+        criteria: SearchCriteria,
+        /// Use UIDs.
+        uid: bool,
     },
     /// FETCH command.
     Fetch {
@@ -148,6 +231,8 @@ pub enum Command {
         items: FetchItems,
         /// Use UIDs.
         uid: bool,
+        /// Only return messages whose mod-sequence is greater (CONDSTORE).
+        changed_since: Option<u64>,
     },
     /// STORE command.
     Store {
@@ -184,11 +269,47 @@ pub enum Command {
     Done,
 }
 
+/// Returns the line sent before each message literal of an APPEND.
+///
+/// The first line carries the tag and mailbox; every line ends with
+/// `{n}\r\n`, after which the client waits for a continuation request.
+pub(crate) fn append_lines(
+    tag: &str,
+    mailbox: &Mailbox,
+    items: &[AppendItem],
+    utf8: bool,
+) -> Vec<Vec<u8>> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let mut line = Vec::new();
+            if i == 0 {
+                line.extend_from_slice(tag.as_bytes());
+                line.extend_from_slice(b" APPEND ");
+                write_mailbox(&mut line, mailbox, utf8);
+            }
+            write_append_prefix(&mut line, item);
+            line.extend_from_slice(b"\r\n");
+            line
+        })
+        .collect()
+}
+
 impl Command {
     /// Serializes the command to bytes with the given tag.
+    ///
+    /// Mailbox names are encoded in modified UTF-7.
     #[must_use]
-    #[allow(clippy::too_many_lines)]
     pub fn serialize(&self, tag: &str) -> Vec<u8> {
+        self.serialize_with(tag, false)
+    }
+
+    /// Serializes the command, sending mailbox names as raw UTF-8 if
+    /// `utf8` is set (UTF8=ACCEPT enabled, RFC 6855).
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn serialize_with(&self, tag: &str, utf8: bool) -> Vec<u8> {
         let mut buf = Vec::new();
 
         // DONE doesn't get a tag (it's sent during IDLE)
@@ -202,6 +323,7 @@ impl Command {
             Self::Noop => buf.extend_from_slice(b"NOOP"),
             Self::Logout => buf.extend_from_slice(b"LOGOUT"),
             Self::StartTls => buf.extend_from_slice(b"STARTTLS"),
+            Self::Compress => buf.extend_from_slice(b"COMPRESS DEFLATE"),
 
             Self::Login { username, password } => {
                 buf.extend_from_slice(b"LOGIN ");
@@ -230,9 +352,10 @@ impl Command {
                         if i > 0 {
                             buf.push(b' ');
                         }
-                        write_astring(&mut buf, key);
+                        // Field names and values are strings, not atoms
+                        write_quoted(&mut buf, key);
                         buf.push(b' ');
-                        write_astring(&mut buf, value);
+                        write_quoted(&mut buf, value);
                     }
                     buf.push(b')');
                 } else {
@@ -250,7 +373,7 @@ impl Command {
 
             Self::Select { mailbox, condstore } => {
                 buf.extend_from_slice(b"SELECT ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
                 if *condstore {
                     buf.extend_from_slice(b" (CONDSTORE)");
                 }
@@ -258,48 +381,93 @@ impl Command {
 
             Self::Examine { mailbox } => {
                 buf.extend_from_slice(b"EXAMINE ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::Create { mailbox } => {
                 buf.extend_from_slice(b"CREATE ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::Delete { mailbox } => {
                 buf.extend_from_slice(b"DELETE ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::Rename { from, to } => {
                 buf.extend_from_slice(b"RENAME ");
-                write_mailbox(&mut buf, from);
+                write_mailbox(&mut buf, from, utf8);
                 buf.push(b' ');
-                write_mailbox(&mut buf, to);
+                write_mailbox(&mut buf, to, utf8);
             }
 
             Self::Subscribe { mailbox } => {
                 buf.extend_from_slice(b"SUBSCRIBE ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::Unsubscribe { mailbox } => {
                 buf.extend_from_slice(b"UNSUBSCRIBE ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::List { reference, pattern } => {
                 buf.extend_from_slice(b"LIST ");
-                write_astring(&mut buf, reference);
+                write_mailbox_pattern(&mut buf, reference, utf8);
                 buf.push(b' ');
-                write_astring(&mut buf, pattern);
+                write_mailbox_pattern(&mut buf, pattern, utf8);
             }
 
+            Self::ListExtended {
+                selection,
+                reference,
+                patterns,
+                return_opts,
+            } => write_list_extended(&mut buf, selection, reference, patterns, return_opts, utf8),
+
             Self::Namespace => buf.extend_from_slice(b"NAMESPACE"),
 
+            Self::GetQuotaRoot { mailbox } => {
+                buf.extend_from_slice(b"GETQUOTAROOT ");
+                write_mailbox(&mut buf, mailbox, utf8);
+            }
+
+            Self::GetQuota { root } => {
+                buf.extend_from_slice(b"GETQUOTA ");
+                write_astring(&mut buf, root);
+            }
+
+            Self::GetMetadata { mailbox, entries } => {
+                buf.extend_from_slice(b"GETMETADATA ");
+                write_mailbox(&mut buf, mailbox, utf8);
+                buf.extend_from_slice(b" (");
+                for (i, entry) in entries.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(b' ');
+                    }
+                    write_astring(&mut buf, entry);
+                }
+                buf.push(b')');
+            }
+
+            Self::SetMetadata { mailbox, entries } => {
+                buf.extend_from_slice(b"SETMETADATA ");
+                write_mailbox(&mut buf, mailbox, utf8);
+                buf.extend_from_slice(b" (");
+                for (i, (entry, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(b' ');
+                    }
+                    write_astring(&mut buf, entry);
+                    buf.push(b' ');
+                    write_metadata_value(&mut buf, value.as_deref());
+                }
+                buf.push(b')');
+            }
+
             Self::Status { mailbox, items } => {
                 buf.extend_from_slice(b"STATUS ");
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
                 buf.extend_from_slice(b" (");
                 for (i, item) in items.iter().enumerate() {
                     if i > 0 {
@@ -313,21 +481,22 @@ impl Command {
             Self::Append {
                 mailbox,
                 flags,
+                date,
                 message,
             } => {
                 buf.extend_from_slice(b"APPEND ");
-                write_mailbox(&mut buf, mailbox);
-                if let Some(flags) = flags {
-                    buf.extend_from_slice(b" (");
-                    for (i, flag) in flags.iter().enumerate() {
-                        if i > 0 {
-                            buf.push(b' ');
-                        }
-                        buf.extend_from_slice(flag.as_str().as_bytes());
-                    }
-                    buf.push(b')');
+                write_mailbox(&mut buf, mailbox, utf8);
+                write_append_args(&mut buf, flags.as_deref(), date.as_ref(), message.len());
+            }
+
+            Self::MultiAppend { mailbox, messages } => {
+                buf.extend_from_slice(b"APPEND ");
+                write_mailbox(&mut buf, mailbox, utf8);
+                for item in messages {
+                    write_append_prefix(&mut buf, item);
+                    buf.extend_from_slice(b"\r\n");
+                    buf.extend_from_slice(&item.message);
                 }
-                buf.extend_from_slice(format!(" {{{}}}", message.len()).as_bytes());
             }
 
             Self::Close => buf.extend_from_slice(b"CLOSE"),
@@ -339,11 +508,69 @@ impl Command {
                 buf.extend_from_slice(uids.to_string().as_bytes());
             }
 
-            Self::Search { criteria, uid } => {
+            Self::Search {
+                charset,
+                criteria,
+                uid,
+                return_opts,
+            } => {
                 if *uid {
                     buf.extend_from_slice(b"UID ");
                 }
                 buf.extend_from_slice(b"SEARCH ");
+                if !return_opts.is_empty() {
+                    buf.extend_from_slice(b"RETURN (");
+                    for (i, opt) in return_opts.iter().enumerate() {
+                        if i > 0 {
+                            buf.push(b' ');
+                        }
+                        buf.extend_from_slice(opt.as_str().as_bytes());
+                    }
+                    buf.extend_from_slice(b") ");
+                }
+                // Non-ASCII text is sent as UTF-8 unless told otherwise
+                let charset = charset
+                    .as_deref()
+                    .or_else(|| (!criteria.is_ascii()).then_some("UTF-8"));
+                if let Some(charset) = charset {
+                    buf.extend_from_slice(b"CHARSET ");
+                    write_astring(&mut buf, charset);
+                    buf.push(b' ');
+                }
+                write_search_criteria(&mut buf, criteria);
+            }
+
+            Self::Sort {
+                keys,
+                charset,
+                criteria,
+                uid,
+            } => {
+                if *uid {
+                    buf.extend_from_slice(b"UID ");
+                }
+                buf.extend_from_slice(b"SORT ");
+                write_sort_keys(&mut buf, keys);
+                buf.push(b' ');
+                write_astring(&mut buf, charset);
+                buf.push(b' ');
+                write_search_criteria(&mut buf, criteria);
+            }
+
+            Self::Thread {
+                algorithm,
+                charset,
+                criteria,
+                uid,
+            } => {
+                if *uid {
+                    buf.extend_from_slice(b"UID ");
+                }
+                buf.extend_from_slice(b"THREAD ");
+                buf.extend_from_slice(algorithm.as_str().as_bytes());
+                buf.push(b' ');
+                write_astring(&mut buf, charset);
+                buf.push(b' ');
                 write_search_criteria(&mut buf, criteria);
             }
 
@@ -351,6 +578,7 @@ impl Command {
                 sequence,
                 items,
                 uid,
+                changed_since,
             } => {
                 if *uid {
                     buf.extend_from_slice(b"UID ");
@@ -359,6 +587,9 @@ impl Command {
                 buf.extend_from_slice(sequence.to_string().as_bytes());
                 buf.push(b' ');
                 write_fetch_items(&mut buf, items);
+                if let Some(modseq) = changed_since {
+                    buf.extend_from_slice(format!(" (CHANGEDSINCE {modseq})").as_bytes());
+                }
             }
 
             Self::Store {
@@ -387,7 +618,7 @@ impl Command {
                 buf.extend_from_slice(b"COPY ");
                 buf.extend_from_slice(sequence.to_string().as_bytes());
                 buf.push(b' ');
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::Move {
@@ -401,7 +632,7 @@ impl Command {
                 buf.extend_from_slice(b"MOVE ");
                 buf.extend_from_slice(sequence.to_string().as_bytes());
                 buf.push(b' ');
-                write_mailbox(&mut buf, mailbox);
+                write_mailbox(&mut buf, mailbox, utf8);
             }
 
             Self::Idle => buf.extend_from_slice(b"IDLE"),
@@ -483,12 +714,105 @@ mod tests {
         assert_eq!(cmd.serialize("A001"), b"A001 LIST \"\" \"*\"\r\n");
     }
 
+    #[test]
+    fn test_list_extended_command() {
+        let cmd = Command::ListExtended {
+            selection: vec![ListSelectOpt::Subscribed],
+            reference: String::new(),
+            patterns: vec!["INBOX".to_string(), "Lists/%".to_string()],
+            return_opts: vec![
+                ListReturnOpt::Children,
+                ListReturnOpt::Status(vec![StatusAttribute::Messages, StatusAttribute::Unseen]),
+            ],
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 LIST (SUBSCRIBED) \"\" (INBOX \"Lists/%\") RETURN (CHILDREN STATUS (MESSAGES UNSEEN))\r\n"
+        );
+
+        let plain = Command::ListExtended {
+            selection: Vec::new(),
+            reference: String::new(),
+            patterns: vec!["*".to_string()],
+            return_opts: Vec::new(),
+        };
+        assert_eq!(plain.serialize("A002"), b"A002 LIST \"\" \"*\"\r\n");
+    }
+
+    #[test]
+    fn test_quota_commands() {
+        let cmd = Command::GetQuotaRoot {
+            mailbox: Mailbox::inbox(),
+        };
+        assert_eq!(cmd.serialize("A001"), b"A001 GETQUOTAROOT INBOX\r\n");
+
+        let cmd = Command::GetQuota {
+            root: String::new(),
+        };
+        assert_eq!(cmd.serialize("A002"), b"A002 GETQUOTA \"\"\r\n");
+    }
+
+    #[test]
+    fn test_metadata_commands() {
+        let cmd = Command::GetMetadata {
+            mailbox: Mailbox::inbox(),
+            entries: vec![
+                "/private/comment".to_string(),
+                "/shared/comment".to_string(),
+            ],
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 GETMETADATA INBOX (/private/comment /shared/comment)\r\n"
+        );
+
+        let cmd = Command::SetMetadata {
+            mailbox: Mailbox::new("Work"),
+            entries: vec![
+                (
+                    "/private/comment".to_string(),
+                    Some("My \"work\" mail".to_string()),
+                ),
+                ("/private/color".to_string(), None),
+            ],
+        };
+        assert_eq!(
+            cmd.serialize("A002"),
+            b"A002 SETMETADATA Work (/private/comment \"My \\\"work\\\" mail\" /private/color NIL)\r\n"
+        );
+
+        // Server annotations use the empty mailbox name
+        let cmd = Command::GetMetadata {
+            mailbox: Mailbox::new(""),
+            entries: vec!["/shared/admin".to_string()],
+        };
+        assert_eq!(
+            cmd.serialize("A003"),
+            b"A003 GETMETADATA \"\" (/shared/admin)\r\n"
+        );
+    }
+
+    #[test]
+    fn test_set_metadata_sends_multiline_value_as_literal() {
+        let cmd = Command::SetMetadata {
+            mailbox: Mailbox::inbox(),
+            entries: vec![("/private/comment".to_string(), Some("a\r\nb".to_string()))],
+        };
+        let bytes = cmd.serialize("A001");
+        assert_eq!(
+            bytes,
+            b"A001 SETMETADATA INBOX (/private/comment {4}\r\na\r\nb)\r\n"
+        );
+        assert_eq!(split_at_literals(&bytes).len(), 2);
+    }
+
     #[test]
     fn test_fetch_command() {
         let cmd = Command::Fetch {
             sequence: SequenceSet::range(1, 10).unwrap(),
             items: FetchItems::Items(vec![FetchAttribute::Flags, FetchAttribute::Uid]),
             uid: false,
+            changed_since: None,
         };
         assert_eq!(cmd.serialize("A001"), b"A001 FETCH 1:10 (FLAGS UID)\r\n");
     }
@@ -499,10 +823,131 @@ mod tests {
             sequence: SequenceSet::All,
             items: FetchItems::All,
             uid: true,
+            changed_since: None,
         };
         assert_eq!(cmd.serialize("A001"), b"A001 UID FETCH * ALL\r\n");
     }
 
+    #[test]
+    fn test_uid_fetch_binary() {
+        let cmd = Command::Fetch {
+            sequence: SequenceSet::single(42).unwrap(),
+            items: FetchItems::Items(vec![
+                FetchAttribute::Binary {
+                    section: vec![2],
+                    peek: true,
+                },
+                FetchAttribute::BinarySize {
+                    section: vec![2, 1],
+                },
+            ]),
+            uid: true,
+            changed_since: None,
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID FETCH 42 (BINARY.PEEK[2] BINARY.SIZE[2.1])\r\n"
+        );
+    }
+
+    #[test]
+    fn test_uid_fetch_body_section_partial() {
+        let cmd = Command::Fetch {
+            sequence: SequenceSet::single(7).unwrap(),
+            items: FetchItems::Items(vec![FetchAttribute::BodySectionPartial {
+                section: vec![1],
+                offset: 0,
+                length: 4096,
+            }]),
+            uid: true,
+            changed_since: None,
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID FETCH 7 BODY.PEEK[1]<0.4096>\r\n"
+        );
+    }
+
+    #[test]
+    fn test_uid_fetch_header_fields() {
+        let cmd = Command::Fetch {
+            sequence: SequenceSet::single(7).unwrap(),
+            items: FetchItems::Items(vec![
+                FetchAttribute::BodyHeaderFields(vec![
+                    "References".to_string(),
+                    "Message-ID".to_string(),
+                ]),
+                FetchAttribute::BodyHeaderFieldsNot(vec!["Received".to_string()]),
+            ]),
+            uid: true,
+            changed_since: None,
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID FETCH 7 (BODY.PEEK[HEADER.FIELDS (REFERENCES MESSAGE-ID)] BODY.PEEK[HEADER.FIELDS.NOT (RECEIVED)])\r\n"
+        );
+    }
+
+    #[test]
+    fn test_uid_fetch_changed_since() {
+        let cmd = Command::Fetch {
+            sequence: SequenceSet::RangeFrom(crate::types::SeqNum::new(1).unwrap()),
+            items: FetchItems::Items(vec![FetchAttribute::Flags, FetchAttribute::Uid]),
+            uid: true,
+            changed_since: Some(12345),
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID FETCH 1:* (FLAGS UID) (CHANGEDSINCE 12345)\r\n"
+        );
+    }
+
+    #[test]
+    fn test_uid_sort_command() {
+        let cmd = Command::Sort {
+            keys: vec![SortKey::Reverse(Box::new(SortKey::Date))],
+            charset: "UTF-8".to_string(),
+            criteria: SearchCriteria::All,
+            uid: true,
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID SORT (REVERSE DATE) UTF-8 ALL\r\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_multiple_keys() {
+        let cmd = Command::Sort {
+            keys: vec![
+                SortKey::Subject,
+                SortKey::Reverse(Box::new(SortKey::Reverse(Box::new(SortKey::Arrival)))),
+                SortKey::Reverse(Box::new(SortKey::Size)),
+            ],
+            charset: "US-ASCII".to_string(),
+            criteria: SearchCriteria::Unseen,
+            uid: false,
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 SORT (SUBJECT ARRIVAL REVERSE SIZE) US-ASCII UNSEEN\r\n"
+        );
+    }
+
+    #[test]
+    fn test_uid_thread_command() {
+        let cmd = Command::Thread {
+            algorithm: ThreadAlgorithm::References,
+            charset: "UTF-8".to_string(),
+            criteria: SearchCriteria::Since("1-Feb-1994".to_string()),
+            uid: true,
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID THREAD REFERENCES UTF-8 SINCE 1-Feb-1994\r\n"
+        );
+    }
+
     #[test]
     fn test_store_command() {
         let cmd = Command::Store {
@@ -520,12 +965,277 @@ mod tests {
     #[test]
     fn test_search_command() {
         let cmd = Command::Search {
+            charset: None,
             criteria: SearchCriteria::Unseen,
             uid: false,
+            return_opts: vec![],
         };
         assert_eq!(cmd.serialize("A001"), b"A001 SEARCH UNSEEN\r\n");
     }
 
+    #[test]
+    fn test_search_return_options() {
+        let cmd = Command::Search {
+            charset: None,
+            criteria: SearchCriteria::Unseen,
+            uid: true,
+            return_opts: vec![
+                SearchReturnOption::Min,
+                SearchReturnOption::Max,
+                SearchReturnOption::Count,
+                SearchReturnOption::All,
+            ],
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 UID SEARCH RETURN (MIN MAX COUNT ALL) UNSEEN\r\n"
+        );
+    }
+
+    #[test]
+    fn test_search_charset_literal() {
+        let cmd = Command::Search {
+            charset: Some("UTF-8".to_string()),
+            criteria: SearchCriteria::Subject("café".to_string()),
+            uid: false,
+            return_opts: vec![],
+        };
+        let bytes = cmd.serialize("A001");
+        assert_eq!(
+            bytes,
+            "A001 SEARCH CHARSET UTF-8 SUBJECT {5}\r\ncafé\r\n".as_bytes()
+        );
+        assert_eq!(
+            split_at_literals(&bytes),
+            vec![
+                b"A001 SEARCH CHARSET UTF-8 SUBJECT {5}\r\n".as_slice(),
+                "café\r\n".as_bytes(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_ascii_stays_quoted() {
+        let cmd = Command::Search {
+            charset: Some("UTF-8".to_string()),
+            criteria: SearchCriteria::Header("X-Tag".to_string(), "a b".to_string()),
+            uid: true,
+            return_opts: vec![],
+        };
+        let bytes = cmd.serialize("A001");
+        assert_eq!(
+            bytes,
+            b"A001 UID SEARCH CHARSET UTF-8 HEADER X-Tag \"a b\"\r\n"
+        );
+        assert_eq!(split_at_literals(&bytes).len(), 1);
+    }
+
+    fn search(criteria: SearchCriteria) -> String {
+        let cmd = Command::Search {
+            charset: None,
+            criteria,
+            uid: false,
+            return_opts: vec![],
+        };
+        String::from_utf8(cmd.serialize("A1")).unwrap()
+    }
+
+    #[test]
+    fn test_search_rfc_examples() {
+        // RFC 9051 section 6.4.4
+        let criteria = SearchCriteria::And(vec![
+            SearchCriteria::Flagged,
+            SearchCriteria::Since("1-Feb-1994".to_string()),
+            SearchCriteria::Not(Box::new(SearchCriteria::From("Smith".to_string()))),
+        ]);
+        assert_eq!(
+            search(criteria),
+            "A1 SEARCH FLAGGED SINCE 1-Feb-1994 NOT FROM \"Smith\"\r\n"
+        );
+
+        let criteria = SearchCriteria::Or(
+            Box::new(SearchCriteria::Body("x".to_string())),
+            Box::new(SearchCriteria::Subject("y".to_string())),
+        );
+        assert_eq!(
+            search(criteria),
+            "A1 SEARCH OR BODY \"x\" SUBJECT \"y\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_search_groups_and_under_or() {
+        let criteria = SearchCriteria::Or(
+            Box::new(SearchCriteria::And(vec![
+                SearchCriteria::From("a".to_string()),
+                SearchCriteria::Unseen,
+            ])),
+            Box::new(SearchCriteria::Not(Box::new(SearchCriteria::And(vec![
+                SearchCriteria::Seen,
+                SearchCriteria::Flagged,
+            ])))),
+        );
+        assert_eq!(
+            search(criteria),
+            "A1 SEARCH OR (FROM \"a\" UNSEEN) NOT (SEEN FLAGGED)\r\n"
+        );
+
+        // A one-element AND needs no parentheses, an empty one matches all
+        let criteria =
+            SearchCriteria::Not(Box::new(SearchCriteria::And(vec![SearchCriteria::Seen])));
+        assert_eq!(search(criteria), "A1 SEARCH NOT SEEN\r\n");
+        assert_eq!(search(SearchCriteria::And(vec![])), "A1 SEARCH ALL\r\n");
+    }
+
+    #[test]
+    fn test_search_nested_or_chain() {
+        // OR is binary, so "any of four senders" nests to the right
+        let criteria = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|from| SearchCriteria::From(from.to_string()))
+            .rev()
+            .reduce(|rest, first| SearchCriteria::Or(Box::new(first), Box::new(rest)))
+            .unwrap();
+        assert_eq!(
+            search(criteria),
+            "A1 SEARCH OR FROM \"a\" OR FROM \"b\" OR FROM \"c\" FROM \"d\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_search_non_ascii_defaults_to_utf8() {
+        let criteria = SearchCriteria::Or(
+            Box::new(SearchCriteria::Subject("naïve".to_string())),
+            Box::new(SearchCriteria::Seen),
+        );
+        assert_eq!(
+            search(criteria),
+            "A1 SEARCH CHARSET UTF-8 OR SUBJECT {6}\r\nnaïve SEEN\r\n"
+        );
+    }
+
+    #[test]
+    fn test_search_line_breaks_use_literal() {
+        let criteria = SearchCriteria::Body("a\r\nb".to_string());
+        assert_eq!(search(criteria), "A1 SEARCH BODY {4}\r\na\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_split_at_literals_skips_literal_data() {
+        let cmd = b"A1 SEARCH TEXT {6}\r\n{1}\r\nx BODY {2}\r\nab\r\n";
+        assert_eq!(
+            split_at_literals(cmd),
+            vec![
+                b"A1 SEARCH TEXT {6}\r\n".as_slice(),
+                b"{1}\r\nx BODY {2}\r\n".as_slice(),
+                b"ab\r\n".as_slice(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mailbox_names_use_modified_utf7() {
+        let cmd = Command::Select {
+            mailbox: Mailbox::new("Entwürfe"),
+            condstore: false,
+        };
+        assert_eq!(cmd.serialize("A001"), b"A001 SELECT Entw&APw-rfe\r\n");
+        assert_eq!(
+            cmd.serialize_with("A001", true),
+            "A001 SELECT Entwürfe\r\n".as_bytes()
+        );
+
+        let cmd = Command::List {
+            reference: String::new(),
+            pattern: "Tom & Jerry/*".to_string(),
+        };
+        assert_eq!(
+            cmd.serialize("A002"),
+            b"A002 LIST \"\" \"Tom &- Jerry/*\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_compress_command() {
+        let cmd = Command::Compress;
+        assert_eq!(cmd.serialize("A001"), b"A001 COMPRESS DEFLATE\r\n");
+    }
+
+    #[test]
+    fn test_multiappend_command() {
+        use chrono::{FixedOffset, TimeZone};
+
+        let date = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2024, 2, 7, 12, 0, 0)
+            .unwrap();
+        let cmd = Command::MultiAppend {
+            mailbox: Mailbox::new("Archive"),
+            messages: vec![
+                AppendItem::new(b"one".to_vec())
+                    .flags(vec![Flag::Seen])
+                    .date(date),
+                AppendItem::new(b"two!".to_vec()),
+            ],
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 APPEND Archive (\\Seen) \" 7-Feb-2024 12:00:00 +0000\" {3}\r\none {4}\r\ntwo!\r\n"
+        );
+    }
+
+    #[test]
+    fn test_append_command_with_date() {
+        use chrono::{FixedOffset, TimeZone};
+
+        let date = FixedOffset::east_opt(-5 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 2, 7, 9, 5, 3)
+            .unwrap();
+        let cmd = Command::Append {
+            mailbox: Mailbox::new("INBOX"),
+            flags: Some(vec![Flag::Seen]),
+            date: Some(date),
+            message: b"hello".to_vec(),
+        };
+        assert_eq!(
+            cmd.serialize("A001"),
+            b"A001 APPEND INBOX (\\Seen) \" 7-Feb-2024 09:05:03 -0500\" {5}\r\n"
+        );
+
+        // Two-digit days aren't padded; no flags means no flag list.
+        let date = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2023, 12, 25, 23, 59, 59)
+            .unwrap();
+        let cmd = Command::Append {
+            mailbox: Mailbox::new("Archive"),
+            flags: None,
+            date: Some(date),
+            message: b"hello".to_vec(),
+        };
+        assert_eq!(
+            cmd.serialize("A002"),
+            b"A002 APPEND Archive \"25-Dec-2023 23:59:59 +0000\" {5}\r\n"
+        );
+    }
+
+    #[test]
+    fn test_append_lines() {
+        let items = [
+            AppendItem::new(b"one".to_vec()),
+            AppendItem::new(b"two!".to_vec()).flags(vec![Flag::Draft]),
+        ];
+        let lines = append_lines("A001", &Mailbox::new("Drafts"), &items, false);
+        assert_eq!(
+            lines,
+            vec![
+                b"A001 APPEND Drafts {3}\r\n".to_vec(),
+                b" (\\Draft) {4}\r\n".to_vec(),
+            ]
+        );
+    }
+
     #[test]
     fn test_idle_command() {
         let cmd = Command::Idle;
@@ -554,7 +1264,7 @@ mod tests {
         };
         assert_eq!(
             cmd.serialize("A001"),
-            b"A001 ID (name mailledger version 0.1.0)\r\n"
+            b"A001 ID (\"name\" \"mailledger\" \"version\" \"0.1.0\")\r\n"
         );
     }
 
@@ -0,0 +1,220 @@
+//! Fluent construction of SEARCH criteria.
+
+use chrono::NaiveDate;
+
+use super::types::SearchCriteria;
+
+/// Date format used by SEARCH keys (RFC 3501 `date`), e.g. `01-Feb-2024`.
+const SEARCH_DATE_FORMAT: &str = "%d-%b-%Y";
+
+/// Builds a [`SearchCriteria`] tree from simple conditions.
+///
+/// Every condition added must match (AND). Use [`Self::or`] and
+/// [`Self::not`] for other combinations.
+///
+/// # Example
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use mailledger_imap::SearchCriteria;
+///
+/// let since = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+/// let criteria = SearchCriteria::builder()
+///     .since(since)
+///     .larger(1_000_000)
+///     .or(
+///         SearchCriteria::From("alice@example.com".into()),
+///         SearchCriteria::From("bob@example.com".into()),
+///     )
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[must_use]
+pub struct SearchCriteriaBuilder {
+    criteria: Vec<SearchCriteria>,
+}
+
+impl SearchCriteriaBuilder {
+    /// Creates a builder that matches all messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches messages received before `date` (internal date).
+    pub fn before(self, date: NaiveDate) -> Self {
+        self.and(SearchCriteria::Before(format_date(date)))
+    }
+
+    /// Matches messages received on or after `date` (internal date).
+    pub fn since(self, date: NaiveDate) -> Self {
+        self.and(SearchCriteria::Since(format_date(date)))
+    }
+
+    /// Matches messages whose Date header is before `date`.
+    pub fn sent_before(self, date: NaiveDate) -> Self {
+        self.and(SearchCriteria::SentBefore(format_date(date)))
+    }
+
+    /// Matches messages whose Date header is on or after `date`.
+    pub fn sent_since(self, date: NaiveDate) -> Self {
+        self.and(SearchCriteria::SentSince(format_date(date)))
+    }
+
+    /// Matches messages larger than `bytes`.
+    pub fn larger(self, bytes: u32) -> Self {
+        self.and(SearchCriteria::Larger(bytes))
+    }
+
+    /// Matches messages smaller than `bytes`.
+    pub fn smaller(self, bytes: u32) -> Self {
+        self.and(SearchCriteria::Smaller(bytes))
+    }
+
+    /// Matches messages whose `name` header contains `value`.
+    pub fn header(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.and(SearchCriteria::Header(name.into(), value.into()))
+    }
+
+    /// Matches messages whose From header contains `text`.
+    pub fn from(self, text: impl Into<String>) -> Self {
+        self.and(SearchCriteria::From(text.into()))
+    }
+
+    /// Matches messages whose To header contains `text`.
+    pub fn to(self, text: impl Into<String>) -> Self {
+        self.and(SearchCriteria::To(text.into()))
+    }
+
+    /// Matches messages whose subject contains `text`.
+    pub fn subject(self, text: impl Into<String>) -> Self {
+        self.and(SearchCriteria::Subject(text.into()))
+    }
+
+    /// Matches messages whose headers or body contain `text`.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.and(SearchCriteria::Text(text.into()))
+    }
+
+    /// Matches messages without the \Seen flag.
+    pub fn unseen(self) -> Self {
+        self.and(SearchCriteria::Unseen)
+    }
+
+    /// Matches messages with the \Flagged flag.
+    pub fn flagged(self) -> Self {
+        self.and(SearchCriteria::Flagged)
+    }
+
+    /// Adds a condition that must also match.
+    pub fn and(mut self, criteria: impl Into<SearchCriteria>) -> Self {
+        self.criteria.push(criteria.into());
+        self
+    }
+
+    /// Adds a condition that matches if either `a` or `b` does.
+    pub fn or(self, a: impl Into<SearchCriteria>, b: impl Into<SearchCriteria>) -> Self {
+        self.and(SearchCriteria::Or(Box::new(a.into()), Box::new(b.into())))
+    }
+
+    /// Adds a condition that matches if `criteria` doesn't.
+    pub fn not(self, criteria: impl Into<SearchCriteria>) -> Self {
+        self.and(SearchCriteria::Not(Box::new(criteria.into())))
+    }
+
+    /// Returns the criteria; `ALL` if no condition was added.
+    #[must_use]
+    pub fn build(mut self) -> SearchCriteria {
+        match self.criteria.len() {
+            0 => SearchCriteria::All,
+            1 => self.criteria.remove(0),
+            _ => SearchCriteria::And(self.criteria),
+        }
+    }
+}
+
+impl From<SearchCriteriaBuilder> for SearchCriteria {
+    fn from(builder: SearchCriteriaBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl SearchCriteria {
+    /// Returns a builder for composing criteria.
+    pub fn builder() -> SearchCriteriaBuilder {
+        SearchCriteriaBuilder::new()
+    }
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format(SEARCH_DATE_FORMAT).to_string()
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+
+    fn search(criteria: SearchCriteria) -> String {
+        let cmd = Command::Search {
+            charset: None,
+            criteria,
+            uid: false,
+            return_opts: Vec::new(),
+        };
+        String::from_utf8(cmd.serialize("A1")).unwrap()
+    }
+
+    #[test]
+    fn test_empty_builder_matches_all() {
+        assert_eq!(SearchCriteria::builder().build(), SearchCriteria::All);
+    }
+
+    #[test]
+    fn test_or_from() {
+        let criteria = SearchCriteria::builder()
+            .or(
+                SearchCriteria::From("a".to_string()),
+                SearchCriteria::From("b".to_string()),
+            )
+            .build();
+        assert_eq!(search(criteria), "A1 SEARCH OR FROM \"a\" FROM \"b\"\r\n");
+    }
+
+    #[test]
+    fn test_not_seen() {
+        let criteria = SearchCriteria::builder().not(SearchCriteria::Seen).build();
+        assert_eq!(search(criteria), "A1 SEARCH NOT SEEN\r\n");
+
+        let criteria = SearchCriteria::builder()
+            .not(SearchCriteria::builder().unseen().flagged())
+            .build();
+        assert_eq!(search(criteria), "A1 SEARCH NOT (UNSEEN FLAGGED)\r\n");
+    }
+
+    #[test]
+    fn test_dates_sizes_and_headers() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let criteria = SearchCriteria::builder()
+            .since(date)
+            .before(date)
+            .sent_since(date)
+            .larger(1024)
+            .smaller(4096)
+            .header("List-Id", "rust")
+            .build();
+        assert_eq!(
+            search(criteria),
+            "A1 SEARCH SINCE 01-Feb-2024 BEFORE 01-Feb-2024 SENTSINCE 01-Feb-2024 \
+             LARGER 1024 SMALLER 4096 HEADER List-Id \"rust\"\r\n"
+        );
+    }
+}
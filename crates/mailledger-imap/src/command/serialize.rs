@@ -1,28 +1,52 @@
 //! Command serialization helpers.
 
-use crate::types::Mailbox;
+use chrono::{DateTime, FixedOffset};
 
-use super::types::{FetchAttribute, FetchItems, SearchCriteria, StoreAction};
+use crate::types::{Flag, Mailbox, encode_utf7};
+
+use super::types::{
+    AppendItem, FetchAttribute, FetchItems, ListReturnOpt, ListSelectOpt, SearchCriteria, SortKey,
+    StoreAction,
+};
 
 /// Writes an astring (atom or quoted string).
 pub fn write_astring(buf: &mut Vec<u8>, s: &str) {
     if s.is_empty() || s.bytes().any(needs_quoting) {
-        buf.push(b'"');
-        for b in s.bytes() {
-            if b == b'"' || b == b'\\' {
-                buf.push(b'\\');
-            }
-            buf.push(b);
-        }
-        buf.push(b'"');
+        write_quoted(buf, s);
     } else {
         buf.extend_from_slice(s.as_bytes());
     }
 }
 
-/// Writes a mailbox name.
-pub fn write_mailbox(buf: &mut Vec<u8>, mailbox: &Mailbox) {
-    write_astring(buf, mailbox.as_str());
+/// Writes a quoted string, escaping `"` and `\`.
+pub fn write_quoted(buf: &mut Vec<u8>, s: &str) {
+    buf.push(b'"');
+    for b in s.bytes() {
+        if b == b'"' || b == b'\\' {
+            buf.push(b'\\');
+        }
+        buf.push(b);
+    }
+    buf.push(b'"');
+}
+
+/// Writes a mailbox name, in modified UTF-7 unless `utf8` (UTF8=ACCEPT)
+/// allows it raw.
+pub fn write_mailbox(buf: &mut Vec<u8>, mailbox: &Mailbox, utf8: bool) {
+    if utf8 {
+        write_astring(buf, mailbox.as_str());
+    } else {
+        write_astring(buf, &String::from_utf8_lossy(&mailbox.to_wire()));
+    }
+}
+
+/// Writes a LIST reference or pattern, encoded like a mailbox name.
+pub fn write_mailbox_pattern(buf: &mut Vec<u8>, pattern: &str, utf8: bool) {
+    if utf8 {
+        write_astring(buf, pattern);
+    } else {
+        write_astring(buf, &encode_utf7(pattern));
+    }
 }
 
 /// Returns true if the byte needs quoting.
@@ -66,6 +90,36 @@ pub fn write_fetch_attribute(buf: &mut Vec<u8>, attr: &FetchAttribute) {
         FetchAttribute::Rfc822Header => buf.extend_from_slice(b"RFC822.HEADER"),
         FetchAttribute::Rfc822Text => buf.extend_from_slice(b"RFC822.TEXT"),
         FetchAttribute::ModSeq => buf.extend_from_slice(b"MODSEQ"),
+        FetchAttribute::GmailLabels => buf.extend_from_slice(b"X-GM-LABELS"),
+        FetchAttribute::GmailThreadId => buf.extend_from_slice(b"X-GM-THRID"),
+        FetchAttribute::GmailMsgId => buf.extend_from_slice(b"X-GM-MSGID"),
+        FetchAttribute::Binary { section, peek } => {
+            if *peek {
+                buf.extend_from_slice(b"BINARY.PEEK");
+            } else {
+                buf.extend_from_slice(b"BINARY");
+            }
+            write_part_section(buf, section);
+        }
+        FetchAttribute::BinarySize { section } => {
+            buf.extend_from_slice(b"BINARY.SIZE");
+            write_part_section(buf, section);
+        }
+        FetchAttribute::BodyHeaderFields(fields) => {
+            write_header_fields(buf, b"HEADER.FIELDS", fields);
+        }
+        FetchAttribute::BodyHeaderFieldsNot(fields) => {
+            write_header_fields(buf, b"HEADER.FIELDS.NOT", fields);
+        }
+        FetchAttribute::BodySectionPartial {
+            section,
+            offset,
+            length,
+        } => {
+            buf.extend_from_slice(b"BODY.PEEK");
+            write_part_section(buf, section);
+            buf.extend_from_slice(format!("<{offset}.{length}>").as_bytes());
+        }
         FetchAttribute::Body {
             section,
             peek,
@@ -87,6 +141,82 @@ pub fn write_fetch_attribute(buf: &mut Vec<u8>, attr: &FetchAttribute) {
     }
 }
 
+/// Writes the ` (flags) "date" {len}` part of an APPEND that precedes a
+/// message literal.
+pub fn write_append_prefix(buf: &mut Vec<u8>, item: &AppendItem) {
+    write_append_args(
+        buf,
+        item.flags.as_deref(),
+        item.date.as_ref(),
+        item.message.len(),
+    );
+}
+
+/// Writes the APPEND arguments after the mailbox, up to the literal
+/// prefix: ` [(flags)] ["date-time"] {len}`.
+pub fn write_append_args(
+    buf: &mut Vec<u8>,
+    flags: Option<&[Flag]>,
+    date: Option<&DateTime<FixedOffset>>,
+    len: usize,
+) {
+    if let Some(flags) = flags {
+        write_flag_list(buf, flags);
+    }
+    if let Some(date) = date {
+        buf.push(b' ');
+        write_date_time(buf, date);
+    }
+    buf.extend_from_slice(format!(" {{{len}}}").as_bytes());
+}
+
+/// Writes ` (flag flag ...)`.
+fn write_flag_list(buf: &mut Vec<u8>, flags: &[Flag]) {
+    buf.extend_from_slice(b" (");
+    for (i, flag) in flags.iter().enumerate() {
+        if i > 0 {
+            buf.push(b' ');
+        }
+        buf.extend_from_slice(flag.as_str().as_bytes());
+    }
+    buf.push(b')');
+}
+
+/// Writes a quoted IMAP `date-time`, e.g. `" 7-Feb-2024 12:00:00 +0000"`.
+pub fn write_date_time(buf: &mut Vec<u8>, date: &DateTime<FixedOffset>) {
+    buf.extend_from_slice(
+        date.format("\"%e-%b-%Y %H:%M:%S %z\"")
+            .to_string()
+            .as_bytes(),
+    );
+}
+
+/// Writes a `[n.n...]` part specifier.
+fn write_part_section(buf: &mut Vec<u8>, section: &[u32]) {
+    buf.push(b'[');
+    for (i, part) in section.iter().enumerate() {
+        if i > 0 {
+            buf.push(b'.');
+        }
+        buf.extend_from_slice(part.to_string().as_bytes());
+    }
+    buf.push(b']');
+}
+
+/// Writes `BODY.PEEK[<kind> (<field> ...)]` with upper-cased field names.
+fn write_header_fields(buf: &mut Vec<u8>, kind: &[u8], fields: &[String]) {
+    buf.extend_from_slice(b"BODY.PEEK[");
+    buf.extend_from_slice(kind);
+    buf.extend_from_slice(b" (");
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            buf.push(b' ');
+        }
+        buf.extend_from_slice(field.to_ascii_uppercase().as_bytes());
+    }
+    buf.extend_from_slice(b")]");
+}
+
 /// Writes STORE action.
 pub fn write_store_action(buf: &mut Vec<u8>, action: &StoreAction, silent: bool) {
     match action {
@@ -135,6 +265,65 @@ pub fn write_store_action(buf: &mut Vec<u8>, action: &StoreAction, silent: bool)
     }
 }
 
+/// Writes a SEARCH string argument.
+///
+/// Non-ASCII text can't go in a quoted string before UTF8=ACCEPT, and CR,
+/// LF and NUL never can, so such text is sent as a synchronizing literal
+/// in the command's CHARSET.
+fn write_search_string(buf: &mut Vec<u8>, s: &str) {
+    if s.bytes()
+        .all(|b| b.is_ascii() && !matches!(b, b'\r' | b'\n' | 0))
+    {
+        write_quoted(buf, s);
+    } else {
+        buf.extend_from_slice(format!("{{{}}}\r\n", s.len()).as_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+}
+
+/// Writes a METADATA entry value: `NIL` to remove the entry, otherwise a
+/// quoted string, or a synchronizing literal for text that can't be quoted.
+pub fn write_metadata_value(buf: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        None => buf.extend_from_slice(b"NIL"),
+        Some(s) => write_search_string(buf, s),
+    }
+}
+
+/// Splits a serialized command after each synchronizing literal header.
+///
+/// Every part but the last ends in `{n}\r\n` and must be followed by a
+/// continuation request from the server before the next part is sent. The
+/// literal data itself is skipped, so bytes inside it are never mistaken
+/// for another literal header.
+pub fn split_at_literals(cmd: &[u8]) -> Vec<&[u8]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = cmd[pos..].windows(3).position(|w| w == b"}\r\n") {
+        let close = pos + offset;
+        let digits_start = cmd[..close]
+            .iter()
+            .rposition(|b| !b.is_ascii_digit())
+            .map_or(0, |i| i + 1);
+        let len = (digits_start > 0 && digits_start < close && cmd[digits_start - 1] == b'{')
+            .then(|| std::str::from_utf8(&cmd[digits_start..close]).ok())
+            .flatten()
+            .and_then(|digits| digits.parse::<usize>().ok());
+
+        pos = close + 3;
+        if let Some(len) = len {
+            parts.push(&cmd[start..pos]);
+            start = pos;
+            pos = (pos + len).min(cmd.len());
+        }
+    }
+
+    parts.push(&cmd[start..]);
+    parts
+}
+
 /// Writes SEARCH criteria.
 pub fn write_search_criteria(buf: &mut Vec<u8>, criteria: &SearchCriteria) {
     match criteria {
@@ -156,23 +345,23 @@ pub fn write_search_criteria(buf: &mut Vec<u8>, criteria: &SearchCriteria) {
         }
         SearchCriteria::Subject(s) => {
             buf.extend_from_slice(b"SUBJECT ");
-            write_astring(buf, s);
+            write_search_string(buf, s);
         }
         SearchCriteria::From(s) => {
             buf.extend_from_slice(b"FROM ");
-            write_astring(buf, s);
+            write_search_string(buf, s);
         }
         SearchCriteria::To(s) => {
             buf.extend_from_slice(b"TO ");
-            write_astring(buf, s);
+            write_search_string(buf, s);
         }
         SearchCriteria::Body(s) => {
             buf.extend_from_slice(b"BODY ");
-            write_astring(buf, s);
+            write_search_string(buf, s);
         }
         SearchCriteria::Text(s) => {
             buf.extend_from_slice(b"TEXT ");
-            write_astring(buf, s);
+            write_search_string(buf, s);
         }
         SearchCriteria::Since(date) => {
             buf.extend_from_slice(b"SINCE ");
@@ -186,6 +375,14 @@ pub fn write_search_criteria(buf: &mut Vec<u8>, criteria: &SearchCriteria) {
             buf.extend_from_slice(b"ON ");
             buf.extend_from_slice(date.as_bytes());
         }
+        SearchCriteria::SentSince(date) => {
+            buf.extend_from_slice(b"SENTSINCE ");
+            buf.extend_from_slice(date.as_bytes());
+        }
+        SearchCriteria::SentBefore(date) => {
+            buf.extend_from_slice(b"SENTBEFORE ");
+            buf.extend_from_slice(date.as_bytes());
+        }
         SearchCriteria::Larger(size) => {
             buf.extend_from_slice(format!("LARGER {size}").as_bytes());
         }
@@ -196,28 +393,129 @@ pub fn write_search_criteria(buf: &mut Vec<u8>, criteria: &SearchCriteria) {
             buf.extend_from_slice(b"HEADER ");
             write_astring(buf, name);
             buf.push(b' ');
-            write_astring(buf, value);
+            write_search_string(buf, value);
         }
         SearchCriteria::ModSeq(modseq) => {
             buf.extend_from_slice(format!("MODSEQ {modseq}").as_bytes());
         }
-        SearchCriteria::And(criteria) => {
-            for (i, c) in criteria.iter().enumerate() {
-                if i > 0 {
-                    buf.push(b' ');
+        SearchCriteria::GmailRaw(query) => {
+            buf.extend_from_slice(b"X-GM-RAW ");
+            write_search_string(buf, query);
+        }
+        SearchCriteria::And(criteria) => match criteria.as_slice() {
+            [] => buf.extend_from_slice(b"ALL"),
+            [c] => write_search_criteria(buf, c),
+            criteria => {
+                for (i, c) in criteria.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(b' ');
+                    }
+                    write_search_key(buf, c);
                 }
-                write_search_criteria(buf, c);
             }
-        }
+        },
         SearchCriteria::Or(a, b) => {
             buf.extend_from_slice(b"OR ");
-            write_search_criteria(buf, a);
+            write_search_key(buf, a);
             buf.push(b' ');
-            write_search_criteria(buf, b);
+            write_search_key(buf, b);
         }
         SearchCriteria::Not(c) => {
             buf.extend_from_slice(b"NOT ");
-            write_search_criteria(buf, c);
+            write_search_key(buf, c);
+        }
+    }
+}
+
+/// Writes criteria as a single search key, parenthesizing an AND of
+/// several keys so OR and NOT apply to the whole group.
+fn write_search_key(buf: &mut Vec<u8>, criteria: &SearchCriteria) {
+    match criteria {
+        SearchCriteria::And(keys) if keys.len() > 1 => {
+            buf.push(b'(');
+            write_search_criteria(buf, criteria);
+            buf.push(b')');
+        }
+        _ => write_search_criteria(buf, criteria),
+    }
+}
+
+/// Writes the arguments of an extended LIST command (RFC 5258), e.g.
+/// `LIST (SUBSCRIBED) "" "*" RETURN (CHILDREN STATUS (MESSAGES UNSEEN))`.
+pub fn write_list_extended(
+    buf: &mut Vec<u8>,
+    selection: &[ListSelectOpt],
+    reference: &str,
+    patterns: &[String],
+    return_opts: &[ListReturnOpt],
+    utf8: bool,
+) {
+    buf.extend_from_slice(b"LIST ");
+    if !selection.is_empty() {
+        buf.push(b'(');
+        for (i, opt) in selection.iter().enumerate() {
+            if i > 0 {
+                buf.push(b' ');
+            }
+            buf.extend_from_slice(opt.as_str().as_bytes());
+        }
+        buf.extend_from_slice(b") ");
+    }
+
+    write_mailbox_pattern(buf, reference, utf8);
+    buf.push(b' ');
+    if let [pattern] = patterns {
+        write_mailbox_pattern(buf, pattern, utf8);
+    } else {
+        buf.push(b'(');
+        for (i, pattern) in patterns.iter().enumerate() {
+            if i > 0 {
+                buf.push(b' ');
+            }
+            write_mailbox_pattern(buf, pattern, utf8);
+        }
+        buf.push(b')');
+    }
+
+    if return_opts.is_empty() {
+        return;
+    }
+    buf.extend_from_slice(b" RETURN (");
+    for (i, opt) in return_opts.iter().enumerate() {
+        if i > 0 {
+            buf.push(b' ');
+        }
+        match opt {
+            ListReturnOpt::Subscribed => buf.extend_from_slice(b"SUBSCRIBED"),
+            ListReturnOpt::Children => buf.extend_from_slice(b"CHILDREN"),
+            ListReturnOpt::SpecialUse => buf.extend_from_slice(b"SPECIAL-USE"),
+            ListReturnOpt::Status(items) => {
+                buf.extend_from_slice(b"STATUS (");
+                for (j, item) in items.iter().enumerate() {
+                    if j > 0 {
+                        buf.push(b' ');
+                    }
+                    buf.extend_from_slice(item.as_str().as_bytes());
+                }
+                buf.push(b')');
+            }
+        }
+    }
+    buf.push(b')');
+}
+
+/// Writes a parenthesized SORT key list.
+pub fn write_sort_keys(buf: &mut Vec<u8>, keys: &[SortKey]) {
+    buf.push(b'(');
+    for (i, key) in keys.iter().enumerate() {
+        if i > 0 {
+            buf.push(b' ');
+        }
+        let (name, reversed) = key.resolve();
+        if reversed {
+            buf.extend_from_slice(b"REVERSE ");
         }
+        buf.extend_from_slice(name.as_bytes());
     }
+    buf.push(b')');
 }
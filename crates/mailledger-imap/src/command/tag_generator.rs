@@ -2,15 +2,43 @@
 //!
 //! Tags are used to match commands with their responses.
 
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 use std::sync::atomic::{AtomicU32, Ordering};
 
+/// Largest random starting counter, leaving room for billions of tags.
+const MAX_RANDOM_BASE: u32 = 1 << 24;
+
+/// Largest gap skipped by [`TagGenerator::reset_on_reconnect`].
+const MAX_RECONNECT_GAP: u32 = 1 << 16;
+
 /// Tag generator for IMAP commands.
 ///
-/// Generates unique sequential tags in the format "A001", "A002", etc.
+/// Generates unique sequential tags made of a prefix and a counter padded
+/// to four digits: "A0000", "A0001", etc.
 #[derive(Debug)]
 pub struct TagGenerator {
     counter: AtomicU32,
-    prefix: char,
+    prefix: Prefix,
+}
+
+/// The text every tag starts with.
+#[derive(Debug, Clone)]
+enum Prefix {
+    /// A single character, from [`TagGenerator::new`].
+    Char(char),
+    /// Validated text, from [`TagGenerator::with_prefix`].
+    Text(String),
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Char(c) => write!(f, "{c}"),
+            Self::Text(text) => f.write_str(text),
+        }
+    }
 }
 
 impl TagGenerator {
@@ -19,24 +47,54 @@ impl TagGenerator {
     pub const fn new(prefix: char) -> Self {
         Self {
             counter: AtomicU32::new(0),
-            prefix,
+            prefix: Prefix::Char(prefix),
         }
     }
 
+    /// Creates a tag generator with a multi-character prefix, such as a
+    /// per-connection name in a pool.
+    ///
+    /// Returns `None` if the prefix contains characters a tag can't hold.
+    #[must_use]
+    pub fn with_prefix(prefix: &str) -> Option<Self> {
+        prefix.bytes().all(is_tag_char).then(|| Self {
+            counter: AtomicU32::new(0),
+            prefix: Prefix::Text(prefix.to_string()),
+        })
+    }
+
+    /// Starts the counter at a random value instead of zero.
+    ///
+    /// Tags then differ between connections, so a late response to a
+    /// command from an earlier connection is unlikely to match a new one.
+    #[must_use]
+    pub fn with_random_base(self) -> Self {
+        self.counter.store(random_base(), Ordering::Relaxed);
+        self
+    }
+
     /// Generates the next tag.
     ///
     /// # Panics
     ///
-    /// Panics if the tag counter would overflow u32::MAX. In practice, this
+    /// Panics if the tag counter would overflow `u32::MAX`. In practice, this
     /// would require 4+ billion tags in a single session, which is unrealistic.
     #[must_use]
     pub fn next(&self) -> String {
         let n = self.counter.fetch_add(1, Ordering::Relaxed);
         // Check for overflow - if we've wrapped, the session is invalid
-        if n == u32::MAX {
-            panic!("tag counter overflow: generated {} tags in this session", n);
-        }
-        format!("{}{:04}", self.prefix, n)
+        assert!(
+            n != u32::MAX,
+            "tag counter overflow: generated {n} tags in this session"
+        );
+        self.format(n)
+    }
+
+    /// Returns the tag for counter value `n`, in the format [`Self::next`]
+    /// uses.
+    #[must_use]
+    pub fn format(&self, n: u32) -> String {
+        format!("{}{n:04}", self.prefix)
     }
 
     /// Returns the current counter value without incrementing.
@@ -49,6 +107,21 @@ impl TagGenerator {
     pub fn reset(&self) {
         self.counter.store(0, Ordering::Relaxed);
     }
+
+    /// Moves the counter ahead by a random gap for a new connection.
+    ///
+    /// Unlike [`Self::reset`], no tag handed out before is generated again,
+    /// so responses still in flight on the old connection can't be taken
+    /// for replies to new commands.
+    pub fn reset_on_reconnect(&self) {
+        let gap = u32::try_from(random_u64() % u64::from(MAX_RECONNECT_GAP)).unwrap_or(0) + 1;
+        let _ = self
+            .counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                // Near the end of the range, start over from a random base
+                Some(n.checked_add(gap).unwrap_or_else(random_base))
+            });
+    }
 }
 
 impl Default for TagGenerator {
@@ -61,11 +134,38 @@ impl Clone for TagGenerator {
     fn clone(&self) -> Self {
         Self {
             counter: AtomicU32::new(self.counter.load(Ordering::Relaxed)),
-            prefix: self.prefix,
+            prefix: self.prefix.clone(),
         }
     }
 }
 
+/// Returns true if `tag` is a valid command tag (RFC 9051 `tag`): one or
+/// more atom characters other than `+`.
+///
+/// `]` is rejected as well, though the grammar allows it, so tags never
+/// look like the end of a response code.
+#[must_use]
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.bytes().all(is_tag_char)
+}
+
+const fn is_tag_char(b: u8) -> bool {
+    b.is_ascii_graphic()
+        && !matches!(
+            b,
+            b'(' | b')' | b'{' | b'%' | b'*' | b'"' | b'\\' | b']' | b'+'
+        )
+}
+
+fn random_base() -> u32 {
+    u32::try_from(random_u64() % u64::from(MAX_RANDOM_BASE)).unwrap_or(0)
+}
+
+/// Returns a random number from the standard library's hasher seed.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -135,6 +235,57 @@ mod tests {
         assert_eq!(generator.next(), "X0100");
     }
 
+    #[test]
+    fn test_with_prefix() {
+        let generator = TagGenerator::with_prefix("conn7.").unwrap();
+        assert_eq!(generator.next(), "conn7.0000");
+        assert_eq!(generator.format(12), "conn7.0012");
+
+        assert!(TagGenerator::with_prefix("a+b").is_none());
+        assert!(TagGenerator::with_prefix("a b").is_none());
+        assert!(TagGenerator::with_prefix("*").is_none());
+    }
+
+    #[test]
+    fn test_random_base_tags_are_unique_and_valid() {
+        let generator = TagGenerator::default().with_random_base();
+        assert!(generator.current() < MAX_RANDOM_BASE);
+
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..10000 {
+            if i % 1000 == 0 {
+                generator.reset_on_reconnect();
+            }
+            let tag = generator.next();
+            assert!(is_valid_tag(&tag), "invalid tag {tag}");
+            assert!(seen.insert(tag), "duplicate tag generated");
+        }
+    }
+
+    #[test]
+    fn test_reset_on_reconnect_moves_forward() {
+        let generator = TagGenerator::default();
+        let _ = generator.next();
+        generator.reset_on_reconnect();
+        let after = generator.current();
+        assert!(after > 1 && after <= 1 + MAX_RECONNECT_GAP);
+
+        // Near the end of the range it starts over from a random base
+        generator.counter.store(u32::MAX - 1, Ordering::Relaxed);
+        generator.reset_on_reconnect();
+        assert!(generator.current() < MAX_RANDOM_BASE);
+    }
+
+    #[test]
+    fn test_is_valid_tag() {
+        assert!(is_valid_tag("A0001"));
+        assert!(is_valid_tag("x.y-1"));
+        assert!(!is_valid_tag(""));
+        assert!(!is_valid_tag("+"));
+        assert!(!is_valid_tag("A 1"));
+        assert!(!is_valid_tag("A]1"));
+    }
+
     #[test]
     #[should_panic(expected = "tag counter overflow")]
     fn test_overflow_detection() {
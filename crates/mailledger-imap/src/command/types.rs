@@ -1,5 +1,7 @@
 //! Command-related type definitions.
 
+use chrono::{DateTime, FixedOffset};
+
 use crate::types::{Flag, SequenceSet, UidSet};
 
 /// STATUS attributes to request.
@@ -32,6 +34,43 @@ impl StatusAttribute {
     }
 }
 
+/// LIST-EXTENDED selection option (RFC 5258).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSelectOpt {
+    /// Only subscribed mailboxes.
+    Subscribed,
+    /// Include remote mailboxes.
+    Remote,
+    /// Also return parents of matching mailboxes with CHILDINFO.
+    RecursiveMatch,
+    /// Only mailboxes with a special-use role (RFC 6154).
+    SpecialUse,
+}
+
+impl ListSelectOpt {
+    pub(crate) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Subscribed => "SUBSCRIBED",
+            Self::Remote => "REMOTE",
+            Self::RecursiveMatch => "RECURSIVEMATCH",
+            Self::SpecialUse => "SPECIAL-USE",
+        }
+    }
+}
+
+/// LIST-EXTENDED return option (RFC 5258).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListReturnOpt {
+    /// Add `\Subscribed` to subscribed mailboxes.
+    Subscribed,
+    /// Add `\HasChildren` or `\HasNoChildren`.
+    Children,
+    /// Add special-use attributes (RFC 6154).
+    SpecialUse,
+    /// Send a STATUS reply for each listed mailbox (LIST-STATUS, RFC 5819).
+    Status(Vec<StatusAttribute>),
+}
+
 /// FETCH items to request.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FetchItems {
@@ -77,6 +116,62 @@ pub enum FetchAttribute {
     Rfc822Text,
     /// MODSEQ.
     ModSeq,
+    /// Decoded content of a body part (RFC 3516), e.g. `BINARY[2]`.
+    Binary {
+        /// Part number path, e.g. `[2, 1]` for part 2.1.
+        section: Vec<u32>,
+        /// Peek (don't set \Seen).
+        peek: bool,
+    },
+    /// Decoded size of a body part (RFC 3516), e.g. `BINARY.SIZE[2]`.
+    BinarySize {
+        /// Part number path, e.g. `[2, 1]` for part 2.1.
+        section: Vec<u32>,
+    },
+    /// Only the named header fields, e.g.
+    /// `BODY.PEEK[HEADER.FIELDS (REFERENCES MESSAGE-ID)]`.
+    ///
+    /// Always sent as `.PEEK` so `\Seen` is left untouched.
+    BodyHeaderFields(Vec<String>),
+    /// All header fields except the named ones, e.g.
+    /// `BODY.PEEK[HEADER.FIELDS.NOT (RECEIVED)]`.
+    BodyHeaderFieldsNot(Vec<String>),
+    /// An octet range of a body part, e.g. `BODY.PEEK[1]<0.4096>`.
+    ///
+    /// Always sent as `.PEEK` so `\Seen` is left untouched.
+    BodySectionPartial {
+        /// Part number path, e.g. `[2, 1]` for part 2.1; empty for the whole message.
+        section: Vec<u32>,
+        /// First octet to return.
+        offset: u32,
+        /// Maximum number of octets to return.
+        length: u32,
+    },
+    /// Gmail labels of the message (`X-GM-LABELS`, requires `X-GM-EXT-1`).
+    GmailLabels,
+    /// Gmail thread ID (`X-GM-THRID`, requires `X-GM-EXT-1`).
+    GmailThreadId,
+    /// Gmail message ID (`X-GM-MSGID`, requires `X-GM-EXT-1`).
+    GmailMsgId,
+}
+
+impl FetchAttribute {
+    /// Returns true if the attribute needs Gmail's `X-GM-EXT-1` extension.
+    #[must_use]
+    pub const fn is_gmail_extension(&self) -> bool {
+        matches!(
+            self,
+            Self::GmailLabels | Self::GmailThreadId | Self::GmailMsgId
+        )
+    }
+}
+
+impl FetchItems {
+    /// Returns true if any requested attribute needs `X-GM-EXT-1`.
+    #[must_use]
+    pub fn uses_gmail_extensions(&self) -> bool {
+        matches!(self, Self::Items(attrs) if attrs.iter().any(FetchAttribute::is_gmail_extension))
+    }
 }
 
 /// STORE action.
@@ -111,6 +206,130 @@ pub enum StoreAction {
     },
 }
 
+/// A message to upload with MULTIAPPEND (RFC 3502).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppendItem {
+    /// Flags to set on the new message.
+    pub flags: Option<Vec<Flag>>,
+    /// Internal date to set; the server uses the current time if `None`.
+    pub date: Option<DateTime<FixedOffset>>,
+    /// Complete RFC 5322 message.
+    pub message: Vec<u8>,
+}
+
+impl AppendItem {
+    /// Creates an item with no flags or date.
+    #[must_use]
+    pub const fn new(message: Vec<u8>) -> Self {
+        Self {
+            flags: None,
+            date: None,
+            message,
+        }
+    }
+
+    /// Sets the flags.
+    #[must_use]
+    pub fn flags(mut self, flags: Vec<Flag>) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Sets the internal date.
+    #[must_use]
+    pub const fn date(mut self, date: DateTime<FixedOffset>) -> Self {
+        self.date = Some(date);
+        self
+    }
+}
+
+/// SORT key (RFC 5256).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortKey {
+    /// Internal date and time of the message.
+    Arrival,
+    /// First CC address mailbox.
+    Cc,
+    /// Sent date (Date header).
+    Date,
+    /// First From address mailbox.
+    From,
+    /// RFC822 size.
+    Size,
+    /// Base subject.
+    Subject,
+    /// First To address mailbox.
+    To,
+    /// Reverses the order of the wrapped key.
+    Reverse(Box<Self>),
+}
+
+impl SortKey {
+    /// Returns the key name and whether it is reversed.
+    ///
+    /// Nested reversals cancel out, since RFC 5256 allows only a single
+    /// `REVERSE` prefix per key.
+    pub(crate) fn resolve(&self) -> (&'static str, bool) {
+        match self {
+            Self::Arrival => ("ARRIVAL", false),
+            Self::Cc => ("CC", false),
+            Self::Date => ("DATE", false),
+            Self::From => ("FROM", false),
+            Self::Size => ("SIZE", false),
+            Self::Subject => ("SUBJECT", false),
+            Self::To => ("TO", false),
+            Self::Reverse(inner) => {
+                let (name, reversed) = inner.resolve();
+                (name, !reversed)
+            }
+        }
+    }
+}
+
+/// ESEARCH result option (RFC 4731).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchReturnOption {
+    /// Lowest matching message number.
+    Min,
+    /// Highest matching message number.
+    Max,
+    /// All matching messages as a compact sequence set.
+    All,
+    /// Number of matching messages.
+    Count,
+}
+
+impl SearchReturnOption {
+    pub(crate) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Min => "MIN",
+            Self::Max => "MAX",
+            Self::All => "ALL",
+            Self::Count => "COUNT",
+        }
+    }
+}
+
+/// THREAD algorithm (RFC 5256).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadAlgorithm {
+    /// Groups by base subject, ordered by sent date.
+    OrderedSubject,
+    /// Builds threads from References/In-Reply-To headers.
+    References,
+}
+
+impl ThreadAlgorithm {
+    /// Returns the algorithm name as used in the command and capability.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::OrderedSubject => "ORDEREDSUBJECT",
+            Self::References => "REFERENCES",
+        }
+    }
+}
+
 /// SEARCH criteria.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SearchCriteria {
@@ -152,6 +371,10 @@ pub enum SearchCriteria {
     Before(String),
     /// Messages on date.
     On(String),
+    /// Messages whose Date header is on or after date.
+    SentSince(String),
+    /// Messages whose Date header is before date.
+    SentBefore(String),
     /// Larger than size.
     Larger(u32),
     /// Smaller than size.
@@ -160,10 +383,46 @@ pub enum SearchCriteria {
     Header(String, String),
     /// Messages with mod-sequence greater than value (CONDSTORE).
     ModSeq(u64),
-    /// AND of criteria.
+    /// Gmail search syntax, e.g. `has:attachment` (`X-GM-RAW`, requires
+    /// `X-GM-EXT-1`).
+    GmailRaw(String),
+    /// AND of criteria; parenthesized when nested.
     And(Vec<Self>),
     /// OR of criteria.
     Or(Box<Self>, Box<Self>),
     /// NOT of criteria.
     Not(Box<Self>),
 }
+
+impl SearchCriteria {
+    /// Returns true if every string argument is ASCII, so the search needs
+    /// no CHARSET.
+    #[must_use]
+    pub fn is_ascii(&self) -> bool {
+        match self {
+            Self::Subject(s)
+            | Self::From(s)
+            | Self::To(s)
+            | Self::Body(s)
+            | Self::Text(s)
+            | Self::GmailRaw(s) => s.is_ascii(),
+            Self::Header(name, value) => name.is_ascii() && value.is_ascii(),
+            Self::And(criteria) => criteria.iter().all(Self::is_ascii),
+            Self::Or(a, b) => a.is_ascii() && b.is_ascii(),
+            Self::Not(c) => c.is_ascii(),
+            _ => true,
+        }
+    }
+
+    /// Returns true if the criteria need Gmail's `X-GM-EXT-1` extension.
+    #[must_use]
+    pub fn uses_gmail_extensions(&self) -> bool {
+        match self {
+            Self::GmailRaw(_) => true,
+            Self::And(criteria) => criteria.iter().any(Self::uses_gmail_extensions),
+            Self::Or(a, b) => a.uses_gmail_extensions() || b.uses_gmail_extensions(),
+            Self::Not(c) => c.uses_gmail_extensions(),
+            _ => false,
+        }
+    }
+}
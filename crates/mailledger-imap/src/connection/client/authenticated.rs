@@ -1,14 +1,20 @@
 //! Implementation for the authenticated state.
 
-use std::fmt::Write;
-
+use chrono::{DateTime, FixedOffset};
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use super::Client;
 use super::states::{Authenticated, Selected};
-use crate::command::Command;
+use crate::command::{
+    AppendItem, Command, ListReturnOpt, ListSelectOpt, append_lines, write_append_args,
+    write_mailbox,
+};
+use crate::connection::{DeflateStream, FramedStream};
 use crate::parser::{Response, ResponseParser, StatusItem, UntaggedResponse};
-use crate::types::{Mailbox, MailboxStatus, ResponseCode, Status};
+use crate::pipeline::{Pipeline, PipelineConfig, QueuedCommand};
+use crate::types::{
+    AppendUid, Capability, Mailbox, MailboxStatus, ResponseCode, Status, Tag, UidSet,
+};
 use crate::{Error, Result};
 
 impl<S> Client<S, Authenticated>
@@ -27,13 +33,34 @@ where
     /// println!("Selected: {}", client.mailbox());
     /// println!("Messages: {}", client.exists());
     /// ```
-    pub async fn select(mut self, mailbox: &str) -> Result<(Client<S, Selected>, MailboxStatus)> {
+    pub async fn select(self, mailbox: &str) -> Result<(Client<S, Selected>, MailboxStatus)> {
+        self.select_with(mailbox, false).await
+    }
+
+    /// Selects a mailbox with CONDSTORE enabled (RFC 7162).
+    ///
+    /// The returned status carries `highest_mod_seq` when the server reports
+    /// `[HIGHESTMODSEQ n]`, which can be passed to
+    /// [`fetch_changed_since`](Client::fetch_changed_since) on the next sync.
+    pub async fn select_condstore(
+        self,
+        mailbox: &str,
+    ) -> Result<(Client<S, Selected>, MailboxStatus)> {
+        self.select_with(mailbox, true).await
+    }
+
+    /// Sends SELECT, optionally with the CONDSTORE parameter.
+    async fn select_with(
+        mut self,
+        mailbox: &str,
+        condstore: bool,
+    ) -> Result<(Client<S, Selected>, MailboxStatus)> {
         let tag = self.tag_gen.next();
         let cmd = Command::Select {
             mailbox: Mailbox::new(mailbox),
-            condstore: false,
+            condstore,
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -46,7 +73,13 @@ where
                 stream: self.stream,
                 tag_gen: self.tag_gen,
                 capabilities: self.capabilities,
-                state: Selected::new(mailbox, false, status.clone()),
+                enabled: self.enabled,
+                greeting: self.greeting,
+                server_id: self.server_id,
+                // The status already counts what SELECT reported.
+                mailbox_updates: Vec::new(),
+                // The server may still grant only read-only access
+                state: Selected::new(mailbox, status.read_only, status.clone()),
             },
             status,
         ))
@@ -61,7 +94,7 @@ where
         let cmd = Command::Examine {
             mailbox: Mailbox::new(mailbox),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -74,6 +107,10 @@ where
                 stream: self.stream,
                 tag_gen: self.tag_gen,
                 capabilities: self.capabilities,
+                enabled: self.enabled,
+                greeting: self.greeting,
+                server_id: self.server_id,
+                mailbox_updates: Vec::new(),
                 state: Selected::new(mailbox, true, status.clone()),
             },
             status,
@@ -91,7 +128,7 @@ where
             reference: reference.to_string(),
             pattern: pattern.to_string(),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -110,19 +147,68 @@ where
         Ok(list_responses)
     }
 
-    /// Creates a new mailbox.
-    pub async fn create(&mut self, mailbox: &str) -> Result<()> {
+    /// Lists mailboxes with LIST-EXTENDED selection and return options.
+    ///
+    /// With [`ListReturnOpt::Status`] the server's interleaved STATUS
+    /// replies are attached to the matching entries, saving a STATUS round
+    /// trip per folder.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// LIST-EXTENDED, or LIST-STATUS when status is requested.
+    pub async fn list_extended(
+        &mut self,
+        selection: &[ListSelectOpt],
+        reference: &str,
+        patterns: &[&str],
+        return_opts: &[ListReturnOpt],
+    ) -> Result<Vec<crate::types::ListResponse>> {
+        if !self.supports_list_extended() {
+            return Err(Error::Unsupported("LIST-EXTENDED".to_string()));
+        }
+        let wants_status = return_opts
+            .iter()
+            .any(|opt| matches!(opt, ListReturnOpt::Status(_)));
+        if wants_status && !self.supports_list_status() {
+            return Err(Error::Unsupported("LIST-STATUS".to_string()));
+        }
+
         let tag = self.tag_gen.next();
-        let cmd = Command::Create {
-            mailbox: Mailbox::new(mailbox),
+        let cmd = Command::ListExtended {
+            selection: selection.to_vec(),
+            reference: reference.to_string(),
+            patterns: patterns.iter().map(ToString::to_string).collect(),
+            return_opts: return_opts.to_vec(),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
         let responses = self.read_until_tagged(&tag).await?;
+        let mut list_responses: Vec<crate::types::ListResponse> = Vec::new();
+
+        for response_bytes in &responses {
+            match ResponseParser::parse(response_bytes) {
+                Ok(Response::Untagged(UntaggedResponse::List(item))) => list_responses.push(item),
+                Ok(Response::Untagged(UntaggedResponse::Status { mailbox, items })) => {
+                    if let Some(entry) = list_responses
+                        .iter_mut()
+                        .rev()
+                        .find(|entry| entry.mailbox == mailbox)
+                    {
+                        entry.status = Some(MailboxStatus::from(items.as_slice()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+        Ok(list_responses)
+    }
+
+    /// Creates a new mailbox.
+    pub async fn create(&mut self, mailbox: &str) -> Result<()> {
+        self.send_create(mailbox).await
     }
 
     /// Deletes a mailbox.
@@ -131,7 +217,7 @@ where
         let cmd = Command::Delete {
             mailbox: Mailbox::new(mailbox),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -147,7 +233,7 @@ where
             from: Mailbox::new(from),
             to: Mailbox::new(to),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -158,17 +244,7 @@ where
 
     /// Subscribes to a mailbox.
     pub async fn subscribe(&mut self, mailbox: &str) -> Result<()> {
-        let tag = self.tag_gen.next();
-        let cmd = Command::Subscribe {
-            mailbox: Mailbox::new(mailbox),
-        }
-        .serialize(&tag);
-
-        self.stream.write_command(&cmd).await?;
-
-        let responses = self.read_until_tagged(&tag).await?;
-        Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+        self.send_subscribe(mailbox).await
     }
 
     /// Unsubscribes from a mailbox.
@@ -177,7 +253,7 @@ where
         let cmd = Command::Unsubscribe {
             mailbox: Mailbox::new(mailbox),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -187,17 +263,29 @@ where
     }
 
     /// Gets the status of a mailbox without selecting it.
+    ///
+    /// Only the requested attributes are filled in; message and unseen
+    /// counts land in `exists` and `unseen_count`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let status = client
+    ///     .status("INBOX", &[StatusAttribute::Messages, StatusAttribute::Unseen])
+    ///     .await?;
+    /// println!("{} unread of {}", status.unseen_count.unwrap_or(0), status.exists);
+    /// ```
     pub async fn status(
         &mut self,
         mailbox: &str,
-        items: Vec<crate::command::StatusAttribute>,
-    ) -> Result<Vec<StatusItem>> {
+        items: &[crate::command::StatusAttribute],
+    ) -> Result<MailboxStatus> {
         let tag = self.tag_gen.next();
         let cmd = Command::Status {
             mailbox: Mailbox::new(mailbox),
-            items,
+            items: items.to_vec(),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -213,63 +301,334 @@ where
         }
 
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(result)
+        Ok(MailboxStatus::from(result.as_slice()))
+    }
+
+    /// Requests STATUS for several mailboxes, pipelining the commands.
+    ///
+    /// Up to [`PipelineConfig`]'s default depth of commands are in flight at
+    /// once; replies are matched to mailboxes by name. Mailboxes the server
+    /// refuses (NO or BAD, e.g. `\Noselect` folders) are skipped, so the
+    /// result may be shorter than `mailboxes`. Results keep the requested
+    /// order.
+    pub async fn status_many(
+        &mut self,
+        mailboxes: &[Mailbox],
+        items: &[crate::command::StatusAttribute],
+    ) -> Result<Vec<(Mailbox, MailboxStatus)>> {
+        let mut pipeline = Pipeline::new(PipelineConfig::default());
+        let mut results = Vec::with_capacity(mailboxes.len());
+
+        for chunk in mailboxes.chunks(pipeline.config().max_depth) {
+            for mailbox in chunk {
+                let command = Command::Status {
+                    mailbox: mailbox.clone(),
+                    items: items.to_vec(),
+                };
+                pipeline.queue(QueuedCommand::new(command, Tag::new(self.tag_gen.next())));
+            }
+
+            let queued = pipeline.flush();
+            let mut cmd = Vec::new();
+            for queued in &queued {
+                cmd.extend(
+                    queued
+                        .command
+                        .serialize_with(queued.tag.as_str(), self.utf8_enabled()),
+                );
+            }
+            self.stream.write_command(&cmd).await?;
+
+            let mut replies: Vec<(Mailbox, Vec<StatusItem>)> = Vec::new();
+            let mut succeeded = Vec::new();
+            while pipeline.in_flight_count() > 0 {
+                let response = self.stream.read_response().await?;
+                match ResponseParser::parse(&response) {
+                    Ok(Response::Untagged(UntaggedResponse::Status { mailbox, items })) => {
+                        replies.push((mailbox, items));
+                    }
+                    Ok(Response::Untagged(UntaggedResponse::Bye { text, .. })) => {
+                        return Err(Error::Bye(text));
+                    }
+                    // Completing the tag first keeps refused commands from
+                    // staying in flight
+                    Ok(Response::Tagged { tag, status, .. })
+                        if pipeline.complete(&tag) && status == Status::Ok =>
+                    {
+                        succeeded.push(tag);
+                    }
+                    _ => {}
+                }
+            }
+
+            for (mailbox, queued) in chunk.iter().zip(&queued) {
+                if !succeeded.contains(&queued.tag) {
+                    continue;
+                }
+                let status_items: Vec<StatusItem> = replies
+                    .iter()
+                    .filter(|(name, _)| same_mailbox(name, mailbox))
+                    .flat_map(|(_, items)| items.iter().cloned())
+                    .collect();
+                results.push((
+                    mailbox.clone(),
+                    MailboxStatus::from(status_items.as_slice()),
+                ));
+            }
+        }
+
+        Ok(results)
     }
 
     /// Appends a message to a mailbox.
     ///
     /// The message should be a complete RFC 5322 message.
+    ///
+    /// Returns the UIDVALIDITY and UID of the new message if the server
+    /// reports `APPENDUID` (UIDPLUS).
     pub async fn append(
         &mut self,
         mailbox: &str,
         flags: Option<Vec<crate::types::Flag>>,
         message: &[u8],
-    ) -> Result<()> {
+    ) -> Result<Option<AppendUid>> {
+        self.append_full(mailbox, flags, None, message).await
+    }
+
+    /// Appends a message, creating `mailbox` first if the server reports
+    /// it doesn't exist with `[TRYCREATE]`.
+    ///
+    /// The APPEND is retried once, after CREATE succeeds.
+    pub async fn append_creating(
+        &mut self,
+        mailbox: &str,
+        flags: Option<Vec<crate::types::Flag>>,
+        message: &[u8],
+    ) -> Result<Option<AppendUid>> {
+        match self.append(mailbox, flags.clone(), message).await {
+            Err(e) if e.is_try_create() => {
+                self.create(mailbox).await?;
+                self.append(mailbox, flags, message).await
+            }
+            result => result,
+        }
+    }
+
+    /// Appends a message with an explicit internal date.
+    ///
+    /// Use this when importing or migrating mail so the message keeps its
+    /// original received date; with `date` set to `None` the server uses the
+    /// current time, like [`append`](Self::append).
+    pub async fn append_full(
+        &mut self,
+        mailbox: &str,
+        flags: Option<Vec<crate::types::Flag>>,
+        date: Option<DateTime<FixedOffset>>,
+        message: &[u8],
+    ) -> Result<Option<AppendUid>> {
         let tag = self.tag_gen.next();
 
         // APPEND uses literals which require continuation handling
         // First, send the command with literal size
-        let mut cmd = format!("{tag} APPEND ");
-        cmd.push_str(&Mailbox::new(mailbox).to_string());
-        if let Some(ref f) = flags {
-            cmd.push_str(" (");
-            for (i, flag) in f.iter().enumerate() {
-                if i > 0 {
-                    cmd.push(' ');
+        let mut cmd = format!("{tag} APPEND ").into_bytes();
+        write_mailbox(&mut cmd, &Mailbox::new(mailbox), self.utf8_enabled());
+        write_append_args(&mut cmd, flags.as_deref(), date.as_ref(), message.len());
+        cmd.extend_from_slice(b"\r\n");
+
+        self.stream.write_command(&cmd).await?;
+        self.wait_for_append_continuation().await?;
+
+        // Send the message data
+        self.stream.write_command(message).await?;
+        self.stream.write_command(b"\r\n").await?;
+
+        // Read the tagged response
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_over_quota(&responses, &tag)?;
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(Self::find_response_code(
+            &responses,
+            ResponseCode::append_uid,
+        ))
+    }
+
+    /// Appends several messages to a mailbox (MULTIAPPEND, RFC 3502).
+    ///
+    /// With MULTIAPPEND the upload is a single command, so either all
+    /// messages are added or none are. Without it, falls back to one APPEND
+    /// per message, which stops at the first failure.
+    ///
+    /// Returns the UIDVALIDITY and the new UIDs if the server reports
+    /// `APPENDUID` (UIDPLUS).
+    pub async fn multi_append(
+        &mut self,
+        mailbox: &str,
+        items: &[AppendItem],
+    ) -> Result<Option<AppendUid>> {
+        if items.is_empty() {
+            return Ok(None);
+        }
+        if self.supports_multiappend() {
+            return self.append_items(mailbox, items).await;
+        }
+
+        let mut validity = None;
+        let mut uids = Vec::new();
+        let mut complete = true;
+        for item in items {
+            match self
+                .append_items(mailbox, std::slice::from_ref(item))
+                .await?
+            {
+                Some(AppendUid {
+                    validity: v,
+                    uids: set,
+                }) if validity.is_none_or(|prev| prev == v) => {
+                    validity = Some(v);
+                    match set {
+                        UidSet::Set(items) => uids.extend(items),
+                        single => uids.push(single),
+                    }
                 }
-                cmd.push_str(flag.as_str());
+                _ => complete = false,
             }
-            cmd.push(')');
         }
-        // Writing to a String never fails
-        let _ = write!(cmd, " {{{}}}\r\n", message.len());
 
-        self.stream.write_command(cmd.as_bytes()).await?;
+        Ok(validity.filter(|_| complete).map(|validity| AppendUid {
+            validity,
+            uids: if uids.len() == 1 {
+                uids.remove(0)
+            } else {
+                UidSet::Set(uids)
+            },
+        }))
+    }
 
-        // Wait for continuation response
+    /// Sends one APPEND command carrying `items` as consecutive literals.
+    async fn append_items(
+        &mut self,
+        mailbox: &str,
+        items: &[AppendItem],
+    ) -> Result<Option<AppendUid>> {
+        let tag = self.tag_gen.next();
+        let lines = append_lines(&tag, &Mailbox::new(mailbox), items, self.utf8_enabled());
+
+        // Each literal must wait for the server's continuation request
+        for (line, item) in lines.iter().zip(items) {
+            self.stream.write_raw(line).await?;
+            self.wait_for_append_continuation().await?;
+            self.stream.write_raw(&item.message).await?;
+        }
+        self.stream.write_raw(b"\r\n").await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_over_quota(&responses, &tag)?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        Ok(Self::find_response_code(
+            &responses,
+            ResponseCode::append_uid,
+        ))
+    }
+
+    /// Waits for the `+` continuation request before sending an APPEND literal.
+    async fn wait_for_append_continuation(&mut self) -> Result<()> {
         let response = self.stream.read_response().await?;
-        if !response.starts_with(b"+") {
-            let parsed = ResponseParser::parse(&response)?;
-            if let Response::Tagged { status, text, .. } = parsed {
-                return match status {
-                    Status::No => Err(Error::No(text)),
-                    Status::Bad => Err(Error::Bad(text)),
-                    _ => Err(Error::Protocol("unexpected response to APPEND".to_string())),
-                };
+        if response.starts_with(b"+") {
+            return Ok(());
+        }
+
+        if let Response::Tagged {
+            status, code, text, ..
+        } = ResponseParser::parse(&response)?
+        {
+            return match status {
+                Status::No | Status::Bad => Err(Error::from_tagged(status, code, text)),
+                _ => Err(Error::Protocol("unexpected response to APPEND".to_string())),
+            };
+        }
+        Err(Error::Protocol(
+            "expected continuation for APPEND".to_string(),
+        ))
+    }
+
+    /// Turns on server extensions with ENABLE (RFC 5161), e.g.
+    /// `client.enable(&["UTF8=ACCEPT"])`.
+    ///
+    /// Returns the extensions the server reported as enabled; names it
+    /// doesn't know are silently ignored. Once UTF8=ACCEPT is enabled the
+    /// server accepts raw UTF-8, see [`utf8_enabled`](Self::utf8_enabled).
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// ENABLE.
+    pub async fn enable(&mut self, capabilities: &[&str]) -> Result<Vec<Capability>> {
+        if !self.has_capability(&Capability::Enable) {
+            return Err(Error::Unsupported("ENABLE".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Enable {
+            capabilities: capabilities.iter().map(ToString::to_string).collect(),
+        }
+        .serialize(&tag);
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        let mut enabled = Vec::new();
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Enabled(caps))) =
+                ResponseParser::parse(response_bytes)
+            {
+                enabled.extend(caps);
             }
-            return Err(Error::Protocol(
-                "expected continuation for APPEND".to_string(),
-            ));
         }
+        for cap in &enabled {
+            if !self.enabled.contains(cap) {
+                self.enabled.push(cap.clone());
+            }
+        }
+        Ok(enabled)
+    }
 
-        // Send the message data
-        self.stream.write_command(message).await?;
-        self.stream.write_command(b"\r\n").await?;
+    /// Enables COMPRESS=DEFLATE (RFC 4978) for the rest of the connection.
+    ///
+    /// On tagged OK, all further traffic in both directions is deflated; the
+    /// returned client behaves exactly like the original otherwise. The
+    /// server accepts COMPRESS once per connection, so issue it right after
+    /// authentication and before heavy traffic such as the initial sync.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// COMPRESS=DEFLATE.
+    pub async fn compress(mut self) -> Result<Client<DeflateStream<S>, Authenticated>> {
+        if !self.supports_compress_deflate() {
+            return Err(Error::Unsupported("COMPRESS=DEFLATE".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Compress.serialize(&tag);
+        self.stream.write_command(&cmd).await?;
 
-        // Read the tagged response
         let responses = self.read_until_tagged(&tag).await?;
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+
+        // The server may already have sent compressed data after its OK.
+        let timeouts = self.stream.timeouts();
+        let limits = self.stream.limits();
+        let (inner, buffered) = self.stream.into_parts();
+        Ok(Client {
+            stream: FramedStream::new(DeflateStream::with_buffered(inner, buffered))
+                .with_limits(limits)
+                .with_timeouts(timeouts),
+            tag_gen: self.tag_gen,
+            capabilities: self.capabilities,
+            enabled: self.enabled,
+            greeting: self.greeting,
+            server_id: self.server_id,
+            mailbox_updates: self.mailbox_updates,
+            state: Authenticated,
+        })
     }
 
     /// Gracefully disconnects from the server.
@@ -287,8 +646,8 @@ where
         let mut status = MailboxStatus::default();
 
         for response_bytes in responses {
-            if let Ok(Response::Untagged(untagged)) = ResponseParser::parse(response_bytes) {
-                match untagged {
+            match ResponseParser::parse(response_bytes) {
+                Ok(Response::Untagged(untagged)) => match untagged {
                     UntaggedResponse::Exists(n) => status.exists = n,
                     UntaggedResponse::Recent(n) => status.recent = n,
                     UntaggedResponse::Flags(flags) => status.flags = flags,
@@ -304,13 +663,30 @@ where
                         ResponseCode::Unseen(v) => {
                             status.unseen = Some(v);
                         }
+                        ResponseCode::HighestModSeq(v) => {
+                            status.highest_mod_seq = Some(v);
+                        }
+                        ResponseCode::NoModSeq => {
+                            status.highest_mod_seq = None;
+                        }
                         _ => {}
                     },
                     _ => {}
-                }
+                },
+                // SELECT may still only grant read-only access
+                Ok(Response::Tagged {
+                    code: Some(ResponseCode::ReadOnly),
+                    ..
+                }) => status.read_only = true,
+                _ => {}
             }
         }
 
         status
     }
 }
+
+/// Compares mailbox names, treating INBOX case-insensitively (RFC 3501).
+fn same_mailbox(a: &Mailbox, b: &Mailbox) -> bool {
+    a == b || (a.as_str().eq_ignore_ascii_case("INBOX") && b.as_str().eq_ignore_ascii_case("INBOX"))
+}
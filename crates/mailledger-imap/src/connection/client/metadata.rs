@@ -0,0 +1,149 @@
+//! METADATA commands (RFC 5464), valid in the authenticated and selected
+//! states.
+//!
+//! An empty mailbox name addresses annotations on the server itself.
+
+use std::collections::HashMap;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::Client;
+use super::states::{Authenticated, Selected};
+use crate::command::Command;
+use crate::parser::{Response, ResponseParser, UntaggedResponse};
+use crate::types::Mailbox;
+use crate::{Error, Result};
+
+impl<S, State> Client<S, State>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Fails unless the server accepts annotations on `mailbox`.
+    fn check_metadata_support(&self, mailbox: &str) -> Result<()> {
+        let supported = if mailbox.is_empty() {
+            self.supports_server_metadata()
+        } else {
+            self.supports_metadata()
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(Error::Unsupported("METADATA".to_string()))
+        }
+    }
+
+    /// Sends GETMETADATA and collects the returned entries.
+    async fn send_get_metadata(
+        &mut self,
+        mailbox: &str,
+        entries: &[&str],
+    ) -> Result<HashMap<String, Option<String>>> {
+        self.check_metadata_support(mailbox)?;
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::GetMetadata {
+            mailbox: Mailbox::new(mailbox),
+            entries: entries.iter().map(ToString::to_string).collect(),
+        }
+        .serialize_with(&tag, self.utf8_enabled());
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        // Servers may split the entries over several responses
+        let mut values = HashMap::new();
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Metadata { entries, .. })) =
+                ResponseParser::parse(response_bytes)
+            {
+                values.extend(entries);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Sends SETMETADATA, waiting for the server before each literal value.
+    async fn send_set_metadata(
+        &mut self,
+        mailbox: &str,
+        entries: &[(&str, Option<&str>)],
+    ) -> Result<()> {
+        self.check_metadata_support(mailbox)?;
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::SetMetadata {
+            mailbox: Mailbox::new(mailbox),
+            entries: entries
+                .iter()
+                .map(|(entry, value)| ((*entry).to_string(), value.map(ToString::to_string)))
+                .collect(),
+        }
+        .serialize_with(&tag, self.utf8_enabled());
+
+        let responses = self.send_with_literals(&tag, &cmd).await?;
+        Self::check_tagged_ok(&responses, &tag)
+    }
+}
+
+impl<S> Client<S, Authenticated>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the values of annotation `entries` on `mailbox`
+    /// (GETMETADATA), such as `/private/comment`.
+    ///
+    /// Entries the server has no value for are missing from the map or
+    /// `None`. Pass an empty `mailbox` for server annotations.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// METADATA.
+    pub async fn get_metadata(
+        &mut self,
+        mailbox: &str,
+        entries: &[&str],
+    ) -> Result<HashMap<String, Option<String>>> {
+        self.send_get_metadata(mailbox, entries).await
+    }
+
+    /// Sets annotation entries on `mailbox` (SETMETADATA); a `None` value
+    /// removes the entry.
+    pub async fn set_metadata(
+        &mut self,
+        mailbox: &str,
+        entries: &[(&str, Option<&str>)],
+    ) -> Result<()> {
+        self.send_set_metadata(mailbox, entries).await
+    }
+}
+
+impl<S> Client<S, Selected>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the values of annotation `entries` on `mailbox`
+    /// (GETMETADATA), such as `/private/comment`.
+    ///
+    /// Entries the server has no value for are missing from the map or
+    /// `None`. Pass an empty `mailbox` for server annotations.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// METADATA.
+    pub async fn get_metadata(
+        &mut self,
+        mailbox: &str,
+        entries: &[&str],
+    ) -> Result<HashMap<String, Option<String>>> {
+        self.send_get_metadata(mailbox, entries).await
+    }
+
+    /// Sets annotation entries on `mailbox` (SETMETADATA); a `None` value
+    /// removes the entry.
+    pub async fn set_metadata(
+        &mut self,
+        mailbox: &str,
+        entries: &[(&str, Option<&str>)],
+    ) -> Result<()> {
+        self.send_set_metadata(mailbox, entries).await
+    }
+}
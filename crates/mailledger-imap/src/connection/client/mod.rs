@@ -24,17 +24,24 @@
 #![allow(clippy::missing_errors_doc)]
 
 mod authenticated;
+mod metadata;
+mod namespace;
 mod not_authenticated;
+mod quota;
 mod selected;
 mod states;
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use tokio::io::{AsyncRead, AsyncWrite};
 
 pub use self::states::{Authenticated, NotAuthenticated, Selected};
-use super::framed::FramedStream;
-use crate::command::{Command, TagGenerator};
-use crate::parser::{Response, ResponseParser, UntaggedResponse};
-use crate::types::{Capability, Status};
+use super::framed::{FramedStream, Timeouts};
+use crate::command::{Command, TagGenerator, ThreadAlgorithm, split_at_literals};
+use crate::parser::{Greeting, Response, ResponseParser, UntaggedResponse};
+use crate::quirks::{ServerQuirks, ServerType};
+use crate::types::{Capability, Mailbox, ResponseCode};
 use crate::{Error, Result};
 
 /// IMAP client connection with type-state.
@@ -43,8 +50,17 @@ use crate::{Error, Result};
 /// For `Selected` state, the state carries runtime information about the mailbox.
 pub struct Client<S, State> {
     pub(crate) stream: FramedStream<S>,
-    pub(crate) tag_gen: TagGenerator,
+    /// Shared so a pool or session can keep one sequence across connections.
+    pub(crate) tag_gen: Arc<TagGenerator>,
     pub(crate) capabilities: Vec<Capability>,
+    /// Extensions turned on with ENABLE (RFC 5161).
+    pub(crate) enabled: Vec<Capability>,
+    /// The server greeting; its text identifies the server.
+    pub(crate) greeting: Greeting,
+    /// Server identification from ID (RFC 2971), if it was exchanged.
+    pub(crate) server_id: Option<HashMap<String, String>>,
+    /// EXISTS and EXPUNGE responses received since they were last taken.
+    pub(crate) mailbox_updates: Vec<UntaggedResponse>,
     /// State data. For marker types this is zero-sized, for `Selected` it holds mailbox info.
     pub(crate) state: State,
 }
@@ -55,6 +71,10 @@ impl<S, State: std::fmt::Debug> std::fmt::Debug for Client<S, State> {
         f.debug_struct("Client")
             .field("tag_gen", &self.tag_gen)
             .field("capabilities", &self.capabilities)
+            .field("enabled", &self.enabled)
+            .field("greeting", &self.greeting)
+            .field("server_id", &self.server_id)
+            .field("mailbox_updates", &self.mailbox_updates)
             .field("state", &self.state)
             .finish_non_exhaustive()
     }
@@ -71,6 +91,102 @@ where
         &self.capabilities
     }
 
+    /// Returns the text of the server greeting.
+    #[must_use]
+    pub fn greeting(&self) -> &str {
+        &self.greeting.text
+    }
+
+    /// Returns the server's ID response (RFC 2971), if ID was sent.
+    ///
+    /// ID is sent automatically before authentication to servers that
+    /// require it.
+    #[must_use]
+    pub const fn server_id(&self) -> Option<&HashMap<String, String>> {
+        self.server_id.as_ref()
+    }
+
+    /// Returns the I/O and command timeouts.
+    #[must_use]
+    pub const fn timeouts(&self) -> Timeouts {
+        self.stream.timeouts()
+    }
+
+    /// Changes the I/O and command timeouts.
+    ///
+    /// After a timeout the server may still answer the abandoned command,
+    /// so the connection should be dropped rather than reused.
+    pub const fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.stream.set_timeouts(timeouts);
+    }
+
+    /// Returns the generator that tags this connection's commands.
+    #[must_use]
+    pub const fn tag_generator(&self) -> &Arc<TagGenerator> {
+        &self.tag_gen
+    }
+
+    /// Tags further commands with `tag_gen`.
+    ///
+    /// Sharing one generator between successive connections, and calling
+    /// [`TagGenerator::reset_on_reconnect`] before each new one, keeps tags
+    /// from repeating across reconnects.
+    pub fn set_tag_generator(&mut self, tag_gen: Arc<TagGenerator>) {
+        self.tag_gen = tag_gen;
+    }
+
+    /// Moves the connection onto `wrap(stream)`, e.g. to erase the stream's
+    /// type.
+    ///
+    /// Fails if the server sent more than has been read, since those bytes
+    /// can't be carried over.
+    pub(crate) fn map_stream<T>(self, wrap: impl FnOnce(S) -> T) -> Result<Client<T, State>>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let timeouts = self.stream.timeouts();
+        let limits = self.stream.limits();
+        let (stream, buffered) = self.stream.into_parts();
+        if !buffered.is_empty() {
+            return Err(Error::Protocol("unexpected data from server".to_string()));
+        }
+        Ok(Client {
+            stream: FramedStream::new(wrap(stream))
+                .with_limits(limits)
+                .with_timeouts(timeouts),
+            tag_gen: self.tag_gen,
+            capabilities: self.capabilities,
+            enabled: self.enabled,
+            greeting: self.greeting,
+            server_id: self.server_id,
+            mailbox_updates: self.mailbox_updates,
+            state: self.state,
+        })
+    }
+
+    /// Takes the EXISTS and EXPUNGE responses received so far.
+    ///
+    /// Servers may report mailbox size changes in the middle of any command,
+    /// not just NOOP or IDLE. Such responses are collected here instead of
+    /// being dropped, oldest first. Since an EXPUNGE renumbers every later
+    /// message, callers should address messages by UID rather than sequence
+    /// number and apply these updates to cached sequence numbers.
+    pub fn take_mailbox_updates(&mut self) -> Vec<UntaggedResponse> {
+        std::mem::take(&mut self.mailbox_updates)
+    }
+
+    /// Identifies the server from its greeting and capabilities.
+    #[must_use]
+    pub fn server_type(&self) -> ServerType {
+        ServerType::detect(&self.capabilities, Some(&self.greeting.text))
+    }
+
+    /// Returns the quirks of the connected server.
+    #[must_use]
+    pub fn quirks(&self) -> ServerQuirks {
+        ServerQuirks::for_server(self.server_type(), &self.capabilities)
+    }
+
     /// Checks if the server has a specific capability.
     #[must_use]
     pub fn has_capability(&self, cap: &Capability) -> bool {
@@ -96,9 +212,11 @@ where
     }
 
     /// Returns true if the server supports NAMESPACE (RFC 2342).
+    ///
+    /// NAMESPACE is part of `IMAP4rev2`, so it is implied by that capability.
     #[must_use]
     pub fn supports_namespace(&self) -> bool {
-        self.has_capability(&Capability::Namespace)
+        self.has_capability(&Capability::Namespace) || self.supports_imap4rev2()
     }
 
     /// Returns true if the server supports CONDSTORE (RFC 7162).
@@ -113,6 +231,102 @@ where
         self.has_capability(&Capability::UidPlus)
     }
 
+    /// Returns true if the server supports SORT (RFC 5256).
+    #[must_use]
+    pub fn supports_sort(&self) -> bool {
+        self.has_capability(&Capability::Sort)
+    }
+
+    /// Returns true if the server supports THREAD with `algorithm` (RFC 5256).
+    #[must_use]
+    pub fn supports_thread(&self, algorithm: ThreadAlgorithm) -> bool {
+        self.capabilities.iter().any(
+            |c| matches!(c, Capability::Thread(a) if a.eq_ignore_ascii_case(algorithm.as_str())),
+        )
+    }
+
+    /// Returns true if the server supports ESEARCH (RFC 4731).
+    #[must_use]
+    pub fn supports_esearch(&self) -> bool {
+        self.has_capability(&Capability::ESearch)
+    }
+
+    /// Returns true if the server supports MULTIAPPEND (RFC 3502).
+    #[must_use]
+    pub fn supports_multiappend(&self) -> bool {
+        self.has_capability(&Capability::MultiAppend)
+    }
+
+    /// Returns true if the server supports BINARY (RFC 3516).
+    #[must_use]
+    pub fn supports_binary(&self) -> bool {
+        self.has_capability(&Capability::Binary)
+    }
+
+    /// Returns true if the server supports COMPRESS=DEFLATE (RFC 4978).
+    #[must_use]
+    pub fn supports_compress_deflate(&self) -> bool {
+        self.capabilities
+            .iter()
+            .any(|c| matches!(c, Capability::Compress(a) if a.eq_ignore_ascii_case("DEFLATE")))
+    }
+
+    /// Returns true if the server supports LIST-EXTENDED (RFC 5258).
+    #[must_use]
+    pub fn supports_list_extended(&self) -> bool {
+        self.has_capability(&Capability::ListExtended)
+    }
+
+    /// Returns true if the server supports LIST-STATUS (RFC 5819).
+    #[must_use]
+    pub fn supports_list_status(&self) -> bool {
+        self.has_capability(&Capability::ListStatus)
+    }
+
+    /// Returns true if the server supports QUOTA (RFC 9208).
+    #[must_use]
+    pub fn supports_quota(&self) -> bool {
+        self.has_capability(&Capability::Quota)
+    }
+
+    /// Returns true if the server supports mailbox annotations with
+    /// METADATA (RFC 5464).
+    ///
+    /// Servers that only advertise METADATA-SERVER accept annotations on
+    /// the server itself; see [`Self::supports_server_metadata`].
+    #[must_use]
+    pub fn supports_metadata(&self) -> bool {
+        self.has_capability(&Capability::Metadata)
+    }
+
+    /// Returns true if the server supports server annotations (RFC 5464).
+    ///
+    /// METADATA implies METADATA-SERVER.
+    #[must_use]
+    pub fn supports_server_metadata(&self) -> bool {
+        self.has_capability(&Capability::MetadataServer) || self.supports_metadata()
+    }
+
+    /// Returns true if the server supports UNSELECT (RFC 3691).
+    ///
+    /// UNSELECT is part of `IMAP4rev2`, so it is implied by that capability.
+    #[must_use]
+    pub fn supports_unselect(&self) -> bool {
+        self.has_capability(&Capability::Unselect) || self.supports_imap4rev2()
+    }
+
+    /// Returns true if the server supports Gmail's `X-GM-EXT-1` extensions.
+    #[must_use]
+    pub fn supports_gmail_extensions(&self) -> bool {
+        self.quirks().gmail_extensions
+    }
+
+    /// Returns true if the server accepts SASL initial responses (RFC 4959).
+    #[must_use]
+    pub fn supports_sasl_ir(&self) -> bool {
+        self.has_capability(&Capability::SaslIr)
+    }
+
     /// Returns true if LOGIN is disabled (e.g., before STARTTLS).
     #[must_use]
     pub fn login_disabled(&self) -> bool {
@@ -122,9 +336,34 @@ where
     /// Returns true if the server supports AUTH=PLAIN (SASL PLAIN mechanism).
     #[must_use]
     pub fn supports_auth_plain(&self) -> bool {
+        self.supports_auth("PLAIN")
+    }
+
+    /// Returns true if the server offers SCRAM-SHA-256 or SCRAM-SHA-1.
+    #[must_use]
+    pub fn supports_scram(&self) -> bool {
+        self.supports_auth("SCRAM-SHA-256") || self.supports_auth("SCRAM-SHA-1")
+    }
+
+    /// Returns true if the server advertises `AUTH=<mechanism>`.
+    #[must_use]
+    pub fn supports_auth(&self, mechanism: &str) -> bool {
         self.capabilities
             .iter()
-            .any(|c| matches!(c, Capability::Auth(m) if m.eq_ignore_ascii_case("PLAIN")))
+            .any(|c| matches!(c, Capability::Auth(m) if m.eq_ignore_ascii_case(mechanism)))
+    }
+
+    /// Returns true if `cap` was turned on with ENABLE.
+    #[must_use]
+    pub fn is_enabled(&self, cap: &Capability) -> bool {
+        self.enabled.contains(cap)
+    }
+
+    /// Returns true if UTF8=ACCEPT (RFC 6855) is enabled, so the server
+    /// takes raw UTF-8 in mailbox names and strings.
+    #[must_use]
+    pub fn utf8_enabled(&self) -> bool {
+        self.is_enabled(&Capability::Utf8Accept)
     }
 
     /// Sends a NOOP command to keep the connection alive.
@@ -139,6 +378,34 @@ where
         Ok(())
     }
 
+    /// Sends CREATE, valid in the authenticated and selected states.
+    async fn send_create(&mut self, mailbox: &str) -> Result<()> {
+        let tag = self.tag_gen.next();
+        let cmd = Command::Create {
+            mailbox: Mailbox::new(mailbox),
+        }
+        .serialize_with(&tag, self.utf8_enabled());
+
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)
+    }
+
+    /// Sends SUBSCRIBE, valid in the authenticated and selected states.
+    async fn send_subscribe(&mut self, mailbox: &str) -> Result<()> {
+        let tag = self.tag_gen.next();
+        let cmd = Command::Subscribe {
+            mailbox: Mailbox::new(mailbox),
+        }
+        .serialize_with(&tag, self.utf8_enabled());
+
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)
+    }
+
     /// Sends a CAPABILITY command and updates the stored capabilities.
     pub async fn capability(&mut self) -> Result<Vec<Capability>> {
         let tag = self.tag_gen.next();
@@ -161,10 +428,109 @@ where
         Ok(self.capabilities.clone())
     }
 
+    /// Sends a command that may contain synchronizing literals and reads
+    /// responses up to its tagged completion.
+    ///
+    /// Waits for a continuation request before each literal. If the server
+    /// rejects the command early with its tagged response, the rest of the
+    /// command is not sent.
+    pub(crate) async fn send_with_literals(
+        &mut self,
+        tag: &str,
+        cmd: &[u8],
+    ) -> Result<Vec<Vec<u8>>> {
+        let parts = split_at_literals(cmd);
+        let mut responses = Vec::new();
+
+        if let Some((last, leading)) = parts.split_last() {
+            for part in leading {
+                self.stream.write_command(part).await?;
+                loop {
+                    let response = self.stream.read_response().await?;
+                    if response.starts_with(b"+") {
+                        break;
+                    }
+                    let is_tagged = response.starts_with(tag.as_bytes())
+                        && response.get(tag.len()) == Some(&b' ');
+                    responses.push(response);
+                    if is_tagged {
+                        return Ok(responses);
+                    }
+                }
+            }
+            self.stream.write_command(last).await?;
+        }
+
+        responses.extend(self.read_until_tagged(tag).await?);
+        Ok(responses)
+    }
+
     /// Reads responses until we get a tagged response matching our tag.
     pub(crate) async fn read_until_tagged(&mut self, tag: &str) -> Result<Vec<Vec<u8>>> {
         let mut accumulator = super::framed::ResponseAccumulator::new(tag);
-        accumulator.read_until_tagged(&mut self.stream).await
+        let responses = accumulator.read_until_tagged(&mut self.stream).await?;
+        for bytes in &responses {
+            self.record_mailbox_update(bytes);
+        }
+        Ok(responses)
+    }
+
+    /// Keeps an untagged EXISTS or EXPUNGE for [`Self::take_mailbox_updates`].
+    pub(crate) fn record_mailbox_update(&mut self, bytes: &[u8]) {
+        let line = bytes.trim_ascii_end();
+        let is_update = |keyword: &[u8]| {
+            line.len() >= keyword.len()
+                && line[line.len() - keyword.len()..].eq_ignore_ascii_case(keyword)
+        };
+        if !line.starts_with(b"* ") || !(is_update(b" EXISTS") || is_update(b" EXPUNGE")) {
+            return;
+        }
+        if let Ok(Response::Untagged(
+            update @ (UntaggedResponse::Exists(_) | UntaggedResponse::Expunge(_)),
+        )) = ResponseParser::parse(bytes)
+        {
+            self.mailbox_updates.push(update);
+        }
+    }
+
+    /// Returns the first response code accepted by `f`, searching the
+    /// tagged and untagged OK responses from last to first.
+    pub(crate) fn find_response_code<T>(
+        responses: &[Vec<u8>],
+        f: impl Fn(&ResponseCode) -> Option<T>,
+    ) -> Option<T> {
+        responses
+            .iter()
+            .rev()
+            .find_map(|bytes| match ResponseParser::parse(bytes) {
+                Ok(
+                    Response::Tagged {
+                        code: Some(code), ..
+                    }
+                    | Response::Untagged(UntaggedResponse::Ok {
+                        code: Some(code), ..
+                    }),
+                ) => f(&code),
+                _ => None,
+            })
+    }
+
+    /// Fails with [`Error::QuotaExceeded`] if the tagged response carries
+    /// `[OVERQUOTA]`.
+    pub(crate) fn check_over_quota(responses: &[Vec<u8>], tag: &str) -> Result<()> {
+        for response_bytes in responses.iter().rev() {
+            if let Ok(Response::Tagged {
+                tag: resp_tag,
+                code: Some(ResponseCode::OverQuota),
+                text,
+                ..
+            }) = ResponseParser::parse(response_bytes)
+                && resp_tag.as_str() == tag
+            {
+                return Err(Error::QuotaExceeded(text));
+            }
+        }
+        Ok(())
     }
 
     /// Checks that the tagged response is OK.
@@ -174,16 +540,15 @@ where
             if let Ok(Response::Tagged {
                 tag: resp_tag,
                 status,
-                code: _,
+                code,
                 text,
             }) = ResponseParser::parse(response_bytes)
                 && resp_tag.as_str() == tag
             {
-                return match status {
-                    Status::Ok | Status::PreAuth => Ok(()),
-                    Status::No => Err(Error::No(text)),
-                    Status::Bad => Err(Error::Bad(text)),
-                    Status::Bye => Err(Error::Bye(text)),
+                return if status.is_ok() {
+                    Ok(())
+                } else {
+                    Err(Error::from_tagged(status, code, text))
                 };
             }
         }
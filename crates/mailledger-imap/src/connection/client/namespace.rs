@@ -0,0 +1,62 @@
+//! NAMESPACE command (RFC 2342), valid in the authenticated and selected states.
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::Client;
+use super::states::{Authenticated, Selected};
+use crate::command::Command;
+use crate::parser::{Namespace, Response, ResponseParser, UntaggedResponse};
+use crate::{Error, Result};
+
+impl<S, State> Client<S, State>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Sends NAMESPACE and returns the parsed reply.
+    async fn send_namespace(&mut self) -> Result<Namespace> {
+        if !self.supports_namespace() {
+            return Err(Error::Unsupported("NAMESPACE".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Namespace.serialize(&tag);
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        Ok(responses
+            .iter()
+            .find_map(|bytes| match ResponseParser::parse(bytes) {
+                Ok(Response::Untagged(UntaggedResponse::Namespace(ns))) => Some(ns),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+}
+
+impl<S> Client<S, Authenticated>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the server's personal, other users' and shared namespaces.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server supports neither
+    /// NAMESPACE nor `IMAP4rev2`.
+    pub async fn namespace(&mut self) -> Result<Namespace> {
+        self.send_namespace().await
+    }
+}
+
+impl<S> Client<S, Selected>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the server's personal, other users' and shared namespaces.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server supports neither
+    /// NAMESPACE nor `IMAP4rev2`.
+    pub async fn namespace(&mut self) -> Result<Namespace> {
+        self.send_namespace().await
+    }
+}
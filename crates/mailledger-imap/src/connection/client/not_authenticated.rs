@@ -1,16 +1,26 @@
 //! Implementation for the not-authenticated state.
 
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rustls::pki_types::ServerName;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
 
 use super::Client;
 use super::states::{Authenticated, NotAuthenticated};
 use crate::command::{Command, TagGenerator};
-use crate::connection::framed::FramedStream;
+use crate::connection::framed::{FrameLimits, FramedStream};
+use crate::connection::stream::{create_tls_connector, handshake_error};
 use crate::parser::{Response, ResponseParser, UntaggedResponse};
-use crate::types::ResponseCode;
+use crate::types::{Capability, ResponseCode, Status};
 use crate::{Error, Result};
 use mailledger_oauth::Token;
-use mailledger_oauth::sasl::{oauthbearer_response, plain_response, xoauth2_response};
+use mailledger_oauth::sasl::{
+    ScramClient, ScramError, ScramHash, oauthbearer_response, plain_response, xoauth2_response,
+};
 
 impl<S> Client<S, NotAuthenticated>
 where
@@ -18,51 +28,176 @@ where
 {
     /// Creates a new client from a connected stream.
     ///
-    /// Reads the server greeting and initial capabilities.
+    /// Reads the server greeting and initial capabilities. If the greeting
+    /// is PREAUTH, take the authenticated client with
+    /// [`Self::preauthenticated`] instead of logging in.
+    ///
+    /// Fails with [`Error::Bye`] if the server refuses the connection.
     pub async fn from_stream(stream: S) -> Result<Self> {
-        let mut framed = FramedStream::new(stream);
-
-        // Read server greeting
-        let greeting = framed.read_response().await?;
-        let response = ResponseParser::parse(&greeting)?;
-
-        // Extract capabilities from greeting if present
-        let mut capabilities = Vec::new();
-        if let Response::Untagged(untagged) = response {
-            match untagged {
-                UntaggedResponse::Ok {
-                    code: Some(ResponseCode::Capability(caps)),
-                    ..
-                }
-                | UntaggedResponse::PreAuth {
-                    code: Some(ResponseCode::Capability(caps)),
-                    ..
-                } => {
-                    capabilities = caps;
-                }
-                UntaggedResponse::Bye { text, .. } => {
-                    return Err(Error::Bye(text));
-                }
-                _ => {}
-            }
+        Self::from_stream_with_limits(stream, FrameLimits::default()).await
+    }
+
+    /// Creates a new client from a connected stream, refusing responses
+    /// beyond `limits` from the greeting on.
+    pub async fn from_stream_with_limits(stream: S, limits: FrameLimits) -> Result<Self> {
+        let mut framed = FramedStream::new(stream).with_limits(limits);
+
+        let greeting = ResponseParser::parse_greeting(&framed.read_response().await?)?;
+        if greeting.status == Status::Bye {
+            return Err(Error::Bye(greeting.text));
         }
 
         Ok(Self {
             stream: framed,
-            tag_gen: TagGenerator::default(),
-            capabilities,
+            tag_gen: Arc::new(TagGenerator::default()),
+            capabilities: greeting.capabilities.clone(),
+            enabled: Vec::new(),
+            greeting,
+            server_id: None,
+            mailbox_updates: Vec::new(),
             state: NotAuthenticated,
         })
     }
 
+    /// Returns true if the server greeted with PREAUTH, so the connection
+    /// is authenticated without logging in.
+    #[must_use]
+    pub fn is_preauthenticated(&self) -> bool {
+        self.greeting.is_preauth()
+    }
+
+    /// Returns the authenticated client if the server greeted with
+    /// PREAUTH, or gives the client back unchanged otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` if the connection still needs to authenticate.
+    #[allow(clippy::result_large_err)] // The client is handed back, not an error value
+    pub fn preauthenticated(self) -> std::result::Result<Client<S, Authenticated>, Self> {
+        if !self.is_preauthenticated() {
+            return Err(self);
+        }
+
+        Ok(Client {
+            stream: self.stream,
+            tag_gen: self.tag_gen,
+            capabilities: self.capabilities,
+            enabled: self.enabled,
+            greeting: self.greeting,
+            server_id: self.server_id,
+            mailbox_updates: self.mailbox_updates,
+            state: Authenticated,
+        })
+    }
+
+    /// Upgrades the connection to TLS using STARTTLS (RFC 3501).
+    ///
+    /// Sends `STARTTLS`, and once the server agrees performs the TLS
+    /// handshake for `domain` over the existing stream. Capabilities seen
+    /// before the upgrade can't be trusted, so they are requested again over
+    /// the encrypted connection.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// STARTTLS.
+    pub async fn starttls(self, domain: &str) -> Result<Client<TlsStream<S>, NotAuthenticated>> {
+        self.starttls_with(domain, create_tls_connector()?, std::convert::identity)
+            .await
+    }
+
+    /// Like [`Self::starttls`], using `connector` and wrapping the TLS
+    /// stream with `wrap`.
+    pub(crate) async fn starttls_with<T>(
+        mut self,
+        domain: &str,
+        connector: TlsConnector,
+        wrap: impl FnOnce(TlsStream<S>) -> T,
+    ) -> Result<Client<T, NotAuthenticated>>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        // A PREAUTH connection can't be upgraded, and going on without TLS
+        // would expose everything the caller meant to protect
+        if self.is_preauthenticated() {
+            return Err(Error::Protocol(
+                "PREAUTH greeting on a connection that requires STARTTLS".to_string(),
+            ));
+        }
+        if self.capabilities.is_empty() {
+            self.capability().await?;
+        }
+        if !self.has_capability(&Capability::StartTls) {
+            return Err(Error::Unsupported("STARTTLS".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::StartTls.serialize(&tag);
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        // Anything after the tagged OK arrived in plaintext and may have
+        // been injected, so refuse to carry it into the TLS session
+        let timeouts = self.stream.timeouts();
+        let limits = self.stream.limits();
+        let (stream, buffered) = self.stream.into_parts();
+        if !buffered.is_empty() {
+            return Err(Error::Protocol(
+                "unexpected data before TLS handshake".to_string(),
+            ));
+        }
+
+        let server_name = ServerName::try_from(domain.to_string())?;
+        let tls = connector
+            .connect(server_name, stream)
+            .await
+            .map_err(handshake_error)?;
+
+        let mut client = Client {
+            stream: FramedStream::new(wrap(tls))
+                .with_limits(limits)
+                .with_timeouts(timeouts),
+            tag_gen: self.tag_gen,
+            capabilities: Vec::new(),
+            enabled: Vec::new(),
+            greeting: self.greeting,
+            server_id: self.server_id,
+            mailbox_updates: self.mailbox_updates,
+            state: NotAuthenticated,
+        };
+        client.capability().await?;
+        Ok(client)
+    }
+
     /// Authenticates with the server using LOGIN.
     ///
     /// Consumes self and returns an authenticated client on success.
-    pub async fn login(
+    ///
+    /// Secure by default: if the server advertises LOGINDISABLED, as it
+    /// must on a connection that isn't encrypted yet, this fails with
+    /// [`Error::StartTlsRequired`] without sending the password. Upgrade
+    /// with [`Self::starttls`] first; [`connect`](crate::connection::connect)
+    /// does so on its own. [`Self::login_insecure`] skips the check.
+    pub async fn login(self, username: &str, password: &str) -> Result<Client<S, Authenticated>> {
+        if self.login_disabled() {
+            return Err(Error::StartTlsRequired);
+        }
+        self.login_insecure(username, password).await
+    }
+
+    /// Authenticates with LOGIN even if the server advertises
+    /// LOGINDISABLED.
+    ///
+    /// **Dangerous**: on a connection without TLS the password is sent in
+    /// the clear. Only for servers that advertise LOGINDISABLED wrongly,
+    /// or connections already protected some other way (e.g. a tunnel).
+    pub async fn login_insecure(
         mut self,
         username: &str,
         password: &str,
     ) -> Result<Client<S, Authenticated>> {
+        self.send_client_id_if_required().await?;
+
         let tag = self.tag_gen.next();
         let cmd = Command::Login {
             username: username.to_string(),
@@ -89,97 +224,283 @@ where
             stream: self.stream,
             tag_gen: self.tag_gen,
             capabilities: self.capabilities,
+            enabled: self.enabled,
+            greeting: self.greeting,
+            server_id: self.server_id,
+            mailbox_updates: self.mailbox_updates,
             state: Authenticated,
         })
     }
 
-    /// Authenticates with the server using SASL PLAIN mechanism (RFC 4616).
+    /// Identifies the client with ID (RFC 2971) if the server wants it
+    /// before authentication.
     ///
-    /// Consumes self and returns an authenticated client on success.
-    /// This sends credentials as base64-encoded `\0username\0password`.
+    /// Servers that insist on ID only need to see it; a NO or BAD reply
+    /// is ignored so authentication can still be attempted. The server's
+    /// own identification is kept for [`Client::server_id`].
+    async fn send_client_id_if_required(&mut self) -> Result<()> {
+        if !self.quirks().requires_id_command_before_login {
+            return Ok(());
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Id {
+            parameters: Some(vec![
+                ("name".to_string(), "mailledger".to_string()),
+                ("version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+            ]),
+        }
+        .serialize(&tag);
+        self.stream.write_command(&cmd).await?;
+
+        for response_bytes in self.read_until_tagged(&tag).await? {
+            if let Ok(Response::Untagged(UntaggedResponse::Id(fields))) =
+                ResponseParser::parse(&response_bytes)
+            {
+                self.server_id = Some(fields);
+            }
+        }
+        Ok(())
+    }
+
+    /// Authenticates using the SASL `mechanism` (AUTHENTICATE).
     ///
-    /// Use this method when the server advertises `AUTH=PLAIN` capability
-    /// and may not support the legacy LOGIN command.
+    /// `ir` is the base64-encoded client response, as produced by
+    /// [`mailledger_oauth::sasl`]. It goes on the command line when the
+    /// server supports SASL-IR (RFC 4959), otherwise in reply to the
+    /// server's first continuation request.
+    ///
+    /// Consumes self and returns an authenticated client on success.
     ///
     /// # Errors
     ///
-    /// Returns an error if authentication fails.
-    pub async fn authenticate_plain(
+    /// Returns [`Error::AuthenticationFailed`] with the server's decoded
+    /// error challenge (e.g. the `OAuth2` JSON status), or the tagged
+    /// response text if it sent none.
+    pub async fn authenticate(
         mut self,
-        username: &str,
-        password: &str,
+        mechanism: &str,
+        ir: Option<&str>,
     ) -> Result<Client<S, Authenticated>> {
-        let auth_string = plain_response(username, password);
+        self.send_client_id_if_required().await?;
+
+        let inline = self.supports_sasl_ir() && ir.is_some();
         let tag = self.tag_gen.next();
         let cmd = Command::Authenticate {
-            mechanism: "PLAIN".to_string(),
-            initial_response: Some(auth_string),
+            mechanism: mechanism.to_string(),
+            // An empty initial response is sent as "=" (RFC 4959)
+            initial_response: ir
+                .filter(|_| inline)
+                .map(|r| if r.is_empty() { "=" } else { r }.to_string()),
         }
         .serialize(&tag);
-
         self.stream.write_command(&cmd).await?;
-        let responses = self.read_until_tagged(&tag).await?;
 
-        // Update capabilities if included in response
-        for response_bytes in &responses {
-            if let Ok(Response::Untagged(UntaggedResponse::Capability(caps))) =
-                ResponseParser::parse(response_bytes)
-            {
-                self.capabilities = caps;
+        let mut pending = if inline { None } else { Some(ir.unwrap_or("")) };
+        let mut server_error = None;
+        let mut responses = Vec::new();
+        loop {
+            let response = self.stream.read_response().await?;
+            if response.starts_with(b"+") {
+                // A challenge after our response carries the failure reason;
+                // acknowledge it so the server sends its NO
+                let reply = pending.take().unwrap_or_else(|| {
+                    server_error = Some(decode_challenge(&response));
+                    if mechanism.eq_ignore_ascii_case("OAUTHBEARER") {
+                        "AQ=="
+                    } else {
+                        ""
+                    }
+                });
+                self.stream
+                    .write_command(format!("{reply}\r\n").as_bytes())
+                    .await?;
+                continue;
+            }
+
+            let done = matches!(
+                ResponseParser::parse(&response),
+                Ok(Response::Tagged { tag: ref t, .. }) if t.as_str() == tag
+            );
+            responses.push(response);
+            if done {
+                break;
             }
         }
 
-        Self::check_tagged_ok(&responses, &tag)?;
+        Self::check_tagged_ok(&responses, &tag).map_err(|e| match e {
+            Error::Tagged { text, .. } => Error::AuthenticationFailed(server_error.unwrap_or(text)),
+            other => other,
+        })?;
 
-        Ok(Client {
-            stream: self.stream,
-            tag_gen: self.tag_gen,
-            capabilities: self.capabilities,
-            state: Authenticated,
-        })
+        Ok(self.into_authenticated(&responses))
     }
 
-    /// Authenticates with the server using `OAuth2` XOAUTH2 mechanism.
+    /// Authenticates with SCRAM-SHA-256, or SCRAM-SHA-1 if that is all the
+    /// server offers (RFC 5802, RFC 7677).
+    ///
+    /// The password never leaves the client, and the server has to prove
+    /// it knows the password too before the connection is trusted.
     ///
     /// Consumes self and returns an authenticated client on success.
-    /// Uses the XOAUTH2 SASL mechanism (Google/Microsoft proprietary).
     ///
     /// # Errors
     ///
-    /// Returns an error if authentication fails or if the server doesn't support XOAUTH2.
-    pub async fn authenticate_xoauth2(
+    /// Fails with [`Error::Unsupported`] if the server offers neither
+    /// mechanism, and with [`Error::AuthenticationFailed`] if the server
+    /// rejects the credentials or its signature doesn't check out.
+    pub async fn authenticate_scram(
         mut self,
-        email: &str,
-        token: &Token,
+        username: &str,
+        password: &str,
     ) -> Result<Client<S, Authenticated>> {
-        let auth_string = xoauth2_response(email, &token.access_token);
+        let hash = if self.supports_auth("SCRAM-SHA-256") {
+            ScramHash::Sha256
+        } else if self.supports_auth("SCRAM-SHA-1") {
+            ScramHash::Sha1
+        } else {
+            return Err(Error::Unsupported("SCRAM".to_string()));
+        };
+        self.send_client_id_if_required().await?;
+
+        let mut scram = ScramClient::new(hash, username, password);
+        let client_first = STANDARD.encode(scram.client_first());
+        let inline = self.supports_sasl_ir();
         let tag = self.tag_gen.next();
         let cmd = Command::Authenticate {
-            mechanism: "XOAUTH2".to_string(),
-            initial_response: Some(auth_string),
+            mechanism: scram.mechanism().to_string(),
+            initial_response: inline.then(|| client_first.clone()),
         }
         .serialize(&tag);
-
         self.stream.write_command(&cmd).await?;
-        let responses = self.read_until_tagged(&tag).await?;
 
-        // Update capabilities if included in response
-        for response_bytes in &responses {
+        let mut sent_first = inline;
+        let mut verified = false;
+        let mut failure = None;
+        let mut responses = Vec::new();
+        loop {
+            let response = self.stream.read_response().await?;
+            if response.starts_with(b"+") {
+                let challenge = decode_challenge(&response);
+                let reply = if !sent_first {
+                    sent_first = true;
+                    Ok(client_first.clone())
+                } else if scram.client_final().is_none() {
+                    scram.handle_server_first(&challenge).and_then(|()| {
+                        scram
+                            .client_final()
+                            .map(|message| STANDARD.encode(message))
+                            .ok_or(ScramError::OutOfOrder)
+                    })
+                } else {
+                    scram.verify_server_final(&challenge).map(|()| {
+                        verified = true;
+                        String::new()
+                    })
+                };
+                // A bad challenge cancels the exchange (RFC 3501)
+                let reply = reply.unwrap_or_else(|e| {
+                    failure = Some(e);
+                    "*".to_string()
+                });
+                self.stream
+                    .write_command(format!("{reply}\r\n").as_bytes())
+                    .await?;
+                continue;
+            }
+
+            let done = matches!(
+                ResponseParser::parse(&response),
+                Ok(Response::Tagged { tag: ref t, .. }) if t.as_str() == tag
+            );
+            responses.push(response);
+            if done {
+                break;
+            }
+        }
+
+        if let Some(e) = failure {
+            return Err(Error::AuthenticationFailed(e.to_string()));
+        }
+        Self::check_tagged_ok(&responses, &tag).map_err(|e| match e {
+            Error::Tagged { text, .. } => Error::AuthenticationFailed(text),
+            other => other,
+        })?;
+        if !verified {
+            return Err(Error::AuthenticationFailed(
+                ScramError::InvalidServerMessage("missing server signature".to_string())
+                    .to_string(),
+            ));
+        }
+
+        Ok(self.into_authenticated(&responses))
+    }
+
+    /// Moves to the authenticated state, taking any capabilities the
+    /// server sent with its reply.
+    fn into_authenticated(mut self, responses: &[Vec<u8>]) -> Client<S, Authenticated> {
+        for response_bytes in responses {
             if let Ok(Response::Untagged(UntaggedResponse::Capability(caps))) =
                 ResponseParser::parse(response_bytes)
             {
                 self.capabilities = caps;
             }
         }
+        if let Some(caps) = Self::find_response_code(responses, |code| match code {
+            ResponseCode::Capability(caps) => Some(caps.clone()),
+            _ => None,
+        }) {
+            self.capabilities = caps;
+        }
 
-        Self::check_tagged_ok(&responses, &tag)?;
-
-        Ok(Client {
+        Client {
             stream: self.stream,
             tag_gen: self.tag_gen,
             capabilities: self.capabilities,
+            enabled: self.enabled,
+            greeting: self.greeting,
+            server_id: self.server_id,
+            mailbox_updates: self.mailbox_updates,
             state: Authenticated,
-        })
+        }
+    }
+
+    /// Authenticates with the server using SASL PLAIN mechanism (RFC 4616).
+    ///
+    /// Consumes self and returns an authenticated client on success.
+    /// This sends credentials as base64-encoded `\0username\0password`
+    /// (see [`plain_response`]).
+    ///
+    /// Use this method when the server advertises `AUTH=PLAIN` capability
+    /// and may not support the legacy LOGIN command.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if authentication fails.
+    pub async fn authenticate_plain(
+        self,
+        username: &str,
+        password: &str,
+    ) -> Result<Client<S, Authenticated>> {
+        let auth_string = plain_response(None, username, password);
+        self.authenticate("PLAIN", Some(&auth_string)).await
+    }
+
+    /// Authenticates with the server using `OAuth2` XOAUTH2 mechanism.
+    ///
+    /// Consumes self and returns an authenticated client on success.
+    /// Uses the XOAUTH2 SASL mechanism (Google/Microsoft proprietary).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if authentication fails or if the server doesn't support XOAUTH2.
+    pub async fn authenticate_xoauth2(
+        self,
+        email: &str,
+        token: &Token,
+    ) -> Result<Client<S, Authenticated>> {
+        let auth_string = xoauth2_response(email, &token.access_token);
+        self.authenticate("XOAUTH2", Some(&auth_string)).await
     }
 
     /// Authenticates with the server using `OAuth2` OAUTHBEARER mechanism.
@@ -191,38 +512,12 @@ where
     ///
     /// Returns an error if authentication fails or if the server doesn't support OAUTHBEARER.
     pub async fn authenticate_oauthbearer(
-        mut self,
+        self,
         email: &str,
         token: &Token,
     ) -> Result<Client<S, Authenticated>> {
         let auth_string = oauthbearer_response(email, &token.access_token);
-        let tag = self.tag_gen.next();
-        let cmd = Command::Authenticate {
-            mechanism: "OAUTHBEARER".to_string(),
-            initial_response: Some(auth_string),
-        }
-        .serialize(&tag);
-
-        self.stream.write_command(&cmd).await?;
-        let responses = self.read_until_tagged(&tag).await?;
-
-        // Update capabilities if included in response
-        for response_bytes in &responses {
-            if let Ok(Response::Untagged(UntaggedResponse::Capability(caps))) =
-                ResponseParser::parse(response_bytes)
-            {
-                self.capabilities = caps;
-            }
-        }
-
-        Self::check_tagged_ok(&responses, &tag)?;
-
-        Ok(Client {
-            stream: self.stream,
-            tag_gen: self.tag_gen,
-            capabilities: self.capabilities,
-            state: Authenticated,
-        })
+        self.authenticate("OAUTHBEARER", Some(&auth_string)).await
     }
 
     /// Gracefully disconnects from the server.
@@ -237,3 +532,14 @@ where
         Ok(())
     }
 }
+
+/// Decodes the base64 payload of a `+` continuation, falling back to the
+/// raw text if it isn't valid base64.
+fn decode_challenge(response: &[u8]) -> String {
+    let text = String::from_utf8_lossy(response);
+    let payload = text.trim_start_matches('+').trim();
+    STANDARD.decode(payload).map_or_else(
+        |_| payload.to_string(),
+        |bytes| String::from_utf8_lossy(&bytes).into_owned(),
+    )
+}
@@ -0,0 +1,121 @@
+//! QUOTA commands (RFC 9208), valid in the authenticated and selected states.
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::Client;
+use super::states::{Authenticated, Selected};
+use crate::command::Command;
+use crate::parser::{Quota, Response, ResponseParser, UntaggedResponse};
+use crate::types::Mailbox;
+use crate::{Error, Result};
+
+impl<S, State> Client<S, State>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Sends GETQUOTAROOT and returns one [`Quota`] per root, in the order
+    /// the server listed the roots.
+    async fn send_get_quota_root(&mut self, mailbox: &str) -> Result<Vec<Quota>> {
+        if !self.supports_quota() {
+            return Err(Error::Unsupported("QUOTA".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::GetQuotaRoot {
+            mailbox: Mailbox::new(mailbox),
+        }
+        .serialize_with(&tag, self.utf8_enabled());
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        let mut roots = Vec::new();
+        let mut quotas = Vec::new();
+        for response_bytes in &responses {
+            match ResponseParser::parse(response_bytes) {
+                Ok(Response::Untagged(UntaggedResponse::QuotaRoot { roots: r, .. })) => roots = r,
+                Ok(Response::Untagged(UntaggedResponse::Quota(quota))) => quotas.push(quota),
+                _ => {}
+            }
+        }
+
+        // A root without a QUOTA reply has no limits
+        Ok(roots
+            .into_iter()
+            .map(|root| {
+                quotas.iter().position(|q| q.root == root).map_or_else(
+                    || Quota {
+                        root,
+                        resources: Vec::new(),
+                    },
+                    |i| quotas.swap_remove(i),
+                )
+            })
+            .collect())
+    }
+
+    /// Sends GETQUOTA for a single root.
+    async fn send_get_quota(&mut self, root: &str) -> Result<Quota> {
+        if !self.supports_quota() {
+            return Err(Error::Unsupported("QUOTA".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::GetQuota {
+            root: root.to_string(),
+        }
+        .serialize(&tag);
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        Self::check_tagged_ok(&responses, &tag)?;
+
+        Ok(responses
+            .iter()
+            .find_map(|bytes| match ResponseParser::parse(bytes) {
+                Ok(Response::Untagged(UntaggedResponse::Quota(quota))) => Some(quota),
+                _ => None,
+            })
+            .unwrap_or_else(|| Quota {
+                root: root.to_string(),
+                resources: Vec::new(),
+            }))
+    }
+}
+
+impl<S> Client<S, Authenticated>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the quotas that apply to `mailbox` (GETQUOTAROOT).
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// QUOTA.
+    pub async fn get_quota_root(&mut self, mailbox: &str) -> Result<Vec<Quota>> {
+        self.send_get_quota_root(mailbox).await
+    }
+
+    /// Returns the usage and limits of a quota root (GETQUOTA).
+    pub async fn get_quota(&mut self, root: &str) -> Result<Quota> {
+        self.send_get_quota(root).await
+    }
+}
+
+impl<S> Client<S, Selected>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Returns the quotas that apply to `mailbox` (GETQUOTAROOT).
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// QUOTA.
+    pub async fn get_quota_root(&mut self, mailbox: &str) -> Result<Vec<Quota>> {
+        self.send_get_quota_root(mailbox).await
+    }
+
+    /// Returns the usage and limits of a quota root (GETQUOTA).
+    pub async fn get_quota(&mut self, root: &str) -> Result<Quota> {
+        self.send_get_quota(root).await
+    }
+}
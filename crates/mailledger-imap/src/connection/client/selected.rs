@@ -1,13 +1,21 @@
 //! Implementation for the selected state.
 
+use futures_util::stream::{self, Stream};
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use super::Client;
 use super::states::{Authenticated, Selected};
-use crate::Result;
-use crate::command::{Command, FetchItems, StoreAction};
-use crate::parser::{FetchItem, Response, ResponseParser, UntaggedResponse};
-use crate::types::{Mailbox, MailboxStatus, SequenceSet};
+use crate::command::{
+    Command, FetchAttribute, FetchItems, SearchCriteria, SearchReturnOption, SortKey, StoreAction,
+    ThreadAlgorithm, write_fetch_items,
+};
+use crate::parser::{
+    EsearchResult, FetchItem, Response, ResponseParser, ThreadNode, UntaggedResponse,
+};
+use crate::types::{
+    Capability, CopyUid, Mailbox, MailboxStatus, ResponseCode, SequenceSet, Uid, UidSet,
+};
+use crate::{Error, Result};
 
 impl<S> Client<S, Selected>
 where
@@ -25,6 +33,18 @@ where
         self.state.is_read_only()
     }
 
+    /// Fails with [`Error::InvalidState`] if the mailbox was opened
+    /// read-only, before `command` reaches the server.
+    fn ensure_writable(&self, command: &str) -> Result<()> {
+        if self.state.is_read_only() {
+            return Err(Error::InvalidState(format!(
+                "{command} needs a read-write mailbox, but {} was opened read-only",
+                self.state.mailbox()
+            )));
+        }
+        Ok(())
+    }
+
     /// Returns the number of messages in the mailbox (from SELECT/EXAMINE response).
     #[must_use]
     pub const fn exists(&self) -> u32 {
@@ -46,9 +66,22 @@ where
         self.state.status()
     }
 
+    /// Creates a new mailbox without leaving this one, e.g. as the
+    /// destination of a COPY that failed with `[TRYCREATE]`.
+    pub async fn create(&mut self, mailbox: &str) -> Result<()> {
+        self.send_create(mailbox).await
+    }
+
+    /// Subscribes to a mailbox without leaving this one.
+    pub async fn subscribe(&mut self, mailbox: &str) -> Result<()> {
+        self.send_subscribe(mailbox).await
+    }
+
     /// Closes the current mailbox and returns to authenticated state.
     ///
-    /// This performs an implicit EXPUNGE if the mailbox was opened read-write.
+    /// This performs an implicit EXPUNGE if the mailbox was opened read-write,
+    /// permanently removing messages flagged `\Deleted`. Use
+    /// [`Self::unselect`] to leave the mailbox without expunging.
     pub async fn close(mut self) -> Result<Client<S, Authenticated>> {
         let tag = self.tag_gen.next();
         let cmd = Command::Close.serialize(&tag);
@@ -57,12 +90,48 @@ where
         let responses = self.read_until_tagged(&tag).await?;
         Self::check_tagged_ok(&responses, &tag)?;
 
-        Ok(Client {
+        Ok(self.into_authenticated())
+    }
+
+    /// Leaves the current mailbox without expunging and returns to
+    /// authenticated state.
+    ///
+    /// Unlike [`Self::close`], messages flagged `\Deleted` are kept. Uses
+    /// UNSELECT (RFC 3691) when supported; otherwise the mailbox is
+    /// re-opened read-only with EXAMINE first, so the CLOSE that follows
+    /// can't expunge anything.
+    pub async fn unselect(mut self) -> Result<Client<S, Authenticated>> {
+        if self.supports_unselect() {
+            let tag = self.tag_gen.next();
+            let cmd = Command::Unselect.serialize(&tag);
+            self.stream.write_command(&cmd).await?;
+
+            let responses = self.read_until_tagged(&tag).await?;
+            Self::check_tagged_ok(&responses, &tag)?;
+
+            return Ok(self.into_authenticated());
+        }
+
+        if !self.is_read_only() {
+            let mailbox = self.mailbox().to_string();
+            (self, _) = self.examine(&mailbox).await?;
+        }
+        self.close().await
+    }
+
+    /// Moves to authenticated state once the mailbox has been left.
+    fn into_authenticated(self) -> Client<S, Authenticated> {
+        Client {
             stream: self.stream,
             tag_gen: self.tag_gen,
             capabilities: self.capabilities,
+            enabled: self.enabled,
+            greeting: self.greeting,
+            server_id: self.server_id,
+            // Updates only make sense for the mailbox being left.
+            mailbox_updates: Vec::new(),
             state: Authenticated,
-        })
+        }
     }
 
     /// Selects a different mailbox for read-write access.
@@ -75,7 +144,7 @@ where
             mailbox: Mailbox::new(mailbox),
             condstore: false,
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -83,8 +152,9 @@ where
         let status = Client::<S, Authenticated>::parse_mailbox_status(&responses);
         Self::check_tagged_ok(&responses, &tag)?;
 
-        // Update the state with new mailbox info
-        self.state = Selected::new(mailbox, false, status.clone());
+        // Update the state with new mailbox info; the server may still
+        // grant only read-only access
+        self.state = Selected::new(mailbox, status.read_only, status.clone());
 
         Ok((self, status))
     }
@@ -95,7 +165,7 @@ where
         let cmd = Command::Examine {
             mailbox: Mailbox::new(mailbox),
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
@@ -159,19 +229,259 @@ where
         Ok(results)
     }
 
+    /// Searches for messages matching typed `criteria`.
+    ///
+    /// `charset` names the charset of string arguments (e.g. `UTF-8`);
+    /// non-ASCII strings are sent as literals. If the server rejects the
+    /// charset with `[BADCHARSET]`, the search is retried once in US-ASCII.
+    /// [`SearchCriteria::GmailRaw`] requires `X-GM-EXT-1`.
+    pub async fn search_criteria(
+        &mut self,
+        criteria: &SearchCriteria,
+        charset: Option<&str>,
+    ) -> Result<Vec<crate::types::SeqNum>> {
+        self.search_command(criteria, charset, false).await
+    }
+
+    /// Like [`search_criteria`](Self::search_criteria), but returns UIDs.
+    pub async fn uid_search_criteria(
+        &mut self,
+        criteria: &SearchCriteria,
+        charset: Option<&str>,
+    ) -> Result<Vec<Uid>> {
+        let ids = self.search_command(criteria, charset, true).await?;
+        // UID SEARCH returns UIDs in the SEARCH response
+        Ok(ids
+            .into_iter()
+            .filter_map(|seq| Uid::new(seq.get()))
+            .collect())
+    }
+
+    /// Sends SEARCH or UID SEARCH, falling back to US-ASCII on BADCHARSET.
+    async fn search_command(
+        &mut self,
+        criteria: &SearchCriteria,
+        charset: Option<&str>,
+        uid: bool,
+    ) -> Result<Vec<crate::types::SeqNum>> {
+        self.check_gmail_extensions(criteria.uses_gmail_extensions())?;
+        let (mut tag, mut responses) = self.send_search(criteria, charset, uid).await?;
+
+        let bad_charset = Self::find_response_code(&responses, |code| {
+            matches!(code, ResponseCode::BadCharset(_)).then_some(())
+        });
+        if bad_charset.is_some() && charset.is_some_and(|c| !c.eq_ignore_ascii_case("US-ASCII")) {
+            tracing::debug!("server rejected SEARCH charset {charset:?}, retrying in US-ASCII");
+            (tag, responses) = self.send_search(criteria, Some("US-ASCII"), uid).await?;
+        }
+
+        let mut results = Vec::new();
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Search(ids))) =
+                ResponseParser::parse(response_bytes)
+            {
+                results.extend(ids);
+            }
+        }
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(results)
+    }
+
+    /// Fails with [`Error::Unsupported`] if Gmail extensions are `needed`
+    /// but the server lacks `X-GM-EXT-1`.
+    fn check_gmail_extensions(&self, needed: bool) -> Result<()> {
+        if needed && !self.supports_gmail_extensions() {
+            return Err(Error::Unsupported("X-GM-EXT-1".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Sends one SEARCH command and returns its tag and responses.
+    async fn send_search(
+        &mut self,
+        criteria: &SearchCriteria,
+        charset: Option<&str>,
+        uid: bool,
+    ) -> Result<(String, Vec<Vec<u8>>)> {
+        let tag = self.tag_gen.next();
+        let cmd = Command::Search {
+            charset: charset.map(str::to_string),
+            criteria: criteria.clone(),
+            uid,
+            return_opts: Vec::new(),
+        }
+        .serialize(&tag);
+
+        let responses = self.send_with_literals(&tag, &cmd).await?;
+        Ok((tag, responses))
+    }
+
+    /// Searches by UID and returns only the requested summary data (RFC 4731).
+    ///
+    /// Avoids transferring every matching UID when only a count or bounds
+    /// are needed; `ALL` comes back as a range-compressed [`UidSet`](crate::types::UidSet).
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise ESEARCH.
+    pub async fn search_return(
+        &mut self,
+        criteria: &SearchCriteria,
+        options: &[SearchReturnOption],
+    ) -> Result<EsearchResult> {
+        if !self.supports_esearch() {
+            return Err(Error::Unsupported("ESEARCH".to_string()));
+        }
+        self.check_gmail_extensions(criteria.uses_gmail_extensions())?;
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Search {
+            charset: (!criteria.is_ascii()).then(|| "UTF-8".to_string()),
+            criteria: criteria.clone(),
+            uid: true,
+            return_opts: options.to_vec(),
+        }
+        .serialize(&tag);
+
+        let responses = self.send_with_literals(&tag, &cmd).await?;
+        let mut result = EsearchResult {
+            uid: true,
+            ..EsearchResult::default()
+        };
+
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Esearch(esearch))) =
+                ResponseParser::parse(response_bytes)
+                && esearch.tag.as_deref().is_none_or(|t| t == tag)
+            {
+                result = esearch;
+            }
+        }
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(result)
+    }
+
+    /// Sorts messages matching `criteria` on the server (RFC 5256).
+    ///
+    /// Returns sequence numbers in sorted order. String comparisons use UTF-8.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise SORT.
+    pub async fn sort(
+        &mut self,
+        keys: &[SortKey],
+        criteria: &SearchCriteria,
+    ) -> Result<Vec<crate::types::SeqNum>> {
+        self.sort_command(keys, criteria, false).await
+    }
+
+    /// Sorts messages matching `criteria` on the server, returning UIDs.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise SORT.
+    pub async fn uid_sort(
+        &mut self,
+        keys: &[SortKey],
+        criteria: &SearchCriteria,
+    ) -> Result<Vec<crate::types::Uid>> {
+        let ids = self.sort_command(keys, criteria, true).await?;
+        // UID SORT returns UIDs in the SORT response
+        Ok(ids
+            .into_iter()
+            .filter_map(|seq| crate::types::Uid::new(seq.get()))
+            .collect())
+    }
+
+    /// Sends SORT or UID SORT and collects the ordered results.
+    async fn sort_command(
+        &mut self,
+        keys: &[SortKey],
+        criteria: &SearchCriteria,
+        uid: bool,
+    ) -> Result<Vec<crate::types::SeqNum>> {
+        if !self.supports_sort() {
+            return Err(Error::Unsupported("SORT".to_string()));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Sort {
+            keys: keys.to_vec(),
+            charset: "UTF-8".to_string(),
+            criteria: criteria.clone(),
+            uid,
+        }
+        .serialize(&tag);
+
+        let responses = self.send_with_literals(&tag, &cmd).await?;
+        let mut results = Vec::new();
+
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Sort(ids))) =
+                ResponseParser::parse(response_bytes)
+            {
+                results.extend(ids);
+            }
+        }
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(results)
+    }
+
+    /// Threads messages matching `criteria` on the server (RFC 5256).
+    ///
+    /// Sends `UID THREAD`, so node ids are UIDs. Returns one root node per
+    /// thread, in the order the server reports them.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// `THREAD=<algorithm>`.
+    pub async fn thread(
+        &mut self,
+        algorithm: ThreadAlgorithm,
+        criteria: &SearchCriteria,
+    ) -> Result<Vec<ThreadNode>> {
+        if !self.supports_thread(algorithm) {
+            return Err(Error::Unsupported(format!("THREAD={}", algorithm.as_str())));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::Thread {
+            algorithm,
+            charset: "UTF-8".to_string(),
+            criteria: criteria.clone(),
+            uid: true,
+        }
+        .serialize(&tag);
+
+        let responses = self.send_with_literals(&tag, &cmd).await?;
+        let mut threads = Vec::new();
+
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Thread(roots))) =
+                ResponseParser::parse(response_bytes)
+            {
+                threads.extend(roots);
+            }
+        }
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(threads)
+    }
+
     /// Fetches message data for the given sequence set.
     ///
-    /// Returns a vector of (sequence number, fetch items) pairs.
+    /// Returns a vector of (sequence number, fetch items) pairs. Gmail
+    /// attributes fail with [`Error::Unsupported`] unless the server
+    /// advertises `X-GM-EXT-1`.
     pub async fn fetch(
         &mut self,
         sequence: &SequenceSet,
         items: FetchItems,
     ) -> Result<Vec<(crate::types::SeqNum, Vec<FetchItem>)>> {
+        self.check_gmail_extensions(items.uses_gmail_extensions())?;
         let tag = self.tag_gen.next();
         let cmd = Command::Fetch {
             sequence: sequence.clone(),
             items,
             uid: false,
+            changed_since: None,
         }
         .serialize(&tag);
 
@@ -194,17 +504,243 @@ where
 
     /// Fetches message data using UIDs.
     ///
-    /// Returns a vector of (sequence number, fetch items) pairs.
+    /// Returns a vector of (sequence number, fetch items) pairs. Gmail
+    /// attributes fail with [`Error::Unsupported`] unless the server
+    /// advertises `X-GM-EXT-1`.
     pub async fn uid_fetch(
         &mut self,
         uid_set: &crate::types::UidSet,
         items: FetchItems,
     ) -> Result<Vec<(crate::types::SeqNum, Vec<FetchItem>)>> {
+        self.check_gmail_extensions(items.uses_gmail_extensions())?;
         let tag = self.tag_gen.next();
         let cmd = Command::Fetch {
             sequence: uid_set.as_sequence_set(),
             items,
             uid: true,
+            changed_since: None,
+        }
+        .serialize(&tag);
+
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        let mut results = Vec::new();
+
+        for response_bytes in &responses {
+            if let Ok(Response::Untagged(UntaggedResponse::Fetch { seq, items })) =
+                ResponseParser::parse(response_bytes)
+            {
+                results.push((seq, items));
+            }
+        }
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(results)
+    }
+
+    /// Fetches message data, yielding each message as its FETCH response
+    /// arrives instead of waiting for the whole batch.
+    ///
+    /// The stream ends after the tagged completion and yields a final error
+    /// if the command fails. Drain it before sending the next command:
+    /// dropping it early leaves the remaining responses unread.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use futures_util::StreamExt;
+    ///
+    /// let mut messages = std::pin::pin!(client.fetch_stream(&seq, FetchItems::Fast));
+    /// while let Some(message) = messages.next().await {
+    ///     let (seq, items) = message?;
+    ///     println!("{seq}: {items:?}");
+    /// }
+    /// ```
+    pub fn fetch_stream(
+        &mut self,
+        sequence: &SequenceSet,
+        items: FetchItems,
+    ) -> impl Stream<Item = Result<(crate::types::SeqNum, Vec<FetchItem>)>> + '_ {
+        self.streamed_fetch(sequence.clone(), items, false)
+    }
+
+    /// Like [`fetch_stream`](Self::fetch_stream), but addresses messages by UID.
+    pub fn uid_fetch_stream(
+        &mut self,
+        uid_set: &UidSet,
+        items: FetchItems,
+    ) -> impl Stream<Item = Result<(crate::types::SeqNum, Vec<FetchItem>)>> + '_ {
+        self.streamed_fetch(uid_set.as_sequence_set(), items, true)
+    }
+
+    fn streamed_fetch(
+        &mut self,
+        sequence: SequenceSet,
+        items: FetchItems,
+        uid: bool,
+    ) -> impl Stream<Item = Result<(crate::types::SeqNum, Vec<FetchItem>)>> + '_ {
+        let tag = self.tag_gen.next();
+        let command = self
+            .check_gmail_extensions(items.uses_gmail_extensions())
+            .map(|()| {
+                Command::Fetch {
+                    sequence,
+                    items,
+                    uid,
+                    changed_since: None,
+                }
+                .serialize(&tag)
+            });
+
+        stream::unfold(Some((self, Some(command), tag)), |state| async move {
+            let (client, command, tag) = state?;
+            match client.next_streamed_fetch(command, &tag).await {
+                Ok(Some(message)) => Some((Ok(message), Some((client, None, tag)))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Sends `command` if given, then reads up to the next FETCH response.
+    ///
+    /// Returns `None` once the tagged OK for `tag` arrives.
+    async fn next_streamed_fetch(
+        &mut self,
+        command: Option<Result<Vec<u8>>>,
+        tag: &str,
+    ) -> Result<Option<(crate::types::SeqNum, Vec<FetchItem>)>> {
+        if let Some(command) = command {
+            self.stream.write_command(&command?).await?;
+        }
+        loop {
+            let response = self.stream.read_response().await?;
+            match ResponseParser::parse(&response) {
+                Ok(Response::Untagged(UntaggedResponse::Fetch { seq, items })) => {
+                    return Ok(Some((seq, items)));
+                }
+                Ok(Response::Untagged(UntaggedResponse::Bye { text, .. })) => {
+                    return Err(Error::Bye(text));
+                }
+                Ok(Response::Tagged { tag: done, .. }) if done.as_str() == tag => {
+                    Self::check_tagged_ok(std::slice::from_ref(&response), tag)?;
+                    return Ok(None);
+                }
+                _ => self.record_mailbox_update(&response),
+            }
+        }
+    }
+
+    /// Fetches a body part decoded by the server (RFC 3516).
+    ///
+    /// `section` is the part number path, e.g. `&[2, 1]` for part 2.1. The
+    /// server strips the content transfer encoding, so the returned bytes are
+    /// the attachment as-is. Uses `BINARY.PEEK`, leaving `\Seen` untouched.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server doesn't advertise
+    /// BINARY; callers should then fetch `BODY[section]` and decode locally.
+    pub async fn fetch_binary(&mut self, uid: Uid, section: &[u32]) -> Result<Vec<u8>> {
+        if !self.supports_binary() {
+            return Err(Error::Unsupported("BINARY".to_string()));
+        }
+
+        let items = FetchItems::Items(vec![FetchAttribute::Binary {
+            section: section.to_vec(),
+            peek: true,
+        }]);
+        let responses = self.uid_fetch(&UidSet::single(uid), items).await?;
+
+        responses
+            .into_iter()
+            .flat_map(|(_, items)| items)
+            .find_map(|item| match item {
+                FetchItem::Binary { data, .. } => Some(data.unwrap_or_default()),
+                _ => None,
+            })
+            .ok_or_else(|| Error::Protocol(format!("no BINARY data returned for UID {uid}")))
+    }
+
+    /// Fetches `length` octets of a body part starting at `offset`.
+    ///
+    /// `section` is the part number path, e.g. `&[1]`; pass `&[]` for the
+    /// whole message. Uses `BODY.PEEK`, leaving `\Seen` untouched. Returns
+    /// the bytes together with the starting octet echoed by the server; fewer
+    /// than `length` bytes means the end of the part was reached.
+    pub async fn fetch_partial(
+        &mut self,
+        uid: Uid,
+        section: &[u32],
+        offset: u32,
+        length: u32,
+    ) -> Result<(Vec<u8>, u32)> {
+        let items = FetchItems::Items(vec![FetchAttribute::BodySectionPartial {
+            section: section.to_vec(),
+            offset,
+            length,
+        }]);
+        let responses = self.uid_fetch(&UidSet::single(uid), items).await?;
+
+        responses
+            .into_iter()
+            .flat_map(|(_, items)| items)
+            .find_map(|item| match item {
+                FetchItem::Body { origin, data, .. } => {
+                    Some((data.unwrap_or_default(), origin.unwrap_or(offset)))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| Error::Protocol(format!("no BODY data returned for UID {uid}")))
+    }
+
+    /// Fetches only the named header fields of a message.
+    ///
+    /// Sends `UID FETCH <uid> (BODY.PEEK[HEADER.FIELDS (...)])`, so `\Seen`
+    /// is left untouched. Returns the raw header block as sent by the server,
+    /// which may be empty if none of the fields are present.
+    pub async fn fetch_headers(&mut self, uid: Uid, fields: &[&str]) -> Result<Vec<u8>> {
+        let items = FetchItems::Items(vec![FetchAttribute::BodyHeaderFields(
+            fields.iter().map(ToString::to_string).collect(),
+        )]);
+        let responses = self.uid_fetch(&UidSet::single(uid), items).await?;
+
+        responses
+            .into_iter()
+            .flat_map(|(_, items)| items)
+            .find_map(|item| match item {
+                FetchItem::Body {
+                    section: Some(section),
+                    data,
+                    ..
+                } if section.to_ascii_uppercase().starts_with("HEADER.FIELDS") => {
+                    Some(data.unwrap_or_default())
+                }
+                _ => None,
+            })
+            .ok_or_else(|| Error::Protocol(format!("no header fields returned for UID {uid}")))
+    }
+
+    /// Fetches messages whose mod-sequence is greater than `modseq` (RFC 7162).
+    ///
+    /// Emits `UID FETCH <seq> <items> (CHANGEDSINCE <modseq>)`, so `seq` holds
+    /// UIDs. The server includes a `MODSEQ` item in every returned message.
+    /// Requires the CONDSTORE capability and a mailbox selected with
+    /// [`select_condstore`](Client::select_condstore).
+    ///
+    /// Returns a vector of (sequence number, fetch items) pairs.
+    pub async fn fetch_changed_since(
+        &mut self,
+        seq: &SequenceSet,
+        items: FetchItems,
+        modseq: u64,
+    ) -> Result<Vec<(crate::types::SeqNum, Vec<FetchItem>)>> {
+        self.check_gmail_extensions(items.uses_gmail_extensions())?;
+        let tag = self.tag_gen.next();
+        let cmd = Command::Fetch {
+            sequence: seq.clone(),
+            items,
+            uid: true,
+            changed_since: Some(modseq),
         }
         .serialize(&tag);
 
@@ -225,14 +761,76 @@ where
         Ok(results)
     }
 
+    /// Fetches messages changed since `modseq` and the UIDs expunged since
+    /// then (RFC 7162 section 3.2.6).
+    ///
+    /// Emits `UID FETCH <uids> <items> (CHANGEDSINCE <modseq> VANISHED)`.
+    /// The server answers with FETCH responses for changed messages and
+    /// `VANISHED (EARLIER)` for expunged UIDs within `uid_set`. Requires
+    /// QRESYNC to have been turned on with ENABLE before selecting.
+    ///
+    /// Returns the fetch responses and the vanished UIDs.
+    pub async fn fetch_vanished_since(
+        &mut self,
+        uid_set: &UidSet,
+        items: FetchItems,
+        modseq: u64,
+    ) -> Result<(Vec<(crate::types::SeqNum, Vec<FetchItem>)>, Vec<Uid>)> {
+        if !self.is_enabled(&Capability::QResync) {
+            return Err(Error::Unsupported(
+                "VANISHED needs QRESYNC to be enabled".to_string(),
+            ));
+        }
+        self.check_gmail_extensions(items.uses_gmail_extensions())?;
+
+        let tag = self.tag_gen.next();
+        let mut cmd = format!("{tag} UID FETCH {uid_set} ").into_bytes();
+        write_fetch_items(&mut cmd, &items);
+        cmd.extend_from_slice(format!(" (CHANGEDSINCE {modseq} VANISHED)\r\n").as_bytes());
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        let mut fetched = Vec::new();
+        let mut vanished = Vec::new();
+
+        for response_bytes in &responses {
+            if let Some(uids) = Self::parse_vanished(response_bytes) {
+                vanished.extend(uids.iter());
+            } else if let Ok(Response::Untagged(UntaggedResponse::Fetch { seq, items })) =
+                ResponseParser::parse(response_bytes)
+            {
+                fetched.push((seq, items));
+            }
+        }
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok((fetched, vanished))
+    }
+
+    /// Reads the UIDs of a `* VANISHED [(EARLIER)] <uid-set>` response.
+    fn parse_vanished(bytes: &[u8]) -> Option<UidSet> {
+        let line = std::str::from_utf8(bytes).ok()?.trim_end();
+        let rest = line
+            .get(..11)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("* VANISHED "))
+            .map(|_| &line[11..])?;
+        let rest = rest
+            .get(..10)
+            .filter(|earlier| earlier.eq_ignore_ascii_case("(EARLIER) "))
+            .map_or(rest, |_| &rest[10..]);
+        UidSet::parse(rest.trim())
+    }
+
     /// Modifies message flags.
     ///
-    /// Returns the updated flags for each affected message.
+    /// Returns the updated flags for each affected message. Fails with
+    /// [`Error::InvalidState`] if the mailbox was opened read-only.
     pub async fn store(
         &mut self,
         sequence: &SequenceSet,
         action: StoreAction,
     ) -> Result<Vec<(crate::types::SeqNum, Vec<FetchItem>)>> {
+        self.ensure_writable("STORE")?;
         let tag = self.tag_gen.next();
         let cmd = Command::Store {
             sequence: sequence.clone(),
@@ -260,11 +858,14 @@ where
     }
 
     /// Modifies message flags silently (no FETCH response).
+    ///
+    /// Fails with [`Error::InvalidState`] if the mailbox was opened read-only.
     pub async fn store_silent(
         &mut self,
         sequence: &SequenceSet,
         action: StoreAction,
     ) -> Result<()> {
+        self.ensure_writable("STORE")?;
         let tag = self.tag_gen.next();
         let cmd = Command::Store {
             sequence: sequence.clone(),
@@ -282,11 +883,14 @@ where
     }
 
     /// Modifies message flags using UIDs.
+    ///
+    /// Fails with [`Error::InvalidState`] if the mailbox was opened read-only.
     pub async fn uid_store(
         &mut self,
         uid_set: &crate::types::UidSet,
         action: StoreAction,
     ) -> Result<Vec<(crate::types::SeqNum, Vec<FetchItem>)>> {
+        self.ensure_writable("UID STORE")?;
         let tag = self.tag_gen.next();
         let cmd = Command::Store {
             sequence: uid_set.as_sequence_set(),
@@ -314,81 +918,114 @@ where
     }
 
     /// Copies messages to another mailbox.
-    pub async fn copy(&mut self, sequence: &SequenceSet, mailbox: &str) -> Result<()> {
+    ///
+    /// Returns the source and destination UIDs if the server reports
+    /// `COPYUID` (UIDPLUS).
+    pub async fn copy(&mut self, sequence: &SequenceSet, mailbox: &str) -> Result<Option<CopyUid>> {
         let tag = self.tag_gen.next();
         let cmd = Command::Copy {
             sequence: sequence.clone(),
             mailbox: Mailbox::new(mailbox),
             uid: false,
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
         let responses = self.read_until_tagged(&tag).await?;
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+        Ok(Self::find_response_code(&responses, ResponseCode::copy_uid))
     }
 
     /// Copies messages to another mailbox using UIDs.
-    pub async fn uid_copy(&mut self, uid_set: &crate::types::UidSet, mailbox: &str) -> Result<()> {
+    ///
+    /// Returns the source and destination UIDs if the server reports
+    /// `COPYUID` (UIDPLUS).
+    pub async fn uid_copy(
+        &mut self,
+        uid_set: &crate::types::UidSet,
+        mailbox: &str,
+    ) -> Result<Option<CopyUid>> {
         let tag = self.tag_gen.next();
         let cmd = Command::Copy {
             sequence: uid_set.as_sequence_set(),
             mailbox: Mailbox::new(mailbox),
             uid: true,
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
         let responses = self.read_until_tagged(&tag).await?;
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+        Ok(Self::find_response_code(&responses, ResponseCode::copy_uid))
     }
 
     /// Moves messages to another mailbox.
     ///
-    /// Requires the MOVE capability (RFC 6851).
-    pub async fn r#move(&mut self, sequence: &SequenceSet, mailbox: &str) -> Result<()> {
+    /// Requires the MOVE capability (RFC 6851). Returns the source and
+    /// destination UIDs if the server reports `COPYUID` (UIDPLUS). Moving
+    /// removes the messages here, so a read-only mailbox fails with
+    /// [`Error::InvalidState`].
+    pub async fn r#move(
+        &mut self,
+        sequence: &SequenceSet,
+        mailbox: &str,
+    ) -> Result<Option<CopyUid>> {
+        self.ensure_writable("MOVE")?;
         let tag = self.tag_gen.next();
         let cmd = Command::Move {
             sequence: sequence.clone(),
             mailbox: Mailbox::new(mailbox),
             uid: false,
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
         let responses = self.read_until_tagged(&tag).await?;
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+        Ok(Self::find_response_code(&responses, ResponseCode::copy_uid))
     }
 
     /// Moves messages to another mailbox using UIDs.
     ///
-    /// Requires the MOVE capability (RFC 6851).
-    pub async fn uid_move(&mut self, uid_set: &crate::types::UidSet, mailbox: &str) -> Result<()> {
+    /// Requires the MOVE capability (RFC 6851). Returns the source and
+    /// destination UIDs if the server reports `COPYUID` (UIDPLUS). Fails
+    /// with [`Error::InvalidState`] if the mailbox was opened read-only.
+    pub async fn uid_move(
+        &mut self,
+        uid_set: &crate::types::UidSet,
+        mailbox: &str,
+    ) -> Result<Option<CopyUid>> {
+        self.ensure_writable("UID MOVE")?;
         let tag = self.tag_gen.next();
         let cmd = Command::Move {
             sequence: uid_set.as_sequence_set(),
             mailbox: Mailbox::new(mailbox),
             uid: true,
         }
-        .serialize(&tag);
+        .serialize_with(&tag, self.utf8_enabled());
 
         self.stream.write_command(&cmd).await?;
 
         let responses = self.read_until_tagged(&tag).await?;
         Self::check_tagged_ok(&responses, &tag)?;
-        Ok(())
+        Ok(Self::find_response_code(&responses, ResponseCode::copy_uid))
     }
 
     /// Permanently removes messages marked as \Deleted.
     ///
-    /// Returns the sequence numbers of expunged messages.
+    /// Returns the sequence number of each `* n EXPUNGE` reply, in the
+    /// order the server sent them. Every expunge renumbers the messages
+    /// after it, so each number is relative to the mailbox as left by the
+    /// previous ones: two `* 3 EXPUNGE` replies remove what were messages 3
+    /// and 4. Apply them to a local list in order, or, when removing known
+    /// sequence numbers yourself, work from the highest down.
+    ///
+    /// Fails with [`Error::InvalidState`] if the mailbox was opened read-only.
     pub async fn expunge(&mut self) -> Result<Vec<crate::types::SeqNum>> {
+        self.ensure_writable("EXPUNGE")?;
         let tag = self.tag_gen.next();
         let cmd = Command::Expunge.serialize(&tag);
         self.stream.write_command(&cmd).await?;
@@ -408,6 +1045,45 @@ where
         Ok(expunged)
     }
 
+    /// Permanently removes only the given messages, if they are marked
+    /// \Deleted (UID EXPUNGE, RFC 4315).
+    ///
+    /// Unlike [`Self::expunge`], messages another client marked \Deleted
+    /// are left alone. Returns the sequence numbers of expunged messages.
+    ///
+    /// Fails with [`Error::Unsupported`] if the server lacks UIDPLUS; plain
+    /// EXPUNGE is then the only, less precise, option. Like it, fails with
+    /// [`Error::InvalidState`] if the mailbox was opened read-only.
+    pub async fn uid_expunge(&mut self, uid_set: &UidSet) -> Result<Vec<crate::types::SeqNum>> {
+        self.ensure_writable("UID EXPUNGE")?;
+        if !self.supports_uidplus() {
+            return Err(Error::Unsupported(
+                "UID EXPUNGE (UIDPLUS); use EXPUNGE instead".to_string(),
+            ));
+        }
+
+        let tag = self.tag_gen.next();
+        let cmd = Command::UidExpunge {
+            uids: uid_set.as_sequence_set(),
+        }
+        .serialize(&tag);
+        self.stream.write_command(&cmd).await?;
+
+        let responses = self.read_until_tagged(&tag).await?;
+        let expunged = responses
+            .iter()
+            .filter_map(
+                |response_bytes| match ResponseParser::parse(response_bytes) {
+                    Ok(Response::Untagged(UntaggedResponse::Expunge(seq))) => Some(seq),
+                    _ => None,
+                },
+            )
+            .collect();
+
+        Self::check_tagged_ok(&responses, &tag)?;
+        Ok(expunged)
+    }
+
     /// Gracefully disconnects from the server.
     pub async fn logout(mut self) -> Result<()> {
         let tag = self.tag_gen.next();
@@ -0,0 +1,316 @@
+//! DEFLATE compression layer for COMPRESS=DEFLATE (RFC 4978).
+//!
+//! Once the server accepts `COMPRESS DEFLATE`, both directions of the
+//! connection carry a raw DEFLATE stream (RFC 1951, no zlib header). Each
+//! flush ends with a sync flush so the peer can decode every command as soon
+//! as it arrives.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Size of each read from the inner stream and of output buffer growth.
+const CHUNK_SIZE: usize = 8192;
+
+/// A stream that deflates writes and inflates reads.
+///
+/// Implements `AsyncRead + AsyncWrite` so it can sit under a
+/// [`FramedStream`](super::FramedStream) like any other transport.
+pub struct DeflateStream<S> {
+    inner: S,
+    compress: Compress,
+    decompress: Decompress,
+    /// Compressed bytes read from `inner` and not yet inflated.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    read_eof: bool,
+    /// Compressed bytes waiting to be written to `inner`.
+    write_buf: Vec<u8>,
+    write_pos: usize,
+    /// Whether data was written since the last sync flush.
+    needs_sync: bool,
+}
+
+impl<S> DeflateStream<S> {
+    /// Wraps a stream whose traffic is DEFLATE-compressed from now on.
+    pub fn new(inner: S) -> Self {
+        Self::with_buffered(inner, Vec::new())
+    }
+
+    /// Wraps a stream, treating `buffered` as compressed data already read.
+    ///
+    /// Used when switching over a framed stream whose read buffer may hold
+    /// bytes the server sent right after its tagged OK.
+    pub(crate) fn with_buffered(inner: S, buffered: Vec<u8>) -> Self {
+        Self {
+            inner,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+            read_buf: buffered,
+            read_pos: 0,
+            read_eof: false,
+            write_buf: Vec::with_capacity(CHUNK_SIZE),
+            write_pos: 0,
+            needs_sync: false,
+        }
+    }
+
+    /// Gets a reference to the underlying stream.
+    pub const fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying stream.
+    pub const fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S> DeflateStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Reads more compressed input from the inner stream.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.read_buf.drain(..self.read_pos);
+        self.read_pos = 0;
+
+        let start = self.read_buf.len();
+        self.read_buf.resize(start + CHUNK_SIZE, 0);
+        let mut buf = ReadBuf::new(&mut self.read_buf[start..]);
+        let result = Pin::new(&mut self.inner).poll_read(cx, &mut buf);
+        let n = buf.filled().len();
+        self.read_buf.truncate(start + n);
+
+        if matches!(result, Poll::Ready(Ok(()))) && n == 0 {
+            self.read_eof = true;
+        }
+        result
+    }
+
+    /// Writes pending compressed output to the inner stream.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_buf.len() {
+            let pending = &self.write_buf[self.write_pos..];
+            match Pin::new(&mut self.inner).poll_write(cx, pending) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                Poll::Ready(Ok(n)) => self.write_pos += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.write_buf.clear();
+        self.write_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Compresses `input` into the write buffer, growing it as needed.
+    fn deflate(&mut self, input: &[u8], flush: FlushCompress) -> io::Result<()> {
+        let start = self.compress.total_in();
+        loop {
+            let consumed = self.consumed_since(start);
+            self.write_buf.reserve(CHUNK_SIZE);
+            self.compress
+                .compress_vec(&input[consumed..], &mut self.write_buf, flush)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            // Spare output room after consuming everything means the
+            // compressor had nothing more to emit.
+            if self.consumed_since(start) == input.len()
+                && self.write_buf.len() < self.write_buf.capacity()
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns how many input bytes the compressor consumed since `start`.
+    fn consumed_since(&self, start: u64) -> usize {
+        usize::try_from(self.compress.total_in() - start).unwrap_or(usize::MAX)
+    }
+}
+
+impl<S> AsyncRead for DeflateStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            let before_in = this.decompress.total_in();
+            let before_out = this.decompress.total_out();
+            let status = this
+                .decompress
+                .decompress(
+                    &this.read_buf[this.read_pos..],
+                    buf.initialize_unfilled(),
+                    FlushDecompress::None,
+                )
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            let consumed = usize::try_from(this.decompress.total_in() - before_in).unwrap_or(0);
+            let produced = usize::try_from(this.decompress.total_out() - before_out).unwrap_or(0);
+            this.read_pos += consumed;
+            buf.advance(produced);
+
+            if produced > 0 || status == Status::StreamEnd || this.read_eof {
+                return Poll::Ready(Ok(()));
+            }
+            if consumed > 0 && this.read_pos < this.read_buf.len() {
+                continue;
+            }
+
+            match this.poll_fill(cx) {
+                Poll::Ready(Ok(())) => {}
+                other => return other,
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for DeflateStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        // Apply backpressure instead of buffering without bound.
+        if this.write_buf.len() >= CHUNK_SIZE {
+            match this.poll_drain(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        this.deflate(buf, FlushCompress::None)?;
+        this.needs_sync = true;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.needs_sync {
+            this.deflate(&[], FlushCompress::Sync)?;
+            this.needs_sync = false;
+        }
+
+        match this.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.as_mut().poll_flush(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut self.get_mut().inner).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trip_lines() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = DeflateStream::new(client);
+        let mut server = BufReader::new(DeflateStream::new(server));
+
+        client.write_all(b"A0001 NOOP\r\n").await.unwrap();
+        client.flush().await.unwrap();
+        let mut line = String::new();
+        server.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "A0001 NOOP\r\n");
+
+        client.write_all(b"A0002 LOGOUT\r\n").await.unwrap();
+        client.flush().await.unwrap();
+        line.clear();
+        server.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "A0002 LOGOUT\r\n");
+    }
+
+    #[tokio::test]
+    async fn round_trip_large_payload() {
+        let payload: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = DeflateStream::new(client);
+        let mut server = DeflateStream::new(server);
+
+        let expected = payload.clone();
+        let writer = tokio::spawn(async move {
+            client.write_all(&payload).await.unwrap();
+            client.shutdown().await.unwrap();
+        });
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        writer.await.unwrap();
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn reads_raw_deflate_with_buffered_prefix() {
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"* OK compressed\r\n").unwrap();
+        encoder.flush().unwrap();
+        let compressed = encoder.get_ref().clone();
+
+        // Split the data between the framed buffer and the socket.
+        let (head, tail) = compressed.split_at(3);
+        let (mut remote, local) = tokio::io::duplex(64);
+        remote.write_all(tail).await.unwrap();
+        drop(remote);
+
+        let mut stream = DeflateStream::with_buffered(local, head.to_vec());
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"* OK compressed\r\n");
+    }
+
+    #[test]
+    fn rejects_corrupt_input() {
+        let (_, local) = tokio::io::duplex(64);
+        let mut stream = DeflateStream::with_buffered(local, vec![0xff; 16]);
+        let mut buf = [0u8; 32];
+        let mut read_buf = ReadBuf::new(&mut buf);
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+        let result = Pin::new(&mut stream).poll_read(&mut cx, &mut read_buf);
+        assert!(matches!(result, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+    }
+}
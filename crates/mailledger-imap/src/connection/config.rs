@@ -1,7 +1,12 @@
 //! Connection configuration types.
 
+use std::sync::Arc;
 use std::time::Duration;
 
+use rustls::client::ClientSessionStore;
+
+use super::framed::{FrameLimits, Timeouts};
+
 /// Connection security mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Security {
@@ -25,6 +30,97 @@ impl Security {
     }
 }
 
+/// How the server's TLS certificate is checked.
+///
+/// By default the certificate must chain to a root from the Mozilla root
+/// program and match the host name.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// Extra trusted root certificates (DER), e.g. a corporate CA.
+    pub root_certs: Vec<Vec<u8>>,
+    /// SHA-256 of the only accepted server certificate (DER).
+    ///
+    /// When set, the server must present exactly this certificate; roots
+    /// and host name are not consulted, so self-signed certificates work.
+    pub pinned_cert_sha256: Option<[u8; 32]>,
+    /// Accepts any server certificate.
+    ///
+    /// **Dangerous**: anyone on the network path can then impersonate the
+    /// server and read the password. Only for testing against local servers.
+    pub accept_invalid_certs: bool,
+    /// Where sessions are kept so reconnects can resume them, see
+    /// [`ConfigBuilder::with_session_cache`].
+    ///
+    /// When `None`, connections with equal options share an in-memory
+    /// cache of 256 sessions.
+    pub session_cache: Option<Arc<dyn ClientSessionStore>>,
+}
+
+impl PartialEq for TlsOptions {
+    fn eq(&self, other: &Self) -> bool {
+        let same_cache = match (&self.session_cache, &other.session_cache) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.root_certs == other.root_certs
+            && self.pinned_cert_sha256 == other.pinned_cert_sha256
+            && self.accept_invalid_certs == other.accept_invalid_certs
+            && same_cache
+    }
+}
+
+impl Eq for TlsOptions {}
+
+/// A SOCKS5 proxy to connect through.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// Proxy hostname.
+    pub host: String,
+    /// Proxy port.
+    pub port: u16,
+    /// Username and password, if the proxy requires them.
+    pub credentials: Option<(String, String)>,
+}
+
+impl ProxyConfig {
+    /// Creates a configuration for a SOCKS5 proxy without authentication.
+    #[must_use]
+    pub fn socks5(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            credentials: None,
+        }
+    }
+
+    /// Authenticates to the proxy with a username and password.
+    #[must_use]
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+}
+
+impl std::fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field(
+                "credentials",
+                &self
+                    .credentials
+                    .as_ref()
+                    .map(|(username, _)| (username, "<redacted>")),
+            )
+            .finish()
+    }
+}
+
 /// IMAP connection configuration.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -38,6 +134,21 @@ pub struct Config {
     pub connect_timeout: Duration,
     /// Read/write timeout.
     pub io_timeout: Duration,
+    /// Longest a command may wait for its tagged response.
+    pub command_timeout: Duration,
+    /// Certificate verification settings.
+    pub tls: TlsOptions,
+    /// Name to send as SNI and to verify the certificate against, if it
+    /// differs from `host` (e.g. when `host` is a tunnel endpoint).
+    pub sni_hostname: Option<String>,
+    /// Proxy to connect through.
+    pub proxy: Option<ProxyConfig>,
+    /// Longest response line accepted from the server.
+    pub max_line_length: usize,
+    /// Largest literal accepted from the server.
+    pub max_literal_length: usize,
+    /// Initial read buffer capacity.
+    pub read_buffer_size: usize,
 }
 
 impl Config {
@@ -50,6 +161,38 @@ impl Config {
             security: Security::Implicit,
             connect_timeout: Duration::from_secs(30),
             io_timeout: Duration::from_secs(60),
+            command_timeout: Duration::from_secs(300),
+            tls: TlsOptions::default(),
+            sni_hostname: None,
+            proxy: None,
+            max_line_length: FrameLimits::default().max_line_length,
+            max_literal_length: FrameLimits::default().max_literal_length,
+            read_buffer_size: FrameLimits::default().read_buffer_size,
+        }
+    }
+
+    /// Returns the name the server's certificate must be valid for.
+    #[must_use]
+    pub fn tls_server_name(&self) -> &str {
+        self.sni_hostname.as_deref().unwrap_or(&self.host)
+    }
+
+    /// Returns the I/O and command timeouts for the connection.
+    #[must_use]
+    pub const fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            io: Some(self.io_timeout),
+            command: Some(self.command_timeout),
+        }
+    }
+
+    /// Returns the size limits for the connection.
+    #[must_use]
+    pub const fn limits(&self) -> FrameLimits {
+        FrameLimits {
+            max_line_length: self.max_line_length,
+            max_literal_length: self.max_literal_length,
+            read_buffer_size: self.read_buffer_size,
         }
     }
 
@@ -68,6 +211,11 @@ pub struct ConfigBuilder {
     security: Security,
     connect_timeout: Duration,
     io_timeout: Duration,
+    command_timeout: Duration,
+    tls: TlsOptions,
+    sni_hostname: Option<String>,
+    proxy: Option<ProxyConfig>,
+    limits: FrameLimits,
 }
 
 impl ConfigBuilder {
@@ -80,6 +228,11 @@ impl ConfigBuilder {
             security: Security::Implicit,
             connect_timeout: Duration::from_secs(30),
             io_timeout: Duration::from_secs(60),
+            command_timeout: Duration::from_secs(300),
+            tls: TlsOptions::default(),
+            sni_hostname: None,
+            proxy: None,
+            limits: FrameLimits::default(),
         }
     }
 
@@ -111,6 +264,102 @@ impl ConfigBuilder {
         self
     }
 
+    /// Sets how long a command may wait for its tagged response.
+    #[must_use]
+    pub const fn command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    /// Sets the longest response line accepted from the server.
+    #[must_use]
+    pub const fn max_line_length(mut self, bytes: usize) -> Self {
+        self.limits.max_line_length = bytes;
+        self
+    }
+
+    /// Sets the largest literal accepted from the server, e.g. to cap the
+    /// size of messages that can be fetched.
+    #[must_use]
+    pub const fn max_literal_length(mut self, bytes: usize) -> Self {
+        self.limits.max_literal_length = bytes;
+        self
+    }
+
+    /// Sets the initial read buffer capacity.
+    #[must_use]
+    pub const fn read_buffer_size(mut self, bytes: usize) -> Self {
+        self.limits.read_buffer_size = bytes;
+        self
+    }
+
+    /// Trusts an additional root certificate (DER), e.g. a corporate CA.
+    #[must_use]
+    pub fn with_root_cert(mut self, der: Vec<u8>) -> Self {
+        self.tls.root_certs.push(der);
+        self
+    }
+
+    /// Accepts only the server certificate with this SHA-256 digest of its
+    /// DER encoding, regardless of who issued it.
+    #[must_use]
+    pub const fn with_pinned_cert_sha256(mut self, sha256: [u8; 32]) -> Self {
+        self.tls.pinned_cert_sha256 = Some(sha256);
+        self
+    }
+
+    /// Uses `hostname` for SNI and certificate verification instead of
+    /// the host connected to, e.g. when connecting through an SSH tunnel.
+    #[must_use]
+    pub fn with_sni_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.sni_hostname = Some(hostname.into());
+        self
+    }
+
+    /// Connects through a SOCKS5 proxy.
+    ///
+    /// The proxy resolves the server's host name, so it is never looked up
+    /// locally.
+    #[must_use]
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Keeps TLS sessions in `cache`, e.g. one that persists them, so
+    /// later connections to the same server name can resume them instead
+    /// of a full handshake.
+    ///
+    /// Without this, connections with equal options already share an
+    /// in-memory cache.
+    ///
+    /// A resumed session doesn't present the certificate again; it is
+    /// trusted because the session it resumes was verified. Sessions are
+    /// only resumed by connections verifying the same way, so a session
+    /// from a [`danger_accept_invalid_certs`](Self::danger_accept_invalid_certs)
+    /// connection can't be resumed by a strict one. Resumption lets the
+    /// server link the connections to one client, and a store that persists
+    /// sessions holds secrets that must be protected like the password.
+    /// Early data (0-RTT) is never sent, so requests can't be replayed.
+    #[must_use]
+    pub fn with_session_cache(mut self, cache: Arc<dyn ClientSessionStore>) -> Self {
+        self.tls.session_cache = Some(cache);
+        self
+    }
+
+    /// Disables server certificate verification.
+    ///
+    /// **Dangerous**: the connection is then encrypted but not
+    /// authenticated, so an attacker on the network path can impersonate
+    /// the server and capture credentials. Only use this for testing
+    /// against local servers; prefer [`Self::with_root_cert`] or
+    /// [`Self::with_pinned_cert_sha256`] for self-signed certificates.
+    #[must_use]
+    pub const fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.tls.accept_invalid_certs = accept;
+        self
+    }
+
     /// Builds the configuration.
     #[must_use]
     pub fn build(self) -> Config {
@@ -120,6 +369,13 @@ impl ConfigBuilder {
             security: self.security,
             connect_timeout: self.connect_timeout,
             io_timeout: self.io_timeout,
+            command_timeout: self.command_timeout,
+            tls: self.tls,
+            sni_hostname: self.sni_hostname,
+            proxy: self.proxy,
+            max_line_length: self.limits.max_line_length,
+            max_literal_length: self.limits.max_literal_length,
+            read_buffer_size: self.limits.read_buffer_size,
         }
     }
 }
@@ -158,12 +414,42 @@ mod tests {
             .port(993)
             .security(Security::Implicit)
             .connect_timeout(Duration::from_secs(10))
+            .command_timeout(Duration::from_secs(20))
             .build();
 
         assert_eq!(config.host, "imap.example.com");
         assert_eq!(config.port, 993);
         assert_eq!(config.security, Security::Implicit);
         assert_eq!(config.connect_timeout, Duration::from_secs(10));
+        assert_eq!(
+            config.timeouts(),
+            Timeouts {
+                io: Some(Duration::from_secs(60)),
+                command: Some(Duration::from_secs(20)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_builder_limits() {
+        assert_eq!(
+            Config::new("imap.example.com").limits(),
+            FrameLimits::default()
+        );
+
+        let config = Config::builder("imap.example.com")
+            .max_line_length(4096)
+            .max_literal_length(25 * 1024 * 1024)
+            .read_buffer_size(64 * 1024)
+            .build();
+        assert_eq!(
+            config.limits(),
+            FrameLimits {
+                max_line_length: 4096,
+                max_literal_length: 25 * 1024 * 1024,
+                read_buffer_size: 64 * 1024,
+            }
+        );
     }
 
     #[test]
@@ -174,4 +460,52 @@ mod tests {
 
         assert_eq!(config.port, 143);
     }
+
+    #[test]
+    fn test_config_builder_tls_options() {
+        let config = Config::builder("imap.example.com")
+            .with_root_cert(vec![1, 2, 3])
+            .with_pinned_cert_sha256([7; 32])
+            .build();
+
+        assert_eq!(config.tls.root_certs, vec![vec![1, 2, 3]]);
+        assert_eq!(config.tls.pinned_cert_sha256, Some([7; 32]));
+        assert!(!config.tls.accept_invalid_certs);
+        assert!(config.tls.session_cache.is_none());
+    }
+
+    #[test]
+    fn test_config_builder_session_cache() {
+        use rustls::client::ClientSessionMemoryCache;
+
+        let cache: Arc<dyn ClientSessionStore> = Arc::new(ClientSessionMemoryCache::new(8));
+        let config = Config::builder("imap.example.com")
+            .with_session_cache(Arc::clone(&cache))
+            .build();
+
+        assert!(Arc::ptr_eq(&config.tls.session_cache.unwrap(), &cache));
+    }
+
+    #[test]
+    fn test_config_builder_sni_and_proxy() {
+        let config = Config::builder("127.0.0.1").port(1993).build();
+        assert_eq!(config.tls_server_name(), "127.0.0.1");
+        assert_eq!(config.proxy, None);
+
+        let proxy = ProxyConfig::socks5("proxy.example.com", 1080).with_credentials("u", "p");
+        let config = Config::builder("127.0.0.1")
+            .with_sni_hostname("imap.example.com")
+            .with_proxy(proxy.clone())
+            .build();
+        assert_eq!(config.tls_server_name(), "imap.example.com");
+        assert_eq!(config.proxy, Some(proxy));
+    }
+
+    #[test]
+    fn test_proxy_debug_redacts_password() {
+        let proxy = ProxyConfig::socks5("proxy.example.com", 1080).with_credentials("u", "hunter2");
+        let debug = format!("{proxy:?}");
+        assert!(debug.contains("\"u\""));
+        assert!(!debug.contains("hunter2"));
+    }
 }
@@ -7,27 +7,69 @@
 #![allow(clippy::missing_errors_doc)]
 
 use std::io;
+use std::time::Duration;
 
 use bytes::BytesMut;
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 
-use crate::Result;
+use crate::{Error, Result};
 
 /// Default buffer size for reading.
 const DEFAULT_BUFFER_SIZE: usize = 8192;
 
-/// Maximum line length to prevent memory exhaustion.
+/// Default maximum line length.
 const MAX_LINE_LENGTH: usize = 1024 * 1024; // 1 MB
 
-/// Maximum literal size to prevent memory exhaustion.
+/// Default maximum literal size.
 const MAX_LITERAL_SIZE: usize = 100 * 1024 * 1024; // 100 MB
 
+/// Bounds on what a server may send, so a broken or hostile one can't
+/// exhaust memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLimits {
+    /// Longest line, without its literals, that is read.
+    pub max_line_length: usize,
+    /// Largest literal that is read; larger ones are refused before any
+    /// memory is set aside for them.
+    pub max_literal_length: usize,
+    /// Read buffer capacity to start with. Larger buffers mean fewer reads
+    /// when fetching big messages.
+    pub read_buffer_size: usize,
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self {
+            max_line_length: MAX_LINE_LENGTH,
+            max_literal_length: MAX_LITERAL_SIZE,
+            read_buffer_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+}
+
+/// Limits on how long I/O may take. `None` waits forever.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Longest a single read or write may make no progress.
+    pub io: Option<Duration>,
+    /// Longest a command may wait for its tagged response.
+    pub command: Option<Duration>,
+}
+
 /// Framed connection for IMAP protocol.
 ///
 /// Handles line-based reading with literal support and buffered writing.
+/// Reads and writes that stall longer than the configured [`Timeouts`]
+/// fail with [`Error::Timeout`]; the connection should then be dropped,
+/// since the server may still answer the abandoned command.
+///
+/// Lines and literals beyond the [`FrameLimits`] fail with
+/// [`Error::Protocol`].
 pub struct FramedStream<S> {
     reader: BufReader<S>,
     write_buffer: BytesMut,
+    timeouts: Timeouts,
+    limits: FrameLimits,
 }
 
 impl<S> FramedStream<S>
@@ -39,7 +81,48 @@ where
         Self {
             reader: BufReader::with_capacity(DEFAULT_BUFFER_SIZE, stream),
             write_buffer: BytesMut::with_capacity(DEFAULT_BUFFER_SIZE),
+            timeouts: Timeouts::default(),
+            limits: FrameLimits::default(),
+        }
+    }
+
+    /// Sets the size limits and read buffer capacity.
+    ///
+    /// The read buffer is only resized if nothing has been read into it yet.
+    #[must_use]
+    pub fn with_limits(mut self, limits: FrameLimits) -> Self {
+        if self.reader.buffer().is_empty()
+            && self.limits.read_buffer_size != limits.read_buffer_size
+        {
+            self.reader =
+                BufReader::with_capacity(limits.read_buffer_size, self.reader.into_inner());
         }
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the size limits.
+    #[must_use]
+    pub const fn limits(&self) -> FrameLimits {
+        self.limits
+    }
+
+    /// Sets the I/O and command timeouts.
+    #[must_use]
+    pub const fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Returns the I/O and command timeouts.
+    #[must_use]
+    pub const fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    /// Changes the I/O and command timeouts.
+    pub const fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
     }
 
     /// Reads a complete IMAP response line, handling literals.
@@ -47,26 +130,49 @@ where
     /// IMAP responses can contain literals in the format `{n}\r\n<n bytes>`.
     /// This method reads the entire response including any embedded literals.
     pub async fn read_response(&mut self) -> Result<Vec<u8>> {
+        self.read_response_after(self.timeouts.io).await
+    }
+
+    /// Waits as long as it takes for the next response, then reads it.
+    ///
+    /// Once the response has started, the I/O timeout applies as usual.
+    /// Used while idling, when the server may rightly stay silent.
+    pub async fn wait_for_response(&mut self) -> Result<Vec<u8>> {
+        self.read_response_after(None).await
+    }
+
+    /// Reads a response, allowing `first_byte` for it to start.
+    async fn read_response_after(&mut self, first_byte: Option<Duration>) -> Result<Vec<u8>> {
         let mut response = Vec::new();
+        let mut limit = first_byte;
 
         loop {
             // Read until CRLF
-            let line = self.read_line().await?;
+            let line = self.read_line(limit).await?;
+            limit = self.timeouts.io;
 
             // Append the line to the response
             response.extend_from_slice(&line);
 
             // Check for literal at end of line: {123} or {123+}
             if let Some(literal_len) = parse_literal_length(&line) {
-                // Validate literal size to prevent DoS via memory exhaustion
-                if literal_len > MAX_LITERAL_SIZE {
-                    return Err(crate::Error::Protocol(format!(
-                        "literal too large: {literal_len} bytes (max {MAX_LITERAL_SIZE})"
+                // Refuse before allocating, the length is the server's word
+                let max = self.limits.max_literal_length;
+                if literal_len > max {
+                    return Err(Error::Protocol(format!(
+                        "response too large: {literal_len}-byte literal (max {max})"
                     )));
                 }
                 // Read the literal data
                 let mut literal = vec![0u8; literal_len];
-                self.reader.read_exact(&mut literal).await?;
+                let mut filled = 0;
+                while filled < literal_len {
+                    let n = timed(limit, self.reader.read(&mut literal[filled..])).await?;
+                    if n == 0 {
+                        return Err(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+                    }
+                    filled += n;
+                }
                 response.extend_from_slice(&literal);
                 // Continue reading (there might be more after the literal)
             } else {
@@ -78,12 +184,15 @@ where
         Ok(response)
     }
 
-    /// Reads a single CRLF-terminated line.
-    async fn read_line(&mut self) -> Result<Vec<u8>> {
+    /// Reads a single CRLF-terminated line, allowing `first_byte` for it
+    /// to start.
+    async fn read_line(&mut self, first_byte: Option<Duration>) -> Result<Vec<u8>> {
         let mut line = Vec::new();
+        let mut limit = first_byte;
 
         loop {
-            let buf = self.reader.fill_buf().await?;
+            let buf = timed(limit, self.reader.fill_buf()).await?;
+            limit = self.timeouts.io;
             if buf.is_empty() {
                 return Err(crate::Error::Io(io::Error::new(
                     io::ErrorKind::UnexpectedEof,
@@ -92,20 +201,19 @@ where
             }
 
             // Look for CRLF
-            if let Some(pos) = find_crlf(buf) {
-                line.extend_from_slice(&buf[..pos + 2]);
-                self.reader.consume(pos + 2);
-                break;
-            }
-
-            // No CRLF found, consume all and continue
-            let len = buf.len();
-            line.extend_from_slice(buf);
+            let crlf = find_crlf(buf);
+            let len = crlf.map_or(buf.len(), |pos| pos + 2);
+            line.extend_from_slice(&buf[..len]);
             self.reader.consume(len);
 
-            // Check for maximum line length
-            if line.len() > MAX_LINE_LENGTH {
-                return Err(crate::Error::Protocol("line too long".to_string()));
+            let max = self.limits.max_line_length;
+            if line.len() > max {
+                return Err(Error::Protocol(format!(
+                    "response too large: line longer than {max} bytes"
+                )));
+            }
+            if crlf.is_some() {
+                break;
             }
         }
 
@@ -118,19 +226,21 @@ where
         self.write_buffer.extend_from_slice(data);
 
         let stream = self.reader.get_mut();
-        stream.write_all(&self.write_buffer).await?;
-        stream.flush().await?;
-
-        Ok(())
+        timed(self.timeouts.io, async {
+            stream.write_all(&self.write_buffer).await?;
+            stream.flush().await
+        })
+        .await
     }
 
     /// Writes raw data to the stream (for literals).
     pub async fn write_raw(&mut self, data: &[u8]) -> Result<()> {
         let stream = self.reader.get_mut();
-        stream.write_all(data).await?;
-        stream.flush().await?;
-
-        Ok(())
+        timed(self.timeouts.io, async {
+            stream.write_all(data).await?;
+            stream.flush().await
+        })
+        .await
     }
 
     /// Gets a reference to the underlying stream.
@@ -149,6 +259,33 @@ where
     pub fn into_inner(self) -> S {
         self.reader.into_inner()
     }
+
+    /// Consumes the framed stream, returning the inner stream and any bytes
+    /// already read from it but not yet consumed.
+    ///
+    /// Used when the wire format changes mid-session (e.g. COMPRESS), where
+    /// the buffered bytes belong to the new layer.
+    pub fn into_parts(self) -> (S, Vec<u8>) {
+        let buffered = self.reader.buffer().to_vec();
+        (self.reader.into_inner(), buffered)
+    }
+}
+
+/// Runs `op`, failing with [`Error::Timeout`] if it takes longer than
+/// `limit`.
+async fn timed<T, E>(
+    limit: Option<Duration>,
+    op: impl Future<Output = std::result::Result<T, E>>,
+) -> Result<T>
+where
+    Error: From<E>,
+{
+    let Some(limit) = limit else {
+        return Ok(op.await?);
+    };
+    Ok(tokio::time::timeout(limit, op)
+        .await
+        .map_err(|_| Error::Timeout(limit))??)
 }
 
 /// Finds the position of CRLF in a buffer.
@@ -203,10 +340,21 @@ impl ResponseAccumulator {
     }
 
     /// Reads responses until a tagged response matching our tag is found.
+    ///
+    /// Fails with [`Error::Timeout`] if the stream's command timeout passes
+    /// first; the responses read so far remain in [`Self::responses`].
     pub async fn read_until_tagged<S>(
         &mut self,
         framed: &mut FramedStream<S>,
     ) -> Result<Vec<Vec<u8>>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let limit = framed.timeouts.command;
+        timed(limit, self.read_all(framed)).await
+    }
+
+    async fn read_all<S>(&mut self, framed: &mut FramedStream<S>) -> Result<Vec<Vec<u8>>>
     where
         S: AsyncRead + AsyncWrite + Unpin,
     {
@@ -345,7 +493,7 @@ mod tests {
             result
                 .unwrap_err()
                 .to_string()
-                .contains("literal too large")
+                .contains("response too large")
         );
     }
 
@@ -381,6 +529,95 @@ mod tests {
 
         let result = framed.read_response().await;
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("line too long"));
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("response too large")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configured_literal_limit_rejects_before_allocation() {
+        use tokio_test::io::Builder;
+
+        // Only the declaration is sent; reading the literal would panic the
+        // mock, and allocating usize::MAX bytes would abort
+        let header = format!("* 1 FETCH (BODY[] {{{}}}\r\n", usize::MAX);
+        let mock = Builder::new().read(header.as_bytes()).build();
+        let limits = FrameLimits {
+            max_literal_length: 16,
+            ..FrameLimits::default()
+        };
+        let mut framed = FramedStream::new(mock).with_limits(limits);
+
+        let result = framed.read_response().await;
+        assert!(
+            matches!(result, Err(Error::Protocol(ref msg)) if msg.starts_with("response too large"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configured_limits() {
+        use tokio_test::io::Builder;
+
+        let limits = FrameLimits {
+            max_line_length: 32,
+            max_literal_length: 5,
+            read_buffer_size: 64 * 1024,
+        };
+
+        let mock = Builder::new()
+            .read(b"* 1 FETCH (BODY {5}\r\n")
+            .read(b"hello)\r\n")
+            .build();
+        let mut framed = FramedStream::new(mock).with_limits(limits);
+        assert_eq!(
+            framed.read_response().await.unwrap(),
+            b"* 1 FETCH (BODY {5}\r\nhello)\r\n"
+        );
+
+        let long_line = format!("* OK {}\r\n", "x".repeat(32));
+        let mock = Builder::new().read(long_line.as_bytes()).build();
+        let mut framed = FramedStream::new(mock).with_limits(limits);
+        let result = framed.read_response().await;
+        assert!(
+            matches!(result, Err(Error::Protocol(ref msg)) if msg.starts_with("response too large"))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stalled_literal_times_out() {
+        let (local, mut server) = tokio::io::duplex(64);
+        server
+            .write_all(b"* 1 FETCH (BODY {10}\r\nhalf")
+            .await
+            .unwrap();
+        let timeouts = Timeouts {
+            io: Some(Duration::from_secs(5)),
+            command: None,
+        };
+        let mut framed = FramedStream::new(local).with_timeouts(timeouts);
+
+        let result = framed.read_response().await;
+        assert!(matches!(result, Err(Error::Timeout(limit)) if limit == Duration::from_secs(5)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_waiting_for_response_ignores_io_timeout() {
+        let (local, mut server) = tokio::io::duplex(64);
+        let timeouts = Timeouts {
+            io: Some(Duration::from_secs(5)),
+            command: None,
+        };
+        let mut framed = FramedStream::new(local).with_timeouts(timeouts);
+
+        let reply = async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            server.write_all(b"* 3 EXISTS\r\n").await.unwrap();
+            server
+        };
+        let (response, _server) = tokio::join!(framed.wait_for_response(), reply);
+        assert_eq!(response.unwrap(), b"* 3 EXISTS\r\n");
     }
 }
@@ -5,17 +5,24 @@
 
 #![allow(clippy::missing_errors_doc)]
 
+use std::collections::VecDeque;
 use std::time::Duration;
 
+use futures_util::stream::{self, Stream};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::timeout;
 
 use super::client::{Client, Selected};
 use super::framed::FramedStream;
+use crate::command::{Command, TagGenerator};
 use crate::parser::{Response, ResponseParser, UntaggedResponse};
-use crate::types::{Flags, SeqNum};
+use crate::types::{Flags, SeqNum, Status};
 use crate::{Error, Result};
 
+/// How long [`IdleHandle::events`] stays in one IDLE before re-issuing it,
+/// per the 29-minute limit in RFC 2177.
+const REIDLE_INTERVAL: Duration = Duration::from_secs(29 * 60);
+
 /// Event received during IDLE.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum IdleEvent {
@@ -39,10 +46,13 @@ pub enum IdleEvent {
 /// Handle for an active IDLE session.
 ///
 /// This type holds a mutable reference to the client and manages the IDLE state.
-/// Call `wait()` to receive events, and `done()` to exit IDLE mode.
+/// Call `wait()` or `events()` to receive events, and `done()` to exit IDLE mode.
 pub struct IdleHandle<'a, S> {
     stream: &'a mut FramedStream<S>,
+    tag_gen: &'a TagGenerator,
     tag: String,
+    /// Events read while re-issuing IDLE, not yet yielded.
+    queued: VecDeque<IdleEvent>,
 }
 
 impl<'a, S> IdleHandle<'a, S>
@@ -50,8 +60,143 @@ where
     S: AsyncRead + AsyncWrite + Unpin,
 {
     /// Creates a new IDLE handle.
-    pub(crate) const fn new(stream: &'a mut FramedStream<S>, tag: String) -> Self {
-        Self { stream, tag }
+    pub(crate) const fn new(
+        stream: &'a mut FramedStream<S>,
+        tag_gen: &'a TagGenerator,
+        tag: String,
+    ) -> Self {
+        Self {
+            stream,
+            tag_gen,
+            tag,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Returns a stream of mailbox events for as long as IDLE lasts.
+    ///
+    /// Unlike [`wait`](Self::wait), the stream keeps the same IDLE running
+    /// across events and re-issues it every 29 minutes on its own. It ends
+    /// when the server terminates IDLE, and yields a final error if the
+    /// connection fails. Drop the stream and call [`done`](Self::done) to
+    /// leave IDLE.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use futures_util::StreamExt;
+    ///
+    /// let mut handle = client.idle().await?;
+    /// let mut events = std::pin::pin!(handle.events());
+    /// while let Some(event) = events.next().await {
+    ///     println!("{:?}", event?);
+    /// }
+    /// ```
+    pub fn events(&mut self) -> impl Stream<Item = Result<IdleEvent>> + '_ {
+        stream::unfold(Some(self), |handle| async move {
+            let handle = handle?;
+            match handle.next_event().await {
+                Ok(Some(event)) => Some((Ok(event), Some(handle))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Reads until the next event, re-issuing IDLE when it gets old.
+    ///
+    /// Returns `None` once the server ends IDLE with a tagged OK.
+    async fn next_event(&mut self) -> Result<Option<IdleEvent>> {
+        loop {
+            if let Some(event) = self.queued.pop_front() {
+                return Ok(Some(event));
+            }
+
+            let Ok(response) = timeout(REIDLE_INTERVAL, self.stream.read_response()).await else {
+                self.reissue().await?;
+                continue;
+            };
+            let response = response?;
+
+            match ResponseParser::parse(&response)? {
+                Response::Untagged(UntaggedResponse::Bye { text, .. }) => {
+                    return Err(Error::Bye(text));
+                }
+                Response::Untagged(untagged) => {
+                    if let Some(event) = Self::untagged_event(untagged) {
+                        return Ok(Some(event));
+                    }
+                }
+                Response::Tagged { .. } => {
+                    // Maps NO/BAD/BYE and foreign tags to errors
+                    self.parse_event(&response)?;
+                    return Ok(None);
+                }
+                Response::Continuation { .. } => {}
+            }
+        }
+    }
+
+    /// Ends the current IDLE and starts a new one under a fresh tag,
+    /// queuing any events that arrive in between.
+    async fn reissue(&mut self) -> Result<()> {
+        self.stream
+            .write_command(&Command::Done.serialize(""))
+            .await?;
+        loop {
+            let response = self.stream.read_response().await?;
+            match ResponseParser::parse(&response)? {
+                Response::Untagged(untagged) => self.queued.extend(Self::untagged_event(untagged)),
+                Response::Tagged { .. } => {
+                    self.parse_event(&response)?;
+                    break;
+                }
+                Response::Continuation { .. } => {}
+            }
+        }
+
+        self.tag = self.tag_gen.next();
+        self.stream
+            .write_command(&Command::Idle.serialize(&self.tag))
+            .await?;
+        loop {
+            let response = self.stream.read_response().await?;
+            if response.starts_with(b"+") {
+                return Ok(());
+            }
+            match ResponseParser::parse(&response)? {
+                Response::Untagged(untagged) => self.queued.extend(Self::untagged_event(untagged)),
+                Response::Tagged {
+                    status, code, text, ..
+                } => {
+                    return Err(match status {
+                        Status::No | Status::Bad => Error::from_tagged(status, code, text),
+                        _ => Error::Protocol("unexpected response to IDLE".to_string()),
+                    });
+                }
+                Response::Continuation { .. } => return Ok(()),
+            }
+        }
+    }
+
+    /// Converts a mailbox update into an event, ignoring other responses.
+    fn untagged_event(untagged: UntaggedResponse) -> Option<IdleEvent> {
+        match untagged {
+            UntaggedResponse::Exists(n) => Some(IdleEvent::Exists(n)),
+            UntaggedResponse::Recent(n) => Some(IdleEvent::Recent(n)),
+            UntaggedResponse::Expunge(seq) => Some(IdleEvent::Expunge(seq)),
+            UntaggedResponse::Fetch { seq, items } => {
+                let flags = items
+                    .into_iter()
+                    .find_map(|item| match item {
+                        crate::parser::FetchItem::Flags(f) => Some(f),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                Some(IdleEvent::Fetch { seq, flags })
+            }
+            _ => None,
+        }
     }
 
     /// Waits for a server event or timeout.
@@ -65,7 +210,7 @@ where
     /// have shorter timeouts (10-30 minutes), so consider using shorter
     /// timeouts in practice.
     pub async fn wait(&mut self, duration: Duration) -> Result<IdleEvent> {
-        match timeout(duration, self.stream.read_response()).await {
+        match timeout(duration, self.stream.wait_for_response()).await {
             Ok(Ok(response)) => self.parse_event(&response),
             Ok(Err(e)) => Err(e),
             Err(_) => Ok(IdleEvent::Timeout),
@@ -75,30 +220,10 @@ where
     /// Parses a response into an `IdleEvent`.
     fn parse_event(&self, response: &[u8]) -> Result<IdleEvent> {
         match ResponseParser::parse(response)? {
-            Response::Untagged(untagged) => match untagged {
-                UntaggedResponse::Exists(n) => Ok(IdleEvent::Exists(n)),
-                UntaggedResponse::Recent(n) => Ok(IdleEvent::Recent(n)),
-                UntaggedResponse::Expunge(seq) => Ok(IdleEvent::Expunge(seq)),
-                UntaggedResponse::Fetch { seq, items } => {
-                    // Extract flags from fetch items
-                    let flags = items
-                        .into_iter()
-                        .find_map(|item| {
-                            if let crate::parser::FetchItem::Flags(f) = item {
-                                Some(f)
-                            } else {
-                                None
-                            }
-                        })
-                        .unwrap_or_default();
-                    Ok(IdleEvent::Fetch { seq, flags })
-                }
-                _ => {
-                    // Ignore other untagged responses during IDLE
-                    // This shouldn't happen often, but we handle it gracefully
-                    Ok(IdleEvent::Timeout)
-                }
-            },
+            // Other untagged responses are ignored during IDLE
+            Response::Untagged(untagged) => {
+                Ok(Self::untagged_event(untagged).unwrap_or(IdleEvent::Timeout))
+            }
             Response::Continuation { .. } => {
                 // Continuation during IDLE is unexpected
                 Err(Error::Protocol(
@@ -106,7 +231,10 @@ where
                 ))
             }
             Response::Tagged {
-                tag, status, text, ..
+                tag,
+                status,
+                code,
+                text,
             } => {
                 // If we receive a tagged response, IDLE was terminated by the server
                 if tag.as_str() == self.tag {
@@ -115,9 +243,9 @@ where
                             // Server terminated IDLE normally (unusual but valid)
                             Ok(IdleEvent::Timeout)
                         }
-                        crate::types::Status::No => Err(Error::No(text)),
-                        crate::types::Status::Bad => Err(Error::Bad(text)),
-                        crate::types::Status::Bye => Err(Error::Bye(text)),
+                        crate::types::Status::No
+                        | crate::types::Status::Bad
+                        | crate::types::Status::Bye => Err(Error::from_tagged(status, code, text)),
                         crate::types::Status::PreAuth => {
                             Err(Error::Protocol("unexpected PREAUTH in IDLE".to_string()))
                         }
@@ -137,8 +265,6 @@ where
     /// This consumes the handle and returns control to the client.
     /// After calling `done()`, the client can issue other commands.
     pub async fn done(self) -> Result<()> {
-        use crate::Command;
-
         // Send DONE (no tag)
         let cmd = Command::Done.serialize("");
         self.stream.write_command(&cmd).await?;
@@ -147,15 +273,18 @@ where
         loop {
             let response = self.stream.read_response().await?;
             if let Ok(Response::Tagged {
-                tag, status, text, ..
+                tag,
+                status,
+                code,
+                text,
             }) = ResponseParser::parse(&response)
                 && tag.as_str() == self.tag
             {
                 return match status {
                     crate::types::Status::Ok => Ok(()),
-                    crate::types::Status::No => Err(Error::No(text)),
-                    crate::types::Status::Bad => Err(Error::Bad(text)),
-                    crate::types::Status::Bye => Err(Error::Bye(text)),
+                    crate::types::Status::No
+                    | crate::types::Status::Bad
+                    | crate::types::Status::Bye => Err(Error::from_tagged(status, code, text)),
                     crate::types::Status::PreAuth => {
                         Err(Error::Protocol("unexpected PREAUTH after DONE".to_string()))
                     }
@@ -196,8 +325,6 @@ where
     /// - IDLE should be re-issued periodically (every 10-29 minutes)
     /// - Some servers may drop idle connections after extended periods
     pub async fn idle(&mut self) -> Result<IdleHandle<'_, S>> {
-        use crate::Command;
-
         let tag = self.tag_gen.next();
         let cmd = Command::Idle.serialize(&tag);
         self.stream.write_command(&cmd).await?;
@@ -206,10 +333,14 @@ where
         let response = self.stream.read_response().await?;
         if !response.starts_with(b"+") {
             let parsed = ResponseParser::parse(&response)?;
-            if let Response::Tagged { status, text, .. } = parsed {
+            if let Response::Tagged {
+                status, code, text, ..
+            } = parsed
+            {
                 return match status {
-                    crate::types::Status::No => Err(Error::No(text)),
-                    crate::types::Status::Bad => Err(Error::Bad(text)),
+                    crate::types::Status::No | crate::types::Status::Bad => {
+                        Err(Error::from_tagged(status, code, text))
+                    }
                     _ => Err(Error::Protocol("unexpected response to IDLE".to_string())),
                 };
             }
@@ -218,6 +349,68 @@ where
             ));
         }
 
-        Ok(IdleHandle::new(&mut self.stream, tag))
+        Ok(IdleHandle::new(&mut self.stream, &self.tag_gen, tag))
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn events_yields_updates_without_reissuing_idle() {
+        let (mut server, local) = tokio::io::duplex(1024);
+        server
+            .write_all(b"* 5 EXISTS\r\n* 3 EXPUNGE\r\n")
+            .await
+            .unwrap();
+
+        let mut stream = FramedStream::new(local);
+        let tag_gen = TagGenerator::default();
+        let mut handle = IdleHandle::new(&mut stream, &tag_gen, "A0001".to_string());
+        let events: Vec<_> = handle.events().take(2).collect().await;
+        drop(handle);
+        drop(stream);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap(), &IdleEvent::Exists(5));
+        assert_eq!(
+            events[1].as_ref().unwrap(),
+            &IdleEvent::Expunge(SeqNum::new(3).unwrap())
+        );
+
+        // Nothing was written back: no DONE, no second IDLE
+        let mut sent = Vec::new();
+        server.read_to_end(&mut sent).await.unwrap();
+        assert!(sent.is_empty());
+    }
+
+    #[tokio::test]
+    async fn events_ends_when_server_terminates_idle() {
+        let (mut server, local) = tokio::io::duplex(1024);
+        server
+            .write_all(b"* 2 EXISTS\r\nA0001 OK IDLE terminated\r\n")
+            .await
+            .unwrap();
+
+        let mut stream = FramedStream::new(local);
+        let tag_gen = TagGenerator::default();
+        let mut handle = IdleHandle::new(&mut stream, &tag_gen, "A0001".to_string());
+        let events: Vec<_> = handle.events().collect().await;
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_ref().unwrap(), &IdleEvent::Exists(2));
     }
 }
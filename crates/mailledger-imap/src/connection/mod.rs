@@ -4,20 +4,27 @@
 //! - Configuration (host, port, security mode)
 //! - TLS/plaintext stream abstraction
 //! - Framed I/O for IMAP protocol
+//! - DEFLATE compression (COMPRESS=DEFLATE)
 //! - Type-state connection wrapper
 //! - IDLE support for real-time notifications
 //! - High-level session with auto-reconnect
 
 mod client;
+mod compress;
 mod config;
 mod framed;
 mod idle;
 mod session;
 mod stream;
+mod verifier;
 
 pub use client::{Authenticated, Client, NotAuthenticated, Selected};
-pub use config::{Config, ConfigBuilder, Security};
-pub use framed::{FramedStream, ResponseAccumulator};
+pub use compress::DeflateStream;
+pub use config::{Config, ConfigBuilder, ProxyConfig, Security, TlsOptions};
+pub use framed::{FrameLimits, FramedStream, ResponseAccumulator, Timeouts};
 pub use idle::{IdleEvent, IdleHandle};
 pub use session::{Session, SessionConfig};
-pub use stream::{ImapStream, connect_plain, connect_tls, create_tls_connector};
+pub use stream::{
+    ImapStream, connect, connect_plain, connect_tls, connect_tls_with, create_tls_connector,
+    create_tls_connector_with,
+};
@@ -29,13 +29,14 @@
 //! session.idle(Duration::from_secs(600)).await?;
 //! ```
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use super::client::{Authenticated, Client, NotAuthenticated, Selected};
-use super::{ImapStream, connect_tls};
-use crate::command::{FetchItems, StoreAction};
+use super::{ImapStream, Timeouts, connect_tls};
+use crate::command::{FetchItems, StoreAction, TagGenerator};
 use crate::parser::FetchItem;
-use crate::types::{ListResponse, MailboxStatus, SeqNum, SequenceSet, UidSet};
+use crate::types::{CopyUid, ListResponse, MailboxStatus, SeqNum, SequenceSet, UidSet};
 use crate::{Error, Result};
 
 /// Configuration for an IMAP session.
@@ -126,6 +127,9 @@ pub struct Session {
     state: SessionState,
     /// Last selected mailbox (for reconnection).
     last_mailbox: Option<String>,
+    /// Tags commands on every connection, so tags never repeat across
+    /// reconnects.
+    tags: Arc<TagGenerator>,
 }
 
 impl Session {
@@ -139,6 +143,7 @@ impl Session {
             config,
             state: SessionState::Disconnected,
             last_mailbox: None,
+            tags: Arc::new(TagGenerator::default().with_random_base()),
         };
 
         session.do_connect().await?;
@@ -273,7 +278,7 @@ impl Session {
     /// # Errors
     ///
     /// Returns an error if not in selected state or copy fails.
-    pub async fn copy(&mut self, sequence: &SequenceSet, mailbox: &str) -> Result<()> {
+    pub async fn copy(&mut self, sequence: &SequenceSet, mailbox: &str) -> Result<Option<CopyUid>> {
         self.ensure_selected().await?;
 
         match &mut self.state {
@@ -287,7 +292,11 @@ impl Session {
     /// # Errors
     ///
     /// Returns an error if not in selected state or move fails.
-    pub async fn r#move(&mut self, sequence: &SequenceSet, mailbox: &str) -> Result<()> {
+    pub async fn r#move(
+        &mut self,
+        sequence: &SequenceSet,
+        mailbox: &str,
+    ) -> Result<Option<CopyUid>> {
         self.ensure_selected().await?;
 
         match &mut self.state {
@@ -391,6 +400,7 @@ impl Session {
     async fn do_reconnect_no_select(&mut self) -> Result<()> {
         for attempt in 1..=self.config.max_reconnect_attempts {
             tracing::info!(attempt, "Attempting to reconnect");
+            self.tags.reset_on_reconnect();
 
             if let Err(e) = self.do_connect().await {
                 tracing::warn!(?e, "Connection attempt failed");
@@ -417,8 +427,19 @@ impl Session {
     // === Private helpers ===
 
     async fn do_connect(&mut self) -> Result<()> {
-        let stream = connect_tls(&self.config.host, self.config.port).await?;
-        let client = Client::from_stream(stream).await?;
+        let limit = self.config.connect_timeout;
+        let mut client = tokio::time::timeout(limit, async {
+            let stream = connect_tls(&self.config.host, self.config.port).await?;
+            Client::from_stream(stream).await
+        })
+        .await
+        .map_err(|_| Error::Timeout(limit))??;
+        // No single read or write can rightly outlast the whole command.
+        client.set_timeouts(Timeouts {
+            io: Some(self.config.command_timeout),
+            command: Some(self.config.command_timeout),
+        });
+        client.set_tag_generator(Arc::clone(&self.tags));
         self.state = SessionState::Connected(client);
         Ok(())
     }
@@ -430,9 +451,14 @@ impl Session {
             return Err(Error::InvalidState("not connected".into()));
         };
 
-        let authenticated = client
-            .login(&self.config.username, &self.config.password)
-            .await?;
+        let authenticated = match client.preauthenticated() {
+            Ok(authenticated) => authenticated,
+            Err(client) => {
+                client
+                    .login(&self.config.username, &self.config.password)
+                    .await?
+            }
+        };
         self.state = SessionState::Authenticated(authenticated);
         Ok(())
     }
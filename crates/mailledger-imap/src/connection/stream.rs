@@ -4,15 +4,23 @@
 
 use std::io;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::task::{Context, Poll};
 
-use rustls::pki_types::ServerName;
+use rustls::ClientConfig;
+use rustls::client::{Resumption, WebPkiServerVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
 use tokio_rustls::TlsConnector;
 use tokio_rustls::client::TlsStream;
+use tokio_socks::tcp::Socks5Stream;
 
+use super::client::{Client, NotAuthenticated};
+use super::config::{Config, Security, TlsOptions};
+use super::verifier::CertVerifier;
+use crate::types::Capability;
 use crate::{Error, Result};
 
 /// A stream that can be either plaintext or TLS.
@@ -94,31 +102,179 @@ impl AsyncWrite for ImapStream {
     }
 }
 
+/// Most client configurations kept for reuse; beyond this the least
+/// recently used one is dropped.
+const MAX_CLIENT_CONFIGS: usize = 8;
+
+/// Client configurations already built, with the options they were built
+/// from, most recently used last.
+struct ConfigCache {
+    entries: Vec<(TlsOptions, Arc<ClientConfig>)>,
+}
+
+impl ConfigCache {
+    const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the configuration built for `options`, calling `build` if
+    /// there is none.
+    fn get_or_build(
+        &mut self,
+        options: &TlsOptions,
+        build: impl FnOnce() -> Result<ClientConfig>,
+    ) -> Result<Arc<ClientConfig>> {
+        if let Some(index) = self.entries.iter().position(|(built, _)| built == options) {
+            let entry = self.entries.remove(index);
+            let config = Arc::clone(&entry.1);
+            self.entries.push(entry);
+            return Ok(config);
+        }
+
+        let config = Arc::new(build()?);
+        if self.entries.len() >= MAX_CLIENT_CONFIGS {
+            self.entries.remove(0);
+        }
+        self.entries.push((options.clone(), Arc::clone(&config)));
+        Ok(config)
+    }
+}
+
+/// rustls only resumes a session with the certificate verifier that
+/// established it, so reconnects must reuse the configuration rather than
+/// build a new one each time.
+static CLIENT_CONFIGS: Mutex<ConfigCache> = Mutex::new(ConfigCache::new());
+
 /// Creates a TLS connector with default root certificates.
 pub fn create_tls_connector() -> Result<TlsConnector> {
-    let root_store = rustls::RootCertStore {
+    create_tls_connector_with(&TlsOptions::default())
+}
+
+/// Creates a TLS connector that verifies certificates as `options` says.
+///
+/// Connectors made with equal options share a configuration, and with it
+/// the session cache, so reconnecting to a server can resume the previous
+/// session instead of a full handshake. Only the most recently used
+/// configurations are kept.
+pub fn create_tls_connector_with(options: &TlsOptions) -> Result<TlsConnector> {
+    let config = CLIENT_CONFIGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_build(options, || client_config(options))?;
+    Ok(TlsConnector::from(config))
+}
+
+/// Builds a client configuration that verifies certificates as `options`
+/// says.
+fn client_config(options: &TlsOptions) -> Result<ClientConfig> {
+    let mut root_store = rustls::RootCertStore {
         roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
     };
+    for der in &options.root_certs {
+        root_store.add(CertificateDer::from(der.clone()))?;
+    }
 
-    let config = rustls::ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+    let builder = ClientConfig::builder_with_provider(crypto_provider())
+        .with_safe_default_protocol_versions()?;
+    let mut config = if options.pinned_cert_sha256.is_some() || options.accept_invalid_certs {
+        let webpki = WebPkiServerVerifier::builder_with_provider(
+            Arc::new(root_store),
+            Arc::clone(builder.crypto_provider()),
+        )
+        .build()
+        .map_err(|e| rustls::Error::General(e.to_string()))?;
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(CertVerifier::new(
+                webpki,
+                options.pinned_cert_sha256,
+            )))
+            .with_no_client_auth()
+    } else {
+        builder
+            .with_root_certificates(root_store)
+            .with_no_client_auth()
+    };
+    if let Some(cache) = &options.session_cache {
+        config.resumption = Resumption::store(Arc::clone(cache));
+    }
 
-    Ok(TlsConnector::from(Arc::new(config)))
+    Ok(config)
+}
+
+/// Returns the process-wide crypto provider, or aws-lc-rs if none was
+/// installed.
+///
+/// rustls only picks a default itself when exactly one provider is built
+/// in, and reqwest's `rustls-tls` brings in ring next to aws-lc-rs.
+fn crypto_provider() -> Arc<CryptoProvider> {
+    CryptoProvider::get_default().map_or_else(
+        || Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+        Arc::clone,
+    )
 }
 
 /// Connects to a server with TLS from the start.
 pub async fn connect_tls(host: &str, port: u16) -> Result<ImapStream> {
+    connect_tls_with(host, port, &TlsOptions::default()).await
+}
+
+/// Connects to a server with TLS from the start, verifying its certificate
+/// as `options` says.
+pub async fn connect_tls_with(host: &str, port: u16, options: &TlsOptions) -> Result<ImapStream> {
     let addr = format!("{host}:{port}");
     let tcp = TcpStream::connect(&addr).await?;
+    tls_handshake(tcp, host, options).await
+}
 
-    let connector = create_tls_connector()?;
-    let server_name = ServerName::try_from(host.to_string())?;
-    let tls = connector.connect(server_name, tcp).await?;
+/// Secures `tcp` with TLS, sending `server_name` as SNI and verifying the
+/// certificate for it.
+async fn tls_handshake(
+    tcp: TcpStream,
+    server_name: &str,
+    options: &TlsOptions,
+) -> Result<ImapStream> {
+    let connector = create_tls_connector_with(options)?;
+    let server_name = ServerName::try_from(server_name.to_string())?;
+    let tls = connector
+        .connect(server_name, tcp)
+        .await
+        .map_err(handshake_error)?;
 
     Ok(ImapStream::Tls(Box::new(tls)))
 }
 
+/// Opens a TCP connection to the server, through the proxy if configured.
+async fn dial(config: &Config) -> Result<TcpStream> {
+    let Some(proxy) = &config.proxy else {
+        return Ok(TcpStream::connect((config.host.as_str(), config.port)).await?);
+    };
+    let proxy_addr = (proxy.host.as_str(), proxy.port);
+    let target = (config.host.as_str(), config.port);
+    let stream = match &proxy.credentials {
+        Some((username, password)) => {
+            Socks5Stream::connect_with_password(proxy_addr, target, username, password).await
+        }
+        None => Socks5Stream::connect(proxy_addr, target).await,
+    };
+    match stream {
+        Ok(stream) => Ok(stream.into_inner()),
+        Err(tokio_socks::Error::Io(e)) => Err(Error::Io(e)),
+        Err(e) => Err(Error::Proxy(e.to_string())),
+    }
+}
+
+/// Surfaces the TLS error behind a failed handshake as [`Error::Tls`].
+pub fn handshake_error(e: io::Error) -> Error {
+    let tls = e
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+        .cloned();
+    tls.map_or_else(|| Error::Io(e), Error::Tls)
+}
+
 /// Connects to a server without TLS (for STARTTLS or testing).
 pub async fn connect_plain(host: &str, port: u16) -> Result<ImapStream> {
     let addr = format!("{host}:{port}");
@@ -126,6 +282,60 @@ pub async fn connect_plain(host: &str, port: u16) -> Result<ImapStream> {
     Ok(ImapStream::Plain(tcp))
 }
 
+/// Connects to the server described by `config` and reads the greeting.
+///
+/// With [`Security::StartTls`] the plaintext connection is upgraded before
+/// returning, so the client is ready to authenticate in every mode. With
+/// [`Security::None`] it is upgraded too if the server advertises
+/// LOGINDISABLED and STARTTLS, so the password is never sent in the clear.
+///
+/// The TCP connection goes through `config.proxy` if set, and TLS verifies
+/// the certificate for [`Config::tls_server_name`].
+///
+/// Establishing the connection is bounded by `config.connect_timeout`; the
+/// returned client applies the I/O and command timeouts and the size limits
+/// from `config`.
+pub async fn connect(config: &Config) -> Result<Client<ImapStream, NotAuthenticated>> {
+    let mut client = tokio::time::timeout(config.connect_timeout, connect_untimed(config))
+        .await
+        .map_err(|_| Error::Timeout(config.connect_timeout))??;
+    client.set_timeouts(config.timeouts());
+    Ok(client)
+}
+
+async fn connect_untimed(config: &Config) -> Result<Client<ImapStream, NotAuthenticated>> {
+    let tcp = dial(config).await?;
+    match config.security {
+        Security::Implicit => {
+            let stream = tls_handshake(tcp, config.tls_server_name(), &config.tls).await?;
+            Client::from_stream_with_limits(stream, config.limits()).await
+        }
+        Security::StartTls => {
+            let connector = create_tls_connector_with(&config.tls)?;
+            Client::from_stream_with_limits(tcp, config.limits())
+                .await?
+                .starttls_with(config.tls_server_name(), connector, ImapStream::tls)
+                .await
+        }
+        Security::None => {
+            let mut client = Client::from_stream_with_limits(tcp, config.limits()).await?;
+            if client.capabilities().is_empty() {
+                client.capability().await?;
+            }
+            // The server won't take a password in the clear, so upgrade as
+            // it asks rather than fall back to something weaker
+            if client.login_disabled() && client.has_capability(&Capability::StartTls) {
+                let connector = create_tls_connector_with(&config.tls)?;
+                client
+                    .starttls_with(config.tls_server_name(), connector, ImapStream::tls)
+                    .await
+            } else {
+                client.map_stream(ImapStream::Plain)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -144,4 +354,55 @@ mod tests {
         let connector = create_tls_connector();
         assert!(connector.is_ok());
     }
+
+    #[test]
+    fn test_tls_connector_reuses_config_for_equal_options() {
+        let pinned = TlsOptions {
+            pinned_cert_sha256: Some([7; 32]),
+            ..TlsOptions::default()
+        };
+        let first = client_config_for(&pinned);
+        assert!(Arc::ptr_eq(&first, &client_config_for(&pinned.clone())));
+        assert!(!Arc::ptr_eq(
+            &first,
+            &client_config_for(&TlsOptions::default())
+        ));
+    }
+
+    #[test]
+    fn test_config_cache_evicts_least_recently_used() {
+        let options: Vec<TlsOptions> = (0..=MAX_CLIENT_CONFIGS)
+            .map(|i| TlsOptions {
+                pinned_cert_sha256: Some([u8::try_from(i).unwrap(); 32]),
+                ..TlsOptions::default()
+            })
+            .collect();
+        let mut cache = ConfigCache::new();
+        let first = cache
+            .get_or_build(&options[0], || client_config(&options[0]))
+            .unwrap();
+        for option in &options[1..MAX_CLIENT_CONFIGS] {
+            cache
+                .get_or_build(option, || client_config(option))
+                .unwrap();
+        }
+
+        // Using the first again makes the second the oldest
+        let reused = cache
+            .get_or_build(&options[0], || unreachable!("already built"))
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &reused));
+
+        let last = &options[MAX_CLIENT_CONFIGS];
+        cache.get_or_build(last, || client_config(last)).unwrap();
+        assert_eq!(cache.entries.len(), MAX_CLIENT_CONFIGS);
+        let cached = |option: &TlsOptions| cache.entries.iter().any(|(built, _)| built == option);
+        assert!(cached(&options[0]));
+        assert!(!cached(&options[1]));
+        assert!(cached(last));
+    }
+
+    fn client_config_for(options: &TlsOptions) -> Arc<ClientConfig> {
+        create_tls_connector_with(options).unwrap().config().clone()
+    }
 }
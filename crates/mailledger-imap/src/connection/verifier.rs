@@ -0,0 +1,69 @@
+//! Server certificate verification beyond the default web PKI checks.
+
+use std::sync::Arc;
+
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+/// Verifies the server certificate against a pin, or not at all.
+///
+/// Handshake signatures are always checked, so the server must hold the
+/// private key of the certificate it presents.
+#[derive(Debug)]
+pub struct CertVerifier {
+    /// Verifier used for handshake signatures.
+    webpki: Arc<WebPkiServerVerifier>,
+    /// SHA-256 of the only accepted leaf certificate.
+    pin: Option<[u8; 32]>,
+}
+
+impl CertVerifier {
+    /// Accepts only the leaf certificate whose SHA-256 is `pin`, or any
+    /// certificate if `pin` is `None`.
+    pub const fn new(webpki: Arc<WebPkiServerVerifier>, pin: Option<[u8; 32]>) -> Self {
+        Self { webpki, pin }
+    }
+}
+
+impl ServerCertVerifier for CertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self.pin {
+            Some(pin) if Sha256::digest(end_entity.as_ref()).as_slice() != pin => Err(
+                rustls::Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure),
+            ),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.webpki.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.webpki.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.webpki.supported_verify_schemes()
+    }
+}
@@ -8,6 +8,8 @@ use std::time::Duration;
 
 use thiserror::Error;
 
+use crate::types::{ResponseCode, Status};
+
 /// Errors that can occur during IMAP operations.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -19,6 +21,10 @@ pub enum Error {
     #[error("TLS error: {0}")]
     Tls(#[from] rustls::Error),
 
+    /// Proxy refused or failed to set up the connection.
+    #[error("Proxy error: {0}")]
+    Proxy(String),
+
     /// Invalid DNS name for TLS.
     #[error("Invalid DNS name: {0}")]
     InvalidDnsName(#[from] rustls::pki_types::InvalidDnsNameError),
@@ -36,13 +42,24 @@ pub enum Error {
     #[error("Authentication failed: {0}")]
     Auth(String),
 
-    /// Server returned NO response.
-    #[error("Server returned NO: {0}")]
-    No(String),
+    /// Server rejected an AUTHENTICATE exchange, with its decoded reason.
+    #[error("Server rejected authentication: {0}")]
+    AuthenticationFailed(String),
 
-    /// Server returned BAD response.
-    #[error("Server returned BAD: {0}")]
-    Bad(String),
+    /// Server refused the operation because it would exceed a quota.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// Server completed a command with NO or BAD.
+    #[error("Server returned {status}: {text}")]
+    Tagged {
+        /// NO for an operational failure, BAD for a protocol error.
+        status: Status,
+        /// Response code sent with the status, e.g. `[TRYCREATE]`.
+        code: Option<ResponseCode>,
+        /// Human-readable text.
+        text: String,
+    },
 
     /// Server sent BYE (disconnecting).
     #[error("Server sent BYE: {0}")]
@@ -77,9 +94,32 @@ pub enum Error {
     /// Server is unavailable.
     #[error("Server unavailable: {0}")]
     Unavailable(String),
+
+    /// Server does not advertise a capability the operation requires.
+    #[error("Server does not support {0}")]
+    Unsupported(String),
+
+    /// Server refuses LOGIN (LOGINDISABLED) until the connection is
+    /// upgraded with STARTTLS.
+    #[error("Server requires STARTTLS before LOGIN")]
+    StartTlsRequired,
 }
 
 impl Error {
+    /// Builds the error for a tagged response that didn't complete with OK.
+    ///
+    /// `BYE` becomes [`Self::Bye`] and `NO [UNAVAILABLE]` becomes
+    /// [`Self::Unavailable`], so both are retried; anything else keeps its
+    /// status and response code in [`Self::Tagged`].
+    #[must_use]
+    pub fn from_tagged(status: Status, code: Option<ResponseCode>, text: String) -> Self {
+        match (status, code) {
+            (Status::Bye, _) => Self::Bye(text),
+            (Status::No, Some(ResponseCode::Unavailable)) => Self::Unavailable(text),
+            (status, code) => Self::Tagged { status, code, text },
+        }
+    }
+
     /// Wraps this error with command context.
     #[must_use]
     pub fn with_command(self, command: impl Into<CommandContext>) -> Self {
@@ -98,6 +138,29 @@ impl Error {
         )
     }
 
+    /// Returns true if retrying the operation may succeed.
+    ///
+    /// Covers server shutdowns (`BYE`), dropped or reset connections,
+    /// timeouts and `NO [UNAVAILABLE]` responses.
+    #[must_use]
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            Self::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+                    | std::io::ErrorKind::TimedOut
+            ),
+            Self::Bye(_) | Self::Timeout(_) | Self::ConnectionLost(_) | Self::Unavailable(_) => {
+                true
+            }
+            Self::Command { source, .. } => source.is_retriable(),
+            _ => false,
+        }
+    }
+
     /// Returns true if this error indicates the connection is dead.
     #[must_use]
     pub const fn is_connection_dead(&self) -> bool {
@@ -111,7 +174,52 @@ impl Error {
     #[must_use]
     pub fn is_auth_error(&self) -> bool {
         matches!(self, Self::Auth(_))
-            || matches!(self, Self::No(text) if text.to_lowercase().contains("auth"))
+            || self.is_auth_failed()
+            || matches!(
+                self,
+                Self::Tagged { status: Status::No, text, .. } if text.to_lowercase().contains("auth")
+            )
+    }
+
+    /// Returns the response code the server sent with a failed command.
+    #[must_use]
+    pub fn response_code(&self) -> Option<&ResponseCode> {
+        match self {
+            Self::Tagged { code, .. } => code.as_ref(),
+            Self::Command { source, .. } => source.response_code(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the command would have exceeded a quota
+    /// (`[OVERQUOTA]`).
+    #[must_use]
+    pub fn is_overquota(&self) -> bool {
+        matches!(self.root(), Self::QuotaExceeded(_))
+            || self.response_code() == Some(&ResponseCode::OverQuota)
+    }
+
+    /// Returns true if the target mailbox doesn't exist but can be created
+    /// (`[TRYCREATE]`).
+    #[must_use]
+    pub fn is_try_create(&self) -> bool {
+        self.response_code() == Some(&ResponseCode::TryCreate)
+    }
+
+    /// Returns true if the server rejected the credentials
+    /// (`[AUTHENTICATIONFAILED]` or a failed AUTHENTICATE).
+    #[must_use]
+    pub fn is_auth_failed(&self) -> bool {
+        matches!(self.root(), Self::AuthenticationFailed(_))
+            || self.response_code() == Some(&ResponseCode::AuthenticationFailed)
+    }
+
+    /// Returns the error beneath any command context.
+    fn root(&self) -> &Self {
+        match self {
+            Self::Command { source, .. } => source.root(),
+            other => other,
+        }
     }
 }
 
@@ -182,3 +290,84 @@ impl<T> ResultExt<T> for Result<T> {
         self.map_err(|e| e.with_command(command))
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::parser::{Response, ResponseParser};
+
+    fn tagged_error(line: &[u8]) -> Error {
+        match ResponseParser::parse(line).unwrap() {
+            Response::Tagged {
+                status, code, text, ..
+            } => Error::from_tagged(status, code, text),
+            other => panic!("Expected tagged, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_tagged_no_keeps_response_code() {
+        let err = tagged_error(b"A001 NO [TRYCREATE] Mailbox doesn't exist: Archive\r\n");
+        assert!(matches!(
+            err,
+            Error::Tagged {
+                status: Status::No,
+                code: Some(ResponseCode::TryCreate),
+                ref text,
+            } if text == "Mailbox doesn't exist: Archive"
+        ));
+        assert!(err.is_try_create());
+        assert!(!err.is_overquota());
+        assert_eq!(
+            err.to_string(),
+            "Server returned NO: Mailbox doesn't exist: Archive"
+        );
+    }
+
+    #[test]
+    fn test_tagged_overquota() {
+        let err = tagged_error(b"A002 NO [OVERQUOTA] Quota exceeded\r\n");
+        assert!(err.is_overquota());
+        assert!(err.with_command("COPY").is_overquota());
+    }
+
+    #[test]
+    fn test_tagged_authentication_failed() {
+        let err = tagged_error(b"A003 NO [AUTHENTICATIONFAILED] Invalid credentials\r\n");
+        assert_eq!(
+            err.response_code(),
+            Some(&ResponseCode::AuthenticationFailed)
+        );
+        assert!(err.is_auth_failed());
+        assert!(err.is_auth_error());
+        assert!(!err.is_retriable());
+    }
+
+    #[test]
+    fn test_tagged_alert_and_bad() {
+        let err = tagged_error(b"A004 NO [ALERT] Account suspended\r\n");
+        assert_eq!(err.response_code(), Some(&ResponseCode::Alert));
+
+        let err = tagged_error(b"A005 BAD Command unrecognized\r\n");
+        assert!(matches!(
+            err,
+            Error::Tagged {
+                status: Status::Bad,
+                code: None,
+                ..
+            }
+        ));
+        assert_eq!(err.response_code(), None);
+    }
+
+    #[test]
+    fn test_tagged_unavailable_and_bye_are_retriable() {
+        let err = tagged_error(b"A006 NO [UNAVAILABLE] Backend down\r\n");
+        assert!(matches!(err, Error::Unavailable(_)));
+        assert!(err.is_retriable());
+
+        let err = tagged_error(b"A007 BYE Shutting down\r\n");
+        assert!(matches!(err, Error::Bye(_)));
+    }
+}
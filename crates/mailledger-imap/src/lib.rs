@@ -111,10 +111,14 @@ pub mod stream_fetch;
 pub mod time;
 pub mod types;
 
-pub use command::{Command, FetchAttribute, FetchItems, SearchCriteria, StoreAction, TagGenerator};
+pub use command::{
+    AppendItem, Command, FetchAttribute, FetchItems, ListReturnOpt, ListSelectOpt, SearchCriteria,
+    SearchCriteriaBuilder, SearchReturnOption, SortKey, StoreAction, TagGenerator, ThreadAlgorithm,
+};
 pub use connection::{
-    Authenticated, Client, Config, ConfigBuilder, FramedStream, IdleEvent, IdleHandle, ImapStream,
-    NotAuthenticated, ResponseAccumulator, Security, Selected, Session, SessionConfig,
+    Authenticated, Client, Config, ConfigBuilder, DeflateStream, FrameLimits, FramedStream,
+    IdleEvent, IdleHandle, ImapStream, NotAuthenticated, ProxyConfig, ResponseAccumulator,
+    Security, Selected, Session, SessionConfig, Timeouts, TlsOptions,
 };
 pub use error::{CommandContext, Error, Result, ResultExt};
 pub use fetch::{
@@ -129,12 +133,12 @@ pub use protocol::{CommandHandle, CommandResult, Protocol, ProtocolEvent, Protoc
 pub use qresync::{
     ChangedMessage, ModSeq, QresyncParams, SyncChanges, SyncState, VanishedResponse,
 };
-pub use quirks::{ServerQuirks, ServerType};
+pub use quirks::{DefaultFolders, ServerQuirks, ServerType};
 pub use stream_fetch::{FetchStreamState, FetchedMessage, StreamFetchOptions};
 pub use time::{BoxClock, Clock, MockClock, SystemClock};
 pub use types::{
-    Capability, Flag, Flags, ListResponse, Mailbox, MailboxAttribute, MailboxStatus, ResponseCode,
-    SeqNum, SequenceSet, Status, Tag, Uid, UidSet, UidValidity,
+    AppendUid, Capability, CopyUid, Flag, Flags, ListResponse, Mailbox, MailboxAttribute,
+    MailboxStatus, ResponseCode, SeqNum, SequenceSet, Status, Tag, Uid, UidSet, UidValidity,
 };
 
 /// IMAP protocol version supported.
@@ -80,7 +80,9 @@ impl<'a> Lexer<'a> {
                     self.skip(2);
                     Ok(Token::Crlf)
                 } else {
-                    Err(self.error("Expected LF after CR"))
+                    let err = self.error("Expected LF after CR");
+                    self.advance();
+                    Err(err)
                 }
             }
 
@@ -122,14 +124,24 @@ impl<'a> Lexer<'a> {
             // Literal
             b'{' => self.read_literal_prefix(),
 
+            // Binary literal (literal8, RFC 3516)
+            b'~' if self.peek_at(1) == Some(b'{') => {
+                self.advance();
+                self.read_literal_prefix()
+            }
+
             // Number or atom starting with digit
             b'0'..=b'9' => self.read_number_or_atom(),
 
             // Atom (including NIL)
             _ if is_atom_char(byte) => self.read_atom(),
 
-            // Invalid character
-            _ => Err(self.error(&format!("Unexpected character: {byte:#04x}"))),
+            // Invalid character; skip it so a caller can resynchronize
+            _ => {
+                let err = self.error(&format!("Unexpected character: {byte:#04x}"));
+                self.advance();
+                Err(err)
+            }
         }
     }
 
@@ -347,6 +359,26 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Reads a 64-bit number.
+    ///
+    /// Mod-sequence values (RFC 7162) may exceed `u32::MAX`, so they cannot
+    /// go through [`Token::Number`].
+    pub fn read_number64(&mut self) -> Result<u64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.advance();
+        }
+
+        if start == self.pos {
+            return Err(self.error("Expected number"));
+        }
+
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.error("Number too large"))
+    }
+
     /// Reads an atom.
     pub fn read_atom_string(&mut self) -> Result<&'a str> {
         match self.next_token()? {
@@ -530,6 +562,16 @@ mod tests {
         assert!(!is_atom_char(b'{'));
     }
 
+    #[test]
+    fn test_literal8() {
+        let mut lexer = Lexer::new(b"~{3}\r\n\x00\xff\n");
+
+        match lexer.next_token().unwrap() {
+            Token::Literal(data) => assert_eq!(data, b"\x00\xff\n"),
+            other => panic!("Expected literal, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_literal_overflow_detection() {
         // Test that we detect overflow in literal size calculation
@@ -576,6 +618,23 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("too large"));
     }
 
+    #[test]
+    fn test_read_number64() {
+        let mut lexer = Lexer::new(b"18446744073709551615 7");
+        assert_eq!(lexer.read_number64().unwrap(), u64::MAX);
+        lexer.expect_space().unwrap();
+        assert_eq!(lexer.read_number64().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_read_number64_errors() {
+        let mut lexer = Lexer::new(b"abc");
+        assert!(lexer.read_number64().is_err());
+
+        let mut lexer = Lexer::new(b"99999999999999999999999");
+        assert!(lexer.read_number64().is_err());
+    }
+
     #[test]
     fn test_literal_plus() {
         let mut lexer = Lexer::new(b"{5+}\r\nhello");
@@ -611,6 +670,7 @@ mod tests {
 
 // Property-based tests using proptest
 #[cfg(test)]
+#[allow(clippy::uninlined_format_args)]
 mod proptests {
     use super::*;
     use proptest::prelude::*;
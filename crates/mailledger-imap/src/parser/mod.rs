@@ -32,6 +32,7 @@ pub mod response;
 
 pub use lexer::{Lexer, Token};
 pub use response::{
-    Address, BodyStructure, Envelope, FetchItem, Response, ResponseParser, StatusItem,
-    UntaggedResponse,
+    Address, AttachmentInfo, BodyDisposition, BodyStructure, Envelope, EsearchResult, FetchItem,
+    Greeting, Namespace, NamespaceEntry, Quota, QuotaResource, Response, ResponseParser,
+    StatusItem, ThreadNode, UntaggedResponse,
 };
@@ -1,11 +1,13 @@
 //! FETCH response parsing.
 
+use mailledger_mime::encoding::decode_rfc2047;
+
 use crate::parser::lexer::{Lexer, Token};
 use crate::types::Uid;
 use crate::{Error, Result};
 
 use super::parse_flag_list;
-use super::types::{Address, BodyStructure, Envelope, FetchItem};
+use super::types::{Address, BodyDisposition, BodyStructure, Envelope, FetchItem};
 
 /// Parses a FETCH response.
 pub fn parse_fetch_response(lexer: &mut Lexer<'_>) -> Result<Vec<FetchItem>> {
@@ -75,13 +77,32 @@ pub fn parse_fetch_response(lexer: &mut Lexer<'_>) -> Result<Vec<FetchItem>> {
                             data,
                         });
                     }
+                    "BINARY" => {
+                        let section = parse_part_section(lexer)?;
+                        lexer.expect_space()?;
+                        let data = match lexer.next_token()? {
+                            Token::Literal(d) => Some(d),
+                            Token::QuotedString(s) => Some(s.into_bytes()),
+                            _ => None,
+                        };
+                        items.push(FetchItem::Binary { section, data });
+                    }
+                    "BINARY.SIZE" => {
+                        let section = parse_part_section(lexer)?;
+                        lexer.expect_space()?;
+                        let size = lexer.read_number()?;
+                        items.push(FetchItem::BinarySize { section, size });
+                    }
                     "MODSEQ" => {
                         lexer.expect_space()?;
                         lexer.expect(Token::LParen)?;
-                        let n = u64::from(lexer.read_number()?);
+                        let n = lexer.read_number64()?;
                         lexer.expect(Token::RParen)?;
                         items.push(FetchItem::ModSeq(n));
                     }
+                    "X-GM-LABELS" | "X-GM-THRID" | "X-GM-MSGID" => {
+                        items.push(parse_gmail_item(&upper, lexer)?);
+                    }
                     _ => {
                         // Skip unknown fetch items
                         skip_fetch_item(lexer)?;
@@ -95,6 +116,39 @@ pub fn parse_fetch_response(lexer: &mut Lexer<'_>) -> Result<Vec<FetchItem>> {
     Ok(items)
 }
 
+/// Parses the value of a Gmail `X-GM-EXT-1` fetch item named `name`.
+fn parse_gmail_item(name: &str, lexer: &mut Lexer<'_>) -> Result<FetchItem> {
+    lexer.expect_space()?;
+    Ok(match name {
+        "X-GM-LABELS" => FetchItem::GmailLabels(parse_gmail_labels(lexer)?),
+        "X-GM-THRID" => FetchItem::GmailThreadId(lexer.read_number64()?),
+        _ => FetchItem::GmailMsgId(lexer.read_number64()?),
+    })
+}
+
+/// Parses an `X-GM-LABELS` list of atoms and strings.
+fn parse_gmail_labels(lexer: &mut Lexer<'_>) -> Result<Vec<String>> {
+    lexer.expect(Token::LParen)?;
+    let mut labels = Vec::new();
+    loop {
+        match lexer.next_token()? {
+            Token::RParen => break,
+            Token::Space => {}
+            Token::Atom(s) => labels.push(s.to_string()),
+            Token::QuotedString(s) => labels.push(s),
+            Token::Literal(data) => labels.push(String::from_utf8_lossy(&data).into_owned()),
+            Token::Number(n) => labels.push(n.to_string()),
+            token => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: format!("Unexpected token in X-GM-LABELS: {token:?}"),
+                });
+            }
+        }
+    }
+    Ok(labels)
+}
+
 /// Parses optional [section] and <origin> from a BODY fetch response.
 ///
 /// In IMAP FETCH responses, BODY can be followed by:
@@ -157,6 +211,24 @@ fn parse_body_section_and_origin(lexer: &mut Lexer<'_>) -> Result<(Option<String
     Ok((section, origin))
 }
 
+/// Parses the `[n.n...]` part specifier of a BINARY fetch response.
+fn parse_part_section(lexer: &mut Lexer<'_>) -> Result<Vec<u32>> {
+    let (section, _) = parse_body_section_and_origin(lexer)?;
+    let Some(section) = section else {
+        return Ok(Vec::new());
+    };
+
+    section
+        .split('.')
+        .map(|part| {
+            part.parse().map_err(|_| Error::Parse {
+                position: lexer.position(),
+                message: format!("invalid BINARY section: {section}"),
+            })
+        })
+        .collect()
+}
+
 /// Parses an envelope structure.
 pub fn parse_envelope(lexer: &mut Lexer<'_>) -> Result<Envelope> {
     lexer.expect(Token::LParen)?;
@@ -238,11 +310,11 @@ pub fn parse_address_list(lexer: &mut Lexer<'_>) -> Result<Vec<Address>> {
     }
 }
 
-/// Parses a single address.
+/// Parses a single address, decoding RFC 2047 encoded-words in the name.
 pub fn parse_address(lexer: &mut Lexer<'_>) -> Result<Address> {
     lexer.expect(Token::LParen)?;
 
-    let name = lexer.read_nstring()?;
+    let name = lexer.read_nstring()?.map(|name| decode_rfc2047(&name));
     lexer.expect_space()?;
 
     let adl = lexer.read_nstring()?;
@@ -268,6 +340,8 @@ pub fn parse_address(lexer: &mut Lexer<'_>) -> Result<Address> {
 /// BODYSTRUCTURE is a complex nested structure. This parser handles:
 /// - Single-part bodies: ("TYPE" "SUBTYPE" params id desc enc size ...)
 /// - Multipart bodies: ((part1) (part2) ... "SUBTYPE" ...)
+///
+/// Of the extension data only Content-Disposition is kept; the rest is skipped.
 pub fn parse_body_structure(lexer: &mut Lexer<'_>) -> Result<BodyStructure> {
     lexer.expect(Token::LParen)?;
 
@@ -332,7 +406,30 @@ pub fn parse_body_structure(lexer: &mut Lexer<'_>) -> Result<BodyStructure> {
             None
         };
 
-        // Skip remaining optional parameters (MD5, disposition, language, location)
+        // MESSAGE/RFC822 carries the nested envelope, body and line count
+        if media_type == "MESSAGE" && media_subtype == "RFC822" {
+            for _ in 0..3 {
+                if lexer.peek() != Some(b' ') {
+                    break;
+                }
+                lexer.advance();
+                skip_body_value(lexer)?;
+            }
+        }
+
+        // Extension data: MD5, then disposition
+        if lexer.peek() == Some(b' ') {
+            lexer.advance();
+            skip_body_value(lexer)?;
+        }
+        let disposition = if lexer.peek() == Some(b' ') {
+            lexer.advance();
+            parse_body_disposition(lexer)?
+        } else {
+            None
+        };
+
+        // Skip remaining optional parameters (language, location)
         skip_to_close_paren(lexer)?;
 
         if media_type == "TEXT" {
@@ -344,6 +441,7 @@ pub fn parse_body_structure(lexer: &mut Lexer<'_>) -> Result<BodyStructure> {
                 encoding,
                 size,
                 lines: lines.unwrap_or(0),
+                disposition,
             })
         } else {
             Ok(BodyStructure::Basic {
@@ -354,11 +452,42 @@ pub fn parse_body_structure(lexer: &mut Lexer<'_>) -> Result<BodyStructure> {
                 description,
                 encoding,
                 size,
+                disposition,
             })
         }
     }
 }
 
+/// Parses a body disposition: NIL or ("TYPE" (params)).
+fn parse_body_disposition(lexer: &mut Lexer<'_>) -> Result<Option<BodyDisposition>> {
+    match lexer.next_token()? {
+        Token::Nil => Ok(None),
+        Token::LParen => {
+            let kind = lexer.read_nstring()?.unwrap_or_default().to_lowercase();
+            lexer.expect_space()?;
+            let params = parse_body_params(lexer)?;
+            lexer.expect(Token::RParen)?;
+            Ok(Some(BodyDisposition { kind, params }))
+        }
+        token => Err(Error::Parse {
+            position: lexer.position(),
+            message: format!("Expected body disposition, got {token:?}"),
+        }),
+    }
+}
+
+/// Skips a single body value: an atom, number, string, literal or list.
+fn skip_body_value(lexer: &mut Lexer<'_>) -> Result<()> {
+    match lexer.next_token()? {
+        Token::LParen => skip_to_close_paren(lexer),
+        Token::Eof => Err(Error::Parse {
+            position: lexer.position(),
+            message: "Unexpected end of body structure".to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
 /// Parses body parameters (NIL or (key value key value ...)).
 fn parse_body_params(lexer: &mut Lexer<'_>) -> Result<Vec<(String, String)>> {
     match lexer.next_token()? {
@@ -391,27 +520,17 @@ fn parse_body_params(lexer: &mut Lexer<'_>) -> Result<Vec<(String, String)>> {
 }
 
 /// Skips to the closing parenthesis at the current nesting level.
+///
+/// Works on tokens so parentheses inside quoted strings and literals
+/// (e.g. a subject like `"Re: (draft)"`) don't throw off the depth.
 fn skip_to_close_paren(lexer: &mut Lexer<'_>) -> Result<()> {
     let mut depth = 1;
     while depth > 0 {
-        match lexer.peek() {
-            Some(b'(') => {
-                depth += 1;
-                lexer.advance();
-            }
-            Some(b')') => {
-                depth -= 1;
-                lexer.advance();
-            }
-            Some(b'{') => {
-                // Skip literal
-                lexer.advance();
-                let _ = lexer.next_token()?;
-            }
-            Some(_) => {
-                lexer.advance();
-            }
-            None => break,
+        match lexer.next_token()? {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::Eof => break,
+            _ => {}
         }
     }
     Ok(())
@@ -462,6 +581,7 @@ pub fn skip_fetch_item(lexer: &mut Lexer<'_>) -> Result<()> {
     clippy::similar_names
 )]
 mod tests {
+    use super::super::types::AttachmentInfo;
     use super::*;
 
     #[test]
@@ -508,6 +628,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_fetch_gmail_extensions() {
+        let data = b"(X-GM-THRID 1278455344230334865 X-GM-MSGID 1278455344230334866 \
+                     X-GM-LABELS (\\Inbox \\Important \"Project X\" receipts) UID 7)";
+        let mut lexer = Lexer::new(data);
+        let items = parse_fetch_response(&mut lexer).unwrap();
+
+        assert_eq!(
+            items,
+            vec![
+                FetchItem::GmailThreadId(1278455344230334865),
+                FetchItem::GmailMsgId(1278455344230334866),
+                FetchItem::GmailLabels(vec![
+                    "\\Inbox".to_string(),
+                    "\\Important".to_string(),
+                    "Project X".to_string(),
+                    "receipts".to_string(),
+                ]),
+                FetchItem::Uid(Uid::new(7).unwrap()),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_fetch_modseq() {
         let data = b"(MODSEQ (12345))";
@@ -522,6 +665,144 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_fetch_binary_literal8() {
+        let data = b"(UID 7 BINARY[2.1] ~{4}\r\n\x00\x01\r\n BINARY.SIZE[2.1] 4)";
+        let mut lexer = Lexer::new(data);
+        let items = parse_fetch_response(&mut lexer).unwrap();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            items[1],
+            FetchItem::Binary {
+                section: vec![2, 1],
+                data: Some(b"\x00\x01\r\n".to_vec()),
+            }
+        );
+        assert_eq!(
+            items[2],
+            FetchItem::BinarySize {
+                section: vec![2, 1],
+                size: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_fetch_binary_nil() {
+        let data = b"(BINARY[3] NIL)";
+        let mut lexer = Lexer::new(data);
+        let items = parse_fetch_response(&mut lexer).unwrap();
+
+        assert_eq!(
+            items,
+            vec![FetchItem::Binary {
+                section: vec![3],
+                data: None,
+            }]
+        );
+    }
+
+    mod body_structure_tests {
+        use super::*;
+
+        fn parse(data: &[u8]) -> BodyStructure {
+            parse_body_structure(&mut Lexer::new(data)).unwrap()
+        }
+
+        #[test]
+        fn single_part_is_part_one() {
+            let body =
+                parse(b"(\"TEXT\" \"PLAIN\" (\"CHARSET\" \"us-ascii\") NIL NIL \"7BIT\" 42 2)");
+            let leaves = body.leaves();
+            assert_eq!(leaves.len(), 1);
+            assert_eq!(leaves[0].0, "1");
+            assert_eq!(body.content_type(), "text/plain");
+            assert!(body.attachments().is_empty());
+        }
+
+        #[test]
+        fn gmail_mixed_with_alternative_and_pdf() {
+            let body = parse(
+                b"(((\"TEXT\" \"PLAIN\" (\"CHARSET\" \"UTF-8\") NIL NIL \"QUOTED-PRINTABLE\" 120 4 NIL NIL NIL)\
+                  (\"TEXT\" \"HTML\" (\"CHARSET\" \"UTF-8\") NIL NIL \"QUOTED-PRINTABLE\" 410 9 NIL NIL NIL) \
+                  \"ALTERNATIVE\" (\"BOUNDARY\" \"000000000000a1b2c3\") NIL NIL)\
+                  (\"APPLICATION\" \"PDF\" (\"NAME\" \"Invoice (March).pdf\") \"<f_lx1>\" NIL \"BASE64\" 53462 NIL \
+                  (\"ATTACHMENT\" (\"FILENAME\" \"Invoice (March).pdf\")) NIL) \
+                  \"MIXED\" (\"BOUNDARY\" \"000000000000d4e5f6\") NIL NIL)",
+            );
+
+            let parts: Vec<_> = body.leaves().into_iter().map(|(n, _)| n).collect();
+            assert_eq!(parts, vec!["1.1", "1.2", "2"]);
+
+            assert_eq!(
+                body.attachments(),
+                vec![AttachmentInfo {
+                    part_number: "2".to_string(),
+                    filename: Some("Invoice (March).pdf".to_string()),
+                    content_type: "application/pdf".to_string(),
+                    encoding: "base64".to_string(),
+                    size: 53462,
+                }]
+            );
+        }
+
+        #[test]
+        fn dovecot_related_inline_image_is_not_attachment() {
+            let body = parse(
+                b"((\"text\" \"html\" (\"charset\" \"utf-8\") NIL NIL \"7bit\" 812 20 NIL NIL NIL NIL)\
+                  (\"image\" \"png\" (\"name\" \"logo.png\") \"<logo@example.com>\" NIL \"base64\" 4216 NIL \
+                  (\"inline\" (\"filename\" \"logo.png\")) NIL NIL) \
+                  \"related\" (\"boundary\" \"rel-b\" \"type\" \"text/html\") NIL NIL NIL)",
+            );
+
+            let (part_number, image) = &body.leaves()[1];
+            assert_eq!(part_number, "2");
+            assert_eq!(image.filename(), Some("logo.png"));
+            assert!(image.disposition().unwrap().is_inline());
+            assert!(body.attachments().is_empty());
+        }
+
+        #[test]
+        fn dovecot_mixed_with_related_and_forwarded_message() {
+            let body = parse(
+                b"(((\"text\" \"html\" (\"charset\" \"utf-8\") NIL NIL \"7bit\" 812 20 NIL NIL NIL NIL)\
+                  (\"image\" \"png\" NIL \"<logo@example.com>\" NIL \"base64\" 4216 NIL (\"inline\" NIL) NIL NIL) \
+                  \"related\" (\"boundary\" \"rel-b\") NIL NIL NIL)\
+                  (\"message\" \"rfc822\" NIL NIL NIL \"7bit\" 1200 \
+                  (\"Mon, 1 Jan 2024 10:00:00 +0000\" \"Re: (draft)\" ((\"Bob\" NIL \"bob\" \"example.com\")) \
+                  NIL NIL NIL NIL NIL NIL \"<a@example.com>\") \
+                  (\"text\" \"plain\" (\"charset\" \"us-ascii\") NIL NIL \"7bit\" 20 1 NIL NIL NIL NIL) 30 NIL \
+                  (\"attachment\" (\"filename\" \"fwd.eml\")) NIL NIL)\
+                  (\"application\" \"octet-stream\" NIL NIL NIL \"base64\" 900 NIL (\"attachment\" NIL) NIL NIL) \
+                  \"mixed\" (\"boundary\" \"mix\") NIL NIL NIL)",
+            );
+
+            let parts: Vec<_> = body.leaves().into_iter().map(|(n, _)| n).collect();
+            assert_eq!(parts, vec!["1.1", "1.2", "2", "3"]);
+
+            let attachments = body.attachments();
+            assert_eq!(attachments.len(), 2);
+            assert_eq!(attachments[0].part_number, "2");
+            assert_eq!(attachments[0].filename.as_deref(), Some("fwd.eml"));
+            assert_eq!(attachments[0].content_type, "message/rfc822");
+            assert_eq!(attachments[1].part_number, "3");
+            assert_eq!(attachments[1].filename, None);
+            assert_eq!(attachments[1].size, 900);
+        }
+
+        #[test]
+        fn name_param_without_disposition_is_attachment() {
+            let body = parse(
+                b"((\"TEXT\" \"PLAIN\" NIL NIL NIL \"7BIT\" 10 1)\
+                  (\"APPLICATION\" \"ZIP\" (\"NAME\" \"a.zip\") NIL NIL \"BASE64\" 300) \"MIXED\")",
+            );
+            let attachments = body.attachments();
+            assert_eq!(attachments.len(), 1);
+            assert_eq!(attachments[0].filename.as_deref(), Some("a.zip"));
+        }
+    }
+
     #[test]
     fn test_parse_body_section_and_origin() {
         let data = b"[TEXT]<100>";
@@ -543,4 +824,70 @@ mod tests {
         assert_eq!(envelope.in_reply_to, Some("in-reply-to".to_string()));
         assert_eq!(envelope.message_id, Some("message-id".to_string()));
     }
+
+    #[test]
+    fn test_parse_envelope_addresses() {
+        let data = b"(\"Mon, 1 Jan 2024 12:00:00 +0000\" \"Hi\" \
+            ((\"=?UTF-8?B?SsO8cmdlbg==?=\" NIL \"jurgen\" \"example.com\")) \
+            ((NIL NIL \"sender\" \"example.com\")) NIL \
+            ((\"Bob\" NIL \"bob\" \"example.org\") (NIL NIL \"carol\" \"example.net\")) \
+            NIL NIL NIL \"<id@example.com>\")";
+        let mut lexer = Lexer::new(data);
+        let envelope = parse_envelope(&mut lexer).unwrap();
+
+        assert_eq!(envelope.from[0].name.as_deref(), Some("J\u{fc}rgen"));
+        assert_eq!(
+            envelope.from_display().as_deref(),
+            Some("J\u{fc}rgen <jurgen@example.com>")
+        );
+        assert_eq!(
+            envelope.sender[0].email().as_deref(),
+            Some("sender@example.com")
+        );
+        assert!(envelope.reply_to.is_empty());
+        assert_eq!(envelope.to.len(), 2);
+        assert_eq!(
+            envelope.to[1].display().as_deref(),
+            Some("carol@example.net")
+        );
+        assert!(envelope.cc.is_empty());
+        assert!(envelope.bcc.is_empty());
+        assert!(envelope.in_reply_to.is_none());
+        assert_eq!(envelope.message_id.as_deref(), Some("<id@example.com>"));
+    }
+
+    #[test]
+    fn test_parse_address_list_with_group() {
+        let data = b"((NIL NIL \"team\" NIL)(\"Ann\" NIL \"ann\" \"example.com\")\
+            (NIL NIL NIL NIL)(NIL NIL \"undisclosed-recipients\" NIL)(NIL NIL NIL NIL))";
+        let mut lexer = Lexer::new(data);
+        let addresses = parse_address_list(&mut lexer).unwrap();
+
+        assert_eq!(addresses.len(), 5);
+        assert_eq!(addresses[0].group_name(), Some("team"));
+        assert_eq!(addresses[0].display(), None);
+        assert_eq!(
+            addresses[1].display().as_deref(),
+            Some("Ann <ann@example.com>")
+        );
+        assert!(addresses[2].is_group_end());
+        assert_eq!(addresses[3].group_name(), Some("undisclosed-recipients"));
+        assert!(addresses[4].is_group_end());
+    }
+
+    #[test]
+    fn test_from_display_skips_group_markers() {
+        let data = b"(NIL NIL ((NIL NIL \"list\" NIL)\
+            (\"Doe, John\" NIL \"john\" \"example.com\")(NIL NIL NIL NIL)) \
+            NIL NIL NIL NIL NIL NIL NIL)";
+        let mut lexer = Lexer::new(data);
+        let envelope = parse_envelope(&mut lexer).unwrap();
+
+        assert!(envelope.date.is_none());
+        assert!(envelope.subject.is_none());
+        assert_eq!(
+            envelope.from_display().as_deref(),
+            Some("\"Doe, John\" <john@example.com>")
+        );
+    }
 }
@@ -1,13 +1,53 @@
 //! Parser helper functions.
 
+use std::collections::HashMap;
+
 use crate::parser::lexer::{Lexer, Token};
 use crate::types::{
     Capability, Flag, Flags, ListResponse, Mailbox, MailboxAttribute, ResponseCode, SeqNum, Uid,
-    UidValidity,
+    UidSet, UidValidity,
 };
 use crate::{Error, Result};
 
-use super::types::StatusItem;
+use super::types::{
+    EsearchResult, Namespace, NamespaceEntry, Quota, QuotaResource, StatusItem, ThreadNode,
+};
+
+/// Reads a sequence set such as `3:5,8` as a [`UidSet`].
+fn read_uid_set(lexer: &mut Lexer<'_>) -> Result<UidSet> {
+    let set = match lexer.next_token()? {
+        Token::Number(n) => n.to_string(),
+        Token::Atom(s) => s.to_string(),
+        token => {
+            return Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Expected sequence set, got {token:?}"),
+            });
+        }
+    };
+    UidSet::parse(&set).ok_or_else(|| Error::Parse {
+        position: lexer.position(),
+        message: format!("Invalid sequence set: {set}"),
+    })
+}
+
+/// Reads the optional ` (charset ...)` list of a BADCHARSET code.
+fn read_charset_list(lexer: &mut Lexer<'_>) -> Result<Vec<String>> {
+    let mut charsets = Vec::new();
+    if lexer.peek() == Some(b' ') {
+        lexer.expect_space()?;
+        lexer.expect(Token::LParen)?;
+        loop {
+            match lexer.next_token()? {
+                Token::RParen | Token::Eof => break,
+                Token::Atom(s) => charsets.push(s.to_string()),
+                Token::QuotedString(s) => charsets.push(s),
+                _ => {}
+            }
+        }
+    }
+    Ok(charsets)
+}
 
 /// Parses a response code.
 pub fn parse_response_code(lexer: &mut Lexer<'_>) -> Result<ResponseCode> {
@@ -22,6 +62,9 @@ pub fn parse_response_code(lexer: &mut Lexer<'_>) -> Result<ResponseCode> {
         "READ-ONLY" => ResponseCode::ReadOnly,
         "READ-WRITE" => ResponseCode::ReadWrite,
         "TRYCREATE" => ResponseCode::TryCreate,
+        "OVERQUOTA" => ResponseCode::OverQuota,
+        "UNAVAILABLE" => ResponseCode::Unavailable,
+        "AUTHENTICATIONFAILED" => ResponseCode::AuthenticationFailed,
         "NOMODSEQ" => ResponseCode::NoModSeq,
         "UIDNEXT" => {
             lexer.expect_space()?;
@@ -52,13 +95,42 @@ pub fn parse_response_code(lexer: &mut Lexer<'_>) -> Result<ResponseCode> {
         }
         "HIGHESTMODSEQ" => {
             lexer.expect_space()?;
-            let n = u64::from(lexer.read_number()?);
+            let n = lexer.read_number64()?;
             ResponseCode::HighestModSeq(n)
         }
+        "APPENDUID" => {
+            lexer.expect_space()?;
+            let n = lexer.read_number()?;
+            let validity = UidValidity::new(n).ok_or_else(|| Error::Parse {
+                position: lexer.position(),
+                message: "Invalid UIDVALIDITY 0".to_string(),
+            })?;
+            lexer.expect_space()?;
+            let uids = read_uid_set(lexer)?;
+            ResponseCode::AppendUid { validity, uids }
+        }
+        "COPYUID" => {
+            lexer.expect_space()?;
+            let n = lexer.read_number()?;
+            let validity = UidValidity::new(n).ok_or_else(|| Error::Parse {
+                position: lexer.position(),
+                message: "Invalid UIDVALIDITY 0".to_string(),
+            })?;
+            lexer.expect_space()?;
+            let source = read_uid_set(lexer)?;
+            lexer.expect_space()?;
+            let dest = read_uid_set(lexer)?;
+            ResponseCode::CopyUid {
+                validity,
+                source,
+                dest,
+            }
+        }
         "CAPABILITY" => {
             let caps = parse_capability_data(lexer)?;
             ResponseCode::Capability(caps)
         }
+        "BADCHARSET" => ResponseCode::BadCharset(read_charset_list(lexer)?),
         "PERMANENTFLAGS" => {
             lexer.expect_space()?;
             let flags = parse_flag_list(lexer)?;
@@ -158,10 +230,38 @@ pub fn parse_list_response(lexer: &mut Lexer<'_>) -> Result<ListResponse> {
     // Parse mailbox name
     let mailbox_name = lexer.read_astring()?;
 
+    // LIST-EXTENDED data, e.g. ("CHILDINFO" ("SUBSCRIBED"))
+    let mut child_info = Vec::new();
+    if lexer.peek() == Some(b' ') && lexer.peek_at(1) == Some(b'(') {
+        lexer.expect_space()?;
+        lexer.expect(Token::LParen)?;
+        loop {
+            let tag = match lexer.next_token()? {
+                Token::RParen | Token::Eof => break,
+                Token::Space => continue,
+                Token::Atom(s) => s.to_string(),
+                Token::QuotedString(s) => s,
+                token => {
+                    return Err(Error::Parse {
+                        position: lexer.position(),
+                        message: format!("Unexpected token in LIST extended data: {token:?}"),
+                    });
+                }
+            };
+            lexer.expect_space()?;
+            let values = read_extended_value(lexer)?;
+            if tag.eq_ignore_ascii_case("CHILDINFO") {
+                child_info = values;
+            }
+        }
+    }
+
     Ok(ListResponse {
         attributes,
         delimiter,
-        mailbox: Mailbox::new(mailbox_name),
+        mailbox: Mailbox::from_wire(mailbox_name.as_bytes()),
+        child_info,
+        status: None,
     })
 }
 
@@ -181,6 +281,192 @@ pub fn parse_search_response(lexer: &mut Lexer<'_>) -> Result<Vec<SeqNum>> {
     Ok(nums)
 }
 
+/// Parses an ESEARCH response.
+///
+/// Format: `[SP "(" "TAG" SP tag ")"] [SP "UID"] *(SP name SP value)`.
+/// Unknown return data (e.g. MODSEQ) is skipped.
+pub fn parse_esearch_response(lexer: &mut Lexer<'_>) -> Result<EsearchResult> {
+    let mut result = EsearchResult::default();
+
+    lexer.skip_spaces();
+    if lexer.peek() == Some(b'(') {
+        lexer.expect(Token::LParen)?;
+        let name = lexer.read_atom_string()?;
+        if !name.eq_ignore_ascii_case("TAG") {
+            return Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Expected TAG in ESEARCH correlator, got {name}"),
+            });
+        }
+        lexer.expect_space()?;
+        result.tag = Some(lexer.read_astring()?);
+        lexer.expect(Token::RParen)?;
+    }
+
+    loop {
+        lexer.skip_spaces();
+        let name = match lexer.next_token()? {
+            Token::Atom(name) => name.to_uppercase(),
+            Token::Crlf | Token::Eof => break,
+            token => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: format!("Unexpected token in ESEARCH: {token:?}"),
+                });
+            }
+        };
+
+        if name == "UID" {
+            result.uid = true;
+            continue;
+        }
+
+        lexer.expect_space()?;
+        match name.as_str() {
+            "MIN" => result.min = Some(lexer.read_number()?),
+            "MAX" => result.max = Some(lexer.read_number()?),
+            "COUNT" => result.count = Some(lexer.read_number()?),
+            "ALL" => result.all = Some(read_uid_set(lexer)?),
+            _ => skip_esearch_value(lexer)?,
+        }
+    }
+
+    Ok(result)
+}
+
+/// Skips a single ESEARCH return value (atom, number or parenthesized list).
+fn skip_esearch_value(lexer: &mut Lexer<'_>) -> Result<()> {
+    let mut depth = 0usize;
+    loop {
+        match lexer.next_token()? {
+            Token::LParen => depth += 1,
+            Token::RParen => depth = depth.saturating_sub(1),
+            Token::Crlf | Token::Eof => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: "Unterminated ESEARCH value".to_string(),
+                });
+            }
+            _ => {}
+        }
+        if depth == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Maximum nesting depth accepted in a THREAD response.
+const MAX_THREAD_DEPTH: usize = 256;
+
+/// Parses a THREAD response into one root node per thread.
+pub fn parse_thread_response(lexer: &mut Lexer<'_>) -> Result<Vec<ThreadNode>> {
+    let mut threads = Vec::new();
+
+    lexer.skip_spaces();
+    while lexer.peek() == Some(b'(') {
+        threads.push(parse_thread_list(lexer, 0)?);
+        lexer.skip_spaces();
+    }
+
+    Ok(threads)
+}
+
+/// Parses one parenthesized `thread-list`.
+///
+/// `(3 6 (4 23)(44 7 96))` is a chain 3 -> 6 whose last member has two
+/// nested sub-threads. A list with no leading members yields a placeholder
+/// node without an id.
+fn parse_thread_list(lexer: &mut Lexer<'_>, depth: usize) -> Result<ThreadNode> {
+    if depth >= MAX_THREAD_DEPTH {
+        return Err(Error::Parse {
+            position: lexer.position(),
+            message: "THREAD response nested too deeply".to_string(),
+        });
+    }
+
+    lexer.expect(Token::LParen)?;
+
+    let mut members = Vec::new();
+    let mut nested = Vec::new();
+
+    loop {
+        match lexer.peek() {
+            Some(b')') => {
+                lexer.advance();
+                break;
+            }
+            Some(b' ') => {
+                lexer.advance();
+            }
+            Some(b'(') => nested.push(parse_thread_list(lexer, depth + 1)?),
+            Some(_) if nested.is_empty() => {
+                let n = lexer.read_number()?;
+                let id = Uid::new(n).ok_or_else(|| Error::Parse {
+                    position: lexer.position(),
+                    message: "Invalid message number 0 in THREAD".to_string(),
+                })?;
+                members.push(id);
+            }
+            Some(_) => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: "Unexpected data after nested THREAD list".to_string(),
+                });
+            }
+            None => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: "Unterminated THREAD list".to_string(),
+                });
+            }
+        }
+    }
+
+    // Fold the member chain from the end so each member parents the next.
+    let mut members = members.into_iter().rev();
+    let Some(last) = members.next() else {
+        return Ok(ThreadNode {
+            id: None,
+            children: nested,
+        });
+    };
+
+    let mut node = ThreadNode {
+        id: Some(last),
+        children: nested,
+    };
+    for id in members {
+        node = ThreadNode {
+            id: Some(id),
+            children: vec![node],
+        };
+    }
+
+    Ok(node)
+}
+
+/// Reads a LIST-EXTENDED value, flattening nested lists into strings.
+fn read_extended_value(lexer: &mut Lexer<'_>) -> Result<Vec<String>> {
+    let mut values = Vec::new();
+    let mut depth = 0;
+    loop {
+        match lexer.next_token()? {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::Atom(s) => values.push(s.to_string()),
+            Token::QuotedString(s) => values.push(s),
+            Token::Number(n) => values.push(n.to_string()),
+            Token::Literal(bytes) => values.push(String::from_utf8_lossy(&bytes).into_owned()),
+            Token::Eof => break,
+            _ => {}
+        }
+        if depth <= 0 {
+            break;
+        }
+    }
+    Ok(values)
+}
+
 /// Parses a STATUS response.
 pub fn parse_status_response(lexer: &mut Lexer<'_>) -> Result<(Mailbox, Vec<StatusItem>)> {
     let mailbox_name = lexer.read_astring()?;
@@ -193,6 +479,10 @@ pub fn parse_status_response(lexer: &mut Lexer<'_>) -> Result<(Mailbox, Vec<Stat
         match lexer.next_token()? {
             Token::RParen => break,
             Token::Space => continue,
+            Token::Atom(name) if name.eq_ignore_ascii_case("HIGHESTMODSEQ") => {
+                lexer.expect_space()?;
+                items.push(StatusItem::HighestModSeq(lexer.read_number64()?));
+            }
             Token::Atom(name) => {
                 lexer.expect_space()?;
                 let value = lexer.read_number()?;
@@ -215,7 +505,6 @@ pub fn parse_status_response(lexer: &mut Lexer<'_>) -> Result<(Mailbox, Vec<Stat
                         }
                     }
                     "UNSEEN" => StatusItem::Unseen(value),
-                    "HIGHESTMODSEQ" => StatusItem::HighestModSeq(u64::from(value)),
                     _ => continue,
                 };
                 items.push(item);
@@ -224,7 +513,199 @@ pub fn parse_status_response(lexer: &mut Lexer<'_>) -> Result<(Mailbox, Vec<Stat
         }
     }
 
-    Ok((Mailbox::new(mailbox_name), items))
+    Ok((Mailbox::from_wire(mailbox_name.as_bytes()), items))
+}
+
+/// Parses a QUOTAROOT response: `mailbox *(SP root)`.
+pub fn parse_quota_root_response(lexer: &mut Lexer<'_>) -> Result<(Mailbox, Vec<String>)> {
+    let mailbox_name = lexer.read_astring()?;
+    let mut roots = Vec::new();
+    while lexer.peek() == Some(b' ') {
+        lexer.expect_space()?;
+        roots.push(lexer.read_astring()?);
+    }
+    Ok((Mailbox::from_wire(mailbox_name.as_bytes()), roots))
+}
+
+/// Parses a QUOTA response: `root SP "(" *(name SP usage SP limit) ")"`.
+pub fn parse_quota_response(lexer: &mut Lexer<'_>) -> Result<Quota> {
+    let root = lexer.read_astring()?;
+    lexer.expect_space()?;
+    lexer.expect(Token::LParen)?;
+
+    let mut resources = Vec::new();
+    loop {
+        match lexer.next_token()? {
+            Token::RParen => break,
+            Token::Space => continue,
+            Token::Atom(name) => {
+                lexer.expect_space()?;
+                let usage = lexer.read_number64()?;
+                lexer.expect_space()?;
+                let limit = lexer.read_number64()?;
+                resources.push(QuotaResource {
+                    name: name.to_uppercase(),
+                    usage,
+                    limit,
+                });
+            }
+            token => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: format!("Unexpected token in QUOTA: {token:?}"),
+                });
+            }
+        }
+    }
+
+    Ok(Quota { root, resources })
+}
+
+/// Parses a METADATA response: `mailbox SP "(" entry SP value *(SP entry
+/// SP value) ")"`, where a `NIL` value means the entry isn't set.
+pub fn parse_metadata_response(
+    lexer: &mut Lexer<'_>,
+) -> Result<(Mailbox, HashMap<String, Option<String>>)> {
+    let mailbox_name = lexer.read_astring()?;
+    lexer.expect_space()?;
+    lexer.expect(Token::LParen)?;
+
+    let mut entries = HashMap::new();
+    loop {
+        match lexer.peek() {
+            Some(b')') => {
+                lexer.advance();
+                break;
+            }
+            Some(b' ') => {
+                lexer.advance();
+            }
+            _ => {
+                let entry = lexer.read_astring()?;
+                lexer.expect_space()?;
+                let value = lexer.read_nstring()?;
+                entries.insert(entry, value);
+            }
+        }
+    }
+
+    Ok((Mailbox::from_wire(mailbox_name.as_bytes()), entries))
+}
+
+/// Parses a NAMESPACE response: three namespace lists, each `NIL` or a
+/// parenthesized list of `(prefix delimiter *extension)`.
+pub fn parse_namespace_response(lexer: &mut Lexer<'_>) -> Result<Namespace> {
+    let personal = parse_namespace_list(lexer)?;
+    lexer.expect_space()?;
+    let other_users = parse_namespace_list(lexer)?;
+    lexer.expect_space()?;
+    let shared = parse_namespace_list(lexer)?;
+    Ok(Namespace {
+        personal,
+        other_users,
+        shared,
+    })
+}
+
+/// Parses one namespace list, returning an empty list for `NIL`.
+fn parse_namespace_list(lexer: &mut Lexer<'_>) -> Result<Vec<NamespaceEntry>> {
+    match lexer.next_token()? {
+        Token::Nil => return Ok(Vec::new()),
+        Token::LParen => {}
+        token => {
+            return Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Expected namespace list, got {token:?}"),
+            });
+        }
+    }
+
+    let mut entries = Vec::new();
+    loop {
+        match lexer.next_token()? {
+            Token::RParen => break,
+            Token::Space => continue,
+            Token::LParen => entries.push(parse_namespace_entry(lexer)?),
+            token => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: format!("Unexpected token in NAMESPACE: {token:?}"),
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Parses a namespace entry after its opening parenthesis.
+fn parse_namespace_entry(lexer: &mut Lexer<'_>) -> Result<NamespaceEntry> {
+    let prefix = lexer.read_astring()?;
+    lexer.expect_space()?;
+    let delimiter = match lexer.next_token()? {
+        Token::Nil => None,
+        Token::QuotedString(s) => s.chars().next(),
+        token => {
+            return Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Expected namespace delimiter, got {token:?}"),
+            });
+        }
+    };
+
+    // Skip extensions such as "X-PARAM" ("FLAG1" "FLAG2")
+    while lexer.peek() == Some(b' ') {
+        lexer.expect_space()?;
+        lexer.read_astring()?;
+        lexer.expect_space()?;
+        read_extended_value(lexer)?;
+    }
+    lexer.expect(Token::RParen)?;
+
+    Ok(NamespaceEntry { prefix, delimiter })
+}
+
+/// Parses an ID response: `NIL` or a parenthesized list of field-value
+/// pairs. Fields with a `NIL` value are left out.
+pub fn parse_id_response(lexer: &mut Lexer<'_>) -> Result<HashMap<String, String>> {
+    let mut fields = HashMap::new();
+    match lexer.next_token()? {
+        Token::Nil => return Ok(fields),
+        Token::LParen => {}
+        token => {
+            return Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Expected ID parameter list, got {token:?}"),
+            });
+        }
+    }
+
+    loop {
+        lexer.skip_spaces();
+        if lexer.peek() == Some(b')') {
+            lexer.advance();
+            break;
+        }
+        let field = lexer.read_astring()?;
+        lexer.expect_space()?;
+        // Be lenient about servers that send atoms where strings belong
+        let value = match lexer.next_token()? {
+            Token::Nil => None,
+            Token::Atom(s) => Some(s.to_string()),
+            Token::Number(n) => Some(n.to_string()),
+            Token::QuotedString(s) => Some(s),
+            Token::Literal(data) => Some(String::from_utf8_lossy(&data).into_owned()),
+            token => {
+                return Err(Error::Parse {
+                    position: lexer.position(),
+                    message: format!("Unexpected token in ID: {token:?}"),
+                });
+            }
+        };
+        if let Some(value) = value {
+            fields.insert(field, value);
+        }
+    }
+    Ok(fields)
 }
 
 /// Reads text until CRLF.
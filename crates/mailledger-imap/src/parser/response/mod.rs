@@ -13,15 +13,21 @@ mod fetch;
 mod helpers;
 mod types;
 
-pub use types::{Address, BodyStructure, Envelope, FetchItem, StatusItem, UntaggedResponse};
+pub use types::{
+    Address, AttachmentInfo, BodyDisposition, BodyStructure, Envelope, EsearchResult, FetchItem,
+    Greeting, Namespace, NamespaceEntry, Quota, QuotaResource, StatusItem, ThreadNode,
+    UntaggedResponse,
+};
 
 use crate::parser::lexer::{Lexer, Token};
 use crate::types::{ResponseCode, SeqNum, Status, Tag};
 use crate::{Error, Result};
 
 use helpers::{
-    parse_capability_data, parse_list_response, parse_response_code, parse_search_response,
-    parse_status_response, read_text_until_crlf,
+    parse_capability_data, parse_esearch_response, parse_id_response, parse_list_response,
+    parse_metadata_response, parse_namespace_response, parse_quota_response,
+    parse_quota_root_response, parse_response_code, parse_search_response, parse_status_response,
+    parse_thread_response, read_text_until_crlf,
 };
 
 /// A parsed IMAP response.
@@ -66,6 +72,36 @@ impl ResponseParser {
         }
     }
 
+    /// Parses the server greeting: an untagged OK, PREAUTH or BYE.
+    ///
+    /// Capabilities in a `[CAPABILITY ...]` code are copied into
+    /// [`Greeting::capabilities`].
+    pub fn parse_greeting(input: &[u8]) -> Result<Greeting> {
+        let (status, code, text) = match Self::parse(input)? {
+            Response::Untagged(UntaggedResponse::Ok { code, text }) => (Status::Ok, code, text),
+            Response::Untagged(UntaggedResponse::PreAuth { code, text }) => {
+                (Status::PreAuth, code, text)
+            }
+            Response::Untagged(UntaggedResponse::Bye { code, text }) => (Status::Bye, code, text),
+            response => {
+                return Err(Error::Protocol(format!(
+                    "expected server greeting, got {response:?}"
+                )));
+            }
+        };
+
+        let capabilities = match &code {
+            Some(ResponseCode::Capability(caps)) => caps.clone(),
+            _ => Vec::new(),
+        };
+        Ok(Greeting {
+            status,
+            code,
+            capabilities,
+            text,
+        })
+    }
+
     /// Parses a tagged response.
     fn parse_tagged(lexer: &mut Lexer<'_>, tag_str: &str) -> Result<Response> {
         lexer.expect_space()?;
@@ -90,99 +126,153 @@ impl ResponseParser {
         let token = lexer.next_token()?;
 
         match token {
-            Token::Atom(s) => {
-                let upper = s.to_uppercase();
-                match upper.as_str() {
-                    "OK" => {
-                        lexer.expect_space()?;
-                        let (code, text) = Self::parse_resp_text(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Ok { code, text }))
-                    }
-                    "NO" => {
-                        lexer.expect_space()?;
-                        let (code, text) = Self::parse_resp_text(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::No { code, text }))
-                    }
-                    "BAD" => {
-                        lexer.expect_space()?;
-                        let (code, text) = Self::parse_resp_text(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Bad { code, text }))
-                    }
-                    "PREAUTH" => {
-                        lexer.expect_space()?;
-                        let (code, text) = Self::parse_resp_text(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::PreAuth { code, text }))
-                    }
-                    "BYE" => {
-                        lexer.expect_space()?;
-                        let (code, text) = Self::parse_resp_text(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Bye { code, text }))
-                    }
-                    "CAPABILITY" => {
-                        let caps = parse_capability_data(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Capability(caps)))
-                    }
-                    "FLAGS" => {
-                        lexer.expect_space()?;
-                        let flags = parse_flag_list(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Flags(flags)))
-                    }
-                    "LIST" => {
-                        lexer.expect_space()?;
-                        let list = parse_list_response(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::List(list)))
-                    }
-                    "SEARCH" => {
-                        let nums = parse_search_response(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Search(nums)))
-                    }
-                    "STATUS" => {
-                        lexer.expect_space()?;
-                        let (mailbox, items) = parse_status_response(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Status {
-                            mailbox,
-                            items,
-                        }))
-                    }
-                    _ => Err(Error::Parse {
-                        position: lexer.position(),
-                        message: format!("Unknown untagged response: {s}"),
-                    }),
-                }
+            Token::Atom(s) => Self::parse_untagged_keyword(lexer, s),
+            Token::Number(n) => Self::parse_message_data(lexer, n),
+            _ => Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Unexpected token in untagged response: {token:?}"),
+            }),
+        }
+    }
+
+    /// Parses an untagged response that starts with a keyword.
+    #[allow(clippy::too_many_lines)]
+    fn parse_untagged_keyword(lexer: &mut Lexer<'_>, s: &str) -> Result<Response> {
+        let upper = s.to_uppercase();
+        match upper.as_str() {
+            "OK" => {
+                lexer.expect_space()?;
+                let (code, text) = Self::parse_resp_text(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Ok { code, text }))
             }
-            Token::Number(n) => {
+            "NO" => {
                 lexer.expect_space()?;
-                let keyword = lexer.read_atom_string()?;
-                let upper = keyword.to_uppercase();
-
-                match upper.as_str() {
-                    "EXISTS" => Ok(Response::Untagged(UntaggedResponse::Exists(n))),
-                    "RECENT" => Ok(Response::Untagged(UntaggedResponse::Recent(n))),
-                    "EXPUNGE" => {
-                        let seq = SeqNum::new(n).ok_or_else(|| Error::Parse {
-                            position: lexer.position(),
-                            message: "Invalid sequence number 0".to_string(),
-                        })?;
-                        Ok(Response::Untagged(UntaggedResponse::Expunge(seq)))
-                    }
-                    "FETCH" => {
-                        let seq = SeqNum::new(n).ok_or_else(|| Error::Parse {
-                            position: lexer.position(),
-                            message: "Invalid sequence number 0".to_string(),
-                        })?;
-                        lexer.expect_space()?;
-                        let items = fetch::parse_fetch_response(lexer)?;
-                        Ok(Response::Untagged(UntaggedResponse::Fetch { seq, items }))
-                    }
-                    _ => Err(Error::Parse {
-                        position: lexer.position(),
-                        message: format!("Unknown message data: {keyword}"),
-                    }),
-                }
+                let (code, text) = Self::parse_resp_text(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::No { code, text }))
+            }
+            "BAD" => {
+                lexer.expect_space()?;
+                let (code, text) = Self::parse_resp_text(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Bad { code, text }))
+            }
+            "PREAUTH" => {
+                lexer.expect_space()?;
+                let (code, text) = Self::parse_resp_text(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::PreAuth { code, text }))
+            }
+            "BYE" => {
+                lexer.expect_space()?;
+                let (code, text) = Self::parse_resp_text(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Bye { code, text }))
+            }
+            "CAPABILITY" => {
+                let caps = parse_capability_data(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Capability(caps)))
+            }
+            "ENABLED" => {
+                let caps = parse_capability_data(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Enabled(caps)))
+            }
+            "FLAGS" => {
+                lexer.expect_space()?;
+                let flags = parse_flag_list(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Flags(flags)))
+            }
+            "LIST" => {
+                lexer.expect_space()?;
+                let list = parse_list_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::List(list)))
+            }
+            "SEARCH" => {
+                let nums = parse_search_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Search(nums)))
+            }
+            "SORT" => {
+                let nums = parse_search_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Sort(nums)))
+            }
+            "ESEARCH" => {
+                let result = parse_esearch_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Esearch(result)))
+            }
+            "THREAD" => {
+                let threads = parse_thread_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Thread(threads)))
+            }
+            "STATUS" => {
+                lexer.expect_space()?;
+                let (mailbox, items) = parse_status_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Status {
+                    mailbox,
+                    items,
+                }))
+            }
+            "QUOTAROOT" => {
+                lexer.expect_space()?;
+                let (mailbox, roots) = parse_quota_root_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::QuotaRoot {
+                    mailbox,
+                    roots,
+                }))
+            }
+            "QUOTA" => {
+                lexer.expect_space()?;
+                let quota = parse_quota_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Quota(quota)))
+            }
+            "METADATA" => {
+                lexer.expect_space()?;
+                let (mailbox, entries) = parse_metadata_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Metadata {
+                    mailbox,
+                    entries,
+                }))
+            }
+            "NAMESPACE" => {
+                lexer.expect_space()?;
+                let namespace = parse_namespace_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Namespace(namespace)))
+            }
+            "ID" => {
+                lexer.expect_space()?;
+                let fields = parse_id_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Id(fields)))
             }
             _ => Err(Error::Parse {
                 position: lexer.position(),
-                message: format!("Unexpected token in untagged response: {token:?}"),
+                message: format!("Unknown untagged response: {s}"),
+            }),
+        }
+    }
+
+    /// Parses untagged message data (`* <n> EXISTS`, `* <n> FETCH ...`).
+    fn parse_message_data(lexer: &mut Lexer<'_>, n: u32) -> Result<Response> {
+        lexer.expect_space()?;
+        let keyword = lexer.read_atom_string()?;
+        let upper = keyword.to_uppercase();
+
+        match upper.as_str() {
+            "EXISTS" => Ok(Response::Untagged(UntaggedResponse::Exists(n))),
+            "RECENT" => Ok(Response::Untagged(UntaggedResponse::Recent(n))),
+            "EXPUNGE" => {
+                let seq = SeqNum::new(n).ok_or_else(|| Error::Parse {
+                    position: lexer.position(),
+                    message: "Invalid sequence number 0".to_string(),
+                })?;
+                Ok(Response::Untagged(UntaggedResponse::Expunge(seq)))
+            }
+            "FETCH" => {
+                let seq = SeqNum::new(n).ok_or_else(|| Error::Parse {
+                    position: lexer.position(),
+                    message: "Invalid sequence number 0".to_string(),
+                })?;
+                lexer.expect_space()?;
+                let items = fetch::parse_fetch_response(lexer)?;
+                Ok(Response::Untagged(UntaggedResponse::Fetch { seq, items }))
+            }
+            _ => Err(Error::Parse {
+                position: lexer.position(),
+                message: format!("Unknown message data: {keyword}"),
             }),
         }
     }
@@ -251,10 +341,61 @@ pub(crate) use helpers::parse_flag_list;
     clippy::similar_names
 )]
 mod tests {
+    use std::collections::HashMap;
+
     use crate::types::{Capability, Flag, MailboxAttribute, ResponseCode};
 
     use super::*;
 
+    #[test]
+    fn test_parse_greeting_with_capabilities() {
+        let greeting = ResponseParser::parse_greeting(
+            b"* OK [CAPABILITY IMAP4rev1 STARTTLS AUTH=PLAIN] Dovecot ready.\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(greeting.status, Status::Ok);
+        assert!(!greeting.is_preauth());
+        assert_eq!(
+            greeting.capabilities,
+            vec![
+                Capability::Imap4Rev1,
+                Capability::StartTls,
+                Capability::Auth("PLAIN".to_string())
+            ]
+        );
+        assert_eq!(greeting.text, "Dovecot ready.");
+    }
+
+    #[test]
+    fn test_parse_preauth_greeting() {
+        let greeting =
+            ResponseParser::parse_greeting(b"* PREAUTH IMAP4rev1 server logged in as Smith\r\n")
+                .unwrap();
+
+        assert!(greeting.is_preauth());
+        assert_eq!(greeting.code, None);
+        assert!(greeting.capabilities.is_empty());
+        assert_eq!(greeting.text, "IMAP4rev1 server logged in as Smith");
+    }
+
+    #[test]
+    fn test_parse_bye_greeting() {
+        let greeting =
+            ResponseParser::parse_greeting(b"* BYE [UNAVAILABLE] Too many connections\r\n")
+                .unwrap();
+
+        assert_eq!(greeting.status, Status::Bye);
+        assert_eq!(greeting.code, Some(ResponseCode::Unavailable));
+        assert_eq!(greeting.text, "Too many connections");
+    }
+
+    #[test]
+    fn test_parse_greeting_rejects_other_responses() {
+        assert!(ResponseParser::parse_greeting(b"* 3 EXISTS\r\n").is_err());
+        assert!(ResponseParser::parse_greeting(b"A001 OK done\r\n").is_err());
+    }
+
     #[test]
     fn test_parse_ok_response() {
         let input = b"* OK IMAP4rev2 server ready\r\n";
@@ -348,6 +489,197 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_list_special_use() {
+        let input = b"* LIST (\\HasNoChildren \\Sent) \"/\" \"Gesendete Objekte\"\r\n";
+        let response = ResponseParser::parse(input).unwrap();
+
+        match response {
+            Response::Untagged(UntaggedResponse::List(list)) => {
+                assert_eq!(list.special_use(), Some(&MailboxAttribute::Sent));
+                assert_eq!(list.mailbox.as_str(), "Gesendete Objekte");
+            }
+            _ => panic!("Expected LIST"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_decodes_utf7() {
+        let input = b"* LIST (\\HasNoChildren) \"/\" \"&U9dP4TDIMOwwpA-\"\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::List(list)) => {
+                assert_eq!(list.mailbox.as_str(), "受信トレイ");
+            }
+            other => panic!("Expected LIST, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_quota_root() {
+        let input = b"* QUOTAROOT INBOX \"#user\"\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::QuotaRoot { mailbox, roots }) => {
+                assert_eq!(mailbox.as_str(), "INBOX");
+                assert_eq!(roots, vec!["#user".to_string()]);
+            }
+            other => panic!("Expected QUOTAROOT, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_quota() {
+        let input = b"* QUOTA \"#user\" (STORAGE 512 1024 MESSAGE 10 5000)\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Quota(quota)) => {
+                assert_eq!(quota.root, "#user");
+                let storage = quota.resource("storage").unwrap();
+                assert_eq!((storage.usage, storage.limit), (512, 1024));
+                assert_eq!(storage.percent_used(), 50);
+                assert_eq!(quota.resources.len(), 2);
+            }
+            other => panic!("Expected QUOTA, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let input =
+            b"* METADATA \"INBOX\" (/private/comment \"My comment\" /shared/comment NIL)\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Metadata { mailbox, entries }) => {
+                assert_eq!(mailbox.as_str(), "INBOX");
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries["/private/comment"].as_deref(), Some("My comment"));
+                assert_eq!(entries["/shared/comment"], None);
+            }
+            other => panic!("Expected METADATA, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_metadata_literal_value() {
+        let input = b"* METADATA \"\" (/shared/admin {7}\r\na\r\nb@cd)\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Metadata { mailbox, entries }) => {
+                assert_eq!(mailbox.as_str(), "");
+                assert_eq!(entries["/shared/admin"].as_deref(), Some("a\r\nb@cd"));
+            }
+            other => panic!("Expected METADATA, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_namespace_rfc2342_examples() {
+        let parse = |input: &[u8]| match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Namespace(ns)) => ns,
+            other => panic!("Expected NAMESPACE, got {other:?}"),
+        };
+        let entry = |prefix: &str, delimiter: Option<char>| NamespaceEntry {
+            prefix: prefix.to_string(),
+            delimiter,
+        };
+
+        // Example 5.1: personal namespace only
+        let ns = parse(b"* NAMESPACE ((\"\" \"/\")) NIL NIL\r\n");
+        assert_eq!(ns.personal, vec![entry("", Some('/'))]);
+        assert!(ns.other_users.is_empty());
+        assert!(ns.shared.is_empty());
+
+        // Example 5.2: shared only, flat hierarchy
+        let ns = parse(b"* NAMESPACE NIL NIL ((\"\" \".\"))\r\n");
+        assert!(ns.personal.is_empty());
+        assert_eq!(ns.shared, vec![entry("", Some('.'))]);
+
+        // Example 5.6: several namespaces of each kind
+        let ns = parse(
+            b"* NAMESPACE ((\"\" \"/\")(\"#mh/\" \"/\" \"X-PARAM\" (\"FLAG1\" \"FLAG2\"))) \
+              ((\"~\" \"/\")) ((\"#shared/\" \"/\")(\"#public/\" \"/\")(\"#ftp/\" \"/\")(\"#news.\" \".\"))\r\n",
+        );
+        assert_eq!(
+            ns.personal,
+            vec![entry("", Some('/')), entry("#mh/", Some('/'))]
+        );
+        assert_eq!(ns.other_users, vec![entry("~", Some('/'))]);
+        assert_eq!(ns.shared.len(), 4);
+        assert_eq!(ns.shared[3], entry("#news.", Some('.')));
+
+        // NIL delimiter
+        let ns = parse(b"* NAMESPACE ((\"INBOX.\" NIL)) NIL NIL\r\n");
+        assert_eq!(ns.personal, vec![entry("INBOX.", None)]);
+    }
+
+    #[test]
+    fn test_parse_namespace_other_and_shared() {
+        let input =
+            b"* NAMESPACE ((\"\" \"/\")) ((\"Other Users/\" \"/\")) ((\"Shared/\" \"/\"))\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Namespace(ns)) => {
+                assert_eq!(ns.other_users[0].prefix, "Other Users/");
+                assert_eq!(ns.shared[0].prefix, "Shared/");
+                assert_eq!(ns.shared[0].delimiter, Some('/'));
+            }
+            other => panic!("Expected NAMESPACE, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_id() {
+        let input = b"* ID (\"name\" \"Cyrus\" \"version\" \"1.5\" \"os\" NIL)\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Id(fields)) => {
+                assert_eq!(fields.len(), 2);
+                assert_eq!(fields["name"], "Cyrus");
+                assert_eq!(fields["version"], "1.5");
+            }
+            other => panic!("Expected ID, got {other:?}"),
+        }
+        assert_eq!(
+            ResponseParser::parse(b"* ID NIL\r\n").unwrap(),
+            Response::Untagged(UntaggedResponse::Id(HashMap::new()))
+        );
+    }
+
+    #[test]
+    fn test_parse_enabled() {
+        let input = b"* ENABLED UTF8=ACCEPT\r\n";
+        assert_eq!(
+            ResponseParser::parse(input).unwrap(),
+            Response::Untagged(UntaggedResponse::Enabled(vec![Capability::Utf8Accept]))
+        );
+    }
+
+    #[test]
+    fn test_parse_badcharset_code() {
+        let input = b"A002 NO [BADCHARSET (US-ASCII \"ISO-8859-1\")] Unsupported charset\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Tagged { code, .. } => assert_eq!(
+                code,
+                Some(ResponseCode::BadCharset(vec![
+                    "US-ASCII".to_string(),
+                    "ISO-8859-1".to_string()
+                ]))
+            ),
+            other => panic!("Expected tagged, got {other:?}"),
+        }
+
+        let input = b"A002 NO [BADCHARSET] Unsupported charset\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Tagged { code, .. } => {
+                assert_eq!(code, Some(ResponseCode::BadCharset(vec![])));
+            }
+            other => panic!("Expected tagged, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_overquota_code() {
+        let input = b"A003 NO [OVERQUOTA] Quota exceeded\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Tagged { code, .. } => assert_eq!(code, Some(ResponseCode::OverQuota)),
+            other => panic!("Expected tagged, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_parse_continuation() {
         let input = b"+ Ready for literal\r\n";
@@ -380,6 +712,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_appenduid_code() {
+        let input = b"A003 OK [APPENDUID 38505 3955:3957] APPEND completed\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Tagged {
+                code: Some(ResponseCode::AppendUid { validity, uids }),
+                ..
+            } => {
+                assert_eq!(validity.get(), 38505);
+                assert_eq!(uids.to_string(), "3955:3957");
+            }
+            other => panic!("Expected APPENDUID code, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_copyuid_code() {
+        let input = b"A004 OK [COPYUID 38505 304,319:320 3956:3958] Done\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Tagged {
+                code: Some(code), ..
+            } => {
+                let copy = code.copy_uid().unwrap();
+                assert_eq!(copy.validity.get(), 38505);
+                assert_eq!(copy.source.to_string(), "304,319:320");
+                assert_eq!(copy.dest.to_string(), "3956:3958");
+            }
+            other => panic!("Expected COPYUID code, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_highest_modseq_code() {
+        let input = b"* OK [HIGHESTMODSEQ 90060115205545359] Highest\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Ok {
+                code: Some(ResponseCode::HighestModSeq(n)),
+                ..
+            }) => assert_eq!(n, 90_060_115_205_545_359),
+            other => panic!("Expected HIGHESTMODSEQ code, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_fetch_large_modseq() {
+        let input = b"* 4 FETCH (UID 8 MODSEQ (12121231000))\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Fetch { items, .. }) => {
+                assert!(items.contains(&FetchItem::ModSeq(12_121_231_000)));
+            }
+            other => panic!("Expected FETCH, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_status_large_modseq() {
+        let input = b"* STATUS INBOX (MESSAGES 3 HIGHESTMODSEQ 7011231777)\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Status { items, .. }) => {
+                assert!(items.contains(&StatusItem::Messages(3)));
+                assert!(items.contains(&StatusItem::HighestModSeq(7_011_231_777)));
+            }
+            other => panic!("Expected STATUS, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_parse_fetch() {
         let input = b"* 1 FETCH (FLAGS (\\Seen) UID 12345)\r\n";
@@ -416,4 +814,178 @@ mod tests {
             _ => panic!("Expected SEARCH"),
         }
     }
+
+    #[test]
+    fn test_parse_sort_preserves_order() {
+        let input = b"* SORT 5 3 4 1 2\r\n";
+        match ResponseParser::parse(input).unwrap() {
+            Response::Untagged(UntaggedResponse::Sort(nums)) => {
+                let values: Vec<u32> = nums.iter().map(|s| s.get()).collect();
+                assert_eq!(values, vec![5, 3, 4, 1, 2]);
+            }
+            other => panic!("Expected SORT, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_empty_sort() {
+        let input = b"* SORT\r\n";
+        assert!(matches!(
+            ResponseParser::parse(input).unwrap(),
+            Response::Untagged(UntaggedResponse::Sort(nums)) if nums.is_empty()
+        ));
+    }
+
+    mod thread_tests {
+        use super::*;
+
+        fn node(id: u32, children: Vec<ThreadNode>) -> ThreadNode {
+            ThreadNode {
+                id: crate::types::Uid::new(id),
+                children,
+            }
+        }
+
+        fn parse_threads(input: &[u8]) -> Vec<ThreadNode> {
+            match ResponseParser::parse(input).unwrap() {
+                Response::Untagged(UntaggedResponse::Thread(threads)) => threads,
+                other => panic!("Expected THREAD, got {other:?}"),
+            }
+        }
+
+        #[test]
+        fn test_rfc5256_ordered_subject_example() {
+            let threads = parse_threads(b"* THREAD (2)(3 6 (4 23)(44 7 96))\r\n");
+            assert_eq!(
+                threads,
+                vec![
+                    node(2, vec![]),
+                    node(
+                        3,
+                        vec![node(
+                            6,
+                            vec![
+                                node(4, vec![node(23, vec![])]),
+                                node(44, vec![node(7, vec![node(96, vec![])])]),
+                            ]
+                        )]
+                    ),
+                ]
+            );
+            let ids: Vec<u32> = threads[1].ids().iter().map(|u| u.get()).collect();
+            assert_eq!(ids, vec![3, 6, 4, 23, 44, 7, 96]);
+            assert_eq!(threads[1].len(), 7);
+        }
+
+        #[test]
+        fn test_rfc5256_references_example() {
+            let threads = parse_threads(
+                b"* THREAD (166)(167)(168)(169)(172)(170)(171)(173)(174 (175)(176)(178)(181)(180))(179)(177 (183)(182)(188)(184)(185)(186)(187)(189))(190)(191)(192)(193)(194 195)(196 (197)(198))(199)(200 202)(201)(203)(204)(205)(206 207)(208)\r\n",
+            );
+            assert_eq!(threads.len(), 25);
+            assert_eq!(
+                threads[8],
+                node(
+                    174,
+                    vec![
+                        node(175, vec![]),
+                        node(176, vec![]),
+                        node(178, vec![]),
+                        node(181, vec![]),
+                        node(180, vec![]),
+                    ]
+                )
+            );
+            assert_eq!(threads[15], node(194, vec![node(195, vec![])]));
+            let total: usize = threads.iter().map(ThreadNode::len).sum();
+            assert_eq!(total, 43);
+        }
+
+        #[test]
+        fn test_missing_parent_placeholder() {
+            let threads = parse_threads(b"* THREAD ((3)(5))\r\n");
+            assert_eq!(
+                threads,
+                vec![ThreadNode {
+                    id: None,
+                    children: vec![node(3, vec![]), node(5, vec![])],
+                }]
+            );
+            assert_eq!(threads[0].len(), 2);
+        }
+
+        #[test]
+        fn test_empty_thread_response() {
+            assert!(parse_threads(b"* THREAD\r\n").is_empty());
+            assert!(parse_threads(b"* THREAD \r\n").is_empty());
+        }
+
+        #[test]
+        fn test_unterminated_thread_list() {
+            assert!(ResponseParser::parse(b"* THREAD (1 2\r\n").is_err());
+        }
+
+        #[test]
+        fn test_member_after_nested_rejected() {
+            assert!(ResponseParser::parse(b"* THREAD (1 (2) 3)\r\n").is_err());
+        }
+    }
+
+    mod esearch_tests {
+        use super::*;
+        use crate::types::{Uid, UidSet};
+
+        fn parse_esearch(input: &[u8]) -> EsearchResult {
+            match ResponseParser::parse(input).unwrap() {
+                Response::Untagged(UntaggedResponse::Esearch(result)) => result,
+                other => panic!("Expected ESEARCH, got {other:?}"),
+            }
+        }
+
+        #[test]
+        fn test_full_result() {
+            let result =
+                parse_esearch(b"* ESEARCH (TAG \"A01\") UID MIN 3 MAX 9 COUNT 5 ALL 3,6,9\r\n");
+            assert_eq!(result.tag.as_deref(), Some("A01"));
+            assert!(result.uid);
+            assert_eq!(result.min, Some(3));
+            assert_eq!(result.max, Some(9));
+            assert_eq!(result.count, Some(5));
+            assert_eq!(result.all.unwrap().to_string(), "3,6,9");
+        }
+
+        #[test]
+        fn test_compressed_ranges() {
+            let result = parse_esearch(b"* ESEARCH (TAG \"A2\") ALL 1:3,5,17:21\r\n");
+            assert!(!result.uid);
+            assert_eq!(result.all.unwrap().to_string(), "1:3,5,17:21");
+        }
+
+        #[test]
+        fn test_single_all_value() {
+            let result = parse_esearch(b"* ESEARCH (TAG \"A3\") UID ALL 42\r\n");
+            assert_eq!(result.all, Some(UidSet::single(Uid::new(42).unwrap())));
+        }
+
+        #[test]
+        fn test_no_matches() {
+            let result = parse_esearch(b"* ESEARCH (TAG \"A4\") UID COUNT 0\r\n");
+            assert_eq!(result.count, Some(0));
+            assert_eq!(result.min, None);
+            assert_eq!(result.all, None);
+        }
+
+        #[test]
+        fn test_without_correlator() {
+            let result = parse_esearch(b"* ESEARCH COUNT 2\r\n");
+            assert_eq!(result.tag, None);
+            assert_eq!(result.count, Some(2));
+        }
+
+        #[test]
+        fn test_unknown_return_data_skipped() {
+            let result = parse_esearch(b"* ESEARCH (TAG \"A5\") MODSEQ 917162500 COUNT 1\r\n");
+            assert_eq!(result.count, Some(1));
+        }
+    }
 }
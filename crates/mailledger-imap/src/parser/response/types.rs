@@ -1,6 +1,34 @@
 //! Response data types.
 
-use crate::types::{Flags, Mailbox, SeqNum, Uid, UidValidity};
+use std::collections::HashMap;
+
+use crate::types::{
+    Capability, Flags, Mailbox, MailboxStatus, ResponseCode, SeqNum, Status, Uid, UidSet,
+    UidValidity,
+};
+
+/// The server greeting, the first response on a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Greeting {
+    /// `Ok` when the client must authenticate, `PreAuth` when the
+    /// connection is already authenticated, or `Bye` when the server
+    /// refuses the connection.
+    pub status: Status,
+    /// Optional response code.
+    pub code: Option<ResponseCode>,
+    /// Capabilities advertised in the greeting, if any.
+    pub capabilities: Vec<Capability>,
+    /// Human-readable text, often identifying the server.
+    pub text: String,
+}
+
+impl Greeting {
+    /// Returns true if the server authenticated the connection already.
+    #[must_use]
+    pub fn is_preauth(&self) -> bool {
+        self.status == Status::PreAuth
+    }
+}
 
 /// FETCH response item.
 #[derive(Debug, Clone, PartialEq)]
@@ -28,6 +56,153 @@ pub enum FetchItem {
     BodyStructure(BodyStructure),
     /// MODSEQ (CONDSTORE).
     ModSeq(u64),
+    /// BINARY section content, already decoded by the server (RFC 3516).
+    Binary {
+        /// Part number path.
+        section: Vec<u32>,
+        /// Decoded data (`None` if the server returned NIL).
+        data: Option<Vec<u8>>,
+    },
+    /// BINARY.SIZE of a section (RFC 3516).
+    BinarySize {
+        /// Part number path.
+        section: Vec<u32>,
+        /// Decoded size in bytes.
+        size: u32,
+    },
+    /// Gmail labels (`X-GM-LABELS`); system labels keep their backslash,
+    /// e.g. `\Inbox`.
+    GmailLabels(Vec<String>),
+    /// Gmail thread ID (`X-GM-THRID`).
+    GmailThreadId(u64),
+    /// Gmail message ID (`X-GM-MSGID`).
+    GmailMsgId(u64),
+}
+
+/// ESEARCH response data (RFC 4731).
+///
+/// Only the items requested with `RETURN (...)` are present. Values are
+/// UIDs when `uid` is set, sequence numbers otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EsearchResult {
+    /// Tag of the command this result belongs to.
+    pub tag: Option<String>,
+    /// Whether the values are UIDs.
+    pub uid: bool,
+    /// Lowest matching message.
+    pub min: Option<u32>,
+    /// Highest matching message.
+    pub max: Option<u32>,
+    /// Number of matching messages.
+    pub count: Option<u32>,
+    /// All matching messages, range-compressed by the server.
+    pub all: Option<UidSet>,
+}
+
+/// A resource within a quota root (RFC 9208), e.g. `STORAGE 512 1024`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaResource {
+    /// Resource name, e.g. `STORAGE` (in KiB) or `MESSAGE`.
+    pub name: String,
+    /// Current usage.
+    pub usage: u64,
+    /// Limit.
+    pub limit: u64,
+}
+
+impl QuotaResource {
+    /// Returns the usage as a percentage of the limit, capped at 100.
+    #[must_use]
+    pub fn percent_used(&self) -> u8 {
+        if self.limit == 0 {
+            return 100;
+        }
+        u8::try_from((self.usage.saturating_mul(100) / self.limit).min(100)).unwrap_or(100)
+    }
+}
+
+/// A QUOTA response (RFC 9208).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Quota {
+    /// Quota root name, often empty or e.g. `#user`.
+    pub root: String,
+    /// Resources limited by this root.
+    pub resources: Vec<QuotaResource>,
+}
+
+impl Quota {
+    /// Returns the resource with the given name, e.g. `STORAGE`.
+    #[must_use]
+    pub fn resource(&self, name: &str) -> Option<&QuotaResource> {
+        self.resources
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// One namespace from a NAMESPACE response (RFC 2342).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceEntry {
+    /// Prefix of mailbox names in this namespace, e.g. `Shared/`.
+    pub prefix: String,
+    /// Hierarchy delimiter, or `None` for a flat namespace.
+    pub delimiter: Option<char>,
+}
+
+/// A NAMESPACE response (RFC 2342).
+///
+/// Each list is empty when the server has no namespace of that kind.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Namespace {
+    /// The user's own mailboxes.
+    pub personal: Vec<NamespaceEntry>,
+    /// Mailboxes of other users.
+    pub other_users: Vec<NamespaceEntry>,
+    /// Mailboxes shared with the user.
+    pub shared: Vec<NamespaceEntry>,
+}
+
+/// A message in a THREAD response tree (RFC 5256).
+///
+/// Ids are UIDs for `UID THREAD` and sequence numbers for plain `THREAD`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadNode {
+    /// Message id, or `None` for a placeholder parent the server knows is
+    /// missing (e.g. `((3)(5))`, where 3 and 5 reply to the same absent message).
+    pub id: Option<Uid>,
+    /// Replies to this message, in the server's order.
+    pub children: Vec<Self>,
+}
+
+impl ThreadNode {
+    /// Returns the ids of this node and all descendants in depth-first order.
+    #[must_use]
+    pub fn ids(&self) -> Vec<Uid> {
+        let mut ids = Vec::new();
+        self.collect_ids(&mut ids);
+        ids
+    }
+
+    /// Returns the number of messages in this subtree.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        usize::from(self.id.is_some()) + self.children.iter().map(Self::len).sum::<usize>()
+    }
+
+    /// Returns true if this subtree contains no messages.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn collect_ids(&self, ids: &mut Vec<Uid>) {
+        if let Some(id) = self.id {
+            ids.push(id);
+        }
+        for child in &self.children {
+            child.collect_ids(ids);
+        }
+    }
 }
 
 /// Message envelope.
@@ -55,7 +230,23 @@ pub struct Envelope {
     pub message_id: Option<String>,
 }
 
+impl Envelope {
+    /// Returns the first From address formatted as `Name <mailbox@host>`.
+    ///
+    /// Falls back to the bare address when there is no display name, and
+    /// skips group markers.
+    #[must_use]
+    pub fn from_display(&self) -> Option<String> {
+        self.from.iter().find_map(Address::display)
+    }
+}
+
 /// Email address from envelope.
+///
+/// Group syntax (RFC 5322 `name: addr, addr;`) is flattened into the list
+/// as markers: the group starts with an address that has a mailbox but no
+/// host, the mailbox being the group name, and ends with an address whose
+/// mailbox and host are both `None`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Address {
     /// Display name.
@@ -77,6 +268,40 @@ impl Address {
             _ => None,
         }
     }
+
+    /// Returns the group name if this address opens a group.
+    #[must_use]
+    pub fn group_name(&self) -> Option<&str> {
+        match (&self.mailbox, &self.host) {
+            (Some(group), None) => Some(group),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this address closes a group.
+    #[must_use]
+    pub const fn is_group_end(&self) -> bool {
+        self.mailbox.is_none() && self.host.is_none()
+    }
+
+    /// Formats the address as `Name <mailbox@host>`, or just `mailbox@host`
+    /// without a display name.
+    ///
+    /// Names containing RFC 5322 specials are quoted. Returns `None` for
+    /// group markers.
+    #[must_use]
+    pub fn display(&self) -> Option<String> {
+        let email = self.email()?;
+        let Some(name) = self.name.as_deref().filter(|n| !n.is_empty()) else {
+            return Some(email);
+        };
+        if name.contains(|c: char| "()<>[]:;@\\,.\"".contains(c)) {
+            let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+            Some(format!("\"{escaped}\" <{email}>"))
+        } else {
+            Some(format!("{name} <{email}>"))
+        }
+    }
 }
 
 /// Body structure (simplified).
@@ -98,6 +323,8 @@ pub enum BodyStructure {
         encoding: String,
         /// Body size in bytes.
         size: u32,
+        /// Content-Disposition, if the server sent extension data.
+        disposition: Option<BodyDisposition>,
     },
     /// Message/RFC822 body.
     Message {
@@ -124,6 +351,8 @@ pub enum BodyStructure {
         size: u32,
         /// Size in lines.
         lines: u32,
+        /// Content-Disposition, if the server sent extension data.
+        disposition: Option<BodyDisposition>,
     },
     /// Multipart body.
     Multipart {
@@ -134,6 +363,170 @@ pub enum BodyStructure {
     },
 }
 
+impl BodyStructure {
+    /// Returns the lowercase `type/subtype` of this part.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        match self {
+            Self::Basic {
+                media_type,
+                media_subtype,
+                ..
+            } => format!("{media_type}/{media_subtype}").to_lowercase(),
+            Self::Message { .. } => "message/rfc822".to_string(),
+            Self::Text { subtype, .. } => format!("text/{subtype}").to_lowercase(),
+            Self::Multipart { subtype, .. } => format!("multipart/{subtype}").to_lowercase(),
+        }
+    }
+
+    /// Returns the Content-Disposition of a single part.
+    #[must_use]
+    pub const fn disposition(&self) -> Option<&BodyDisposition> {
+        match self {
+            Self::Basic { disposition, .. } | Self::Text { disposition, .. } => {
+                disposition.as_ref()
+            }
+            Self::Message { .. } | Self::Multipart { .. } => None,
+        }
+    }
+
+    /// Returns the filename from Content-Disposition, falling back to the
+    /// Content-Type `name` parameter.
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        let params = match self {
+            Self::Basic { params, .. } | Self::Text { params, .. } => params,
+            Self::Message { .. } | Self::Multipart { .. } => return None,
+        };
+        self.disposition()
+            .and_then(BodyDisposition::filename)
+            .or_else(|| find_param(params, "name"))
+    }
+
+    /// Returns every non-multipart part with its IMAP part number.
+    ///
+    /// Part numbers follow RFC 3501: a single-part message is part `1`,
+    /// children of a multipart are numbered from 1 and nested with dots
+    /// (`2.1`). Nested `message/rfc822` parts are returned as one leaf.
+    #[must_use]
+    pub fn leaves(&self) -> Vec<(String, &Self)> {
+        let mut leaves = Vec::new();
+        match self {
+            Self::Multipart { .. } => self.collect_leaves("", &mut leaves),
+            _ => leaves.push(("1".to_string(), self)),
+        }
+        leaves
+    }
+
+    fn collect_leaves<'a>(&'a self, prefix: &str, leaves: &mut Vec<(String, &'a Self)>) {
+        match self {
+            Self::Multipart { bodies, .. } => {
+                for (i, body) in bodies.iter().enumerate() {
+                    let part_number = if prefix.is_empty() {
+                        (i + 1).to_string()
+                    } else {
+                        format!("{prefix}.{}", i + 1)
+                    };
+                    body.collect_leaves(&part_number, leaves);
+                }
+            }
+            _ => leaves.push((prefix.to_string(), self)),
+        }
+    }
+
+    /// Returns the parts that are attachments rather than message content.
+    ///
+    /// A part counts as an attachment if its disposition is `attachment`,
+    /// or if it has a filename and is not marked `inline`. Inline parts such
+    /// as images referenced by `Content-ID` are excluded.
+    #[must_use]
+    pub fn attachments(&self) -> Vec<AttachmentInfo> {
+        self.leaves()
+            .into_iter()
+            .filter(|(_, part)| match part.disposition() {
+                Some(d) if d.is_attachment() => true,
+                Some(d) if d.is_inline() => false,
+                _ => part.filename().is_some(),
+            })
+            .map(|(part_number, part)| AttachmentInfo {
+                filename: part.filename().map(ToString::to_string),
+                content_type: part.content_type(),
+                encoding: part.encoding().to_lowercase(),
+                size: part.size(),
+                part_number,
+            })
+            .collect()
+    }
+
+    /// Returns the Content-Transfer-Encoding of a single part.
+    fn encoding(&self) -> &str {
+        match self {
+            Self::Basic { encoding, .. } | Self::Text { encoding, .. } => encoding,
+            Self::Message { .. } | Self::Multipart { .. } => "",
+        }
+    }
+
+    /// Returns the encoded size of a single part in bytes.
+    const fn size(&self) -> u32 {
+        match self {
+            Self::Basic { size, .. } | Self::Text { size, .. } => *size,
+            Self::Message { .. } | Self::Multipart { .. } => 0,
+        }
+    }
+}
+
+/// Content-Disposition from BODYSTRUCTURE extension data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyDisposition {
+    /// Disposition type, lowercased (`attachment`, `inline`, ...).
+    pub kind: String,
+    /// Disposition parameters such as `filename`.
+    pub params: Vec<(String, String)>,
+}
+
+impl BodyDisposition {
+    /// Returns true for `attachment`.
+    #[must_use]
+    pub fn is_attachment(&self) -> bool {
+        self.kind == "attachment"
+    }
+
+    /// Returns true for `inline`.
+    #[must_use]
+    pub fn is_inline(&self) -> bool {
+        self.kind == "inline"
+    }
+
+    /// Returns the `filename` parameter.
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        find_param(&self.params, "filename")
+    }
+}
+
+/// An attachment located in a BODYSTRUCTURE tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentInfo {
+    /// IMAP part number, e.g. `2` or `1.3`.
+    pub part_number: String,
+    /// Filename, if one was given.
+    pub filename: Option<String>,
+    /// Lowercase `type/subtype`.
+    pub content_type: String,
+    /// Lowercase Content-Transfer-Encoding.
+    pub encoding: String,
+    /// Encoded size in bytes.
+    pub size: u32,
+}
+
+/// Looks up a body parameter by case-insensitive name.
+fn find_param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
 /// STATUS response item.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StatusItem {
@@ -151,6 +544,23 @@ pub enum StatusItem {
     HighestModSeq(u64),
 }
 
+impl From<&[StatusItem]> for MailboxStatus {
+    fn from(items: &[StatusItem]) -> Self {
+        let mut status = Self::default();
+        for item in items {
+            match *item {
+                StatusItem::Messages(n) => status.exists = n,
+                StatusItem::Recent(n) => status.recent = n,
+                StatusItem::UidNext(uid) => status.uid_next = Some(uid),
+                StatusItem::UidValidity(v) => status.uid_validity = Some(v),
+                StatusItem::Unseen(n) => status.unseen_count = Some(n),
+                StatusItem::HighestModSeq(m) => status.highest_mod_seq = Some(m),
+            }
+        }
+        status
+    }
+}
+
 /// Untagged response data.
 #[derive(Debug, Clone, PartialEq)]
 pub enum UntaggedResponse {
@@ -191,6 +601,8 @@ pub enum UntaggedResponse {
     },
     /// CAPABILITY response.
     Capability(Vec<crate::types::Capability>),
+    /// ENABLED response (RFC 5161): extensions the server turned on.
+    Enabled(Vec<crate::types::Capability>),
     /// LIST response.
     List(crate::types::ListResponse),
     /// FLAGS response.
@@ -210,6 +622,12 @@ pub enum UntaggedResponse {
     },
     /// SEARCH response.
     Search(Vec<SeqNum>),
+    /// SORT response (RFC 5256), in sorted order.
+    Sort(Vec<SeqNum>),
+    /// THREAD response (RFC 5256), one root node per thread.
+    Thread(Vec<ThreadNode>),
+    /// ESEARCH response (RFC 4731).
+    Esearch(EsearchResult),
     /// STATUS response.
     Status {
         /// Mailbox name.
@@ -217,6 +635,26 @@ pub enum UntaggedResponse {
         /// Status items.
         items: Vec<StatusItem>,
     },
+    /// QUOTAROOT response (RFC 9208).
+    QuotaRoot {
+        /// Mailbox the roots apply to.
+        mailbox: Mailbox,
+        /// Quota root names.
+        roots: Vec<String>,
+    },
+    /// QUOTA response (RFC 9208).
+    Quota(Quota),
+    /// METADATA response (RFC 5464).
+    Metadata {
+        /// Mailbox the entries belong to, empty for server annotations.
+        mailbox: Mailbox,
+        /// Entry values by name; `None` for entries that aren't set.
+        entries: HashMap<String, Option<String>>,
+    },
+    /// NAMESPACE response (RFC 2342).
+    Namespace(Namespace),
+    /// ID response (RFC 2971): server identification fields, empty for NIL.
+    Id(HashMap<String, String>),
 }
 
 #[cfg(test)]
@@ -398,6 +836,7 @@ mod tests {
                 description: Some("PDF attachment".to_string()),
                 encoding: "base64".to_string(),
                 size: 102400,
+                disposition: None,
             };
             if let BodyStructure::Basic {
                 media_type,
@@ -424,6 +863,7 @@ mod tests {
                 encoding: "7bit".to_string(),
                 size: 500,
                 lines: 25,
+                disposition: None,
             };
             if let BodyStructure::Text { subtype, lines, .. } = body {
                 assert_eq!(subtype, "plain");
@@ -443,6 +883,7 @@ mod tests {
                 encoding: "7bit".to_string(),
                 size: 100,
                 lines: 5,
+                disposition: None,
             };
             let part2 = BodyStructure::Text {
                 subtype: "html".to_string(),
@@ -452,6 +893,7 @@ mod tests {
                 encoding: "quoted-printable".to_string(),
                 size: 500,
                 lines: 20,
+                disposition: None,
             };
             let body = BodyStructure::Multipart {
                 bodies: vec![part1, part2],
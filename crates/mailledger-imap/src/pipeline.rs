@@ -44,7 +44,7 @@
 use std::collections::VecDeque;
 use std::time::Duration;
 
-use crate::command::Command;
+use crate::command::{Command, split_at_literals};
 use crate::types::Tag;
 
 /// Default maximum pipeline depth.
@@ -303,13 +303,20 @@ impl Command {
             Self::Noop
             | Self::Capability
             | Self::List { .. }
+            | Self::ListExtended { .. }
             | Self::Namespace
+            | Self::GetQuotaRoot { .. }
+            | Self::GetQuota { .. }
+            | Self::GetMetadata { .. }
             | Self::Status { .. } => PipelineSafety::Safe,
 
             // Can pipeline with caution
-            Self::Fetch { .. } | Self::Search { .. } | Self::Copy { .. } | Self::Move { .. } => {
-                PipelineSafety::Caution
-            }
+            Self::Fetch { .. }
+            | Self::Search { .. }
+            | Self::Sort { .. }
+            | Self::Thread { .. }
+            | Self::Copy { .. }
+            | Self::Move { .. } => PipelineSafety::Caution,
 
             // Should not pipeline - state changes
             Self::Login { .. }
@@ -322,6 +329,7 @@ impl Command {
             | Self::UidExpunge { .. }
             | Self::Store { .. }
             | Self::Append { .. }
+            | Self::MultiAppend { .. }
             | Self::Idle
             | Self::Done
             | Self::Create { .. }
@@ -330,8 +338,10 @@ impl Command {
             | Self::Subscribe { .. }
             | Self::Unsubscribe { .. }
             | Self::StartTls
+            | Self::Compress
             | Self::Authenticate { .. }
             | Self::Id { .. }
+            | Self::SetMetadata { .. }
             | Self::Enable { .. } => PipelineSafety::Unsafe,
         }
     }
@@ -345,7 +355,14 @@ impl Command {
     /// Returns `true` if this command contains a literal.
     #[must_use]
     pub fn has_literal(&self) -> bool {
-        matches!(self, Self::Append { .. })
+        match self {
+            Self::Append { .. } | Self::MultiAppend { .. } => true,
+            Self::Search { .. }
+            | Self::Sort { .. }
+            | Self::Thread { .. }
+            | Self::SetMetadata { .. } => split_at_literals(&self.serialize("")).len() > 1,
+            _ => false,
+        }
     }
 }
 
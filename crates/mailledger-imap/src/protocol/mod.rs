@@ -55,7 +55,7 @@ pub use transmit::Transmit;
 
 use crate::command::{Command, TagGenerator};
 use crate::handler::ResponseHandler;
-use crate::parser::{Response, ResponseParser, UntaggedResponse};
+use crate::parser::{Greeting, Response, ResponseParser, UntaggedResponse};
 use crate::types::{Capability, MailboxStatus, ResponseCode, Status, Tag};
 use crate::{Error, Result};
 
@@ -99,11 +99,10 @@ impl CommandResult {
     ///
     /// Returns an error if the status is NO, BAD, or BYE.
     pub fn into_result(self) -> Result<Vec<UntaggedResponse>> {
-        match self.status {
-            Status::Ok | Status::PreAuth => Ok(self.responses),
-            Status::No => Err(Error::No(self.text)),
-            Status::Bad => Err(Error::Bad(self.text)),
-            Status::Bye => Err(Error::Bye(self.text)),
+        if self.is_ok() {
+            Ok(self.responses)
+        } else {
+            Err(Error::from_tagged(self.status, self.code, self.text))
         }
     }
 }
@@ -119,8 +118,11 @@ pub enum ProtocolEvent {
         result: CommandResult,
     },
     /// Server greeting received (initial connection).
+    ///
+    /// A `PreAuth` status moves the protocol straight to the authenticated
+    /// state, and `Bye` to logout.
     Greeting {
-        /// Greeting status.
+        /// Greeting status: `Ok`, `PreAuth` or `Bye`.
         status: Status,
         /// Optional response code.
         code: Option<ResponseCode>,
@@ -156,7 +158,7 @@ pub struct Protocol {
     tag_gen: TagGenerator,
     /// Server capabilities.
     capabilities: Vec<Capability>,
-    /// Pending commands awaiting responses.
+    /// Pending commands awaiting responses, in send order.
     pending: VecDeque<PendingCommand>,
     /// Outbound data queue.
     outbound: VecDeque<Transmit>,
@@ -214,12 +216,27 @@ impl Protocol {
         self.capabilities.contains(cap)
     }
 
+    /// Returns whether the server greeting has been processed.
+    #[must_use]
+    pub fn greeting_received(&self) -> bool {
+        self.greeting_received
+    }
+
     /// Returns the current mailbox status (when selected).
     #[must_use]
     pub fn mailbox_status(&self) -> Option<&MailboxStatus> {
         self.mailbox_status.as_ref()
     }
 
+    /// Returns the number of commands sent or queued but not yet completed.
+    ///
+    /// Several commands may be in flight at once; untagged data is credited
+    /// to the oldest of them.
+    #[must_use]
+    pub fn commands_in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
     /// Returns whether we're in IDLE mode.
     #[must_use]
     pub fn is_idle(&self) -> bool {
@@ -279,33 +296,30 @@ impl Protocol {
     }
 
     /// Finds the end of a complete response in the inbound buffer.
+    ///
+    /// A response is a line that may end in a `{n}` literal, in which case
+    /// exactly `n` bytes follow and the response continues on the line
+    /// after them, possibly with further literals. Literal contents are
+    /// skipped unscanned, so CRLFs or tagged-looking lines inside them don't
+    /// end the response.
     fn find_complete_response(&self) -> Option<usize> {
-        // Look for CRLF
-        for i in 0..self.inbound.len().saturating_sub(1) {
-            if self.inbound[i] == b'\r' && self.inbound[i + 1] == b'\n' {
-                // Check for literal
-                if let Some(literal_len) = self.parse_literal_at_end(&self.inbound[..=i + 1]) {
-                    // Need more data for the literal
-                    let total_needed = i + 2 + literal_len;
-                    if self.inbound.len() >= total_needed {
-                        // Have the literal, look for the next CRLF
-                        for j in total_needed..self.inbound.len().saturating_sub(1) {
-                            if self.inbound[j] == b'\r' && self.inbound[j + 1] == b'\n' {
-                                return Some(j + 1);
-                            }
-                        }
+        let mut pos = 0;
+        loop {
+            let line_end = pos + self.inbound[pos..].windows(2).position(|w| w == b"\r\n")? + 1;
+            match Self::parse_literal_at_end(&self.inbound[pos..=line_end]) {
+                Some(literal_len) => {
+                    pos = line_end + 1 + literal_len;
+                    if pos > self.inbound.len() {
+                        return None;
                     }
-                    return None;
                 }
-                return Some(i + 1);
+                None => return Some(line_end),
             }
         }
-        None
     }
 
     /// Parses a literal length from the end of a line.
-    #[allow(clippy::unused_self)] // Method for potential future use of self
-    fn parse_literal_at_end(&self, line: &[u8]) -> Option<usize> {
+    fn parse_literal_at_end(line: &[u8]) -> Option<usize> {
         if !line.ends_with(b"\r\n") {
             return None;
         }
@@ -333,6 +347,14 @@ impl Protocol {
         data: &[u8],
         handler: &mut dyn ResponseHandler,
     ) -> Option<ProtocolEvent> {
+        // Only the first response can be the greeting
+        if !self.greeting_received {
+            self.greeting_received = true;
+            if let Ok(greeting) = ResponseParser::parse_greeting(data) {
+                return Some(self.handle_greeting(greeting, handler));
+            }
+        }
+
         let Ok(response) = ResponseParser::parse(data) else {
             return None;
         };
@@ -356,6 +378,32 @@ impl Protocol {
         }
     }
 
+    /// Handles the server greeting, keeping any capabilities it lists so
+    /// no CAPABILITY command is needed.
+    fn handle_greeting(
+        &mut self,
+        greeting: Greeting,
+        handler: &mut dyn ResponseHandler,
+    ) -> ProtocolEvent {
+        if !greeting.capabilities.is_empty() {
+            self.capabilities = greeting.capabilities;
+        }
+        match greeting.status {
+            Status::PreAuth => self.state = ProtocolState::Authenticated,
+            Status::Bye => {
+                handler.on_bye(&greeting.text);
+                self.state = ProtocolState::Logout;
+            }
+            _ => {}
+        }
+
+        ProtocolEvent::Greeting {
+            status: greeting.status,
+            code: greeting.code,
+            text: greeting.text,
+        }
+    }
+
     /// Handles a tagged response.
     #[allow(clippy::needless_pass_by_value)] // Tag is small and consumed in comparisons
     fn handle_tagged(
@@ -432,8 +480,10 @@ impl Protocol {
             }
         }
 
-        // Add to pending command responses (if any command is pending)
-        if let Some(pending) = self.pending.back_mut() {
+        // RFC 3501 §5.5 doesn't tie untagged data to a particular command, so
+        // credit it to the oldest one still in flight. Callers should only
+        // pipeline commands whose untagged responses can't be confused.
+        if let Some(pending) = self.pending.front_mut() {
             pending.responses.push(response);
         }
     }
@@ -459,6 +509,14 @@ impl Protocol {
 
     // === Command Methods ===
 
+    /// Queues an arbitrary command.
+    ///
+    /// Commands may be queued before earlier ones complete; see
+    /// [`commands_in_flight`](Self::commands_in_flight).
+    pub fn queue(&mut self, cmd: &Command) -> CommandHandle {
+        self.queue_command(cmd)
+    }
+
     /// Queues a LOGIN command.
     pub fn login(&mut self, username: &str, password: &str) -> CommandHandle {
         // State transition happens on successful response
@@ -585,6 +643,64 @@ mod tests {
         assert!(protocol.capabilities().is_empty());
     }
 
+    #[test]
+    fn test_greeting_with_capabilities() {
+        let mut protocol = Protocol::new();
+        let mut handler = NoopHandler;
+
+        let events = protocol.handle_input(
+            b"* OK [CAPABILITY IMAP4rev1 IDLE] Server ready\r\n",
+            &mut handler,
+        );
+
+        assert!(protocol.greeting_received());
+        assert!(matches!(protocol.state(), ProtocolState::NotAuthenticated));
+        assert!(protocol.has_capability(&Capability::Idle));
+        assert!(matches!(
+            &events[..],
+            [ProtocolEvent::Greeting { status: Status::Ok, text, .. }] if text == "Server ready"
+        ));
+    }
+
+    #[test]
+    fn test_preauth_greeting_authenticates() {
+        let mut protocol = Protocol::new();
+        let mut handler = NoopHandler;
+
+        let events = protocol.handle_input(b"* PREAUTH Welcome back\r\n", &mut handler);
+
+        assert!(protocol.state().is_authenticated());
+        assert!(matches!(
+            &events[..],
+            [ProtocolEvent::Greeting {
+                status: Status::PreAuth,
+                ..
+            }]
+        ));
+
+        // Later untagged OKs are ordinary responses
+        let events = protocol.handle_input(b"* OK Still here\r\n", &mut handler);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_bye_greeting() {
+        let mut protocol = Protocol::new();
+        let mut handler = crate::handler::CollectingHandler::new();
+
+        let events = protocol.handle_input(b"* BYE Too many connections\r\n", &mut handler);
+
+        assert!(matches!(protocol.state(), ProtocolState::Logout));
+        assert_eq!(handler.events.len(), 1);
+        assert!(matches!(
+            &events[..],
+            [ProtocolEvent::Greeting {
+                status: Status::Bye,
+                ..
+            }]
+        ));
+    }
+
     #[test]
     fn test_queue_command() {
         let mut protocol = Protocol::new();
@@ -623,6 +739,147 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pipelined_responses_are_partitioned() {
+        use crate::command::{FetchAttribute, FetchItems, StoreAction};
+        use crate::types::{Flag, SequenceSet};
+
+        let mut protocol = Protocol::new();
+        let mut handler = NoopHandler;
+
+        let fetch = protocol.queue(&Command::Fetch {
+            sequence: SequenceSet::single(1).unwrap(),
+            items: FetchItems::Items(vec![FetchAttribute::Flags]),
+            uid: false,
+            changed_since: None,
+        });
+        let store = protocol.queue(&Command::Store {
+            sequence: SequenceSet::single(2).unwrap(),
+            action: StoreAction::AddFlags(vec![Flag::Seen]),
+            uid: false,
+            silent: false,
+        });
+        assert_eq!(protocol.commands_in_flight(), 2);
+        while protocol.poll_transmit().is_some() {}
+
+        let input = format!(
+            "* 1 FETCH (FLAGS ())\r\n\
+             * 3 EXISTS\r\n\
+             {} OK FETCH completed\r\n\
+             * 2 FETCH (FLAGS (\\Seen))\r\n\
+             {} OK STORE completed\r\n",
+            fetch.tag().as_str(),
+            store.tag().as_str(),
+        );
+        let events = protocol.handle_input(input.as_bytes(), &mut handler);
+        assert_eq!(protocol.commands_in_flight(), 0);
+
+        let results: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                ProtocolEvent::CommandComplete { handle, result } => Some((handle, result)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(results.len(), 2);
+
+        let (handle, result) = &results[0];
+        assert_eq!(handle, &fetch);
+        assert_eq!(result.responses.len(), 2);
+        assert!(matches!(
+            result.responses[0],
+            UntaggedResponse::Fetch { seq, .. } if seq.get() == 1
+        ));
+        assert!(matches!(result.responses[1], UntaggedResponse::Exists(3)));
+
+        let (handle, result) = &results[1];
+        assert_eq!(handle, &store);
+        assert_eq!(result.responses.len(), 1);
+        assert!(matches!(
+            result.responses[0],
+            UntaggedResponse::Fetch { seq, .. } if seq.get() == 2
+        ));
+    }
+
+    fn fetch_body_sections(result: &CommandResult) -> Vec<Vec<u8>> {
+        result
+            .responses
+            .iter()
+            .flat_map(|response| match response {
+                UntaggedResponse::Fetch { items, .. } => items.clone(),
+                _ => Vec::new(),
+            })
+            .filter_map(|item| match item {
+                crate::parser::FetchItem::Body { data, .. } => data,
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn queue_fetch(protocol: &mut Protocol) -> CommandHandle {
+        use crate::command::{FetchAttribute, FetchItems};
+        use crate::types::SequenceSet;
+
+        let handle = protocol.queue(&Command::Fetch {
+            sequence: SequenceSet::single(1).unwrap(),
+            items: FetchItems::Items(vec![FetchAttribute::Rfc822]),
+            uid: false,
+            changed_since: None,
+        });
+        while protocol.poll_transmit().is_some() {}
+        handle
+    }
+
+    #[test]
+    fn test_response_with_two_literals() {
+        let mut protocol = Protocol::new();
+        let mut handler = NoopHandler;
+        let fetch = queue_fetch(&mut protocol);
+        let tag = fetch.tag().as_str().to_string();
+
+        let input = format!(
+            "* 1 FETCH (BODY[HEADER] {{14}}\r\nSubject: x\r\n\r\n BODY[TEXT] {{6}}\r\nhello\n)\r\n\
+             {tag} OK FETCH completed\r\n"
+        );
+        // Feed byte by byte so every literal boundary is split
+        let mut events = Vec::new();
+        for byte in input.as_bytes() {
+            events.extend(protocol.handle_input(std::slice::from_ref(byte), &mut handler));
+        }
+
+        assert_eq!(events.len(), 1);
+        let ProtocolEvent::CommandComplete { result, .. } = &events[0] else {
+            panic!("Expected CommandComplete event");
+        };
+        assert_eq!(
+            fetch_body_sections(result),
+            vec![b"Subject: x\r\n\r\n".to_vec(), b"hello\n".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_literal_containing_tagged_line() {
+        let mut protocol = Protocol::new();
+        let mut handler = NoopHandler;
+        let fetch = queue_fetch(&mut protocol);
+        let tag = fetch.tag().as_str().to_string();
+
+        let body = format!("Hi\r\n{tag} OK fake\r\n");
+        let head = format!("* 1 FETCH (RFC822 {{{}}}\r\n{body}", body.len());
+        let events = protocol.handle_input(head.as_bytes(), &mut handler);
+        assert!(events.is_empty());
+        assert_eq!(protocol.commands_in_flight(), 1);
+
+        let tail = format!(")\r\n{tag} OK FETCH completed\r\n");
+        let events = protocol.handle_input(tail.as_bytes(), &mut handler);
+        assert_eq!(events.len(), 1);
+        let ProtocolEvent::CommandComplete { result, .. } = &events[0] else {
+            panic!("Expected CommandComplete event");
+        };
+        assert_eq!(result.text, "FETCH completed");
+        assert_eq!(fetch_body_sections(result), vec![body.into_bytes()]);
+    }
+
     #[test]
     fn test_handle_untagged_exists() {
         let mut protocol = Protocol::new();
@@ -4,6 +4,7 @@
 //! non-standard behaviors. This module provides detection and workarounds
 //! for common server quirks.
 
+use crate::command::{FetchAttribute, FetchItems};
 use crate::types::Capability;
 
 /// Known IMAP server types with specific quirks.
@@ -28,23 +29,35 @@ pub enum ServerType {
     Courier,
     /// Cyrus IMAP.
     Cyrus,
+    /// Coremail, used by `NetEase` (163.com, 126.com).
+    Coremail,
 }
 
 impl ServerType {
     /// Detects the server type from capabilities and greeting.
+    ///
+    /// `greeting` is the text of the server's initial `* OK` response.
     #[must_use]
     pub fn detect(capabilities: &[Capability], greeting: Option<&str>) -> Self {
-        // Check for Gmail-specific extensions
-        for cap in capabilities {
-            if let Capability::Unknown(s) = cap {
-                let upper = s.to_uppercase();
-                if upper.starts_with("X-GM-") {
-                    return Self::Gmail;
-                }
-                if upper.contains("XLIST") && upper.contains("XYMHIGHESTMODSEQ") {
-                    return Self::Yahoo;
-                }
-            }
+        let has_unknown = |pred: &dyn Fn(&str) -> bool| {
+            capabilities
+                .iter()
+                .any(|cap| matches!(cap, Capability::Unknown(s) if pred(&s.to_uppercase())))
+        };
+
+        // Check for provider-specific extensions
+        if capabilities.contains(&Capability::GmailExt1)
+            || has_unknown(&|cap| cap.starts_with("X-GM-"))
+        {
+            return Self::Gmail;
+        }
+        if has_unknown(&|cap| cap.contains("XLIST"))
+            && has_unknown(&|cap| cap.contains("XYMHIGHESTMODSEQ"))
+        {
+            return Self::Yahoo;
+        }
+        if has_unknown(&|cap| cap.starts_with("X-CM-")) {
+            return Self::Coremail;
         }
 
         // Check greeting for server identification
@@ -56,6 +69,13 @@ impl ServerType {
             if lower.contains("outlook") || lower.contains("microsoft") {
                 return Self::Outlook;
             }
+            // Fastmail runs Cyrus, so it must be checked first
+            if lower.contains("fastmail") || lower.contains("messagingengine") {
+                return Self::Fastmail;
+            }
+            if lower.contains("icloud") || lower.contains("iscream") || lower.contains("apple") {
+                return Self::ICloud;
+            }
             if lower.contains("dovecot") {
                 return Self::Dovecot;
             }
@@ -65,18 +85,91 @@ impl ServerType {
             if lower.contains("cyrus") {
                 return Self::Cyrus;
             }
-            if lower.contains("fastmail") {
-                return Self::Fastmail;
-            }
-            if lower.contains("icloud") || lower.contains("apple") {
-                return Self::ICloud;
+            if lower.contains("coremail") {
+                return Self::Coremail;
             }
         }
 
+        // iCloud advertises Apple push even when the greeting is opaque
+        if has_unknown(&|cap| cap == "XAPPLEPUSHSERVICE") {
+            return Self::ICloud;
+        }
+
         Self::Unknown
     }
 }
 
+/// Names of the special-use folders a server creates by default.
+///
+/// Used when the server doesn't advertise SPECIAL-USE attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultFolders {
+    /// Sent messages.
+    pub sent: &'static str,
+    /// Drafts.
+    pub drafts: &'static str,
+    /// Deleted messages.
+    pub trash: &'static str,
+    /// Spam.
+    pub junk: &'static str,
+    /// Archived messages.
+    pub archive: &'static str,
+}
+
+impl DefaultFolders {
+    /// Common names used by most servers.
+    pub const GENERIC: Self = Self {
+        sent: "Sent",
+        drafts: "Drafts",
+        trash: "Trash",
+        junk: "Junk",
+        archive: "Archive",
+    };
+
+    /// Returns the default folder names for a server type.
+    #[must_use]
+    pub const fn for_server(server_type: ServerType) -> Self {
+        match server_type {
+            ServerType::Gmail => Self {
+                sent: "[Gmail]/Sent Mail",
+                drafts: "[Gmail]/Drafts",
+                trash: "[Gmail]/Trash",
+                junk: "[Gmail]/Spam",
+                archive: "[Gmail]/All Mail",
+            },
+            ServerType::Outlook => Self {
+                trash: "Deleted",
+                ..Self::GENERIC
+            },
+            ServerType::Yahoo => Self {
+                drafts: "Draft",
+                junk: "Bulk",
+                ..Self::GENERIC
+            },
+            ServerType::ICloud => Self {
+                sent: "Sent Messages",
+                trash: "Deleted Messages",
+                ..Self::GENERIC
+            },
+            ServerType::Fastmail => Self {
+                junk: "Spam",
+                ..Self::GENERIC
+            },
+            ServerType::Unknown
+            | ServerType::Dovecot
+            | ServerType::Courier
+            | ServerType::Cyrus
+            | ServerType::Coremail => Self::GENERIC,
+        }
+    }
+}
+
+impl Default for DefaultFolders {
+    fn default() -> Self {
+        Self::GENERIC
+    }
+}
+
 /// Server-specific quirks and workarounds.
 #[derive(Debug, Clone, Default)]
 #[allow(clippy::struct_excessive_bools)]
@@ -87,6 +180,10 @@ pub struct ServerQuirks {
     /// Gmail uses labels instead of folders, with special semantics.
     pub gmail_labels: bool,
 
+    /// Server supports Gmail's `X-GM-EXT-1` extensions: label, thread ID
+    /// and message ID FETCH attributes and `X-GM-RAW` search.
+    pub gmail_extensions: bool,
+
     /// Some servers require INBOX to be uppercase.
     pub inbox_case_sensitive: bool,
 
@@ -108,6 +205,21 @@ pub struct ServerQuirks {
 
     /// Server may include extra whitespace in responses.
     pub lenient_parsing: bool,
+
+    /// Server rejects or mishandles the ALL/FAST/FULL FETCH macros, so the
+    /// attributes must be listed explicitly (iCloud).
+    pub avoid_fetch_all_macro: bool,
+
+    /// Server wants an ID command (RFC 2971) before LOGIN and may refuse or
+    /// throttle clients that skip it (iCloud, Yahoo, Coremail).
+    pub requires_id_command_before_login: bool,
+
+    /// Folder names to fall back on when SPECIAL-USE is not advertised.
+    pub default_folders: DefaultFolders,
+
+    /// Most UIDs to name in a single FETCH. Larger requests are split, since
+    /// some servers reject or truncate long commands (Gmail).
+    pub max_fetch_uids: usize,
 }
 
 impl ServerQuirks {
@@ -121,9 +233,12 @@ impl ServerQuirks {
 
         let base = Self {
             server_type,
+            gmail_extensions: capabilities.contains(&Capability::GmailExt1),
             native_move: has_move,
             literal_plus: has_literal_plus,
             lenient_parsing: true, // Enable lenient parsing by default
+            default_folders: DefaultFolders::for_server(server_type),
+            max_fetch_uids: 1000,
             ..Default::default()
         };
 
@@ -133,6 +248,7 @@ impl ServerQuirks {
                 inbox_case_sensitive: false,
                 idle_timeout_secs: 600, // 10 minutes
                 unordered_responses: true,
+                max_fetch_uids: 500,
                 ..base
             },
             ServerType::Outlook | ServerType::Fastmail => Self {
@@ -140,9 +256,23 @@ impl ServerQuirks {
                 idle_timeout_secs: 1740, // 29 minutes
                 ..base
             },
-            ServerType::Yahoo | ServerType::ICloud => Self {
+            ServerType::Yahoo => Self {
+                inbox_case_sensitive: false,
+                idle_timeout_secs: 1200, // 20 minutes
+                requires_id_command_before_login: true,
+                ..base
+            },
+            ServerType::ICloud => Self {
                 inbox_case_sensitive: false,
                 idle_timeout_secs: 1200, // 20 minutes
+                avoid_fetch_all_macro: true,
+                requires_id_command_before_login: true,
+                ..base
+            },
+            ServerType::Coremail => Self {
+                inbox_case_sensitive: false,
+                idle_timeout_secs: 1740,
+                requires_id_command_before_login: true,
                 ..base
             },
             ServerType::Dovecot => Self {
@@ -171,6 +301,37 @@ impl ServerQuirks {
         std::time::Duration::from_secs(u64::from(self.idle_timeout_secs))
     }
 
+    /// Adjusts FETCH items for the server.
+    ///
+    /// When the server mishandles FETCH macros, ALL, FAST and FULL are
+    /// expanded into their attributes. FULL's BODY is requested as
+    /// BODYSTRUCTURE, which carries the same information and more.
+    #[must_use]
+    pub fn fetch_items(&self, items: FetchItems) -> FetchItems {
+        if !self.avoid_fetch_all_macro {
+            return items;
+        }
+        let fast = [
+            FetchAttribute::Flags,
+            FetchAttribute::InternalDate,
+            FetchAttribute::Rfc822Size,
+        ];
+        match items {
+            FetchItems::Fast => FetchItems::Items(fast.to_vec()),
+            FetchItems::All => {
+                FetchItems::Items([fast.as_slice(), &[FetchAttribute::Envelope]].concat())
+            }
+            FetchItems::Full => FetchItems::Items(
+                [
+                    fast.as_slice(),
+                    &[FetchAttribute::Envelope, FetchAttribute::BodyStructure],
+                ]
+                .concat(),
+            ),
+            items @ FetchItems::Items(_) => items,
+        }
+    }
+
     /// Normalizes a mailbox name according to server quirks.
     ///
     /// For example, ensures INBOX is uppercase when required.
@@ -200,6 +361,8 @@ mod tests {
     fn test_detect_gmail() {
         let caps = vec![Capability::Unknown("X-GM-EXT-1".to_string())];
         assert_eq!(ServerType::detect(&caps, None), ServerType::Gmail);
+        let caps = vec![Capability::parse("X-GM-EXT-1")];
+        assert_eq!(ServerType::detect(&caps, None), ServerType::Gmail);
     }
 
     #[test]
@@ -211,11 +374,95 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_icloud_from_greeting() {
+        let caps = vec![
+            Capability::Unknown("XAPPLEPUSHSERVICE".to_string()),
+            Capability::Imap4Rev1,
+        ];
+        let greeting = "iSCREAM ready to rumble (1S:1159) p71-imap-iscream033";
+        assert_eq!(
+            ServerType::detect(&caps, Some(greeting)),
+            ServerType::ICloud
+        );
+        assert_eq!(ServerType::detect(&[], Some(greeting)), ServerType::ICloud);
+        assert_eq!(
+            ServerType::detect(&caps, Some("IMAP4rev1 server ready")),
+            ServerType::ICloud
+        );
+    }
+
+    #[test]
+    fn test_detect_fastmail_before_cyrus() {
+        let greeting = "imap.fastmail.com Cyrus IMAP 3.9.0-alpha0 server ready";
+        assert_eq!(
+            ServerType::detect(&[Capability::Imap4Rev1], Some(greeting)),
+            ServerType::Fastmail
+        );
+        assert_eq!(
+            ServerType::detect(&[], Some("mail.example.org Cyrus IMAP 3.4.2 server ready")),
+            ServerType::Cyrus
+        );
+    }
+
+    #[test]
+    fn test_detect_coremail() {
+        let caps = vec![
+            Capability::Imap4Rev1,
+            Capability::Id,
+            Capability::Unknown("X-CM-EXT-1".to_string()),
+        ];
+        assert_eq!(ServerType::detect(&caps, None), ServerType::Coremail);
+        assert_eq!(
+            ServerType::detect(
+                &[],
+                Some("Coremail System IMap Server Ready(163com[10774b260cc7a37d26d71b52404dcf5c])")
+            ),
+            ServerType::Coremail
+        );
+        assert!(
+            ServerQuirks::for_server(ServerType::Coremail, &caps).requires_id_command_before_login
+        );
+    }
+
+    #[test]
+    fn test_icloud_quirks() {
+        let quirks = ServerQuirks::for_server(ServerType::ICloud, &[]);
+        assert!(quirks.avoid_fetch_all_macro);
+        assert!(quirks.requires_id_command_before_login);
+        assert_eq!(quirks.default_folders.sent, "Sent Messages");
+        assert_eq!(
+            quirks.fetch_items(FetchItems::All),
+            FetchItems::Items(vec![
+                FetchAttribute::Flags,
+                FetchAttribute::InternalDate,
+                FetchAttribute::Rfc822Size,
+                FetchAttribute::Envelope,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_fetch_macros_kept_by_default() {
+        let quirks = ServerQuirks::for_server(ServerType::Fastmail, &[]);
+        assert!(!quirks.requires_id_command_before_login);
+        assert_eq!(quirks.default_folders.junk, "Spam");
+        assert_eq!(quirks.fetch_items(FetchItems::All), FetchItems::All);
+    }
+
     #[test]
     fn test_gmail_quirks() {
         let quirks = ServerQuirks::for_server(ServerType::Gmail, &[]);
         assert!(quirks.gmail_labels);
+        assert!(!quirks.gmail_extensions);
         assert_eq!(quirks.idle_timeout_secs, 600);
+        assert!(
+            quirks.max_fetch_uids
+                < ServerQuirks::for_server(ServerType::Unknown, &[]).max_fetch_uids
+        );
+
+        let quirks = ServerQuirks::for_server(ServerType::Gmail, &[Capability::GmailExt1]);
+        assert!(quirks.gmail_extensions);
     }
 
     #[test]
@@ -68,12 +68,15 @@ impl FetchStreamState {
                 Some(result)
             }
             Response::Tagged {
-                tag, status, text, ..
+                tag,
+                status,
+                code,
+                text,
             } => {
                 if Some(&tag.as_str().to_string()) == self.tag.as_ref() {
                     self.complete = true;
                     if !matches!(status, crate::types::Status::Ok) {
-                        self.error = Some(Error::No(text));
+                        self.error = Some(Error::from_tagged(status, code, text));
                     }
                 }
                 None
@@ -251,6 +254,15 @@ impl StreamFetchOptions {
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
 mod tests {
     use super::*;
 
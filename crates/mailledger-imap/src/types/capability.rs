@@ -23,6 +23,18 @@ impl Status {
     }
 }
 
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "OK",
+            Self::No => "NO",
+            Self::Bad => "BAD",
+            Self::PreAuth => "PREAUTH",
+            Self::Bye => "BYE",
+        })
+    }
+}
+
 /// Server capability.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Capability {
@@ -62,6 +74,34 @@ pub enum Capability {
     Id,
     /// SPECIAL-USE mailboxes (RFC 6154)
     SpecialUse,
+    /// SORT extension (RFC 5256)
+    Sort,
+    /// THREAD extension with the given algorithm (RFC 5256)
+    Thread(String),
+    /// ESEARCH extension (RFC 4731)
+    ESearch,
+    /// MULTIAPPEND extension (RFC 3502)
+    MultiAppend,
+    /// BINARY extension (RFC 3516)
+    Binary,
+    /// COMPRESS extension with the given algorithm (RFC 4978)
+    Compress(String),
+    /// SASL initial response (RFC 4959)
+    SaslIr,
+    /// LIST-EXTENDED (RFC 5258)
+    ListExtended,
+    /// LIST-STATUS (RFC 5819)
+    ListStatus,
+    /// QUOTA extension (RFC 2087, RFC 9208)
+    Quota,
+    /// METADATA extension: server and mailbox annotations (RFC 5464)
+    Metadata,
+    /// METADATA-SERVER extension: server annotations only (RFC 5464)
+    MetadataServer,
+    /// UNSELECT command (RFC 3691)
+    Unselect,
+    /// Gmail IMAP extensions: labels, thread and message IDs, raw search
+    GmailExt1,
     /// Unknown capability
     Unknown(String),
 }
@@ -89,7 +129,21 @@ impl Capability {
             "UNSTRICT" => Self::Unstrict,
             "ID" => Self::Id,
             "SPECIAL-USE" => Self::SpecialUse,
+            "SORT" => Self::Sort,
+            "ESEARCH" => Self::ESearch,
+            "BINARY" => Self::Binary,
+            "MULTIAPPEND" => Self::MultiAppend,
+            "SASL-IR" => Self::SaslIr,
+            "LIST-EXTENDED" => Self::ListExtended,
+            "LIST-STATUS" => Self::ListStatus,
+            "QUOTA" => Self::Quota,
+            "METADATA" => Self::Metadata,
+            "METADATA-SERVER" => Self::MetadataServer,
+            "UNSELECT" => Self::Unselect,
+            "X-GM-EXT-1" => Self::GmailExt1,
             _ if upper.starts_with("AUTH=") => Self::Auth(s[5..].to_string()),
+            _ if upper.starts_with("THREAD=") => Self::Thread(s[7..].to_string()),
+            _ if upper.starts_with("COMPRESS=") => Self::Compress(s[9..].to_string()),
             _ => Self::Unknown(s.to_string()),
         }
     }
@@ -116,6 +170,20 @@ impl std::fmt::Display for Capability {
             Self::Unstrict => write!(f, "UNSTRICT"),
             Self::Id => write!(f, "ID"),
             Self::SpecialUse => write!(f, "SPECIAL-USE"),
+            Self::Sort => write!(f, "SORT"),
+            Self::Thread(algorithm) => write!(f, "THREAD={algorithm}"),
+            Self::ESearch => write!(f, "ESEARCH"),
+            Self::Binary => write!(f, "BINARY"),
+            Self::MultiAppend => write!(f, "MULTIAPPEND"),
+            Self::Compress(algorithm) => write!(f, "COMPRESS={algorithm}"),
+            Self::SaslIr => write!(f, "SASL-IR"),
+            Self::ListExtended => write!(f, "LIST-EXTENDED"),
+            Self::ListStatus => write!(f, "LIST-STATUS"),
+            Self::Quota => write!(f, "QUOTA"),
+            Self::Metadata => write!(f, "METADATA"),
+            Self::MetadataServer => write!(f, "METADATA-SERVER"),
+            Self::Unselect => write!(f, "UNSELECT"),
+            Self::GmailExt1 => write!(f, "X-GM-EXT-1"),
             Self::Unknown(s) => write!(f, "{s}"),
         }
     }
@@ -267,6 +335,75 @@ mod tests {
             assert_eq!(Capability::parse("SPECIAL-USE"), Capability::SpecialUse);
         }
 
+        #[test]
+        fn parse_sort() {
+            assert_eq!(Capability::parse("SORT"), Capability::Sort);
+            assert_eq!(
+                Capability::parse("SORT=DISPLAY"),
+                Capability::Unknown("SORT=DISPLAY".to_string())
+            );
+        }
+
+        #[test]
+        fn parse_esearch() {
+            assert_eq!(Capability::parse("ESEARCH"), Capability::ESearch);
+        }
+
+        #[test]
+        fn parse_thread() {
+            assert_eq!(
+                Capability::parse("THREAD=REFERENCES"),
+                Capability::Thread("REFERENCES".to_string())
+            );
+            assert_eq!(
+                Capability::parse("thread=orderedsubject"),
+                Capability::Thread("orderedsubject".to_string())
+            );
+        }
+
+        #[test]
+        fn parse_binary() {
+            assert_eq!(Capability::parse("BINARY"), Capability::Binary);
+        }
+
+        #[test]
+        fn parse_multiappend() {
+            assert_eq!(Capability::parse("MULTIAPPEND"), Capability::MultiAppend);
+        }
+
+        #[test]
+        fn parse_compress() {
+            assert_eq!(
+                Capability::parse("COMPRESS=DEFLATE"),
+                Capability::Compress("DEFLATE".to_string())
+            );
+        }
+
+        #[test]
+        fn parse_sasl_ir() {
+            assert_eq!(Capability::parse("SASL-IR"), Capability::SaslIr);
+        }
+
+        #[test]
+        fn parse_list_extended() {
+            assert_eq!(Capability::parse("LIST-EXTENDED"), Capability::ListExtended);
+            assert_eq!(Capability::parse("LIST-STATUS"), Capability::ListStatus);
+        }
+
+        #[test]
+        fn parse_quota() {
+            assert_eq!(Capability::parse("QUOTA"), Capability::Quota);
+        }
+
+        #[test]
+        fn parse_metadata() {
+            assert_eq!(Capability::parse("METADATA"), Capability::Metadata);
+            assert_eq!(
+                Capability::parse("metadata-server"),
+                Capability::MetadataServer
+            );
+        }
+
         #[test]
         fn parse_unknown() {
             let cap = Capability::parse("XSOMETHING");
@@ -295,6 +432,22 @@ mod tests {
             );
         }
 
+        #[test]
+        fn display_thread() {
+            assert_eq!(
+                format!("{}", Capability::Thread("REFERENCES".to_string())),
+                "THREAD=REFERENCES"
+            );
+        }
+
+        #[test]
+        fn display_compress() {
+            assert_eq!(
+                format!("{}", Capability::Compress("DEFLATE".to_string())),
+                "COMPRESS=DEFLATE"
+            );
+        }
+
         #[test]
         fn display_unknown() {
             assert_eq!(
@@ -1,7 +1,14 @@
 //! Mailbox types.
 
+use base64::Engine;
+use base64::alphabet::IMAP_MUTF7;
+use base64::engine::general_purpose::{GeneralPurpose, NO_PAD};
+
 use super::{Flags, SeqNum, Uid, UidValidity};
 
+/// Modified base64 used inside `&...-` runs (RFC 3501 §5.1.3).
+const MUTF7: GeneralPurpose = GeneralPurpose::new(&IMAP_MUTF7, NO_PAD);
+
 /// Mailbox name.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Mailbox(pub String);
@@ -24,6 +31,88 @@ impl Mailbox {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Decodes a mailbox name as sent by the server (modified UTF-7,
+    /// RFC 3501 §5.1.3).
+    ///
+    /// Names that aren't valid modified UTF-7, such as raw UTF-8 from a
+    /// server with UTF8=ACCEPT enabled, are kept as they are.
+    #[must_use]
+    pub fn from_wire(bytes: &[u8]) -> Self {
+        let raw = String::from_utf8_lossy(bytes);
+        Self(decode_utf7(&raw).unwrap_or_else(|| raw.into_owned()))
+    }
+
+    /// Encodes the name in modified UTF-7 for sending to the server.
+    #[must_use]
+    pub fn to_wire(&self) -> Vec<u8> {
+        encode_utf7(&self.0).into_bytes()
+    }
+}
+
+/// Encodes a string in modified UTF-7.
+pub fn encode_utf7(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending: Vec<u8> = Vec::new();
+
+    for c in s.chars() {
+        if (' '..='~').contains(&c) {
+            flush_utf7(&mut out, &mut pending);
+            if c == '&' {
+                out.push_str("&-");
+            } else {
+                out.push(c);
+            }
+        } else {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                pending.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+    flush_utf7(&mut out, &mut pending);
+    out
+}
+
+/// Writes buffered UTF-16BE bytes as a `&...-` run.
+fn flush_utf7(out: &mut String, pending: &mut Vec<u8>) {
+    if !pending.is_empty() {
+        out.push('&');
+        out.push_str(&MUTF7.encode(&pending));
+        out.push('-');
+        pending.clear();
+    }
+}
+
+/// Decodes modified UTF-7, returning `None` if `s` isn't valid.
+fn decode_utf7(s: &str) -> Option<String> {
+    if !s.is_ascii() {
+        return None;
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let run = &rest[start + 1..];
+        let end = run.find('-')?;
+        if end == 0 {
+            out.push('&');
+        } else {
+            let bytes = MUTF7.decode(&run[..end]).ok()?;
+            if bytes.len() % 2 != 0 {
+                return None;
+            }
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            out.push_str(&String::from_utf16(&units).ok()?);
+        }
+        rest = &run[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
 }
 
 impl std::fmt::Display for Mailbox {
@@ -32,8 +121,8 @@ impl std::fmt::Display for Mailbox {
     }
 }
 
-/// Mailbox status information from SELECT/EXAMINE.
-#[derive(Debug, Clone, Default)]
+/// Mailbox status information from SELECT/EXAMINE or STATUS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MailboxStatus {
     /// Number of messages in the mailbox.
     pub exists: u32,
@@ -41,6 +130,8 @@ pub struct MailboxStatus {
     pub recent: u32,
     /// First unseen message sequence number.
     pub unseen: Option<SeqNum>,
+    /// Number of unseen messages (STATUS only).
+    pub unseen_count: Option<u32>,
     /// Next UID to be assigned.
     pub uid_next: Option<Uid>,
     /// UIDVALIDITY value.
@@ -64,6 +155,22 @@ pub struct ListResponse {
     pub delimiter: Option<char>,
     /// Mailbox name.
     pub mailbox: Mailbox,
+    /// CHILDINFO extended data (LIST-EXTENDED), e.g. `SUBSCRIBED` when a
+    /// child matches the selection but this mailbox doesn't.
+    pub child_info: Vec<String>,
+    /// Status returned alongside the entry (LIST-STATUS, RFC 5819).
+    pub status: Option<MailboxStatus>,
+}
+
+impl ListResponse {
+    /// Returns the server-declared role of this mailbox (RFC 6154), if any.
+    ///
+    /// Lets callers find the Sent or Trash folder without guessing from
+    /// localized names.
+    #[must_use]
+    pub fn special_use(&self) -> Option<&MailboxAttribute> {
+        self.attributes.iter().find(|a| a.is_special_use())
+    }
 }
 
 /// Mailbox attributes from LIST response.
@@ -128,6 +235,22 @@ impl MailboxAttribute {
             _ => Self::Unknown(s.to_string()),
         }
     }
+
+    /// Returns true if this attribute declares a mailbox role (RFC 6154, RFC 8457).
+    #[must_use]
+    pub const fn is_special_use(&self) -> bool {
+        matches!(
+            self,
+            Self::All
+                | Self::Archive
+                | Self::Drafts
+                | Self::Flagged
+                | Self::Junk
+                | Self::Sent
+                | Self::Trash
+                | Self::Important
+        )
+    }
 }
 
 #[cfg(test)]
@@ -170,6 +293,37 @@ mod tests {
             assert_eq!(format!("{mb}"), "Sent");
         }
 
+        #[test]
+        fn utf7_round_trip() {
+            for (decoded, wire) in [
+                ("INBOX", "INBOX"),
+                ("Entwürfe", "Entw&APw-rfe"),
+                ("受信トレイ", "&U9dP4TDIMOwwpA-"),
+                ("~peter/mail/台北/日本語", "~peter/mail/&U,BTFw-/&ZeVnLIqe-"),
+                ("Tom & Jerry", "Tom &- Jerry"),
+                ("&", "&-"),
+                ("😀", "&2D3eAA-"),
+            ] {
+                let mb = Mailbox::new(decoded);
+                assert_eq!(mb.to_wire(), wire.as_bytes());
+                assert_eq!(Mailbox::from_wire(wire.as_bytes()), mb);
+                assert_eq!(mb.as_str(), decoded);
+            }
+        }
+
+        #[test]
+        fn utf7_adjacent_runs_are_merged() {
+            assert_eq!(Mailbox::new("äö").to_wire(), b"&AOQA9g-");
+        }
+
+        #[test]
+        fn from_wire_keeps_invalid_utf7() {
+            // Unterminated run, bad base64, and raw UTF-8 from UTF8=ACCEPT
+            for raw in ["R&D", "&!!!-", "Entwürfe"] {
+                assert_eq!(Mailbox::from_wire(raw.as_bytes()).as_str(), raw);
+            }
+        }
+
         #[test]
         fn equality() {
             let mb1 = Mailbox::new("INBOX");
@@ -204,6 +358,7 @@ mod tests {
                 exists: 100,
                 recent: 5,
                 unseen: SeqNum::new(50),
+                unseen_count: None,
                 uid_next: Uid::new(101),
                 uid_validity: UidValidity::new(123456),
                 flags: Flags::from_vec(vec![Flag::Seen, Flag::Flagged]),
@@ -227,6 +382,8 @@ mod tests {
                 attributes: vec![MailboxAttribute::HasChildren, MailboxAttribute::Sent],
                 delimiter: Some('/'),
                 mailbox: Mailbox::new("Sent"),
+                child_info: Vec::new(),
+                status: None,
             };
             assert_eq!(resp.attributes.len(), 2);
             assert_eq!(resp.delimiter, Some('/'));
@@ -239,6 +396,8 @@ mod tests {
                 attributes: vec![],
                 delimiter: None,
                 mailbox: Mailbox::new("INBOX"),
+                child_info: Vec::new(),
+                status: None,
             };
             assert!(resp.delimiter.is_none());
         }
@@ -15,8 +15,9 @@ mod sequence;
 pub use capability::{Capability, Status};
 pub use flags::{Flag, Flags};
 pub use identifiers::{SeqNum, Tag, Uid, UidValidity};
+pub(crate) use mailbox::encode_utf7;
 pub use mailbox::{ListResponse, Mailbox, MailboxAttribute, MailboxStatus};
-pub use response_code::ResponseCode;
+pub use response_code::{AppendUid, CopyUid, ResponseCode};
 pub use sequence::{SequenceSet, UidSet};
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 //! Response codes.
 
-use super::{Capability, Flag, SeqNum, Uid, UidValidity};
+use super::{Capability, Flag, SeqNum, Uid, UidSet, UidValidity};
 
 /// Response code from a tagged response.
 ///
@@ -21,27 +21,35 @@ pub enum ResponseCode {
     ReadWrite,
     /// TRYCREATE: Mailbox doesn't exist, but can be created.
     TryCreate,
+    /// BADCHARSET: SEARCH charset not supported; lists the ones that are.
+    BadCharset(Vec<String>),
+    /// OVERQUOTA: Operation would exceed a quota (RFC 9208).
+    OverQuota,
+    /// UNAVAILABLE: Temporary failure; the command may succeed later (RFC 5530).
+    Unavailable,
+    /// AUTHENTICATIONFAILED: Credentials were rejected (RFC 5530).
+    AuthenticationFailed,
     /// UIDNEXT: Next UID to be assigned.
     UidNext(Uid),
     /// UIDVALIDITY: Unique identifier validity value.
     UidValidity(UidValidity),
     /// UNSEEN: First unseen message sequence number.
     Unseen(SeqNum),
-    /// APPENDUID: UID assigned to appended message.
+    /// APPENDUID: UIDs assigned to appended messages (UIDPLUS).
     AppendUid {
         /// UIDVALIDITY of the mailbox.
-        uidvalidity: UidValidity,
-        /// UID of the appended message.
-        uid: Uid,
+        validity: UidValidity,
+        /// UIDs of the appended messages; a set for MULTIAPPEND.
+        uids: UidSet,
     },
-    /// COPYUID: UIDs of copied messages.
+    /// COPYUID: UIDs of copied messages (UIDPLUS).
     CopyUid {
         /// UIDVALIDITY of the destination mailbox.
-        uidvalidity: UidValidity,
+        validity: UidValidity,
         /// Source UIDs.
-        source_uids: Vec<Uid>,
-        /// Destination UIDs.
-        dest_uids: Vec<Uid>,
+        source: UidSet,
+        /// Destination UIDs, in the same order as `source`.
+        dest: UidSet,
     },
     /// HIGHESTMODSEQ: Highest mod-sequence value (CONDSTORE).
     HighestModSeq(u64),
@@ -51,6 +59,57 @@ pub enum ResponseCode {
     Unknown(String),
 }
 
+/// Where APPEND placed the new messages, from `[APPENDUID ...]` (RFC 4315).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppendUid {
+    /// UIDVALIDITY of the target mailbox.
+    pub validity: UidValidity,
+    /// UIDs of the appended messages.
+    pub uids: UidSet,
+}
+
+/// Where COPY placed the messages, from `[COPYUID ...]` (RFC 4315).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyUid {
+    /// UIDVALIDITY of the destination mailbox.
+    pub validity: UidValidity,
+    /// UIDs of the source messages.
+    pub source: UidSet,
+    /// UIDs of the copies, in the same order as `source`.
+    pub dest: UidSet,
+}
+
+impl ResponseCode {
+    /// Returns the APPENDUID data if this is an `APPENDUID` code.
+    #[must_use]
+    pub fn append_uid(&self) -> Option<AppendUid> {
+        match self {
+            Self::AppendUid { validity, uids } => Some(AppendUid {
+                validity: *validity,
+                uids: uids.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the COPYUID data if this is a `COPYUID` code.
+    #[must_use]
+    pub fn copy_uid(&self) -> Option<CopyUid> {
+        match self {
+            Self::CopyUid {
+                validity,
+                source,
+                dest,
+            } => Some(CopyUid {
+                validity: *validity,
+                source: source.clone(),
+                dest: dest.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -154,12 +213,12 @@ mod tests {
         let uv = UidValidity::new(999).unwrap();
         let uid = Uid::new(50).unwrap();
         let code = ResponseCode::AppendUid {
-            uidvalidity: uv,
-            uid,
+            validity: uv,
+            uids: UidSet::single(uid),
         };
-        if let ResponseCode::AppendUid { uidvalidity, uid } = code {
-            assert_eq!(uidvalidity.get(), 999);
-            assert_eq!(uid.get(), 50);
+        if let ResponseCode::AppendUid { validity, uids } = code {
+            assert_eq!(validity.get(), 999);
+            assert_eq!(uids, UidSet::single(uid));
         } else {
             panic!("Expected AppendUid variant");
         }
@@ -168,25 +227,16 @@ mod tests {
     #[test]
     fn copy_uid() {
         let uv = UidValidity::new(888).unwrap();
-        let src = vec![Uid::new(1).unwrap(), Uid::new(2).unwrap()];
-        let dst = vec![Uid::new(101).unwrap(), Uid::new(102).unwrap()];
         let code = ResponseCode::CopyUid {
-            uidvalidity: uv,
-            source_uids: src,
-            dest_uids: dst,
+            validity: uv,
+            source: UidSet::parse("1:2").unwrap(),
+            dest: UidSet::parse("101:102").unwrap(),
         };
-        if let ResponseCode::CopyUid {
-            uidvalidity,
-            source_uids,
-            dest_uids,
-        } = code
-        {
-            assert_eq!(uidvalidity.get(), 888);
-            assert_eq!(source_uids.len(), 2);
-            assert_eq!(dest_uids.len(), 2);
-        } else {
-            panic!("Expected CopyUid variant");
-        }
+        let copy = code.copy_uid().unwrap();
+        assert_eq!(copy.validity.get(), 888);
+        assert_eq!(copy.source.to_string(), "1:2");
+        assert_eq!(copy.dest.to_string(), "101:102");
+        assert!(code.append_uid().is_none());
     }
 
     #[test]
@@ -1,7 +1,47 @@
 //! Sequence sets for message ranges.
 
+use std::num::NonZeroU32;
+
 use super::{SeqNum, Uid};
 
+/// Sorts and dedups `values`, then merges consecutive numbers into
+/// inclusive `(start, end)` runs.
+fn coalesce(values: impl Iterator<Item = NonZeroU32>) -> Vec<(NonZeroU32, NonZeroU32)> {
+    let mut sorted: Vec<NonZeroU32> = values.collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut runs: Vec<(NonZeroU32, NonZeroU32)> = Vec::new();
+    for n in sorted {
+        match runs.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(n) => *end = n,
+            _ => runs.push((n, n)),
+        }
+    }
+    runs
+}
+
+/// Wraps several elements in a set, or returns a lone element as-is.
+fn collapse<T>(mut items: Vec<T>, set: impl FnOnce(Vec<T>) -> T) -> Option<T> {
+    match items.len() {
+        0 => None,
+        1 => items.pop(),
+        _ => Some(set(items)),
+    }
+}
+
+/// Yields every number from `start` to `end`, in either order, as IMAP
+/// treats `5:3` the same as `3:5`.
+fn span(start: NonZeroU32, end: NonZeroU32) -> impl Iterator<Item = NonZeroU32> {
+    (start.min(end).get()..=start.max(end).get()).filter_map(NonZeroU32::new)
+}
+
+/// Number of values [`span`] yields.
+fn span_len(start: NonZeroU32, end: NonZeroU32) -> usize {
+    let count = u64::from(start.get().abs_diff(end.get())) + 1;
+    usize::try_from(count).unwrap_or(usize::MAX)
+}
+
 /// Sequence set for specifying message ranges.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SequenceSet {
@@ -29,6 +69,61 @@ impl SequenceSet {
     pub fn range(start: u32, end: u32) -> Option<Self> {
         Some(Self::Range(SeqNum::new(start)?, SeqNum::new(end)?))
     }
+
+    /// Builds the most compact set covering `seq_nums`.
+    ///
+    /// The numbers are sorted and deduplicated, and runs of consecutive
+    /// numbers become ranges, so `1,2,3,5,8` is written `1:3,5,8`. Returns
+    /// `None` if `seq_nums` is empty.
+    #[must_use]
+    pub fn from_seq_nums(seq_nums: &[SeqNum]) -> Option<Self> {
+        let items = coalesce(seq_nums.iter().map(|n| n.0))
+            .into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    Self::Single(SeqNum(start))
+                } else {
+                    Self::Range(SeqNum(start), SeqNum(end))
+                }
+            })
+            .collect();
+        collapse(items, Self::Set)
+    }
+
+    /// Iterates over the sequence numbers in the set, in the order written.
+    ///
+    /// `*` stands for the last message, which isn't known here, so
+    /// [`SequenceSet::All`] and [`SequenceSet::RangeFrom`] are skipped.
+    /// Numbers listed twice are yielded twice.
+    pub fn iter(&self) -> impl Iterator<Item = SeqNum> + '_ {
+        self.numbers().map(SeqNum)
+    }
+
+    /// Returns how many sequence numbers [`iter`](Self::iter) yields.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Single(_) => 1,
+            Self::Range(start, end) => span_len(start.0, end.0),
+            Self::RangeFrom(_) | Self::All => 0,
+            Self::Set(items) => items.iter().map(Self::len).sum(),
+        }
+    }
+
+    /// Returns `true` if [`iter`](Self::iter) yields nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn numbers(&self) -> Box<dyn Iterator<Item = NonZeroU32> + '_> {
+        match self {
+            Self::Single(n) => Box::new(std::iter::once(n.0)),
+            Self::Range(start, end) => Box::new(span(start.0, end.0)),
+            Self::RangeFrom(_) | Self::All => Box::new(std::iter::empty()),
+            Self::Set(items) => Box::new(items.iter().flat_map(Self::numbers)),
+        }
+    }
 }
 
 impl std::fmt::Display for SequenceSet {
@@ -74,6 +169,87 @@ impl UidSet {
         Self::Range(start, end)
     }
 
+    /// Builds the most compact set covering `uids`, such as search results.
+    ///
+    /// The UIDs are sorted and deduplicated, and runs of consecutive UIDs
+    /// become ranges, so `1,2,3,5,8` is written `1:3,5,8`. Returns `None` if
+    /// `uids` is empty.
+    #[must_use]
+    pub fn from_uids(uids: &[Uid]) -> Option<Self> {
+        let items = coalesce(uids.iter().map(|uid| uid.0))
+            .into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    Self::Single(Uid(start))
+                } else {
+                    Self::Range(Uid(start), Uid(end))
+                }
+            })
+            .collect();
+        collapse(items, Self::Set)
+    }
+
+    /// Iterates over the UIDs in the set, in the order written.
+    ///
+    /// `*` stands for the highest UID in the mailbox, which isn't known
+    /// here, so [`UidSet::All`] and [`UidSet::RangeFrom`] are skipped. UIDs
+    /// listed twice are yielded twice.
+    pub fn iter(&self) -> impl Iterator<Item = Uid> + '_ {
+        self.numbers().map(Uid)
+    }
+
+    /// Returns how many UIDs [`iter`](Self::iter) yields.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Single(_) => 1,
+            Self::Range(start, end) => span_len(start.0, end.0),
+            Self::RangeFrom(_) | Self::All => 0,
+            Self::Set(items) => items.iter().map(Self::len).sum(),
+        }
+    }
+
+    /// Returns `true` if [`iter`](Self::iter) yields nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn numbers(&self) -> Box<dyn Iterator<Item = NonZeroU32> + '_> {
+        match self {
+            Self::Single(uid) => Box::new(std::iter::once(uid.0)),
+            Self::Range(start, end) => Box::new(span(start.0, end.0)),
+            Self::RangeFrom(_) | Self::All => Box::new(std::iter::empty()),
+            Self::Set(items) => Box::new(items.iter().flat_map(Self::numbers)),
+        }
+    }
+
+    /// Parses an IMAP sequence-set string such as `3:5,7,9:*`.
+    ///
+    /// Returns `None` if the string is empty or contains a zero or
+    /// non-numeric UID. A single element is returned as-is rather than
+    /// wrapped in [`UidSet::Set`].
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let parse_uid = |part: &str| part.parse().ok().and_then(Uid::new);
+
+        let mut items = s
+            .split(',')
+            .map(|item| match item.split_once(':') {
+                _ if item == "*" => Some(Self::All),
+                Some((start, "*")) => parse_uid(start).map(Self::RangeFrom),
+                Some((start, end)) => Some(Self::Range(parse_uid(start)?, parse_uid(end)?)),
+                None => parse_uid(item).map(Self::Single),
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        match items.len() {
+            0 => None,
+            1 => items.pop(),
+            _ => Some(Self::Set(items)),
+        }
+    }
+
     /// Converts this UID set to a sequence set for use in UID commands.
     ///
     /// This is used internally for UID FETCH, UID STORE, etc. commands
@@ -189,6 +365,33 @@ mod tests {
             assert_eq!(format!("{seq}"), "*");
         }
 
+        #[test]
+        fn from_seq_nums_coalesces() {
+            let nums: Vec<SeqNum> = [8, 2, 1, 5, 3, 2]
+                .into_iter()
+                .filter_map(SeqNum::new)
+                .collect();
+            let seq = SequenceSet::from_seq_nums(&nums).unwrap();
+            assert_eq!(seq.to_string(), "1:3,5,8");
+            assert_eq!(seq.len(), 5);
+            let back: Vec<u32> = seq.iter().map(SeqNum::get).collect();
+            assert_eq!(back, [1, 2, 3, 5, 8]);
+            assert_eq!(SequenceSet::from_seq_nums(&[]), None);
+        }
+
+        #[test]
+        fn open_ended_sets_are_not_enumerated() {
+            let seq = SequenceSet::Set(vec![
+                SequenceSet::range(4, 2).unwrap(),
+                SequenceSet::RangeFrom(SeqNum::new(9).unwrap()),
+                SequenceSet::All,
+            ]);
+            let nums: Vec<u32> = seq.iter().map(SeqNum::get).collect();
+            assert_eq!(nums, [2, 3, 4]);
+            assert_eq!(seq.len(), 3);
+            assert!(SequenceSet::All.is_empty());
+        }
+
         #[test]
         fn display_set() {
             let seq = SequenceSet::Set(vec![
@@ -266,6 +469,78 @@ mod tests {
             }
         }
 
+        #[test]
+        fn parse_roundtrip() {
+            for input in ["7", "1:5", "9:*", "*", "3,6,9", "1:3,5,8:*"] {
+                assert_eq!(UidSet::parse(input).unwrap().to_string(), input);
+            }
+        }
+
+        #[test]
+        fn parse_single_is_not_wrapped() {
+            assert_eq!(
+                UidSet::parse("42"),
+                Some(UidSet::single(Uid::new(42).unwrap()))
+            );
+        }
+
+        #[test]
+        fn parse_invalid() {
+            assert_eq!(UidSet::parse(""), None);
+            assert_eq!(UidSet::parse("0"), None);
+            assert_eq!(UidSet::parse("1,,2"), None);
+            assert_eq!(UidSet::parse("a:3"), None);
+        }
+
+        #[test]
+        fn from_uids_coalesces_consecutive_uids() {
+            let uids: Vec<Uid> = [5, 1, 3, 2, 8, 3]
+                .into_iter()
+                .filter_map(Uid::new)
+                .collect();
+            let set = UidSet::from_uids(&uids).unwrap();
+            assert_eq!(set.to_string(), "1:3,5,8");
+            assert_eq!(set.len(), 5);
+            let back: Vec<u32> = set.iter().map(Uid::get).collect();
+            assert_eq!(back, [1, 2, 3, 5, 8]);
+        }
+
+        #[test]
+        fn from_uids_single_and_empty() {
+            let uid = Uid::new(42).unwrap();
+            assert_eq!(UidSet::from_uids(&[uid, uid]), Some(UidSet::single(uid)));
+            assert_eq!(UidSet::from_uids(&[]), None);
+            assert_eq!(
+                UidSet::from_uids(&[uid, Uid::new(43).unwrap()]),
+                Some(UidSet::range(uid, Uid::new(43).unwrap()))
+            );
+        }
+
+        #[test]
+        fn from_uids_large_gappy_set() {
+            // Blocks of 100 consecutive UIDs, each followed by a gap of 50
+            let uids: Vec<Uid> = (0..1000u32)
+                .map(|i| (i / 100) * 150 + i % 100 + 1)
+                .filter_map(Uid::new)
+                .collect();
+            let set = UidSet::from_uids(&uids).unwrap();
+            assert!(matches!(&set, UidSet::Set(items) if items.len() == 10));
+            assert!(set.to_string().starts_with("1:100,151:250,301:400,"));
+            assert!(set.to_string().ends_with(",1351:1450"));
+            assert_eq!(set.len(), 1000);
+            assert!(set.iter().eq(uids.iter().copied()));
+        }
+
+        #[test]
+        fn iter_skips_open_ended_ranges() {
+            let set = UidSet::parse("3:1,7,9:*").unwrap();
+            let uids: Vec<u32> = set.iter().map(Uid::get).collect();
+            assert_eq!(uids, [1, 2, 3, 7]);
+            assert_eq!(set.len(), 4);
+            assert!(UidSet::All.is_empty());
+            assert!(!set.is_empty());
+        }
+
         #[test]
         fn as_sequence_set_all() {
             let set = UidSet::All;
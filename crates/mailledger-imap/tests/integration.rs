@@ -12,31 +12,33 @@
 
 use std::io::{self, Cursor};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
-use mailledger_imap::{Client, FetchItems, ResponseParser, SequenceSet};
+use mailledger_imap::{Client, FetchItems, ResponseParser, SearchCriteria, SequenceSet};
 
 /// Mock stream that returns predefined responses.
 struct MockStream {
     /// Responses to return (in order).
     responses: Cursor<Vec<u8>>,
-    /// Captured commands sent by the client.
-    sent: Vec<u8>,
+    /// Captured commands sent by the client, shared so tests can inspect
+    /// them after the client takes ownership of the stream.
+    sent: Arc<Mutex<Vec<u8>>>,
 }
 
 impl MockStream {
     fn new(responses: &[u8]) -> Self {
         Self {
             responses: Cursor::new(responses.to_vec()),
-            sent: Vec::new(),
+            sent: Arc::default(),
         }
     }
 
-    #[allow(dead_code)]
-    fn sent_data(&self) -> &[u8] {
-        &self.sent
+    /// Returns a handle to the commands sent by the client.
+    fn sent_data(&self) -> Arc<Mutex<Vec<u8>>> {
+        Arc::clone(&self.sent)
     }
 }
 
@@ -64,11 +66,11 @@ impl AsyncRead for MockStream {
 
 impl AsyncWrite for MockStream {
     fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         _cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        self.sent.extend_from_slice(buf);
+        self.sent.lock().unwrap().extend_from_slice(buf);
         Poll::Ready(Ok(buf.len()))
     }
 
@@ -276,3 +278,1715 @@ fn test_capability_parsing() {
         _ => panic!("Expected AUTH capability"),
     }
 }
+
+#[tokio::test]
+async fn test_condstore_select_and_fetch_changed_since() {
+    use mailledger_imap::parser::FetchItem;
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 CONDSTORE] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 3 EXISTS\r\n\
+                   * OK [UIDVALIDITY 42] UIDs valid\r\n\
+                   * OK [HIGHESTMODSEQ 7011231777] Highest\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   * 2 FETCH (UID 8 FLAGS (\\Seen) MODSEQ (7011231778))\r\n\
+                   A0002 OK FETCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, status) = client.select_condstore("INBOX").await.unwrap();
+    assert_eq!(status.exists, 3);
+    assert_eq!(status.highest_mod_seq, Some(7_011_231_777));
+
+    let changed = client
+        .fetch_changed_since(&SequenceSet::All, FetchItems::Fast, 7_011_231_777)
+        .await
+        .unwrap();
+    assert_eq!(changed.len(), 1);
+    assert!(changed[0].1.contains(&FetchItem::ModSeq(7_011_231_778)));
+}
+
+#[tokio::test]
+async fn test_uid_sort_returns_server_order() {
+    use mailledger_imap::{SearchCriteria, SortKey};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 SORT] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 3 EXISTS\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   * SORT 30 10 20\r\n\
+                   A0002 OK SORT completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uids = client
+        .uid_sort(
+            &[SortKey::Reverse(Box::new(SortKey::Date))],
+            &SearchCriteria::All,
+        )
+        .await
+        .unwrap();
+    let values: Vec<u32> = uids.iter().map(|u| u.get()).collect();
+    assert_eq!(values, vec![30, 10, 20]);
+}
+
+#[tokio::test]
+async fn test_sort_requires_capability() {
+    use mailledger_imap::{Error, SearchCriteria, SortKey};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let result = client.sort(&[SortKey::Date], &SearchCriteria::All).await;
+    assert!(matches!(result, Err(Error::Unsupported(cap)) if cap == "SORT"));
+}
+
+#[tokio::test]
+async fn test_thread_references() {
+    use mailledger_imap::{SearchCriteria, ThreadAlgorithm};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 THREAD=REFERENCES] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   * THREAD (2)(3 6 (4 23)(44 7 96))\r\n\
+                   A0002 OK THREAD completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+    assert!(client.supports_thread(ThreadAlgorithm::References));
+    assert!(!client.supports_thread(ThreadAlgorithm::OrderedSubject));
+
+    let threads = client
+        .thread(ThreadAlgorithm::References, &SearchCriteria::All)
+        .await
+        .unwrap();
+    assert_eq!(threads.len(), 2);
+    assert_eq!(threads[1].len(), 7);
+}
+
+#[tokio::test]
+async fn test_search_return_esearch() {
+    use mailledger_imap::{SearchCriteria, SearchReturnOption};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 ESEARCH] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   * ESEARCH (TAG \"A0002\") UID COUNT 5 ALL 3:5,8,10\r\n\
+                   A0002 OK SEARCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let result = client
+        .search_return(
+            &SearchCriteria::Unseen,
+            &[SearchReturnOption::Count, SearchReturnOption::All],
+        )
+        .await
+        .unwrap();
+    assert!(result.uid);
+    assert_eq!(result.count, Some(5));
+    assert_eq!(result.all.unwrap().to_string(), "3:5,8,10");
+}
+
+#[tokio::test]
+async fn test_compress_deflate() {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(b"* 3 EXISTS\r\nA0002 OK SELECT completed\r\n")
+        .unwrap();
+    encoder.flush().unwrap();
+
+    let mut script = b"* OK [CAPABILITY IMAP4rev1 COMPRESS=DEFLATE] ready\r\n\
+                       A0000 OK LOGIN completed\r\n\
+                       A0001 OK DEFLATE active\r\n"
+        .to_vec();
+    script.extend_from_slice(encoder.get_ref());
+
+    let client = Client::from_stream(MockStream::new(&script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    assert!(client.supports_compress_deflate());
+
+    let client = client.compress().await.unwrap();
+    let (_, status) = client.select("INBOX").await.unwrap();
+    assert_eq!(status.exists, 3);
+}
+
+#[tokio::test]
+async fn test_compress_unsupported() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+
+    let result = client.compress().await;
+    assert!(matches!(
+        result,
+        Err(mailledger_imap::Error::Unsupported(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_fetch_binary() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 BINARY] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   * 1 FETCH (UID 9 BINARY[2] ~{3}\r\n\x00\xfe\x01)\r\n\
+                   A0002 OK FETCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uid = mailledger_imap::Uid::new(9).unwrap();
+    let data = client.fetch_binary(uid, &[2]).await.unwrap();
+    assert_eq!(data, vec![0x00, 0xfe, 0x01]);
+}
+
+#[tokio::test]
+async fn test_fetch_partial() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   * 1 FETCH (UID 9 BODY[1]<4096> {5}\r\nHello)\r\n\
+                   A0002 OK FETCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uid = mailledger_imap::Uid::new(9).unwrap();
+    let (data, origin) = client.fetch_partial(uid, &[1], 4096, 4096).await.unwrap();
+    assert_eq!(data, b"Hello");
+    assert_eq!(origin, 4096);
+}
+
+#[tokio::test]
+async fn test_fetch_headers() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   * 1 FETCH (UID 9 BODY[HEADER.FIELDS (REFERENCES LIST-UNSUBSCRIBE)] {25}\r\n\
+                   References: <a@example>\r\n)\r\n\
+                   A0002 OK FETCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uid = mailledger_imap::Uid::new(9).unwrap();
+    let headers = client
+        .fetch_headers(uid, &["References", "List-Unsubscribe"])
+        .await
+        .unwrap();
+    assert_eq!(headers, b"References: <a@example>\r\n");
+}
+
+#[tokio::test]
+async fn test_multi_append() {
+    use mailledger_imap::{AppendItem, AppendUid};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 MULTIAPPEND UIDPLUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   + Ready for literal\r\n\
+                   + Ready for literal\r\n\
+                   A0001 OK [APPENDUID 38505 3955:3956] APPEND completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let items = [
+        AppendItem::new(b"Subject: one\r\n\r\nbody".to_vec()),
+        AppendItem::new(b"Subject: two\r\n\r\nbody".to_vec()),
+    ];
+    let AppendUid { validity, uids } = client
+        .multi_append("Archive", &items)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(validity.get(), 38505);
+    assert_eq!(uids.to_string(), "3955:3956");
+}
+
+#[tokio::test]
+async fn test_multi_append_sequential_fallback() {
+    use mailledger_imap::AppendItem;
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 UIDPLUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   + Ready for literal\r\n\
+                   A0001 OK [APPENDUID 38505 10] APPEND completed\r\n\
+                   + Ready for literal\r\n\
+                   A0002 OK [APPENDUID 38505 11] APPEND completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let items = [
+        AppendItem::new(b"one".to_vec()),
+        AppendItem::new(b"two".to_vec()),
+    ];
+    let appended = client
+        .multi_append("Archive", &items)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(appended.uids.to_string(), "10,11");
+}
+
+#[tokio::test]
+async fn test_append_returns_appenduid() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 UIDPLUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   + Ready for literal\r\n\
+                   A0001 OK [APPENDUID 38505 3955] APPEND completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let appended = client
+        .append("Sent", None, b"Subject: hi\r\n\r\nbody")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(appended.validity.get(), 38505);
+    assert_eq!(appended.uids.to_string(), "3955");
+}
+
+#[tokio::test]
+async fn test_uid_copy_returns_copyuid() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 UIDPLUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   A0002 OK [COPYUID 38505 304,319:320 3956:3958] Done\r\n\
+                   A0003 OK COPY completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uids = mailledger_imap::UidSet::parse("304,319:320").unwrap();
+    let copied = client.uid_copy(&uids, "Archive").await.unwrap().unwrap();
+    assert_eq!(copied.validity.get(), 38505);
+    assert_eq!(copied.source.to_string(), "304,319:320");
+    assert_eq!(copied.dest.to_string(), "3956:3958");
+
+    // Without UIDPLUS data the copy still succeeds
+    assert_eq!(client.uid_copy(&uids, "Archive").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_starttls_unsupported() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let err = client.starttls("imap.example.com").await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_starttls_rejects_injected_plaintext() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 STARTTLS LOGINDISABLED] ready\r\n\
+                   A0000 OK Begin TLS negotiation now\r\n\
+                   * OK [CAPABILITY IMAP4rev1 AUTH=PLAIN] injected\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let err = client.starttls("imap.example.com").await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Protocol(_)));
+}
+
+#[tokio::test]
+async fn test_starttls_refused_after_preauth() {
+    let script = b"* PREAUTH [CAPABILITY IMAP4rev1 STARTTLS] ready\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let err = client.starttls("imap.example.com").await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Protocol(_)));
+    assert!(sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_greeting_capabilities_skip_capability_command() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 IDLE AUTH=PLAIN] Server ready\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    assert!(!client.is_preauthenticated());
+    assert!(client.supports_idle());
+    assert_eq!(client.greeting(), "Server ready");
+
+    let client = client.preauthenticated().unwrap_err();
+    client.login("user", "pass").await.unwrap();
+    assert_eq!(
+        sent.lock().unwrap().as_slice(),
+        b"A0000 LOGIN user pass\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_preauth_greeting_skips_login() {
+    let script = b"* PREAUTH [CAPABILITY IMAP4rev1] Logged in as smith\r\n\
+                   * LIST () \"/\" INBOX\r\n\
+                   A0000 OK LIST completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    assert!(client.is_preauthenticated());
+
+    let mut client = client.preauthenticated().unwrap();
+    let mailboxes = client.list("", "*").await.unwrap();
+    assert_eq!(mailboxes.len(), 1);
+    assert_eq!(
+        sent.lock().unwrap().as_slice(),
+        b"A0000 LIST \"\" \"*\"\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_bye_greeting_is_an_error() {
+    let script = b"* BYE Too many connections from your IP\r\n";
+
+    let err = Client::from_stream(MockStream::new(script))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Bye(text) if text.starts_with("Too many")));
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    use hmac::{Hmac, Mac};
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Plays the server side of SCRAM-SHA-256 for `user`/`pencil`, checking
+/// the client proof. With `forge` the server signature is wrong. Returns
+/// the client's reply to the server-final message.
+async fn scram_server(stream: tokio::io::DuplexStream, forge: bool) -> String {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use sha2::Digest;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (read, mut write) = tokio::io::split(stream);
+    let mut lines = BufReader::new(read).lines();
+    let decode = |line: String| String::from_utf8(STANDARD.decode(line).unwrap()).unwrap();
+
+    write
+        .write_all(b"* OK [CAPABILITY IMAP4rev1 AUTH=PLAIN AUTH=SCRAM-SHA-256] ready\r\n")
+        .await
+        .unwrap();
+    let command = lines.next_line().await.unwrap().unwrap();
+    assert_eq!(command, "A0000 AUTHENTICATE SCRAM-SHA-256");
+    write.write_all(b"+ \r\n").await.unwrap();
+
+    let client_first = decode(lines.next_line().await.unwrap().unwrap());
+    let client_first_bare = client_first.strip_prefix("n,,").unwrap();
+    let client_nonce = client_first_bare.strip_prefix("n=user,r=").unwrap();
+    let server_first = format!("r={client_nonce}srv,s={},i=1", STANDARD.encode(b"salt"));
+    write
+        .write_all(format!("+ {}\r\n", STANDARD.encode(&server_first)).as_bytes())
+        .await
+        .unwrap();
+
+    let client_final = decode(lines.next_line().await.unwrap().unwrap());
+    let (without_proof, proof) = client_final.split_once(",p=").unwrap();
+    assert_eq!(without_proof, format!("c=biws,r={client_nonce}srv"));
+    let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+
+    // Hi() with one iteration is a single HMAC
+    let salted = hmac_sha256(b"pencil", b"salt\0\0\0\x01");
+    let client_key = hmac_sha256(&salted, b"Client Key");
+    let stored_key = sha2::Sha256::digest(&client_key);
+    let client_signature = hmac_sha256(&stored_key, auth_message.as_bytes());
+    let expected_proof: Vec<u8> = client_key
+        .iter()
+        .zip(&client_signature)
+        .map(|(a, b)| a ^ b)
+        .collect();
+    assert_eq!(STANDARD.decode(proof).unwrap(), expected_proof);
+
+    let server_key = hmac_sha256(&salted, b"Server Key");
+    let mut signature = hmac_sha256(&server_key, auth_message.as_bytes());
+    if forge {
+        signature[0] ^= 0xff;
+    }
+    let server_final = format!("v={}", STANDARD.encode(signature));
+    write
+        .write_all(format!("+ {}\r\n", STANDARD.encode(server_final)).as_bytes())
+        .await
+        .unwrap();
+
+    let reply = lines.next_line().await.unwrap().unwrap();
+    let tagged = if reply.is_empty() {
+        "A0000 OK [CAPABILITY IMAP4rev1 IDLE] Logged in\r\n"
+    } else {
+        "A0000 BAD Authentication cancelled\r\n"
+    };
+    write.write_all(tagged.as_bytes()).await.unwrap();
+    reply
+}
+
+#[tokio::test]
+async fn test_authenticate_scram_sha256() {
+    let (local, remote) = tokio::io::duplex(4096);
+    let server = tokio::spawn(scram_server(remote, false));
+
+    let client = Client::from_stream(local).await.unwrap();
+    assert!(client.supports_scram());
+    let client = client.authenticate_scram("user", "pencil").await.unwrap();
+
+    assert!(client.supports_idle());
+    assert_eq!(server.await.unwrap(), "");
+}
+
+#[tokio::test]
+async fn test_authenticate_scram_rejects_forged_server_signature() {
+    let (local, remote) = tokio::io::duplex(4096);
+    let server = tokio::spawn(scram_server(remote, true));
+
+    let client = Client::from_stream(local).await.unwrap();
+    let err = client
+        .authenticate_scram("user", "pencil")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        mailledger_imap::Error::AuthenticationFailed(_)
+    ));
+    // The exchange is cancelled rather than acknowledged
+    assert_eq!(server.await.unwrap(), "*");
+}
+
+#[tokio::test]
+async fn test_authenticate_scram_unsupported() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 AUTH=PLAIN] ready\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    assert!(!client.supports_scram());
+    let err = client
+        .authenticate_scram("user", "pencil")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_login_rejected_when_disabled() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 STARTTLS LOGINDISABLED] ready\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let err = client.login("user", "pass").await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::StartTlsRequired));
+    // The password never leaves the client
+    assert!(sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_login_insecure_ignores_logindisabled() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 STARTTLS LOGINDISABLED] ready\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    client.login_insecure("user", "pass").await.unwrap();
+    assert_eq!(
+        sent.lock().unwrap().as_slice(),
+        b"A0000 LOGIN user pass\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_authenticate_sasl_ir() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 SASL-IR AUTH=XOAUTH2] ready\r\n\
+                   A0000 OK [CAPABILITY IMAP4rev1 IDLE] Success\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client
+        .authenticate("XOAUTH2", Some("dXNlcg=="))
+        .await
+        .unwrap();
+    assert!(client.supports_idle());
+}
+
+#[tokio::test]
+async fn test_authenticate_continuation() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 AUTH=OAUTHBEARER] ready\r\n\
+                   + \r\n\
+                   A0000 OK Success\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    assert!(
+        client
+            .authenticate("OAUTHBEARER", Some("dXNlcg=="))
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_authenticate_failure_decodes_challenge() {
+    // {"status":"401","schemes":"bearer"}
+    let script = b"* OK [CAPABILITY IMAP4rev1 SASL-IR AUTH=XOAUTH2] ready\r\n\
+                   + eyJzdGF0dXMiOiI0MDEiLCJzY2hlbWVzIjoiYmVhcmVyIn0=\r\n\
+                   A0000 NO [AUTHENTICATIONFAILED] Invalid credentials\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let err = client
+        .authenticate("XOAUTH2", Some("dXNlcg=="))
+        .await
+        .unwrap_err();
+    match err {
+        mailledger_imap::Error::AuthenticationFailed(reason) => {
+            assert_eq!(reason, r#"{"status":"401","schemes":"bearer"}"#);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_list_extended_with_status() {
+    use mailledger_imap::command::StatusAttribute;
+    use mailledger_imap::{ListReturnOpt, ListSelectOpt};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 LIST-EXTENDED LIST-STATUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * LIST (\\Subscribed \\HasNoChildren) \"/\" INBOX\r\n\
+                   * STATUS INBOX (MESSAGES 231 UNSEEN 12)\r\n\
+                   * LIST (\\NonExistent) \"/\" Lists (\"CHILDINFO\" (\"SUBSCRIBED\"))\r\n\
+                   * LIST (\\Subscribed \\HasNoChildren) \"/\" \"Lists/rust\"\r\n\
+                   * STATUS \"Lists/rust\" (MESSAGES 4 UNSEEN 0)\r\n\
+                   A0001 OK LIST completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let entries = client
+        .list_extended(
+            &[ListSelectOpt::Subscribed],
+            "",
+            &["*"],
+            &[
+                ListReturnOpt::Children,
+                ListReturnOpt::Status(vec![StatusAttribute::Messages, StatusAttribute::Unseen]),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 3);
+    let inbox = entries[0].status.as_ref().unwrap();
+    assert_eq!(inbox.exists, 231);
+    assert_eq!(inbox.unseen_count, Some(12));
+    assert_eq!(entries[1].child_info, vec!["SUBSCRIBED".to_string()]);
+    assert!(entries[1].status.is_none());
+    assert_eq!(entries[2].status.as_ref().unwrap().exists, 4);
+}
+
+#[tokio::test]
+async fn test_status() {
+    use mailledger_imap::command::StatusAttribute;
+
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * STATUS \"INBOX\" (MESSAGES 231 UNSEEN 12 RECENT 3)\r\n\
+                   A0001 OK STATUS completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let status = client
+        .status(
+            "INBOX",
+            &[
+                StatusAttribute::Messages,
+                StatusAttribute::Unseen,
+                StatusAttribute::Recent,
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(status.exists, 231);
+    assert_eq!(status.unseen_count, Some(12));
+    assert_eq!(status.recent, 3);
+    assert_eq!(status.uid_next, None);
+}
+
+#[tokio::test]
+async fn test_list_extended_unsupported() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let err = client
+        .list_extended(&[], "", &["*"], &[])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_get_quota_root() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 QUOTA] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * QUOTAROOT INBOX \"#user\"\r\n\
+                   * QUOTA \"#user\" (STORAGE 512 1024)\r\n\
+                   A0001 OK Getquotaroot completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let quotas = client.get_quota_root("INBOX").await.unwrap();
+    assert_eq!(quotas.len(), 1);
+    assert_eq!(quotas[0].root, "#user");
+    let storage = quotas[0].resource("STORAGE").unwrap();
+    assert_eq!((storage.usage, storage.limit), (512, 1024));
+}
+
+#[tokio::test]
+async fn test_get_and_set_metadata() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 METADATA] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * METADATA \"INBOX\" (/private/comment \"Newsletters\" /shared/comment NIL)\r\n\
+                   A0001 OK Getmetadata completed\r\n\
+                   A0002 OK Setmetadata completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let entries = client
+        .get_metadata("INBOX", &["/private/comment", "/shared/comment"])
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries["/private/comment"].as_deref(), Some("Newsletters"));
+    assert_eq!(entries["/shared/comment"], None);
+
+    client
+        .set_metadata("INBOX", &[("/private/comment", None)])
+        .await
+        .unwrap();
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.contains("A0001 GETMETADATA INBOX (/private/comment /shared/comment)\r\n"));
+    assert!(sent.contains("A0002 SETMETADATA INBOX (/private/comment NIL)\r\n"));
+}
+
+#[tokio::test]
+async fn test_metadata_unsupported() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 METADATA-SERVER] ready\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    // Only server annotations are supported
+    let err = client
+        .get_metadata("INBOX", &["/private/comment"])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_append_over_quota() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 QUOTA] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   + Ready for literal\r\n\
+                   A0001 NO [OVERQUOTA] Quota exceeded\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let err = client.append("Sent", None, b"body").await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::QuotaExceeded(_)));
+}
+
+#[tokio::test]
+async fn test_no_unavailable_is_retriable() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 NO [UNAVAILABLE] Backend down\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let err = client.create("Archive").await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unavailable(_)));
+    assert!(err.is_retriable());
+}
+
+#[tokio::test]
+async fn test_login_failure_keeps_response_code() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 NO [AUTHENTICATIONFAILED] Invalid credentials (Failure)\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let Err(err) = client.login("user", "wrong").await else {
+        panic!("login should fail");
+    };
+    assert!(err.is_auth_failed());
+    assert!(matches!(
+        err,
+        mailledger_imap::Error::Tagged {
+            status: mailledger_imap::types::Status::No,
+            code: Some(mailledger_imap::types::ResponseCode::AuthenticationFailed),
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn test_append_creating_creates_missing_mailbox() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 UIDPLUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 NO [TRYCREATE] Mailbox doesn't exist: Receipts\r\n\
+                   A0002 OK CREATE completed\r\n\
+                   + Ready for literal\r\n\
+                   A0003 OK [APPENDUID 7 1] APPEND completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let appended = client
+        .append_creating("Receipts", None, b"body")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(appended.uids.to_string(), "1");
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.contains("A0001 APPEND Receipts {4}\r\n"));
+    assert!(sent.contains("A0002 CREATE Receipts\r\n"));
+    assert!(sent.contains("A0003 APPEND Receipts {4}\r\nbody\r\n"));
+}
+
+#[tokio::test]
+async fn test_plain_append_reports_trycreate() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 NO [TRYCREATE] Mailbox doesn't exist: Receipts\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let err = client.append("Receipts", None, b"body").await.unwrap_err();
+    assert!(err.is_try_create());
+}
+
+#[tokio::test]
+async fn test_namespace() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 NAMESPACE] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * NAMESPACE ((\"\" \"/\")) ((\"Other Users/\" \"/\")) NIL\r\n\
+                   A0001 OK Namespace completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let ns = client.namespace().await.unwrap();
+    assert_eq!(ns.personal[0].prefix, "");
+    assert_eq!(ns.personal[0].delimiter, Some('/'));
+    assert_eq!(ns.other_users[0].prefix, "Other Users/");
+    assert!(ns.shared.is_empty());
+}
+
+#[tokio::test]
+async fn test_namespace_unsupported() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let err = client.namespace().await.unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_enable_utf8_accept() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 ENABLE UTF8=ACCEPT] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * ENABLED UTF8=ACCEPT\r\n\
+                   A0001 OK Enable completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+    assert!(!client.utf8_enabled());
+
+    let enabled = client.enable(&["UTF8=ACCEPT"]).await.unwrap();
+    assert_eq!(enabled, vec![mailledger_imap::Capability::Utf8Accept]);
+    assert!(client.utf8_enabled());
+}
+
+#[tokio::test]
+async fn test_search_utf8_literal() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 1 EXISTS\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   + Ready for literal\r\n\
+                   * SEARCH 1\r\n\
+                   A0002 OK SEARCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let criteria = SearchCriteria::Subject("café".to_string());
+    let ids = client
+        .search_criteria(&criteria, Some("UTF-8"))
+        .await
+        .unwrap();
+    assert_eq!(ids.len(), 1);
+}
+
+#[tokio::test]
+async fn test_search_literal_rejected_before_continuation() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 1 EXISTS\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   A0002 NO [BADCHARSET (US-ASCII)] Unsupported charset\r\n\
+                   A0003 OK SEARCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    // Both attempts are answered at the literal header with a tagged
+    // response, so the literal data is never sent.
+    let criteria = SearchCriteria::Subject("café".to_string());
+    let ids = client.search_criteria(&criteria, Some("UTF-8")).await;
+    assert!(ids.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_search_badcharset_falls_back_to_ascii() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 2 EXISTS\r\n\
+                   A0001 OK SELECT completed\r\n\
+                   A0002 NO [BADCHARSET (US-ASCII)] Unsupported charset\r\n\
+                   * SEARCH 2\r\n\
+                   A0003 OK SEARCH completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let criteria = SearchCriteria::From("alice".to_string());
+    let uids = client
+        .uid_search_criteria(&criteria, Some("ISO-2022-JP"))
+        .await
+        .unwrap();
+    assert_eq!(uids.len(), 1);
+}
+
+#[tokio::test]
+async fn test_login_sends_id_to_icloud() {
+    use mailledger_imap::ServerType;
+
+    let script = b"* OK [CAPABILITY XAPPLEPUSHSERVICE IMAP4 IMAP4rev1 ID] iSCREAM ready to rumble (1S:1159)\r\n\
+                   * ID (\"name\" \"iSCREAM\")\r\n\
+                   A0000 OK ID completed\r\n\
+                   A0001 OK LOGIN completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    assert_eq!(client.server_type(), ServerType::ICloud);
+    assert!(client.greeting().starts_with("iSCREAM"));
+    let client = client.login("user", "pass").await.unwrap();
+    assert!(client.quirks().requires_id_command_before_login);
+    assert_eq!(client.server_id().unwrap()["name"], "iSCREAM");
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.starts_with("A0000 ID (\"name\" \"mailledger\" \"version\" "));
+    assert!(sent.contains("\r\nA0001 LOGIN user pass\r\n"));
+}
+
+#[tokio::test]
+async fn test_login_skips_id_for_other_servers() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 ID] Dovecot ready.\r\n\
+                   A0000 OK LOGIN completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    assert!(client.server_id().is_none());
+}
+
+#[tokio::test]
+async fn test_unselect() {
+    let script = b"* OK [CAPABILITY IMAP4rev1 UNSELECT] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 3 EXISTS\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   A0002 OK UNSELECT completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (client, _) = client.select("INBOX").await.unwrap();
+    assert!(client.supports_unselect());
+    client.unselect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unselect_falls_back_to_examine_and_close() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   A0002 OK [READ-ONLY] EXAMINE completed\r\n\
+                   A0003 OK CLOSE completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (client, _) = client.select("INBOX").await.unwrap();
+    assert!(!client.supports_unselect());
+    client.unselect().await.unwrap();
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.ends_with("A0002 EXAMINE INBOX\r\nA0003 CLOSE\r\n"));
+}
+
+#[tokio::test]
+async fn test_expunge_collects_interleaved_replies() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   * 3 EXPUNGE\r\n\
+                   * 5 FETCH (FLAGS (\\Seen))\r\n\
+                   * 3 EXPUNGE\r\n\
+                   * 8 EXISTS\r\n\
+                   * 7 EXPUNGE\r\n\
+                   A0002 OK EXPUNGE completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let expunged: Vec<u32> = client
+        .expunge()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(mailledger_imap::SeqNum::get)
+        .collect();
+    assert_eq!(expunged, [3, 3, 7]);
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.ends_with("A0002 EXPUNGE\r\n"));
+}
+
+#[tokio::test]
+async fn test_fetch_vanished_since() {
+    use mailledger_imap::{FetchAttribute, FetchItems, Uid, UidSet};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 ENABLE CONDSTORE QRESYNC] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * ENABLED QRESYNC\r\n\
+                   A0001 OK Enable completed\r\n\
+                   A0002 OK [READ-WRITE] SELECT completed\r\n\
+                   * VANISHED (EARLIER) 3:4,9\r\n\
+                   * 2 FETCH (UID 7 FLAGS (\\Seen) MODSEQ (120))\r\n\
+                   A0003 OK UID FETCH completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+    client.enable(&["QRESYNC"]).await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uids = UidSet::range(Uid::new(1).unwrap(), Uid::new(10).unwrap());
+    let items = FetchItems::Items(vec![FetchAttribute::Uid, FetchAttribute::Flags]);
+    let (fetched, vanished) = client
+        .fetch_vanished_since(&uids, items, 100)
+        .await
+        .unwrap();
+    assert_eq!(fetched.len(), 1);
+    let vanished: Vec<u32> = vanished.into_iter().map(Uid::get).collect();
+    assert_eq!(vanished, [3, 4, 9]);
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.ends_with("A0003 UID FETCH 1:10 (UID FLAGS) (CHANGEDSINCE 100 VANISHED)\r\n"));
+}
+
+#[tokio::test]
+async fn test_uid_expunge() {
+    use mailledger_imap::{Uid, UidSet};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 UIDPLUS] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   * 3 EXPUNGE\r\n\
+                   * 3 EXPUNGE\r\n\
+                   A0002 OK UID EXPUNGE completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let uids = UidSet::range(Uid::new(100).unwrap(), Uid::new(101).unwrap());
+    let expunged = client.uid_expunge(&uids).await.unwrap();
+    assert_eq!(expunged.len(), 2);
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.ends_with("A0002 UID EXPUNGE 100:101\r\n"));
+}
+
+#[tokio::test]
+async fn test_uid_expunge_requires_uidplus() {
+    use mailledger_imap::{Uid, UidSet};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let err = client
+        .uid_expunge(&UidSet::single(Uid::new(7).unwrap()))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_status_many_skips_refused_mailboxes() {
+    use mailledger_imap::Mailbox;
+    use mailledger_imap::command::StatusAttribute;
+
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * STATUS INBOX (MESSAGES 10 UNSEEN 2)\r\n\
+                   A0001 OK STATUS completed\r\n\
+                   A0002 NO [NONEXISTENT] Mailbox is not selectable\r\n\
+                   * STATUS Archive (MESSAGES 500 UNSEEN 0)\r\n\
+                   A0003 OK STATUS completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let mailboxes = [
+        Mailbox::new("inbox"),
+        Mailbox::new("[Gmail]"),
+        Mailbox::new("Archive"),
+    ];
+    let statuses = client
+        .status_many(
+            &mailboxes,
+            &[StatusAttribute::Messages, StatusAttribute::Unseen],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].0, Mailbox::new("inbox"));
+    assert_eq!(statuses[0].1.exists, 10);
+    assert_eq!(statuses[0].1.unseen_count, Some(2));
+    assert_eq!(statuses[1].0, Mailbox::new("Archive"));
+    assert_eq!(statuses[1].1.exists, 500);
+
+    // All three commands go out before any reply is read
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.ends_with(
+        "A0001 STATUS inbox (MESSAGES UNSEEN)\r\n\
+         A0002 STATUS [Gmail] (MESSAGES UNSEEN)\r\n\
+         A0003 STATUS Archive (MESSAGES UNSEEN)\r\n"
+    ));
+}
+
+#[tokio::test]
+async fn test_gmail_labels_and_raw_search() {
+    use mailledger_imap::FetchAttribute;
+    use mailledger_imap::parser::FetchItem;
+    use mailledger_imap::types::{Uid, UidSet};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 X-GM-EXT-1] Gimap ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   * 1 FETCH (UID 7 X-GM-THRID 1278455344230334865 X-GM-LABELS (\\Inbox \"Project X\"))\r\n\
+                   A0002 OK Success\r\n\
+                   * SEARCH 7\r\n\
+                   A0003 OK SEARCH completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+    assert!(client.supports_gmail_extensions());
+
+    let uid = Uid::new(7).unwrap();
+    let items = FetchItems::Items(vec![
+        FetchAttribute::GmailThreadId,
+        FetchAttribute::GmailLabels,
+    ]);
+    let results = client.uid_fetch(&UidSet::single(uid), items).await.unwrap();
+    assert!(
+        results[0]
+            .1
+            .contains(&FetchItem::GmailThreadId(1_278_455_344_230_334_865))
+    );
+    assert!(results[0].1.contains(&FetchItem::GmailLabels(vec![
+        "\\Inbox".to_string(),
+        "Project X".to_string(),
+    ])));
+
+    let criteria = SearchCriteria::GmailRaw("has:attachment".to_string());
+    let uids = client.uid_search_criteria(&criteria, None).await.unwrap();
+    assert_eq!(uids, vec![uid]);
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.contains("A0002 UID FETCH 7 (X-GM-THRID X-GM-LABELS)\r\n"));
+    assert!(sent.contains("A0003 UID SEARCH X-GM-RAW \"has:attachment\"\r\n"));
+}
+
+#[tokio::test]
+async fn test_gmail_extensions_require_capability() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let criteria = SearchCriteria::GmailRaw("has:attachment".to_string());
+    let err = client
+        .uid_search_criteria(&criteria, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, mailledger_imap::Error::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn test_append_full_keeps_internal_date() {
+    use chrono::{FixedOffset, TimeZone};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   + Ready for literal\r\n\
+                   A0001 OK APPEND completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+
+    let date = FixedOffset::east_opt(3600)
+        .unwrap()
+        .with_ymd_and_hms(2024, 2, 7, 12, 0, 0)
+        .unwrap();
+    client
+        .append_full("INBOX", None, Some(date), b"body")
+        .await
+        .unwrap();
+
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert!(sent.contains("A0001 APPEND INBOX \" 7-Feb-2024 12:00:00 +0100\" {4}\r\nbody\r\n"));
+}
+
+#[tokio::test]
+async fn test_fetch_stream_yields_before_completion() {
+    use futures_util::StreamExt;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    let (local, mut server) = tokio::io::duplex(4096);
+    server
+        .write_all(
+            b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+              A0000 OK LOGIN completed\r\n\
+              A0001 OK [READ-WRITE] SELECT completed\r\n\
+              * 1 FETCH (UID 11 FLAGS (\\Seen))\r\n\
+              * 2 FETCH (UID 12 FLAGS ())\r\n\
+              * 3 FETCH (UID 13 FLAGS ())\r\n",
+        )
+        .await
+        .unwrap();
+
+    let client = Client::from_stream(local).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+
+    let mut messages =
+        std::pin::pin!(client.fetch_stream(&SequenceSet::range(1, 3).unwrap(), FetchItems::Fast));
+    let mut seqs = Vec::new();
+    for _ in 0..3 {
+        // The tagged OK hasn't been sent yet, so each message must arrive on its own.
+        let (seq, _) = tokio::time::timeout(Duration::from_secs(5), messages.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        seqs.push(seq.get());
+    }
+    assert_eq!(seqs, vec![1, 2, 3]);
+
+    server
+        .write_all(b"A0002 OK FETCH completed\r\n")
+        .await
+        .unwrap();
+    assert!(messages.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_mailbox_updates_during_fetch() {
+    use mailledger_imap::UntaggedResponse;
+
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 4 EXISTS\r\n\
+                   A0001 OK [READ-WRITE] SELECT completed\r\n\
+                   * 1 FETCH (UID 11 FLAGS (\\Seen))\r\n\
+                   * 3 EXPUNGE\r\n\
+                   * 2 FETCH (UID 12 FLAGS ())\r\n\
+                   * 4 EXISTS\r\n\
+                   A0002 OK FETCH completed\r\n\
+                   * 2 EXPUNGE\r\n\
+                   A0003 OK NOOP completed\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, _) = client.select("INBOX").await.unwrap();
+    assert!(client.take_mailbox_updates().is_empty());
+
+    let messages = client
+        .fetch(&SequenceSet::range(1, 2).unwrap(), FetchItems::Fast)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        client.take_mailbox_updates(),
+        vec![
+            UntaggedResponse::Expunge(mailledger_imap::SeqNum::new(3).unwrap()),
+            UntaggedResponse::Exists(4),
+        ]
+    );
+    assert!(client.take_mailbox_updates().is_empty());
+
+    client.noop().await.unwrap();
+    assert_eq!(
+        client.take_mailbox_updates(),
+        vec![UntaggedResponse::Expunge(
+            mailledger_imap::SeqNum::new(2).unwrap()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_command_times_out_without_tagged_response() {
+    use mailledger_imap::{Error, Timeouts};
+    use std::time::{Duration, Instant};
+    use tokio::io::AsyncWriteExt;
+
+    let (local, mut server) = tokio::io::duplex(4096);
+    server
+        .write_all(
+            b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+              A0000 OK LOGIN completed\r\n\
+              * 2 EXISTS\r\n",
+        )
+        .await
+        .unwrap();
+
+    let client = Client::from_stream(local).await.unwrap();
+    let mut client = client.login("user", "pass").await.unwrap();
+    client.set_timeouts(Timeouts {
+        io: None,
+        command: Some(Duration::from_millis(200)),
+    });
+
+    let started = Instant::now();
+    let result = client.noop().await;
+    assert!(matches!(result, Err(Error::Timeout(limit)) if limit == Duration::from_millis(200)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+const LOCALHOST_CERT: &[u8] = include_bytes!("fixtures/localhost.crt.pem");
+const LOCALHOST_KEY: &[u8] = include_bytes!("fixtures/localhost.key.pem");
+
+/// Serves one IMAP greeting over TLS with a self-signed localhost certificate.
+async fn spawn_tls_server() -> (u16, Vec<u8>) {
+    let (port, der, _) = spawn_tls_server_with_sni().await;
+    (port, der)
+}
+
+/// Like [`spawn_tls_server`], also reporting the SNI name the client sent.
+async fn spawn_tls_server_with_sni()
+-> (u16, Vec<u8>, tokio::sync::oneshot::Receiver<Option<String>>) {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use tokio::io::AsyncWriteExt;
+
+    let cert = CertificateDer::from_pem_slice(LOCALHOST_CERT).unwrap();
+    let key = PrivateKeyDer::from_pem_slice(LOCALHOST_KEY).unwrap();
+    let der = cert.to_vec();
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::aws_lc_rs::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(vec![cert], key)
+    .unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sni_tx, sni_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        if let Ok(mut tls) = acceptor.accept(tcp).await {
+            let _ = sni_tx.send(tls.get_ref().1.server_name().map(str::to_string));
+            let _ = tls
+                .write_all(b"* OK [CAPABILITY IMAP4rev1] ready\r\n")
+                .await;
+            let _ = tls.flush().await;
+            // Keep the connection open until the client is done.
+            let _ = tokio::io::AsyncReadExt::read(&mut tls, &mut [0; 1]).await;
+        }
+    });
+    (port, der, sni_rx)
+}
+
+fn tls_config(port: u16) -> mailledger_imap::ConfigBuilder {
+    mailledger_imap::Config::builder("localhost").port(port)
+}
+
+#[tokio::test]
+async fn test_tls_pinned_cert_is_accepted() {
+    use sha2::{Digest, Sha256};
+
+    let (port, der) = spawn_tls_server().await;
+    let pin: [u8; 32] = Sha256::digest(&der).into();
+    let config = tls_config(port).with_pinned_cert_sha256(pin).build();
+
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "ready");
+}
+
+#[tokio::test]
+async fn test_tls_pin_mismatch_is_rejected() {
+    use mailledger_imap::Error;
+    use rustls::CertificateError;
+
+    let (port, _) = spawn_tls_server().await;
+    let config = tls_config(port).with_pinned_cert_sha256([0; 32]).build();
+
+    let result = mailledger_imap::connection::connect(&config).await;
+    assert!(matches!(
+        result,
+        Err(Error::Tls(rustls::Error::InvalidCertificate(
+            CertificateError::ApplicationVerificationFailure
+        )))
+    ));
+}
+
+#[tokio::test]
+async fn test_tls_custom_root_cert() {
+    use mailledger_imap::Error;
+    use rustls::CertificateError;
+
+    let (port, _) = spawn_tls_server().await;
+    let result = mailledger_imap::connection::connect(&tls_config(port).build()).await;
+    assert!(matches!(
+        result,
+        Err(Error::Tls(rustls::Error::InvalidCertificate(
+            CertificateError::UnknownIssuer
+        )))
+    ));
+
+    let (port, der) = spawn_tls_server().await;
+    let config = tls_config(port).with_root_cert(der).build();
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "ready");
+}
+
+#[tokio::test]
+async fn test_tls_accept_invalid_certs() {
+    let (port, _) = spawn_tls_server().await;
+    let config = tls_config(port).danger_accept_invalid_certs(true).build();
+
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "ready");
+}
+
+#[tokio::test]
+async fn test_tls_sni_hostname_override() {
+    let (port, der, sni) = spawn_tls_server_with_sni().await;
+    // Connect by address, as through a tunnel, but verify as localhost.
+    let config = mailledger_imap::Config::builder("127.0.0.1")
+        .port(port)
+        .with_root_cert(der)
+        .with_sni_hostname("localhost")
+        .build();
+
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "ready");
+    assert_eq!(sni.await.unwrap().as_deref(), Some("localhost"));
+}
+
+/// Serves an IMAP greeting over TLS on each of `connections` connections,
+/// reporting whether each handshake resumed an earlier session.
+async fn spawn_tls_server_resuming(
+    connections: usize,
+) -> (u16, Vec<u8>, tokio::sync::mpsc::Receiver<bool>) {
+    use rustls::HandshakeKind;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use tokio::io::AsyncWriteExt;
+
+    let cert = CertificateDer::from_pem_slice(LOCALHOST_CERT).unwrap();
+    let key = PrivateKeyDer::from_pem_slice(LOCALHOST_KEY).unwrap();
+    let der = cert.to_vec();
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::aws_lc_rs::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(vec![cert], key)
+    .unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (resumed_tx, resumed_rx) = tokio::sync::mpsc::channel(connections);
+    tokio::spawn(async move {
+        for _ in 0..connections {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut tls = acceptor.accept(tcp).await.unwrap();
+            let kind = tls.get_ref().1.handshake_kind();
+            let _ = resumed_tx.send(kind == Some(HandshakeKind::Resumed)).await;
+            tls.write_all(b"* OK [CAPABILITY IMAP4rev1] ready\r\n")
+                .await
+                .unwrap();
+            tls.flush().await.unwrap();
+            let _ = tokio::io::AsyncReadExt::read(&mut tls, &mut [0; 1]).await;
+        }
+    });
+    (port, der, resumed_rx)
+}
+
+/// A session cache that counts the tickets handed out for resumption.
+#[derive(Debug)]
+struct CountingSessionCache {
+    inner: rustls::client::ClientSessionMemoryCache,
+    tickets_offered: Mutex<usize>,
+}
+
+impl rustls::client::ClientSessionStore for CountingSessionCache {
+    fn set_kx_hint(
+        &self,
+        server_name: rustls::pki_types::ServerName<'static>,
+        group: rustls::NamedGroup,
+    ) {
+        self.inner.set_kx_hint(server_name, group);
+    }
+
+    fn kx_hint(
+        &self,
+        server_name: &rustls::pki_types::ServerName<'_>,
+    ) -> Option<rustls::NamedGroup> {
+        self.inner.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(
+        &self,
+        server_name: rustls::pki_types::ServerName<'static>,
+        value: rustls::client::Tls12ClientSessionValue,
+    ) {
+        self.inner.set_tls12_session(server_name, value);
+    }
+
+    fn tls12_session(
+        &self,
+        server_name: &rustls::pki_types::ServerName<'_>,
+    ) -> Option<rustls::client::Tls12ClientSessionValue> {
+        self.inner.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &rustls::pki_types::ServerName<'static>) {
+        self.inner.remove_tls12_session(server_name);
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: rustls::pki_types::ServerName<'static>,
+        value: rustls::client::Tls13ClientSessionValue,
+    ) {
+        self.inner.insert_tls13_ticket(server_name, value);
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &rustls::pki_types::ServerName<'static>,
+    ) -> Option<rustls::client::Tls13ClientSessionValue> {
+        let ticket = self.inner.take_tls13_ticket(server_name);
+        if ticket.is_some() {
+            *self.tickets_offered.lock().unwrap() += 1;
+        }
+        ticket
+    }
+}
+
+#[tokio::test]
+async fn test_tls_reconnect_resumes_session() {
+    let (port, der, mut resumed) = spawn_tls_server_resuming(2).await;
+    let cache = Arc::new(CountingSessionCache {
+        inner: rustls::client::ClientSessionMemoryCache::new(64),
+        tickets_offered: Mutex::new(0),
+    });
+    let config = tls_config(port)
+        .with_root_cert(der)
+        .with_session_cache(cache.clone())
+        .build();
+
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "ready");
+    drop(client);
+    assert!(!resumed.recv().await.unwrap());
+    assert_eq!(*cache.tickets_offered.lock().unwrap(), 0);
+
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "ready");
+    assert!(resumed.recv().await.unwrap());
+    assert_eq!(*cache.tickets_offered.lock().unwrap(), 1);
+}
+
+/// Accepts one unauthenticated SOCKS5 CONNECT, reports the requested target
+/// and forwards the connection to a plain IMAP greeting.
+async fn spawn_socks5_proxy() -> (u16, tokio::sync::oneshot::Receiver<(String, u16)>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (target_tx, target_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut conn, _) = listener.accept().await.unwrap();
+        let mut methods = [0; 3];
+        conn.read_exact(&mut methods).await.unwrap();
+        assert_eq!(methods, [5, 1, 0]);
+        conn.write_all(&[5, 0]).await.unwrap();
+
+        // VER CMD RSV ATYP=domain LEN
+        let mut request = [0; 5];
+        conn.read_exact(&mut request).await.unwrap();
+        assert_eq!(request[..4], [5, 1, 0, 3]);
+        let mut host = vec![0; usize::from(request[4])];
+        conn.read_exact(&mut host).await.unwrap();
+        let target_port = conn.read_u16().await.unwrap();
+        let _ = target_tx.send((String::from_utf8(host).unwrap(), target_port));
+
+        conn.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+            .await
+            .unwrap();
+        conn.write_all(b"* OK [CAPABILITY IMAP4rev1] proxied\r\n")
+            .await
+            .unwrap();
+        let _ = conn.read(&mut [0; 1]).await;
+    });
+    (port, target_rx)
+}
+
+#[tokio::test]
+async fn test_connect_through_socks5_proxy() {
+    use mailledger_imap::{ProxyConfig, Security};
+
+    let (proxy_port, target) = spawn_socks5_proxy().await;
+    let config = mailledger_imap::Config::builder("imap.example.com")
+        .security(Security::None)
+        .with_proxy(ProxyConfig::socks5("127.0.0.1", proxy_port))
+        .build();
+
+    let client = mailledger_imap::connection::connect(&config).await.unwrap();
+    assert_eq!(client.greeting(), "proxied");
+    assert_eq!(target.await.unwrap(), ("imap.example.com".to_string(), 143));
+}
+
+#[tokio::test]
+async fn test_read_only_mailbox_rejects_changes_locally() {
+    use mailledger_imap::{Error, Flag, StoreAction, UidSet};
+
+    let script = b"* OK [CAPABILITY IMAP4rev1 UIDPLUS MOVE] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   * 3 EXISTS\r\n\
+                   A0001 OK [READ-ONLY] EXAMINE completed\r\n";
+
+    let stream = MockStream::new(script);
+    let sent = stream.sent_data();
+    let client = Client::from_stream(stream).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (mut client, status) = client.examine("INBOX").await.unwrap();
+    assert!(client.is_read_only());
+    assert!(status.read_only);
+
+    let uids = UidSet::parse("1:3").unwrap();
+    let seen = || StoreAction::AddFlags(vec![Flag::Seen]);
+    let result = client.uid_store(&uids, seen()).await;
+    assert!(matches!(result, Err(Error::InvalidState(message)) if message.contains("read-only")));
+    assert!(client.store(&SequenceSet::All, seen()).await.is_err());
+    assert!(client.expunge().await.is_err());
+    assert!(client.uid_expunge(&uids).await.is_err());
+    assert!(client.uid_move(&uids, "Archive").await.is_err());
+
+    // None of them reached the server
+    let sent = String::from_utf8(sent.lock().unwrap().clone()).unwrap();
+    assert_eq!(sent, "A0000 LOGIN user pass\r\nA0001 EXAMINE INBOX\r\n");
+}
+
+#[tokio::test]
+async fn test_select_granted_read_only() {
+    let script = b"* OK [CAPABILITY IMAP4rev1] ready\r\n\
+                   A0000 OK LOGIN completed\r\n\
+                   A0001 OK [READ-ONLY] SELECT completed, shared mailbox\r\n";
+
+    let client = Client::from_stream(MockStream::new(script)).await.unwrap();
+    let client = client.login("user", "pass").await.unwrap();
+    let (client, status) = client.select("Shared/Team").await.unwrap();
+    assert!(status.read_only);
+    assert!(client.is_read_only());
+}
@@ -0,0 +1,532 @@
+//! MIME message generation.
+//!
+//! Attachments are read and Base64-encoded as the message is streamed, so a
+//! large file never has to be held in memory in full.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::content_type::ContentType;
+use crate::dkim::DkimSigner;
+use crate::encoding::{encode_base64, encode_quoted_printable, encode_rfc2047};
+use crate::error::Result;
+use crate::header::Headers;
+use crate::message::Message;
+
+/// Bytes of input per Base64 line, giving the 76-character lines of RFC 2045.
+const BASE64_LINE_INPUT: usize = 57;
+
+/// Bytes read from an attachment per streamed chunk; a whole number of lines.
+const CHUNK_INPUT: usize = BASE64_LINE_INPUT * 1024;
+
+/// A file attached to an outgoing message.
+pub struct Attachment {
+    filename: String,
+    content_type: ContentType,
+    reader: Pin<Box<dyn AsyncRead + Send>>,
+}
+
+impl Attachment {
+    /// Creates an attachment from data already in memory.
+    #[must_use]
+    pub fn from_bytes(
+        filename: impl Into<String>,
+        content_type: ContentType,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self::from_reader(filename, content_type, std::io::Cursor::new(data.into()))
+    }
+
+    /// Creates an attachment whose content is read from `reader` while the
+    /// message is streamed.
+    #[must_use]
+    pub fn from_reader(
+        filename: impl Into<String>,
+        content_type: ContentType,
+        reader: impl AsyncRead + Send + 'static,
+    ) -> Self {
+        Self {
+            filename: filename.into(),
+            content_type,
+            reader: Box::pin(reader),
+        }
+    }
+
+    /// Opens a file to attach, guessing its content type from the extension.
+    ///
+    /// The file is read lazily when the message is built.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path).await?;
+        let filename = path.file_name().map_or_else(
+            || "attachment".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let content_type = guess_content_type(path);
+        Ok(Self::from_reader(filename, content_type, file))
+    }
+
+    /// Returns the attachment's file name.
+    #[must_use]
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Returns the attachment's content type.
+    #[must_use]
+    pub const fn content_type(&self) -> &ContentType {
+        &self.content_type
+    }
+}
+
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Builder for outgoing MIME messages.
+///
+/// A plain text body alone produces a `text/plain` message; adding an HTML
+/// body makes it `multipart/alternative`, and attachments wrap the body in
+/// `multipart/mixed`.
+#[derive(Debug, Default)]
+pub struct MessageBuilder {
+    headers: Headers,
+    subject: Option<String>,
+    text_body: Option<String>,
+    html_body: Option<String>,
+    attachments: Vec<Attachment>,
+    dkim: Option<DkimSigner>,
+}
+
+impl MessageBuilder {
+    /// Creates an empty builder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the From header.
+    #[must_use]
+    pub fn from(mut self, address: impl Into<String>) -> Self {
+        self.headers.set("From", address);
+        self
+    }
+
+    /// Adds a To recipient.
+    #[must_use]
+    pub fn to(mut self, address: impl Into<String>) -> Self {
+        self.headers.add("To", address);
+        self
+    }
+
+    /// Adds a Cc recipient.
+    #[must_use]
+    pub fn cc(mut self, address: impl Into<String>) -> Self {
+        self.headers.add("Cc", address);
+        self
+    }
+
+    /// Sets the subject, RFC 2047-encoding it if it is not plain ASCII.
+    #[must_use]
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Adds an arbitrary header.
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.add(name, value);
+        self
+    }
+
+    /// Sets the plain text body.
+    #[must_use]
+    pub fn text_body(mut self, body: impl Into<String>) -> Self {
+        self.text_body = Some(body.into());
+        self
+    }
+
+    /// Sets the HTML body.
+    #[must_use]
+    pub fn html_body(mut self, body: impl Into<String>) -> Self {
+        self.html_body = Some(body.into());
+        self
+    }
+
+    /// Adds an attachment.
+    #[must_use]
+    pub fn attach(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Signs the built message with `signer`.
+    ///
+    /// The body hash needs the whole message, so a signed
+    /// [`build_stream`](Self::build_stream) buffers it before yielding.
+    #[must_use]
+    pub fn sign_dkim(mut self, signer: DkimSigner) -> Self {
+        self.dkim = Some(signer);
+        self
+    }
+
+    /// Builds the message in memory.
+    ///
+    /// Prefer [`build_stream`](Self::build_stream) when sending, so large
+    /// attachments are not buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attachment cannot be read or the generated
+    /// message cannot be parsed back.
+    pub async fn build(mut self) -> Result<Message> {
+        let signer = self.dkim.take();
+        let raw: Vec<u8> = self.unsigned_stream().try_concat().await?;
+        let mut message = Message::parse(raw)?;
+        if let Some(signer) = signer {
+            message.sign_dkim(&signer)?;
+        }
+        Ok(message)
+    }
+
+    /// Serializes the message as a stream of CRLF-terminated chunks.
+    ///
+    /// Attachments are read and Base64-encoded one chunk at a time, so memory
+    /// use does not grow with their size. A read error ends the stream.
+    ///
+    /// With [`sign_dkim`](Self::sign_dkim) set, the message is buffered and
+    /// signed, and its [`to_eml_bytes`](Message::to_eml_bytes) serialization
+    /// is yielded as a single chunk.
+    pub fn build_stream(mut self) -> impl Stream<Item = std::io::Result<Vec<u8>>> + Send {
+        match self.dkim.take() {
+            None => self.unsigned_stream().left_stream(),
+            Some(signer) => stream::once(async move {
+                let raw: Vec<u8> = self.unsigned_stream().try_concat().await?;
+                let mut message = Message::parse(raw).map_err(std::io::Error::other)?;
+                message.sign_dkim(&signer).map_err(std::io::Error::other)?;
+                Ok(message.to_eml_bytes())
+            })
+            .right_stream(),
+        }
+    }
+
+    /// Streams the message without signing it.
+    fn unsigned_stream(self) -> impl Stream<Item = std::io::Result<Vec<u8>>> + Send {
+        let segments = self.segments();
+        stream::unfold(segments, |mut segments| async move {
+            loop {
+                match segments.front_mut()? {
+                    Segment::Raw(bytes) => {
+                        let bytes = std::mem::take(bytes);
+                        segments.pop_front();
+                        return Some((Ok(bytes), segments));
+                    }
+                    Segment::Base64(reader) => match read_base64_chunk(reader).await {
+                        Ok(Some(chunk)) => return Some((Ok(chunk), segments)),
+                        Ok(None) => {
+                            segments.pop_front();
+                        }
+                        Err(e) => {
+                            segments.clear();
+                            return Some((Err(e), segments));
+                        }
+                    },
+                }
+            }
+        })
+    }
+
+    /// Lays the message out as literal text and attachment readers.
+    fn segments(self) -> VecDeque<Segment> {
+        let mut headers = self.headers;
+        if let Some(subject) = &self.subject {
+            let encoded = encode_rfc2047(subject, "utf-8").unwrap_or_else(|_| subject.clone());
+            headers.set("Subject", encoded);
+        }
+        headers.set("MIME-Version", "1.0");
+
+        let body = body_entity(self.text_body, self.html_body);
+        let mut segments = VecDeque::new();
+        if self.attachments.is_empty() {
+            headers.set("Content-Type", body.content_type);
+            for (name, value) in body.headers {
+                headers.set(name, value);
+            }
+            segments.push_back(Segment::Raw(
+                format!("{headers}\r\n{}", body.body).into_bytes(),
+            ));
+            return segments;
+        }
+
+        let boundary = boundary();
+        headers.set(
+            "Content-Type",
+            ContentType::new("multipart", "mixed")
+                .with_parameter("boundary", &boundary)
+                .to_string(),
+        );
+        let mut text = format!("{headers}\r\n--{boundary}\r\n{body}\r\n");
+        for attachment in self.attachments {
+            let filename = quoted_filename(&attachment.filename);
+            let content_type = attachment
+                .content_type
+                .with_parameter("name", &attachment.filename);
+            let _ = write!(
+                text,
+                "--{boundary}\r\n\
+                 Content-Type: {content_type}\r\n\
+                 Content-Disposition: attachment; filename={filename}\r\n\
+                 Content-Transfer-Encoding: base64\r\n\r\n"
+            );
+            segments.push_back(Segment::Raw(std::mem::take(&mut text).into_bytes()));
+            segments.push_back(Segment::Base64(attachment.reader));
+        }
+        segments.push_back(Segment::Raw(format!("--{boundary}--\r\n").into_bytes()));
+        segments
+    }
+}
+
+/// A piece of the serialized message.
+enum Segment {
+    /// Literal text.
+    Raw(Vec<u8>),
+    /// Attachment content, Base64-encoded as it is read.
+    Base64(Pin<Box<dyn AsyncRead + Send>>),
+}
+
+/// The message body: text, HTML or both as `multipart/alternative`.
+struct BodyEntity {
+    content_type: String,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl fmt::Display for BodyEntity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Content-Type: {}\r\n", self.content_type)?;
+        for (name, value) in &self.headers {
+            write!(f, "{name}: {value}\r\n")?;
+        }
+        write!(f, "\r\n{}", self.body)
+    }
+}
+
+/// Builds the body entity from the text and HTML bodies.
+fn body_entity(text: Option<String>, html: Option<String>) -> BodyEntity {
+    let leaf = |content_type: ContentType, body: &str| BodyEntity {
+        content_type: content_type.with_parameter("charset", "utf-8").to_string(),
+        headers: vec![("Content-Transfer-Encoding", "quoted-printable".to_string())],
+        body: format!("{}\r\n", encode_quoted_printable(body)),
+    };
+    match (text, html) {
+        (text, None) => leaf(
+            ContentType::text_plain(),
+            text.as_deref().unwrap_or_default(),
+        ),
+        (None, Some(html)) => leaf(ContentType::text_html(), &html),
+        (Some(text), Some(html)) => {
+            let boundary = boundary();
+            let text = leaf(ContentType::text_plain(), &text);
+            let html = leaf(ContentType::text_html(), &html);
+            BodyEntity {
+                content_type: ContentType::new("multipart", "alternative")
+                    .with_parameter("boundary", &boundary)
+                    .to_string(),
+                headers: Vec::new(),
+                body: format!(
+                    "--{boundary}\r\n{text}\r\n--{boundary}\r\n{html}\r\n--{boundary}--\r\n"
+                ),
+            }
+        }
+    }
+}
+
+/// Reads up to one chunk from `reader` and returns it as Base64 lines, or
+/// `None` at end of input.
+///
+/// The buffer is filled completely before encoding so that every line but
+/// the last is full length.
+async fn read_base64_chunk(
+    reader: &mut Pin<Box<dyn AsyncRead + Send>>,
+) -> std::io::Result<Option<Vec<u8>>> {
+    let mut buf = vec![0; CHUNK_INPUT];
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    if filled == 0 {
+        return Ok(None);
+    }
+
+    let mut out = Vec::with_capacity(filled / BASE64_LINE_INPUT * 78 + 78);
+    for line in buf[..filled].chunks(BASE64_LINE_INPUT) {
+        out.extend_from_slice(encode_base64(line).as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    Ok(Some(out))
+}
+
+/// Returns a boundary unique within this process.
+pub fn boundary() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("mailledger-{nanos:x}-{n}")
+}
+
+/// Quotes a filename for Content-Disposition, RFC 2047-encoding non-ASCII.
+fn quoted_filename(filename: &str) -> String {
+    let encoded = encode_rfc2047(filename, "utf-8").unwrap_or_else(|_| filename.to_string());
+    format!("\"{}\"", encoded.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Guesses a content type from a file extension.
+fn guess_content_type(path: &Path) -> ContentType {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let (main, sub) = match extension.as_str() {
+        "pdf" => ("application", "pdf"),
+        "zip" => ("application", "zip"),
+        "json" => ("application", "json"),
+        "png" => ("image", "png"),
+        "jpg" | "jpeg" => ("image", "jpeg"),
+        "gif" => ("image", "gif"),
+        "svg" => ("image", "svg+xml"),
+        "txt" => ("text", "plain"),
+        "csv" => ("text", "csv"),
+        "htm" | "html" => ("text", "html"),
+        _ => ("application", "octet-stream"),
+    };
+    ContentType::new(main, sub)
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_base64;
+
+    #[tokio::test]
+    async fn text_only_message_round_trips() {
+        let message = MessageBuilder::new()
+            .from("a@example.com")
+            .to("b@example.com")
+            .subject("Grüße")
+            .text_body("Hello")
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(message.subject().as_deref(), Some("Grüße"));
+        assert_eq!(message.body_text().unwrap().trim_end(), "Hello");
+    }
+
+    #[tokio::test]
+    async fn streams_large_file_attachment_in_chunks() {
+        const SIZE: usize = 10 * 1024 * 1024;
+        let path = std::env::temp_dir().join(format!("mailledger-mime-{}.bin", std::process::id()));
+        let data: Vec<u8> = (0..=250u8).cycle().take(SIZE).collect();
+        tokio::fs::write(&path, &data).await.unwrap();
+
+        let attachment = Attachment::from_file(&path).await.unwrap();
+        let chunks: Vec<Vec<u8>> = MessageBuilder::new()
+            .from("a@example.com")
+            .to("b@example.com")
+            .text_body("See attached")
+            .attach(attachment)
+            .build_stream()
+            .try_collect()
+            .await
+            .unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        // No chunk holds more than one read's worth of Base64
+        assert!(
+            chunks
+                .iter()
+                .all(|chunk| chunk.len() <= CHUNK_INPUT / 57 * 78)
+        );
+        let raw = String::from_utf8(chunks.concat()).unwrap();
+        let boundary = raw
+            .split("boundary=")
+            .nth(1)
+            .unwrap()
+            .split(['\r', ';'])
+            .next()
+            .unwrap()
+            .trim_matches('"')
+            .to_string();
+        assert_eq!(raw.matches(&format!("--{boundary}\r\n")).count(), 2);
+        assert!(raw.ends_with(&format!("--{boundary}--\r\n")));
+
+        let (_, encoded) = raw
+            .split_once("Content-Transfer-Encoding: base64\r\n\r\n")
+            .unwrap();
+        let encoded = encoded
+            .strip_suffix(&format!("--{boundary}--\r\n"))
+            .unwrap();
+        let lines: Vec<&str> = encoded.split_terminator("\r\n").collect();
+        assert_eq!(lines.len(), SIZE.div_ceil(57));
+        assert!(lines[..lines.len() - 1].iter().all(|line| line.len() == 76));
+        assert_eq!(encoded.len(), SIZE.div_ceil(3) * 4 + lines.len() * 2);
+        assert_eq!(decode_base64(&lines.concat()).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn attachment_with_html_alternative_parses() {
+        let message = MessageBuilder::new()
+            .from("a@example.com")
+            .to("b@example.com")
+            .text_body("plain")
+            .html_body("<p>rich</p>")
+            .attach(Attachment::from_bytes(
+                "notes \"v2\".txt",
+                ContentType::text_plain(),
+                b"hello".to_vec(),
+            ))
+            .build()
+            .await
+            .unwrap();
+
+        assert!(message.is_multipart().unwrap());
+        assert_eq!(message.text_part().unwrap().trim_end(), "plain");
+        assert_eq!(message.html_part().unwrap().trim_end(), "<p>rich</p>");
+        let (_, attachment) = message
+            .walk_parts()
+            .find(|(_, part)| part.disposition().as_deref() == Some("attachment"))
+            .unwrap();
+        assert_eq!(attachment.decode_body().unwrap(), b"hello");
+    }
+}
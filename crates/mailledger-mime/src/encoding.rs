@@ -1,10 +1,12 @@
 //! MIME encoding and decoding utilities.
 //!
-//! Supports Base64, Quoted-Printable, and RFC 2047 header encoding.
+//! Supports Base64, Quoted-Printable, RFC 2047 header encoding, and
+//! `format=flowed` (RFC 3676) text.
 
-use crate::error::{Error, Result};
+use crate::error::Result;
 use base64::Engine;
-use base64::engine::general_purpose::STANDARD;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use encoding_rs::{Encoding, WINDOWS_1252};
 use std::fmt::Write as _;
 
 /// Encodes data as Base64.
@@ -22,6 +24,58 @@ pub fn decode_base64(data: &str) -> Result<Vec<u8>> {
     STANDARD.decode(data).map_err(Into::into)
 }
 
+/// Incremental Base64 decoder for bodies that arrive in chunks.
+///
+/// Whitespace, including the CRLFs between encoded lines, is skipped, and an
+/// incomplete 4-character quantum at the end of a chunk is carried over to
+/// the next one, so chunks may be split anywhere.
+#[derive(Debug, Default)]
+pub struct Base64Decoder {
+    /// Encoded characters not yet decoded; fewer than four between calls.
+    pending: Vec<u8>,
+}
+
+impl Base64Decoder {
+    /// Creates a decoder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the complete quanta of `chunk`, appending them to `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is not valid Base64.
+    pub fn decode(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        self.pending
+            .extend(chunk.iter().copied().filter(|b| !b.is_ascii_whitespace()));
+        let complete = self.pending.len() / 4 * 4;
+        if complete > 0 {
+            STANDARD.decode_vec(&self.pending[..complete], out)?;
+            self.pending.drain(..complete);
+        }
+        Ok(())
+    }
+
+    /// Decodes the final, possibly unpadded, quantum.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the leftover characters are not valid Base64.
+    pub fn finish(self, out: &mut Vec<u8>) -> Result<()> {
+        let rest = self
+            .pending
+            .iter()
+            .rposition(|&b| b != b'=')
+            .map_or(&[][..], |last| &self.pending[..=last]);
+        if !rest.is_empty() {
+            STANDARD_NO_PAD.decode_vec(rest, out)?;
+        }
+        Ok(())
+    }
+}
+
 /// Maximum line length for Quoted-Printable encoding.
 const MAX_LINE_LENGTH: usize = 76;
 
@@ -71,50 +125,55 @@ pub fn encode_quoted_printable(text: &str) -> String {
 
 /// Decodes Quoted-Printable text (RFC 2045).
 ///
+/// Decoding is lenient, as RFC 2045 section 6.7 recommends: soft line
+/// breaks may end in CRLF or a bare LF, hex digits may be either case,
+/// whitespace added to line ends in transit is dropped, and an `=` that does
+/// not start a valid escape is kept as-is. Hard line breaks are preserved.
+///
 /// # Errors
 ///
-/// Returns an error if the input contains invalid escape sequences.
-pub fn decode_quoted_printable(text: &str) -> Result<String> {
-    let mut result = Vec::new();
-    let mut chars = text.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '=' {
-            // Soft line break
-            if chars.peek() == Some(&'\r') {
-                chars.next(); // consume \r
-                if chars.peek() == Some(&'\n') {
-                    chars.next(); // consume \n
-                    continue;
-                }
-            } else if chars.peek() == Some(&'\n') {
-                chars.next(); // consume \n
-                continue;
-            }
+/// Never fails in lenient mode; the `Result` is kept for callers that
+/// propagate decoding errors uniformly.
+pub fn decode_quoted_printable(text: &str) -> Result<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len());
+    let mut lines = text.split('\n').peekable();
+
+    while let Some(line) = lines.next() {
+        let (line, line_break) = line
+            .strip_suffix('\r')
+            .map_or((line, &b"\n"[..]), |line| (line, &b"\r\n"[..]));
+        let line = line.trim_end_matches([' ', '\t']);
+        let (line, soft_break) = line
+            .strip_suffix('=')
+            .map_or((line, false), |line| (line, true));
+
+        decode_qp_line(line.as_bytes(), &mut result);
+        if lines.peek().is_some() && !soft_break {
+            result.extend_from_slice(line_break);
+        }
+    }
 
-            // Hex encoded byte
-            let hex: String = chars.by_ref().take(2).collect();
-            if hex.len() == 2 {
-                let byte = u8::from_str_radix(&hex, 16)
-                    .map_err(|e| Error::InvalidEncoding(format!("Invalid hex: {e}")))?;
-                result.push(byte);
-            } else {
-                return Err(Error::InvalidEncoding(
-                    "Incomplete escape sequence".to_string(),
-                ));
-            }
-        } else if ch.is_ascii() {
-            // ASCII characters can be safely cast to u8
-            result.push(ch as u8);
+    Ok(result)
+}
+
+/// Decodes the `=XX` escapes of one Quoted-Printable line into `out`.
+fn decode_qp_line(line: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < line.len() {
+        let escaped = (line[i] == b'=')
+            .then(|| line.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            out.push(byte);
+            i += 3;
         } else {
-            // Non-ASCII characters need UTF-8 encoding
-            let mut buf = [0u8; 4];
-            let bytes = ch.encode_utf8(&mut buf);
-            result.extend_from_slice(bytes.as_bytes());
+            out.push(line[i]);
+            i += 1;
         }
     }
-
-    String::from_utf8(result).map_err(Into::into)
 }
 
 /// Encodes a header value using RFC 2047 encoding.
@@ -140,46 +199,183 @@ pub fn encode_rfc2047(text: &str, charset: &str) -> Result<String> {
     Ok(format!("=?{charset}?B?{encoded}?="))
 }
 
-/// Decodes RFC 2047 encoded header value.
-///
-/// Format: `=?charset?encoding?encoded-text?=`
-///
-/// # Errors
+/// Decodes RFC 2047 encoded-words in a header value.
 ///
-/// Returns an error if the input is not valid RFC 2047 format.
-pub fn decode_rfc2047(text: &str) -> Result<String> {
-    // Check for RFC 2047 format
-    if !text.starts_with("=?") || !text.ends_with("?=") {
-        return Ok(text.to_string());
+/// Each `=?charset?encoding?encoded-text?=` is decoded with the `B` (Base64)
+/// or `Q` (quoted-printable) encoding; text outside encoded-words is kept.
+/// Whitespace between adjacent encoded-words is dropped, and their bytes are
+/// joined before charset conversion so multibyte characters may be split
+/// across words. Malformed encoded-words are left as-is.
+#[must_use]
+pub fn decode_rfc2047(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pending: Option<(String, Vec<u8>)> = None;
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some((charset, bytes, len)) = parse_encoded_word(rest) {
+            match &mut pending {
+                Some((current, buf)) if current.eq_ignore_ascii_case(&charset) => {
+                    buf.extend_from_slice(&bytes);
+                }
+                _ => {
+                    flush_encoded_words(&mut out, pending.take());
+                    pending = Some((charset, bytes));
+                }
+            }
+            rest = &rest[len..];
+            let trimmed = rest.trim_start_matches([' ', '\t', '\r', '\n']);
+            if parse_encoded_word(trimmed).is_some() {
+                rest = trimmed;
+            }
+            continue;
+        }
+
+        flush_encoded_words(&mut out, pending.take());
+        let first = rest.chars().next().map_or(1, char::len_utf8);
+        let next = rest[first..].find("=?").map_or(rest.len(), |i| i + first);
+        out.push_str(&rest[..next]);
+        rest = &rest[next..];
+    }
+
+    flush_encoded_words(&mut out, pending);
+    out
+}
+
+/// Parses an encoded-word at the start of `text`, returning its charset,
+/// decoded bytes, and length in `text`.
+fn parse_encoded_word(text: &str) -> Option<(String, Vec<u8>, usize)> {
+    let inner = text.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let encoded = &inner[..end];
+    if charset.is_empty()
+        || charset.contains(char::is_whitespace)
+        || encoded.contains(char::is_whitespace)
+    {
+        return None;
     }
 
-    let inner = &text[2..text.len() - 2];
-    let parts: Vec<&str> = inner.split('?').collect();
+    let bytes = match encoding {
+        "B" | "b" => STANDARD
+            .decode(encoded)
+            .or_else(|_| STANDARD_NO_PAD.decode(encoded.trim_end_matches('=')))
+            .ok()?,
+        "Q" | "q" => decode_q(encoded)?,
+        _ => return None,
+    };
+    // Drop an RFC 2231 language suffix such as `utf-8*en`.
+    let name = charset.split('*').next().unwrap_or(charset);
+    let len = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    Some((name.to_string(), bytes, len))
+}
+
+/// Decodes RFC 2047 `Q` encoding: `_` is a space and `=XX` a hex byte.
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => out.push(b' '),
+            b'=' => {
+                let hex = text.get(i + 1..i + 3)?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 2;
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    Some(out)
+}
 
-    if parts.len() != 3 {
-        return Err(Error::InvalidEncoding(
-            "Invalid RFC 2047 format".to_string(),
-        ));
+/// Appends pending encoded-word bytes to `out`, converted from their charset.
+fn flush_encoded_words(out: &mut String, pending: Option<(String, Vec<u8>)>) {
+    if let Some((charset, bytes)) = pending {
+        out.push_str(&decode_charset(&bytes, &charset));
     }
+}
+
+/// Transcodes bytes in the named charset to UTF-8.
+///
+/// Charset labels are resolved as in the WHATWG Encoding Standard, so
+/// aliases such as `latin2` or `sjis` work. Unknown charsets are decoded as
+/// UTF-8 with invalid sequences replaced.
+#[must_use]
+pub fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    Encoding::for_label(charset.trim().as_bytes()).map_or_else(
+        || String::from_utf8_lossy(bytes).into_owned(),
+        |encoding| encoding.decode(bytes).0.into_owned(),
+    )
+}
 
-    let encoding = parts[1].to_uppercase();
-    let encoded_text = parts[2];
+/// Decodes text whose charset was not declared: UTF-8 if valid, otherwise
+/// Windows-1252.
+#[must_use]
+pub fn decode_undeclared_charset(bytes: &[u8]) -> String {
+    std::str::from_utf8(bytes).map_or_else(
+        |_| WINDOWS_1252.decode(bytes).0.into_owned(),
+        ToString::to_string,
+    )
+}
+
+/// Decodes a `format=flowed` text body (RFC 3676).
+///
+/// Lines ending in a space are soft breaks and are joined with the next
+/// line at the same quote depth. Space-stuffing is removed, quoted lines are
+/// re-prefixed with `>` per level, and the `-- ` signature separator is
+/// kept as a hard break. With `delsp`, the trailing space of each soft
+/// break is deleted rather than kept between the joined words.
+#[must_use]
+pub fn decode_format_flowed(body: &str, delsp: bool) -> String {
+    let mut lines = Vec::new();
+    let mut paragraph: Option<(usize, String)> = None;
+
+    for line in body.lines() {
+        let depth = line.bytes().take_while(|&b| b == b'>').count();
+        let text = &line[depth..];
+        let text = text.strip_prefix(' ').unwrap_or(text);
+        let flowed = text.ends_with(' ') && text != "-- ";
+
+        if paragraph.as_ref().is_some_and(|(d, _)| *d != depth)
+            && let Some(done) = paragraph.take()
+        {
+            lines.push(done);
+        }
+
+        let text = if flowed && delsp {
+            &text[..text.len() - 1]
+        } else {
+            text
+        };
+        paragraph
+            .get_or_insert_with(|| (depth, String::new()))
+            .1
+            .push_str(text);
+
+        if !flowed && let Some(done) = paragraph.take() {
+            lines.push(done);
+        }
+    }
+    lines.extend(paragraph);
 
-    match encoding.as_str() {
-        "B" => {
-            // Base64
-            let decoded = decode_base64(encoded_text)?;
-            String::from_utf8(decoded).map_err(Into::into)
+    let mut out = String::with_capacity(body.len());
+    for (i, (depth, text)) in lines.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
         }
-        "Q" => {
-            // Quoted-Printable (with underscore for space)
-            let text_with_spaces = encoded_text.replace('_', " ");
-            decode_quoted_printable(&text_with_spaces)
+        if *depth > 0 {
+            out.push_str(&">".repeat(*depth));
+            out.push(' ');
         }
-        _ => Err(Error::InvalidEncoding(format!(
-            "Unknown encoding: {encoding}"
-        ))),
+        out.push_str(text);
     }
+    if body.ends_with('\n') {
+        out.push('\n');
+    }
+    out
 }
 
 #[cfg(test)]
@@ -220,18 +416,51 @@ mod tests {
     fn test_quoted_printable_decode() {
         let encoded = "Hello, World!";
         let decoded = decode_quoted_printable(encoded).unwrap();
-        assert_eq!(decoded, "Hello, World!");
+        assert_eq!(decoded, b"Hello, World!");
 
         let encoded = "H=C3=A9llo";
         let decoded = decode_quoted_printable(encoded).unwrap();
-        assert_eq!(decoded, "Héllo");
+        assert_eq!(decoded, "Héllo".as_bytes());
     }
 
     #[test]
     fn test_quoted_printable_soft_line_break() {
         let encoded = "Hello=\r\nWorld";
         let decoded = decode_quoted_printable(encoded).unwrap();
-        assert_eq!(decoded, "HelloWorld");
+        assert_eq!(decoded, b"HelloWorld");
+    }
+
+    #[test]
+    fn test_quoted_printable_soft_break_with_bare_lf() {
+        let decoded = decode_quoted_printable("Hel=\nlo=  \nWorld").unwrap();
+        assert_eq!(decoded, b"HelloWorld");
+    }
+
+    #[test]
+    fn test_quoted_printable_soft_break_at_end_of_body() {
+        let decoded = decode_quoted_printable("line one\r\nunfinish=\r\n").unwrap();
+        assert_eq!(decoded, b"line one\r\nunfinish");
+
+        let decoded = decode_quoted_printable("mid-wo=").unwrap();
+        assert_eq!(decoded, b"mid-wo");
+    }
+
+    #[test]
+    fn test_quoted_printable_hex_either_case() {
+        let decoded = decode_quoted_printable("a=3Db=3dc=e9").unwrap();
+        assert_eq!(decoded, b"a=b=c\xe9");
+    }
+
+    #[test]
+    fn test_quoted_printable_invalid_escape_passes_through() {
+        let decoded = decode_quoted_printable("x = y, 100%=zz, end=4").unwrap();
+        assert_eq!(decoded, b"x = y, 100%=zz, end=4");
+    }
+
+    #[test]
+    fn test_quoted_printable_strips_transport_whitespace_keeps_hard_breaks() {
+        let decoded = decode_quoted_printable("one  \r\ntwo\t\nthree=20\r\n").unwrap();
+        assert_eq!(decoded, b"one\r\ntwo\nthree \r\n");
     }
 
     #[test]
@@ -249,28 +478,51 @@ mod tests {
     #[test]
     fn test_rfc2047_decode() {
         let encoded = "Hello";
-        let decoded = decode_rfc2047(encoded).unwrap();
+        let decoded = decode_rfc2047(encoded);
         assert_eq!(decoded, "Hello");
 
         let encoded = "=?utf-8?B?SMOpbGxv?=";
-        let decoded = decode_rfc2047(encoded).unwrap();
+        let decoded = decode_rfc2047(encoded);
         assert_eq!(decoded, "Héllo");
     }
 
     #[test]
     fn test_rfc2047_quoted_printable() {
         let encoded = "=?utf-8?Q?H=C3=A9llo?=";
-        let decoded = decode_rfc2047(encoded).unwrap();
+        let decoded = decode_rfc2047(encoded);
         assert_eq!(decoded, "Héllo");
     }
 
+    #[test]
+    fn test_rfc2047_mixed_encoded_and_plain() {
+        assert_eq!(
+            decode_rfc2047("Re: =?UTF-8?B?Q2Fmw6k=?= menu"),
+            "Re: Café menu"
+        );
+        assert_eq!(decode_rfc2047("a =?bogus"), "a =?bogus");
+    }
+
+    #[test]
+    fn test_rfc2047_adjacent_words_join_split_multibyte() {
+        assert_eq!(
+            decode_rfc2047("=?UTF-8?Q?Caf=C3?= \r\n =?UTF-8?Q?=A9_au_lait?="),
+            "Café au lait"
+        );
+    }
+
+    #[test]
+    fn test_rfc2047_legacy_charsets() {
+        assert_eq!(decode_rfc2047("=?ISO-8859-1?Q?caf=E9?="), "café");
+        assert_eq!(decode_rfc2047("=?windows-1252?Q?=80_price?="), "€ price");
+    }
+
     #[test]
     fn test_quoted_printable_decode_non_ascii() {
         // Test that non-ASCII characters in the input are properly handled
         let encoded = "Héllo=C3=A9";
         let decoded = decode_quoted_printable(encoded).unwrap();
         // The non-ASCII 'é' at the start should be preserved as UTF-8
-        assert!(decoded.contains("Héllo"));
+        assert_eq!(decoded, "Hélloé".as_bytes());
     }
 
     #[test]
@@ -278,7 +530,7 @@ mod tests {
         // Test mixed ASCII and non-ASCII
         let encoded = "Hello=20World!=C3=A9";
         let decoded = decode_quoted_printable(encoded).unwrap();
-        assert_eq!(decoded, "Hello World!é");
+        assert_eq!(decoded, "Hello World!é".as_bytes());
     }
 
     #[test]
@@ -296,4 +548,115 @@ mod tests {
         let decoded = decode_base64(&encoded.replace('\n', "")).unwrap();
         assert_eq!(decoded, b"Hello, World!");
     }
+
+    #[test]
+    fn test_decode_charset_latin2() {
+        // "Łódź" in ISO-8859-2
+        assert_eq!(
+            decode_charset(&[0xA3, 0xF3, 0x64, 0xBC], "ISO-8859-2"),
+            "Łódź"
+        );
+    }
+
+    #[test]
+    fn test_decode_charset_shift_jis() {
+        // "日本" in Shift_JIS
+        assert_eq!(
+            decode_charset(&[0x93, 0xFA, 0x96, 0x7B], "Shift_JIS"),
+            "日本"
+        );
+    }
+
+    #[test]
+    fn test_decode_charset_unknown_is_lossy_utf8() {
+        assert_eq!(
+            decode_charset(b"caf\xC3\xA9\xFF", "x-unknown"),
+            "café\u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn test_decode_undeclared_charset_falls_back_to_windows_1252() {
+        assert_eq!(decode_undeclared_charset("café".as_bytes()), "café");
+        assert_eq!(decode_undeclared_charset(b"\x80 caf\xE9"), "€ café");
+    }
+
+    #[test]
+    fn test_format_flowed_joins_soft_breaks() {
+        let body = "This is a long \r\nparagraph that \r\nwraps.\r\nNext line.\r\n";
+        assert_eq!(
+            decode_format_flowed(body, false),
+            "This is a long paragraph that wraps.\nNext line.\n"
+        );
+    }
+
+    #[test]
+    fn test_format_flowed_quoted() {
+        let body = "> Quoted text \r\n> continues.\r\n>> Deeper \r\n> back to one\r\nReply";
+        assert_eq!(
+            decode_format_flowed(body, false),
+            "> Quoted text continues.\n>> Deeper \n> back to one\nReply"
+        );
+    }
+
+    #[test]
+    fn test_format_flowed_delsp() {
+        let body = "Split wor \r\nd here\r\n";
+        assert_eq!(decode_format_flowed(body, true), "Split word here\n");
+    }
+
+    #[test]
+    fn test_format_flowed_space_stuffing_and_signature() {
+        let body = " From here\r\n >not a quote\r\n-- \r\nSig";
+        assert_eq!(
+            decode_format_flowed(body, false),
+            "From here\n>not a quote\n-- \nSig"
+        );
+    }
+
+    /// Decodes `encoded` fed to a [`Base64Decoder`] in `size`-byte chunks.
+    fn decode_in_chunks(encoded: &[u8], size: usize) -> Vec<u8> {
+        let mut decoder = Base64Decoder::new();
+        let mut out = Vec::new();
+        for chunk in encoded.chunks(size) {
+            decoder.decode(chunk, &mut out).unwrap();
+        }
+        decoder.finish(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_base64_decoder_splits_mid_quantum() {
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = encode_base64(&data);
+        let wrapped: Vec<u8> = encoded
+            .as_bytes()
+            .chunks(76)
+            .flat_map(|line| [line, b"\r\n"].concat())
+            .collect();
+
+        for size in [1, 2, 3, 5, 7, 77, 78, 1000] {
+            assert_eq!(decode_in_chunks(&wrapped, size), data, "chunk size {size}");
+        }
+    }
+
+    #[test]
+    fn test_base64_decoder_handles_padding_and_split_crlf() {
+        // "Hello!!" encodes to "SGVsbG8hIQ==" with two padding characters
+        let encoded = b"SGVs\r\nbG8h\r\nIQ==\r\n";
+        for size in 1..encoded.len() {
+            assert_eq!(decode_in_chunks(encoded, size), b"Hello!!");
+        }
+    }
+
+    #[test]
+    fn test_base64_decoder_accepts_missing_padding() {
+        assert_eq!(decode_in_chunks(b"SGVsbG8hIQ", 3), b"Hello!!");
+    }
+
+    #[test]
+    fn test_base64_decoder_rejects_invalid_input() {
+        let mut decoder = Base64Decoder::new();
+        assert!(decoder.decode(b"SG*s", &mut Vec::new()).is_err());
+    }
 }
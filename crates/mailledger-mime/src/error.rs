@@ -40,7 +40,15 @@ pub enum Error {
     #[error("Missing required header: {0}")]
     MissingHeader(String),
 
+    /// DKIM key or signing error.
+    #[error("DKIM error: {0}")]
+    Dkim(String),
+
     /// Parse error.
     #[error("Parse error: {0}")]
     Parse(String),
+
+    /// I/O error while reading an attachment.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
 }
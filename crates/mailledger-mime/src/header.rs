@@ -2,13 +2,20 @@
 
 use crate::encoding::{decode_rfc2047, encode_rfc2047};
 use crate::error::Result;
-use std::collections::HashMap;
+use std::borrow::Cow;
 use std::fmt;
 
+/// Preferred maximum line length, excluding CRLF (RFC 5322 section 2.1.1).
+const FOLD_WIDTH: usize = 78;
+
 /// Collection of email headers.
-#[derive(Debug, Clone, Default)]
+///
+/// Headers keep the order and spelling they were added or parsed with, so a
+/// parsed message serializes back with its header block intact. Lookups are
+/// case-insensitive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Headers {
-    headers: HashMap<String, Vec<String>>,
+    headers: Vec<(String, String)>,
 }
 
 impl Headers {
@@ -18,47 +25,68 @@ impl Headers {
         Self::default()
     }
 
-    /// Adds a header value.
+    /// Adds a header value after any existing headers.
     pub fn add(&mut self, name: impl Into<String>, value: impl Into<String>) {
-        let name = name.into().to_lowercase();
-        let value = value.into();
-        self.headers.entry(name).or_default().push(value);
+        self.headers.push((name.into(), value.into()));
     }
 
     /// Sets a header value, replacing any existing values.
+    ///
+    /// The value takes the position of the first existing occurrence, or is
+    /// appended if the header is not present.
     pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
-        let name = name.into().to_lowercase();
+        let name = name.into();
         let value = value.into();
-        self.headers.insert(name, vec![value]);
+        match self.position(&name) {
+            Some(index) => {
+                self.headers[index].1 = value;
+                let mut seen = 0;
+                self.headers.retain(|(existing, _)| {
+                    if !existing.eq_ignore_ascii_case(&name) {
+                        return true;
+                    }
+                    seen += 1;
+                    seen == 1
+                });
+            }
+            None => self.headers.push((name, value)),
+        }
     }
 
     /// Gets the first value for a header.
     #[must_use]
     pub fn get(&self, name: &str) -> Option<&str> {
-        self.headers
-            .get(&name.to_lowercase())
-            .and_then(|v| v.first().map(String::as_str))
+        self.position(name)
+            .map(|index| self.headers[index].1.as_str())
     }
 
     /// Gets all values for a header.
     #[must_use]
     pub fn get_all(&self, name: &str) -> Vec<&str> {
         self.headers
-            .get(&name.to_lowercase())
-            .map(|v| v.iter().map(String::as_str).collect())
-            .unwrap_or_default()
+            .iter()
+            .filter(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
     }
 
     /// Removes all values for a header.
     pub fn remove(&mut self, name: &str) {
-        self.headers.remove(&name.to_lowercase());
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
     }
 
-    /// Returns an iterator over all headers.
+    /// Returns an iterator over all headers in order.
     pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
         self.headers
             .iter()
-            .flat_map(|(name, values)| values.iter().map(move |v| (name.as_str(), v.as_str())))
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|(existing, _)| existing.eq_ignore_ascii_case(name))
     }
 
     /// Parses headers from raw text.
@@ -88,11 +116,11 @@ impl Headers {
                 break;
             }
 
-            // Check for continuation line (starts with space or tab)
+            // Continuation line (starts with space or tab): unfolding only
+            // removes the line break, so the leading whitespace is kept.
             if line.starts_with(' ') || line.starts_with('\t') {
                 if current_name.is_some() {
-                    current_value.push(' ');
-                    current_value.push_str(line.trim());
+                    current_value.push_str(line.trim_end());
                 }
             } else {
                 // Save previous header if exists
@@ -117,6 +145,31 @@ impl Headers {
         Ok(headers)
     }
 
+    /// Folds a header value so that `name: value` lines stay within 78
+    /// characters.
+    ///
+    /// Breaks are inserted as CRLF before a space, so unfolding restores the
+    /// value exactly. A word that does not fit is left on its own line, which
+    /// may then exceed 78 characters (RFC 5322 allows up to 998).
+    #[must_use]
+    pub fn fold_value(name: &str, value: &str) -> String {
+        let mut folded = String::with_capacity(value.len() + value.len() / FOLD_WIDTH * 3);
+        let mut line_len = name.len() + 2;
+        for (i, word) in value.split(' ').enumerate() {
+            if i > 0 {
+                if line_len + 1 + word.len() > FOLD_WIDTH && !word.is_empty() {
+                    folded.push_str("\r\n");
+                    line_len = 0;
+                }
+                folded.push(' ');
+                line_len += 1;
+            }
+            folded.push_str(word);
+            line_len += word.len();
+        }
+        folded
+    }
+
     /// Encodes a header value using RFC 2047 if needed.
     ///
     /// # Errors
@@ -132,37 +185,40 @@ impl Headers {
     ///
     /// Returns an error if decoding fails.
     pub fn decode_value(value: &str) -> Result<String> {
-        decode_rfc2047(value)
+        Ok(decode_rfc2047(value))
     }
 }
 
 impl fmt::Display for Headers {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut sorted_headers: Vec<_> = self.headers.iter().collect();
-        sorted_headers.sort_by(|(a, _), (b, _)| a.cmp(b));
-
-        for (name, values) in sorted_headers {
-            // Capitalize header name (e.g., "content-type" -> "Content-Type")
-            let capitalized = name
-                .split('-')
-                .map(|part| {
-                    let mut chars = part.chars();
-                    chars.next().map_or_else(String::new, |first| {
-                        first.to_uppercase().collect::<String>() + chars.as_str()
-                    })
-                })
-                .collect::<Vec<_>>()
-                .join("-");
-
-            for value in values {
-                writeln!(f, "{capitalized}: {value}")?;
-            }
+        for (name, value) in &self.headers {
+            let folded = Self::fold_value(name, value);
+            write!(f, "{}: {folded}\r\n", display_name(name))?;
         }
 
         Ok(())
     }
 }
 
+/// Capitalizes an all-lowercase header name (e.g., "content-type" ->
+/// "Content-Type"); names with any uppercase letter are kept as written.
+fn display_name(name: &str) -> Cow<'_, str> {
+    if name.chars().any(char::is_uppercase) {
+        return Cow::Borrowed(name);
+    }
+    let capitalized = name
+        .split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().collect::<String>() + chars.as_str()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("-");
+    Cow::Owned(capitalized)
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -258,4 +314,51 @@ mod tests {
         }
         assert_eq!(count, 2);
     }
+
+    #[test]
+    fn test_headers_keep_order_and_spelling() {
+        let mut headers = Headers::new();
+        headers.add("X-Mailer", "test");
+        headers.add("Subject", "Hi");
+        headers.add("From", "a@example.com");
+        headers.add("Received", "by one");
+        headers.add("Received", "by two");
+        headers.set("subject", "Hello");
+
+        assert_eq!(
+            headers.to_string(),
+            "X-Mailer: test\r\nSubject: Hello\r\nFrom: a@example.com\r\n\
+             Received: by one\r\nReceived: by two\r\n"
+        );
+        assert_eq!(Headers::parse(&headers.to_string()).unwrap(), headers);
+    }
+
+    #[test]
+    fn test_fold_value_short_is_unchanged() {
+        assert_eq!(Headers::fold_value("Subject", "Hello world"), "Hello world");
+    }
+
+    #[test]
+    fn test_long_references_fold_and_round_trip() {
+        let references: Vec<String> = (0..8)
+            .map(|i| format!("<message-{i}.1234567890@mail.example.com>"))
+            .collect();
+        let references = references.join(" ");
+        assert!(references.len() > 300);
+
+        let mut headers = Headers::new();
+        headers.add("References", references.clone());
+        let text = headers.to_string();
+
+        assert!(text.lines().count() > 1);
+        assert!(text.lines().all(|line| line.len() <= 78));
+        let parsed = Headers::parse(&text).unwrap();
+        assert_eq!(parsed.get("references"), Some(references.as_str()));
+    }
+
+    #[test]
+    fn test_parse_unfolds_tab_continuation() {
+        let headers = Headers::parse("Subject: one\r\n\ttwo\r\n\r\n").unwrap();
+        assert_eq!(headers.get("subject"), Some("one\ttwo"));
+    }
 }
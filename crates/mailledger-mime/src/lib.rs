@@ -9,6 +9,8 @@
 //! - **Encoding/Decoding**: Base64, Quoted-Printable, RFC 2047 header encoding
 //! - **Content types**: Full MIME content type support
 //! - **Multipart**: Mixed, alternative, related message types
+//! - **DKIM**: `rsa-sha256` signing of outgoing messages
+//! - **Read receipts**: RFC 8098 disposition notifications
 //!
 //! ## Quick Start
 //!
@@ -25,7 +27,7 @@
 //!                    Hello, World!";
 //!
 //! let message = Message::parse(raw_message)?;
-//! println!("Subject: {}", message.subject().unwrap_or("(no subject)"));
+//! println!("Subject: {}", message.subject().as_deref().unwrap_or("(no subject)"));
 //! println!("Body: {}", message.body_text()?);
 //! ```
 //!
@@ -39,7 +41,8 @@
 //!     .to("recipient@example.com")
 //!     .subject("Test Message")
 //!     .text_body("Hello, World!")
-//!     .build()?;
+//!     .build()
+//!     .await?;
 //!
 //! println!("{}", message.to_string());
 //! ```
@@ -49,15 +52,16 @@
 //! ```ignore
 //! use mailledger_mime::{MessageBuilder, Attachment};
 //!
-//! let attachment = Attachment::from_file("document.pdf")?;
+//! // The file is read as the message is streamed, not up front
+//! let attachment = Attachment::from_file("document.pdf").await?;
 //!
-//! let message = MessageBuilder::new()
+//! let chunks = MessageBuilder::new()
 //!     .from("sender@example.com")
 //!     .to("recipient@example.com")
 //!     .subject("Document")
 //!     .text_body("Please find the attached document.")
 //!     .attach(attachment)
-//!     .build()?;
+//!     .build_stream();
 //! ```
 //!
 //! ### Multipart Messages
@@ -71,7 +75,8 @@
 //!     .subject("Test")
 //!     .text_body("Plain text version")
 //!     .html_body("<html><body><h1>HTML version</h1></body></html>")
-//!     .build()?; // Creates multipart/alternative
+//!     .build()
+//!     .await?; // Creates multipart/alternative
 //! ```
 //!
 //! ### Encoding/Decoding
@@ -96,14 +101,20 @@
 #![warn(clippy::pedantic)]
 #![forbid(unsafe_code)]
 
+mod builder;
 mod content_type;
 mod error;
 mod header;
 mod message;
 
+pub mod dkim;
 pub mod encoding;
+pub mod mdn;
 
+pub use builder::{Attachment, MessageBuilder};
 pub use content_type::ContentType;
+pub use dkim::DkimSigner;
 pub use error::{Error, Result};
 pub use header::Headers;
+pub use mdn::{MdnDisposition, MdnMode, MdnReport, generate_mdn, parse_mdn};
 pub use message::{Message, Part, TransferEncoding};
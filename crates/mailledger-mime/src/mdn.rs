@@ -0,0 +1,373 @@
+//! Message Disposition Notifications (RFC 8098).
+//!
+//! A sender asks for a read receipt with `Disposition-Notification-To`.
+//! [`generate_mdn`] builds the `multipart/report` answer and [`parse_mdn`]
+//! reads one back, so a receipt can be matched to the message it is about.
+
+use crate::builder::boundary;
+use crate::content_type::ContentType;
+use crate::encoding::encode_rfc2047;
+use crate::header::Headers;
+use crate::message::{Message, Part};
+
+/// Product named in the `Reporting-UA` field.
+const REPORTING_UA: &str = "MailLedger";
+
+/// What happened to the original message (RFC 8098 section 3.2.6.2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MdnDisposition {
+    /// Shown to the recipient.
+    Displayed,
+    /// Deleted without being shown.
+    Deleted,
+    /// Sent on somewhere without being shown.
+    Dispatched,
+    /// Handled in some other way without being shown.
+    Processed,
+}
+
+impl MdnDisposition {
+    /// Returns the disposition-type keyword.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Displayed => "displayed",
+            Self::Deleted => "deleted",
+            Self::Dispatched => "dispatched",
+            Self::Processed => "processed",
+        }
+    }
+
+    /// Parses a disposition-type keyword, ignoring case and any
+    /// `/modifier` suffix.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let keyword = s.split('/').next().unwrap_or_default().trim();
+        [
+            Self::Displayed,
+            Self::Deleted,
+            Self::Dispatched,
+            Self::Processed,
+        ]
+        .into_iter()
+        .find(|disposition| keyword.eq_ignore_ascii_case(disposition.as_str()))
+    }
+
+    /// Word used in the notification's subject.
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Displayed => "Read",
+            Self::Deleted => "Deleted",
+            Self::Dispatched => "Dispatched",
+            Self::Processed => "Processed",
+        }
+    }
+}
+
+/// Whether the user chose to send the notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MdnMode {
+    /// Sent after asking the user (`manual-action/MDN-sent-manually`).
+    Manual,
+    /// Sent by policy without asking (`automatic-action/MDN-sent-automatically`).
+    Automatic,
+}
+
+impl MdnMode {
+    /// Returns the action-mode and sending-mode pair.
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Manual => "manual-action/MDN-sent-manually",
+            Self::Automatic => "automatic-action/MDN-sent-automatically",
+        }
+    }
+}
+
+/// The machine-readable fields of a received notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MdnReport {
+    /// Message-ID of the message the notification is about.
+    pub original_message_id: Option<String>,
+    /// Address the notification was generated for.
+    pub final_recipient: Option<String>,
+    /// What happened to the message.
+    pub disposition: MdnDisposition,
+    /// How the notification was triggered.
+    pub mode: MdnMode,
+}
+
+impl Message {
+    /// Gets the address a read receipt was requested for, from
+    /// `Disposition-Notification-To`.
+    #[must_use]
+    pub fn disposition_notification_to(&self) -> Option<&str> {
+        self.headers
+            .get("disposition-notification-to")
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Builds a notification telling the sender of `original` what happened to
+/// it.
+///
+/// `recipient` is the bare address of the user reporting, used for `From`
+/// and `Final-Recipient`. The result is a `multipart/report` holding a
+/// human-readable note, the `message/disposition-notification` fields and
+/// the original headers. Returns `None` if `original` did not ask for a
+/// receipt.
+#[must_use]
+pub fn generate_mdn(
+    original: &Message,
+    disposition: MdnDisposition,
+    mode: MdnMode,
+    recipient: &str,
+) -> Option<Message> {
+    let notify_to = original.disposition_notification_to()?;
+    let subject = original.subject().unwrap_or_default();
+    let message_id = original.message_id();
+
+    let boundary = boundary();
+    let content_type = ContentType::new("multipart", "report")
+        .with_parameter("report-type", "disposition-notification")
+        .with_parameter("boundary", &boundary);
+
+    let mut headers = Headers::new();
+    headers.set("From", recipient);
+    headers.set("To", notify_to);
+    let subject_line = format!("{}: {subject}", disposition.label());
+    headers.set(
+        "Subject",
+        encode_rfc2047(&subject_line, "utf-8").unwrap_or(subject_line),
+    );
+    headers.set("Date", chrono::Utc::now().to_rfc2822());
+    if let Some(id) = message_id {
+        headers.set("In-Reply-To", id);
+        headers.set("References", id);
+    }
+    // Keeps auto-responders from answering the receipt (RFC 3834)
+    headers.set("Auto-Submitted", "auto-replied");
+    headers.set("MIME-Version", "1.0");
+    headers.set("Content-Type", content_type.to_string());
+
+    let note = match disposition {
+        MdnDisposition::Displayed => format!(
+            "The message sent to {recipient} with the subject \"{subject}\" has been \
+             displayed. This is no guarantee that it has been read or understood."
+        ),
+        other => format!(
+            "The message sent to {recipient} with the subject \"{subject}\" has been {} \
+             without being displayed.",
+            other.as_str()
+        ),
+    };
+    let mut note_headers = Headers::new();
+    note_headers.set("Content-Type", "text/plain; charset=utf-8");
+    note_headers.set("Content-Transfer-Encoding", "8bit");
+
+    let mut fields = vec![format!("Reporting-UA: {REPORTING_UA}")];
+    if let Some(original_recipient) = original.headers.get("original-recipient") {
+        fields.push(format!("Original-Recipient: {original_recipient}"));
+    }
+    fields.push(format!("Final-Recipient: rfc822; {recipient}"));
+    if let Some(id) = message_id {
+        fields.push(format!("Original-Message-ID: {id}"));
+    }
+    fields.push(format!(
+        "Disposition: {}; {}",
+        mode.as_str(),
+        disposition.as_str()
+    ));
+    let mut fields = fields.join("\r\n");
+    fields.push_str("\r\n");
+    let mut fields_headers = Headers::new();
+    fields_headers.set("Content-Type", "message/disposition-notification");
+
+    let mut original_headers = Headers::new();
+    original_headers.set("Content-Type", "text/rfc822-headers");
+
+    Some(Message::multipart(
+        headers,
+        vec![
+            Part::new(note_headers, note.into_bytes()),
+            Part::new(fields_headers, fields.into_bytes()),
+            Part::new(original_headers, original.headers.to_string().into_bytes()),
+        ],
+    ))
+}
+
+/// Reads the notification fields from a received `multipart/report`.
+///
+/// Returns `None` if `message` is not a disposition notification or its
+/// `Disposition` field is missing or unknown.
+#[must_use]
+pub fn parse_mdn(message: &Message) -> Option<MdnReport> {
+    let content_type = message.content_type().ok()?;
+    let is_report = content_type.main_type == "multipart"
+        && content_type.sub_type == "report"
+        && content_type
+            .parameters
+            .get("report-type")
+            .is_some_and(|report| report.eq_ignore_ascii_case("disposition-notification"));
+    if !is_report {
+        return None;
+    }
+
+    let part = message.parts.iter().find(|part| {
+        part.content_type()
+            .is_ok_and(|ct| ct.main_type == "message" && ct.sub_type == "disposition-notification")
+    })?;
+    let fields = Headers::parse(&String::from_utf8_lossy(&part.body)).ok()?;
+
+    let (mode, disposition) = fields.get("disposition")?.split_once(';')?;
+    let mode = if mode
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with("automatic-action")
+    {
+        MdnMode::Automatic
+    } else {
+        MdnMode::Manual
+    };
+    let disposition = MdnDisposition::parse(disposition)?;
+
+    let final_recipient = fields.get("final-recipient").map(|value| {
+        value
+            .split_once(';')
+            .map_or(value, |(_, address)| address)
+            .trim()
+            .to_string()
+    });
+
+    Some(MdnReport {
+        original_message_id: fields.get("original-message-id").map(ToString::to_string),
+        final_recipient,
+        disposition,
+        mode,
+    })
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+
+    fn original() -> Message {
+        Message::parse(
+            "From: Alice <alice@example.com>\r\n\
+             To: bob@example.com\r\n\
+             Subject: Quarterly numbers\r\n\
+             Message-ID: <q3@example.com>\r\n\
+             Disposition-Notification-To: alice@example.com\r\n\
+             \r\n\
+             See attached.",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_generated_report_structure() {
+        let mdn = generate_mdn(
+            &original(),
+            MdnDisposition::Displayed,
+            MdnMode::Manual,
+            "bob@example.com",
+        )
+        .unwrap();
+
+        let content_type = mdn.content_type().unwrap();
+        assert_eq!(content_type.sub_type, "report");
+        assert_eq!(
+            content_type.parameters.get("report-type").unwrap(),
+            "disposition-notification"
+        );
+        assert_eq!(mdn.headers.get("to"), Some("alice@example.com"));
+        assert_eq!(mdn.headers.get("in-reply-to"), Some("<q3@example.com>"));
+        assert_eq!(mdn.subject().unwrap(), "Read: Quarterly numbers");
+
+        // The parts survive serialization in RFC 8098 order
+        let parsed = Message::parse(mdn.to_eml_bytes()).unwrap();
+        let types: Vec<String> = parsed
+            .parts
+            .iter()
+            .map(|part| {
+                let ct = part.content_type().unwrap();
+                format!("{}/{}", ct.main_type, ct.sub_type)
+            })
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "text/plain",
+                "message/disposition-notification",
+                "text/rfc822-headers"
+            ]
+        );
+
+        let fields = String::from_utf8(parsed.parts[1].body.clone()).unwrap();
+        assert!(fields.contains("Final-Recipient: rfc822; bob@example.com\r\n"));
+        assert!(fields.contains("Original-Message-ID: <q3@example.com>\r\n"));
+        assert!(fields.contains("Disposition: manual-action/MDN-sent-manually; displayed\r\n"));
+        let headers = String::from_utf8(parsed.parts[2].body.clone()).unwrap();
+        assert!(headers.contains("Subject: Quarterly numbers"));
+    }
+
+    #[test]
+    fn test_parse_generated_report() {
+        let mdn = generate_mdn(
+            &original(),
+            MdnDisposition::Deleted,
+            MdnMode::Automatic,
+            "bob@example.com",
+        )
+        .unwrap();
+        let report = parse_mdn(&Message::parse(mdn.to_eml_bytes()).unwrap()).unwrap();
+        assert_eq!(
+            report,
+            MdnReport {
+                original_message_id: Some("<q3@example.com>".to_string()),
+                final_recipient: Some("bob@example.com".to_string()),
+                disposition: MdnDisposition::Deleted,
+                mode: MdnMode::Automatic,
+            }
+        );
+    }
+
+    #[test]
+    fn test_no_receipt_without_request() {
+        let mut message = original();
+        message.headers.remove("Disposition-Notification-To");
+        assert!(message.disposition_notification_to().is_none());
+        assert!(
+            generate_mdn(
+                &message,
+                MdnDisposition::Displayed,
+                MdnMode::Manual,
+                "bob@example.com"
+            )
+            .is_none()
+        );
+        assert!(parse_mdn(&message).is_none());
+    }
+
+    #[test]
+    fn test_disposition_parse_ignores_case_and_modifier() {
+        assert_eq!(
+            MdnDisposition::parse(" Displayed"),
+            Some(MdnDisposition::Displayed)
+        );
+        assert_eq!(
+            MdnDisposition::parse("processed/error"),
+            Some(MdnDisposition::Processed)
+        );
+        assert_eq!(MdnDisposition::parse("denied"), None);
+    }
+}
@@ -1,9 +1,14 @@
 //! MIME message structure and handling.
 
 use crate::content_type::ContentType;
-use crate::encoding::{decode_base64, decode_quoted_printable};
+use crate::dkim::DkimSigner;
+use crate::encoding::{
+    decode_base64, decode_charset, decode_format_flowed, decode_quoted_printable, decode_rfc2047,
+    decode_undeclared_charset,
+};
 use crate::error::{Error, Result};
 use crate::header::Headers;
+use std::collections::HashMap;
 use std::fmt;
 
 /// Transfer encoding types.
@@ -47,20 +52,56 @@ impl fmt::Display for TransferEncoding {
     }
 }
 
+/// Maximum multipart nesting depth; deeper parts are kept as opaque leaves.
+const MAX_DEPTH: usize = 32;
+
 /// MIME message part.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Part {
     /// Part headers.
     pub headers: Headers,
     /// Part body (raw bytes).
     pub body: Vec<u8>,
+    /// Child parts, if this part is itself multipart.
+    pub parts: Vec<Self>,
 }
 
 impl Part {
     /// Creates a new part.
     #[must_use]
     pub const fn new(headers: Headers, body: Vec<u8>) -> Self {
-        Self { headers, body }
+        Self {
+            headers,
+            body,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Parses a part from raw bytes, splitting multipart bodies recursively.
+    fn parse(raw: &[u8], depth: usize) -> Result<Self> {
+        let (headers, body) = split_header_body(raw)?;
+        let parts = child_parts(&headers, body, depth)?;
+        Ok(Self {
+            headers,
+            body: body.to_vec(),
+            parts,
+        })
+    }
+
+    /// Gets the Content-ID without its angle brackets.
+    #[must_use]
+    pub fn content_id(&self) -> Option<&str> {
+        self.headers.get("content-id").map(bare_content_id)
+    }
+
+    /// Gets the disposition type from Content-Disposition (e.g. `inline`,
+    /// `attachment`), lowercased.
+    #[must_use]
+    pub fn disposition(&self) -> Option<String> {
+        self.headers
+            .get("content-disposition")
+            .and_then(|value| value.split(';').next())
+            .map(|kind| kind.trim().to_lowercase())
     }
 
     /// Gets the content type.
@@ -97,8 +138,7 @@ impl Part {
             }
             TransferEncoding::QuotedPrintable => {
                 let body_str = String::from_utf8_lossy(&self.body);
-                let decoded = decode_quoted_printable(&body_str)?;
-                Ok(decoded.into_bytes())
+                decode_quoted_printable(&body_str)
             }
             _ => Ok(self.body.clone()),
         }
@@ -108,15 +148,15 @@ impl Part {
     ///
     /// # Errors
     ///
-    /// Returns an error if decoding or UTF-8 conversion fails.
+    /// Returns an error if the transfer encoding cannot be decoded.
     pub fn body_text(&self) -> Result<String> {
         let decoded = self.decode_body()?;
-        String::from_utf8(decoded).map_err(Into::into)
+        Ok(decode_text(self.headers.get("content-type"), &decoded))
     }
 }
 
 /// MIME message.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Message {
     /// Message headers.
     pub headers: Headers,
@@ -127,6 +167,54 @@ pub struct Message {
 }
 
 impl Message {
+    /// Parses a raw RFC 5322 message.
+    ///
+    /// Multipart bodies are split on their boundaries recursively, so
+    /// `multipart/mixed` containing `multipart/alternative` containing
+    /// `multipart/related` becomes a tree of [`Part`]s.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the headers are malformed or a multipart part has
+    /// no boundary.
+    pub fn parse(raw: impl AsRef<[u8]>) -> Result<Self> {
+        let (headers, body) = split_header_body(raw.as_ref())?;
+        let parts = child_parts(&headers, body, 0)?;
+        if parts.is_empty() {
+            Ok(Self::single_part(headers, body.to_vec()))
+        } else {
+            Ok(Self::multipart(headers, parts))
+        }
+    }
+
+    /// Parses the contents of an `.eml` file.
+    ///
+    /// Equivalent to [`Message::parse`]; pairs with [`Message::to_eml_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the headers are malformed or a multipart part has
+    /// no boundary.
+    pub fn from_eml_bytes(raw: &[u8]) -> Result<Self> {
+        Self::parse(raw)
+    }
+
+    /// Serializes the message as RFC 5322 bytes suitable for an `.eml` file.
+    ///
+    /// Headers are written in their original order with folding and CRLF line
+    /// endings. Multipart messages are rebuilt from their parts and boundary;
+    /// bodies are written byte-for-byte, so 8-bit content survives.
+    #[must_use]
+    pub fn to_eml_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let body = self.body.as_deref().unwrap_or_default();
+        write_entity(&mut out, &self.headers, body, &self.parts);
+        if !self.parts.is_empty() {
+            out.extend_from_slice(b"\r\n");
+        }
+        out
+    }
+
     /// Creates a new message.
     #[must_use]
     pub const fn new(headers: Headers) -> Self {
@@ -177,22 +265,22 @@ impl Message {
         Ok(self.content_type()?.is_multipart())
     }
 
-    /// Gets the From header.
+    /// Gets the From header, with RFC 2047 encoded-words decoded.
     #[must_use]
-    pub fn from(&self) -> Option<&str> {
-        self.headers.get("from")
+    pub fn from(&self) -> Option<String> {
+        self.headers.get("from").map(decode_rfc2047)
     }
 
-    /// Gets the To header.
+    /// Gets the To header, with RFC 2047 encoded-words decoded.
     #[must_use]
-    pub fn to(&self) -> Option<&str> {
-        self.headers.get("to")
+    pub fn to(&self) -> Option<String> {
+        self.headers.get("to").map(decode_rfc2047)
     }
 
-    /// Gets the Subject header.
+    /// Gets the Subject header, with RFC 2047 encoded-words decoded.
     #[must_use]
-    pub fn subject(&self) -> Option<&str> {
-        self.headers.get("subject")
+    pub fn subject(&self) -> Option<String> {
+        self.headers.get("subject").map(decode_rfc2047)
     }
 
     /// Gets the Date header.
@@ -207,6 +295,22 @@ impl Message {
         self.headers.get("message-id")
     }
 
+    /// Adds a `DKIM-Signature` header signed by `signer`.
+    ///
+    /// Call this after all other headers and the body are final, then send
+    /// [`to_eml_bytes`](Self::to_eml_bytes) unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if signing fails; see [`DkimSigner::sign`].
+    pub fn sign_dkim(&mut self, signer: &DkimSigner) -> Result<()> {
+        let signature = signer.sign(self)?;
+        for (name, value) in signature.iter() {
+            self.headers.add(name, value);
+        }
+        Ok(())
+    }
+
     /// Gets the body as text for single-part messages.
     ///
     /// # Errors
@@ -238,13 +342,71 @@ impl Message {
             }
             TransferEncoding::QuotedPrintable => {
                 let body_str = String::from_utf8_lossy(body);
-                let decoded = decode_quoted_printable(&body_str)?;
-                decoded.into_bytes()
+                decode_quoted_printable(&body_str)?
             }
             _ => body.clone(),
         };
 
-        String::from_utf8(decoded).map_err(Into::into)
+        Ok(decode_text(self.headers.get("content-type"), &decoded))
+    }
+
+    /// Returns every leaf part with its MIME path, in document order.
+    ///
+    /// Paths use 1-based section numbers as in IMAP `BODY[1.2]`. Single-part
+    /// messages have no parts; use [`body_text`](Self::body_text) instead.
+    pub fn walk_parts(&self) -> impl Iterator<Item = (Vec<usize>, &Part)> {
+        let mut leaves = Vec::new();
+        collect_leaves(&self.parts, &mut Vec::new(), &mut leaves);
+        leaves.into_iter()
+    }
+
+    /// Finds the part with the given Content-ID.
+    ///
+    /// Accepts the ID with or without angle brackets; matching ignores case.
+    #[must_use]
+    pub fn find_part(&self, content_id: &str) -> Option<&Part> {
+        let wanted = bare_content_id(content_id);
+        let mut stack: Vec<&Part> = self.parts.iter().collect();
+        while let Some(part) = stack.pop() {
+            if part
+                .content_id()
+                .is_some_and(|id| id.eq_ignore_ascii_case(wanted))
+            {
+                return Some(part);
+            }
+            stack.extend(&part.parts);
+        }
+        None
+    }
+
+    /// Returns every part that has a Content-ID, keyed by the bare ID in
+    /// lowercase.
+    #[must_use]
+    pub fn inline_parts(&self) -> HashMap<String, &Part> {
+        let mut inline = HashMap::new();
+        let mut stack: Vec<&Part> = self.parts.iter().collect();
+        while let Some(part) = stack.pop() {
+            if let Some(id) = part.content_id() {
+                inline.entry(id.to_lowercase()).or_insert(part);
+            }
+            stack.extend(&part.parts);
+        }
+        inline
+    }
+
+    /// Resolves a `cid:` URL from an HTML body (RFC 2392) to its part.
+    ///
+    /// The `cid:` prefix is optional and the URL is percent-decoded before
+    /// matching. Use [`Part::decode_body`] and [`Part::content_type`] on the
+    /// result to get the image bytes and type.
+    #[must_use]
+    pub fn resolve_cid(&self, cid: &str) -> Option<&Part> {
+        let cid = cid.trim();
+        let id = cid
+            .get(..4)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("cid:"))
+            .map_or(cid, |_| &cid[4..]);
+        self.find_part(&percent_decode(id))
     }
 
     /// Finds the first text/plain part in a multipart message.
@@ -253,7 +415,7 @@ impl Message {
     ///
     /// Returns an error if no text part is found or decoding fails.
     pub fn text_part(&self) -> Result<String> {
-        for part in &self.parts {
+        for (_, part) in self.walk_parts() {
             let ct = part.content_type()?;
             if ct.main_type == "text" && ct.sub_type == "plain" {
                 return part.body_text();
@@ -269,7 +431,7 @@ impl Message {
     ///
     /// Returns an error if no HTML part is found or decoding fails.
     pub fn html_part(&self) -> Result<String> {
-        for part in &self.parts {
+        for (_, part) in self.walk_parts() {
             let ct = part.content_type()?;
             if ct.main_type == "text" && ct.sub_type == "html" {
                 return part.body_text();
@@ -280,6 +442,201 @@ impl Message {
     }
 }
 
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = Vec::new();
+        write_entity(&mut out, &self.headers, &self.body, &self.parts);
+        f.write_str(&String::from_utf8_lossy(&out))
+    }
+}
+
+impl fmt::Display for Message {
+    /// Serializes the message like [`Message::to_eml_bytes`], replacing any
+    /// bytes that are not UTF-8.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_eml_bytes()))
+    }
+}
+
+/// Writes headers, a blank line, and either the parts or the body.
+fn write_entity(out: &mut Vec<u8>, headers: &Headers, body: &[u8], parts: &[Part]) {
+    out.extend_from_slice(headers.to_string().as_bytes());
+    out.extend_from_slice(b"\r\n");
+    let boundary = headers
+        .get("content-type")
+        .and_then(|value| ContentType::parse(value).ok())
+        .and_then(|content_type| content_type.boundary().map(str::to_string));
+    match boundary {
+        Some(boundary) if !parts.is_empty() => {
+            for part in parts {
+                out.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+                write_entity(out, &part.headers, &part.body, &part.parts);
+                out.extend_from_slice(b"\r\n");
+            }
+            // The line break after the close delimiter belongs to the
+            // enclosing entity, so nested bodies parse back byte-for-byte.
+            out.extend_from_slice(format!("--{boundary}--").as_bytes());
+        }
+        _ => out.extend_from_slice(body),
+    }
+}
+
+/// Decodes `format=flowed` text/plain bodies; other text is returned as-is.
+/// Converts a transfer-decoded body to text.
+///
+/// Transcodes from the declared charset, or from UTF-8 falling back to
+/// Windows-1252 when none is declared, then reflows `format=flowed` text.
+fn decode_text(content_type: Option<&str>, bytes: &[u8]) -> String {
+    let Some(content_type) = content_type.and_then(|value| ContentType::parse(value).ok()) else {
+        return decode_undeclared_charset(bytes);
+    };
+    let text = content_type.parameters.get("charset").map_or_else(
+        || decode_undeclared_charset(bytes),
+        |charset| decode_charset(bytes, charset),
+    );
+    let param_is = |name: &str, expected: &str| {
+        content_type
+            .parameters
+            .get(name)
+            .is_some_and(|value| value.eq_ignore_ascii_case(expected))
+    };
+    if content_type.is_text() && content_type.sub_type == "plain" && param_is("format", "flowed") {
+        decode_format_flowed(&text, param_is("delsp", "yes"))
+    } else {
+        text
+    }
+}
+
+/// Strips whitespace and angle brackets from a Content-ID.
+fn bare_content_id(id: &str) -> &str {
+    id.trim().trim_start_matches('<').trim_end_matches('>')
+}
+
+/// Decodes `%XX` escapes; malformed escapes are kept as-is.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Splits raw bytes into lines, yielding each line's start offset, its
+/// content without the line ending, and the offset of the next line.
+fn lines(data: &[u8]) -> impl Iterator<Item = (usize, &[u8], usize)> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        if pos >= data.len() {
+            return None;
+        }
+        let start = pos;
+        let end = data[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |i| start + i);
+        pos = (end + 1).min(data.len());
+        let line = &data[start..end];
+        Some((start, line.strip_suffix(b"\r").unwrap_or(line), pos))
+    })
+}
+
+/// Splits raw bytes at the first blank line into parsed headers and body.
+fn split_header_body(raw: &[u8]) -> Result<(Headers, &[u8])> {
+    let (head, body) = lines(raw)
+        .find(|(_, line, _)| line.is_empty())
+        .map_or((raw, &[][..]), |(start, _, next)| {
+            (&raw[..start], &raw[next..])
+        });
+    let headers = Headers::parse(&String::from_utf8_lossy(head))?;
+    Ok((headers, body))
+}
+
+/// Parses the children of a multipart body; other bodies have none.
+fn child_parts(headers: &Headers, body: &[u8], depth: usize) -> Result<Vec<Part>> {
+    let Some(content_type) = headers
+        .get("content-type")
+        .and_then(|value| ContentType::parse(value).ok())
+    else {
+        return Ok(Vec::new());
+    };
+    if !content_type.is_multipart() || depth >= MAX_DEPTH {
+        return Ok(Vec::new());
+    }
+    let boundary = content_type.boundary().ok_or(Error::MissingBoundary)?;
+    split_multipart(body, boundary)
+        .into_iter()
+        .map(|raw| Part::parse(raw, depth + 1))
+        .collect()
+}
+
+/// Splits a multipart body into the raw bytes of each part (RFC 2046).
+///
+/// Only whole lines consisting of `--boundary` or `--boundary--` (plus
+/// optional trailing whitespace) are delimiters, so the boundary string may
+/// appear inside part content. The preamble and epilogue are dropped.
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{boundary}");
+    let mut parts = Vec::new();
+    let mut current = None;
+
+    for (start, line, next) in lines(body) {
+        let Some(rest) = line.strip_prefix(delimiter.as_bytes()) else {
+            continue;
+        };
+        let closing = rest.starts_with(b"--");
+        let padding = if closing { &rest[2..] } else { rest };
+        if !padding.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        if let Some(content_start) = current {
+            // The line break before a delimiter belongs to the delimiter.
+            let content = &body[content_start..start];
+            let content = content.strip_suffix(b"\n").unwrap_or(content);
+            parts.push(content.strip_suffix(b"\r").unwrap_or(content));
+        }
+        if closing {
+            return parts;
+        }
+        current = Some(next);
+    }
+
+    // Tolerate a missing close delimiter.
+    if let Some(content_start) = current {
+        parts.push(&body[content_start..]);
+    }
+    parts
+}
+
+/// Collects the leaves under `parts` with their 1-based paths.
+fn collect_leaves<'a>(
+    parts: &'a [Part],
+    path: &mut Vec<usize>,
+    leaves: &mut Vec<(Vec<usize>, &'a Part)>,
+) {
+    for (index, part) in parts.iter().enumerate() {
+        path.push(index + 1);
+        if part.parts.is_empty() {
+            leaves.push((path.clone(), part));
+        } else {
+            collect_leaves(&part.parts, path, leaves);
+        }
+        path.pop();
+    }
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -332,9 +689,9 @@ mod tests {
         let body = b"Hello, World!".to_vec();
         let message = Message::single_part(headers, body);
 
-        assert_eq!(message.from(), Some("sender@example.com"));
-        assert_eq!(message.to(), Some("recipient@example.com"));
-        assert_eq!(message.subject(), Some("Test"));
+        assert_eq!(message.from().as_deref(), Some("sender@example.com"));
+        assert_eq!(message.to().as_deref(), Some("recipient@example.com"));
+        assert_eq!(message.subject().as_deref(), Some("Test"));
         assert_eq!(message.body_text().unwrap(), "Hello, World!");
     }
 
@@ -356,4 +713,227 @@ mod tests {
         assert!(message.is_multipart().unwrap());
         assert_eq!(message.parts.len(), 2);
     }
+
+    const NESTED: &str = "From: a@example.com\r\n\
+Subject: Nested\r\n\
+Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+\r\n\
+This is the preamble.\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=\"alt\"\r\n\
+\r\n\
+--alt\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Plain text, mentioning --outer inline\r\n\
+--outer-not-a-delimiter\r\n\
+--alt\r\n\
+Content-Type: multipart/related; boundary=\"rel\"\r\n\
+\r\n\
+--rel\r\n\
+Content-Type: text/html\r\n\
+\r\n\
+<img src=\"cid:logo@example.com\">\r\n\
+--rel\r\n\
+Content-Type: image/png\r\n\
+Content-ID: <logo@example.com>\r\n\
+Content-Disposition: inline\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+iVBORw0K\r\n\
+--rel--\r\n\
+--alt--\r\n\
+--outer\r\n\
+Content-Type: application/pdf\r\n\
+Content-Disposition: attachment; filename=\"doc.pdf\"\r\n\
+\r\n\
+PDF\r\n\
+--outer--\r\n\
+Epilogue.\r\n";
+
+    #[test]
+    fn test_parse_nested_multipart() {
+        let message = Message::parse(NESTED).unwrap();
+        assert_eq!(message.subject().as_deref(), Some("Nested"));
+        assert_eq!(message.parts.len(), 2);
+
+        let leaves: Vec<(Vec<usize>, String)> = message
+            .walk_parts()
+            .map(|(path, part)| {
+                let ct = part.content_type().unwrap();
+                (path, format!("{}/{}", ct.main_type, ct.sub_type))
+            })
+            .collect();
+        assert_eq!(
+            leaves,
+            [
+                (vec![1, 1], "text/plain".to_string()),
+                (vec![1, 2, 1], "text/html".to_string()),
+                (vec![1, 2, 2], "image/png".to_string()),
+                (vec![2], "application/pdf".to_string()),
+            ]
+        );
+        assert_eq!(
+            message.parts[1].disposition().as_deref(),
+            Some("attachment")
+        );
+    }
+
+    #[test]
+    fn test_boundary_substring_in_body_is_not_a_delimiter() {
+        let message = Message::parse(NESTED).unwrap();
+        assert_eq!(
+            message.text_part().unwrap(),
+            "Plain text, mentioning --outer inline\r\n--outer-not-a-delimiter"
+        );
+    }
+
+    #[test]
+    fn test_find_part_by_content_id() {
+        let message = Message::parse(NESTED).unwrap();
+        let image = message.find_part("<logo@example.com>").unwrap();
+        assert_eq!(image.content_id(), Some("logo@example.com"));
+        assert_eq!(image.disposition().as_deref(), Some("inline"));
+        assert_eq!(image.decode_body().unwrap(), b"\x89PNG\r\n");
+        assert!(message.find_part("<missing@example.com>").is_none());
+    }
+
+    #[test]
+    fn test_resolve_cid_url() {
+        let message = Message::parse(NESTED).unwrap();
+        let image = message.resolve_cid("CID:Logo%40Example.com").unwrap();
+        assert_eq!(image.content_type().unwrap().sub_type, "png");
+        assert!(message.resolve_cid("cid:other@example.com").is_none());
+    }
+
+    #[test]
+    fn test_inline_parts_keyed_by_lowercase_id() {
+        let message = Message::parse(NESTED).unwrap();
+        let inline = message.inline_parts();
+        assert_eq!(inline.len(), 1);
+        assert!(inline.contains_key("logo@example.com"));
+    }
+
+    #[test]
+    fn test_body_text_decodes_format_flowed() {
+        let message = Message::parse(
+            "Content-Type: text/plain; format=flowed; delsp=yes\r\n\r\nHel \r\nlo\r\n",
+        )
+        .unwrap();
+        assert_eq!(message.body_text().unwrap(), "Hello\n");
+    }
+
+    #[test]
+    fn test_to_string_round_trips_nested_message() {
+        let message = Message::parse(NESTED).unwrap();
+        let reparsed = Message::parse(message.to_string()).unwrap();
+        let paths: Vec<Vec<usize>> = reparsed.walk_parts().map(|(path, _)| path).collect();
+        assert_eq!(paths, [vec![1, 1], vec![1, 2, 1], vec![1, 2, 2], vec![2]]);
+        assert_eq!(reparsed.text_part().unwrap(), message.text_part().unwrap());
+    }
+
+    #[test]
+    fn test_eml_round_trip_preserves_structure() {
+        let message = Message::from_eml_bytes(NESTED.as_bytes()).unwrap();
+        let eml = message.to_eml_bytes();
+        let reparsed = Message::from_eml_bytes(&eml).unwrap();
+
+        assert_eq!(reparsed, message);
+        assert_eq!(reparsed.to_eml_bytes(), eml);
+        let names: Vec<&str> = reparsed.headers.iter().map(|(name, _)| name).collect();
+        let original: Vec<&str> = message.headers.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, original);
+    }
+
+    #[test]
+    fn test_eml_bytes_keep_8bit_body() {
+        let raw = b"Subject: Caf\xe9\r\nContent-Type: text/plain; charset=iso-8859-1\r\n\
+            Content-Transfer-Encoding: 8bit\r\n\r\nCaf\xe9\r\n";
+        let message = Message::from_eml_bytes(raw).unwrap();
+        assert_eq!(message.body.as_deref(), Some(&b"Caf\xe9\r\n"[..]));
+        assert!(message.to_eml_bytes().ends_with(b"\r\n\r\nCaf\xe9\r\n"));
+    }
+
+    #[test]
+    fn test_to_string_folds_long_headers() {
+        let references = (0..8)
+            .map(|i| format!("<id-{i}.1234567890@mail.example.com>"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut headers = Headers::new();
+        headers.add("references", references.clone());
+        let message = Message::single_part(headers, b"Body".to_vec());
+
+        let text = message.to_string();
+        assert!(text.lines().all(|line| line.len() <= 78));
+        let reparsed = Message::parse(&text).unwrap();
+        assert_eq!(
+            reparsed.headers.get("references"),
+            Some(references.as_str())
+        );
+        assert_eq!(reparsed.body_text().unwrap(), "Body");
+    }
+
+    #[test]
+    fn test_accessors_decode_encoded_words() {
+        let message = Message::parse(
+            "From: =?ISO-8859-1?Q?Andr=E9?= <andre@example.com>\r\n\
+             Subject: =?UTF-8?B?Q2Fmw6k=?=\r\n\r\nBody",
+        )
+        .unwrap();
+        assert_eq!(message.subject().as_deref(), Some("Café"));
+        assert_eq!(message.from().as_deref(), Some("André <andre@example.com>"));
+    }
+
+    #[test]
+    fn test_body_text_transcodes_declared_charset() {
+        let mut raw = b"Content-Type: text/plain; charset=ISO-8859-2\r\n\
+            Content-Transfer-Encoding: 8bit\r\n\r\n"
+            .to_vec();
+        raw.extend_from_slice(&[0xA3, 0xF3, 0x64, 0xBC]);
+        let message = Message::parse(&raw).unwrap();
+        assert_eq!(message.body_text().unwrap(), "Łódź");
+    }
+
+    #[test]
+    fn test_part_body_text_transcodes_shift_jis() {
+        let mut raw = b"Content-Type: multipart/alternative; boundary=b\r\n\r\n\
+            --b\r\nContent-Type: text/plain; charset=Shift_JIS\r\n\r\n"
+            .to_vec();
+        raw.extend_from_slice(&[0x93, 0xFA, 0x96, 0x7B]);
+        raw.extend_from_slice(b"\r\n--b--\r\n");
+        let message = Message::parse(&raw).unwrap();
+        assert_eq!(message.text_part().unwrap(), "日本");
+    }
+
+    #[test]
+    fn test_body_text_decodes_latin1_quoted_printable() {
+        let message = Message::parse(
+            "Content-Type: text/plain; charset=iso-8859-1\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\r\n\
+             caf=e9 au l=\r\nait =3D 2\r\n",
+        )
+        .unwrap();
+        assert_eq!(message.body_text().unwrap(), "café au lait = 2\r\n");
+    }
+
+    #[test]
+    fn test_body_text_without_charset_falls_back_to_windows_1252() {
+        let message = Message::parse(b"Subject: Hi\r\n\r\ncaf\xE9").unwrap();
+        assert_eq!(message.body_text().unwrap(), "café");
+    }
+
+    #[test]
+    fn test_parse_single_part() {
+        let message = Message::parse("Subject: Hi\r\n\r\nHello, World!").unwrap();
+        assert!(message.parts.is_empty());
+        assert_eq!(message.body_text().unwrap(), "Hello, World!");
+        assert_eq!(message.walk_parts().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_multipart_without_boundary() {
+        let result = Message::parse("Content-Type: multipart/mixed\r\n\r\nbody");
+        assert!(matches!(result, Err(Error::MissingBoundary)));
+    }
 }
@@ -12,6 +12,11 @@ pub enum Error {
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
 
+    /// Keyring access error.
+    #[cfg(feature = "keyring")]
+    #[error("Keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+
     /// HTTP request error.
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
@@ -33,6 +38,10 @@ pub enum Error {
     #[error("Token expired")]
     TokenExpired,
 
+    /// No token stored for the account.
+    #[error("No token stored for account {0}")]
+    NoStoredToken(String),
+
     /// No refresh token available.
     #[error("No refresh token available")]
     NoRefreshToken,
@@ -45,6 +54,10 @@ pub enum Error {
     #[error("Authorization timed out after {0} seconds")]
     Timeout(u64),
 
+    /// Redirect `state` did not match the one sent with the authorization request.
+    #[error("OAuth2 state mismatch")]
+    StateMismatch,
+
     /// User denied authorization.
     #[error("User denied authorization")]
     AccessDenied,
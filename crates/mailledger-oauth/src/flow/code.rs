@@ -1,10 +1,33 @@
 //! Authorization Code Flow implementation.
 
 use super::{OAuthClient, PkceChallenge};
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::token::Token;
+use std::net::SocketAddr;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use url::Url;
 
+/// Default time to wait for the browser redirect.
+const DEFAULT_LISTEN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Time a loopback connection gets to send its request head.
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest redirect request head accepted by the loopback listener.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Page shown in the browser once the code has been captured.
+const SUCCESS_PAGE: &str = "<!DOCTYPE html><html><head><title>Signed in</title></head>\
+<body><p>Authorization complete. You can close this window and return to MailLedger.</p>\
+</body></html>";
+
+/// Page shown in the browser when the redirect carried an error.
+const FAILURE_PAGE: &str = "<!DOCTYPE html><html><head><title>Sign-in failed</title></head>\
+<body><p>Authorization failed. Return to MailLedger and try again.</p></body></html>";
+
 /// Authorization Code Flow for `OAuth2`.
 ///
 /// This flow is suitable for applications that can open a browser
@@ -13,22 +36,50 @@ use url::Url;
 pub struct AuthorizationCodeFlow {
     client: OAuthClient,
     pkce: Option<PkceChallenge>,
+    /// State passed to the last [`authorization_url`](Self::authorization_url) call.
+    state: Mutex<Option<String>>,
+    listen_timeout: Duration,
 }
 
 impl AuthorizationCodeFlow {
     /// Creates a new authorization code flow.
     #[must_use]
     pub const fn new(client: OAuthClient) -> Self {
-        Self { client, pkce: None }
+        Self {
+            client,
+            pkce: None,
+            state: Mutex::new(None),
+            listen_timeout: DEFAULT_LISTEN_TIMEOUT,
+        }
     }
 
     /// Enables PKCE for enhanced security (recommended for public clients).
+    ///
+    /// Generates a random verifier; [`authorization_url`](Self::authorization_url)
+    /// sends its S256 challenge and [`exchange_code`](Self::exchange_code)
+    /// sends the verifier.
     #[must_use]
     pub fn with_pkce(mut self) -> Self {
         self.pkce = Some(PkceChallenge::generate());
         self
     }
 
+    /// Enables PKCE with an existing challenge, e.g. one restored with
+    /// [`PkceChallenge::from_verifier`] after the app restarted mid-flow.
+    #[must_use]
+    pub fn with_pkce_challenge(mut self, pkce: PkceChallenge) -> Self {
+        self.pkce = Some(pkce);
+        self
+    }
+
+    /// Sets how long [`listen_for_code`](Self::listen_for_code) waits for the
+    /// redirect (default 5 minutes).
+    #[must_use]
+    pub const fn with_listen_timeout(mut self, timeout: Duration) -> Self {
+        self.listen_timeout = timeout;
+        self
+    }
+
     /// Builds the authorization URL for user consent.
     ///
     /// The user should be redirected to this URL to authorize the application.
@@ -43,6 +94,7 @@ impl AuthorizationCodeFlow {
     /// Returns an error if the URL cannot be constructed.
     pub fn authorization_url(&self, scopes: Option<&[String]>, state: Option<&str>) -> Result<Url> {
         let mut url = self.client.provider.auth_url.clone();
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = state.map(str::to_string);
 
         {
             let mut pairs = url.query_pairs_mut();
@@ -90,6 +142,79 @@ impl AuthorizationCodeFlow {
         Ok(url)
     }
 
+    /// Waits for the browser redirect on a loopback HTTP listener.
+    ///
+    /// Binds `bind`, which should match the host and port of the client's
+    /// redirect URI, and answers requests until one carries a `code` or an
+    /// `error`. The browser is shown a short result page. The returned
+    /// `state` has already been checked against the one passed to
+    /// [`authorization_url`](Self::authorization_url).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if binding fails, no redirect arrives within the
+    /// listen timeout, the user denied access, the provider returned an
+    /// error, or the state does not match.
+    pub async fn listen_for_code(&self, bind: SocketAddr) -> Result<(String, Option<String>)> {
+        let listener = TcpListener::bind(bind).await?;
+        tokio::time::timeout(self.listen_timeout, self.accept_redirect(&listener))
+            .await
+            .map_err(|_| Error::Timeout(self.listen_timeout.as_secs()))?
+    }
+
+    /// Accepts connections until one is the authorization redirect.
+    async fn accept_redirect(&self, listener: &TcpListener) -> Result<(String, Option<String>)> {
+        loop {
+            let (mut stream, _) = listener.accept().await?;
+            // A connection that never sends a request must not hold up the
+            // browser's redirect behind it.
+            let Ok(params) =
+                tokio::time::timeout(REQUEST_READ_TIMEOUT, read_redirect_params(&mut stream)).await
+            else {
+                continue;
+            };
+            let Some(params) = params else {
+                // Favicon requests, probes and the like.
+                respond(&mut stream, "404 Not Found", "").await;
+                continue;
+            };
+
+            let get = |name: &str| {
+                params
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            };
+            let returned_state = get("state");
+            let expected_state = self
+                .state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone();
+
+            let result = if let Some(error) = get("error") {
+                Err(if error == "access_denied" {
+                    Error::AccessDenied
+                } else {
+                    Error::oauth_error(error, get("error_description").unwrap_or_default())
+                })
+            } else if expected_state.is_some() && returned_state != expected_state {
+                Err(Error::StateMismatch)
+            } else if let Some(code) = get("code") {
+                Ok((code, returned_state))
+            } else {
+                respond(&mut stream, "404 Not Found", "").await;
+                continue;
+            };
+
+            match &result {
+                Ok(_) => respond(&mut stream, "200 OK", SUCCESS_PAGE).await,
+                Err(_) => respond(&mut stream, "400 Bad Request", FAILURE_PAGE).await,
+            }
+            return result;
+        }
+    }
+
     /// Exchanges the authorization code for an access token.
     ///
     /// # Arguments
@@ -107,6 +232,12 @@ impl AuthorizationCodeFlow {
             .await
     }
 
+    /// Returns the PKCE challenge if PKCE is enabled.
+    #[must_use]
+    pub const fn pkce_challenge(&self) -> Option<&PkceChallenge> {
+        self.pkce.as_ref()
+    }
+
     /// Returns the PKCE verifier if PKCE is enabled.
     #[must_use]
     pub fn pkce_verifier(&self) -> Option<&str> {
@@ -114,6 +245,43 @@ impl AuthorizationCodeFlow {
     }
 }
 
+/// Reads an HTTP request head and returns its query parameters.
+///
+/// Returns `None` for anything that is not a GET with a query string.
+async fn read_redirect_params(stream: &mut TcpStream) -> Option<Vec<(String, String)>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await.ok()?;
+        if n == 0 || head.len() + n > MAX_REQUEST_SIZE {
+            return None;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next()?.split(' ');
+    if request_line.next()? != "GET" {
+        return None;
+    }
+    let target = request_line.next()?;
+    let url = Url::parse("http://localhost").ok()?.join(target).ok()?;
+    url.query()?;
+    Some(url.query_pairs().into_owned().collect())
+}
+
+/// Writes a minimal HTML response and closes the connection.
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/html; charset=utf-8\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    // The browser going away does not affect the result.
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -127,6 +295,7 @@ impl AuthorizationCodeFlow {
 mod tests {
     use super::*;
     use crate::provider::Provider;
+    use tokio::net::TcpListener;
 
     #[test]
     fn test_authorization_url() {
@@ -184,4 +353,157 @@ mod tests {
         assert!(url.as_str().contains("access_type=offline"));
         assert!(url.as_str().contains("prompt=consent"));
     }
+
+    #[test]
+    fn test_authorization_url_sends_s256_challenge() {
+        let pkce =
+            PkceChallenge::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk").unwrap();
+        let client = OAuthClient::new("test_client", Provider::google().unwrap());
+        let flow = AuthorizationCodeFlow::new(client).with_pkce_challenge(pkce);
+
+        let url = flow.authorization_url(None, None).unwrap();
+
+        assert!(
+            url.as_str()
+                .contains("code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM")
+        );
+        assert!(url.as_str().contains("code_challenge_method=S256"));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_sends_stored_verifier() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("grant_type=authorization_code") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = r#"{"access_token":"token","token_type":"Bearer"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let provider = Provider::new(
+            "Test",
+            format!("http://{addr}/auth"),
+            format!("http://{addr}/token"),
+        )
+        .unwrap();
+        let flow = AuthorizationCodeFlow::new(OAuthClient::new("client", provider)).with_pkce();
+        let verifier = flow.pkce_verifier().unwrap().to_string();
+
+        flow.exchange_code("abc", None).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.contains(&format!("code_verifier={verifier}")));
+    }
+
+    /// Sends a GET to the listener once it is up and returns the response.
+    async fn redirect(addr: SocketAddr, target: &str) -> String {
+        let mut stream = loop {
+            if let Ok(stream) = TcpStream::connect(addr).await {
+                break stream;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        stream
+            .write_all(format!("GET {target} HTTP/1.1\r\nHost: {addr}\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn loopback_flow() -> (AuthorizationCodeFlow, SocketAddr) {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = OAuthClient::new("test_client", Provider::google().unwrap())
+            .with_redirect_uri(format!("http://{addr}/callback"));
+        (AuthorizationCodeFlow::new(client), addr)
+    }
+
+    #[tokio::test]
+    async fn test_listen_for_code_captures_code_and_state() {
+        let (flow, addr) = loopback_flow();
+        flow.authorization_url(None, Some("xyz")).unwrap();
+
+        let browser = tokio::spawn(async move {
+            let favicon = redirect(addr, "/favicon.ico").await;
+            let page = redirect(addr, "/callback?code=abc%2F123&state=xyz").await;
+            (favicon, page)
+        });
+        let (code, state) = flow.listen_for_code(addr).await.unwrap();
+        let (favicon, page) = browser.await.unwrap();
+
+        assert_eq!(code, "abc/123");
+        assert_eq!(state.as_deref(), Some("xyz"));
+        assert!(favicon.starts_with("HTTP/1.1 404"));
+        assert!(page.starts_with("HTTP/1.1 200 OK"));
+        assert!(page.contains("Authorization complete"));
+    }
+
+    #[tokio::test]
+    async fn test_listen_for_code_skips_idle_connection() {
+        let (flow, addr) = loopback_flow();
+
+        let browser = tokio::spawn(async move {
+            let idle = loop {
+                if let Ok(stream) = TcpStream::connect(addr).await {
+                    break stream;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            };
+            let page = redirect(addr, "/callback?code=abc").await;
+            drop(idle);
+            page
+        });
+        let (code, _) = flow.listen_for_code(addr).await.unwrap();
+
+        assert_eq!(code, "abc");
+        assert!(browser.await.unwrap().starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[tokio::test]
+    async fn test_listen_for_code_rejects_state_mismatch() {
+        let (flow, addr) = loopback_flow();
+        flow.authorization_url(None, Some("expected")).unwrap();
+
+        let browser = tokio::spawn(redirect(addr, "/callback?code=abc&state=forged"));
+        let result = flow.listen_for_code(addr).await;
+
+        assert!(matches!(result, Err(Error::StateMismatch)));
+        assert!(browser.await.unwrap().starts_with("HTTP/1.1 400"));
+    }
+
+    #[tokio::test]
+    async fn test_listen_for_code_reports_access_denied() {
+        let (flow, addr) = loopback_flow();
+
+        let browser = tokio::spawn(redirect(addr, "/callback?error=access_denied"));
+        let result = flow.listen_for_code(addr).await;
+
+        assert!(matches!(result, Err(Error::AccessDenied)));
+        browser.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_listen_for_code_times_out() {
+        let (flow, addr) = loopback_flow();
+        let flow = flow.with_listen_timeout(Duration::from_millis(50));
+
+        let result = flow.listen_for_code(addr).await;
+
+        assert!(matches!(result, Err(Error::Timeout(0))));
+    }
 }
@@ -5,14 +5,15 @@ mod device;
 mod pkce;
 
 pub use code::AuthorizationCodeFlow;
-pub use device::DeviceFlow;
+pub use device::{DeviceAuthorization, DeviceFlow};
 pub use pkce::PkceChallenge;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::provider::Provider;
-use crate::token::{ErrorResponse, Token, TokenResponse};
+use crate::token::{ErrorResponse, Token, TokenResponse, TokenStore};
 use reqwest::Client;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// Common `OAuth2` client configuration.
 #[derive(Debug, Clone)]
@@ -27,6 +28,9 @@ pub struct OAuthClient {
     pub provider: Provider,
     /// HTTP client.
     http_client: Client,
+    /// Per-account locks serializing [`ensure_valid`](Self::ensure_valid),
+    /// shared between clones.
+    refresh_locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
 }
 
 impl OAuthClient {
@@ -39,6 +43,7 @@ impl OAuthClient {
             redirect_uri: None,
             provider,
             http_client: Client::new(),
+            refresh_locks: Arc::default(),
         }
     }
 
@@ -96,6 +101,46 @@ impl OAuthClient {
         Ok(new_token)
     }
 
+    /// Returns a usable token for `account` from `store`.
+    ///
+    /// A stored token that is expired (or about to be) is refreshed and the
+    /// new token saved back to the store before it is returned.
+    ///
+    /// Concurrent calls for the same account on this client or its clones
+    /// run one at a time, so only the first refreshes and the rest load the
+    /// token it saved. Providers that rotate refresh tokens would otherwise
+    /// invalidate the loser of the race.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no token is stored, the store fails, or the
+    /// refresh fails.
+    pub async fn ensure_valid(
+        &self,
+        store: &(impl TokenStore + ?Sized),
+        account: &str,
+    ) -> Result<Token> {
+        let lock = Arc::clone(
+            self.refresh_locks
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(account.to_string())
+                .or_default(),
+        );
+        let _guard = lock.lock().await;
+
+        let token = store
+            .load(account)?
+            .ok_or_else(|| Error::NoStoredToken(account.to_string()))?;
+        if !token.is_expired() {
+            return Ok(token);
+        }
+
+        let refreshed = self.refresh_token(&token).await?;
+        store.save(account, &refreshed)?;
+        Ok(refreshed)
+    }
+
     /// Exchanges an authorization code for tokens.
     ///
     /// # Errors
@@ -151,7 +196,13 @@ impl OAuthClient {
     clippy::used_underscore_items,
     clippy::similar_names
 )]
-mod tests {
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use chrono::{Duration, Utc};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
     use super::*;
 
     #[test]
@@ -175,4 +226,127 @@ mod tests {
             Some("http://localhost:8080")
         );
     }
+
+    /// In-memory store for exercising `ensure_valid`.
+    #[derive(Debug, Default)]
+    struct MemoryStore(Mutex<HashMap<String, Token>>);
+
+    impl TokenStore for MemoryStore {
+        fn save(&self, account: &str, token: &Token) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(account.to_string(), token.clone());
+            Ok(())
+        }
+
+        fn load(&self, account: &str) -> Result<Option<Token>> {
+            Ok(self.0.lock().unwrap().get(account).cloned())
+        }
+
+        fn delete(&self, account: &str) -> Result<()> {
+            self.0.lock().unwrap().remove(account);
+            Ok(())
+        }
+    }
+
+    /// Client whose token endpoint issues new tokens, slowly enough for
+    /// concurrent callers to overlap, counting the refresh requests.
+    pub async fn refreshing_client() -> (OAuthClient, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&refreshes);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !String::from_utf8_lossy(&request).contains("grant_type=refresh_token") {
+                        let read = stream.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..read]);
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    let body = format!(
+                        r#"{{"access_token":"fresh{n}","token_type":"Bearer","expires_in":3600}}"#
+                    );
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        let provider = Provider::new(
+            "Test",
+            format!("http://{addr}/auth"),
+            format!("http://{addr}/token"),
+        )
+        .unwrap();
+        (OAuthClient::new("client", provider), refreshes)
+    }
+
+    pub fn expired_token() -> Token {
+        Token::new("stale", "Bearer")
+            .with_refresh_token("refresh")
+            .with_expires_at(Utc::now() - Duration::minutes(5))
+    }
+
+    #[tokio::test]
+    async fn test_ensure_valid_refreshes_and_saves_expired_token() {
+        let store = MemoryStore::default();
+        store.save("user", &expired_token()).unwrap();
+        let (client, _) = refreshing_client().await;
+
+        let token = client.ensure_valid(&store, "user").await.unwrap();
+
+        assert_eq!(token.access_token, "fresh1");
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+        let saved = store.load("user").unwrap().unwrap();
+        assert_eq!(saved.access_token, "fresh1");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_ensure_valid_refreshes_once() {
+        let store = Arc::new(MemoryStore::default());
+        store.save("user", &expired_token()).unwrap();
+        let (client, refreshes) = refreshing_client().await;
+
+        let tasks: Vec<_> = (0..2)
+            .map(|_| {
+                let client = client.clone();
+                let store = Arc::clone(&store);
+                tokio::spawn(async move { client.ensure_valid(&*store, "user").await })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().access_token, "fresh1");
+        }
+
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_valid_returns_unexpired_token() {
+        let store = MemoryStore::default();
+        store
+            .save("user", &Token::new("current", "Bearer"))
+            .unwrap();
+        let client = OAuthClient::new("client", Provider::google().unwrap());
+
+        let token = client.ensure_valid(&store, "user").await.unwrap();
+
+        assert_eq!(token.access_token, "current");
+    }
+
+    #[tokio::test]
+    async fn test_ensure_valid_without_stored_token() {
+        let client = OAuthClient::new("client", Provider::google().unwrap());
+        let result = client.ensure_valid(&MemoryStore::default(), "user").await;
+        assert!(matches!(result, Err(Error::NoStoredToken(_))));
+    }
 }
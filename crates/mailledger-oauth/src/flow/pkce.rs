@@ -5,9 +5,11 @@
 
 use base64::Engine;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
-use rand::Rng;
+use rand::RngCore;
 use sha2::{Digest, Sha256};
 
+use crate::error::{Error, Result};
+
 /// PKCE code challenge and verifier pair.
 #[derive(Debug, Clone)]
 pub struct PkceChallenge {
@@ -35,9 +37,36 @@ impl PkceChallenge {
         }
     }
 
+    /// Recreates the challenge for a known verifier, e.g. one saved while
+    /// the user was in the browser.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the verifier is not 43-128 unreserved characters
+    /// (RFC 7636 section 4.1).
+    pub fn from_verifier(verifier: impl Into<String>) -> Result<Self> {
+        let verifier = verifier.into();
+        let unreserved = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~');
+        if !(43..=128).contains(&verifier.len()) || !verifier.chars().all(unreserved) {
+            return Err(Error::InvalidConfig(
+                "PKCE verifier must be 43-128 unreserved characters".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            challenge: Self::compute_challenge(&verifier),
+            verifier,
+            method: "S256".to_string(),
+        })
+    }
+
     /// Generates a random code verifier (43-128 characters).
+    ///
+    /// 32 bytes from the thread-local CSPRNG encode to 43 base64url
+    /// characters.
     fn generate_verifier() -> String {
-        let random_bytes: Vec<u8> = (0..32).map(|_| rand::thread_rng().r#gen::<u8>()).collect();
+        let mut random_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut random_bytes);
         URL_SAFE_NO_PAD.encode(random_bytes)
     }
 
@@ -108,6 +137,24 @@ mod tests {
         assert_eq!(challenge, challenge2);
     }
 
+    #[test]
+    fn test_rfc7636_appendix_b_vector() {
+        let pkce =
+            PkceChallenge::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk").unwrap();
+        assert_eq!(
+            pkce.challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(pkce.method(), "S256");
+    }
+
+    #[test]
+    fn test_from_verifier_rejects_invalid() {
+        assert!(PkceChallenge::from_verifier("too-short").is_err());
+        assert!(PkceChallenge::from_verifier("a".repeat(129)).is_err());
+        assert!(PkceChallenge::from_verifier(format!("{}+", "a".repeat(42))).is_err());
+    }
+
     #[test]
     fn test_multiple_generations_unique() {
         let pkce1 = PkceChallenge::generate();
@@ -5,7 +5,8 @@
 //! ## Features
 //!
 //! - **Authorization flows**: Authorization Code Flow (with PKCE) and Device Flow
-//! - **Token management**: Automatic refresh, expiration checking
+//! - **Token management**: Automatic refresh, expiration checking, keyring (`keyring`
+//!   feature) and file storage
 //! - **Provider configurations**: Pre-configured for Gmail, Outlook, Yahoo
 //! - **SASL mechanisms**: OAUTHBEARER (RFC 7628) and XOAUTH2 (proprietary)
 //!
@@ -31,9 +32,9 @@
 //!     let auth_url = flow.authorization_url(None, Some("random_state"))?;
 //!     println!("Visit: {}", auth_url);
 //!
-//!     // After user authorizes, exchange code for token
-//!     let code = "authorization_code_from_redirect";
-//!     let token = flow.exchange_code(code, None).await?;
+//!     // Catch the browser redirect, then exchange the code for a token
+//!     let (code, _state) = flow.listen_for_code("127.0.0.1:8080".parse()?).await?;
+//!     let token = flow.exchange_code(&code, None).await?;
 //!
 //!     println!("Access token: {}", token.access_token);
 //!     Ok(())
@@ -107,6 +108,10 @@ pub mod sasl;
 pub mod token;
 
 pub use error::{Error, Result};
-pub use flow::{AuthorizationCodeFlow, DeviceFlow, OAuthClient, PkceChallenge};
+pub use flow::{
+    AuthorizationCodeFlow, DeviceAuthorization, DeviceFlow, OAuthClient, PkceChallenge,
+};
 pub use provider::Provider;
-pub use token::Token;
+#[cfg(feature = "keyring")]
+pub use token::KeyringTokenStore;
+pub use token::{FileTokenStore, SharedToken, Token, TokenStore};
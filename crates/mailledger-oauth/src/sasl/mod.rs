@@ -2,22 +2,38 @@
 //!
 //! Implements:
 //! - PLAIN (RFC 4616) - Basic username/password authentication
+//! - LOGIN (draft-murchison-sasl-login) - Legacy username/password
+//!   exchange; see [`SaslLogin`]
+//! - CRAM-MD5 (RFC 2195) - Legacy challenge-response password authentication
 //! - OAUTHBEARER (RFC 7628) - Standard `OAuth2` authentication
 //! - XOAUTH2 (Google/Microsoft proprietary) - Legacy `OAuth2` authentication
+//! - SCRAM-SHA-1 and SCRAM-SHA-256 (RFC 5802, RFC 7677) - Challenge-response
+//!   password authentication; see [`ScramClient`]
+
+mod login;
+mod scram;
+
+use std::fmt::Write;
 
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+
+pub use login::SaslLogin;
+pub use scram::{ScramClient, ScramError, ScramHash};
 
 /// Generates PLAIN initial response (RFC 4616).
 ///
-/// Format: `\0<username>\0<password>` (base64 encoded)
+/// Format: `<authzid>\0<username>\0<password>` (base64 encoded)
 ///
-/// The PLAIN mechanism sends credentials as: authorization-id (empty),
+/// The PLAIN mechanism sends credentials as: authorization-id,
 /// authentication-id (username), and password, separated by NUL bytes.
 ///
 /// # Arguments
 ///
-/// * `username` - Authentication username
+/// * `authzid` - Identity to act as, or `None` to act as `username`
+/// * `username` - Authentication identity
 /// * `password` - Authentication password
 ///
 /// # Example
@@ -25,17 +41,59 @@ use base64::engine::general_purpose::STANDARD;
 /// ```
 /// use mailledger_oauth::sasl::plain_response;
 ///
-/// let response = plain_response("user@example.com", "password123");
+/// let response = plain_response(None, "tim", "tanstaaftanstaaf");
+/// assert_eq!(response, "AHRpbQB0YW5zdGFhZnRhbnN0YWFm");
 /// // Can be used with IMAP AUTHENTICATE PLAIN or SMTP AUTH PLAIN
 /// ```
 #[must_use]
-pub fn plain_response(username: &str, password: &str) -> String {
-    // Format: \0username\0password
-    // The first NUL is for the authorization identity (empty = same as auth identity)
-    let auth_string = format!("\0{username}\0{password}");
+pub fn plain_response(authzid: Option<&str>, username: &str, password: &str) -> String {
+    // An empty authorization identity means "same as username"
+    let authzid = authzid.unwrap_or_default();
+    let auth_string = format!("{authzid}\0{username}\0{password}");
     STANDARD.encode(auth_string.as_bytes())
 }
 
+/// Generates the response to a CRAM-MD5 challenge (RFC 2195).
+///
+/// Format: `<username> <hex HMAC-MD5 of the challenge>` (base64 encoded)
+///
+/// `challenge` is the base64 text of the server's challenge. Returns `None`
+/// if it isn't valid base64.
+///
+/// CRAM-MD5 keeps the password off the wire, but MD5 is weak and servers
+/// must store the password in the clear, so prefer PLAIN over TLS.
+///
+/// # Example
+///
+/// ```
+/// use mailledger_oauth::sasl::cram_md5_response;
+///
+/// let response = cram_md5_response(
+///     "tim",
+///     "tanstaaftanstaaf",
+///     "PDE4OTYuNjk3MTcwOTUyQHBvc3RvZmZpY2UucmVzdG9uLm1jaS5uZXQ+",
+/// );
+/// assert_eq!(
+///     response.as_deref(),
+///     Some("dGltIGI5MTNhNjAyYzdlZGE3YTQ5NWI0ZTZlNzMzNGQzODkw")
+/// );
+/// ```
+#[must_use]
+pub fn cram_md5_response(username: &str, password: &str, challenge: &str) -> Option<String> {
+    let challenge = STANDARD.decode(challenge.trim()).ok()?;
+
+    let mut mac = <Hmac<Md5> as Mac>::new_from_slice(password.as_bytes())
+        .unwrap_or_else(|_| unreachable!("HMAC accepts keys of any length"));
+    mac.update(&challenge);
+    let digest = mac.finalize().into_bytes();
+
+    let mut response = format!("{username} ");
+    for byte in digest {
+        let _ = write!(response, "{byte:02x}");
+    }
+    Some(STANDARD.encode(response.as_bytes()))
+}
+
 /// Generates OAUTHBEARER initial response (RFC 7628).
 ///
 /// Format: `n,a=<user>,\x01auth=Bearer <token>\x01\x01`
@@ -181,7 +239,7 @@ mod tests {
 
     #[test]
     fn test_plain_response() {
-        let response = plain_response("user@example.com", "password123");
+        let response = plain_response(None, "user@example.com", "password123");
         let decoded = STANDARD.decode(&response).unwrap();
 
         // Format should be \0username\0password
@@ -194,7 +252,7 @@ mod tests {
 
     #[test]
     fn test_plain_response_format() {
-        let response = plain_response("test", "pass");
+        let response = plain_response(None, "test", "pass");
         let decoded = STANDARD.decode(&response).unwrap();
         let decoded_str = String::from_utf8(decoded).unwrap();
 
@@ -205,10 +263,37 @@ mod tests {
     #[test]
     fn test_plain_response_special_chars() {
         // Password with special characters
-        let response = plain_response("user", "pass@word!");
+        let response = plain_response(None, "user", "pass@word!");
         let decoded = STANDARD.decode(&response).unwrap();
         let decoded_str = String::from_utf8(decoded).unwrap();
 
         assert_eq!(decoded_str, "\0user\0pass@word!");
     }
+
+    #[test]
+    fn test_cram_md5_response_rfc2195_example() {
+        let challenge = "PDE4OTYuNjk3MTcwOTUyQHBvc3RvZmZpY2UucmVzdG9uLm1jaS5uZXQ+";
+        let response = cram_md5_response("tim", "tanstaaftanstaaf", challenge).unwrap();
+        assert_eq!(response, "dGltIGI5MTNhNjAyYzdlZGE3YTQ5NWI0ZTZlNzMzNGQzODkw");
+
+        let decoded = String::from_utf8(STANDARD.decode(&response).unwrap()).unwrap();
+        assert_eq!(decoded, "tim b913a602c7eda7a495b4e6e7334d3890");
+    }
+
+    #[test]
+    fn test_cram_md5_response_invalid_challenge() {
+        assert_eq!(cram_md5_response("tim", "secret", "not base64!"), None);
+    }
+
+    #[test]
+    fn test_plain_response_rfc4616_examples() {
+        assert_eq!(
+            plain_response(None, "tim", "tanstaaftanstaaf"),
+            "AHRpbQB0YW5zdGFhZnRhbnN0YWFm"
+        );
+        assert_eq!(
+            plain_response(Some("Ursel"), "Kurt", "xipj3plmq"),
+            "VXJzZWwAS3VydAB4aXBqM3BsbXE="
+        );
+    }
 }
@@ -0,0 +1,444 @@
+//! SCRAM client (RFC 5802) for SHA-1 and SHA-256 (RFC 7677).
+//!
+//! SCRAM proves knowledge of the password without sending it, and lets the
+//! client check that the server knows it too. Messages are exchanged as
+//! plain text; the protocol layer base64-encodes them.
+//!
+//! Channel binding isn't supported: the client always sends the `n,,`
+//! header, including when a `-PLUS` mechanism name is given.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac_array;
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// GS2 header for a client without channel binding.
+const GS2_HEADER: &str = "n,,";
+
+/// Largest iteration count accepted from a server.
+///
+/// The count comes from the server, so it is bounded: a hostile one could
+/// otherwise make the client compute billions of HMACs. Servers use a few
+/// thousand by default.
+const MAX_ITERATIONS: u32 = 1_000_000;
+
+/// Hash function of a SCRAM mechanism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScramHash {
+    /// SCRAM-SHA-1 (RFC 5802).
+    Sha1,
+    /// SCRAM-SHA-256 (RFC 7677).
+    Sha256,
+}
+
+impl ScramHash {
+    /// Returns the SASL mechanism name.
+    #[must_use]
+    pub const fn mechanism(self) -> &'static str {
+        match self {
+            Self::Sha1 => "SCRAM-SHA-1",
+            Self::Sha256 => "SCRAM-SHA-256",
+        }
+    }
+
+    /// Parses a SASL mechanism name, ignoring case and a `-PLUS` suffix.
+    #[must_use]
+    pub fn from_mechanism(mechanism: &str) -> Option<Self> {
+        let upper = mechanism.to_ascii_uppercase();
+        match upper.strip_suffix("-PLUS").unwrap_or(&upper) {
+            "SCRAM-SHA-1" => Some(Self::Sha1),
+            "SCRAM-SHA-256" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => mac::<Hmac<Sha1>>(key, data),
+            Self::Sha256 => mac::<Hmac<Sha256>>(key, data),
+        }
+    }
+
+    /// `Hi()` from RFC 5802: PBKDF2 with HMAC and a single output block.
+    fn salted_password(self, password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        match self {
+            Self::Sha1 => pbkdf2_hmac_array::<Sha1, 20>(password, salt, iterations).to_vec(),
+            Self::Sha256 => pbkdf2_hmac_array::<Sha256, 32>(password, salt, iterations).to_vec(),
+        }
+    }
+}
+
+fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key)
+        .unwrap_or_else(|_| unreachable!("HMAC accepts keys of any length"));
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn xor_in_place(target: &mut [u8], other: &[u8]) {
+    for (a, b) in target.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+/// Errors from a SCRAM exchange.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScramError {
+    /// A server message couldn't be parsed.
+    #[error("Invalid SCRAM server message: {0}")]
+    InvalidServerMessage(String),
+    /// The server nonce doesn't extend the client nonce.
+    #[error("SCRAM server nonce doesn't match the client nonce")]
+    NonceMismatch,
+    /// The server reported an error (`e=`).
+    #[error("SCRAM server error: {0}")]
+    Server(String),
+    /// The server signature is wrong, so the server doesn't know the password.
+    #[error("SCRAM server signature mismatch")]
+    InvalidServerSignature,
+    /// A message was handled before the one it depends on.
+    #[error("SCRAM messages out of order")]
+    OutOfOrder,
+}
+
+/// Client side of a SCRAM exchange.
+///
+/// ```
+/// use mailledger_oauth::sasl::{ScramClient, ScramHash};
+///
+/// let scram = ScramClient::new(ScramHash::Sha256, "user", "pencil");
+/// let first = scram.client_first();
+/// assert!(first.starts_with("n,,n=user,r="));
+/// // Send `first`, pass the server's reply to `handle_server_first`, send
+/// // `client_final()`, then check the reply with `verify_server_final`.
+/// ```
+#[derive(Clone)]
+pub struct ScramClient {
+    hash: ScramHash,
+    password: String,
+    client_nonce: String,
+    client_first_bare: String,
+    client_final: Option<String>,
+    server_signature: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for ScramClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScramClient")
+            .field("hash", &self.hash)
+            .field("password", &"<redacted>")
+            .field("client_nonce", &self.client_nonce)
+            .field("client_first_bare", &self.client_first_bare)
+            .field("client_final", &self.client_final)
+            .field("server_signature", &self.server_signature)
+            .finish()
+    }
+}
+
+impl ScramClient {
+    /// Creates a client with a random nonce.
+    ///
+    /// The password is used as given; `SASLprep` normalization is not
+    /// applied, which makes no difference for ASCII passwords.
+    #[must_use]
+    pub fn new(hash: ScramHash, username: &str, password: &str) -> Self {
+        let mut random_bytes = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut random_bytes);
+        Self::with_nonce(hash, username, password, &STANDARD.encode(random_bytes))
+    }
+
+    fn with_nonce(hash: ScramHash, username: &str, password: &str, nonce: &str) -> Self {
+        Self {
+            hash,
+            password: password.to_string(),
+            client_nonce: nonce.to_string(),
+            client_first_bare: format!("n={},r={nonce}", escape_username(username)),
+            client_final: None,
+            server_signature: None,
+        }
+    }
+
+    /// Returns the SASL mechanism name.
+    #[must_use]
+    pub const fn mechanism(&self) -> &'static str {
+        self.hash.mechanism()
+    }
+
+    /// Returns the client-first message.
+    #[must_use]
+    pub fn client_first(&self) -> String {
+        format!("{GS2_HEADER}{}", self.client_first_bare)
+    }
+
+    /// Handles the server-first message, preparing the client-final one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the message is malformed, reports an error, or carries a
+    /// nonce that doesn't extend ours.
+    pub fn handle_server_first(&mut self, server_first: &str) -> Result<(), ScramError> {
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for (key, value) in attributes(server_first)? {
+            match key {
+                "r" => nonce = Some(value),
+                "s" => salt = Some(value),
+                "i" => iterations = Some(value),
+                "e" => return Err(ScramError::Server(value.to_string())),
+                "m" => {
+                    return Err(ScramError::InvalidServerMessage(
+                        "unsupported mandatory extension".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let missing = |name: &str| ScramError::InvalidServerMessage(format!("missing {name}"));
+        let nonce = nonce.ok_or_else(|| missing("nonce"))?;
+        if nonce.len() <= self.client_nonce.len() || !nonce.starts_with(&self.client_nonce) {
+            return Err(ScramError::NonceMismatch);
+        }
+        let salt = STANDARD
+            .decode(salt.ok_or_else(|| missing("salt"))?)
+            .map_err(|e| ScramError::InvalidServerMessage(format!("salt: {e}")))?;
+        let iterations = iterations
+            .ok_or_else(|| missing("iteration count"))?
+            .parse::<u32>()
+            .ok()
+            .filter(|i| (1..=MAX_ITERATIONS).contains(i))
+            .ok_or_else(|| ScramError::InvalidServerMessage("iteration count".to_string()))?;
+
+        let without_proof = format!("c={},r={nonce}", STANDARD.encode(GS2_HEADER));
+        let auth_message = format!("{},{server_first},{without_proof}", self.client_first_bare);
+
+        let hash = self.hash;
+        let salted = hash.salted_password(self.password.as_bytes(), &salt, iterations);
+        let client_key = hash.hmac(&salted, b"Client Key");
+        let stored_key = hash.hash(&client_key);
+        let mut proof = hash.hmac(&stored_key, auth_message.as_bytes());
+        xor_in_place(&mut proof, &client_key);
+        let server_key = hash.hmac(&salted, b"Server Key");
+
+        self.server_signature = Some(hash.hmac(&server_key, auth_message.as_bytes()));
+        self.client_final = Some(format!("{without_proof},p={}", STANDARD.encode(proof)));
+        Ok(())
+    }
+
+    /// Returns the client-final message, once the server-first message has
+    /// been handled.
+    #[must_use]
+    pub fn client_final(&self) -> Option<&str> {
+        self.client_final.as_deref()
+    }
+
+    /// Checks the server-final message, proving the server knows the
+    /// password.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the server reports an error, its signature is wrong or
+    /// missing, or the server-first message hasn't been handled.
+    pub fn verify_server_final(&self, server_final: &str) -> Result<(), ScramError> {
+        let expected = self
+            .server_signature
+            .as_ref()
+            .ok_or(ScramError::OutOfOrder)?;
+
+        for (key, value) in attributes(server_final)? {
+            match key {
+                "e" => return Err(ScramError::Server(value.to_string())),
+                "v" => {
+                    let signature = STANDARD
+                        .decode(value)
+                        .map_err(|_| ScramError::InvalidServerSignature)?;
+                    return if &signature == expected {
+                        Ok(())
+                    } else {
+                        Err(ScramError::InvalidServerSignature)
+                    };
+                }
+                _ => {}
+            }
+        }
+        Err(ScramError::InvalidServerMessage(
+            "missing verifier".to_string(),
+        ))
+    }
+}
+
+/// Escapes `=` and `,` in a username (`saslname` in RFC 5802).
+fn escape_username(username: &str) -> String {
+    username.replace('=', "=3D").replace(',', "=2C")
+}
+
+/// Splits a server message into `key=value` attributes.
+fn attributes(message: &str) -> Result<Vec<(&str, &str)>, ScramError> {
+    message
+        .split(',')
+        .map(|attribute| {
+            attribute
+                .split_once('=')
+                .ok_or_else(|| ScramError::InvalidServerMessage(attribute.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc5802_sha1_exchange() {
+        let mut scram = ScramClient::with_nonce(
+            ScramHash::Sha1,
+            "user",
+            "pencil",
+            "fyko+d2lbbFgONRv9qkxdawL",
+        );
+
+        assert_eq!(scram.client_first(), "n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL");
+        scram
+            .handle_server_first(
+                "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+            )
+            .unwrap();
+        assert_eq!(
+            scram.client_final(),
+            Some(
+                "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,\
+                 p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="
+            )
+        );
+        scram
+            .verify_server_final("v=rmF9pqV8S7suAoZWja4dJRkFsKQ=")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rfc7677_sha256_exchange() {
+        let mut scram =
+            ScramClient::with_nonce(ScramHash::Sha256, "user", "pencil", "rOprNGfwEbeRWgbNEkqO");
+
+        assert_eq!(scram.client_first(), "n,,n=user,r=rOprNGfwEbeRWgbNEkqO");
+        scram
+            .handle_server_first(
+                "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+                 s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            )
+            .unwrap();
+        assert_eq!(
+            scram.client_final(),
+            Some(
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+                 p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+            )
+        );
+        scram
+            .verify_server_final("v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_wrong_server_signature_is_rejected() {
+        let mut scram = ScramClient::with_nonce(
+            ScramHash::Sha1,
+            "user",
+            "pencil",
+            "fyko+d2lbbFgONRv9qkxdawL",
+        );
+        assert_eq!(
+            scram.verify_server_final("v=rmF9pqV8S7suAoZWja4dJRkFsKQ="),
+            Err(ScramError::OutOfOrder)
+        );
+
+        scram
+            .handle_server_first(
+                "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+            )
+            .unwrap();
+        assert_eq!(
+            scram.verify_server_final("v=AAAAAAAAAAAAAAAAAAAAAAAAAAA="),
+            Err(ScramError::InvalidServerSignature)
+        );
+        assert_eq!(
+            scram.verify_server_final("e=invalid-proof"),
+            Err(ScramError::Server("invalid-proof".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_server_nonce_must_extend_client_nonce() {
+        let mut scram = ScramClient::with_nonce(ScramHash::Sha256, "user", "pencil", "abc");
+        assert_eq!(
+            scram.handle_server_first("r=xyz123,s=QSXCR+Q6sek8bf92,i=4096"),
+            Err(ScramError::NonceMismatch)
+        );
+        assert_eq!(
+            scram.handle_server_first("r=abc,s=QSXCR+Q6sek8bf92,i=4096"),
+            Err(ScramError::NonceMismatch)
+        );
+        assert!(matches!(
+            scram.handle_server_first("r=abc123,s=QSXCR+Q6sek8bf92,i=0"),
+            Err(ScramError::InvalidServerMessage(_))
+        ));
+        assert!(scram.client_final().is_none());
+    }
+
+    #[test]
+    fn test_excessive_iteration_count_is_rejected() {
+        let mut scram = ScramClient::with_nonce(ScramHash::Sha256, "user", "pencil", "abc");
+        assert!(matches!(
+            scram.handle_server_first("r=abc123,s=QSXCR+Q6sek8bf92,i=4294967295"),
+            Err(ScramError::InvalidServerMessage(_))
+        ));
+        assert!(scram.client_final().is_none());
+    }
+
+    #[test]
+    fn test_debug_redacts_password() {
+        let scram = ScramClient::with_nonce(ScramHash::Sha1, "user", "hunter2", "n");
+        let debug = format!("{scram:?}");
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_username_is_escaped() {
+        let scram = ScramClient::with_nonce(ScramHash::Sha1, "a=b,c", "pw", "n");
+        assert_eq!(scram.client_first(), "n,,n=a=3Db=2Cc,r=n");
+    }
+
+    #[test]
+    fn test_mechanism_names() {
+        assert_eq!(
+            ScramHash::from_mechanism("scram-sha-256"),
+            Some(ScramHash::Sha256)
+        );
+        assert_eq!(
+            ScramHash::from_mechanism("SCRAM-SHA-1-PLUS"),
+            Some(ScramHash::Sha1)
+        );
+        assert_eq!(ScramHash::from_mechanism("PLAIN"), None);
+        assert_eq!(ScramHash::Sha256.mechanism(), "SCRAM-SHA-256");
+
+        let scram = ScramClient::new(ScramHash::Sha256, "user", "pencil");
+        assert_eq!(scram.mechanism(), "SCRAM-SHA-256");
+        assert_ne!(
+            scram.client_first(),
+            ScramClient::new(ScramHash::Sha256, "user", "pencil").client_first()
+        );
+    }
+}
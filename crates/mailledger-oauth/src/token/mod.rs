@@ -1,5 +1,13 @@
 //! `OAuth2` token types and management.
 
+mod shared;
+mod store;
+
+pub use shared::SharedToken;
+#[cfg(feature = "keyring")]
+pub use store::KeyringTokenStore;
+pub use store::{FileTokenStore, TokenStore};
+
 use crate::error::{Error, Result};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,112 @@
+//! A token shared between connections that refreshes it.
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use super::{Token, TokenStore};
+use crate::error::Result;
+use crate::flow::OAuthClient;
+
+/// An account's token shared by every connection that uses it.
+///
+/// Clones share the same token. [`get`](Self::get) refreshes an expired
+/// token while holding the lock, so when IMAP and SMTP notice expiry at the
+/// same time only one refresh request reaches the provider and both get its
+/// result.
+#[derive(Debug, Clone)]
+pub struct SharedToken {
+    client: OAuthClient,
+    token: Arc<Mutex<Token>>,
+    store: Option<(Arc<dyn TokenStore>, String)>,
+}
+
+impl SharedToken {
+    /// Wraps a token that `client` can refresh.
+    #[must_use]
+    pub fn new(client: OAuthClient, token: Token) -> Self {
+        Self {
+            client,
+            token: Arc::new(Mutex::new(token)),
+            store: None,
+        }
+    }
+
+    /// Saves refreshed tokens to `store` under `account`.
+    #[must_use]
+    pub fn with_store(mut self, store: Arc<dyn TokenStore>, account: impl Into<String>) -> Self {
+        self.store = Some((store, account.into()));
+        self
+    }
+
+    /// Returns a valid token, refreshing it first if it has expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the refresh or saving the refreshed token fails.
+    pub async fn get(&self) -> Result<Token> {
+        let mut token = self.token.lock().await;
+        if token.is_expired() {
+            let refreshed = self.client.refresh_token(&token).await?;
+            if let Some((store, account)) = &self.store {
+                store.save(account, &refreshed)?;
+            }
+            *token = refreshed;
+        }
+        Ok(token.clone())
+    }
+
+    /// Returns a valid access token, refreshing it first if it has expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the refresh fails.
+    pub async fn access_token(&self) -> Result<String> {
+        Ok(self.get().await?.access_token)
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::flow::tests::{expired_token, refreshing_client};
+
+    #[tokio::test]
+    async fn test_concurrent_get_refreshes_once() {
+        let (client, refreshes) = refreshing_client().await;
+        let shared = SharedToken::new(client, expired_token());
+
+        let imap = tokio::spawn({
+            let shared = shared.clone();
+            async move { shared.access_token().await }
+        });
+        let smtp = tokio::spawn({
+            let shared = shared.clone();
+            async move { shared.access_token().await }
+        });
+
+        assert_eq!(imap.await.unwrap().unwrap(), "fresh1");
+        assert_eq!(smtp.await.unwrap().unwrap(), "fresh1");
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_keeps_valid_token() {
+        let (client, refreshes) = refreshing_client().await;
+        let shared = SharedToken::new(client, Token::new("current", "Bearer"));
+
+        assert_eq!(shared.access_token().await.unwrap(), "current");
+        assert_eq!(refreshes.load(Ordering::SeqCst), 0);
+    }
+}
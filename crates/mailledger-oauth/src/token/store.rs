@@ -0,0 +1,253 @@
+//! Persistent token storage.
+//!
+//! [`TokenStore`] abstracts where tokens live between runs:
+//! - [`KeyringTokenStore`]: the platform's secure credential storage
+//!   (requires the `keyring` feature)
+//! - [`FileTokenStore`]: JSON files readable only by the current user
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "keyring")]
+use keyring::Entry;
+
+use super::Token;
+use crate::error::Result;
+
+/// Default keyring service name.
+#[cfg(feature = "keyring")]
+const DEFAULT_SERVICE: &str = "mailledger";
+
+/// Storage for `OAuth2` tokens, keyed by account name.
+pub trait TokenStore: Send + Sync + std::fmt::Debug {
+    /// Saves the token for an account, replacing any previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token cannot be written.
+    fn save(&self, account: &str, token: &Token) -> Result<()>;
+
+    /// Loads the token for an account, or `None` if none is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage cannot be read or the stored token is
+    /// corrupt.
+    fn load(&self, account: &str) -> Result<Option<Token>>;
+
+    /// Deletes the token for an account. Deleting a missing token succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage cannot be modified.
+    fn delete(&self, account: &str) -> Result<()>;
+}
+
+/// Stores tokens as JSON in the system keyring.
+///
+/// Linux uses the Secret Service, macOS the Keychain and Windows the
+/// Credential Manager.
+#[cfg(feature = "keyring")]
+#[derive(Debug, Clone)]
+pub struct KeyringTokenStore {
+    service: String,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringTokenStore {
+    /// Creates a store using the `mailledger` keyring service.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_service(DEFAULT_SERVICE)
+    }
+
+    /// Creates a store using a custom keyring service name.
+    #[must_use]
+    pub fn with_service(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, account: &str) -> Result<Entry> {
+        Ok(Entry::new(
+            &self.service,
+            &format!("oauth_token:{account}"),
+        )?)
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl Default for KeyringTokenStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl TokenStore for KeyringTokenStore {
+    fn save(&self, account: &str, token: &Token) -> Result<()> {
+        self.entry(account)?
+            .set_password(&serde_json::to_string(token)?)?;
+        Ok(())
+    }
+
+    fn load(&self, account: &str) -> Result<Option<Token>> {
+        match self.entry(account)?.get_password() {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match self.entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Stores each account's token as a JSON file in a directory.
+///
+/// Files are created with mode 0600 on Unix so only the owner can read them.
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    dir: PathBuf,
+}
+
+impl FileTokenStore {
+    /// Creates a store in `dir`, which is created on first save.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the directory tokens are stored in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the file path for an account.
+    ///
+    /// Characters that are unsafe in file names are percent-escaped, so
+    /// distinct accounts never share a file.
+    fn path(&self, account: &str) -> PathBuf {
+        use std::fmt::Write as _;
+
+        let mut name = String::with_capacity(account.len() + 5);
+        for byte in account.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'@' | b'.' | b'_' | b'-') {
+                name.push(char::from(byte));
+            } else {
+                let _ = write!(name, "%{byte:02X}");
+            }
+        }
+        name.push_str(".json");
+        self.dir.join(name)
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn save(&self, account: &str, token: &Token) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_vec_pretty(token)?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let path = self.path(account);
+        let mut file = options.open(&path)?;
+        // The mode only applies to new files; tighten existing ones too.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(&json)?;
+        Ok(())
+    }
+
+    fn load(&self, account: &str) -> Result<Option<Token>> {
+        match fs::read(self.path(account)) {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match fs::remove_file(self.path(account)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> FileTokenStore {
+        let dir =
+            std::env::temp_dir().join(format!("mailledger-oauth-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        FileTokenStore::new(dir)
+    }
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let store = temp_store("round-trip");
+        let token = Token::new("access", "Bearer").with_refresh_token("refresh");
+
+        assert!(store.load("user@example.com").unwrap().is_none());
+        store.save("user@example.com", &token).unwrap();
+        let loaded = store.load("user@example.com").unwrap().unwrap();
+        assert_eq!(loaded.access_token, "access");
+        assert_eq!(loaded.refresh_token.as_deref(), Some("refresh"));
+
+        store.delete("user@example.com").unwrap();
+        store.delete("user@example.com").unwrap();
+        assert!(store.load("user@example.com").unwrap().is_none());
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_store_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let store = temp_store("mode");
+        store.save("user", &Token::new("access", "Bearer")).unwrap();
+        let mode = fs::metadata(store.path("user"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_escapes_account_names() {
+        let store = FileTokenStore::new("/tokens");
+        assert_eq!(
+            store.path("../a b@example.com"),
+            Path::new("/tokens/..%2Fa%20b@example.com.json")
+        );
+    }
+}
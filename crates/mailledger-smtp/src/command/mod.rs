@@ -1,6 +1,6 @@
 //! SMTP command builder.
 
-use crate::types::{Address, AuthMechanism};
+use crate::types::{Address, AuthMechanism, DsnNotify, DsnRet, xtext};
 
 /// SMTP command.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,14 +32,31 @@ pub enum Command {
         body: Option<String>,
         /// SIZE parameter
         size: Option<usize>,
+        /// RET parameter (DSN)
+        ret: Option<DsnRet>,
+        /// ENVID parameter (DSN), xtext-encoded on the wire
+        envid: Option<String>,
+        /// SMTPUTF8 parameter: the envelope or message contains UTF-8
+        smtputf8: bool,
     },
     /// RCPT TO - Add recipient
     RcptTo {
         /// Recipient address
         to: Address,
+        /// NOTIFY parameter (DSN)
+        notify: Option<Vec<DsnNotify>>,
+        /// ORCPT parameter (DSN): original recipient address
+        orcpt: Option<String>,
     },
     /// DATA - Begin message data
     Data,
+    /// BDAT - Send a message chunk (CHUNKING)
+    Bdat {
+        /// Number of bytes following the command
+        size: usize,
+        /// Whether this is the final chunk
+        last: bool,
+    },
     /// RSET - Reset transaction
     Rset,
     /// VRFY - Verify address
@@ -82,7 +99,14 @@ impl Command {
                     buf.extend_from_slice(resp.as_bytes());
                 }
             }
-            Self::MailFrom { from, body, size } => {
+            Self::MailFrom {
+                from,
+                body,
+                size,
+                ret,
+                envid,
+                smtputf8,
+            } => {
                 buf.extend_from_slice(b"MAIL FROM:<");
                 buf.extend_from_slice(from.as_str().as_bytes());
                 buf.push(b'>');
@@ -93,15 +117,41 @@ impl Command {
                 if let Some(msg_size) = size {
                     buf.extend_from_slice(format!(" SIZE={msg_size}").as_bytes());
                 }
+                if let Some(ret) = ret {
+                    buf.extend_from_slice(b" RET=");
+                    buf.extend_from_slice(ret.as_str().as_bytes());
+                }
+                if let Some(envid) = envid {
+                    buf.extend_from_slice(b" ENVID=");
+                    buf.extend_from_slice(xtext(envid).as_bytes());
+                }
+                if *smtputf8 {
+                    buf.extend_from_slice(b" SMTPUTF8");
+                }
             }
-            Self::RcptTo { to } => {
+            Self::RcptTo { to, notify, orcpt } => {
                 buf.extend_from_slice(b"RCPT TO:<");
                 buf.extend_from_slice(to.as_str().as_bytes());
                 buf.push(b'>');
+                if let Some(notify) = notify {
+                    let values: Vec<&str> = notify.iter().map(|n| n.as_str()).collect();
+                    buf.extend_from_slice(b" NOTIFY=");
+                    buf.extend_from_slice(values.join(",").as_bytes());
+                }
+                if let Some(orcpt) = orcpt {
+                    buf.extend_from_slice(b" ORCPT=rfc822;");
+                    buf.extend_from_slice(xtext(orcpt).as_bytes());
+                }
             }
             Self::Data => {
                 buf.extend_from_slice(b"DATA");
             }
+            Self::Bdat { size, last } => {
+                buf.extend_from_slice(format!("BDAT {size}").as_bytes());
+                if *last {
+                    buf.extend_from_slice(b" LAST");
+                }
+            }
             Self::Rset => {
                 buf.extend_from_slice(b"RSET");
             }
@@ -122,6 +172,85 @@ impl Command {
     }
 }
 
+/// Encodes a message for sending after DATA.
+///
+/// Line endings are normalized to CRLF, lines starting with `.` are
+/// byte-stuffed and the terminating `.` line is appended.
+#[must_use]
+pub fn encode_data(message: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(message.len() + 5);
+    for line in message.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.first() == Some(&b'.') {
+            buf.push(b'.');
+        }
+        buf.extend_from_slice(line);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(b".\r\n");
+    buf
+}
+
+/// Incremental form of [`encode_data`] for messages sent in chunks.
+///
+/// Line endings split across chunks are handled; a final line break is
+/// only added if the message does not already end with one.
+#[derive(Debug)]
+pub struct DataEncoder {
+    /// The next byte starts a line.
+    line_start: bool,
+    /// The previous chunk ended with a CR that may begin a CRLF.
+    pending_cr: bool,
+}
+
+impl Default for DataEncoder {
+    fn default() -> Self {
+        Self {
+            line_start: true,
+            pending_cr: false,
+        }
+    }
+}
+
+impl DataEncoder {
+    /// Encodes the next chunk of the message.
+    #[must_use]
+    pub fn encode(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(chunk.len() + chunk.len() / 64 + 2);
+        for &byte in chunk {
+            if std::mem::take(&mut self.pending_cr) && byte != b'\n' {
+                buf.push(b'\r');
+                self.line_start = false;
+            }
+            match byte {
+                b'\r' => self.pending_cr = true,
+                b'\n' => {
+                    buf.extend_from_slice(b"\r\n");
+                    self.line_start = true;
+                }
+                _ => {
+                    if self.line_start && byte == b'.' {
+                        buf.push(b'.');
+                    }
+                    buf.push(byte);
+                    self.line_start = false;
+                }
+            }
+        }
+        buf
+    }
+
+    /// Ends the message, returning the terminating `.` line.
+    #[must_use]
+    pub fn finish(self) -> Vec<u8> {
+        if self.line_start && !self.pending_cr {
+            b".\r\n".to_vec()
+        } else {
+            b"\r\n.\r\n".to_vec()
+        }
+    }
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -172,16 +301,35 @@ mod tests {
             from: Address::new("sender@example.com").unwrap(),
             body: None,
             size: None,
+            ret: None,
+            envid: None,
+            smtputf8: false,
         };
         assert_eq!(cmd.serialize(), b"MAIL FROM:<sender@example.com>\r\n");
     }
 
+    #[test]
+    fn test_mail_from_null_reverse_path() {
+        let cmd = Command::MailFrom {
+            from: Address::null(),
+            body: None,
+            size: None,
+            ret: None,
+            envid: None,
+            smtputf8: false,
+        };
+        assert_eq!(cmd.serialize(), b"MAIL FROM:<>\r\n");
+    }
+
     #[test]
     fn test_mail_from_with_params() {
         let cmd = Command::MailFrom {
             from: Address::new("sender@example.com").unwrap(),
             body: Some("8BITMIME".to_string()),
             size: Some(12345),
+            ret: None,
+            envid: None,
+            smtputf8: false,
         };
         assert_eq!(
             cmd.serialize(),
@@ -193,16 +341,77 @@ mod tests {
     fn test_rcpt_to_command() {
         let cmd = Command::RcptTo {
             to: Address::new("recipient@example.com").unwrap(),
+            notify: None,
+            orcpt: None,
         };
         assert_eq!(cmd.serialize(), b"RCPT TO:<recipient@example.com>\r\n");
     }
 
+    #[test]
+    fn test_mail_from_with_dsn() {
+        let cmd = Command::MailFrom {
+            from: Address::new("a@example.com").unwrap(),
+            body: None,
+            size: None,
+            ret: Some(DsnRet::Hdrs),
+            envid: Some("xyz".to_string()),
+            smtputf8: false,
+        };
+        assert_eq!(
+            cmd.serialize(),
+            b"MAIL FROM:<a@example.com> RET=HDRS ENVID=xyz\r\n"
+        );
+    }
+
+    #[test]
+    fn test_mail_from_smtputf8() {
+        let cmd = Command::MailFrom {
+            from: Address::new("用户@例え.jp").unwrap(),
+            body: None,
+            size: None,
+            ret: None,
+            envid: None,
+            smtputf8: true,
+        };
+        assert_eq!(
+            cmd.serialize(),
+            "MAIL FROM:<用户@例え.jp> SMTPUTF8\r\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_rcpt_to_with_dsn() {
+        let cmd = Command::RcptTo {
+            to: Address::new("b@example.com").unwrap(),
+            notify: Some(vec![DsnNotify::Success, DsnNotify::Failure]),
+            orcpt: Some("b+list@example.com".to_string()),
+        };
+        assert_eq!(
+            cmd.serialize(),
+            b"RCPT TO:<b@example.com> NOTIFY=SUCCESS,FAILURE ORCPT=rfc822;b+2Blist@example.com\r\n"
+        );
+    }
+
     #[test]
     fn test_data_command() {
         let cmd = Command::Data;
         assert_eq!(cmd.serialize(), b"DATA\r\n");
     }
 
+    #[test]
+    fn test_bdat_command() {
+        let cmd = Command::Bdat {
+            size: 4096,
+            last: false,
+        };
+        assert_eq!(cmd.serialize(), b"BDAT 4096\r\n");
+        let cmd = Command::Bdat {
+            size: 0,
+            last: true,
+        };
+        assert_eq!(cmd.serialize(), b"BDAT 0 LAST\r\n");
+    }
+
     #[test]
     fn test_rset_command() {
         let cmd = Command::Rset;
@@ -220,4 +429,25 @@ mod tests {
         let cmd = Command::Noop;
         assert_eq!(cmd.serialize(), b"NOOP\r\n");
     }
+
+    #[test]
+    fn data_encoder_handles_lines_split_across_chunks() {
+        let mut encoder = DataEncoder::default();
+        let mut out = Vec::new();
+        for chunk in [&b"Subject: hi\r"[..], b"\n\r\n.", b"body\nend\r"] {
+            out.extend(encoder.encode(chunk));
+        }
+        out.extend(encoder.finish());
+
+        assert_eq!(out, b"Subject: hi\r\n\r\n..body\r\nend\r\n.\r\n");
+    }
+
+    #[test]
+    fn data_encoder_keeps_bare_cr_and_terminates_once() {
+        let mut encoder = DataEncoder::default();
+        let mut out = encoder.encode(b"a\r.b\r\n");
+        out.extend(encoder.finish());
+
+        assert_eq!(out, b"a\r.b\r\n.\r\n");
+    }
 }
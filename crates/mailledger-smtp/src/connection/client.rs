@@ -1,13 +1,15 @@
 //! Type-state SMTP client.
 
 use super::{ServerInfo, SmtpStream};
-use crate::command::Command;
+use crate::command::{Command, DataEncoder, encode_data};
 use crate::error::{Error, Result};
 use crate::parser::{is_last_reply_line, parse_reply};
-use crate::types::{Address, AuthMechanism, Extension, Reply, ReplyCode};
-use base64::Engine;
+use crate::types::{Address, AuthMechanism, DsnNotify, DsnRet, Extension, Reply, ReplyCode};
+use futures_util::{Stream, StreamExt};
+use mailledger_oauth::sasl::{SaslLogin, cram_md5_response, plain_response};
 use std::collections::HashSet;
 use std::marker::PhantomData;
+use std::pin::pin;
 
 /// Type-state marker for connected state.
 #[derive(Debug)]
@@ -34,9 +36,22 @@ pub struct Data;
 pub struct Client<State> {
     stream: SmtpStream,
     server_info: ServerInfo,
+    envelope: EnvelopeParams,
     _state: PhantomData<State>,
 }
 
+/// Envelope state carried between MAIL FROM and RCPT TO.
+#[derive(Debug, Default)]
+struct EnvelopeParams {
+    /// The current transaction was started with SMTPUTF8.
+    smtputf8: bool,
+    /// DSN parameters queued for the next MAIL FROM or RCPT TO.
+    ret: Option<DsnRet>,
+    envid: Option<String>,
+    notify: Option<Vec<DsnNotify>>,
+    orcpt: Option<String>,
+}
+
 /// Connection trait for all states.
 pub trait SmtpConnection {
     /// Returns the server information.
@@ -78,6 +93,7 @@ impl Client<Connected> {
                 hostname,
                 extensions: HashSet::new(),
             },
+            envelope: EnvelopeParams::default(),
             _state: PhantomData,
         })
     }
@@ -94,7 +110,7 @@ impl Client<Connected> {
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         // Parse extensions from EHLO response (skip first line which is greeting)
@@ -121,7 +137,7 @@ impl Client<Connected> {
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         // Upgrade stream to TLS
@@ -134,7 +150,7 @@ impl Client<Connected> {
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         // Re-parse extensions
@@ -152,101 +168,297 @@ impl Client<Connected> {
     /// # Errors
     ///
     /// Returns an error if authentication fails.
-    pub async fn auth_plain(
-        mut self,
+    pub async fn auth_plain(self, username: &str, password: &str) -> Result<Client<Authenticated>> {
+        let encoded = plain_response(None, username, password);
+        self.auth(AuthMechanism::Plain, encoded).await
+    }
+
+    /// Authenticates using the legacy LOGIN mechanism, answering the
+    /// server's username and password prompts in turn.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if authentication fails.
+    pub async fn auth_login(self, username: &str, password: &str) -> Result<Client<Authenticated>> {
+        let mut login = SaslLogin::new(username, password);
+        self.auth_exchange(AuthMechanism::Login, |challenge| login.respond(challenge))
+            .await
+    }
+
+    /// Authenticates using CRAM-MD5 (RFC 2195), answering the server's
+    /// challenge with an HMAC-MD5 digest keyed by the password.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if authentication fails or the challenge is not
+    /// valid base64.
+    pub async fn auth_cram_md5(
+        self,
         username: &str,
         password: &str,
     ) -> Result<Client<Authenticated>> {
-        // Build PLAIN response: \0username\0password
-        let credentials = format!("\0{username}\0{password}");
-        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials.as_bytes());
+        let mut answered = false;
+        self.auth_exchange(AuthMechanism::CramMd5, |challenge| {
+            // There is exactly one challenge
+            if std::mem::replace(&mut answered, true) {
+                return None;
+            }
+            cram_md5_response(username, password, challenge)
+        })
+        .await
+    }
+
+    /// Runs an AUTH exchange without an initial response, answering each
+    /// 334 challenge with `respond` until the server accepts or rejects it.
+    ///
+    /// If `respond` has no answer the exchange is cancelled with `*`.
+    async fn auth_exchange(
+        mut self,
+        mechanism: AuthMechanism,
+        mut respond: impl FnMut(&str) -> Option<String>,
+    ) -> Result<Client<Authenticated>> {
+        let cmd = Command::Auth {
+            mechanism,
+            initial_response: None,
+        };
+        let mut reply = self.send_command(cmd).await?;
+
+        while reply.code == ReplyCode::AUTH_CONTINUE {
+            let challenge = reply.message.first().map_or("", String::as_str);
+            let Some(response) = respond(challenge) else {
+                self.stream.write_all(b"*\r\n").await?;
+                self.receive_reply().await?;
+                return Err(Error::Protocol(format!(
+                    "unexpected {} challenge: {challenge}",
+                    mechanism.as_str()
+                )));
+            };
+            self.stream
+                .write_all(format!("{response}\r\n").as_bytes())
+                .await?;
+            reply = self.receive_reply().await?;
+        }
+
+        if !reply.is_success() {
+            return Err(Error::from_reply(&reply));
+        }
 
+        Ok(Client {
+            stream: self.stream,
+            server_info: self.server_info,
+            envelope: self.envelope,
+            _state: PhantomData,
+        })
+    }
+
+    /// Authenticates with a mechanism that completes with its initial
+    /// response, such as `XOAUTH2` or `OAUTHBEARER`.
+    ///
+    /// `initial_response` must already be base64-encoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if authentication fails.
+    pub async fn auth(
+        mut self,
+        mechanism: AuthMechanism,
+        initial_response: String,
+    ) -> Result<Client<Authenticated>> {
         let cmd = Command::Auth {
-            mechanism: AuthMechanism::Plain,
-            initial_response: Some(encoded),
+            mechanism,
+            initial_response: Some(initial_response),
         };
 
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
 
+    /// Sets the DSN RET parameter for the next MAIL FROM.
+    #[must_use]
+    pub const fn dsn_ret(mut self, ret: DsnRet) -> Self {
+        self.envelope.ret = Some(ret);
+        self
+    }
+
+    /// Sets the DSN ENVID parameter for the next MAIL FROM.
+    #[must_use]
+    pub fn dsn_envid(mut self, envid: impl Into<String>) -> Self {
+        self.envelope.envid = Some(envid.into());
+        self
+    }
+
+    /// Sends RSET to clear any transaction state before reusing the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RSET command fails.
+    pub async fn reset(mut self) -> Result<Self> {
+        let cmd = Command::Rset;
+        let reply = self.send_command(cmd).await?;
+
+        if !reply.is_success() {
+            return Err(Error::from_reply(&reply));
+        }
+
+        Ok(self)
+    }
+
     /// Starts a mail transaction without authentication (if server allows).
     ///
+    /// A non-ASCII sender starts an SMTPUTF8 transaction if the server
+    /// supports it; otherwise its domain is punycode-encoded. Use
+    /// [`send_envelope`](Self::send_envelope) when only recipients need UTF-8.
+    ///
     /// # Errors
     ///
     /// Returns an error if the MAIL FROM command fails.
+    /// Returns [`Error::NotSupported`] if DSN parameters were set but the
+    /// server does not advertise DSN, or if the sender's local part is not
+    /// ASCII and the server lacks SMTPUTF8.
     pub async fn mail_from(mut self, from: Address) -> Result<Client<MailTransaction>> {
-        let cmd = Command::MailFrom {
-            from,
-            body: None,
-            size: None,
-        };
+        let cmd = self.mail_command(from, false)?;
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
+
+    /// Sends MAIL FROM, RCPT TO for every recipient and DATA in one batch.
+    ///
+    /// With PIPELINING the commands go out in a single write and the replies
+    /// are read back in order; otherwise they are sent one at a time. A
+    /// rejected recipient does not abort the transaction: each recipient is
+    /// returned with its reply, and the message goes to those accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if MAIL FROM or DATA fails, or if no recipient was
+    /// accepted.
+    pub async fn send_envelope(
+        self,
+        from: Address,
+        recipients: &[Address],
+    ) -> Result<(Client<Data>, Vec<(Address, Reply)>)> {
+        self.envelope(from, recipients).await
+    }
 }
 
 impl Client<Authenticated> {
+    /// Sets the DSN RET parameter for the next MAIL FROM.
+    #[must_use]
+    pub const fn dsn_ret(mut self, ret: DsnRet) -> Self {
+        self.envelope.ret = Some(ret);
+        self
+    }
+
+    /// Sets the DSN ENVID parameter for the next MAIL FROM.
+    #[must_use]
+    pub fn dsn_envid(mut self, envid: impl Into<String>) -> Self {
+        self.envelope.envid = Some(envid.into());
+        self
+    }
+
     /// Starts a mail transaction.
     ///
+    /// A non-ASCII sender starts an SMTPUTF8 transaction if the server
+    /// supports it; otherwise its domain is punycode-encoded. Use
+    /// [`send_envelope`](Self::send_envelope) when only recipients need UTF-8.
+    ///
     /// # Errors
     ///
     /// Returns an error if the MAIL FROM command fails.
+    /// Returns [`Error::NotSupported`] if DSN parameters were set but the
+    /// server does not advertise DSN, or if the sender's local part is not
+    /// ASCII and the server lacks SMTPUTF8.
     pub async fn mail_from(mut self, from: Address) -> Result<Client<MailTransaction>> {
-        let cmd = Command::MailFrom {
-            from,
-            body: None,
-            size: None,
-        };
+        let cmd = self.mail_command(from, false)?;
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
+
+    /// Sends MAIL FROM, RCPT TO for every recipient and DATA in one batch.
+    ///
+    /// With PIPELINING the commands go out in a single write and the replies
+    /// are read back in order; otherwise they are sent one at a time. A
+    /// rejected recipient does not abort the transaction: each recipient is
+    /// returned with its reply, and the message goes to those accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if MAIL FROM or DATA fails, or if no recipient was
+    /// accepted.
+    pub async fn send_envelope(
+        self,
+        from: Address,
+        recipients: &[Address],
+    ) -> Result<(Client<Data>, Vec<(Address, Reply)>)> {
+        self.envelope(from, recipients).await
+    }
 }
 
 impl Client<MailTransaction> {
+    /// Sets the DSN NOTIFY parameter for the next RCPT TO.
+    #[must_use]
+    pub fn dsn_notify(mut self, notify: &[DsnNotify]) -> Self {
+        self.envelope.notify = Some(notify.to_vec());
+        self
+    }
+
+    /// Sets the DSN ORCPT parameter (original recipient) for the next RCPT TO.
+    #[must_use]
+    pub fn dsn_orcpt(mut self, orcpt: impl Into<String>) -> Self {
+        self.envelope.orcpt = Some(orcpt.into());
+        self
+    }
+
     /// Adds a recipient to the transaction.
     ///
     /// # Errors
     ///
-    /// Returns an error if the RCPT TO command fails.
+    /// Returns an error if the RCPT TO command fails, or
+    /// [`Error::NotSupported`] if DSN parameters were set but the server
+    /// does not advertise DSN, or if the recipient's local part is not ASCII
+    /// outside an SMTPUTF8 transaction.
     pub async fn rcpt_to(mut self, to: Address) -> Result<Client<RecipientAdded>> {
-        let cmd = Command::RcptTo { to };
+        let cmd = self.rcpt_command(to)?;
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
@@ -261,29 +473,47 @@ impl Client<MailTransaction> {
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
 }
 
 impl Client<RecipientAdded> {
+    /// Sets the DSN NOTIFY parameter for the next RCPT TO.
+    #[must_use]
+    pub fn dsn_notify(mut self, notify: &[DsnNotify]) -> Self {
+        self.envelope.notify = Some(notify.to_vec());
+        self
+    }
+
+    /// Sets the DSN ORCPT parameter (original recipient) for the next RCPT TO.
+    #[must_use]
+    pub fn dsn_orcpt(mut self, orcpt: impl Into<String>) -> Self {
+        self.envelope.orcpt = Some(orcpt.into());
+        self
+    }
+
     /// Adds another recipient to the transaction.
     ///
     /// # Errors
     ///
-    /// Returns an error if the RCPT TO command fails.
+    /// Returns an error if the RCPT TO command fails, or
+    /// [`Error::NotSupported`] if DSN parameters were set but the server
+    /// does not advertise DSN, or if the recipient's local part is not ASCII
+    /// outside an SMTPUTF8 transaction.
     pub async fn rcpt_to(mut self, to: Address) -> Result<Self> {
-        let cmd = Command::RcptTo { to };
+        let cmd = self.rcpt_command(to)?;
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(self)
@@ -299,12 +529,69 @@ impl Client<RecipientAdded> {
         let reply = self.send_command(cmd).await?;
 
         if reply.code != ReplyCode::START_DATA {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
+        }
+
+        Ok(Client {
+            stream: self.stream,
+            server_info: self.server_info,
+            envelope: self.envelope,
+            _state: PhantomData,
+        })
+    }
+
+    /// Sends the message with BDAT chunks (RFC 3030) instead of DATA.
+    ///
+    /// Each chunk is sent as `BDAT <size>` followed by its raw bytes, so the
+    /// message is neither dot-stuffed nor rewritten to CRLF. Empty chunks are
+    /// skipped and `BDAT 0 LAST` completes the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotSupported`] if the server does not advertise
+    /// CHUNKING, [`Error::Io`] if the stream yields an error, or an error if
+    /// the server rejects any chunk.
+    pub async fn bdat(
+        mut self,
+        chunks: impl Stream<Item = std::io::Result<Vec<u8>>>,
+    ) -> Result<Client<Connected>> {
+        if !self.server_info.supports(&Extension::Chunking) {
+            return Err(Error::NotSupported("CHUNKING".into()));
+        }
+
+        let mut chunks = pin!(chunks);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            if chunk.is_empty() {
+                continue;
+            }
+            let cmd = Command::Bdat {
+                size: chunk.len(),
+                last: false,
+            };
+            self.stream.write_all(&cmd.serialize()).await?;
+            self.stream.write_all(&chunk).await?;
+            let reply = self.receive_reply().await?;
+
+            if !reply.is_success() {
+                return Err(Error::from_reply(&reply));
+            }
+        }
+
+        let cmd = Command::Bdat {
+            size: 0,
+            last: true,
+        };
+        let reply = self.send_command(cmd).await?;
+
+        if !reply.is_success() {
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
@@ -319,12 +606,13 @@ impl Client<RecipientAdded> {
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
@@ -340,37 +628,45 @@ impl Client<Data> {
     ///
     /// Returns an error if sending the message fails or server rejects it.
     pub async fn send_message(mut self, message: &[u8]) -> Result<Client<Connected>> {
-        // Send message with proper line ending normalization
-        // and byte-stuffing (leading dots)
-        for line in message.split(|&b| b == b'\n') {
-            let line = if !line.is_empty() && line[line.len() - 1] == b'\r' {
-                &line[..line.len() - 1]
-            } else {
-                line
-            };
-
-            // Byte-stuff lines starting with '.'
-            if !line.is_empty() && line[0] == b'.' {
-                self.stream.write_all(b".").await?;
-            }
+        self.stream.write_all(&encode_data(message)).await?;
+        self.finish_data().await
+    }
 
-            self.stream.write_all(line).await?;
-            self.stream.write_all(b"\r\n").await?;
+    /// Sends the message content from a stream of chunks and completes the
+    /// transaction.
+    ///
+    /// Chunks are dot-stuffed and normalized to CRLF as they arrive, so the
+    /// whole message is never held in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the stream yields an error, which leaves the
+    /// connection unusable, or an error if the server rejects the message.
+    pub async fn send_message_stream(
+        mut self,
+        chunks: impl Stream<Item = std::io::Result<Vec<u8>>>,
+    ) -> Result<Client<Connected>> {
+        let mut encoder = DataEncoder::default();
+        let mut chunks = pin!(chunks);
+        while let Some(chunk) = chunks.next().await {
+            self.stream.write_all(&encoder.encode(&chunk?)).await?;
         }
+        self.stream.write_all(&encoder.finish()).await?;
+        self.finish_data().await
+    }
 
-        // Send terminating sequence
-        self.stream.write_all(b".\r\n").await?;
-
-        // Read server response
-        let reply = Self::read_reply(&mut self.stream).await?;
+    /// Reads the reply to the end of the message data.
+    async fn finish_data(mut self) -> Result<Client<Connected>> {
+        let reply = self.receive_reply().await?;
 
         if !reply.is_success() {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(Client {
             stream: self.stream,
             server_info: self.server_info,
+            envelope: self.envelope,
             _state: PhantomData,
         })
     }
@@ -378,10 +674,134 @@ impl Client<Data> {
 
 // Common implementation for all states
 impl<S> Client<S> {
+    /// Builds MAIL FROM, consuming any queued RET/ENVID parameters.
+    ///
+    /// The transaction uses SMTPUTF8 when the server supports it and the
+    /// sender or `utf8_recipients` need it; otherwise the sender's domain is
+    /// punycode-encoded.
+    fn mail_command(&mut self, from: Address, utf8_recipients: bool) -> Result<Command> {
+        let ret = self.envelope.ret.take();
+        let envid = self.envelope.envid.take();
+        if (ret.is_some() || envid.is_some()) && !self.server_info.supports(&Extension::Dsn) {
+            return Err(Error::NotSupported("DSN".into()));
+        }
+        let smtputf8 = self.server_info.supports(&Extension::SmtpUtf8)
+            && (utf8_recipients || !from.is_ascii());
+        let from = if smtputf8 { from } else { from.into_ascii()? };
+        self.envelope.smtputf8 = smtputf8;
+        Ok(Command::MailFrom {
+            from,
+            body: None,
+            size: None,
+            ret,
+            envid,
+            smtputf8,
+        })
+    }
+
+    /// Shared implementation of `send_envelope`.
+    async fn envelope(
+        mut self,
+        from: Address,
+        recipients: &[Address],
+    ) -> Result<(Client<Data>, Vec<(Address, Reply)>)> {
+        if recipients.is_empty() {
+            return Err(Error::InvalidState("no recipients".into()));
+        }
+
+        let utf8_recipients = recipients.iter().any(|to| !to.is_ascii());
+        let mail = self.mail_command(from, utf8_recipients)?;
+        let mut rcpts = Vec::with_capacity(recipients.len());
+        for to in recipients {
+            rcpts.push(self.rcpt_command(to.clone())?);
+        }
+
+        let pipelined = self.server_info.supports(&Extension::Pipelining);
+        if pipelined {
+            let mut batch = mail.serialize();
+            for cmd in &rcpts {
+                batch.extend_from_slice(&cmd.serialize());
+            }
+            batch.extend_from_slice(&Command::Data.serialize());
+            self.stream.write_all(&batch).await?;
+        }
+
+        let reply = self.batched_reply(mail, pipelined).await?;
+        if !reply.is_success() {
+            return Err(Error::from_reply(&reply));
+        }
+
+        let mut results = Vec::with_capacity(recipients.len());
+        for (to, cmd) in recipients.iter().zip(rcpts) {
+            let reply = self.batched_reply(cmd, pipelined).await?;
+            results.push((to.clone(), reply));
+        }
+        if !results.iter().any(|(_, reply)| reply.is_success()) {
+            // The server may still have accepted the pipelined DATA; end the
+            // empty message so the session stays usable.
+            if pipelined && self.receive_reply().await?.code == ReplyCode::START_DATA {
+                self.stream.write_all(b".\r\n").await?;
+                self.receive_reply().await?;
+            }
+            let (_, reply) = &results[0];
+            return Err(Error::from_reply(reply));
+        }
+
+        let reply = self.batched_reply(Command::Data, pipelined).await?;
+        if reply.code != ReplyCode::START_DATA {
+            return Err(Error::from_reply(&reply));
+        }
+
+        Ok((
+            Client {
+                stream: self.stream,
+                server_info: self.server_info,
+                envelope: self.envelope,
+                _state: PhantomData,
+            },
+            results,
+        ))
+    }
+
+    /// Reads the reply to an already-written command, or sends it first
+    /// when not pipelining.
+    async fn batched_reply(&mut self, cmd: Command, pipelined: bool) -> Result<Reply> {
+        if pipelined {
+            self.receive_reply().await
+        } else {
+            self.send_command(cmd).await
+        }
+    }
+
+    /// Builds RCPT TO, consuming any queued NOTIFY/ORCPT parameters.
+    fn rcpt_command(&mut self, to: Address) -> Result<Command> {
+        let notify = self.envelope.notify.take();
+        let orcpt = self.envelope.orcpt.take();
+        if (notify.is_some() || orcpt.is_some()) && !self.server_info.supports(&Extension::Dsn) {
+            return Err(Error::NotSupported("DSN".into()));
+        }
+        let to = if self.envelope.smtputf8 {
+            to
+        } else {
+            to.into_ascii()?
+        };
+        Ok(Command::RcptTo { to, notify, orcpt })
+    }
+
     async fn send_command(&mut self, cmd: Command) -> Result<Reply> {
         let data = cmd.serialize();
         self.stream.write_all(&data).await?;
-        Self::read_reply(&mut self.stream).await
+        self.receive_reply().await
+    }
+
+    /// Reads a reply, parsing enhanced status codes if the server sends them.
+    async fn receive_reply(&mut self) -> Result<Reply> {
+        let reply = Self::read_reply(&mut self.stream).await?;
+        if self.server_info.supports(&Extension::EnhancedStatusCodes) {
+            Ok(reply.with_enhanced_code())
+        } else {
+            Ok(reply)
+        }
     }
 
     async fn read_reply(stream: &mut SmtpStream) -> Result<Reply> {
@@ -413,9 +833,378 @@ impl<S> Client<S> {
         let reply = self.send_command(cmd).await?;
 
         if !reply.is_success() && reply.code != ReplyCode::CLOSING {
-            return Err(Error::smtp_error(reply.code.as_u16(), reply.message_text()));
+            return Err(Error::from_reply(&reply));
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+    use crate::connection::connect;
+    use futures_util::stream;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Starts a server advertising `extensions` that accepts every command
+    /// and returns everything the client sent.
+    async fn serve(extensions: &'static str) -> (u16, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            let mut received = Vec::new();
+            socket.write_all(b"220 test ESMTP\r\n").await.unwrap();
+            loop {
+                let mut line = String::new();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                received.extend_from_slice(line.as_bytes());
+                let reply = if line.starts_with("EHLO") {
+                    format!("250-test\r\n250 {extensions}\r\n")
+                } else if let Some(size) = line.strip_prefix("BDAT ") {
+                    let size: usize = size.split_whitespace().next().unwrap().parse().unwrap();
+                    let mut chunk = vec![0; size];
+                    socket.read_exact(&mut chunk).await.unwrap();
+                    received.extend_from_slice(&chunk);
+                    "250 OK\r\n".to_string()
+                } else if line.starts_with("QUIT") {
+                    "221 bye\r\n".to_string()
+                } else {
+                    "250 OK\r\n".to_string()
+                };
+                socket.write_all(reply.as_bytes()).await.unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    /// Starts a PIPELINING server that reads `commands` envelope lines before
+    /// answering all of them with `replies` in a single write.
+    async fn serve_pipelined(commands: usize, replies: &'static str) -> (u16, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            let mut received = Vec::new();
+            socket.write_all(b"220 test ESMTP\r\n").await.unwrap();
+            let mut line = String::new();
+            socket.read_line(&mut line).await.unwrap();
+            socket
+                .write_all(b"250-test\r\n250 PIPELINING\r\n")
+                .await
+                .unwrap();
+            for _ in 0..commands {
+                line.clear();
+                socket.read_line(&mut line).await.unwrap();
+                received.extend_from_slice(line.as_bytes());
+            }
+            socket.write_all(replies.as_bytes()).await.unwrap();
+            loop {
+                line.clear();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                received.extend_from_slice(line.as_bytes());
+                if line == ".\r\n" {
+                    socket.write_all(b"250 queued\r\n").await.unwrap();
+                } else if line.starts_with("QUIT") {
+                    socket.write_all(b"221 bye\r\n").await.unwrap();
+                }
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    /// Starts a server that answers each line the client sends with the
+    /// next of `replies`, and returns everything the client sent.
+    async fn serve_script(replies: &'static [&'static str]) -> (u16, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            let mut received = Vec::new();
+            socket.write_all(b"220 test ESMTP\r\n").await.unwrap();
+            for reply in replies {
+                let mut line = String::new();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                received.extend_from_slice(line.as_bytes());
+                socket.write_all(reply.as_bytes()).await.unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    async fn connected(port: u16) -> Client<Connected> {
+        let stream = connect("127.0.0.1", port).await.unwrap();
+        Client::from_stream(stream)
+            .await
+            .unwrap()
+            .ehlo("client")
+            .await
+            .unwrap()
+    }
+
+    fn addresses(addrs: &[&str]) -> Vec<Address> {
+        addrs.iter().map(|a| Address::new(*a).unwrap()).collect()
+    }
+
+    async fn recipient_added(port: u16) -> Client<RecipientAdded> {
+        connected(port)
+            .await
+            .mail_from(Address::new("a@example.com").unwrap())
+            .await
+            .unwrap()
+            .rcpt_to(Address::new("b@example.com").unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn bdat_sends_chunks_without_dot_stuffing() {
+        let (port, server) = serve("CHUNKING").await;
+        let chunks = stream::iter(vec![
+            Ok(b"Subject: hi\r\n".to_vec()),
+            Ok(b"\r\n.body\r\n".to_vec()),
+        ]);
+
+        let client = recipient_added(port).await.bdat(chunks).await.unwrap();
+        client.quit().await.unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            String::from_utf8(received).unwrap(),
+            "EHLO client\r\n\
+             MAIL FROM:<a@example.com>\r\n\
+             RCPT TO:<b@example.com>\r\n\
+             BDAT 13\r\nSubject: hi\r\n\
+             BDAT 9\r\n\r\n.body\r\n\
+             BDAT 0 LAST\r\n\
+             QUIT\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn bdat_skips_empty_chunks_before_last() {
+        let (port, server) = serve("CHUNKING").await;
+        let chunks = stream::iter(vec![Ok(Vec::new())]);
+
+        let client = recipient_added(port).await.bdat(chunks).await.unwrap();
+        client.quit().await.unwrap();
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.ends_with("RCPT TO:<b@example.com>\r\nBDAT 0 LAST\r\nQUIT\r\n"));
+    }
+
+    #[tokio::test]
+    async fn send_envelope_pipelines_and_reports_rejected_recipients() {
+        let (port, server) = serve_pipelined(
+            5,
+            "250 OK\r\n250 OK\r\n550 No such user\r\n250 OK\r\n354 Go ahead\r\n",
+        )
+        .await;
+        let recipients = addresses(&["b@example.com", "c@example.com", "d@example.com"]);
+
+        let (client, results) = connected(port)
+            .await
+            .send_envelope(Address::new("a@example.com").unwrap(), &recipients)
+            .await
+            .unwrap();
+        let codes: Vec<u16> = results
+            .iter()
+            .map(|(_, reply)| reply.code.as_u16())
+            .collect();
+        assert_eq!(codes, [250, 550, 250]);
+        assert_eq!(results[1].0.as_str(), "c@example.com");
+
+        let client = client.send_message(b"hi").await.unwrap();
+        client.quit().await.unwrap();
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.starts_with(
+            "MAIL FROM:<a@example.com>\r\n\
+             RCPT TO:<b@example.com>\r\n\
+             RCPT TO:<c@example.com>\r\n\
+             RCPT TO:<d@example.com>\r\n\
+             DATA\r\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_message_stream_encodes_chunks_as_they_arrive() {
+        let (port, server) = serve_pipelined(3, "250 OK\r\n250 OK\r\n354 Go ahead\r\n").await;
+        let chunks = stream::iter(vec![
+            Ok(b"Subject: hi\r".to_vec()),
+            Ok(b"\n\r\n.body\n".to_vec()),
+        ]);
+
+        let (client, _) = connected(port)
+            .await
+            .send_envelope(
+                Address::new("a@example.com").unwrap(),
+                &addresses(&["b@example.com"]),
+            )
+            .await
+            .unwrap();
+        let client = client.send_message_stream(chunks).await.unwrap();
+        client.quit().await.unwrap();
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.ends_with("DATA\r\nSubject: hi\r\n\r\n..body\r\n.\r\nQUIT\r\n"));
+    }
+
+    #[tokio::test]
+    async fn send_envelope_fails_when_every_recipient_is_rejected() {
+        let (port, server) =
+            serve_pipelined(3, "250 OK\r\n550 No such user\r\n354 Go ahead\r\n").await;
+        let recipients = addresses(&["b@example.com"]);
+
+        let result = connected(port)
+            .await
+            .send_envelope(Address::new("a@example.com").unwrap(), &recipients)
+            .await;
+        assert!(matches!(result, Err(Error::SmtpError { code: 550, .. })));
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.ends_with("DATA\r\n.\r\n"));
+    }
+
+    #[tokio::test]
+    async fn utf8_sender_uses_smtputf8_when_supported() {
+        let (port, server) = serve("SMTPUTF8").await;
+
+        let client = connected(port)
+            .await
+            .mail_from(Address::new("用户@例え.jp").unwrap())
+            .await
+            .unwrap()
+            .rcpt_to(Address::new("b@例え.jp").unwrap())
+            .await
+            .unwrap();
+        client.quit().await.unwrap();
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.contains("MAIL FROM:<用户@例え.jp> SMTPUTF8\r\nRCPT TO:<b@例え.jp>\r\n"));
+    }
+
+    #[tokio::test]
+    async fn utf8_domain_is_punycoded_without_smtputf8() {
+        let (port, server) = serve("8BITMIME").await;
+
+        let client = connected(port)
+            .await
+            .mail_from(Address::new("a@例え.jp").unwrap())
+            .await
+            .unwrap();
+        let result = client.rcpt_to(Address::new("用户@例え.jp").unwrap()).await;
+        assert!(matches!(result, Err(Error::NotSupported(_))));
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.ends_with("MAIL FROM:<a@xn--r8jz45g.jp>\r\n"));
+    }
+
+    #[tokio::test]
+    async fn bdat_requires_chunking() {
+        let (port, _server) = serve("8BITMIME").await;
+        let chunks = stream::iter(vec![Ok(b"x".to_vec())]);
+
+        let result = recipient_added(port).await.bdat(chunks).await;
+        assert!(matches!(result, Err(Error::NotSupported(_))));
+    }
+
+    #[tokio::test]
+    async fn auth_login_answers_username_and_password_prompts() {
+        let (port, server) = serve_script(&[
+            "250-test\r\n250 AUTH LOGIN CRAM-MD5\r\n",
+            "334 VXNlcm5hbWU6\r\n",
+            "334 UGFzc3dvcmQ6\r\n",
+            "235 Authentication successful\r\n",
+        ])
+        .await;
+
+        connected(port)
+            .await
+            .auth_login("tim", "tanstaaftanstaaf")
+            .await
+            .unwrap();
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert_eq!(
+            received,
+            "EHLO client\r\nAUTH LOGIN\r\ndGlt\r\ndGFuc3RhYWZ0YW5zdGFhZg==\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_login_reports_rejected_credentials() {
+        let (port, _server) = serve_script(&[
+            "250-test\r\n250 AUTH LOGIN\r\n",
+            "334 VXNlcm5hbWU6\r\n",
+            "334 UGFzc3dvcmQ6\r\n",
+            "535 Authentication credentials invalid\r\n",
+        ])
+        .await;
+
+        let result = connected(port).await.auth_login("tim", "wrong").await;
+        assert!(matches!(result, Err(Error::SmtpError { code: 535, .. })));
+    }
+
+    #[tokio::test]
+    async fn auth_cram_md5_answers_rfc2195_challenge() {
+        let (port, server) = serve_script(&[
+            "250-test\r\n250 AUTH CRAM-MD5\r\n",
+            "334 PDE4OTYuNjk3MTcwOTUyQHBvc3RvZmZpY2UucmVzdG9uLm1jaS5uZXQ+\r\n",
+            "235 Authentication successful\r\n",
+        ])
+        .await;
+
+        connected(port)
+            .await
+            .auth_cram_md5("tim", "tanstaaftanstaaf")
+            .await
+            .unwrap();
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert_eq!(
+            received,
+            "EHLO client\r\nAUTH CRAM-MD5\r\ndGltIGI5MTNhNjAyYzdlZGE3YTQ5NWI0ZTZlNzMzNGQzODkw\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_cram_md5_cancels_on_invalid_challenge() {
+        let (port, server) = serve_script(&[
+            "250-test\r\n250 AUTH CRAM-MD5\r\n",
+            "334 not base64!\r\n",
+            "501 Authentication cancelled\r\n",
+        ])
+        .await;
+
+        let result = connected(port).await.auth_cram_md5("tim", "secret").await;
+        assert!(matches!(result, Err(Error::Protocol(_))));
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        assert!(received.ends_with("AUTH CRAM-MD5\r\n*\r\n"));
+    }
+}
@@ -2,12 +2,12 @@
 
 use crate::error::{Error, Result};
 use rustls::pki_types::ServerName;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio_rustls::{
     TlsConnector,
-    rustls::{ClientConfig, RootCertStore},
+    rustls::{ClientConfig, RootCertStore, crypto::CryptoProvider},
 };
 
 /// SMTP stream (TCP or TLS).
@@ -68,7 +68,7 @@ impl SmtpStream {
             Self::Tls(_) => return Err(Error::Protocol("Already using TLS".into())),
         };
 
-        let connector = create_tls_connector();
+        let connector = create_tls_connector()?;
         let server_name = ServerName::try_from(hostname.to_string())
             .map_err(|_| Error::Protocol(format!("Invalid hostname: {hostname}")))?;
 
@@ -97,7 +97,7 @@ pub async fn connect_tls(hostname: &str, port: u16) -> Result<SmtpStream> {
     let addr = format!("{hostname}:{port}");
     let tcp_stream = TcpStream::connect(&addr).await?;
 
-    let connector = create_tls_connector();
+    let connector = create_tls_connector()?;
     let server_name = ServerName::try_from(hostname.to_string())
         .map_err(|_| Error::Protocol(format!("Invalid hostname: {hostname}")))?;
 
@@ -105,15 +105,36 @@ pub async fn connect_tls(hostname: &str, port: u16) -> Result<SmtpStream> {
     Ok(SmtpStream::Tls(Box::new(BufReader::new(tls_stream))))
 }
 
-/// Creates a TLS connector with system root certificates.
-fn create_tls_connector() -> TlsConnector {
-    let root_store = RootCertStore {
-        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
-    };
+/// TLS configuration shared by every connection, so reconnecting to a
+/// server can resume the previous session from its in-memory cache.
+static TLS_CONFIG: LazyLock<std::result::Result<Arc<ClientConfig>, rustls::Error>> =
+    LazyLock::new(|| {
+        let root_store = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
 
-    let config = ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+        let config = ClientConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(root_store)
+            .with_no_client_auth();
 
-    TlsConnector::from(Arc::new(config))
+        Ok(Arc::new(config))
+    });
+
+/// Returns the process-wide crypto provider, or aws-lc-rs if none was
+/// installed.
+///
+/// rustls only picks a default itself when exactly one provider is built
+/// in, which isn't the case when this crate shares a build with ring.
+fn crypto_provider() -> Arc<CryptoProvider> {
+    CryptoProvider::get_default().map_or_else(
+        || Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+        Arc::clone,
+    )
+}
+
+/// Creates a TLS connector with system root certificates.
+fn create_tls_connector() -> Result<TlsConnector> {
+    let config = TLS_CONFIG.as_ref().map_err(|e| Error::Tls(e.clone()))?;
+    Ok(TlsConnector::from(Arc::clone(config)))
 }
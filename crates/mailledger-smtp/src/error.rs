@@ -1,5 +1,6 @@
 //! Error types for SMTP operations.
 
+use crate::types::Reply;
 use std::io;
 
 /// Result type alias for SMTP operations.
@@ -23,6 +24,8 @@ pub enum Error {
         code: u16,
         /// Error message from server.
         message: String,
+        /// Enhanced status code (RFC 3463), if the server sent one.
+        enhanced_code: Option<(u8, u8, u16)>,
     },
 
     /// Protocol error (unexpected response).
@@ -61,18 +64,43 @@ impl Error {
         Self::SmtpError {
             code,
             message: message.into(),
+            enhanced_code: None,
         }
     }
 
-    /// Returns true if this is a permanent error (5xx).
+    /// Creates an SMTP error from a failed reply, keeping its enhanced code.
+    #[must_use]
+    pub fn from_reply(reply: &Reply) -> Self {
+        Self::SmtpError {
+            code: reply.code.as_u16(),
+            message: reply.message_text(),
+            enhanced_code: reply.enhanced_code,
+        }
+    }
+
+    /// Returns true if this is a permanent error (5xx, or enhanced class 5).
     #[must_use]
     pub const fn is_permanent(&self) -> bool {
-        matches!(self, Self::SmtpError { code, .. } if *code >= 500 && *code < 600)
+        match self {
+            Self::SmtpError {
+                enhanced_code: Some((class, _, _)),
+                ..
+            } => *class == 5,
+            Self::SmtpError { code, .. } => *code >= 500 && *code < 600,
+            _ => false,
+        }
     }
 
-    /// Returns true if this is a transient error (4xx).
+    /// Returns true if this is a transient error (4xx, or enhanced class 4).
     #[must_use]
     pub const fn is_transient(&self) -> bool {
-        matches!(self, Self::SmtpError { code, .. } if *code >= 400 && *code < 500)
+        match self {
+            Self::SmtpError {
+                enhanced_code: Some((class, _, _)),
+                ..
+            } => *class == 4,
+            Self::SmtpError { code, .. } => *code >= 400 && *code < 500,
+            _ => false,
+        }
     }
 }
@@ -65,6 +65,7 @@
 //! - [`command`]: SMTP command builders
 //! - [`connection`]: Connection management and type-state client
 //! - [`parser`]: Response parser
+//! - [`protocol`]: Sans-I/O protocol state machine
 //! - [`types`]: Core SMTP types (addresses, extensions, replies)
 
 #![warn(missing_docs)]
@@ -76,6 +77,7 @@ pub mod command;
 pub mod connection;
 mod error;
 pub mod parser;
+pub mod protocol;
 pub mod types;
 
 pub use connection::{
@@ -83,7 +85,8 @@ pub use connection::{
     SmtpConnection,
 };
 pub use error::{Error, Result};
-pub use types::{Address, AuthMechanism, Extension, Mailbox, Reply, ReplyCode};
+pub use protocol::{SmtpEvent, SmtpProtocol};
+pub use types::{Address, AuthMechanism, DsnNotify, DsnRet, Extension, Mailbox, Reply, ReplyCode};
 
 /// SMTP protocol version supported.
 pub const SMTP_VERSION: &str = "SMTP/ESMTP (RFC 5321)";
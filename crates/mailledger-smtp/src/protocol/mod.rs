@@ -0,0 +1,451 @@
+//! Sans-I/O SMTP protocol implementation.
+//!
+//! [`SmtpProtocol`] is a pure state machine: commands are queued with its
+//! methods, bytes to send are taken with [`SmtpProtocol::poll_transmit`] and
+//! bytes received are fed to [`SmtpProtocol::handle_input`], which reports
+//! complete replies as [`SmtpEvent`]s. No I/O happens inside, so whole
+//! sessions can be tested by injecting server replies.
+//!
+//! SMTP answers every command with exactly one reply, in order, so replies
+//! are matched to commands first in, first out. Several commands may be in
+//! flight at once when the server supports PIPELINING.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use mailledger_smtp::protocol::{SmtpEvent, SmtpProtocol};
+//!
+//! let mut protocol = SmtpProtocol::new();
+//! let ehlo = protocol.ehlo("client.example.com");
+//!
+//! for event in protocol.handle_input(received_bytes)? {
+//!     match event {
+//!         SmtpEvent::Greeting { reply } => { /* ... */ }
+//!         SmtpEvent::ReplyComplete { handle, reply } => { /* ... */ }
+//!         SmtpEvent::Unsolicited { reply } => { /* ... */ }
+//!     }
+//! }
+//! while let Some(transmit) = protocol.poll_transmit() {
+//!     send_to_server(&transmit.data);
+//! }
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::command::{Command, encode_data};
+use crate::error::Result;
+use crate::parser::{is_last_reply_line, parse_reply};
+use crate::types::{Address, AuthMechanism, Extension, Reply};
+
+/// Data to transmit to the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transmit {
+    /// Raw bytes to send to the server.
+    pub data: Vec<u8>,
+}
+
+/// A handle to a command awaiting its reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommandHandle {
+    id: u64,
+}
+
+impl CommandHandle {
+    /// Returns the position of the command in send order.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// Events produced by the protocol state machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmtpEvent {
+    /// The server greeting was received.
+    Greeting {
+        /// The greeting reply (220 when the server accepts the session).
+        reply: Reply,
+    },
+    /// A command received its reply.
+    ReplyComplete {
+        /// The command handle.
+        handle: CommandHandle,
+        /// The reply.
+        reply: Reply,
+    },
+    /// A reply arrived while no command was waiting, e.g. a 421 shutdown.
+    Unsolicited {
+        /// The reply.
+        reply: Reply,
+    },
+}
+
+/// A command waiting for its reply.
+struct PendingCommand {
+    handle: CommandHandle,
+    /// Whether the reply lists EHLO extensions.
+    is_ehlo: bool,
+}
+
+/// Sans-I/O SMTP protocol state machine.
+///
+/// Queued commands are held back until the server greeting arrives, as a
+/// client must not talk first (RFC 5321 section 3.1).
+#[derive(Default)]
+pub struct SmtpProtocol {
+    /// Commands sent or queued, awaiting replies in order.
+    pending: VecDeque<PendingCommand>,
+    /// Id for the next queued command.
+    next_id: u64,
+    /// Outbound data queue.
+    outbound: VecDeque<Transmit>,
+    /// Inbound bytes not yet forming a complete line.
+    inbound: Vec<u8>,
+    /// Lines of the reply being received.
+    lines: Vec<String>,
+    /// Whether the greeting has been received.
+    greeting_received: bool,
+    /// Extensions from the last successful EHLO.
+    extensions: HashSet<Extension>,
+}
+
+impl SmtpProtocol {
+    /// Creates a protocol instance waiting for the server greeting.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the extensions advertised in the last successful EHLO reply.
+    #[must_use]
+    pub const fn extensions(&self) -> &HashSet<Extension> {
+        &self.extensions
+    }
+
+    /// Checks if the server advertised an extension.
+    #[must_use]
+    pub fn supports(&self, ext: &Extension) -> bool {
+        self.extensions.contains(ext)
+    }
+
+    /// Returns the number of commands awaiting a reply.
+    #[must_use]
+    pub fn commands_in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the next data to transmit, if any.
+    ///
+    /// Returns `None` until the server greeting has been received.
+    pub fn poll_transmit(&mut self) -> Option<Transmit> {
+        if !self.greeting_received {
+            return None;
+        }
+        self.outbound.pop_front()
+    }
+
+    /// Feeds received data into the protocol.
+    ///
+    /// Returns an event for each reply completed by the data. Partial lines
+    /// and multi-line replies are buffered until complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reply is malformed.
+    pub fn handle_input(&mut self, data: &[u8]) -> Result<Vec<SmtpEvent>> {
+        self.inbound.extend_from_slice(data);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.inbound.windows(2).position(|w| w == b"\r\n") {
+            let line: Vec<u8> = self.inbound.drain(..pos + 2).collect();
+            let line = String::from_utf8_lossy(&line[..pos]).into_owned();
+            if line.is_empty() {
+                continue;
+            }
+
+            let is_last = is_last_reply_line(&line);
+            self.lines.push(line);
+            if is_last {
+                let reply = parse_reply(&std::mem::take(&mut self.lines))?;
+                events.push(self.complete_reply(reply));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Matches a complete reply to the greeting or the oldest command.
+    fn complete_reply(&mut self, reply: Reply) -> SmtpEvent {
+        if !self.greeting_received {
+            self.greeting_received = true;
+            return SmtpEvent::Greeting { reply };
+        }
+
+        let reply = if self.supports(&Extension::EnhancedStatusCodes) {
+            reply.with_enhanced_code()
+        } else {
+            reply
+        };
+        let Some(pending) = self.pending.pop_front() else {
+            return SmtpEvent::Unsolicited { reply };
+        };
+        if pending.is_ehlo && reply.is_success() {
+            // The first line is the server's greeting to us
+            self.extensions = reply
+                .message
+                .iter()
+                .skip(1)
+                .map(|line| Extension::parse(line))
+                .collect();
+        }
+        SmtpEvent::ReplyComplete {
+            handle: pending.handle,
+            reply,
+        }
+    }
+
+    /// Queues raw data that the server answers with one reply.
+    fn queue_data(&mut self, data: Vec<u8>, is_ehlo: bool) -> CommandHandle {
+        let handle = CommandHandle { id: self.next_id };
+        self.next_id += 1;
+        self.pending.push_back(PendingCommand { handle, is_ehlo });
+        self.outbound.push_back(Transmit { data });
+        handle
+    }
+
+    // === Command Methods ===
+
+    /// Queues an arbitrary command.
+    pub fn queue(&mut self, cmd: &Command) -> CommandHandle {
+        self.queue_data(cmd.serialize(), matches!(cmd, Command::Ehlo { .. }))
+    }
+
+    /// Queues EHLO; a successful reply updates [`Self::extensions`].
+    pub fn ehlo(&mut self, hostname: &str) -> CommandHandle {
+        self.queue(&Command::Ehlo {
+            hostname: hostname.to_string(),
+        })
+    }
+
+    /// Queues AUTH with an optional initial response.
+    ///
+    /// A 334 reply asks for more data; answer it with
+    /// [`Self::auth_response`].
+    pub fn auth(
+        &mut self,
+        mechanism: AuthMechanism,
+        initial_response: Option<&str>,
+    ) -> CommandHandle {
+        self.queue(&Command::Auth {
+            mechanism,
+            initial_response: initial_response.map(str::to_string),
+        })
+    }
+
+    /// Queues a response line to a 334 challenge during AUTH.
+    pub fn auth_response(&mut self, response: &str) -> CommandHandle {
+        self.queue_data(format!("{response}\r\n").into_bytes(), false)
+    }
+
+    /// Queues MAIL FROM without parameters.
+    pub fn mail_from(&mut self, from: Address) -> CommandHandle {
+        self.queue(&Command::MailFrom {
+            from,
+            body: None,
+            size: None,
+            ret: None,
+            envid: None,
+            smtputf8: false,
+        })
+    }
+
+    /// Queues RCPT TO without parameters.
+    pub fn rcpt_to(&mut self, to: Address) -> CommandHandle {
+        self.queue(&Command::RcptTo {
+            to,
+            notify: None,
+            orcpt: None,
+        })
+    }
+
+    /// Queues DATA. After a 354 reply, send the content with
+    /// [`Self::message`].
+    pub fn data(&mut self) -> CommandHandle {
+        self.queue(&Command::Data)
+    }
+
+    /// Queues the message content after DATA was accepted.
+    ///
+    /// The message is dot-stuffed and terminated as [`encode_data`] does.
+    pub fn message(&mut self, message: &[u8]) -> CommandHandle {
+        self.queue_data(encode_data(message), false)
+    }
+
+    /// Queues QUIT.
+    pub fn quit(&mut self) -> CommandHandle {
+        self.queue(&Command::Quit)
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+
+    /// Takes all queued transmits as one string.
+    fn sent(protocol: &mut SmtpProtocol) -> String {
+        let mut out = Vec::new();
+        while let Some(transmit) = protocol.poll_transmit() {
+            out.extend_from_slice(&transmit.data);
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Feeds `input` and returns the code of each completed reply.
+    fn codes(protocol: &mut SmtpProtocol, input: &str) -> Vec<(Option<u64>, u16)> {
+        protocol
+            .handle_input(input.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|event| match event {
+                SmtpEvent::Greeting { reply } | SmtpEvent::Unsolicited { reply } => {
+                    (None, reply.code.as_u16())
+                }
+                SmtpEvent::ReplyComplete { handle, reply } => {
+                    (Some(handle.id()), reply.code.as_u16())
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_complete_send_transaction() {
+        let mut protocol = SmtpProtocol::new();
+        let ehlo = protocol.ehlo("client.example.com");
+        // Nothing may be sent before the greeting.
+        assert_eq!(protocol.poll_transmit(), None);
+
+        assert_eq!(
+            codes(&mut protocol, "220 smtp.example.com ESMTP\r\n"),
+            vec![(None, 220)]
+        );
+        assert_eq!(sent(&mut protocol), "EHLO client.example.com\r\n");
+
+        let events = protocol
+            .handle_input(
+                b"250-smtp.example.com\r\n250-PIPELINING\r\n\
+                  250-ENHANCEDSTATUSCODES\r\n250 AUTH PLAIN\r\n",
+            )
+            .unwrap();
+        assert!(matches!(
+            &events[..],
+            [SmtpEvent::ReplyComplete { handle, reply }]
+                if *handle == ehlo && reply.message.len() == 4
+        ));
+        assert!(protocol.supports(&Extension::Pipelining));
+
+        let auth = protocol.auth(AuthMechanism::Plain, Some("AHVzZXIAcGFzcw=="));
+        assert_eq!(sent(&mut protocol), "AUTH PLAIN AHVzZXIAcGFzcw==\r\n");
+        assert_eq!(
+            codes(&mut protocol, "235 2.7.0 Authentication successful\r\n"),
+            vec![(Some(auth.id()), 235)]
+        );
+
+        // Envelope commands are pipelined and answered in order.
+        let mail = protocol.mail_from(Address::new("alice@example.com").unwrap());
+        let rcpt = protocol.rcpt_to(Address::new("bob@example.com").unwrap());
+        let data = protocol.data();
+        assert_eq!(protocol.commands_in_flight(), 3);
+        assert_eq!(
+            sent(&mut protocol),
+            "MAIL FROM:<alice@example.com>\r\nRCPT TO:<bob@example.com>\r\nDATA\r\n"
+        );
+        assert_eq!(
+            codes(
+                &mut protocol,
+                "250 2.1.0 OK\r\n250 2.1.5 OK\r\n354 Go ahead\r\n"
+            ),
+            vec![
+                (Some(mail.id()), 250),
+                (Some(rcpt.id()), 250),
+                (Some(data.id()), 354)
+            ]
+        );
+
+        let message = protocol.message(b"Subject: Hi\r\n\r\n.hidden\r\nBye");
+        assert_eq!(
+            sent(&mut protocol),
+            "Subject: Hi\r\n\r\n..hidden\r\nBye\r\n.\r\n"
+        );
+        let events = protocol
+            .handle_input(b"250 2.0.0 Queued as 1234\r\n")
+            .unwrap();
+        assert!(matches!(
+            &events[..],
+            [SmtpEvent::ReplyComplete { handle, reply }]
+                if *handle == message && reply.enhanced_code == Some((2, 0, 0))
+        ));
+
+        let quit = protocol.quit();
+        assert_eq!(sent(&mut protocol), "QUIT\r\n");
+        assert_eq!(
+            codes(&mut protocol, "221 2.0.0 Bye\r\n"),
+            vec![(Some(quit.id()), 221)]
+        );
+        assert_eq!(protocol.commands_in_flight(), 0);
+    }
+
+    #[test]
+    fn test_reply_split_across_reads() {
+        let mut protocol = SmtpProtocol::new();
+        protocol.ehlo("client");
+        assert!(protocol.handle_input(b"220 ready\r").unwrap().is_empty());
+        assert_eq!(
+            codes(&mut protocol, "\n250-host\r\n250-SIZE 1000"),
+            vec![(None, 220)]
+        );
+        assert_eq!(
+            codes(&mut protocol, "\r\n250 8BITMIME\r\n"),
+            vec![(Some(0), 250)]
+        );
+        assert!(protocol.supports(&Extension::Size(Some(1000))));
+        assert!(protocol.supports(&Extension::EightBitMime));
+    }
+
+    #[test]
+    fn test_auth_challenge_and_unsolicited_reply() {
+        let mut protocol = SmtpProtocol::new();
+        protocol.handle_input(b"220 ready\r\n").unwrap();
+
+        let auth = protocol.auth(AuthMechanism::Login, None);
+        assert_eq!(
+            codes(&mut protocol, "334 VXNlcm5hbWU6\r\n"),
+            vec![(Some(auth.id()), 334)]
+        );
+        let response = protocol.auth_response("dXNlcg==");
+        assert_eq!(sent(&mut protocol), "AUTH LOGIN\r\ndXNlcg==\r\n");
+        assert_eq!(
+            codes(&mut protocol, "334 UGFzc3dvcmQ6\r\n"),
+            vec![(Some(response.id()), 334)]
+        );
+
+        // Nothing is waiting, so a shutdown notice stands on its own.
+        assert_eq!(
+            codes(&mut protocol, "421 Shutting down\r\n"),
+            vec![(None, 421)]
+        );
+    }
+
+    #[test]
+    fn test_malformed_reply_is_an_error() {
+        let mut protocol = SmtpProtocol::new();
+        assert!(protocol.handle_input(b"hey you\r\n").is_err());
+    }
+}
@@ -18,13 +18,74 @@ impl Address {
         Ok(Self(addr))
     }
 
+    /// Returns the null reverse-path `<>`, used as the sender of
+    /// notifications such as bounces and read receipts so they are never
+    /// answered in turn (RFC 5321 section 4.5.5).
+    #[must_use]
+    pub const fn null() -> Self {
+        Self(String::new())
+    }
+
+    /// Returns true if this is the null reverse-path.
+    #[must_use]
+    pub const fn is_null(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Returns the address as a string slice.
     #[must_use]
     pub fn as_str(&self) -> &str {
         &self.0
     }
 
-    /// Validates an email address (basic validation).
+    /// Returns the part before the `@`.
+    #[must_use]
+    pub fn local_part(&self) -> &str {
+        self.0.split_once('@').map_or("", |(local, _)| local)
+    }
+
+    /// Returns the part after the `@`.
+    #[must_use]
+    pub fn domain(&self) -> &str {
+        self.0.split_once('@').map_or("", |(_, domain)| domain)
+    }
+
+    /// Returns true if the address is plain ASCII and needs no SMTPUTF8.
+    #[must_use]
+    pub fn is_ascii(&self) -> bool {
+        self.0.is_ascii()
+    }
+
+    /// Converts the address for a server without SMTPUTF8 by punycode-encoding
+    /// the domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotSupported`] if the local part is not ASCII, since
+    /// it cannot be represented without SMTPUTF8, or an error if the domain
+    /// is not a valid internationalized domain name.
+    pub fn into_ascii(self) -> Result<Self> {
+        if self.is_ascii() {
+            return Ok(self);
+        }
+        if !self.local_part().is_ascii() {
+            return Err(Error::NotSupported(format!(
+                "SMTPUTF8 (non-ASCII local part in {self})"
+            )));
+        }
+        let domain = idna::domain_to_ascii(self.domain()).map_err(|_| {
+            Error::InvalidAddress(format!(
+                "Invalid internationalized domain: {}",
+                self.domain()
+            ))
+        })?;
+        Ok(Self(format!("{}@{domain}", self.local_part())))
+    }
+
+    /// Validates an email address.
+    ///
+    /// UTF-8 is allowed in both parts (RFC 6531); whitespace, control
+    /// characters and angle brackets are not.
     fn validate(addr: &str) -> Result<()> {
         if addr.is_empty() {
             return Err(Error::InvalidAddress("Address cannot be empty".into()));
@@ -47,6 +108,15 @@ impl Address {
             ));
         }
 
+        if addr
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '<' || c == '>')
+        {
+            return Err(Error::InvalidAddress(
+                "Address cannot contain whitespace, control characters or angle brackets".into(),
+            ));
+        }
+
         Ok(())
     }
 }
@@ -131,6 +201,32 @@ mod tests {
         assert!(Address::new("user@").is_err());
     }
 
+    #[test]
+    fn test_invalid_address_whitespace() {
+        assert!(Address::new("us er@example.com").is_err());
+        assert!(Address::new("user@example.com>").is_err());
+    }
+
+    #[test]
+    fn test_utf8_address() {
+        let addr = Address::new("用户@例え.jp").unwrap();
+        assert!(!addr.is_ascii());
+        assert_eq!(addr.local_part(), "用户");
+        assert_eq!(addr.domain(), "例え.jp");
+    }
+
+    #[test]
+    fn test_into_ascii_punycodes_domain() {
+        let addr = Address::new("user@例え.jp").unwrap().into_ascii().unwrap();
+        assert_eq!(addr.as_str(), "user@xn--r8jz45g.jp");
+    }
+
+    #[test]
+    fn test_into_ascii_rejects_utf8_local_part() {
+        let result = Address::new("用户@例え.jp").unwrap().into_ascii();
+        assert!(matches!(result, Err(Error::NotSupported(_))));
+    }
+
     #[test]
     fn test_mailbox_new() {
         let mailbox = Mailbox::new("user@example.com").unwrap();
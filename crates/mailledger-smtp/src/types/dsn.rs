@@ -0,0 +1,91 @@
+//! Delivery status notification parameters (RFC 3461).
+
+/// RET parameter of MAIL FROM: how much of the message a DSN should return.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DsnRet {
+    /// FULL - return the entire message
+    Full,
+    /// HDRS - return only the headers
+    Hdrs,
+}
+
+impl DsnRet {
+    /// Returns the parameter value as a string.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "FULL",
+            Self::Hdrs => "HDRS",
+        }
+    }
+}
+
+/// NOTIFY parameter of RCPT TO: which events should produce a DSN.
+///
+/// `Never` must not be combined with any other value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DsnNotify {
+    /// SUCCESS - notify on successful delivery
+    Success,
+    /// FAILURE - notify on delivery failure
+    Failure,
+    /// DELAY - notify when delivery is delayed
+    Delay,
+    /// NEVER - never send a DSN
+    Never,
+}
+
+impl DsnNotify {
+    /// Returns the parameter value as a string.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "SUCCESS",
+            Self::Failure => "FAILURE",
+            Self::Delay => "DELAY",
+            Self::Never => "NEVER",
+        }
+    }
+}
+
+/// Encodes a parameter value as xtext (RFC 3461 section 4).
+///
+/// Characters outside printable ASCII, `+` and `=` become `+XX`.
+#[must_use]
+pub fn xtext(value: &str) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if (b'!'..=b'~').contains(&byte) && byte != b'+' && byte != b'=' {
+            out.push(char::from(byte));
+        } else {
+            let _ = write!(out, "+{byte:02X}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::unwrap_used,
+    clippy::redundant_clone,
+    clippy::manual_string_new,
+    clippy::needless_collect,
+    clippy::unreadable_literal,
+    clippy::used_underscore_items,
+    clippy::similar_names
+)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xtext_passes_plain_values() {
+        assert_eq!(xtext("user@example.com"), "user@example.com");
+    }
+
+    #[test]
+    fn xtext_escapes_plus_equals_and_space() {
+        assert_eq!(xtext("a+b=c d"), "a+2Bb+3Dc+20d");
+    }
+}
@@ -21,6 +21,8 @@ pub enum Extension {
     Dsn,
     /// BINARYMIME - Binary MIME
     BinaryMime,
+    /// ENHANCEDSTATUSCODES - RFC 3463 status codes in replies
+    EnhancedStatusCodes,
     /// Unknown extension
     Unknown(String),
 }
@@ -54,6 +56,7 @@ impl Extension {
             "SMTPUTF8" => Self::SmtpUtf8,
             "DSN" => Self::Dsn,
             "BINARYMIME" => Self::BinaryMime,
+            "ENHANCEDSTATUSCODES" => Self::EnhancedStatusCodes,
             _ => Self::Unknown(line.to_string()),
         }
     }
@@ -195,6 +198,14 @@ mod tests {
             assert_eq!(Extension::parse("DSN"), Extension::Dsn);
         }
 
+        #[test]
+        fn parse_enhancedstatuscodes() {
+            assert_eq!(
+                Extension::parse("ENHANCEDSTATUSCODES"),
+                Extension::EnhancedStatusCodes
+            );
+        }
+
         #[test]
         fn parse_binarymime() {
             assert_eq!(Extension::parse("BINARYMIME"), Extension::BinaryMime);
@@ -1,9 +1,12 @@
 //! Core SMTP types.
 
 mod address;
+mod dsn;
 mod extension;
 mod reply;
 
 pub use address::{Address, Mailbox};
+pub(crate) use dsn::xtext;
+pub use dsn::{DsnNotify, DsnRet};
 pub use extension::{AuthMechanism, Extension};
 pub use reply::{Reply, ReplyCode};
@@ -7,6 +7,9 @@ pub struct Reply {
     pub code: ReplyCode,
     /// Reply message lines.
     pub message: Vec<String>,
+    /// Enhanced status code `class.subject.detail` (RFC 3463), parsed when
+    /// the server advertises ENHANCEDSTATUSCODES.
+    pub enhanced_code: Option<(u8, u8, u16)>,
 }
 
 impl Reply {
@@ -14,7 +17,21 @@ impl Reply {
     #[must_use]
     #[allow(clippy::missing_const_for_fn)] // Vec is not const-compatible
     pub fn new(code: ReplyCode, message: Vec<String>) -> Self {
-        Self { code, message }
+        Self {
+            code,
+            message,
+            enhanced_code: None,
+        }
+    }
+
+    /// Parses the enhanced status code from the start of the first line.
+    #[must_use]
+    pub fn with_enhanced_code(mut self) -> Self {
+        self.enhanced_code = self
+            .message
+            .first()
+            .and_then(|line| parse_enhanced_code(line));
+        self
     }
 
     /// Returns true if this is a success reply (2xx).
@@ -35,6 +52,26 @@ impl Reply {
         self.code.is_permanent()
     }
 
+    /// Returns true if the failure is temporary and the command may succeed
+    /// later. Uses the enhanced status class when present, else the code.
+    #[must_use]
+    pub const fn is_transient(&self) -> bool {
+        match self.enhanced_code {
+            Some((class, _, _)) => class == 4,
+            None => self.code.is_transient(),
+        }
+    }
+
+    /// Returns true if the failure is permanent. Uses the enhanced status
+    /// class when present, else the code.
+    #[must_use]
+    pub const fn is_permanent(&self) -> bool {
+        match self.enhanced_code {
+            Some((class, _, _)) => class == 5,
+            None => self.code.is_permanent(),
+        }
+    }
+
     /// Returns the full message as a single string.
     #[must_use]
     pub fn message_text(&self) -> String {
@@ -42,6 +79,16 @@ impl Reply {
     }
 }
 
+/// Parses a leading `X.Y.Z` enhanced status code from a reply line.
+fn parse_enhanced_code(line: &str) -> Option<(u8, u8, u16)> {
+    let token = line.split_whitespace().next()?;
+    let mut parts = token.splitn(3, '.');
+    let class: u8 = parts.next()?.parse().ok()?;
+    let subject = parts.next()?.parse().ok()?;
+    let detail = parts.next()?.parse().ok()?;
+    matches!(class, 2 | 4 | 5).then_some((class, subject, detail))
+}
+
 /// SMTP reply code.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ReplyCode(u16);
@@ -255,6 +302,40 @@ mod tests {
             assert!(reply.is_permanent_error());
         }
 
+        #[test]
+        fn enhanced_code_parsed_from_first_line() {
+            let reply = Reply::new(
+                ReplyCode::MAILBOX_BUSY,
+                vec!["4.2.2 Mailbox full".to_string()],
+            )
+            .with_enhanced_code();
+            assert_eq!(reply.enhanced_code, Some((4, 2, 2)));
+            assert!(reply.is_transient());
+            assert!(!reply.is_permanent());
+        }
+
+        #[test]
+        fn enhanced_code_class_overrides_reply_code() {
+            let reply = Reply::new(
+                ReplyCode::MAILBOX_UNAVAILABLE,
+                vec!["4.7.1 Try again later".to_string()],
+            )
+            .with_enhanced_code();
+            assert!(reply.is_transient());
+            assert!(reply.is_permanent_error());
+        }
+
+        #[test]
+        fn enhanced_code_absent() {
+            let reply = Reply::new(
+                ReplyCode::MAILBOX_UNAVAILABLE,
+                vec!["No such user".to_string()],
+            )
+            .with_enhanced_code();
+            assert_eq!(reply.enhanced_code, None);
+            assert!(reply.is_permanent());
+        }
+
         #[test]
         fn message_text_single_line() {
             let reply = Reply::new(ReplyCode::OK, vec!["Message sent".to_string()]);
@@ -15,11 +15,15 @@ use iced::event::{self, Event};
 use iced::keyboard::{self, Key, Modifiers};
 use iced::mouse;
 use iced::widget::{Space, column, container, image, markdown, row, text, text_editor};
+use iced::window;
 use iced::{Background, Border, Element, Length, Subscription, Task};
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use mailledger_core::RemotePolicy;
 
 use message::{
     AccountSetupMessage, ComposeMessage, KeyboardAction, Message, PaneDivider, ScreenerMessage,
@@ -27,13 +31,18 @@ use message::{
 };
 use model::{
     AccountSetupState, AppSettings, AutocompleteField, ComposeState, Folder, FolderId, FolderType,
-    FontSize, InlineImage, InlineImageState, ListDensity, MessageContent, MessageId,
-    MessageSummary, SettingsState, Thread, ViewMode, group_into_threads,
+    FontSize, InlineImage, InlineImageState, ListDensity, MessageChanges, MessageContent,
+    MessageId, MessageSummary, ReadReceiptPolicy, SendOutcome, SettingsState, Thread, TriageTab,
+    ViewMode, group_into_threads,
 };
 use style::widgets::palette::{self, ThemeMode};
 use style::widgets::radius;
 use view::PendingSender;
 
+/// IMAP sessions shared by all background tasks, one per account.
+static IMAP_POOL: LazyLock<mailledger_core::ConnectionPool> =
+    LazyLock::new(mailledger_core::ConnectionPool::new);
+
 /// Sanitizes a filename to prevent path traversal attacks.
 ///
 /// This function removes or replaces dangerous characters that could be used
@@ -97,6 +106,12 @@ fn main() -> iced::Result {
         .run()
 }
 
+/// How often the message being composed is saved to Drafts if it changed.
+const DRAFT_AUTOSAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How often queued messages are retried while online.
+const OUTBOX_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Main application state.
 #[allow(clippy::struct_excessive_bools)] // State flags are clearer as bools for now
 struct MailLedger {
@@ -114,6 +129,10 @@ struct MailLedger {
     all_messages: Vec<MessageSummary>,
     /// Currently selected message.
     selected_message: Option<MessageId>,
+    /// Messages picked for a bulk action.
+    selected_messages: HashSet<MessageId>,
+    /// Keyboard modifiers currently held.
+    modifiers: Modifiers,
     /// Content of the selected message.
     message_content: Option<MessageContent>,
     /// Parsed markdown items for message body display.
@@ -144,8 +163,10 @@ struct MailLedger {
     is_loading_messages: bool,
     /// Error message to display.
     error_message: Option<String>,
-    /// Whether IDLE monitoring is active.
-    is_idle_active: bool,
+    /// HIGHESTMODSEQ of the open folder, for CONDSTORE refreshes.
+    highest_mod_seq: Option<u64>,
+    /// Highest UID loaded per folder, for fetching only new mail.
+    last_seen_uid: HashMap<FolderId, u32>,
     /// Current theme mode (light/dark).
     theme_mode: ThemeMode,
     /// Pending senders for the Screener.
@@ -166,10 +187,18 @@ struct MailLedger {
     snooze_dropdown_open: bool,
     /// Active search filters.
     search_filters: HashSet<SearchFilter>,
+    /// Selected triage tab; `None` shows every inbox message.
+    triage_tab: Option<TriageTab>,
     /// Font size preference.
     font_size: FontSize,
     /// List density preference.
     list_density: ListDensity,
+    /// Read receipt policy.
+    read_receipts: ReadReceiptPolicy,
+    /// When remote images in messages load.
+    load_remote_images: RemotePolicy,
+    /// Addresses whose remote images always load.
+    trusted_senders: HashSet<String>,
     /// Message list scroll offset for virtual scrolling.
     message_list_scroll_offset: f32,
     /// Message list viewport height for virtual scrolling.
@@ -182,6 +211,8 @@ struct MailLedger {
     dragging_divider: Option<PaneDivider>,
     /// Whether we're currently offline (no server connection).
     is_offline: bool,
+    /// Whether queued messages are being sent.
+    is_flushing_outbox: bool,
 }
 
 impl Default for MailLedger {
@@ -194,6 +225,8 @@ impl Default for MailLedger {
             messages: Vec::new(),
             all_messages: Vec::new(),
             selected_message: None,
+            selected_messages: HashSet::new(),
+            modifiers: Modifiers::default(),
             message_content: None,
             markdown_items: Vec::new(),
             inline_images: Vec::new(),
@@ -209,7 +242,8 @@ impl Default for MailLedger {
             is_loading_folders: false,
             is_loading_messages: false,
             error_message: None,
-            is_idle_active: false,
+            highest_mod_seq: None,
+            last_seen_uid: HashMap::new(),
             theme_mode: ThemeMode::Dark, // Default to dark mode
             pending_senders: Vec::new(),
             is_loading_screener: false,
@@ -220,14 +254,19 @@ impl Default for MailLedger {
             quoted_expanded: false,
             snooze_dropdown_open: false,
             search_filters: HashSet::new(),
+            triage_tab: None,
             font_size: FontSize::Medium,
             list_density: ListDensity::Comfortable,
+            read_receipts: ReadReceiptPolicy::Ask,
+            load_remote_images: RemotePolicy::TrustedSenders,
+            trusted_senders: HashSet::new(),
             message_list_scroll_offset: 0.0,
             message_list_viewport_height: 600.0, // Default viewport height
             sidebar_width: 220.0,
             message_list_width: 380.0,
             dragging_divider: None,
             is_offline: false,
+            is_flushing_outbox: false,
         }
     }
 }
@@ -263,6 +302,11 @@ impl MailLedger {
             });
         }
 
+        // Apply the triage tab; untriaged messages (other folders) stay visible
+        if let Some(tab) = self.triage_tab {
+            filtered.retain(|msg| msg.triage.is_none_or(|triage| triage == tab));
+        }
+
         // Apply quick filters
         for filter in &self.search_filters {
             match filter {
@@ -280,6 +324,129 @@ impl MailLedger {
 
         self.messages = filtered;
     }
+
+    /// Reload the open folder from the server.
+    fn reload_messages(&mut self) -> Task<Message> {
+        if let Some(account) = self.current_account.clone()
+            && let Some(folder_id) = self.selected_folder
+            && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+        {
+            self.is_loading_messages = true;
+            return Task::perform(
+                load_messages(account, folder_path, folder_id, false),
+                Message::MessagesLoaded,
+            );
+        }
+        Task::none()
+    }
+
+    /// Fetch what changed in the open folder since it was loaded.
+    ///
+    /// Falls back to a full reload when offline, when nothing is loaded yet,
+    /// or when the server didn't report a HIGHESTMODSEQ (no CONDSTORE).
+    fn refresh_changes(&mut self) -> Task<Message> {
+        if !self.is_offline
+            && let Some(account) = self.current_account.clone()
+            && let Some(folder_id) = self.selected_folder
+            && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+            && let Some(since) = self.highest_mod_seq
+            && let Some(oldest) = self.all_messages.iter().map(|msg| msg.id.0).min()
+        {
+            return Task::perform(
+                load_changes(account, folder_path, folder_id, since, oldest),
+                Message::ChangesLoaded,
+            );
+        }
+        self.reload_messages()
+    }
+
+    /// Fetch mail that arrived after the newest loaded message.
+    ///
+    /// Uses CONDSTORE when available, which also catches anything missed
+    /// while disconnected.
+    fn fetch_new_mail(&mut self) -> Task<Message> {
+        if self.highest_mod_seq.is_none()
+            && !self.is_offline
+            && let Some(account) = self.current_account.clone()
+            && let Some(folder_id) = self.selected_folder
+            && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+            && let Some(&last_uid) = self.last_seen_uid.get(&folder_id)
+        {
+            return Task::perform(
+                load_new_messages(account, folder_path, folder_id, last_uid),
+                Message::NewMessagesLoaded,
+            );
+        }
+        self.refresh_changes()
+    }
+
+    /// Re-read the flags of the loaded messages in place.
+    fn refresh_flags(&mut self) -> Task<Message> {
+        if self.highest_mod_seq.is_none()
+            && !self.is_offline
+            && !self.all_messages.is_empty()
+            && let Some(account) = self.current_account.clone()
+            && let Some(folder_id) = self.selected_folder
+            && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+        {
+            let uids = self.all_messages.iter().map(|msg| msg.id.0).collect();
+            return Task::perform(
+                load_flag_updates(account, folder_path, uids),
+                Message::FlagsUpdated,
+            );
+        }
+        self.refresh_changes()
+    }
+
+    /// Append newly arrived messages, keeping selection and scroll position.
+    fn append_messages(&mut self, new_messages: Vec<MessageSummary>) {
+        if let Some(folder_id) = self.selected_folder
+            && let Some(last_uid) = new_messages.iter().map(|msg| msg.id.0).max()
+        {
+            self.last_seen_uid.insert(folder_id, last_uid);
+        }
+        for message in new_messages {
+            if !self.all_messages.iter().any(|m| m.id == message.id) {
+                self.all_messages.push(message);
+            }
+        }
+        if self.view_mode == ViewMode::Threaded {
+            self.threads = group_into_threads(&self.all_messages);
+        }
+        self.filter_messages();
+    }
+
+    /// Merge an incremental refresh into the loaded messages.
+    fn apply_changes(&mut self, changes: MessageChanges) {
+        self.highest_mod_seq = changes.highest_mod_seq;
+        for message in changes.changed {
+            if let Some(existing) = self.all_messages.iter_mut().find(|m| m.id == message.id) {
+                *existing = message;
+            } else {
+                self.all_messages.push(message);
+            }
+        }
+        self.all_messages
+            .retain(|msg| changes.present.contains(&msg.id.0));
+        self.all_messages.sort_by_key(|msg| msg.id.0);
+        if let Some(folder_id) = self.selected_folder
+            && let Some(last_uid) = self.all_messages.last().map(|msg| msg.id.0)
+        {
+            self.last_seen_uid.insert(folder_id, last_uid);
+        }
+
+        if self
+            .selected_message
+            .is_some_and(|id| !changes.present.contains(&id.0))
+        {
+            self.selected_message = None;
+            self.message_content = None;
+        }
+        if self.view_mode == ViewMode::Threaded {
+            self.threads = group_into_threads(&self.all_messages);
+        }
+        self.filter_messages();
+    }
 }
 
 impl MailLedger {
@@ -290,7 +457,11 @@ impl MailLedger {
         app.apply_theme(); // Apply default theme initially
         let settings_task = Task::perform(load_settings(), Message::SettingsLoaded);
         let account_task = Task::perform(load_account(), Message::AccountLoaded);
-        (app, Task::batch([settings_task, account_task]))
+        let trusted_task = Task::perform(load_trusted_senders(), Message::TrustedSendersLoaded);
+        (
+            app,
+            Task::batch([settings_task, account_task, trusted_task]),
+        )
     }
 
     /// Update state based on message.
@@ -325,6 +496,7 @@ impl MailLedger {
             Message::SelectFolder(folder_id) => {
                 self.selected_folder = Some(folder_id);
                 self.selected_message = None;
+                self.selected_messages.clear();
                 self.message_content = None;
 
                 // Load messages from IMAP if we have an account and folder path
@@ -335,7 +507,8 @@ impl MailLedger {
                     self.is_loading_messages = true;
                     self.messages.clear();
                     return Task::perform(
-                        load_messages(account, folder_path, folder_id),
+                        // Previewing a folder shouldn't use up its \Recent flags
+                        load_messages(account, folder_path, folder_id, true),
                         Message::MessagesLoaded,
                     );
                 }
@@ -356,7 +529,7 @@ impl MailLedger {
                     let folder_path = folder.path.clone();
                     self.is_loading_messages = true;
                     return Task::perform(
-                        load_messages(account, folder_path, folder_id),
+                        load_messages(account, folder_path, folder_id, false),
                         Message::MessagesLoaded,
                     );
                 }
@@ -370,6 +543,20 @@ impl MailLedger {
                 // If empty search, just filter locally
                 if self.search_query.is_empty() && self.search_filters.is_empty() {
                     self.filter_messages();
+                } else if self.is_offline
+                    && !self.search_query.is_empty()
+                    && let Some(account_id) = self.current_account.as_ref().and_then(|a| a.id)
+                {
+                    // Server unreachable: use the cache's full-text index
+                    let folder_path = self
+                        .selected_folder
+                        .and_then(|id| self.folder_paths.get(&id))
+                        .cloned()
+                        .unwrap_or_else(|| "INBOX".to_string());
+                    return Task::perform(
+                        search_cached_messages(account_id, folder_path, self.search_query.clone()),
+                        Message::SearchResultsLoaded,
+                    );
                 } else if let Some(account) = self.current_account.clone() {
                     // Use IMAP SEARCH for server-side search
                     let criteria = self.build_search_criteria();
@@ -410,6 +597,10 @@ impl MailLedger {
                 }
                 self.filter_messages();
             }
+            Message::SelectTriageTab(tab) => {
+                self.triage_tab = tab;
+                self.filter_messages();
+            }
             Message::ToggleViewMode => {
                 self.view_mode = match self.view_mode {
                     ViewMode::Flat => ViewMode::Threaded,
@@ -459,6 +650,14 @@ impl MailLedger {
                 }
             }
             Message::SelectMessage(message_id) => {
+                if self.modifiers.command() {
+                    return Task::done(Message::ToggleMessageSelection(message_id));
+                }
+                if self.modifiers.shift() {
+                    self.extend_selection_to(message_id);
+                    return Task::none();
+                }
+                self.selected_messages.clear();
                 self.selected_message = Some(message_id);
                 self.message_content = None; // Clear while loading
                 self.inline_images.clear();
@@ -478,10 +677,12 @@ impl MailLedger {
             Message::MessageContentLoaded(result) => {
                 let mut html_body = None;
                 let mut cache_task = Task::none();
+                let mut receipt_task = Task::none();
                 match result {
-                    Ok(Some(content)) => {
+                    Ok(Some(mut content)) => {
                         html_body.clone_from(&content.body_html);
                         self.is_offline = false;
+                        receipt_task = self.answer_read_receipt_request(&mut content);
 
                         // Cache content for offline use
                         if let Some(account) = self.current_account.as_ref()
@@ -542,11 +743,41 @@ impl MailLedger {
                 self.inline_images.clear();
                 if let Some(html) = html_body {
                     let urls = extract_image_urls(&html);
-                    let (inline_images, task) = prepare_inline_images(urls);
+                    let load = self.message_content.as_ref().is_some_and(|content| {
+                        self.load_remote_images
+                            .allows(&content.from_email, &self.trusted_senders)
+                    });
+                    let (inline_images, task) = prepare_inline_images(urls, load);
                     self.inline_images = inline_images;
-                    return Task::batch([task, cache_task]);
+                    return Task::batch([task, cache_task, receipt_task]);
+                }
+                return Task::batch([cache_task, receipt_task]);
+            }
+            Message::LoadRemoteImages => {
+                return load_inline_images(&mut self.inline_images);
+            }
+            Message::TrustSender => {
+                let Some(sender) = self
+                    .message_content
+                    .as_ref()
+                    .map(|content| content.from_email.clone())
+                else {
+                    return Task::none();
+                };
+                if let Some(address) = mailledger_core::remote_content::sender_address(&sender) {
+                    self.trusted_senders.insert(address);
+                }
+                let save_task = Task::perform(trust_sender(sender), Message::SenderTrusted);
+                return Task::batch([load_inline_images(&mut self.inline_images), save_task]);
+            }
+            Message::TrustedSendersLoaded(result) => match result {
+                Ok(senders) => self.trusted_senders.extend(senders),
+                Err(e) => tracing::warn!("Failed to load trusted senders: {}", e),
+            },
+            Message::SenderTrusted(result) => {
+                if let Err(e) = result {
+                    self.error_message = Some(format!("Failed to trust sender: {e}"));
                 }
-                return cache_task;
             }
             Message::InlineImageLoaded { url, result } => {
                 if let Some(entry) = self.inline_images.iter_mut().find(|img| img.url == url) {
@@ -572,11 +803,36 @@ impl MailLedger {
             }
             Message::DeleteMessage(message_id) => {
                 self.messages.retain(|m| m.id != message_id);
+                self.all_messages.retain(|m| m.id != message_id);
                 if self.selected_message == Some(message_id) {
                     self.selected_message = None;
                     self.message_content = None;
                 }
+
+                if let Some(account) = self.current_account.clone()
+                    && let Some(folder_id) = self.selected_folder
+                    && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+                {
+                    return Task::perform(
+                        delete_message(account, folder_path, message_id.0),
+                        Message::MessageDeleted,
+                    );
+                }
             }
+            Message::MessageDeleted(result) => match result {
+                // Exactly our message went; the list already reflects that
+                Ok(1) => {}
+                Ok(expunged) => {
+                    // A plain EXPUNGE also removed messages other clients
+                    // deleted, or ours was already gone: resync
+                    info!("Expunge removed {} messages, refreshing", expunged);
+                    return Task::done(Message::RefreshMessages);
+                }
+                Err(e) => {
+                    self.error_message = Some(format!("Failed to delete message: {e}"));
+                    return Task::done(Message::RefreshMessages);
+                }
+            },
             Message::DeleteSelected => {
                 if let Some(message_id) = self.selected_message {
                     self.messages.retain(|m| m.id != message_id);
@@ -627,6 +883,60 @@ impl MailLedger {
                     return Task::done(Message::RefreshMessages);
                 }
             }
+            Message::MoveToFolder {
+                message_id,
+                folder_id,
+            } => {
+                if let Some(account) = self.current_account.clone()
+                    && let Some(source_id) = self.selected_folder
+                    && source_id != folder_id
+                    && let Some(folder_path) = self.folder_paths.get(&source_id).cloned()
+                    && let Some(destination) = self.folder_paths.get(&folder_id).cloned()
+                {
+                    // Remove from local list immediately for snappy UI
+                    self.messages.retain(|m| m.id != message_id);
+                    self.all_messages.retain(|m| m.id != message_id);
+                    self.selected_messages.remove(&message_id);
+                    if self.selected_message == Some(message_id) {
+                        self.selected_message = None;
+                        self.message_content = None;
+                    }
+
+                    return Task::perform(
+                        move_message_to(account, folder_path, message_id.0, destination),
+                        Message::MessageMoved,
+                    );
+                }
+            }
+            Message::MessageMoved(result) => {
+                if let Err(e) = result {
+                    self.error_message = Some(format!("Failed to move message: {e}"));
+                    // Refresh to restore the message if the move failed
+                    return Task::done(Message::RefreshMessages);
+                }
+            }
+            Message::ToggleMessageSelection(message_id) => {
+                if !self.selected_messages.remove(&message_id) {
+                    self.selected_messages.insert(message_id);
+                }
+            }
+            Message::ArchiveSelection => {
+                return self.move_selection(FolderType::Archive, "Archive");
+            }
+            Message::DeleteSelection => {
+                return self.move_selection(FolderType::Trash, "Trash");
+            }
+            Message::MarkSelectionRead => return self.mark_selection_read(),
+            Message::SelectionActionDone(result) => {
+                if let Err(e) = result {
+                    self.error_message = Some(format!("Failed to update selected messages: {e}"));
+                    // Refresh to restore the list if the action failed
+                    return Task::done(Message::RefreshMessages);
+                }
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+            }
             Message::DownloadAttachment {
                 message_id,
                 part_number,
@@ -691,6 +1001,31 @@ impl MailLedger {
                     self.error_message = Some(format!("Failed to download attachment: {e}"));
                 }
             },
+            Message::SaveAsEml => {
+                if let Some(account) = self.current_account.clone()
+                    && let Some(folder_id) = self.selected_folder
+                    && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+                    && let Some(content) = self.message_content.as_ref()
+                {
+                    return Task::perform(
+                        save_eml_task(account, folder_path, content.id.0, content.subject.clone()),
+                        Message::AttachmentDownloaded,
+                    );
+                }
+            }
+            Message::EmlDropped(path) => {
+                return Task::perform(load_eml(path), Message::EmlLoaded);
+            }
+            Message::EmlLoaded(result) => match result {
+                Ok((subject, from, body)) => {
+                    self.compose_state = ComposeState::forward(&subject, &body, &from);
+                    self.compose_body = text_editor::Content::with_text(&self.compose_state.body);
+                    self.current_view = View::Compose;
+                }
+                Err(e) => {
+                    self.error_message = Some(format!("Failed to open .eml file: {e}"));
+                }
+            },
             Message::ComposeNew => {
                 self.compose_state = ComposeState::new();
                 self.compose_body = text_editor::Content::new();
@@ -711,6 +1046,61 @@ impl MailLedger {
                     self.error_message = Some(format!("Failed to open HTML: {err}"));
                 }
             }
+            Message::Unsubscribe => {
+                if let Some(info) = self
+                    .message_content
+                    .as_ref()
+                    .and_then(|content| content.unsubscribe.clone())
+                {
+                    if info.one_click || info.mailto.is_some() {
+                        if let Some(account) = self.current_account.clone() {
+                            return Task::perform(
+                                unsubscribe_task(account, info),
+                                Message::Unsubscribed,
+                            );
+                        }
+                    } else if let Some(url) = &info.http
+                        && let Err(err) = opener::open(url.as_str())
+                    {
+                        self.error_message = Some(format!("Failed to open link: {err}"));
+                    }
+                }
+            }
+            Message::Unsubscribed(result) => match result {
+                Ok(()) => {
+                    info!("Unsubscribed from mailing list");
+                    if let Some(content) = self.message_content.as_mut() {
+                        content.unsubscribe = None;
+                    }
+                }
+                Err(e) => {
+                    self.error_message = Some(format!("Failed to unsubscribe: {e}"));
+                }
+            },
+            Message::SendReadReceipt => {
+                if let Some(request) = self
+                    .message_content
+                    .as_mut()
+                    .and_then(|content| content.read_receipt_request.take())
+                    && let Some(account) = self.current_account.clone()
+                {
+                    return Task::perform(
+                        send_read_receipt_task(account, request, mailledger_mime::MdnMode::Manual),
+                        Message::ReadReceiptSent,
+                    );
+                }
+            }
+            Message::DismissReadReceipt => {
+                if let Some(content) = self.message_content.as_mut() {
+                    content.read_receipt_request = None;
+                }
+            }
+            Message::ReadReceiptSent(result) => match result {
+                Ok(()) => info!("Read receipt sent"),
+                Err(e) => {
+                    self.error_message = Some(format!("Failed to send read receipt: {e}"));
+                }
+            },
             Message::LinkClicked(url) => {
                 // Open links in the default browser
                 if let Err(err) = opener::open(url.as_str()) {
@@ -767,9 +1157,10 @@ impl MailLedger {
             Message::EmailSent(result) => {
                 self.compose_state.is_sending = false;
                 match result {
-                    Ok(()) => {
-                        info!("Email sent successfully");
+                    Ok(outcome) => {
+                        info!("Email {outcome:?}");
                         self.compose_state.send_success = true;
+                        self.compose_state.send_queued = outcome == SendOutcome::Queued;
                         self.compose_state.send_error = None;
 
                         // Record recipients as contacts for future autocomplete
@@ -796,6 +1187,28 @@ impl MailLedger {
                     }
                 }
             }
+            Message::FlushOutbox => return self.start_outbox_flush(),
+            Message::OutboxFlushed(result) => {
+                self.is_flushing_outbox = false;
+                match result {
+                    Ok(summary) if summary.failed > 0 => {
+                        self.error_message = Some(format!(
+                            "{} queued message(s) could not be sent",
+                            summary.failed
+                        ));
+                    }
+                    Ok(summary) if summary.sent > 0 || summary.retrying > 0 => {
+                        info!(
+                            "Outbox flushed: {} sent, {} retrying",
+                            summary.sent, summary.retrying
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        self.error_message = Some(format!("Failed to send queued messages: {e}"));
+                    }
+                }
+            }
             Message::Settings(msg) => {
                 return self.handle_settings(msg);
             }
@@ -951,6 +1364,8 @@ impl MailLedger {
                     self.theme_mode = settings.theme_mode;
                     self.font_size = settings.font_size;
                     self.list_density = settings.list_density;
+                    self.read_receipts = settings.read_receipts;
+                    self.load_remote_images = settings.load_remote_images;
                     self.apply_theme();
                 }
                 Err(e) => {
@@ -988,7 +1403,7 @@ impl MailLedger {
                                 let folder_id = inbox.id;
                                 self.is_loading_messages = true;
                                 return Task::perform(
-                                    load_messages(account, folder_path, folder_id),
+                                    load_messages(account, folder_path, folder_id, true),
                                     Message::MessagesLoaded,
                                 );
                             }
@@ -1003,8 +1418,19 @@ impl MailLedger {
             Message::MessagesLoaded(result) => {
                 self.is_loading_messages = false;
                 match result {
-                    Ok(messages) => {
-                        self.is_offline = false; // We're online if fetch succeeded
+                    Ok((messages, highest_mod_seq)) => {
+                        self.highest_mod_seq = highest_mod_seq;
+                        if let Some(folder_id) = self.selected_folder
+                            && let Some(last_uid) = messages.iter().map(|msg| msg.id.0).max()
+                        {
+                            self.last_seen_uid.insert(folder_id, last_uid);
+                        }
+                        // We're online if fetch succeeded
+                        let reconnected = if std::mem::replace(&mut self.is_offline, false) {
+                            Task::done(Message::ConnectionStateChanged(true))
+                        } else {
+                            Task::none()
+                        };
                         self.selected_message = None;
                         self.message_content = None;
 
@@ -1024,13 +1450,11 @@ impl MailLedger {
                                 cache_messages(account_id, folder_path, messages_for_cache),
                                 |_| Message::WindowResized(0, 0), // Ignore result
                             );
-                            // Start IDLE after caching
-                            return Task::batch([cache_task, Task::done(Message::StartIdle)]);
+                            return Task::batch([cache_task, reconnected]);
                         }
                         self.all_messages = messages;
                         self.filter_messages();
-                        // Start IDLE monitoring after messages are loaded
-                        return Task::done(Message::StartIdle);
+                        return reconnected;
                     }
                     Err(e) => {
                         // Connection failed - try loading from cache
@@ -1051,74 +1475,58 @@ impl MailLedger {
                     }
                 }
             }
-            Message::StartIdle => {
-                // Start IDLE monitoring on the current folder
-                if let Some(account) = self.current_account.clone()
-                    && let Some(folder_id) = self.selected_folder
-                    && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
-                    && !self.is_idle_active
-                {
-                    self.is_idle_active = true;
-                    info!("Starting IDLE monitoring on {}", folder_path);
-                    return Task::perform(start_idle(account, folder_path), Message::IdleReceived);
+            Message::IdleEvent(event) => {
+                info!("IDLE event received: {:?}", event);
+                match event {
+                    mailledger_core::IdleEvent::NewMail(count) => {
+                        // Show desktop notification for new mail
+                        show_new_mail_notification(count);
+                        return self.fetch_new_mail();
+                    }
+                    mailledger_core::IdleEvent::FlagsChanged => return self.refresh_flags(),
+                    mailledger_core::IdleEvent::Expunge => return self.refresh_changes(),
+                    mailledger_core::IdleEvent::Timeout => {}
+                    mailledger_core::IdleEvent::Disconnected(reason) => {
+                        // The subscription reconnects on its own
+                        info!("IDLE disconnected: {}", reason);
+                    }
                 }
             }
-            Message::IdleReceived(result) => {
-                self.is_idle_active = false;
-                match result {
-                    Ok(event) => {
-                        info!("IDLE event received: {:?}", event);
-                        match event {
-                            mailledger_core::IdleEvent::NewMail(count) => {
-                                // Show desktop notification for new mail
-                                show_new_mail_notification(count);
-
-                                // New mail arrived - refresh messages
-                                if let Some(account) = self.current_account.clone()
-                                    && let Some(folder_id) = self.selected_folder
-                                    && let Some(folder_path) =
-                                        self.folder_paths.get(&folder_id).cloned()
-                                {
-                                    self.is_loading_messages = true;
-                                    return Task::perform(
-                                        load_messages(account, folder_path, folder_id),
-                                        Message::MessagesLoaded,
-                                    );
-                                }
-                            }
-                            mailledger_core::IdleEvent::Expunge
-                            | mailledger_core::IdleEvent::FlagsChanged => {
-                                // Refresh messages on expunge or flag change
-                                if let Some(account) = self.current_account.clone()
-                                    && let Some(folder_id) = self.selected_folder
-                                    && let Some(folder_path) =
-                                        self.folder_paths.get(&folder_id).cloned()
-                                {
-                                    self.is_loading_messages = true;
-                                    return Task::perform(
-                                        load_messages(account, folder_path, folder_id),
-                                        Message::MessagesLoaded,
-                                    );
-                                }
-                            }
-                            mailledger_core::IdleEvent::Timeout
-                            | mailledger_core::IdleEvent::Disconnected(_) => {
-                                // Restart IDLE on timeout or disconnect
-                                return Task::done(Message::StartIdle);
-                            }
+            Message::NewMessagesLoaded(result) => match result {
+                Ok(new_messages) => self.append_messages(new_messages),
+                Err(e) => {
+                    tracing::warn!("Fetching new mail failed, reloading: {}", e);
+                    return self.reload_messages();
+                }
+            },
+            Message::FlagsUpdated(result) => match result {
+                Ok(updates) => {
+                    for (id, flags) in updates {
+                        if let Some(msg) = self.all_messages.iter_mut().find(|m| m.id == id) {
+                            msg.is_read = flags.is_seen();
+                            msg.is_flagged = flags.is_flagged();
                         }
                     }
-                    Err(e) => {
-                        // Log error but try to restart IDLE
-                        info!("IDLE error: {}, restarting...", e);
-                        return Task::done(Message::StartIdle);
-                    }
+                    self.filter_messages();
                 }
-            }
+                Err(e) => {
+                    tracing::warn!("Fetching flags failed, reloading: {}", e);
+                    return self.reload_messages();
+                }
+            },
+            Message::ChangesLoaded(result) => match result {
+                Ok(changes) => self.apply_changes(changes),
+                Err(e) => {
+                    tracing::warn!("Incremental refresh failed, reloading: {}", e);
+                    return self.reload_messages();
+                }
+            },
             Message::ConnectionStateChanged(is_online) => {
                 self.is_offline = !is_online;
                 if is_online {
                     info!("Connection restored - now online");
+                    // Deliver anything queued while offline
+                    return self.start_outbox_flush();
                 } else {
                     info!("Connection lost - now offline");
                 }
@@ -1147,6 +1555,8 @@ impl MailLedger {
                                 thread_id: None,
                                 message_id: None,
                                 in_reply_to: None,
+                                triage: None,
+                                receipt: None,
                             })
                             .collect();
                         self.all_messages = self.messages.clone();
@@ -1163,6 +1573,11 @@ impl MailLedger {
                     Ok(Some(cached)) => {
                         // Parse from string to get name and email
                         let (from_name, from_email) = parse_email_address(&cached.from);
+                        let attachments = cached
+                            .attachments()
+                            .iter()
+                            .map(model::Attachment::from_core)
+                            .collect();
 
                         // Convert cached content to MessageContent
                         self.message_content = Some(MessageContent {
@@ -1175,7 +1590,9 @@ impl MailLedger {
                             date: cached.date,
                             body_text: cached.body_text,
                             body_html: cached.body_html,
-                            attachments: Vec::new(), // TODO: deserialize from JSON
+                            attachments,
+                            unsubscribe: None,
+                            read_receipt_request: None,
                         });
                         // Parse markdown for text body
                         if let Some(ref content) = self.message_content {
@@ -1270,6 +1687,87 @@ impl MailLedger {
 
     /// Handle keyboard shortcut actions.
     #[allow(clippy::too_many_lines)]
+    /// Adds every message between the focused one and `message_id` to the
+    /// bulk selection.
+    fn extend_selection_to(&mut self, message_id: MessageId) {
+        let position = |id: MessageId| self.messages.iter().position(|m| m.id == id);
+        let (Some(anchor), Some(target)) = (
+            self.selected_message.and_then(position),
+            position(message_id),
+        ) else {
+            self.selected_messages.insert(message_id);
+            return;
+        };
+        let (start, end) = (anchor.min(target), anchor.max(target));
+        self.selected_messages
+            .extend(self.messages[start..=end].iter().map(|m| m.id));
+    }
+
+    /// Account, folder path and sorted UIDs of the bulk selection.
+    fn selection_target(&self) -> Option<(mailledger_core::Account, String, Vec<u32>)> {
+        if self.selected_messages.is_empty() {
+            return None;
+        }
+        let account = self.current_account.clone()?;
+        let folder_path = self.folder_paths.get(&self.selected_folder?)?.clone();
+        let mut uids: Vec<u32> = self.selected_messages.iter().map(|id| id.0).collect();
+        uids.sort_unstable();
+        Some((account, folder_path, uids))
+    }
+
+    /// Moves the bulk selection to the folder of `folder_type` with one command.
+    fn move_selection(&mut self, folder_type: FolderType, folder_name: &str) -> Task<Message> {
+        let Some(destination) = self
+            .folders
+            .iter()
+            .find(|f| f.folder_type == folder_type)
+            .map(|f| f.path.clone())
+        else {
+            self.error_message = Some(format!("No {folder_name} folder found"));
+            return Task::none();
+        };
+        let Some((account, folder_path, uids)) = self.selection_target() else {
+            return Task::none();
+        };
+
+        // Remove from local list immediately for snappy UI
+        let selection = std::mem::take(&mut self.selected_messages);
+        self.messages.retain(|m| !selection.contains(&m.id));
+        self.all_messages.retain(|m| !selection.contains(&m.id));
+        if self
+            .selected_message
+            .is_some_and(|id| selection.contains(&id))
+        {
+            self.selected_message = None;
+            self.message_content = None;
+        }
+
+        let delete = folder_type == FolderType::Trash;
+        Task::perform(
+            move_selected_messages(account, folder_path, uids, destination, delete),
+            Message::SelectionActionDone,
+        )
+    }
+
+    /// Marks the bulk selection as read with one command.
+    fn mark_selection_read(&mut self) -> Task<Message> {
+        let Some((account, folder_path, uids)) = self.selection_target() else {
+            return Task::none();
+        };
+
+        let selection = std::mem::take(&mut self.selected_messages);
+        for msg in self.messages.iter_mut().chain(self.all_messages.iter_mut()) {
+            if selection.contains(&msg.id) {
+                msg.is_read = true;
+            }
+        }
+
+        Task::perform(
+            mark_selected_read(account, folder_path, uids),
+            Message::SelectionActionDone,
+        )
+    }
+
     fn handle_keyboard_action(&mut self, action: KeyboardAction) -> Task<Message> {
         match action {
             KeyboardAction::ComposeNew => {
@@ -1293,6 +1791,9 @@ impl MailLedger {
                 }
             }
             KeyboardAction::Delete => {
+                if self.current_view == View::Inbox && !self.selected_messages.is_empty() {
+                    return Task::done(Message::DeleteSelection);
+                }
                 if self.current_view == View::Inbox
                     && let Some(message_id) = self.selected_message
                 {
@@ -1300,12 +1801,22 @@ impl MailLedger {
                 }
             }
             KeyboardAction::Archive => {
+                if self.current_view == View::Inbox && !self.selected_messages.is_empty() {
+                    return Task::done(Message::ArchiveSelection);
+                }
                 if self.current_view == View::Inbox
                     && let Some(message_id) = self.selected_message
                 {
                     return Task::done(Message::ArchiveMessage(message_id));
                 }
             }
+            KeyboardAction::ToggleSelection => {
+                if self.current_view == View::Inbox
+                    && let Some(message_id) = self.selected_message
+                {
+                    return Task::done(Message::ToggleMessageSelection(message_id));
+                }
+            }
             KeyboardAction::ToggleStar => {
                 if self.current_view == View::Inbox
                     && let Some(message_id) = self.selected_message
@@ -1346,6 +1857,7 @@ impl MailLedger {
                 }
                 View::Inbox => {
                     // Clear selection
+                    self.selected_messages.clear();
                     self.selected_message = None;
                     self.message_content = None;
                 }
@@ -1414,8 +1926,25 @@ impl MailLedger {
             AccountSetupMessage::EmailChanged(email) => {
                 self.account_setup.email.clone_from(&email);
                 // Auto-detect settings when email changes
-                if email.contains('@') {
-                    self.account_setup.auto_detect_from_email();
+                if email.contains('@')
+                    && let Some(domain) = self.account_setup.auto_detect_from_email()
+                {
+                    return Task::perform(
+                        async move { mailledger_core::discover(&domain).await },
+                        move |config| {
+                            Message::AccountSetup(AccountSetupMessage::SettingsDiscovered(
+                                email, config,
+                            ))
+                        },
+                    );
+                }
+            }
+            AccountSetupMessage::SettingsDiscovered(email, config) => {
+                // Ignore lookups for addresses the user has since edited.
+                if email == self.account_setup.email
+                    && let Some(config) = config
+                {
+                    self.account_setup.apply_discovered(&config);
                 }
             }
             AccountSetupMessage::ImapHostChanged(host) => {
@@ -1448,6 +1977,11 @@ impl MailLedger {
             AccountSetupMessage::SmtpPasswordChanged(password) => {
                 self.account_setup.smtp_password = password;
             }
+            msg @ (AccountSetupMessage::OAuthClientIdChanged(_)
+            | AccountSetupMessage::SignIn(_)
+            | AccountSetupMessage::SignInStarted(_)
+            | AccountSetupMessage::SignedIn(_)
+            | AccountSetupMessage::SignOut) => return self.handle_sign_in(msg),
             AccountSetupMessage::Save => {
                 if self.account_setup.validate() {
                     self.account_setup.is_saving = true;
@@ -1471,6 +2005,59 @@ impl MailLedger {
         Task::none()
     }
 
+    /// Handle `OAuth2` sign-in during account setup.
+    fn handle_sign_in(&mut self, msg: AccountSetupMessage) -> Task<Message> {
+        let setup = &mut self.account_setup;
+        match msg {
+            AccountSetupMessage::OAuthClientIdChanged(client_id) => {
+                setup.oauth_client_id = client_id;
+            }
+            AccountSetupMessage::SignIn(provider) => {
+                if setup.oauth_client_id.trim().is_empty() {
+                    setup.save_error = Some("Enter the OAuth client ID first".to_string());
+                    return Task::none();
+                }
+                setup.is_signing_in = true;
+                setup.save_error = None;
+                let client_id = setup.oauth_client_id.trim().to_string();
+                return Task::perform(
+                    async move {
+                        mailledger_core::start_device_sign_in(provider, &client_id)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    |result| Message::AccountSetup(AccountSetupMessage::SignInStarted(result)),
+                );
+            }
+            AccountSetupMessage::SignInStarted(Ok(sign_in)) => {
+                setup.device_sign_in = Some(sign_in.clone());
+                return Task::perform(
+                    async move {
+                        mailledger_core::finish_device_sign_in(&sign_in)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    |result| Message::AccountSetup(AccountSetupMessage::SignedIn(result)),
+                );
+            }
+            AccountSetupMessage::SignedIn(Ok(auth)) => {
+                setup.is_signing_in = false;
+                setup.device_sign_in = None;
+                setup.oauth = Some(auth);
+            }
+            AccountSetupMessage::SignInStarted(Err(e)) | AccountSetupMessage::SignedIn(Err(e)) => {
+                setup.is_signing_in = false;
+                setup.device_sign_in = None;
+                setup.save_error = Some(format!("Sign-in failed: {e}"));
+            }
+            AccountSetupMessage::SignOut => {
+                setup.oauth = None;
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
     /// Handle compose messages.
     fn handle_compose(&mut self, msg: ComposeMessage) -> Task<Message> {
         use message::FormattingStyle;
@@ -1479,25 +2066,30 @@ impl MailLedger {
             ComposeMessage::ToChanged(to) => {
                 let task = self.trigger_autocomplete(&to, AutocompleteField::To);
                 self.compose_state.to = to;
+                self.compose_state.draft_dirty = true;
                 return task;
             }
             ComposeMessage::CcChanged(cc) => {
                 let task = self.trigger_autocomplete(&cc, AutocompleteField::Cc);
                 self.compose_state.cc = cc;
+                self.compose_state.draft_dirty = true;
                 return task;
             }
             ComposeMessage::BccChanged(bcc) => {
                 let task = self.trigger_autocomplete(&bcc, AutocompleteField::Bcc);
                 self.compose_state.bcc = bcc;
+                self.compose_state.draft_dirty = true;
                 return task;
             }
             ComposeMessage::SubjectChanged(subject) => {
                 self.compose_state.subject = subject;
+                self.compose_state.draft_dirty = true;
             }
             ComposeMessage::BodyChanged(_) => {
                 // Legacy handler - body now uses text_editor
             }
             ComposeMessage::BodyAction(action) => {
+                self.compose_state.draft_dirty |= action.is_edit();
                 self.compose_body.perform(action);
             }
             ComposeMessage::InsertFormatting(style) => {
@@ -1508,6 +2100,7 @@ impl MailLedger {
                     FormattingStyle::Link => "[text](url)",
                 };
                 // Insert at cursor using Edit action
+                self.compose_state.draft_dirty = true;
                 self.compose_body
                     .perform(text_editor::Action::Edit(text_editor::Edit::Paste(
                         std::sync::Arc::new(insert_text.to_string()),
@@ -1520,17 +2113,41 @@ impl MailLedger {
                     self.compose_state.is_sending = true;
                     self.compose_state.send_error = None;
                     self.compose_state.send_success = false;
+                    self.compose_state.send_queued = false;
                     // Get body from text_editor Content
                     let body_text = self.compose_body.text();
                     let message = self
                         .compose_state
                         .to_outgoing_with_body(&account.email, &body_text);
-                    return Task::perform(send_email(account, message), Message::EmailSent);
+                    return Task::perform(
+                        send_email(account, message, self.is_offline),
+                        Message::EmailSent,
+                    );
                 } else {
                     self.compose_state.send_error =
                         Some("No account configured. Please set up an account first.".to_string());
                 }
             }
+            ComposeMessage::SaveDraft => return self.save_draft(),
+            ComposeMessage::AutosaveDraft => {
+                if self.compose_state.draft_dirty {
+                    return self.save_draft();
+                }
+            }
+            ComposeMessage::DraftSaved(result) => {
+                // Ignore results for a compose session that was closed meanwhile
+                if !self.compose_state.is_saving_draft {
+                    return Task::none();
+                }
+                self.compose_state.is_saving_draft = false;
+                match result {
+                    Ok(uid) => self.compose_state.draft_uid = Some(uid),
+                    Err(e) => {
+                        self.compose_state.draft_dirty = true;
+                        self.compose_state.send_error = Some(format!("Failed to save draft: {e}"));
+                    }
+                }
+            }
             ComposeMessage::Cancel => {
                 self.compose_state = ComposeState::new();
                 self.compose_body = text_editor::Content::new();
@@ -1544,6 +2161,7 @@ impl MailLedger {
             }
             ComposeMessage::SelectSuggestion(index) => {
                 self.compose_state.apply_suggestion(index);
+                self.compose_state.draft_dirty = true;
             }
             ComposeMessage::DismissSuggestions => {
                 self.compose_state.clear_suggestions();
@@ -1552,6 +2170,26 @@ impl MailLedger {
         Task::none()
     }
 
+    /// Saves the message being composed to the Drafts folder, replacing the
+    /// previously saved copy.
+    fn save_draft(&mut self) -> Task<Message> {
+        let Some(account) = self.current_account.clone() else {
+            return Task::none();
+        };
+        if self.compose_state.is_saving_draft {
+            return Task::none();
+        }
+        self.compose_state.is_saving_draft = true;
+        self.compose_state.draft_dirty = false;
+        let message = self
+            .compose_state
+            .to_outgoing_with_body(&account.email, &self.compose_body.text());
+        Task::perform(
+            save_draft(account, message, self.compose_state.draft_uid),
+            |result| Message::Compose(ComposeMessage::DraftSaved(result)),
+        )
+    }
+
     /// Handle screener messages.
     fn handle_screener(&mut self, msg: ScreenerMessage) -> Task<Message> {
         let Some(account_id) = self.current_account.as_ref().and_then(|a| a.id) else {
@@ -1633,16 +2271,83 @@ impl MailLedger {
                     Message::SettingsSaved,
                 );
             }
+            SettingsMessage::SetReadReceipts(policy) => {
+                self.read_receipts = policy;
+                info!("Read receipt policy changed to {:?}", self.read_receipts);
+                return Task::perform(
+                    save_settings(self.current_settings()),
+                    Message::SettingsSaved,
+                );
+            }
+            SettingsMessage::SetRemoteImages(policy) => {
+                self.load_remote_images = policy;
+                info!(
+                    "Remote image policy changed to {:?}",
+                    self.load_remote_images
+                );
+                return Task::perform(
+                    save_settings(self.current_settings()),
+                    Message::SettingsSaved,
+                );
+            }
+        }
+        Task::none()
+    }
+
+    /// Applies the read receipt policy to freshly loaded content.
+    ///
+    /// Only unread messages are answered, so reopening a message never
+    /// sends a second receipt. With [`ReadReceiptPolicy::Ask`] the request is
+    /// left on the content for the message view to prompt about, as it is
+    /// with [`ReadReceiptPolicy::Always`] when the receipt would go somewhere
+    /// other than the message's `Return-Path`.
+    fn answer_read_receipt_request(&self, content: &mut MessageContent) -> Task<Message> {
+        let was_unread = self
+            .messages
+            .iter()
+            .find(|m| m.id == content.id)
+            .is_some_and(|m| !m.is_read);
+        if !was_unread || self.read_receipts == ReadReceiptPolicy::Never {
+            content.read_receipt_request = None;
+        }
+
+        if self.read_receipts == ReadReceiptPolicy::Always
+            && content
+                .read_receipt_request
+                .as_ref()
+                .is_some_and(mailledger_core::read_receipt_matches_return_path)
+            && let Some(request) = content.read_receipt_request.take()
+            && let Some(account) = self.current_account.clone()
+        {
+            return Task::perform(
+                send_read_receipt_task(account, request, mailledger_mime::MdnMode::Automatic),
+                Message::ReadReceiptSent,
+            );
         }
         Task::none()
     }
 
+    /// Sends the queued messages that are due, unless offline or a flush is
+    /// already running.
+    fn start_outbox_flush(&mut self) -> Task<Message> {
+        if self.is_offline || self.is_flushing_outbox {
+            return Task::none();
+        }
+        let Some(account) = self.current_account.clone() else {
+            return Task::none();
+        };
+        self.is_flushing_outbox = true;
+        Task::perform(flush_outbox(account), Message::OutboxFlushed)
+    }
+
     /// Creates an `AppSettings` from current state.
     const fn current_settings(&self) -> AppSettings {
         AppSettings {
             theme_mode: self.theme_mode,
             font_size: self.font_size,
             list_density: self.list_density,
+            read_receipts: self.read_receipts,
+            load_remote_images: self.load_remote_images,
         }
     }
 
@@ -1752,10 +2457,11 @@ impl MailLedger {
             ));
         }
 
-        // Message list
-        main_content = main_content.push(view::view_message_list(
+        // Message list, with triage tabs when the inbox has been triaged
+        let message_list = view::view_message_list(
             &self.messages,
             self.selected_message,
+            &self.selected_messages,
             self.is_loading_messages,
             self.view_mode,
             &self.threads,
@@ -1765,7 +2471,18 @@ impl MailLedger {
             self.message_list_scroll_offset,
             self.message_list_viewport_height,
             self.message_list_width,
-        ));
+        );
+        if self.all_messages.iter().any(|msg| msg.triage.is_some()) {
+            main_content = main_content.push(
+                column![
+                    view::view_triage_tabs(&self.all_messages, self.triage_tab),
+                    message_list
+                ]
+                .width(Length::Fixed(self.message_list_width)),
+            );
+        } else {
+            main_content = main_content.push(message_list);
+        }
 
         // Divider between message list and message view
         main_content = main_content.push(view::view_pane_divider(
@@ -1807,6 +2524,8 @@ impl MailLedger {
             self.theme_mode,
             self.font_size,
             self.list_density,
+            self.read_receipts,
+            self.load_remote_images,
         )
     }
 
@@ -1820,7 +2539,7 @@ impl MailLedger {
         let is_dragging = self.dragging_divider.is_some();
 
         // Listen to all events when dragging for smooth tracking
-        if is_dragging {
+        let input = if is_dragging {
             event::listen().map(|event| match event {
                 Event::Mouse(mouse::Event::CursorMoved { position }) => {
                     Message::PaneDragMoved(position.x)
@@ -1831,17 +2550,87 @@ impl MailLedger {
                 Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
                     handle_key_press(key, modifiers).unwrap_or(Message::WindowResized(0, 0))
                 }
+                Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                    Message::ModifiersChanged(modifiers)
+                }
                 _ => Message::WindowResized(0, 0),
             })
         } else {
-            keyboard::listen().map(|event| {
-                if let keyboard::Event::KeyPressed { key, modifiers, .. } = event {
+            keyboard::listen().map(|event| match event {
+                keyboard::Event::KeyPressed { key, modifiers, .. } => {
                     handle_key_press(key, modifiers).unwrap_or(Message::WindowResized(0, 0))
-                } else {
-                    Message::WindowResized(0, 0)
                 }
+                keyboard::Event::ModifiersChanged(modifiers) => {
+                    Message::ModifiersChanged(modifiers)
+                }
+                _ => Message::WindowResized(0, 0),
             })
+        };
+
+        let file_drop = event::listen_with(|event, _status, _window| match event {
+            Event::Window(window::Event::FileDropped(path))
+                if path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("eml")) =>
+            {
+                Some(Message::EmlDropped(path))
+            }
+            _ => None,
+        });
+
+        let mut subscriptions = vec![input, file_drop];
+
+        // One persistent IDLE session on the open folder
+        if let Some(account) = self.current_account.clone()
+            && let Some(folder_id) = self.selected_folder
+            && let Some(folder_path) = self.folder_paths.get(&folder_id).cloned()
+        {
+            let target = IdleTarget {
+                account,
+                folder_path,
+            };
+            subscriptions.push(
+                Subscription::run_with(target, |target| {
+                    mailledger_core::idle_subscription(
+                        target.account.clone(),
+                        target.folder_path.clone(),
+                    )
+                })
+                .map(Message::IdleEvent),
+            );
+        }
+
+        // Retry queued messages, including those whose send failed while
+        // online
+        if self.current_account.is_some() && !self.is_offline {
+            subscriptions
+                .push(iced::time::every(OUTBOX_FLUSH_INTERVAL).map(|_| Message::FlushOutbox));
         }
+
+        if self.current_view == View::Compose {
+            subscriptions.push(
+                iced::time::every(DRAFT_AUTOSAVE_INTERVAL)
+                    .map(|_| Message::Compose(ComposeMessage::AutosaveDraft)),
+            );
+        }
+        Subscription::batch(subscriptions)
+    }
+}
+
+/// Account and folder watched by the IDLE subscription.
+///
+/// Hashed by identity only, so the session is restarted when either changes
+/// but not when unrelated account settings are edited.
+struct IdleTarget {
+    account: mailledger_core::Account,
+    folder_path: String,
+}
+
+impl std::hash::Hash for IdleTarget {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.account.id.hash(state);
+        self.account.email.hash(state);
+        self.folder_path.hash(state);
     }
 }
 
@@ -1891,6 +2680,10 @@ fn handle_key_press(key: Key, modifiers: Modifiers) -> Option<Message> {
         Key::Character(c) if !ctrl && !shift && c.as_str() == "s" => {
             Some(Message::KeyPressed(KeyboardAction::ToggleStar))
         }
+        // x: Toggle bulk selection (Gmail style)
+        Key::Character(c) if !ctrl && !shift && c.as_str() == "x" => {
+            Some(Message::KeyPressed(KeyboardAction::ToggleSelection))
+        }
         // u: Mark unread (Gmail style)
         Key::Character(c) if !ctrl && !shift && c.as_str() == "u" => {
             Some(Message::KeyPressed(KeyboardAction::MarkUnread))
@@ -1931,10 +2724,11 @@ fn handle_key_press(key: Key, modifiers: Modifiers) -> Option<Message> {
     }
 }
 
-fn prepare_inline_images(urls: Vec<String>) -> (Vec<InlineImage>, Task<Message>) {
+/// Collects the remote images of a message, downloading them only if `load`
+/// is set; otherwise they stay blocked until the user asks for them.
+fn prepare_inline_images(urls: Vec<String>, load: bool) -> (Vec<InlineImage>, Task<Message>) {
     let mut seen = HashSet::new();
     let mut inline_images = Vec::new();
-    let mut tasks = Vec::new();
 
     for url in urls {
         if !seen.insert(url.clone()) {
@@ -1943,14 +2737,9 @@ fn prepare_inline_images(urls: Vec<String>) -> (Vec<InlineImage>, Task<Message>)
 
         if url.starts_with("http://") || url.starts_with("https://") {
             inline_images.push(InlineImage {
-                url: url.clone(),
-                state: InlineImageState::Loading,
+                url,
+                state: InlineImageState::Blocked,
             });
-
-            tasks.push(Task::perform(
-                download_inline_image(url.clone()),
-                move |result| Message::InlineImageLoaded { url, result },
-            ));
         }
 
         if inline_images.len() >= 10 {
@@ -1958,15 +2747,38 @@ fn prepare_inline_images(urls: Vec<String>) -> (Vec<InlineImage>, Task<Message>)
         }
     }
 
-    let task = if tasks.is_empty() {
-        Task::none()
+    let task = if load {
+        load_inline_images(&mut inline_images)
     } else {
-        Task::batch(tasks)
+        Task::none()
     };
 
     (inline_images, task)
 }
 
+/// Starts downloading every blocked image.
+fn load_inline_images(inline_images: &mut [InlineImage]) -> Task<Message> {
+    let mut tasks = Vec::new();
+
+    for entry in inline_images
+        .iter_mut()
+        .filter(|img| matches!(img.state, InlineImageState::Blocked))
+    {
+        entry.state = InlineImageState::Loading;
+        let url = entry.url.clone();
+        tasks.push(Task::perform(
+            download_inline_image(url.clone()),
+            move |result| Message::InlineImageLoaded { url, result },
+        ));
+    }
+
+    if tasks.is_empty() {
+        Task::none()
+    } else {
+        Task::batch(tasks)
+    }
+}
+
 /// Convert message content to markdown text.
 ///
 /// Priority: HTML (converted via htmd) > plain text > empty message.
@@ -2337,22 +3149,22 @@ async fn execute_search(
     folder_path: String,
     criteria: mailledger_core::SearchCriteria,
 ) -> Result<Vec<mailledger_imap::Uid>, String> {
-    use mailledger_core::{connect_and_login, search_messages, select_folder};
-
-    // Connect and login
-    let client = connect_and_login(&account)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Select the folder (takes ownership of client)
-    let (mut selected, _status) = select_folder(client, &folder_path)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Execute search
-    let uids = search_messages(&mut selected, &criteria)
-        .await
-        .map_err(|e| e.to_string())?;
+    use mailledger_core::{MailServiceError, RetryPolicy, search_messages, with_backoff};
+
+    // Reuse the pooled session, selecting the folder. A session that fails
+    // is dropped rather than released, so a retry reconnects.
+    let (account, folder_path, criteria) = (&account, folder_path.as_str(), &criteria);
+    let uids = with_backoff(
+        || async move {
+            let (mut selected, _status) = IMAP_POOL.selected(account, folder_path).await?;
+            let uids = search_messages(&mut selected, criteria).await?;
+            IMAP_POOL.release(account, selected);
+            Ok::<_, MailServiceError>(uids)
+        },
+        &RetryPolicy::default(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
 
     tracing::debug!("IMAP search returned {} UIDs", uids.len());
     Ok(uids)
@@ -2382,36 +3194,16 @@ async fn save_account(account: mailledger_core::Account) -> Result<(), String> {
     Ok(())
 }
 
-/// Test IMAP connection.
-async fn test_connection(account: mailledger_core::Account) -> Result<(), String> {
-    use mailledger_imap::connection::{Security, connect_tls};
-
-    let security = match account.imap.security {
-        mailledger_core::Security::Tls => Security::Implicit,
-        mailledger_core::Security::StartTls => Security::StartTls,
-        mailledger_core::Security::None => Security::None,
-    };
-
-    // For now, only support implicit TLS
-    if security != Security::Implicit {
-        return Err("Only SSL/TLS connections are currently supported".to_string());
-    }
-
-    let stream = connect_tls(&account.imap.host, account.imap.port)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let client = mailledger_imap::connection::Client::from_stream(stream)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let _auth_client = client
-        .login(&account.imap.username, &account.imap.password)
+/// Test IMAP connection and find out what the server supports.
+async fn test_connection(
+    account: mailledger_core::Account,
+) -> Result<mailledger_core::ConnectionProbe, String> {
+    let probe = mailledger_core::probe_connection(&account)
         .await
         .map_err(|e| e.to_string())?;
 
     tracing::info!("Connection test successful for {}", account.email);
-    Ok(())
+    Ok(probe)
 }
 
 /// Load default account from database.
@@ -2473,13 +3265,28 @@ async fn load_all_accounts() -> Result<Vec<mailledger_core::Account>, String> {
 
 /// Load folders from IMAP server.
 async fn load_folders(account: mailledger_core::Account) -> Result<Vec<Folder>, String> {
-    use mailledger_core::{connect_and_login, list_folders};
+    use mailledger_core::{folder_counts, list_folders};
 
-    let mut client = connect_and_login(&account)
+    let mut client = IMAP_POOL
+        .authenticated(&account)
         .await
         .map_err(|e| e.to_string())?;
 
-    let core_folders = list_folders(&mut client).await.map_err(|e| e.to_string())?;
+    let mut core_folders = list_folders(&mut client).await.map_err(|e| e.to_string())?;
+
+    // Unread badges are nice to have; show the folders even if STATUS fails
+    match folder_counts(&mut client, &core_folders).await {
+        Ok(counts) => {
+            for folder in &mut core_folders {
+                if let Some(c) = counts.get(&folder.path) {
+                    folder.unread_count = Some(c.unread);
+                    folder.total_count = Some(c.total);
+                }
+            }
+            IMAP_POOL.release_authenticated(&account, client);
+        }
+        Err(e) => tracing::warn!("Failed to load folder counts: {}", e),
+    }
 
     // Convert core folders to GUI folders with sequential IDs
     let folders: Vec<Folder> = core_folders
@@ -2497,84 +3304,427 @@ async fn load_folders(account: mailledger_core::Account) -> Result<Vec<Folder>,
 }
 
 /// Load messages from a folder.
+///
+/// With `read_only` the folder is opened with EXAMINE, so browsing leaves
+/// its `\Recent` flags for other clients.
 async fn load_messages(
     account: mailledger_core::Account,
     folder_path: String,
     folder_id: FolderId,
+    read_only: bool,
+) -> Result<(Vec<MessageSummary>, Option<u64>), String> {
+    use mailledger_core::{
+        MailServiceError, RetryPolicy, fetch_messages_by_uid, search_messages_matching,
+        with_backoff,
+    };
+
+    // A session that fails is dropped rather than released, so a retry
+    // reconnects and opens the folder again
+    let (pooled_account, pooled_path) = (&account, folder_path.as_str());
+    let (mut core_messages, highest_mod_seq) = with_backoff(
+        || async move {
+            let (mut selected_client, status) = if read_only {
+                IMAP_POOL.examined(pooled_account, pooled_path).await?
+            } else {
+                IMAP_POOL.selected(pooled_account, pooled_path).await?
+            };
+
+            if status.exists == 0 {
+                IMAP_POOL.release(pooled_account, selected_client);
+                return Ok((Vec::new(), status.highest_mod_seq));
+            }
+
+            // Fetch the most recent messages (up to 50). UIDs are sparse, so
+            // list them rather than guessing a range below UIDNEXT.
+            let mut uids = search_messages_matching(
+                &mut selected_client,
+                &mailledger_imap::SearchCriteria::All,
+            )
+            .await?;
+            uids.sort_unstable();
+            let newest = &uids[uids.len().saturating_sub(50)..];
+
+            let core_messages = fetch_messages_by_uid(&mut selected_client, newest).await?;
+            IMAP_POOL.release(pooled_account, selected_client);
+            Ok::<_, MailServiceError>((core_messages, status.highest_mod_seq))
+        },
+        &RetryPolicy::default(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Route inbox mail by sender decision; blocked senders are dropped
+    let mut tabs = HashMap::new();
+    if folder_path.eq_ignore_ascii_case("INBOX")
+        && let Some(account_id) = account.id
+    {
+        match triage_messages(account_id, &mut core_messages).await {
+            Ok(triaged) => tabs = triaged,
+            Err(e) => tracing::warn!("Failed to apply triage: {}", e),
+        }
+    }
+
+    let mut receipts = HashMap::new();
+    if let Some(account_id) = account.id {
+        match receipt_dispositions(account_id).await {
+            Ok(loaded) => receipts = loaded,
+            Err(e) => tracing::warn!("Failed to load read receipts: {}", e),
+        }
+    }
+
+    // Convert core messages to GUI messages
+    let messages: Vec<MessageSummary> = core_messages
+        .iter()
+        .map(|m| MessageSummary {
+            triage: tabs.get(&m.uid).copied(),
+            receipt: m
+                .message_id
+                .as_ref()
+                .and_then(|id| receipts.get(id))
+                .copied(),
+            ..MessageSummary::from_core(folder_id, m)
+        })
+        .collect();
+
+    tracing::info!("Loaded {} messages from {}", messages.len(), folder_path);
+    Ok((messages, highest_mod_seq))
+}
+
+/// Fetch messages that arrived after `last_uid`.
+async fn load_new_messages(
+    account: mailledger_core::Account,
+    folder_path: String,
+    folder_id: FolderId,
+    last_uid: u32,
 ) -> Result<Vec<MessageSummary>, String> {
-    use mailledger_core::{connect_and_login, fetch_messages, select_folder};
-    use mailledger_imap::types::UidSet;
+    let last_uid = mailledger_imap::types::Uid::new(last_uid).ok_or("Invalid UID")?;
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut new_messages = mailledger_core::fetch_new_since(&mut selected_client, last_uid)
+        .await
+        .map_err(|e| e.to_string())?;
 
-    let client = connect_and_login(&account)
+    // Filters run on new inbox mail before triage; what they move or
+    // delete leaves the list
+    if folder_path.eq_ignore_ascii_case("INBOX")
+        && let Some(account_id) = account.id
+    {
+        match apply_filters(account_id, &new_messages, &mut selected_client).await {
+            Ok(report) => new_messages.retain(|m| !report.removed(m.uid)),
+            Err(e) => tracing::warn!("Failed to apply filters: {}", e),
+        }
+    }
+    IMAP_POOL.release(&account, selected_client);
+
+    let mut tabs = HashMap::new();
+    if folder_path.eq_ignore_ascii_case("INBOX")
+        && let Some(account_id) = account.id
+    {
+        match triage_messages(account_id, &mut new_messages).await {
+            Ok(triaged) => tabs = triaged,
+            Err(e) => tracing::warn!("Failed to apply triage: {}", e),
+        }
+    }
+
+    Ok(new_messages
+        .iter()
+        .map(|m| MessageSummary {
+            triage: tabs.get(&m.uid).copied(),
+            ..MessageSummary::from_core(folder_id, m)
+        })
+        .collect())
+}
+
+/// Fetch the current flags of the given messages.
+async fn load_flag_updates(
+    account: mailledger_core::Account,
+    folder_path: String,
+    uids: Vec<u32>,
+) -> Result<Vec<(MessageId, mailledger_imap::types::Flags)>, String> {
+    use mailledger_imap::types::{Uid, UidSet};
+
+    let uids: Vec<Uid> = uids.into_iter().filter_map(Uid::new).collect();
+    let Some(uid_set) = UidSet::from_uids(&uids) else {
+        return Ok(Vec::new());
+    };
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let updates = mailledger_core::fetch_flag_updates(&mut selected_client, &uid_set)
         .await
         .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
 
-    let (mut selected_client, status) = select_folder(client, &folder_path)
+    Ok(updates
+        .into_iter()
+        .map(|(uid, flags)| (MessageId(uid.get()), flags))
+        .collect())
+}
+
+/// Fetch messages changed since `since_modseq`, from UID `oldest` up.
+async fn load_changes(
+    account: mailledger_core::Account,
+    folder_path: String,
+    folder_id: FolderId,
+    since_modseq: u64,
+    oldest: u32,
+) -> Result<MessageChanges, String> {
+    use mailledger_imap::types::{Uid, UidSet};
+
+    let uid_set = UidSet::RangeFrom(Uid::new(oldest).ok_or("Invalid UID")?);
+    let (mut selected_client, status) = IMAP_POOL
+        .selected(&account, &folder_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let changes = mailledger_core::fetch_changes(&mut selected_client, &uid_set, since_modseq)
         .await
         .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
 
-    // Get the number of messages and highest possible UID
-    let total = status.exists;
-    if total == 0 {
-        return Ok(Vec::new());
+    let mut changed = changes.changed;
+    let mut tabs = HashMap::new();
+    if folder_path.eq_ignore_ascii_case("INBOX")
+        && let Some(account_id) = account.id
+    {
+        match triage_messages(account_id, &mut changed).await {
+            Ok(triaged) => tabs = triaged,
+            Err(e) => tracing::warn!("Failed to apply triage: {}", e),
+        }
     }
 
-    // Use UIDNEXT - 1 as the max UID (UIDs are not sequential with message count)
-    // If UIDNEXT is not available, use 1:* by setting a high upper bound
-    let max_uid = status
-        .uid_next
-        .map_or(u32::MAX, |u| u.get().saturating_sub(1));
+    let mut receipts = HashMap::new();
+    if let Some(account_id) = account.id {
+        match receipt_dispositions(account_id).await {
+            Ok(loaded) => receipts = loaded,
+            Err(e) => tracing::warn!("Failed to load read receipts: {}", e),
+        }
+    }
 
-    // Fetch the most recent messages (up to 50)
-    // We fetch from (max_uid - 49) to max_uid to get the latest messages
-    let fetch_count = total.min(50);
-    let start_uid = if max_uid > fetch_count {
-        max_uid - fetch_count + 1
-    } else {
-        1
-    };
+    Ok(MessageChanges {
+        changed: changed
+            .iter()
+            .map(|m| MessageSummary {
+                triage: tabs.get(&m.uid).copied(),
+                receipt: m
+                    .message_id
+                    .as_ref()
+                    .and_then(|id| receipts.get(id))
+                    .copied(),
+                ..MessageSummary::from_core(folder_id, m)
+            })
+            .collect(),
+        present: changes.present.iter().map(|uid| uid.get()).collect(),
+        highest_mod_seq: status.highest_mod_seq,
+    })
+}
 
-    let uid_set = UidSet::range(
-        mailledger_imap::types::Uid::new(start_uid).ok_or("Invalid UID")?,
-        mailledger_imap::types::Uid::new(max_uid).ok_or("Invalid UID")?,
-    );
+/// Load the read receipts received for the account's sent messages, keyed
+/// by Message-ID.
+async fn receipt_dispositions(
+    account_id: mailledger_core::AccountId,
+) -> Result<HashMap<String, mailledger_mime::MdnDisposition>, String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
 
-    let core_messages = fetch_messages(&mut selected_client, &uid_set)
+    let db_path = data_dir.join("receipts.db");
+    let repo = mailledger_core::ReceiptRepository::new(db_path.to_str().unwrap_or("receipts.db"))
         .await
         .map_err(|e| e.to_string())?;
 
-    // Convert core messages to GUI messages
-    let messages: Vec<MessageSummary> = core_messages
-        .iter()
-        .map(|m| MessageSummary::from_core(folder_id, m))
+    repo.dispositions(account_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Open the trusted sender store in the data directory.
+async fn trusted_sender_repository() -> Result<mailledger_core::TrustedSenderRepository, String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    let db_path = data_dir.join("trusted_senders.db");
+    mailledger_core::TrustedSenderRepository::new(db_path.to_str().unwrap_or("trusted_senders.db"))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Load the senders whose remote images always load.
+async fn load_trusted_senders() -> Result<HashSet<String>, String> {
+    trusted_sender_repository()
+        .await?
+        .list()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Store a sender as trusted.
+async fn trust_sender(sender: String) -> Result<(), String> {
+    trusted_sender_repository()
+        .await?
+        .trust(&sender)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Record a read receipt found in an opened message.
+async fn record_read_receipt(
+    account_id: mailledger_core::AccountId,
+    report: &mailledger_mime::MdnReport,
+) -> Result<(), String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    let db_path = data_dir.join("receipts.db");
+    let repo = mailledger_core::ReceiptRepository::new(db_path.to_str().unwrap_or("receipts.db"))
+        .await
+        .map_err(|e| e.to_string())?;
+    repo.record(account_id, report)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Apply sender triage to freshly loaded inbox messages, returning the tab
+/// each remaining message belongs to.
+async fn triage_messages(
+    account_id: mailledger_core::AccountId,
+    messages: &mut Vec<mailledger_core::MessageSummary>,
+) -> Result<HashMap<mailledger_imap::types::Uid, TriageTab>, String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+
+    let db_path = data_dir.join("triage.db");
+    let repo = mailledger_core::TriageRepository::new(db_path.to_str().unwrap_or("triage.db"))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let view = repo
+        .apply_triage(account_id, messages)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let buckets = [
+        (view.imbox, TriageTab::Imbox),
+        (view.feed, TriageTab::Feed),
+        (view.paper_trail, TriageTab::PaperTrail),
+        (view.screener, TriageTab::Screener),
+    ];
+    let mut tabs: HashMap<_, _> = buckets
+        .into_iter()
+        .flat_map(|(bucket, tab)| bucket.into_iter().map(move |msg| (msg.uid, tab)))
         .collect();
 
-    tracing::info!("Loaded {} messages from {}", messages.len(), folder_path);
-    Ok(messages)
+    // A category set by a filter wins over the sender's
+    let categories = open_filters()
+        .await?
+        .categories(account_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    for message in messages.iter() {
+        if let Some(category) = message
+            .message_id
+            .as_ref()
+            .and_then(|id| categories.get(id))
+        {
+            tabs.insert(message.uid, TriageTab::from_category(*category));
+        }
+    }
+    Ok(tabs)
+}
+
+/// Run the account's filters over new messages in the selected folder.
+async fn apply_filters(
+    account_id: mailledger_core::AccountId,
+    messages: &[mailledger_core::MessageSummary],
+    client: &mut mailledger_core::SelectedClient,
+) -> Result<mailledger_core::FilterReport, String> {
+    open_filters()
+        .await?
+        .apply_filters(account_id, messages, client)
+        .await
+        .map_err(|e| e.to_string())
 }
 
-/// Send an email via SMTP.
+/// Open the message filters in the application database.
+async fn open_filters() -> Result<mailledger_core::FilterRepository, String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+
+    let db_path = data_dir.join("mailledger.db");
+    mailledger_core::FilterRepository::new(db_path.to_str().unwrap_or("mailledger.db"))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Send an email via SMTP, queueing it in the outbox when offline or when
+/// the send fails in a way that may succeed later.
 async fn send_email(
     account: mailledger_core::Account,
     message: mailledger_core::OutgoingMessage,
-) -> Result<(), String> {
-    mailledger_core::send_email(&account, message)
+    offline: bool,
+) -> Result<SendOutcome, String> {
+    if !offline {
+        match mailledger_core::send_email(&account, message.clone()).await {
+            Ok(()) => return Ok(SendOutcome::Sent),
+            Err(e) if e.is_permanent() => return Err(e.to_string()),
+            Err(e) => tracing::info!("Send failed, queueing in outbox: {e}"),
+        }
+    }
+
+    let account_id = account.id.ok_or("Account has not been saved")?;
+    open_outbox()
+        .await?
+        .enqueue(account_id, &message)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(SendOutcome::Queued)
+}
+
+/// Send queued messages whose next attempt is due.
+async fn flush_outbox(
+    account: mailledger_core::Account,
+) -> Result<mailledger_core::FlushSummary, String> {
+    let outbox = open_outbox().await?;
+    mailledger_core::flush_outbox(&account, &outbox)
         .await
         .map_err(|e| e.to_string())
 }
 
-/// Start IDLE monitoring on a folder.
-///
-/// Uses a 5-minute timeout (RFC 2177 recommends re-issuing IDLE every 29 minutes,
-/// but we use shorter intervals for responsiveness and to handle server timeouts).
-async fn start_idle(
+/// Open the outbox in the application database.
+async fn open_outbox() -> Result<mailledger_core::OutboxRepository, String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+
+    let db_path = data_dir.join("mailledger.db");
+    mailledger_core::OutboxRepository::new(db_path.to_str().unwrap_or("mailledger.db"))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save a draft to the account's Drafts folder.
+async fn save_draft(
     account: mailledger_core::Account,
-    folder_path: String,
-) -> Result<mailledger_core::IdleEvent, String> {
-    const IDLE_TIMEOUT_SECS: u64 = 300; // 5 minutes
+    message: mailledger_core::OutgoingMessage,
+    replaces: Option<u32>,
+) -> Result<u32, String> {
+    use mailledger_imap::types::Uid;
 
-    mailledger_core::idle_monitor(&account, &folder_path, IDLE_TIMEOUT_SECS)
+    mailledger_core::save_draft(&account, &message, replaces.and_then(Uid::new), false)
         .await
+        .map(Uid::get)
         .map_err(|e| e.to_string())
 }
 
@@ -2681,14 +3831,29 @@ async fn download_attachment_task(
     filename: String,
     encoding: String,
 ) -> Result<(String, Vec<u8>), String> {
-    use mailledger_core::{connect_and_login, download_attachment, select_folder};
+    use mailledger_core::download_attachment;
     use mailledger_imap::types::Uid;
 
-    let client = connect_and_login(&account)
-        .await
-        .map_err(|e| e.to_string())?;
+    // Previously downloaded attachments open without the server
+    let cache = match account.id {
+        Some(account_id) => open_cache().await.ok().map(|repo| (account_id, repo)),
+        None => None,
+    };
+    if let Some((account_id, repo)) = &cache
+        && let Ok(Some(data)) = repo
+            .get_attachment(*account_id, &folder_path, uid, &part_number)
+            .await
+    {
+        tracing::info!(
+            "Opened cached attachment: {} ({} bytes)",
+            filename,
+            data.len()
+        );
+        return Ok((filename, data));
+    }
 
-    let (mut selected_client, _status) = select_folder(client, &folder_path)
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -2697,11 +3862,75 @@ async fn download_attachment_task(
     let data = download_attachment(&mut selected_client, imap_uid, &part_number, &encoding)
         .await
         .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
+
+    if let Some((account_id, repo)) = &cache
+        && let Err(e) = repo
+            .cache_attachment(*account_id, &folder_path, uid, &part_number, &data)
+            .await
+    {
+        tracing::warn!("Failed to cache attachment: {}", e);
+    }
 
     tracing::info!("Downloaded attachment: {} ({} bytes)", filename, data.len());
     Ok((filename, data))
 }
 
+/// Unsubscribe from a mailing list by one-click POST or email.
+async fn unsubscribe_task(
+    account: mailledger_core::Account,
+    info: mailledger_core::UnsubscribeInfo,
+) -> Result<(), String> {
+    mailledger_core::unsubscribe(&account, &info)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Fetch a message's full source for saving as `<subject>.eml`.
+async fn save_eml_task(
+    account: mailledger_core::Account,
+    folder_path: String,
+    uid: u32,
+    subject: String,
+) -> Result<(String, Vec<u8>), String> {
+    use mailledger_core::fetch_raw_message;
+    use mailledger_imap::types::Uid;
+
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let imap_uid = Uid::new(uid).ok_or("Invalid UID")?;
+
+    let data = fetch_raw_message(&mut selected_client, imap_uid)
+        .await
+        .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
+
+    let name = if subject.trim().is_empty() {
+        "message"
+    } else {
+        subject.trim()
+    };
+    Ok((format!("{name}.eml"), data))
+}
+
+/// Read a dropped `.eml` file and extract what a forward needs.
+async fn load_eml(path: std::path::PathBuf) -> Result<(String, String, String), String> {
+    let raw = tokio::fs::read(&path).await.map_err(|e| e.to_string())?;
+    let message = mailledger_mime::Message::from_eml_bytes(&raw).map_err(|e| e.to_string())?;
+
+    let subject = message.subject().unwrap_or_default();
+    let from = message.from().unwrap_or_default();
+    let body = message
+        .body_text()
+        .or_else(|_| message.text_part())
+        .or_else(|_| message.html_part().map(|html| html_to_markdown(&html)))
+        .unwrap_or_default();
+    Ok((subject, from, body))
+}
+
 /// Archive a message (move to Archive folder).
 async fn archive_message(
     account: mailledger_core::Account,
@@ -2709,53 +3938,192 @@ async fn archive_message(
     uid: u32,
     archive_folder: String,
 ) -> Result<(), String> {
-    use mailledger_core::{archive_message as core_archive, connect_and_login, select_folder};
+    use mailledger_core::archive_message as core_archive;
     use mailledger_imap::types::Uid;
 
-    let client = connect_and_login(&account)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let (mut selected_client, _status) = select_folder(client, &folder_path)
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
         .await
         .map_err(|e| e.to_string())?;
 
     let imap_uid = Uid::new(uid).ok_or("Invalid UID")?;
 
-    core_archive(&mut selected_client, imap_uid, &archive_folder)
+    core_archive(&mut selected_client, imap_uid, &archive_folder, false)
         .await
         .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
 
     tracing::info!("Archived message UID {} to {}", uid, archive_folder);
     Ok(())
 }
 
-/// Load full message content from IMAP.
-async fn load_message_content(
+/// Permanently delete a message, returning how many messages the server
+/// expunged.
+async fn delete_message(
     account: mailledger_core::Account,
     folder_path: String,
     uid: u32,
-) -> Result<Option<MessageContent>, String> {
-    use mailledger_core::{connect_and_login, fetch_message_content, select_folder};
+) -> Result<usize, String> {
     use mailledger_imap::types::Uid;
 
-    let client = connect_and_login(&account)
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
         .await
         .map_err(|e| e.to_string())?;
 
-    let (mut selected_client, _status) = select_folder(client, &folder_path)
+    let imap_uid = Uid::new(uid).ok_or("Invalid UID")?;
+
+    let expunged = mailledger_core::delete_permanently(&mut selected_client, imap_uid)
+        .await
+        .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
+
+    tracing::info!("Deleted message UID {}", uid);
+    Ok(expunged.len())
+}
+
+/// Move a message to another folder.
+async fn move_message_to(
+    account: mailledger_core::Account,
+    folder_path: String,
+    uid: u32,
+    destination: String,
+) -> Result<(), String> {
+    use mailledger_core::move_messages;
+    use mailledger_imap::types::{Uid, UidSet};
+
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
         .await
         .map_err(|e| e.to_string())?;
 
     let imap_uid = Uid::new(uid).ok_or("Invalid UID")?;
 
-    let content = fetch_message_content(&mut selected_client, imap_uid)
+    move_messages(
+        &mut selected_client,
+        &UidSet::single(imap_uid),
+        &destination,
+        false,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
+
+    tracing::info!("Moved message UID {} to {}", uid, destination);
+    Ok(())
+}
+
+/// Build a UID set covering `uids`.
+fn uid_set_of(uids: &[u32]) -> Result<mailledger_imap::types::UidSet, String> {
+    use mailledger_imap::types::{Uid, UidSet};
+
+    let uids = uids
+        .iter()
+        .map(|&uid| Uid::new(uid).ok_or("Invalid UID"))
+        .collect::<Result<Vec<_>, _>>()?;
+    UidSet::from_uids(&uids).ok_or_else(|| "Invalid UID".to_string())
+}
+
+/// Move several messages to Archive, or to Trash when `delete` is set.
+async fn move_selected_messages(
+    account: mailledger_core::Account,
+    folder_path: String,
+    uids: Vec<u32>,
+    destination: String,
+    delete: bool,
+) -> Result<(), String> {
+    let uid_set = uid_set_of(&uids)?;
+
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if delete {
+        mailledger_core::delete_many(&mut selected_client, &uid_set, &destination).await
+    } else {
+        mailledger_core::archive_many(&mut selected_client, &uid_set, &destination, false).await
+    }
+    .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
+
+    tracing::info!("Moved {} messages to {}", uids.len(), destination);
+    Ok(())
+}
+
+/// Mark several messages as read.
+async fn mark_selected_read(
+    account: mailledger_core::Account,
+    folder_path: String,
+    uids: Vec<u32>,
+) -> Result<(), String> {
+    let uid_set = uid_set_of(&uids)?;
+
+    let (mut selected_client, _status) = IMAP_POOL
+        .selected(&account, &folder_path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    mailledger_core::mark_read_many(&mut selected_client, &uid_set)
         .await
         .map_err(|e| e.to_string())?;
+    IMAP_POOL.release(&account, selected_client);
+    Ok(())
+}
+
+/// Load full message content from IMAP.
+async fn load_message_content(
+    account: mailledger_core::Account,
+    folder_path: String,
+    uid: u32,
+) -> Result<Option<MessageContent>, String> {
+    use mailledger_core::{
+        MailServiceError, PreferredBody, RetryPolicy, fetch_message_content, with_backoff,
+    };
+    use mailledger_imap::types::Uid;
+
+    let imap_uid = Uid::new(uid).ok_or("Invalid UID")?;
+
+    // A session that fails is dropped rather than released, so a retry
+    // reconnects and selects the folder again
+    let (pooled_account, pooled_path) = (&account, folder_path.as_str());
+    let content = with_backoff(
+        || async move {
+            let (mut selected_client, _status) =
+                IMAP_POOL.selected(pooled_account, pooled_path).await?;
+            let content =
+                fetch_message_content(&mut selected_client, imap_uid, PreferredBody::Both).await?;
+            IMAP_POOL.release(pooled_account, selected_client);
+            Ok::<_, MailServiceError>(content)
+        },
+        &RetryPolicy::default(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Receipts for our sent mail mark those messages as read
+    if let Some(c) = &content
+        && let Some(report) = &c.receipt
+        && let Some(account_id) = account.id
+        && let Err(e) = record_read_receipt(account_id, report).await
+    {
+        tracing::warn!("Failed to record read receipt: {}", e);
+    }
 
     Ok(content.map(|c| MessageContent::from_core(&c)))
 }
 
+/// Send a read receipt for a message.
+async fn send_read_receipt_task(
+    account: mailledger_core::Account,
+    content: mailledger_core::MessageContent,
+    mode: mailledger_mime::MdnMode,
+) -> Result<(), String> {
+    mailledger_core::send_read_receipt(&account, &content, mode)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Show a desktop notification for new mail.
 ///
 /// Uses notify-rust for cross-platform notifications.
@@ -2847,6 +4215,47 @@ async fn load_cached_messages(
         .map_err(|e| e.to_string())
 }
 
+/// Open the offline cache database.
+async fn open_cache() -> Result<mailledger_core::CacheRepository, String> {
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+    std::fs::create_dir_all(&data_dir).ok();
+
+    let db_path = data_dir.join("cache.db");
+    mailledger_core::CacheRepository::new(db_path.to_str().unwrap_or("cache.db"))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Search cached messages of a folder when offline.
+async fn search_cached_messages(
+    account_id: mailledger_core::AccountId,
+    folder_path: String,
+    query: String,
+) -> Result<Vec<mailledger_imap::Uid>, String> {
+    use mailledger_core::{CacheRepository, search_offline};
+
+    let data_dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("mailledger");
+
+    let db_path = data_dir.join("cache.db");
+    let repo = CacheRepository::new(db_path.to_str().unwrap_or("cache.db"))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let results = search_offline(&repo, account_id, &query, 200)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(results
+        .into_iter()
+        .filter(|s| s.folder_path == folder_path)
+        .filter_map(|s| mailledger_imap::Uid::new(s.uid))
+        .collect())
+}
+
 /// Cache message content after fetching.
 async fn cache_message_content(
     account_id: mailledger_core::AccountId,
@@ -2884,7 +4293,13 @@ async fn cache_message_content(
         date: content.date.clone(),
         body_text: content.body_text.clone(),
         body_html: content.body_html.clone(),
-        attachments_json: None, // TODO: serialize attachments
+        attachments_json: CachedMessageContent::encode_attachments(
+            &content
+                .attachments
+                .iter()
+                .map(model::Attachment::to_core)
+                .collect::<Vec<_>>(),
+        ),
         cached_at: Utc::now(),
     };
 
@@ -2,7 +2,9 @@
 //!
 //! In the Elm architecture, Messages are events that trigger state changes.
 
-use crate::model::{AppSettings, Folder, FolderId, MessageId, MessageSummary};
+use crate::model::{
+    AppSettings, Folder, FolderId, MessageChanges, MessageId, MessageSummary, SendOutcome,
+};
 
 /// Re-export snooze duration for use in messages.
 pub use mailledger_core::SnoozeDuration;
@@ -43,6 +45,30 @@ pub enum Message {
     ArchiveSelected,
     /// Message archived result.
     MessageArchived(Result<(), String>),
+    /// Message permanently deleted; holds how many messages the server
+    /// expunged.
+    MessageDeleted(Result<usize, String>),
+    /// Move a message to another folder.
+    MoveToFolder {
+        /// Message to move.
+        message_id: MessageId,
+        /// Destination folder.
+        folder_id: FolderId,
+    },
+    /// Message moved result.
+    MessageMoved(Result<(), String>),
+    /// Add or remove a message from the bulk selection.
+    ToggleMessageSelection(MessageId),
+    /// Archive every message in the bulk selection.
+    ArchiveSelection,
+    /// Move every message in the bulk selection to Trash.
+    DeleteSelection,
+    /// Mark every message in the bulk selection as read.
+    MarkSelectionRead,
+    /// Bulk action result.
+    SelectionActionDone(Result<(), String>),
+    /// Keyboard modifiers changed (used for shift/ctrl-click selection).
+    ModifiersChanged(iced::keyboard::Modifiers),
     /// Download an attachment.
     DownloadAttachment {
         /// Message ID containing the attachment.
@@ -56,6 +82,12 @@ pub enum Message {
     },
     /// Attachment download complete.
     AttachmentDownloaded(Result<(String, Vec<u8>), String>),
+    /// Save the open message to the downloads folder as an `.eml` file.
+    SaveAsEml,
+    /// A file was dropped on the window; `.eml` files open as a forward.
+    EmlDropped(std::path::PathBuf),
+    /// Dropped `.eml` file parsed into (subject, from, body text).
+    EmlLoaded(Result<(String, String, String), String>),
 
     // Snooze
     /// Snooze the currently selected message.
@@ -81,7 +113,11 @@ pub enum Message {
     /// Compose form messages.
     Compose(ComposeMessage),
     /// Email sent result.
-    EmailSent(Result<(), String>),
+    EmailSent(Result<SendOutcome, String>),
+    /// Time to send queued messages whose next attempt is due.
+    FlushOutbox,
+    /// Queued messages were sent.
+    OutboxFlushed(Result<mailledger_core::FlushSummary, String>),
 
     // Settings
     /// Settings screen messages.
@@ -107,8 +143,8 @@ pub enum Message {
     AccountSetup(AccountSetupMessage),
     /// Account saved successfully.
     AccountSaved(Result<(), String>),
-    /// Connection test completed.
-    ConnectionTested(Result<(), String>),
+    /// Connection test completed with what the server supports.
+    ConnectionTested(Result<mailledger_core::ConnectionProbe, String>),
     /// Settings saved.
     SettingsSaved(Result<(), String>),
     /// Settings loaded.
@@ -133,16 +169,34 @@ pub enum Message {
     AccountLoaded(Result<Option<mailledger_core::Account>, String>),
     /// Folders loaded from IMAP server.
     FoldersLoaded(Result<Vec<Folder>, String>),
-    /// Messages loaded from IMAP server.
-    MessagesLoaded(Result<Vec<MessageSummary>, String>),
+    /// Messages loaded from IMAP server, with the folder's HIGHESTMODSEQ.
+    MessagesLoaded(Result<(Vec<MessageSummary>, Option<u64>), String>),
     /// Message content loaded from IMAP server.
     MessageContentLoaded(Result<Option<crate::model::MessageContent>, String>),
     /// Open the current message HTML in an external viewer.
     OpenHtml,
     /// HTML open completed.
     HtmlOpened(Result<(), String>),
+    /// Unsubscribe from the mailing list of the open message.
+    Unsubscribe,
+    /// Unsubscribe request completed.
+    Unsubscribed(Result<(), String>),
+    /// Send the read receipt the open message asked for.
+    SendReadReceipt,
+    /// Decline the open message's read receipt request.
+    DismissReadReceipt,
+    /// Read receipt sent.
+    ReadReceiptSent(Result<(), String>),
     /// Link clicked in message content (markdown).
     LinkClicked(String),
+    /// Load the remote images blocked in the open message.
+    LoadRemoteImages,
+    /// Trust the open message's sender and load its remote images.
+    TrustSender,
+    /// Trusted senders loaded from the database.
+    TrustedSendersLoaded(Result<std::collections::HashSet<String>, String>),
+    /// Sender stored as trusted.
+    SenderTrusted(Result<(), String>),
     /// Inline image loaded from a remote source.
     InlineImageLoaded {
         /// Image source URL.
@@ -152,10 +206,14 @@ pub enum Message {
     },
 
     // IDLE Operations
-    /// Start IDLE monitoring on the current folder.
-    StartIdle,
-    /// IDLE event received.
-    IdleReceived(Result<mailledger_core::IdleEvent, String>),
+    /// Event from the IDLE subscription on the current folder.
+    IdleEvent(mailledger_core::IdleEvent),
+    /// New mail fetched after an IDLE event.
+    NewMessagesLoaded(Result<Vec<MessageSummary>, String>),
+    /// Current flags of loaded messages, fetched after an IDLE event.
+    FlagsUpdated(Result<Vec<(MessageId, mailledger_imap::types::Flags)>, String>),
+    /// Changes fetched after an IDLE event.
+    ChangesLoaded(Result<MessageChanges, String>),
 
     // Offline Support
     /// Connection state changed (online/offline).
@@ -174,6 +232,8 @@ pub enum Message {
     SearchResultsLoaded(Result<Vec<mailledger_imap::Uid>, String>),
     /// Toggle a search filter chip.
     ToggleSearchFilter(SearchFilter),
+    /// Show one triage tab, or every inbox message with `None`.
+    SelectTriageTab(Option<crate::model::TriageTab>),
 
     // Threading
     /// Toggle between flat and threaded view.
@@ -221,6 +281,8 @@ pub enum KeyboardAction {
     ToggleStar,
     /// Mark message unread (u).
     MarkUnread,
+    /// Toggle the selected message in the bulk selection (x).
+    ToggleSelection,
     /// Refresh folders (F5 or Ctrl+Shift+R when not in Inbox).
     Refresh,
     /// Cancel/close current view (Escape).
@@ -268,6 +330,18 @@ pub enum AccountSetupMessage {
     SmtpUsernameChanged(String),
     /// SMTP password changed.
     SmtpPasswordChanged(String),
+    /// Auto-discovery finished for the email address it was started for.
+    SettingsDiscovered(String, Option<mailledger_core::DiscoveredConfig>),
+    /// `OAuth2` client ID changed.
+    OAuthClientIdChanged(String),
+    /// Start signing in with a provider instead of passwords.
+    SignIn(mailledger_core::OAuthProvider),
+    /// The provider issued a device code for the user to enter.
+    SignInStarted(Result<mailledger_core::DeviceSignIn, String>),
+    /// The user finished (or abandoned) signing in.
+    SignedIn(Result<mailledger_core::AuthMethod, String>),
+    /// Go back to password authentication.
+    SignOut,
     /// Save account.
     Save,
     /// Test connection.
@@ -287,6 +361,10 @@ pub enum SettingsMessage {
     SetFontSize(crate::model::FontSize),
     /// Change list density.
     SetDensity(crate::model::ListDensity),
+    /// Change the read receipt policy.
+    SetReadReceipts(crate::model::ReadReceiptPolicy),
+    /// Change when remote images load.
+    SetRemoteImages(mailledger_core::RemotePolicy),
 }
 
 /// Messages for compose form.
@@ -309,6 +387,12 @@ pub enum ComposeMessage {
     InsertFormatting(FormattingStyle),
     /// Send the message.
     Send,
+    /// Save the message to the server's Drafts folder.
+    SaveDraft,
+    /// Periodic autosave; saves only if the message changed.
+    AutosaveDraft,
+    /// Draft saved with the given UID.
+    DraftSaved(Result<u32, String>),
     /// Cancel composing.
     Cancel,
     /// Contact suggestions loaded from database.
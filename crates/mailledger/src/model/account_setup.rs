@@ -31,6 +31,14 @@ pub struct AccountSetupState {
     pub smtp_username: String,
     /// SMTP password.
     pub smtp_password: String,
+    /// `OAuth2` client ID used to sign in.
+    pub oauth_client_id: String,
+    /// Sign-in replacing both passwords, once the user has signed in.
+    pub oauth: Option<mailledger_core::AuthMethod>,
+    /// Device-flow sign-in waiting for the user to enter the code.
+    pub device_sign_in: Option<mailledger_core::DeviceSignIn>,
+    /// Whether a sign-in is in progress.
+    pub is_signing_in: bool,
     /// Validation errors by field name.
     pub errors: HashMap<String, String>,
     /// Error from save operation.
@@ -39,8 +47,8 @@ pub struct AccountSetupState {
     pub is_saving: bool,
     /// Whether connection test is in progress.
     pub is_testing: bool,
-    /// Connection test result.
-    pub test_result: Option<Result<(), String>>,
+    /// Connection test result, with what the server supports.
+    pub test_result: Option<Result<mailledger_core::ConnectionProbe, String>>,
     /// Whether this account is the default.
     pub is_default: bool,
 }
@@ -67,29 +75,30 @@ impl AccountSetupState {
         self.email.clone_from(&account.email);
         self.imap_host.clone_from(&account.imap.host);
         self.imap_port = account.imap.port.to_string();
-        self.imap_security = match account.imap.security {
-            mailledger_core::Security::StartTls => "starttls",
-            mailledger_core::Security::None => "none",
-            mailledger_core::Security::Tls => "tls",
-        }
-        .to_string();
+        self.imap_security = security_name(account.imap.security).to_string();
         self.imap_username.clone_from(&account.imap.username);
-        self.imap_password.clone_from(&account.imap.password);
+        self.imap_password = account.imap.auth.password().unwrap_or_default().to_string();
         self.smtp_host.clone_from(&account.smtp.host);
         self.smtp_port = account.smtp.port.to_string();
-        self.smtp_security = match account.smtp.security {
-            mailledger_core::Security::StartTls => "starttls",
-            mailledger_core::Security::None => "none",
-            mailledger_core::Security::Tls => "tls",
-        }
-        .to_string();
+        self.smtp_security = security_name(account.smtp.security).to_string();
         self.smtp_username.clone_from(&account.smtp.username);
-        self.smtp_password.clone_from(&account.smtp.password);
+        self.smtp_password = account.smtp.auth.password().unwrap_or_default().to_string();
+        self.oauth = None;
+        if let auth @ mailledger_core::AuthMethod::OAuth { client_id, .. } = &account.imap.auth {
+            self.oauth_client_id.clone_from(client_id);
+            self.oauth = Some(auth.clone());
+        }
         self.is_default = account.is_default;
     }
 
     /// Auto-detect settings from email address.
-    pub fn auto_detect_from_email(&mut self) {
+    ///
+    /// Well-known providers are filled in directly. For other domains that
+    /// look complete, returns the domain so the caller can run
+    /// [`mailledger_core::discover`] and pass the result to
+    /// [`apply_discovered`](Self::apply_discovered).
+    pub fn auto_detect_from_email(&mut self) -> Option<String> {
+        let mut discover_domain = None;
         if let Some(domain) = self.email.split('@').nth(1) {
             match domain.to_lowercase().as_str() {
                 "gmail.com" | "googlemail.com" => {
@@ -132,6 +141,12 @@ impl AccountSetupState {
                     if self.name.is_empty() {
                         self.name = domain.to_string();
                     }
+                    if domain
+                        .rsplit_once('.')
+                        .is_some_and(|(name, tld)| !name.is_empty() && tld.len() >= 2)
+                    {
+                        discover_domain = Some(domain.to_lowercase());
+                    }
                 }
             }
         }
@@ -143,6 +158,18 @@ impl AccountSetupState {
         if self.smtp_username.is_empty() {
             self.smtp_username = self.email.clone();
         }
+
+        discover_domain
+    }
+
+    /// Fill in server settings found by auto-discovery.
+    pub fn apply_discovered(&mut self, config: &mailledger_core::DiscoveredConfig) {
+        self.imap_host.clone_from(&config.imap_host);
+        self.imap_port = config.imap_port.to_string();
+        self.imap_security = security_name(config.imap_security).to_string();
+        self.smtp_host.clone_from(&config.smtp_host);
+        self.smtp_port = config.smtp_port.to_string();
+        self.smtp_security = security_name(config.smtp_security).to_string();
     }
 
     /// Validate the form and return errors.
@@ -174,7 +201,7 @@ impl AccountSetupState {
                 "IMAP username is required".to_string(),
             );
         }
-        if self.imap_password.is_empty() {
+        if self.oauth.is_none() && self.imap_password.is_empty() {
             self.errors.insert(
                 "imap_password".to_string(),
                 "IMAP password is required".to_string(),
@@ -193,7 +220,7 @@ impl AccountSetupState {
                 "SMTP username is required".to_string(),
             );
         }
-        if self.smtp_password.is_empty() {
+        if self.oauth.is_none() && self.smtp_password.is_empty() {
             self.errors.insert(
                 "smtp_password".to_string(),
                 "SMTP password is required".to_string(),
@@ -206,7 +233,7 @@ impl AccountSetupState {
     /// Convert to core Account type.
     #[must_use]
     pub fn to_account(&self) -> mailledger_core::Account {
-        use mailledger_core::{ImapConfig, Security, SmtpConfig};
+        use mailledger_core::{AuthMethod, ImapConfig, Security, SmtpConfig};
 
         let parse_security = |s: &str| match s {
             "starttls" => Security::StartTls,
@@ -223,16 +250,31 @@ impl AccountSetupState {
                 port: self.imap_port.parse().unwrap_or(993),
                 security: parse_security(&self.imap_security),
                 username: self.imap_username.clone(),
-                password: self.imap_password.clone(),
+                auth: self
+                    .oauth
+                    .clone()
+                    .unwrap_or_else(|| AuthMethod::Password(self.imap_password.clone())),
             },
             smtp: SmtpConfig {
                 host: self.smtp_host.clone(),
                 port: self.smtp_port.parse().unwrap_or(465),
                 security: parse_security(&self.smtp_security),
                 username: self.smtp_username.clone(),
-                password: self.smtp_password.clone(),
+                auth: self
+                    .oauth
+                    .clone()
+                    .unwrap_or_else(|| AuthMethod::Password(self.smtp_password.clone())),
             },
             is_default: self.is_default,
         }
     }
 }
+
+/// Form value for a security mode.
+const fn security_name(security: mailledger_core::Security) -> &'static str {
+    match security {
+        mailledger_core::Security::StartTls => "starttls",
+        mailledger_core::Security::None => "none",
+        mailledger_core::Security::Tls => "tls",
+    }
+}
@@ -12,6 +12,15 @@ pub enum AutocompleteField {
     Bcc,
 }
 
+/// What happened to a message the user sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendOutcome {
+    /// Delivered to the SMTP server.
+    Sent,
+    /// Saved to the outbox to be sent when the connection returns.
+    Queued,
+}
+
 /// State for the compose message form.
 #[derive(Debug, Clone, Default)]
 pub struct ComposeState {
@@ -32,12 +41,20 @@ pub struct ComposeState {
     pub send_error: Option<String>,
     /// Success message after sending.
     pub send_success: bool,
+    /// Whether the sent message went to the outbox instead of the server.
+    pub send_queued: bool,
     /// Contact suggestions for autocomplete.
     pub suggestions: Vec<mailledger_core::Contact>,
     /// Which field is currently being autocompleted.
     pub active_autocomplete: Option<AutocompleteField>,
     /// Index of currently selected suggestion (for keyboard navigation).
     pub selected_suggestion: usize,
+    /// UID of the last saved copy in the Drafts folder.
+    pub draft_uid: Option<u32>,
+    /// Whether the message changed since the draft was last saved.
+    pub draft_dirty: bool,
+    /// Whether a draft save is in flight.
+    pub is_saving_draft: bool,
 }
 
 impl ComposeState {
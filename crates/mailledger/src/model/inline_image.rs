@@ -5,6 +5,8 @@ use iced::widget::image;
 /// Inline image loading state.
 #[derive(Debug, Clone)]
 pub enum InlineImageState {
+    /// Remote content is blocked until the user chooses to load it.
+    Blocked,
     /// Image is still loading.
     Loading,
     /// Image successfully loaded.
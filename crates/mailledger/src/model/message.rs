@@ -2,6 +2,7 @@
 
 use super::FolderId;
 use chrono::{DateTime, Local};
+use std::collections::HashSet;
 
 /// Parses a "from" field into name and email parts.
 fn parse_from_field(from: &str) -> (String, String) {
@@ -93,6 +94,60 @@ pub struct MessageSummary {
     pub message_id: Option<String>,
     /// In-Reply-To header.
     pub in_reply_to: Option<String>,
+    /// Triage tab the sender routes this message to; `None` outside the inbox.
+    pub triage: Option<TriageTab>,
+    /// Disposition reported by a read receipt for this (sent) message.
+    pub receipt: Option<mailledger_mime::MdnDisposition>,
+}
+
+/// Messages that changed in the open folder since it was loaded.
+#[derive(Debug, Clone)]
+pub struct MessageChanges {
+    /// New messages and messages whose flags changed.
+    pub changed: Vec<MessageSummary>,
+    /// UIDs still in the folder, from the oldest loaded message up.
+    pub present: HashSet<u32>,
+    /// The folder's new HIGHESTMODSEQ.
+    pub highest_mod_seq: Option<u64>,
+}
+
+/// Sender-based inbox tabs produced by triage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriageTab {
+    /// Approved senders routed to the Imbox.
+    Imbox,
+    /// Approved senders routed to The Feed.
+    Feed,
+    /// Approved senders routed to the Paper Trail.
+    PaperTrail,
+    /// Senders waiting to be screened.
+    Screener,
+}
+
+impl TriageTab {
+    /// All tabs in display order.
+    pub const ALL: [Self; 4] = [Self::Imbox, Self::Feed, Self::PaperTrail, Self::Screener];
+
+    /// The tab for an inbox category.
+    #[must_use]
+    pub const fn from_category(category: mailledger_core::InboxCategory) -> Self {
+        match category {
+            mailledger_core::InboxCategory::Imbox => Self::Imbox,
+            mailledger_core::InboxCategory::Feed => Self::Feed,
+            mailledger_core::InboxCategory::PaperTrail => Self::PaperTrail,
+        }
+    }
+
+    /// Tab label.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Imbox => mailledger_core::InboxCategory::Imbox.display_name(),
+            Self::Feed => mailledger_core::InboxCategory::Feed.display_name(),
+            Self::PaperTrail => mailledger_core::InboxCategory::PaperTrail.display_name(),
+            Self::Screener => "Screener",
+        }
+    }
 }
 
 /// Full message content for display in the message view.
@@ -119,6 +174,11 @@ pub struct MessageContent {
     pub body_html: Option<String>,
     /// Attachments.
     pub attachments: Vec<Attachment>,
+    /// Mailing-list unsubscribe methods, if the message has any.
+    pub unsubscribe: Option<mailledger_core::UnsubscribeInfo>,
+    /// The loaded content, kept while the sender's request for a read
+    /// receipt is unanswered.
+    pub read_receipt_request: Option<mailledger_core::MessageContent>,
 }
 
 /// An attachment in a message.
@@ -143,7 +203,10 @@ impl MessageSummary {
     pub fn from_core(folder_id: FolderId, core_msg: &mailledger_core::MessageSummary) -> Self {
         // Extract name and email from the "from" field
         // Format can be "Name <email>" or just "email"
-        let (from_name, from_email) = parse_from_field(&core_msg.from);
+        let (from_name, mut from_email) = parse_from_field(&core_msg.from);
+        if !core_msg.from_email.is_empty() {
+            from_email.clone_from(&core_msg.from_email);
+        }
 
         Self {
             id: MessageId(core_msg.uid.get()),
@@ -159,6 +222,8 @@ impl MessageSummary {
             thread_id: core_msg.thread_id.clone(),
             message_id: core_msg.message_id.clone(),
             in_reply_to: core_msg.in_reply_to.clone(),
+            triage: None,
+            receipt: None,
         }
     }
 
@@ -181,6 +246,8 @@ impl MessageSummary {
                 thread_id: None,
                 message_id: Some("<msg1@example.com>".into()),
                 in_reply_to: None,
+                triage: None,
+                receipt: None,
             },
             Self {
                 id: MessageId(2),
@@ -196,6 +263,8 @@ impl MessageSummary {
                 thread_id: None,
                 message_id: Some("<msg2@example.com>".into()),
                 in_reply_to: None,
+                triage: None,
+                receipt: None,
             },
             Self {
                 id: MessageId(3),
@@ -211,6 +280,8 @@ impl MessageSummary {
                 thread_id: None,
                 message_id: Some("<msg3@example.com>".into()),
                 in_reply_to: None,
+                triage: None,
+                receipt: None,
             },
             Self {
                 id: MessageId(4),
@@ -241,6 +312,8 @@ impl MessageSummary {
                 thread_id: None,
                 message_id: Some("<msg5@example.com>".into()),
                 in_reply_to: None,
+                triage: None,
+                receipt: None,
             },
         ]
     }
@@ -267,6 +340,11 @@ impl MessageContent {
                 .iter()
                 .map(Attachment::from_core)
                 .collect(),
+            unsubscribe: core_content.unsubscribe.clone(),
+            read_receipt_request: core_content
+                .read_receipt_to
+                .is_some()
+                .then(|| core_content.clone()),
         }
     }
 
@@ -288,6 +366,8 @@ impl MessageContent {
             )),
             body_html: None,
             attachments: vec![],
+            unsubscribe: None,
+            read_receipt_request: None,
         }
     }
 }
@@ -305,6 +385,18 @@ impl Attachment {
         }
     }
 
+    /// Converts back to core service data, e.g. for caching.
+    #[must_use]
+    pub fn to_core(&self) -> mailledger_core::Attachment {
+        mailledger_core::Attachment {
+            filename: self.filename.clone(),
+            mime_type: self.mime_type.clone(),
+            size: self.size,
+            part_number: self.part_number.clone(),
+            encoding: self.encoding.clone(),
+        }
+    }
+
     /// Returns a human-readable size string (e.g., "1.5 MB").
     #[must_use]
     #[allow(clippy::cast_precision_loss)] // Acceptable for display purposes
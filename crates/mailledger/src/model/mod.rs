@@ -9,10 +9,14 @@ mod settings;
 mod thread;
 
 pub use account_setup::AccountSetupState;
-pub use compose::{AutocompleteField, ComposeState};
+pub use compose::{AutocompleteField, ComposeState, SendOutcome};
 pub use folder::{Folder, FolderId, FolderType};
 pub use inline_image::{InlineImage, InlineImageState};
 #[allow(unused_imports)] // Attachment is part of MessageContent's public API
-pub use message::{Attachment, MessageContent, MessageId, MessageSummary};
-pub use settings::{AppSettings, FontSize, ListDensity, SettingsSection, SettingsState};
+pub use message::{
+    Attachment, MessageChanges, MessageContent, MessageId, MessageSummary, TriageTab,
+};
+pub use settings::{
+    AppSettings, FontSize, ListDensity, ReadReceiptPolicy, SettingsSection, SettingsState,
+};
 pub use thread::{Thread, ViewMode, group_into_threads};
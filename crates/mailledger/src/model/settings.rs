@@ -1,5 +1,7 @@
 //! Settings model.
 
+use mailledger_core::RemotePolicy;
+
 use crate::style::widgets::palette::ThemeMode;
 
 /// State for the settings screen.
@@ -17,6 +19,8 @@ pub enum SettingsSection {
     Account,
     /// Appearance settings.
     Appearance,
+    /// Privacy settings.
+    Privacy,
     /// About the application.
     About,
 }
@@ -45,6 +49,18 @@ pub enum ListDensity {
     Spacious,
 }
 
+/// How to answer messages that ask for a read receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadReceiptPolicy {
+    /// Ask each time (default).
+    #[default]
+    Ask,
+    /// Send receipts without asking.
+    Always,
+    /// Never send receipts.
+    Never,
+}
+
 impl FontSize {
     /// Returns the base font size in pixels.
     #[must_use]
@@ -121,6 +137,12 @@ pub struct AppSettings {
     /// List density preference.
     #[serde(default, with = "list_density_serde")]
     pub list_density: ListDensity,
+    /// Read receipt policy.
+    #[serde(default, with = "read_receipt_policy_serde")]
+    pub read_receipts: ReadReceiptPolicy,
+    /// When remote images in messages load.
+    #[serde(default)]
+    pub load_remote_images: RemotePolicy,
 }
 
 impl Default for AppSettings {
@@ -129,6 +151,8 @@ impl Default for AppSettings {
             theme_mode: ThemeMode::Dark, // Default to dark mode for modern look
             font_size: FontSize::Medium,
             list_density: ListDensity::Comfortable,
+            read_receipts: ReadReceiptPolicy::Ask,
+            load_remote_images: RemotePolicy::TrustedSenders,
         }
     }
 }
@@ -224,6 +248,37 @@ mod list_density_serde {
     }
 }
 
+/// Serde helpers for `ReadReceiptPolicy`.
+mod read_receipt_policy_serde {
+    use super::ReadReceiptPolicy;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S>(policy: &ReadReceiptPolicy, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s = match policy {
+            ReadReceiptPolicy::Ask => "ask",
+            ReadReceiptPolicy::Always => "always",
+            ReadReceiptPolicy::Never => "never",
+        };
+        serializer.serialize_str(s)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<ReadReceiptPolicy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "always" => Ok(ReadReceiptPolicy::Always),
+            "never" => Ok(ReadReceiptPolicy::Never),
+            _ => Ok(ReadReceiptPolicy::Ask),
+        }
+    }
+}
+
 impl SettingsState {
     /// Creates a new settings state.
     #[must_use]
@@ -23,9 +23,11 @@ pub fn view_account_setup(state: &AccountSetupState) -> Element<'_, Message> {
         .color(p.text_secondary);
 
     let basic_section = create_basic_section(state);
+    let sign_in_section = create_sign_in_section(state);
     let imap_section = create_imap_section(state);
     let smtp_section = create_smtp_section(state);
     let error_display = create_error_display(state);
+    let probe_display = create_probe_display(state);
     let buttons = create_action_buttons(state);
 
     // Main content
@@ -34,9 +36,11 @@ pub fn view_account_setup(state: &AccountSetupState) -> Element<'_, Message> {
         subtitle,
         Space::new().height(20),
         basic_section,
+        sign_in_section,
         imap_section,
         smtp_section,
         error_display,
+        probe_display,
         Space::new().height(20),
         buttons,
     ]
@@ -81,72 +85,147 @@ fn create_basic_section(state: &AccountSetupState) -> Element<'_, Message> {
     )
 }
 
+/// Create the `OAuth2` sign-in section, an alternative to passwords.
+fn create_sign_in_section(state: &AccountSetupState) -> Element<'_, Message> {
+    let p = palette::current();
+
+    let content: Element<'_, Message> =
+        if let Some(mailledger_core::AuthMethod::OAuth { provider, .. }) = &state.oauth {
+            row![
+                text(format!("Signed in with {}", provider.display_name()))
+                    .size(14)
+                    .color(p.text_primary),
+                Space::new().width(Length::Fill),
+                button(text("Use Password Instead").size(14))
+                    .on_press(Message::AccountSetup(AccountSetupMessage::SignOut))
+                    .padding([10, 20])
+                    .style(widgets::secondary_button_style),
+            ]
+            .align_y(Alignment::Center)
+            .into()
+        } else if let Some(sign_in) = &state.device_sign_in {
+            column![
+                text(format!(
+                    "Visit {} and enter the code:",
+                    sign_in.authorization.verification_uri
+                ))
+                .size(14)
+                .color(p.text_secondary),
+                text(&sign_in.authorization.user_code)
+                    .size(24)
+                    .color(p.text_primary),
+                text("Waiting for you to finish signing in...")
+                    .size(12)
+                    .color(p.text_secondary),
+            ]
+            .spacing(8)
+            .into()
+        } else {
+            let sign_in_button =
+                |label, provider| {
+                    button(text(label).size(14))
+                        .on_press_maybe((!state.is_signing_in).then_some(Message::AccountSetup(
+                            AccountSetupMessage::SignIn(provider),
+                        )))
+                        .padding([10, 20])
+                        .style(widgets::toolbar_button_style)
+                };
+            column![
+                labeled_input(
+                    "OAuth Client ID",
+                    "Application (client) ID",
+                    &state.oauth_client_id,
+                    AccountSetupMessage::OAuthClientIdChanged,
+                    None,
+                ),
+                row![
+                    sign_in_button(
+                        "Sign in with Google",
+                        mailledger_core::OAuthProvider::Google
+                    ),
+                    sign_in_button(
+                        "Sign in with Microsoft",
+                        mailledger_core::OAuthProvider::Microsoft
+                    ),
+                ]
+                .spacing(12),
+            ]
+            .spacing(12)
+            .into()
+        };
+
+    create_section("Sign In (instead of passwords)", content)
+}
+
 /// Create the IMAP server configuration section.
 fn create_imap_section(state: &AccountSetupState) -> Element<'_, Message> {
-    create_section(
-        "Incoming Mail (IMAP)",
-        column![
-            create_server_port_row(
-                &state.imap_host,
-                &state.imap_port,
-                "imap.example.com",
-                "993",
-                AccountSetupMessage::ImapHostChanged,
-                AccountSetupMessage::ImapPortChanged,
-            ),
-            create_security_row(&state.imap_security, |s| {
-                Message::AccountSetup(AccountSetupMessage::ImapSecurityChanged(parse_security(s)))
-            }),
-            labeled_input(
-                "Username",
-                "user@example.com",
-                &state.imap_username,
-                AccountSetupMessage::ImapUsernameChanged,
-                state.errors.get("imap_username"),
-            ),
-            labeled_password(
-                "Password",
-                &state.imap_password,
-                AccountSetupMessage::ImapPasswordChanged,
-                state.errors.get("imap_password"),
-            ),
-        ]
-        .spacing(12),
-    )
+    let mut fields = column![
+        create_server_port_row(
+            &state.imap_host,
+            &state.imap_port,
+            "imap.example.com",
+            "993",
+            AccountSetupMessage::ImapHostChanged,
+            AccountSetupMessage::ImapPortChanged,
+        ),
+        create_security_row(&state.imap_security, |s| {
+            Message::AccountSetup(AccountSetupMessage::ImapSecurityChanged(parse_security(s)))
+        }),
+        labeled_input(
+            "Username",
+            "user@example.com",
+            &state.imap_username,
+            AccountSetupMessage::ImapUsernameChanged,
+            state.errors.get("imap_username"),
+        ),
+    ]
+    .spacing(12);
+    // A sign-in replaces the password
+    if state.oauth.is_none() {
+        fields = fields.push(labeled_password(
+            "Password",
+            &state.imap_password,
+            AccountSetupMessage::ImapPasswordChanged,
+            state.errors.get("imap_password"),
+        ));
+    }
+
+    create_section("Incoming Mail (IMAP)", fields)
 }
 
 /// Create the SMTP server configuration section.
 fn create_smtp_section(state: &AccountSetupState) -> Element<'_, Message> {
-    create_section(
-        "Outgoing Mail (SMTP)",
-        column![
-            create_server_port_row(
-                &state.smtp_host,
-                &state.smtp_port,
-                "smtp.example.com",
-                "465",
-                AccountSetupMessage::SmtpHostChanged,
-                AccountSetupMessage::SmtpPortChanged,
-            ),
-            create_security_row(&state.smtp_security, |s| {
-                Message::AccountSetup(AccountSetupMessage::SmtpSecurityChanged(parse_security(s)))
-            }),
-            labeled_input(
-                "Username",
-                "user@example.com",
-                &state.smtp_username,
-                AccountSetupMessage::SmtpUsernameChanged,
-                state.errors.get("smtp_username"),
-            ),
-            labeled_password(
-                "Password",
-                &state.smtp_password,
-                AccountSetupMessage::SmtpPasswordChanged,
-                state.errors.get("smtp_password"),
-            ),
-        ]
-        .spacing(12),
-    )
+    let mut fields = column![
+        create_server_port_row(
+            &state.smtp_host,
+            &state.smtp_port,
+            "smtp.example.com",
+            "465",
+            AccountSetupMessage::SmtpHostChanged,
+            AccountSetupMessage::SmtpPortChanged,
+        ),
+        create_security_row(&state.smtp_security, |s| {
+            Message::AccountSetup(AccountSetupMessage::SmtpSecurityChanged(parse_security(s)))
+        }),
+        labeled_input(
+            "Username",
+            "user@example.com",
+            &state.smtp_username,
+            AccountSetupMessage::SmtpUsernameChanged,
+            state.errors.get("smtp_username"),
+        ),
+    ]
+    .spacing(12);
+    if state.oauth.is_none() {
+        fields = fields.push(labeled_password(
+            "Password",
+            &state.smtp_password,
+            AccountSetupMessage::SmtpPasswordChanged,
+            state.errors.get("smtp_password"),
+        ));
+    }
+
+    create_section("Outgoing Mail (SMTP)", fields)
 }
 
 /// Create the server and port input row.
@@ -220,6 +299,58 @@ fn create_error_display(state: &AccountSetupState) -> Element<'_, Message> {
     )
 }
 
+/// Create the summary of what the server supports after a connection test.
+fn create_probe_display(state: &AccountSetupState) -> Element<'_, Message> {
+    let Some(Ok(probe)) = &state.test_result else {
+        return Space::new().height(0).into();
+    };
+    let p = palette::current();
+    let yes_no = |supported: bool| if supported { "Yes" } else { "No" };
+
+    let mut col = column![
+        text(format!(
+            "Push notifications (IDLE): {}",
+            yes_no(probe.supports_idle)
+        ))
+        .size(13)
+        .color(p.text_primary),
+        text(format!(
+            "Server-side move (MOVE): {}",
+            yes_no(probe.supports_move)
+        ))
+        .size(13)
+        .color(p.text_primary),
+        text(format!("OAuth2 sign-in: {}", yes_no(probe.supports_oauth)))
+            .size(13)
+            .color(p.text_primary),
+    ]
+    .spacing(6);
+
+    if !probe.special_use_folders.is_empty() {
+        let folders = probe
+            .special_use_folders
+            .iter()
+            .map(|(path, attribute)| format!("{attribute:?}: {path}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        col = col.push(
+            text(format!("Special folders: {folders}"))
+                .size(13)
+                .color(p.text_primary),
+        );
+    }
+
+    let capabilities = probe
+        .capabilities
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    col = col.push(text(capabilities).size(11).color(p.text_secondary));
+
+    create_section("Server Support", col)
+}
+
 /// Create the action buttons row.
 fn create_action_buttons(state: &AccountSetupState) -> Element<'_, Message> {
     row![
@@ -123,7 +123,12 @@ fn view_status(state: &ComposeState) -> Element<'static, Message> {
 
     state.send_error.as_ref().map_or_else(
         || {
-            if state.send_success {
+            if state.send_queued {
+                text("Message queued. It will be sent once the server is reachable.")
+                    .size(14)
+                    .color(p.text_secondary)
+                    .into()
+            } else if state.send_success {
                 text("Message sent successfully!")
                     .size(14)
                     .color(p.accent_green)
@@ -149,12 +154,28 @@ fn view_buttons(state: &ComposeState) -> Element<'static, Message> {
             .on_press(Message::Compose(ComposeMessage::Send))
     };
 
+    let draft_btn = if state.is_saving_draft {
+        button(text("Saving...").size(14))
+            .padding([10, 20])
+            .style(widgets::secondary_button_style)
+    } else {
+        let label = if state.draft_uid.is_some() && !state.draft_dirty {
+            "Draft Saved"
+        } else {
+            "Save Draft"
+        };
+        button(text(label).size(14))
+            .padding([10, 20])
+            .style(widgets::secondary_button_style)
+            .on_press(Message::Compose(ComposeMessage::SaveDraft))
+    };
+
     let cancel_btn = button(text("Cancel").size(14))
         .padding([10, 20])
         .style(widgets::secondary_button_style)
         .on_press(Message::Compose(ComposeMessage::Cancel));
 
-    row![send_btn, cancel_btn].spacing(12).into()
+    row![send_btn, draft_btn, cancel_btn].spacing(12).into()
 }
 
 /// Creates a labeled input field row (without suggestions).
@@ -6,7 +6,7 @@ use iced::widget::{Column, button, column, container, row, scrollable, text};
 use iced::{Background, Border, Element, Length};
 
 use crate::message::Message;
-use crate::model::{FontSize, ListDensity, MessageId, MessageSummary, Thread, ViewMode};
+use crate::model::{FontSize, ListDensity, MessageId, MessageSummary, Thread, TriageTab, ViewMode};
 use crate::style::widgets::{
     message_button_style, message_list_style, message_row_border_style, message_row_selected_style,
     message_row_style, palette, primary_button_style, scrollable_style, secondary_button_style,
@@ -17,6 +17,7 @@ use crate::style::widgets::{
 pub fn view_message_list(
     messages: &[MessageSummary],
     selected_message: Option<MessageId>,
+    bulk_selection: &HashSet<MessageId>,
     is_loading: bool,
     view_mode: ViewMode,
     threads: &[Thread],
@@ -101,12 +102,34 @@ pub fn view_message_list(
     .style(secondary_button_style)
     .on_press(Message::ToggleViewMode);
 
+    let mut toolbar = row![].spacing(6).align_y(iced::Alignment::Center);
+    if !bulk_selection.is_empty() {
+        let count = text(format!("{} selected", bulk_selection.len()))
+            .size(12)
+            .style(|_theme| {
+                let p = palette::current();
+                text::Style {
+                    color: Some(p.text_secondary),
+                }
+            });
+        let action = |label: &'static str, message: Message| {
+            button(text(label).size(12))
+                .padding([6, 10])
+                .style(secondary_button_style)
+                .on_press(message)
+        };
+        toolbar = toolbar
+            .push(count)
+            .push(action("Mark read", Message::MarkSelectionRead))
+            .push(action("Archive", Message::ArchiveSelection))
+            .push(action("Delete", Message::DeleteSelection));
+    }
+
     let header = container(
-        row![
-            iced::widget::Space::new().width(Length::Fill),
-            view_mode_toggle,
-        ]
-        .padding([8, 12]),
+        toolbar
+            .push(iced::widget::Space::new().width(Length::Fill))
+            .push(view_mode_toggle)
+            .padding([8, 12]),
     )
     .style(|_theme| {
         let p = palette::current();
@@ -186,9 +209,13 @@ pub fn view_message_list(
         .take(end_idx - start_idx)
     {
         let element = match item {
-            RowItem::Message(msg, indented) => {
-                view_message_row(msg, selected_message, indented, font_size, list_density)
-            }
+            RowItem::Message(msg, indented) => view_message_row(
+                msg,
+                selected_message == Some(msg.id) || bulk_selection.contains(&msg.id),
+                indented,
+                font_size,
+                list_density,
+            ),
             RowItem::ThreadHeader(thread, expanded) => {
                 view_thread_header(thread, expanded, font_size, list_density)
             }
@@ -355,12 +382,11 @@ fn view_thread_header(
 #[allow(clippy::too_many_lines)]
 fn view_message_row(
     msg: &MessageSummary,
-    selected: Option<MessageId>,
+    is_selected: bool,
     indented: bool,
     font_size: FontSize,
     list_density: ListDensity,
 ) -> Element<'static, Message> {
-    let is_selected = selected == Some(msg.id);
     let base = font_size.base_size();
     let snippet_size = font_size.snippet_size();
     let row_padding = list_density.row_padding();
@@ -452,6 +478,16 @@ fn view_message_row(
         }));
     }
 
+    // Read receipt indicator for sent messages
+    if msg.receipt == Some(mailledger_mime::MdnDisposition::Displayed) {
+        indicators = indicators.push(text("Read").size(11).style(|_theme| {
+            let p = palette::current();
+            text::Style {
+                color: Some(p.accent_green),
+            }
+        }));
+    }
+
     // Unread indicator (indigo dot) - Air style
     if !msg.is_read {
         indicators = indicators.push(container(text("")).width(8).height(8).style(|_theme| {
@@ -592,3 +628,61 @@ fn view_avatar(name: &str, avatar_size: f32) -> Element<'static, Message> {
     })
     .into()
 }
+
+/// Renders the triage tabs shown above a triaged inbox, with message counts.
+pub fn view_triage_tabs(
+    messages: &[MessageSummary],
+    selected: Option<TriageTab>,
+) -> Element<'static, Message> {
+    let all = view_triage_tab(format!("All ({})", messages.len()), None, selected);
+    let tabs = TriageTab::ALL.into_iter().map(|tab| {
+        let count = messages
+            .iter()
+            .filter(|msg| msg.triage == Some(tab))
+            .count();
+        view_triage_tab(format!("{} ({count})", tab.label()), Some(tab), selected)
+    });
+
+    let tab_row = iced::widget::Row::with_children(std::iter::once(all).chain(tabs))
+        .spacing(6)
+        .wrap();
+
+    container(tab_row)
+        .padding([8, 12])
+        .width(Length::Fill)
+        .into()
+}
+
+/// A single triage tab button.
+fn view_triage_tab(
+    label: String,
+    tab: Option<TriageTab>,
+    selected: Option<TriageTab>,
+) -> Element<'static, Message> {
+    let is_active = tab == selected;
+    button(text(label).size(12))
+        .padding([6, 12])
+        .style(move |_theme, status| {
+            let p = palette::current();
+            let (bg, text_color, border_color) = if is_active {
+                (p.primary, p.text_on_primary, p.primary)
+            } else {
+                match status {
+                    button::Status::Hovered => (p.hover, p.text_primary, p.border_medium),
+                    _ => (p.surface, p.text_secondary, p.border_subtle),
+                }
+            };
+            button::Style {
+                background: Some(Background::Color(bg)),
+                text_color,
+                border: Border {
+                    color: border_color,
+                    width: 1.0,
+                    radius: 16.0.into(),
+                },
+                ..Default::default()
+            }
+        })
+        .on_press(Message::SelectTriageTab(tab))
+        .into()
+}
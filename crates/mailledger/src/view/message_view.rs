@@ -230,6 +230,11 @@ fn view_toolbar(
             None
         });
 
+    let save_eml_btn = button(text("Save .eml").size(14))
+        .padding([8, 14])
+        .style(toolbar_button_style)
+        .on_press(Message::SaveAsEml);
+
     let spacer = iced::widget::Space::new().width(Length::Fill);
 
     let toolbar = row![
@@ -240,6 +245,7 @@ fn view_toolbar(
         snooze_widget,
         read_toggle_btn,
         view_html_btn,
+        save_eml_btn,
         spacer,
         delete_btn
     ]
@@ -357,6 +363,20 @@ fn view_header(msg: &MessageContent, font_size: FontSize) -> Element<'static, Me
         base,
     );
 
+    // Newsletters get an unsubscribe button beside the sender
+    let from_row = if msg.unsubscribe.is_some() {
+        let unsubscribe_btn = button(text("Unsubscribe").size(base - 1))
+            .padding([4, 10])
+            .style(toolbar_button_style)
+            .on_press(Message::Unsubscribe);
+        row![from_row, unsubscribe_btn]
+            .spacing(12)
+            .align_y(iced::Alignment::Center)
+            .into()
+    } else {
+        from_row
+    };
+
     // To field
     let to_row = view_field_row("To", &msg.to.join(", "), base);
 
@@ -371,6 +391,32 @@ fn view_header(msg: &MessageContent, font_size: FontSize) -> Element<'static, Me
     // Date field
     header_fields.push(view_field_row("Date", &msg.date, base));
 
+    // Prompt for an unanswered read receipt request
+    if msg.read_receipt_request.is_some() {
+        let prompt = text("The sender asked for a read receipt.")
+            .size(base - 1)
+            .style(|_theme| {
+                let p = palette::current();
+                text::Style {
+                    color: Some(p.text_secondary),
+                }
+            });
+        let send_btn = button(text("Send Receipt").size(base - 1))
+            .padding([4, 10])
+            .style(toolbar_button_style)
+            .on_press(Message::SendReadReceipt);
+        let ignore_btn = button(text("Ignore").size(base - 1))
+            .padding([4, 10])
+            .style(toolbar_button_style)
+            .on_press(Message::DismissReadReceipt);
+        header_fields.push(
+            row![prompt, send_btn, ignore_btn]
+                .spacing(12)
+                .align_y(iced::Alignment::Center)
+                .into(),
+        );
+    }
+
     let header_col = Column::with_children(header_fields)
         .spacing(8)
         .padding([20, 24]);
@@ -548,6 +594,14 @@ fn view_body<'a>(
 
     let mut content: Column<'a, Message> = column![md_view].spacing(16);
 
+    // Offer to load remote images the content policy held back
+    if inline_images
+        .iter()
+        .any(|img| matches!(img.state, InlineImageState::Blocked))
+    {
+        content = content.push(view_remote_content_prompt(font_size));
+    }
+
     // Add inline images if present
     if !inline_images.is_empty() {
         let images = Column::with_children(
@@ -568,10 +622,42 @@ fn view_body<'a>(
         .into()
 }
 
+/// Renders the prompt shown while remote images are blocked.
+fn view_remote_content_prompt(font_size: FontSize) -> Element<'static, Message> {
+    let base = font_size.base_size();
+    let prompt = text("Remote images are hidden to protect your privacy.")
+        .size(base - 1)
+        .style(|_theme| {
+            let p = palette::current();
+            text::Style {
+                color: Some(p.text_secondary),
+            }
+        });
+    let load_btn = button(text("Load Images").size(base - 1))
+        .padding([4, 10])
+        .style(toolbar_button_style)
+        .on_press(Message::LoadRemoteImages);
+    let trust_btn = button(text("Always Load From Sender").size(base - 1))
+        .padding([4, 10])
+        .style(toolbar_button_style)
+        .on_press(Message::TrustSender);
+
+    row![prompt, load_btn, trust_btn]
+        .spacing(12)
+        .align_y(iced::Alignment::Center)
+        .into()
+}
+
 /// Render an inline image.
 fn view_inline_image(image_entry: &InlineImage) -> Element<'static, Message> {
     let p = palette::current();
     match &image_entry.state {
+        InlineImageState::Blocked => text("Remote image hidden")
+            .size(13)
+            .style(move |_theme| text::Style {
+                color: Some(p.text_muted),
+            })
+            .into(),
         InlineImageState::Loading => text("Loading image...")
             .size(13)
             .style(move |_theme| text::Style {
@@ -13,7 +13,7 @@ mod sidebar;
 pub use account_setup::view_account_setup;
 pub use compose::view_compose;
 pub use header::view_header;
-pub use message_list::view_message_list;
+pub use message_list::{view_message_list, view_triage_tabs};
 pub use message_view::view_message_content;
 pub use pane_divider::view_pane_divider;
 pub use screener::{PendingSender, view_screener};
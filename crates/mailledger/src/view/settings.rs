@@ -2,9 +2,10 @@
 
 use iced::widget::{Space, button, column, container, row, scrollable, text, toggler};
 use iced::{Element, Length};
+use mailledger_core::RemotePolicy;
 
 use crate::message::{Message, SettingsMessage, View};
-use crate::model::{FontSize, ListDensity, SettingsSection, SettingsState};
+use crate::model::{FontSize, ListDensity, ReadReceiptPolicy, SettingsSection, SettingsState};
 use crate::style::widgets::palette::{self, ThemeMode};
 
 /// Renders the settings view.
@@ -14,6 +15,8 @@ pub fn view_settings(
     theme_mode: ThemeMode,
     font_size: FontSize,
     list_density: ListDensity,
+    read_receipts: ReadReceiptPolicy,
+    load_remote_images: RemotePolicy,
 ) -> Element<'static, Message> {
     let p = palette::current();
 
@@ -27,6 +30,7 @@ pub fn view_settings(
             SettingsSection::Appearance,
             state.selected_section
         ),
+        section_tab("Privacy", SettingsSection::Privacy, state.selected_section),
         section_tab("About", SettingsSection::About, state.selected_section),
     ]
     .spacing(4);
@@ -35,6 +39,7 @@ pub fn view_settings(
     let content: Element<'static, Message> = match state.selected_section {
         SettingsSection::Account => view_account_section(account),
         SettingsSection::Appearance => view_appearance_section(theme_mode, font_size, list_density),
+        SettingsSection::Privacy => view_privacy_section(read_receipts, load_remote_images),
         SettingsSection::About => view_about_section(),
     };
 
@@ -370,6 +375,109 @@ fn density_button(
         .into()
 }
 
+/// Privacy settings section with the read receipt and remote image pickers.
+fn view_privacy_section(
+    read_receipts: ReadReceiptPolicy,
+    load_remote_images: RemotePolicy,
+) -> Element<'static, Message> {
+    let p = palette::current();
+
+    let receipt_picker = row![
+        text("Read Receipts")
+            .size(14)
+            .color(p.text_secondary)
+            .width(Length::Fixed(120.0)),
+        read_receipt_button("Ask", ReadReceiptPolicy::Ask, read_receipts),
+        read_receipt_button("Always", ReadReceiptPolicy::Always, read_receipts),
+        read_receipt_button("Never", ReadReceiptPolicy::Never, read_receipts),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center);
+
+    let receipt_description = text(match read_receipts {
+        ReadReceiptPolicy::Ask => "Ask before telling a sender you opened their message",
+        ReadReceiptPolicy::Always => "Send a receipt when a sender asks for one",
+        ReadReceiptPolicy::Never => "Ignore requests for read receipts",
+    })
+    .size(12)
+    .color(p.text_muted);
+
+    let remote_picker = row![
+        text("Remote Images")
+            .size(14)
+            .color(p.text_secondary)
+            .width(Length::Fixed(120.0)),
+        remote_policy_button(RemotePolicy::TrustedSenders, load_remote_images),
+        remote_policy_button(RemotePolicy::Always, load_remote_images),
+        remote_policy_button(RemotePolicy::Never, load_remote_images),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center);
+
+    let remote_description = text(match load_remote_images {
+        RemotePolicy::TrustedSenders => "Load images only from senders you trust",
+        RemotePolicy::Always => "Load images in every message",
+        RemotePolicy::Never => "Ask before loading images in any message",
+    })
+    .size(12)
+    .color(p.text_muted);
+
+    column![
+        text("Privacy").size(20).color(p.text_primary),
+        Space::new().height(Length::Fixed(16.0)),
+        receipt_picker,
+        Space::new().height(Length::Fixed(8.0)),
+        receipt_description,
+        Space::new().height(Length::Fixed(16.0)),
+        remote_picker,
+        Space::new().height(Length::Fixed(8.0)),
+        remote_description,
+    ]
+    .spacing(4)
+    .into()
+}
+
+/// Creates a read receipt policy selection button.
+fn read_receipt_button(
+    label: &str,
+    policy: ReadReceiptPolicy,
+    current: ReadReceiptPolicy,
+) -> Element<'static, Message> {
+    let is_active = policy == current;
+    let label_owned = label.to_string();
+
+    button(text(label_owned).size(13))
+        .padding([6, 14])
+        .style(move |theme, status| {
+            let p = palette::current();
+            if is_active {
+                primary_button_style_themed(&p, theme, status)
+            } else {
+                secondary_button_style_themed(&p, theme, status)
+            }
+        })
+        .on_press(Message::Settings(SettingsMessage::SetReadReceipts(policy)))
+        .into()
+}
+
+/// Creates a remote image policy selection button.
+fn remote_policy_button(policy: RemotePolicy, current: RemotePolicy) -> Element<'static, Message> {
+    let is_active = policy == current;
+
+    button(text(policy.label()).size(13))
+        .padding([6, 14])
+        .style(move |theme, status| {
+            let p = palette::current();
+            if is_active {
+                primary_button_style_themed(&p, theme, status)
+            } else {
+                secondary_button_style_themed(&p, theme, status)
+            }
+        })
+        .on_press(Message::Settings(SettingsMessage::SetRemoteImages(policy)))
+        .into()
+}
+
 /// About section.
 fn view_about_section() -> Element<'static, Message> {
     let p = palette::current();